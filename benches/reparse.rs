@@ -0,0 +1,64 @@
+//! Compares parsing a large Rust file from scratch with reparsing it incrementally
+//! after inserting a character in the middle, which is what the buffer does on every edit.
+//!
+//! Run with `cargo bench --bench reparse`.
+
+use std::time::{Duration, Instant};
+
+use tree_sitter::{InputEdit, Parser, Point, Tree};
+
+const ITERATIONS: u32 = 10;
+
+fn main() {
+    let mut parser = Parser::new();
+    parser
+        .set_language(&tree_sitter_rust::language())
+        .expect("the Rust grammar is compatible with tree-sitter");
+
+    // About 4MB of Rust code
+    let content: String = (0..100_000)
+        .map(|index| format!("fn f{index}(x: usize) -> usize {{\n    x + {index}\n}}\n"))
+        .collect();
+    let tree = parse(&mut parser, &content, None);
+
+    let middle = content.len() / 2;
+    let edited = format!("{} {}", &content[..middle], &content[middle..]);
+    let position = point(&content, middle);
+    let edit = InputEdit {
+        start_byte: middle,
+        old_end_byte: middle,
+        new_end_byte: middle + 1,
+        start_position: position,
+        old_end_position: position,
+        new_end_position: Point::new(position.row, position.column + 1),
+    };
+
+    let full = average(|| {
+        parse(&mut parser, &edited, None);
+    });
+    let incremental = average(|| {
+        let mut tree = tree.clone();
+        tree.edit(&edit);
+        parse(&mut parser, &edited, Some(&tree));
+    });
+    println!("full parse: {full:?}, incremental reparse: {incremental:?}");
+}
+
+fn parse(parser: &mut Parser, content: &str, old_tree: Option<&Tree>) -> Tree {
+    parser
+        .parse(content, old_tree)
+        .expect("parsing is not cancelled")
+}
+
+fn point(content: &str, byte: usize) -> Point {
+    let before = &content[..byte];
+    let row = before.matches('\n').count();
+    let column = byte - before.rfind('\n').map_or(0, |index| index + 1);
+    Point::new(row, column)
+}
+
+fn average(mut run: impl FnMut()) -> Duration {
+    let start = Instant::now();
+    (0..ITERATIONS).for_each(|_| run());
+    start.elapsed() / ITERATIONS
+}
@@ -0,0 +1,9 @@
+[
+  (function_definition)
+  (struct_specifier)
+  (enum_specifier)
+  (union_specifier)
+  (compound_statement)
+  (initializer_list)
+  (comment)
+] @fold
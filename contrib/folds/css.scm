@@ -0,0 +1,6 @@
+[
+  (rule_set)
+  (media_statement)
+  (keyframes_statement)
+  (comment)
+] @fold
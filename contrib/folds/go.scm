@@ -0,0 +1,9 @@
+[
+  (function_declaration)
+  (method_declaration)
+  (type_declaration)
+  (import_declaration)
+  (block)
+  (composite_literal)
+  (comment)
+] @fold
@@ -0,0 +1,12 @@
+[
+  (function_declaration)
+  (class_declaration)
+  (method_definition)
+  (statement_block)
+  (object)
+  (array)
+  (arguments)
+  (switch_body)
+  (import_statement)
+  (comment)
+] @fold
@@ -0,0 +1,18 @@
+[
+  (function_item)
+  (impl_item)
+  (trait_item)
+  (struct_item)
+  (enum_item)
+  (union_item)
+  (mod_item)
+  (macro_definition)
+  (block)
+  (match_block)
+  (use_declaration)
+  (call_expression)
+  (struct_expression)
+  (array_expression)
+  (line_comment)
+  (block_comment)
+] @fold
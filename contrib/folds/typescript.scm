@@ -0,0 +1,15 @@
+[
+  (function_declaration)
+  (class_declaration)
+  (interface_declaration)
+  (method_definition)
+  (statement_block)
+  (object)
+  (object_type)
+  (enum_body)
+  (array)
+  (arguments)
+  (switch_body)
+  (import_statement)
+  (comment)
+] @fold
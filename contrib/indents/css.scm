@@ -0,0 +1 @@
+(block) @indent
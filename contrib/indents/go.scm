@@ -0,0 +1,8 @@
+[
+  (block)
+  (field_declaration_list)
+  (interface_type)
+  (literal_value)
+  (argument_list)
+  (parameter_list)
+] @indent
@@ -0,0 +1,12 @@
+[
+  (statement_block)
+  (class_body)
+  (switch_body)
+  (object)
+  (object_pattern)
+  (array)
+  (array_pattern)
+  (named_imports)
+  (arguments)
+  (formal_parameters)
+] @indent
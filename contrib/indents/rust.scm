@@ -0,0 +1,14 @@
+[
+  (block)
+  (declaration_list)
+  (field_declaration_list)
+  (enum_variant_list)
+  (field_initializer_list)
+  (match_block)
+  (use_list)
+  (token_tree)
+  (arguments)
+  (parameters)
+  (array_expression)
+  (tuple_expression)
+] @indent
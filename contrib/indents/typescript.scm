@@ -0,0 +1,14 @@
+[
+  (statement_block)
+  (class_body)
+  (switch_body)
+  (enum_body)
+  (object_type)
+  (object)
+  (object_pattern)
+  (array)
+  (array_pattern)
+  (named_imports)
+  (arguments)
+  (formal_parameters)
+] @indent
@@ -0,0 +1,21 @@
+(function_declaration
+  body: (block) @function.inner) @function.outer
+
+(method_declaration
+  body: (block) @function.inner) @function.outer
+
+(func_literal
+  body: (block) @function.inner) @function.outer
+
+(type_declaration
+  (type_spec
+    type: (struct_type
+      (field_declaration_list) @class.inner))) @class.outer
+
+(parameter_list
+  (_) @parameter.inner)
+
+(argument_list
+  (_) @parameter.inner)
+
+(comment) @comment.outer
@@ -0,0 +1,19 @@
+(function_definition
+  body: (block) @function.inner) @function.outer
+
+(lambda
+  body: (_) @function.inner) @function.outer
+
+(class_definition
+  body: (block) @class.inner) @class.outer
+
+(parameters
+  (_) @parameter.inner)
+
+(lambda_parameters
+  (_) @parameter.inner)
+
+(argument_list
+  (_) @parameter.inner)
+
+(comment) @comment.outer
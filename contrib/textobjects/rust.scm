@@ -0,0 +1,39 @@
+(function_item
+  body: (block) @function.inner) @function.outer
+
+(closure_expression
+  body: (_) @function.inner) @function.outer
+
+(struct_item
+  body: (field_declaration_list) @class.inner) @class.outer
+
+(enum_item
+  body: (enum_variant_list) @class.inner) @class.outer
+
+(union_item
+  body: (field_declaration_list) @class.inner) @class.outer
+
+(trait_item
+  body: (declaration_list) @class.inner) @class.outer
+
+(impl_item
+  body: (declaration_list) @class.inner) @class.outer
+
+(parameters
+  (_) @parameter.inner)
+
+(closure_parameters
+  (_) @parameter.inner)
+
+(arguments
+  (_) @parameter.inner)
+
+(type_parameters
+  (_) @parameter.inner)
+
+(type_arguments
+  (_) @parameter.inner)
+
+(line_comment) @comment.outer
+
+(block_comment) @comment.outer
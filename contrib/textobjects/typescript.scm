@@ -0,0 +1,40 @@
+(function_declaration
+  body: (statement_block) @function.inner) @function.outer
+
+(function_expression
+  body: (statement_block) @function.inner) @function.outer
+
+(generator_function_declaration
+  body: (statement_block) @function.inner) @function.outer
+
+(arrow_function
+  body: (_) @function.inner) @function.outer
+
+(method_definition
+  body: (statement_block) @function.inner) @function.outer
+
+(class_declaration
+  body: (class_body) @class.inner) @class.outer
+
+(abstract_class_declaration
+  body: (class_body) @class.inner) @class.outer
+
+(class
+  body: (class_body) @class.inner) @class.outer
+
+(interface_declaration
+  body: (_) @class.inner) @class.outer
+
+(formal_parameters
+  (_) @parameter.inner)
+
+(arguments
+  (_) @parameter.inner)
+
+(type_parameters
+  (_) @parameter.inner)
+
+(type_arguments
+  (_) @parameter.inner)
+
+(comment) @comment.outer
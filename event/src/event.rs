@@ -8,6 +8,11 @@ pub enum Event {
     Mouse(crossterm::event::MouseEvent),
     Paste(String),
     Resize(u16, u16),
+    /// The text being composed by an input method, which is not inserted yet.
+    /// An empty string means the composition is cancelled.
+    ImePreedit(String),
+    /// The text composed by an input method, such as a CJK phrase.
+    ImeCommit(String),
 }
 
 impl From<crossterm::event::Event> for Event {
@@ -40,6 +45,36 @@ impl KeyEvent {
         }
     }
 
+    /// Same as parsing `c` as a key, where uppercase characters come with the Shift modifier.
+    pub fn from_char(c: char) -> KeyEvent {
+        KeyEvent::new(
+            crossterm::event::KeyCode::Char(c),
+            KeyModifiers::None.add_shift(c.is_uppercase()),
+        )
+    }
+
+    /// Shifted characters are represented in the same way regardless of the terminal:
+    /// uppercase when Shift is the only modifier (e.g. `A`), otherwise lowercase (e.g.
+    /// `ctrl+shift+a`), because terminals that speak the kitty keyboard protocol report the
+    /// unshifted character, whereas legacy terminals report the shifted one (e.g. `alt+A`).
+    pub(crate) fn normalize(self) -> KeyEvent {
+        let crossterm::event::KeyCode::Char(c) = self.code else {
+            return self;
+        };
+        let mut chars: Vec<_> = match self.modifiers {
+            KeyModifiers::Shift => c.to_uppercase().collect(),
+            KeyModifiers::CtrlShift | KeyModifiers::AltShift | KeyModifiers::CtrlAltShift => {
+                c.to_lowercase().collect()
+            }
+            _ => return self,
+        };
+        // Characters whose other case is several characters (e.g. `ß`) are kept as they are
+        match (chars.pop(), chars.is_empty()) {
+            (Some(c), true) => KeyEvent::new(crossterm::event::KeyCode::Char(c), self.modifiers),
+            _ => self,
+        }
+    }
+
     pub fn to_rust_code(&self) -> String {
         format!(
             "event::KeyEvent {{ code: crossterm::event::KeyCode::{:#?}, modifiers: event::KeyModifiers::{:#?}, }}",
@@ -102,6 +137,7 @@ impl From<crossterm::event::KeyEvent> for KeyEvent {
             code: value.code,
             modifiers: value.modifiers.into(),
         }
+        .normalize()
     }
 }
 
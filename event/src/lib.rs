@@ -1,4 +1,5 @@
 pub mod event;
+pub mod reader;
 
 use std::collections::HashSet;
 
@@ -28,7 +29,8 @@ impl Token {
                 Ok(KeyEvent::new(
                     result.key_code,
                     Token::parse_modifiers(modifiers)?.add_shift(result.shift),
-                ))
+                )
+                .normalize())
             }
             _ => {
                 let result = Token::parse_key_code(&self.0)?;
@@ -123,7 +125,7 @@ mod test_key_event {
 
     use crate::{KeyEvent, KeyModifiers};
 
-    use super::parse_key_events;
+    use super::{parse_key_event, parse_key_events};
     use pretty_assertions::assert_eq;
 
     #[test]
@@ -135,6 +137,7 @@ mod test_key_event {
         run_test("ctrl+a");
         run_test("ctrl+shift+t");
         run_test("alt+shift+backspace");
+        run_test("ctrl+enter");
     }
 
     #[test]
@@ -194,6 +197,36 @@ mod test_key_event {
         );
     }
 
+    #[test]
+    fn kitty_and_legacy_shifted_characters() {
+        use crossterm::event::KeyModifiers as Modifiers;
+        fn from_terminal(code: KeyCode, modifiers: Modifiers) -> KeyEvent {
+            crossterm::event::KeyEvent::new(code, modifiers).into()
+        }
+        // Reported by terminals that speak the kitty keyboard protocol
+        assert_eq!(
+            from_terminal(KeyCode::Char('a'), Modifiers::CONTROL | Modifiers::SHIFT),
+            parse_key_event("ctrl+shift+a").unwrap()
+        );
+        // Reported by legacy terminals
+        assert_eq!(
+            from_terminal(KeyCode::Char('A'), Modifiers::ALT | Modifiers::SHIFT),
+            parse_key_event("alt+shift+a").unwrap()
+        );
+        assert_eq!(
+            parse_key_event("alt+A").unwrap(),
+            parse_key_event("alt+shift+a").unwrap()
+        );
+        assert_eq!(
+            from_terminal(KeyCode::Char('a'), Modifiers::SHIFT),
+            parse_key_event("A").unwrap()
+        );
+        assert_eq!(
+            from_terminal(KeyCode::Enter, Modifiers::CONTROL),
+            parse_key_event("ctrl+enter").unwrap()
+        );
+    }
+
     #[test]
     fn invisible_keys() {
         assert_eq!(
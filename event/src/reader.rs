@@ -0,0 +1,111 @@
+use std::{io, time::Duration};
+
+use crossterm::event::{KeyCode, KeyEventKind, KeyModifiers};
+
+use crate::event::Event;
+
+/// Reads terminal events, where the text committed by an input method
+/// is combined into a single `Event::ImeCommit`.
+///
+/// Terminals write the text committed by an input method (e.g. a CJK phrase) at once,
+/// which crossterm reports as one key event per character.
+/// Handling them separately would create one undo entry for each character.
+#[derive(Default)]
+pub struct EventReader {
+    /// The event that ended the previous burst of characters, which is returned by the next `read`.
+    pending: Option<crossterm::event::Event>,
+}
+
+impl EventReader {
+    pub fn read(&mut self) -> io::Result<Event> {
+        let first = match self.pending.take() {
+            Some(event) => event,
+            None => crossterm::event::read()?,
+        };
+        let (event, pending) = combine(first, || {
+            if crossterm::event::poll(Duration::ZERO)? {
+                crossterm::event::read().map(Some)
+            } else {
+                Ok(None)
+            }
+        })?;
+        self.pending = pending;
+        Ok(event)
+    }
+}
+
+/// `next` returns the next event only if it is already available.
+///
+/// Returns the combined event, and the event that ended the burst of characters (if any).
+fn combine(
+    first: crossterm::event::Event,
+    mut next: impl FnMut() -> io::Result<Option<crossterm::event::Event>>,
+) -> io::Result<(Event, Option<crossterm::event::Event>)> {
+    // Only a non-ASCII character starts a composition,
+    // so that a burst of ASCII keys (e.g. keymaps typed over a slow connection)
+    // is still handled key by key
+    let Some(c) = typed_char(&first).filter(|c| !c.is_ascii()) else {
+        return Ok((first.into(), None));
+    };
+    let mut text = c.to_string();
+    while let Some(event) = next()? {
+        match typed_char(&event) {
+            Some(c) => text.push(c),
+            None => return Ok((commit(first, text), Some(event))),
+        }
+    }
+    Ok((commit(first, text), None))
+}
+
+fn commit(first: crossterm::event::Event, text: String) -> Event {
+    if text.chars().count() == 1 {
+        first.into()
+    } else {
+        Event::ImeCommit(text)
+    }
+}
+
+fn typed_char(event: &crossterm::event::Event) -> Option<char> {
+    match event {
+        crossterm::event::Event::Key(key)
+            if key.kind == KeyEventKind::Press
+                && key.modifiers.difference(KeyModifiers::SHIFT).is_empty() =>
+        {
+            match key.code {
+                KeyCode::Char(c) => Some(c),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test_reader {
+    use crossterm::event::{KeyCode, KeyModifiers};
+
+    use super::combine;
+    use crate::event::Event;
+
+    fn key(code: KeyCode) -> crossterm::event::Event {
+        crossterm::event::Event::Key(crossterm::event::KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    #[test]
+    fn combine_burst_of_non_ascii_characters() {
+        let mut events = vec![key(KeyCode::Char('好')), key(KeyCode::Enter)].into_iter();
+        let (event, pending) = combine(key(KeyCode::Char('你')), || Ok(events.next())).unwrap();
+        assert!(matches!(event, Event::ImeCommit(text) if text == "你好"));
+        assert_eq!(pending, Some(key(KeyCode::Enter)));
+    }
+
+    #[test]
+    fn ascii_characters_are_not_combined() {
+        let (event, pending) = combine(key(KeyCode::Char('a')), || {
+            Ok(Some(key(KeyCode::Char('b'))))
+        })
+        .unwrap();
+        assert!(matches!(event, Event::Key(key) if key.code == KeyCode::Char('a')));
+        assert_eq!(pending, None);
+    }
+}
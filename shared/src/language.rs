@@ -42,9 +42,35 @@ pub struct Language {
     pub(crate) lsp_command: Option<LspCommand>,
     pub(crate) tree_sitter_grammar_config: Option<GrammarConfig>,
     pub(crate) highlight_query: Option<&'static str>,
+    /// Uses the capture names of `nvim-treesitter-textobjects`,
+    /// e.g. `@function.outer`, `@function.inner` and `@parameter.inner`
+    pub(crate) textobjects_query: Option<&'static str>,
+    /// Uses the `@indent` capture, where a new line within an `@indent` node
+    /// is indented one level deeper than the line where the node starts
+    pub(crate) indents_query: Option<&'static str>,
+    /// Uses the `@fold` capture, where the lines of an `@fold` node
+    /// (except the line where it starts) are hidden when it is folded
+    pub(crate) folds_query: Option<&'static str>,
+    /// The brackets and quotes that are closed automatically in Insert mode
+    pub(crate) auto_pairs: &'static [(char, char)],
+    pub(crate) line_comment_prefix: Option<&'static str>,
+    /// The open and close tokens of block comments, e.g. `("/*", "*/")`
+    pub(crate) block_comment_affixes: Option<(&'static str, &'static str)>,
+    /// The separator of the module paths of imports, e.g. `::` for `use foo::bar`,
+    /// where the module path mirrors the path of the file
+    pub(crate) module_separator: Option<&'static str>,
     pub(crate) formatter_command: Option<Command>,
 }
 
+pub(crate) const DEFAULT_AUTO_PAIRS: &[(char, char)] = &[
+    ('(', ')'),
+    ('[', ']'),
+    ('{', '}'),
+    ('"', '"'),
+    ('\'', '\''),
+    ('`', '`'),
+];
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct LspCommand {
     pub(crate) command: Command,
@@ -66,6 +92,13 @@ impl Language {
             file_names: &[""],
             lsp_language_id: None,
             highlight_query: None,
+            textobjects_query: None,
+            indents_query: None,
+            folds_query: None,
+            auto_pairs: DEFAULT_AUTO_PAIRS,
+            line_comment_prefix: None,
+            block_comment_affixes: None,
+            module_separator: None,
             lsp_command: None,
             tree_sitter_grammar_config: None,
             formatter_command: None,
@@ -138,6 +171,34 @@ impl Language {
             })
     }
 
+    pub fn textobjects_query(&self) -> Option<&'static str> {
+        self.textobjects_query
+    }
+
+    pub fn indents_query(&self) -> Option<&'static str> {
+        self.indents_query
+    }
+
+    pub fn folds_query(&self) -> Option<&'static str> {
+        self.folds_query
+    }
+
+    pub fn auto_pairs(&self) -> &'static [(char, char)] {
+        self.auto_pairs
+    }
+
+    pub fn line_comment_prefix(&self) -> Option<&'static str> {
+        self.line_comment_prefix
+    }
+
+    pub fn block_comment_affixes(&self) -> Option<(&'static str, &'static str)> {
+        self.block_comment_affixes
+    }
+
+    pub fn module_separator(&self) -> Option<&'static str> {
+        self.module_separator
+    }
+
     pub fn locals_query(&self) -> Option<&'static str> {
         None
     }
@@ -152,6 +213,12 @@ impl Language {
             .map(|command| ProcessCommand::new(command.command.0, command.command.1))
     }
 
+    /// The ID of the Tree-sitter grammar, which is unique among the languages,
+    /// unlike the LSP language ID, which is not defined for every language.
+    pub fn name(&self) -> Option<&'static str> {
+        Some(self.tree_sitter_grammar_config.as_ref()?.id)
+    }
+
     pub fn tree_sitter_grammar_id(&self) -> Option<String> {
         Some(self.tree_sitter_grammar_config()?.grammar_id)
     }
@@ -184,6 +251,13 @@ pub fn from_extension(extension: &str) -> Option<Language> {
         .map(|language| (*language).clone())
 }
 
+pub fn all() -> Vec<Language> {
+    LANGUAGES
+        .iter()
+        .map(|language| (*language).clone())
+        .collect()
+}
+
 pub(crate) fn from_filename(path: &CanonicalizedPath) -> Option<Language> {
     let file_name = path.file_name()?;
     LANGUAGES
@@ -1,4 +1,6 @@
-use super::language::{Command, GrammarConfig, Language, LanguageId, LspCommand};
+use super::language::{
+    Command, GrammarConfig, Language, LanguageId, LspCommand, DEFAULT_AUTO_PAIRS,
+};
 
 pub const LANGUAGES: &[&Language] = &[
     &bash(),
@@ -46,6 +48,8 @@ const fn bash() -> Language {
             subpath: None,
         }),
         formatter_command: Some(Command("shfmt", &[".sh", ".bash"])),
+        line_comment_prefix: Some("#"),
+        block_comment_affixes: None,
         ..Language::new()
     }
 }
@@ -66,7 +70,14 @@ const fn c() -> Language {
             subpath: None,
         }),
         highlight_query: None,
+        textobjects_query: Some(include_str!("../../contrib/textobjects/c.scm")),
+        indents_query: Some(include_str!("../../contrib/indents/c.scm")),
+        folds_query: Some(include_str!("../../contrib/folds/c.scm")),
+        auto_pairs: DEFAULT_AUTO_PAIRS,
         formatter_command: None,
+        line_comment_prefix: Some("//"),
+        block_comment_affixes: Some(("/*", "*/")),
+        module_separator: None,
     }
 }
 
@@ -83,7 +94,15 @@ const fn common_lisp() -> Language {
             subpath: None,
         }),
         highlight_query: None,
+        textobjects_query: None,
+        indents_query: None,
+        folds_query: None,
+        // Single quotes are used for quoting
+        auto_pairs: &[('(', ')'), ('[', ']'), ('{', '}'), ('"', '"')],
         formatter_command: None,
+        line_comment_prefix: Some(";"),
+        block_comment_affixes: Some(("#|", "|#")),
+        module_separator: None,
     }
 }
 
@@ -94,6 +113,10 @@ const fn csv() -> Language {
         lsp_language_id: None,
         lsp_command: None,
         highlight_query: None,
+        textobjects_query: None,
+        indents_query: None,
+        folds_query: None,
+        auto_pairs: DEFAULT_AUTO_PAIRS,
         formatter_command: None,
         tree_sitter_grammar_config: Some(GrammarConfig {
             id: "csv",
@@ -101,6 +124,9 @@ const fn csv() -> Language {
             commit: "main",
             subpath: None,
         }),
+        line_comment_prefix: None,
+        block_comment_affixes: None,
+        module_separator: None,
     }
 }
 
@@ -111,6 +137,10 @@ const fn css() -> Language {
         lsp_language_id: None,
         lsp_command: None,
         highlight_query: None,
+        textobjects_query: None,
+        indents_query: Some(include_str!("../../contrib/indents/css.scm")),
+        folds_query: Some(include_str!("../../contrib/folds/css.scm")),
+        auto_pairs: DEFAULT_AUTO_PAIRS,
         formatter_command: Some(Command("prettierd", &[".css"])),
         tree_sitter_grammar_config: Some(GrammarConfig {
             id: "css",
@@ -118,6 +148,9 @@ const fn css() -> Language {
             commit: "master",
             subpath: None,
         }),
+        line_comment_prefix: None,
+        block_comment_affixes: Some(("/*", "*/")),
+        module_separator: None,
     }
 }
 
@@ -128,6 +161,10 @@ const fn dockerfile() -> Language {
         lsp_language_id: None,
         lsp_command: None,
         highlight_query: None,
+        textobjects_query: None,
+        indents_query: None,
+        folds_query: None,
+        auto_pairs: DEFAULT_AUTO_PAIRS,
         formatter_command: None,
         tree_sitter_grammar_config: Some(GrammarConfig {
             id: "dockerfile",
@@ -135,6 +172,9 @@ const fn dockerfile() -> Language {
             commit: "main",
             subpath: None,
         }),
+        line_comment_prefix: Some("#"),
+        block_comment_affixes: None,
+        module_separator: None,
     }
 }
 
@@ -153,6 +193,8 @@ const fn graphql() -> Language {
             command: Command("graphql-lsp", &["server", "-m", "stream"]),
             initialization_options: Some(r#"{ "graphql-config.load.legacy": true }"#),
         }),
+        line_comment_prefix: Some("#"),
+        block_comment_affixes: None,
         ..Language::new()
     }
 }
@@ -172,6 +214,8 @@ const fn html() -> Language {
             subpath: None,
         }),
         formatter_command: Some(Command("prettierd", &[".html"])),
+        line_comment_prefix: None,
+        block_comment_affixes: Some(("<!--", "-->")),
         ..Language::new()
     }
 }
@@ -195,6 +239,12 @@ const fn javascript(jsx: bool) -> Language {
             subpath: None,
         }),
         formatter_command: Some(Command("prettierd", if jsx { &[".jsx"] } else { &[".js"] })),
+        textobjects_query: Some(include_str!("../../contrib/textobjects/javascript.scm")),
+        indents_query: Some(include_str!("../../contrib/indents/javascript.scm")),
+        folds_query: Some(include_str!("../../contrib/folds/javascript.scm")),
+        auto_pairs: DEFAULT_AUTO_PAIRS,
+        line_comment_prefix: Some("//"),
+        block_comment_affixes: Some(("/*", "*/")),
         ..Language::new()
     }
 }
@@ -212,7 +262,14 @@ const fn json() -> Language {
             subpath: None,
         }),
         highlight_query: None,
+        textobjects_query: None,
+        indents_query: Some(include_str!("../../contrib/indents/json.scm")),
+        folds_query: Some(include_str!("../../contrib/folds/json.scm")),
+        auto_pairs: DEFAULT_AUTO_PAIRS,
         formatter_command: Some(Command("prettierd", &[".json"])),
+        line_comment_prefix: None,
+        block_comment_affixes: None,
+        module_separator: None,
     }
 }
 
@@ -229,7 +286,14 @@ const fn just() -> Language {
             subpath: None,
         }),
         highlight_query: None,
+        textobjects_query: None,
+        indents_query: None,
+        folds_query: None,
+        auto_pairs: DEFAULT_AUTO_PAIRS,
         formatter_command: None,
+        line_comment_prefix: Some("#"),
+        block_comment_affixes: None,
+        module_separator: None,
     }
 }
 
@@ -249,7 +313,14 @@ const fn nix() -> Language {
             subpath: None,
         }),
         highlight_query: None,
+        textobjects_query: None,
+        indents_query: None,
+        folds_query: None,
+        auto_pairs: DEFAULT_AUTO_PAIRS,
         formatter_command: Some(Command("nixfmt", &[])),
+        line_comment_prefix: Some("#"),
+        block_comment_affixes: Some(("/*", "*/")),
+        module_separator: None,
     }
 }
 
@@ -268,6 +339,8 @@ const fn markdown() -> Language {
             subpath: Some("tree-sitter-markdown"),
         }),
         formatter_command: Some(Command("prettierd", &[".md"])),
+        line_comment_prefix: None,
+        block_comment_affixes: Some(("<!--", "-->")),
         ..Language::new()
     }
 }
@@ -287,6 +360,13 @@ const fn python() -> Language {
             subpath: None,
         }),
         formatter_command: Some(Command("ruff", &["format", "--stdin-filename", ".py"])),
+        textobjects_query: Some(include_str!("../../contrib/textobjects/python.scm")),
+        indents_query: None,
+        folds_query: None,
+        auto_pairs: DEFAULT_AUTO_PAIRS,
+        line_comment_prefix: Some("#"),
+        block_comment_affixes: None,
+        module_separator: Some("."),
         ..Language::new()
     }
 }
@@ -307,10 +387,17 @@ const fn rescript() -> Language {
             subpath: None,
         }),
         highlight_query: None,
+        textobjects_query: None,
+        indents_query: None,
+        folds_query: None,
+        auto_pairs: DEFAULT_AUTO_PAIRS,
         formatter_command: Some(Command(
             "./node_modules/.bin/rescript",
             &["format", "-stdin", ".res"],
         )),
+        line_comment_prefix: Some("//"),
+        block_comment_affixes: Some(("/*", "*/")),
+        module_separator: None,
     }
 }
 
@@ -324,6 +411,11 @@ const fn rust() -> Language {
             ..LspCommand::default()
         }),
         highlight_query: None,
+        textobjects_query: Some(include_str!("../../contrib/textobjects/rust.scm")),
+        indents_query: Some(include_str!("../../contrib/indents/rust.scm")),
+        folds_query: Some(include_str!("../../contrib/folds/rust.scm")),
+        // Single quotes are mostly used for lifetimes
+        auto_pairs: &[('(', ')'), ('[', ']'), ('{', '}'), ('"', '"')],
         tree_sitter_grammar_config: Some(GrammarConfig {
             id: "rust",
             url: "https://github.com/tree-sitter/tree-sitter-rust",
@@ -331,6 +423,9 @@ const fn rust() -> Language {
             subpath: None,
         }),
         formatter_command: Some(Command("rustfmt", &["--edition=2021"])),
+        line_comment_prefix: Some("//"),
+        block_comment_affixes: Some(("/*", "*/")),
+        module_separator: Some("::"),
     }
 }
 
@@ -346,6 +441,8 @@ const fn sql() -> Language {
             subpath: None,
         }),
         formatter_command: Some(Command("sql-formatter", &["--language", "postgresql"])),
+        line_comment_prefix: Some("--"),
+        block_comment_affixes: Some(("/*", "*/")),
         ..Language::new()
     }
 }
@@ -365,6 +462,8 @@ const fn swift() -> Language {
             subpath: None,
         }),
         formatter_command: Some(Command("swiftformat", &[])),
+        line_comment_prefix: Some("//"),
+        block_comment_affixes: Some(("/*", "*/")),
         ..Language::new()
     }
 }
@@ -382,7 +481,14 @@ const fn toml() -> Language {
             subpath: None,
         }),
         highlight_query: None,
+        textobjects_query: None,
+        indents_query: None,
+        folds_query: None,
+        auto_pairs: DEFAULT_AUTO_PAIRS,
         formatter_command: None,
+        line_comment_prefix: Some("#"),
+        block_comment_affixes: None,
+        module_separator: None,
     }
 }
 
@@ -399,7 +505,14 @@ const fn tree_sitter_query() -> Language {
             subpath: None,
         }),
         highlight_query: None,
+        textobjects_query: None,
+        indents_query: None,
+        folds_query: None,
+        auto_pairs: DEFAULT_AUTO_PAIRS,
         formatter_command: None,
+        line_comment_prefix: Some(";"),
+        block_comment_affixes: None,
+        module_separator: None,
     }
 }
 
@@ -430,6 +543,12 @@ const fn typescript(tsx: bool) -> Language {
             subpath: Some(choice(tsx, "tsx", "typescript")),
         }),
         formatter_command: Some(Command("prettierd", choice(tsx, &[".tsx"], &[".ts"]))),
+        textobjects_query: Some(include_str!("../../contrib/textobjects/typescript.scm")),
+        indents_query: Some(include_str!("../../contrib/indents/typescript.scm")),
+        folds_query: Some(include_str!("../../contrib/folds/typescript.scm")),
+        auto_pairs: DEFAULT_AUTO_PAIRS,
+        line_comment_prefix: Some("//"),
+        block_comment_affixes: Some(("/*", "*/")),
         ..Language::new()
     }
 }
@@ -448,6 +567,13 @@ const fn xml() -> Language {
         }),
         formatter_command: None,
         highlight_query: None,
+        textobjects_query: None,
+        indents_query: None,
+        folds_query: None,
+        auto_pairs: DEFAULT_AUTO_PAIRS,
+        line_comment_prefix: None,
+        block_comment_affixes: Some(("<!--", "-->")),
+        module_separator: None,
     }
 }
 
@@ -465,6 +591,13 @@ const fn yaml() -> Language {
         }),
         formatter_command: None,
         highlight_query: None,
+        textobjects_query: None,
+        indents_query: None,
+        folds_query: None,
+        auto_pairs: DEFAULT_AUTO_PAIRS,
+        line_comment_prefix: Some("#"),
+        block_comment_affixes: None,
+        module_separator: None,
     }
 }
 
@@ -485,6 +618,13 @@ const fn zig() -> Language {
         }),
         formatter_command: Some(Command("zig", &["fmt", "--stdin"])),
         highlight_query: None,
+        textobjects_query: None,
+        indents_query: None,
+        folds_query: None,
+        auto_pairs: DEFAULT_AUTO_PAIRS,
+        line_comment_prefix: Some("//"),
+        block_comment_affixes: None,
+        module_separator: None,
     }
 }
 
@@ -505,5 +645,12 @@ const fn golang() -> Language {
         }),
         formatter_command: Some(Command("gofmt", &[])),
         highlight_query: None,
+        textobjects_query: Some(include_str!("../../contrib/textobjects/go.scm")),
+        indents_query: Some(include_str!("../../contrib/indents/go.scm")),
+        folds_query: Some(include_str!("../../contrib/folds/go.scm")),
+        auto_pairs: DEFAULT_AUTO_PAIRS,
+        line_comment_prefix: Some("//"),
+        block_comment_affixes: Some(("/*", "*/")),
+        module_separator: None,
     }
 }
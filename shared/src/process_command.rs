@@ -31,6 +31,27 @@ impl ProcessCommand {
         }
     }
 
+    pub fn command(&self) -> &str {
+        &self.command
+    }
+
+    /// Runs this command through `wrapper`, for example `["docker", "exec", "-i", "dev"]`
+    /// wraps `rust-analyzer` into `docker exec -i dev rust-analyzer`.
+    pub fn wrap(self, wrapper: &[String]) -> anyhow::Result<Self> {
+        let (command, args) = wrapper
+            .split_first()
+            .ok_or_else(|| anyhow::anyhow!("The wrapper of {:?} is empty", self.command))?;
+        Ok(Self {
+            command: command.clone(),
+            args: args
+                .iter()
+                .cloned()
+                .chain(std::iter::once(self.command))
+                .chain(self.args)
+                .collect(),
+        })
+    }
+
     pub fn spawn(&self) -> SpawnCommandResult {
         log::info!("ProcessCommand::spawn {:?} {:?}", self.command, self.args);
         if which::which(&self.command).is_err() {
@@ -115,6 +136,19 @@ impl std::fmt::Display for ProcessCommand {
 mod test_process_command {
     use super::ProcessCommand;
 
+    #[test]
+    fn wrap_command() -> anyhow::Result<()> {
+        let wrapper = ["docker", "exec", "-i", "dev"].map(String::from);
+        assert_eq!(
+            ProcessCommand::new("rust-analyzer", &["--log"])
+                .wrap(&wrapper)?
+                .to_string(),
+            "docker exec -i dev rust-analyzer --log"
+        );
+        assert!(ProcessCommand::new("rust-analyzer", &[]).wrap(&[]).is_err());
+        Ok(())
+    }
+
     #[test]
     fn failed_command_includes_exit_code_and_stderr() {
         let err = ProcessCommand::new("bash", &["-c", "yo"])
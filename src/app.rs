@@ -1,47 +1,94 @@
 use crate::{
     buffer::Buffer,
     clipboard::CopiedTexts,
+    compiler_output,
     components::{
         component::{Component, ComponentId, GetGridResult},
+        debug_panel::DebugPanel,
+        diff_view::{DiffBase, DiffView},
         dropdown::{DropdownItem, DropdownRender},
-        editor::{DispatchEditor, Editor, IfCurrentNotFound, Movement},
+        editor::{Direction, DispatchEditor, Editor, IfCurrentNotFound, Mode, Movement},
+        hierarchy_view::HierarchyView,
+        hover_popup::HoverPopup,
         keymap_legend::{
             Keymap, KeymapLegendBody, KeymapLegendConfig, KeymapLegendSection, Keymaps,
         },
+        markdown_preview::{self, MarkdownPreview, MARKDOWN_PREVIEW_DEBOUNCE},
+        passphrase_prompt::PassphrasePrompt,
         prompt::{Prompt, PromptConfig, PromptHistoryKey},
+        smooth_scroll::SMOOTH_SCROLL_FRAME_INTERVAL,
         suggestive_editor::{
             DispatchSuggestiveEditor, Info, SuggestiveEditor, SuggestiveEditorFilter,
         },
+        task_output::TaskOutput,
     },
-    context::{Context, GlobalMode, LocalSearchConfigMode, QuickfixListSource, Search},
+    config::{Config, Indentation},
+    context::{
+        Context, GlobalMode, LocalSearchConfig, LocalSearchConfigMode, QuickfixListSource, Search,
+    },
+    dap::session::{DebugCommand, DebugSession, DebugSessionUpdate},
+    encryption::{Encryption, Passphrase},
+    file_drop,
+    file_watcher::FileWatcher,
+    frecency::Frecency,
     frontend::Frontend,
-    git,
-    grid::{Grid, LineUpdate},
+    git::{self, blame::BlameLine},
     history::History,
+    keymap_config::{KeymapConfig, KeymapMode, Lookup, Target, KEY_SEQUENCE_TIMEOUT},
     layout::Layout,
     list::{self, grep::RegexConfig, WalkBuilderConfig},
     lsp::{
         completion::CompletionItem,
+        diagnostic::{DiagnosticFilter, Severity},
         goto_definition_response::GotoDefinitionResponse,
+        hierarchy::{HierarchyContext, HierarchyItem, HierarchyKind},
+        hover::Hover,
         manager::LspManager,
         process::{FromEditor, LspNotification, ResponseContext},
         symbols::Symbols,
         workspace_edit::WorkspaceEdit,
     },
+    macros::{self, MacroName, Macros},
+    markdown,
+    named_mark::{self, GlobalMark, GlobalMarks},
+    plugin::{PluginAction, PluginBuffer, Plugins},
     position::Position,
-    quickfix_list::{Location, QuickfixList, QuickfixListItem, QuickfixListType},
+    quickfix_list::{
+        DiagnosticSeverityRange, Location, QuickfixList, QuickfixListItem, QuickfixListType,
+    },
+    recovery::{self, Recovery, RecoveryFile},
+    refactor_log::RefactorLog,
+    reference_rename,
     screen::{Screen, Window},
-    selection::SelectionMode,
+    screen_reader::{ScreenReader, ScreenReaderState},
+    selection::{CharIndex, SelectionMode, SelectionSet},
+    server::{RemoteClients, RemoteRequest},
+    session::{Session, SessionFile, SessionStore},
+    shell_task::{RunningShellTask, ShellTask},
+    spell_check::SpellChecker,
+    status_line::{self, StatusLineComponent},
+    style::Style,
     syntax_highlight::{HighlighedSpans, SyntaxHighlightRequest},
+    task::{TaskId, TaskOrigin, Tasks},
+    timer::{Timer, TimerKey},
+    transformation::Transformation,
     ui_tree::{ComponentKind, KindedComponent},
+    workspace_edit_review::{ReviewDecision, WorkspaceEditReview},
 };
 use event::event::Event;
 use itertools::{Either, Itertools};
+use my_proc_macros::key;
 use name_variant::NamedVariant;
-use shared::{canonicalized_path::CanonicalizedPath, language::Language};
+use nonempty::NonEmpty;
+use ropey::Rope;
+use shared::{
+    canonicalized_path::CanonicalizedPath,
+    language::{self, Language},
+};
 use std::{
     any::TypeId,
     cell::RefCell,
+    collections::{HashMap, HashSet, VecDeque},
     path::{Path, PathBuf},
     rc::Rc,
     sync::{
@@ -51,6 +98,11 @@ use std::{
 };
 use DispatchEditor::*;
 
+/// The number of lines shown in the preview of the file picker
+const FILE_PREVIEW_LINES_COUNT: usize = 100;
+/// The number of recently closed files listed in the buffer picker
+const RECENTLY_CLOSED_FILES_LIMIT: usize = 10;
+
 pub(crate) struct App<T: Frontend> {
     context: Context,
 
@@ -75,21 +127,127 @@ pub(crate) struct App<T: Frontend> {
 
     /// Used for navigating between opened files
     file_path_history: History<CanonicalizedPath>,
+    /// Used for ranking the items of file picker
+    file_frecency: Frecency<PathBuf>,
+    /// Sorted from the least recently closed to the most recently closed
+    recently_closed_files: Vec<CanonicalizedPath>,
     status_line_components: Vec<StatusLineComponent>,
     last_action_description: Option<String>,
+    /// Files that are being blamed in the background, so that they are not blamed twice
+    pending_git_blames: HashSet<CanonicalizedPath>,
+    screen_reader: Option<ScreenReader>,
+    bell: Bell,
+    /// True while the status line is flashed by the visual bell
+    is_bell_flashing: bool,
+    keymap_config: KeymapConfig,
+    /// The keys typed so far of a key sequence of `keymap_config`
+    pending_keys: Vec<event::KeyEvent>,
+    /// Incremented whenever a key is pending, so that only the timeout of the
+    /// latest pending key flushes the pending keys
+    pending_keys_id: usize,
+    /// Incremented whenever the previewed markdown file is edited, so that only the debounce of
+    /// the latest edit refreshes the preview
+    markdown_preview_refresh_id: usize,
+    /// Where the cursor was when the document highlights were last scheduled
+    document_highlight_anchor: Option<(ComponentId, CharIndex)>,
+    /// Incremented whenever the cursor moves, so that only the cursor that rests
+    /// for `Config::document_highlight_delay` requests the document highlights
+    document_highlight_id: usize,
+    macros: Macros,
+    /// The scratch buffers opened for editing macros, which are written back to their macros
+    macro_editors: HashMap<ComponentId, MacroName>,
+    macro_recording: Option<MacroRecording>,
+    /// The macros being run, innermost last, so that a macro cannot run itself
+    running_macros: Vec<MacroName>,
+    /// The local marks live in their buffers only
+    global_marks: GlobalMarks,
+    shell_task: Option<RunningShellTask>,
+    /// Incremented whenever a shell task is run
+    shell_task_id: usize,
+    /// Rerun by `Dispatch::RerunShellTask`
+    last_shell_task: Option<ShellTask>,
+    debug_session: Option<DebugSession>,
+    /// Incremented whenever a debugging session is started
+    debug_session_id: usize,
+    /// `None` if there is no plugin directory
+    plugins: Option<Plugins>,
+    /// The clients of the headless server mode, see `server`
+    remote_clients: Option<RemoteClients>,
+    refactor_log: RefactorLog,
+    /// Describes the request (e.g. a rename) whose workspace edit is yet to be received
+    /// from the LSP server, which is recorded in `refactor_log`
+    pending_refactor: Option<String>,
+    config: Config,
+    /// Initialized from `config`, and then adjusted by the diagnostic filtering commands
+    diagnostic_filter: DiagnosticFilter,
+    /// Started when the app is run, so that tests are not interrupted by the changes they make
+    file_watcher: Option<FileWatcher>,
+    /// Persists the unsaved changes of the buffers, which is `None` in tests
+    recovery: Option<Recovery>,
+    /// Saves the session of the working directory on exit, which is `None` in tests
+    session_store: Option<SessionStore>,
+    /// The long operations that are running in the background
+    tasks: Tasks,
+    /// Sends the delayed messages, for example the debounced requests
+    timer: Timer,
+    /// The global search (or replace preview) that is running, which is cancelled by the next one
+    global_search_task: Option<TaskId>,
+    /// The large workspace edit whose files are being reviewed
+    workspace_edit_review: Option<WorkspaceEditReview>,
+    /// The prompt of the file under review, which cancels the review if it is closed without a decision
+    workspace_edit_review_prompt: Option<ComponentId>,
+    /// The windows that are scrolled together, with the scroll offsets they were last seen with
+    scroll_bound_windows: Vec<(ComponentId, u16)>,
+    hover_popup: Option<HoverPopup>,
+}
+
+struct MacroRecording {
+    register: char,
+    keys: Vec<event::KeyEvent>,
+    /// The number of recorded keys before the key sequence being typed,
+    /// so that the key sequence that stops the recording is not recorded
+    sequence_start: usize,
 }
 
 const GLOBAL_TITLE_BAR_HEIGHT: u16 = 1;
 
-#[derive(Clone)]
-pub(crate) enum StatusLineComponent {
-    CurrentWorkingDirectory,
-    GitBranch,
-    Mode,
-    SelectionMode,
-    LastDispatch,
+/// How the user is notified of rejected actions,
+/// such as unbound keys or movements beyond the boundaries of the buffer.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) enum Bell {
+    #[default]
+    None,
+    /// Flashes the status line
+    Visual,
+    /// Rings the terminal bell
+    Audible,
+}
+
+impl Bell {
+    pub(crate) fn parse_config(config: &str) -> anyhow::Result<Bell> {
+        Ok(match config.trim() {
+            "none" => Bell::None,
+            "visual" => Bell::Visual,
+            "audible" => Bell::Audible,
+            name => return Err(anyhow::anyhow!("Unknown bell: {name:?}")),
+        })
+    }
 }
 
+/// The number of identical movement keys that are handled at most
+/// when they are queued up faster than they are rendered
+const MAX_QUEUED_KEY_REPEATS: usize = 8;
+
+/// Pastes that are longer than this (in bytes) are applied once a frame is rendered,
+/// see `App::defer_large_paste`
+pub(crate) const LARGE_PASTE_THRESHOLD: usize = 1024 * 1024;
+
+/// How long the status line stays flashed by the visual bell
+const BELL_FLASH_DURATION: std::time::Duration = std::time::Duration::from_millis(150);
+
+/// Average silent reading speed, used to estimate the reading time of prose
+const WORDS_PER_MINUTE: usize = 200;
+
 impl<T: Frontend> App<T> {
     #[cfg(test)]
     pub(crate) fn new(
@@ -124,6 +282,9 @@ impl<T: Frontend> App<T> {
             context: Context::new(working_directory.clone()),
             receiver,
             lsp_manager: LspManager::new(sender.clone(), working_directory.clone()),
+            tasks: Tasks::new(sender.clone()),
+            timer: Timer::new(sender.clone()),
+            global_search_task: None,
             enable_lsp: true,
             sender,
             layout: Layout::new(
@@ -136,9 +297,44 @@ impl<T: Frontend> App<T> {
             global_title: None,
 
             file_path_history: History::new(),
+            file_frecency: Frecency::new(),
+            recently_closed_files: Vec::new(),
 
             status_line_components,
             last_action_description: None,
+            pending_git_blames: HashSet::new(),
+            screen_reader: None,
+            bell: Bell::default(),
+            is_bell_flashing: false,
+            keymap_config: KeymapConfig::default(),
+            pending_keys: Vec::new(),
+            pending_keys_id: 0,
+            markdown_preview_refresh_id: 0,
+            document_highlight_anchor: None,
+            document_highlight_id: 0,
+            macros: Macros::default(),
+            macro_editors: HashMap::new(),
+            macro_recording: None,
+            running_macros: Vec::new(),
+            global_marks: GlobalMarks::default(),
+            shell_task: None,
+            shell_task_id: 0,
+            last_shell_task: None,
+            debug_session: None,
+            debug_session_id: 0,
+            plugins: None,
+            remote_clients: None,
+            refactor_log: RefactorLog::default(),
+            pending_refactor: None,
+            config: Config::default(),
+            diagnostic_filter: DiagnosticFilter::default(),
+            file_watcher: None,
+            recovery: None,
+            session_store: None,
+            workspace_edit_review: None,
+            workspace_edit_review_prompt: None,
+            scroll_bound_windows: Vec::new(),
+            hover_popup: None,
         };
         Ok(app)
     }
@@ -160,47 +356,274 @@ impl<T: Frontend> App<T> {
             frontend.enter_alternate_screen()?;
             frontend.enable_raw_mode()?;
             frontend.enable_mouse_capture()?;
+            if let Some(mut events) = frontend.event_source() {
+                let sender = self.sender.clone();
+                std::thread::spawn(move || {
+                    while let Ok(event) = events.read() {
+                        if sender.send(AppMessage::Event(event)).is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+        }
+
+        let file_watcher = FileWatcher::start(self.sender.clone());
+        for buffer in self.layout.buffers() {
+            if let Some(path) = buffer.borrow().path() {
+                file_watcher.watch(&path)
+            }
         }
+        self.file_watcher = Some(file_watcher);
+        self.tasks.start_spinner(self.sender.clone());
+
+        let sender = self.sender.clone();
+        std::thread::spawn(move || loop {
+            std::thread::sleep(recovery::RECOVERY_INTERVAL);
+            if sender.send(AppMessage::PersistUnsavedChanges).is_err() {
+                break;
+            }
+        });
 
         if let Some(entry_path) = entry_path {
-            self.open_file(&entry_path, OpenFileOption::Focus)?;
+            self.focus_file(&entry_path)?;
+        } else {
+            self.restore_session()?;
+        }
+        if self.has_recovery_files() {
+            self.open_recovery_prompt()?;
         }
 
         self.render()?;
+        self.announce_to_screen_reader()?;
 
         while let Ok(message) = self.receiver.recv() {
-            match message {
-                AppMessage::Event(event) => self.handle_event(event),
-                AppMessage::LspNotification(notification) => {
-                    self.handle_lsp_notification(notification).map(|_| false)
-                }
-                AppMessage::QuitAll => {
-                    self.quit()?;
-                    Ok(true)
-                }
-                AppMessage::SyntaxHighlightResponse {
-                    component_id,
-                    highlighted_spans,
-                } => self
-                    .update_highlighted_spans(component_id, highlighted_spans)
-                    .map(|_| false),
-            }
-            .unwrap_or_else(|e| {
-                self.show_global_info(Info::new("ERROR".to_string(), e.to_string()));
-                false
-            });
+            self.handle_queued_messages(message)?;
 
             if self.should_quit() {
                 break;
             }
 
             self.render()?;
+            self.announce_to_screen_reader()?;
         }
 
         self.quit()
     }
 
+    /// Handles `message` together with the messages that are already queued,
+    /// so that only one frame is rendered for all of them.
+    ///
+    /// When rendering cannot keep up (e.g. over a high-latency connection),
+    /// the surplus of a held-down movement key is discarded,
+    /// so that the editor does not fall behind the keyboard.
+    fn handle_queued_messages(&mut self, message: AppMessage) -> anyhow::Result<()> {
+        let mut queue: VecDeque<_> = std::iter::once(message)
+            .chain(self.receiver.try_iter())
+            .collect();
+        while let Some(message) = queue.pop_front() {
+            if let AppMessage::Event(Event::Key(key)) = &message {
+                let repeats = queue
+                    .iter()
+                    .take_while(|queued| {
+                        matches!(queued, AppMessage::Event(Event::Key(queued)) if queued == key)
+                    })
+                    .count();
+                if repeats >= MAX_QUEUED_KEY_REPEATS && self.is_movement_key(key) {
+                    queue.drain(..repeats + 1 - MAX_QUEUED_KEY_REPEATS);
+                }
+            }
+            self.handle_app_message(message)?;
+            if self.should_quit() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn is_movement_key(&self, key: &event::KeyEvent) -> bool {
+        self.current_component()
+            .borrow()
+            .editor()
+            .is_movement_key(&self.context, key)
+    }
+
+    fn handle_app_message(&mut self, message: AppMessage) -> anyhow::Result<()> {
+        match message {
+            AppMessage::Event(event) => self.handle_event(event),
+            AppMessage::LspNotification(notification) => {
+                self.handle_lsp_notification(notification).map(|_| false)
+            }
+            AppMessage::QuitAll => {
+                self.quit()?;
+                Ok(true)
+            }
+            AppMessage::SyntaxHighlightResponse {
+                component_id,
+                highlighted_spans,
+            } => self
+                .update_highlighted_spans(component_id, highlighted_spans)
+                .map(|_| false),
+            AppMessage::TerminalOutput(bytes) => {
+                self.layout.process_terminal_output(&bytes);
+                Ok(false)
+            }
+            AppMessage::TerminalExited(component_id) => {
+                self.layout.handle_terminal_exited(component_id);
+                Ok(false)
+            }
+            AppMessage::PipeToShellFinished(finished) => {
+                self.handle_pipe_to_shell_finished(finished).map(|_| false)
+            }
+            AppMessage::GlobalSearchFinished(result) => {
+                self.handle_global_search_finished(result).map(|_| false)
+            }
+            AppMessage::FilePickerFilesFound {
+                component_id,
+                result,
+            } => self
+                .handle_file_picker_files_found(component_id, result)
+                .map(|_| false),
+            AppMessage::GlobalReplacePreviewFinished(result) => self
+                .handle_global_replace_preview_finished(result)
+                .map(|_| false),
+            AppMessage::WriteSelectionsFinished {
+                path,
+                append,
+                result,
+            } => {
+                self.handle_write_selections_finished(path, append, result);
+                Ok(false)
+            }
+            AppMessage::InsertBelowCursorFinished {
+                component_id,
+                source,
+                result,
+            } => self
+                .handle_insert_below_cursor_finished(component_id, source, result)
+                .map(|_| false),
+            AppMessage::ShellTaskOutput { id, line } => {
+                self.handle_shell_task_output(id, line).map(|_| false)
+            }
+            AppMessage::ShellTaskExited { id } => self.handle_shell_task_exited(id).map(|_| false),
+            AppMessage::DebugAdapterMessage {
+                session_id,
+                message,
+            } => self
+                .handle_debug_adapter_message(session_id, message)
+                .map(|_| false),
+            AppMessage::DebugAdapterExited { session_id } => {
+                self.handle_debug_adapter_exited(session_id).map(|_| false)
+            }
+            AppMessage::GitBlameFinished {
+                path,
+                content,
+                open_panel,
+                result,
+            } => self
+                .handle_git_blame_finished(path, content, open_panel, result)
+                .map(|_| false),
+            AppMessage::BellFlashFinished => {
+                self.is_bell_flashing = false;
+                Ok(false)
+            }
+            AppMessage::ScrollAnimationTicked => {
+                let running_animations_count = self
+                    .layout
+                    .components()
+                    .into_iter()
+                    .filter(|component| {
+                        component
+                            .component()
+                            .borrow_mut()
+                            .editor_mut()
+                            .tick_scroll_animation()
+                    })
+                    .count();
+                if running_animations_count > 0 {
+                    self.animate_scroll()
+                }
+                Ok(false)
+            }
+            // The app is rendered after every message anyway
+            AppMessage::RenderRemainingCells => Ok(false),
+            AppMessage::MarkdownPreviewRefreshDue { id } => {
+                self.refresh_markdown_preview(id).map(|_| false)
+            }
+            AppMessage::DocumentHighlightDue { id } => {
+                self.request_document_highlights(id).map(|_| false)
+            }
+            AppMessage::KeySequenceTimeout { id } => {
+                self.handle_key_sequence_timeout(id).map(|_| false)
+            }
+            AppMessage::FileChangedOnDisk(path) => {
+                self.handle_file_changed_on_disk(path).map(|_| false)
+            }
+            AppMessage::PersistUnsavedChanges => self.persist_unsaved_changes().map(|_| false),
+            AppMessage::LargePasteDue {
+                component_id,
+                content,
+            } => {
+                // The paste is dropped if another component is focused in the meantime
+                if self.current_component().borrow().id() == component_id {
+                    self.handle_component_event(Event::Paste(content))
+                }
+                Ok(false)
+            }
+            AppMessage::RemoteClientConnected => {
+                // The whole screen is rendered for the new client
+                self.frontend
+                    .lock()
+                    .unwrap()
+                    .set_previous_screen(Screen::default());
+                Ok(false)
+            }
+            AppMessage::RemoteRequest {
+                client_id,
+                id,
+                request,
+            } => self
+                .handle_remote_request(client_id, id, request)
+                .map(|_| false),
+            AppMessage::TaskFinished { id, message } => {
+                if self.tasks.finish(id) {
+                    self.handle_app_message(*message).map(|_| false)
+                } else {
+                    Ok(false)
+                }
+            }
+            AppMessage::TaskSpinnerTicked => Ok(false),
+        }
+        .unwrap_or_else(|e| {
+            self.show_global_info(Info::new("ERROR".to_string(), e.to_string()));
+            false
+        });
+        Ok(())
+    }
+
+    #[cfg(test)]
+    /// Wait for the next message sent by a background thread (e.g. the output of a shell command),
+    /// and handle it as if it were received by `App::run`.
+    pub(crate) fn wait_for_app_message(&mut self) -> anyhow::Result<()> {
+        let message = self
+            .receiver
+            .recv_timeout(std::time::Duration::from_secs(10))?;
+        self.handle_app_message(message)
+    }
+
     pub(crate) fn quit(&mut self) -> anyhow::Result<()> {
+        if let Some(running) = self.shell_task.take() {
+            running.kill()
+        }
+        self.stop_debugging();
+        self.sync_global_marks()
+            .unwrap_or_else(|error| log::error!("App::quit: failed to save marks: {error:?}"));
+        // Failing to save the session should not prevent the editor from exiting
+        self.save_session()
+            .unwrap_or_else(|error| log::error!("App::quit: failed to save session: {error:?}"));
+        if let Some(recovery) = self.recovery.as_mut() {
+            recovery.remove_persisted()?;
+        }
         let mut frontend = self.frontend.lock().unwrap();
         frontend.leave_alternate_screen()?;
         frontend.disable_raw_mode()?;
@@ -216,10 +639,55 @@ impl<T: Frontend> App<T> {
 
     /// Returns true if the app should quit.
     fn handle_event(&mut self, event: Event) -> anyhow::Result<bool> {
-        // Pass event to focused window
+        // The passphrase of encrypted files must not end up in the macro register
+        let is_passphrase_prompt =
+            self.current_component().borrow().type_id() == TypeId::of::<PassphrasePrompt>();
+        if let (Event::Key(key), Some(recording)) = (&event, &mut self.macro_recording) {
+            // The keys of running macros are not recorded, only the keys that run them
+            if self.running_macros.is_empty() && !is_passphrase_prompt {
+                if !self.layout.current_component_is_keymap_legend() {
+                    recording.sequence_start = recording.keys.len();
+                }
+                recording.keys.push(key.clone());
+            }
+        }
         let component = self.current_component();
-        self.context
-            .set_contextual_keymaps(component.borrow().contextual_keymaps());
+        let event = match event {
+            // Files dropped onto the terminal are received as a paste of their paths,
+            // which cannot be told apart from pasting the paths, so the user is asked
+            Event::Paste(content) if component.borrow().editor().mode == Mode::Normal => {
+                match file_drop::parse_dropped_paths(&content) {
+                    Some(paths) => {
+                        self.open_dropped_files_prompt(paths, content)?;
+                        return Ok(false);
+                    }
+                    None => Event::Paste(content),
+                }
+            }
+            event => event,
+        };
+        let event = match event {
+            Event::Paste(content) if content.len() > LARGE_PASTE_THRESHOLD => {
+                self.defer_large_paste(component.borrow().id(), content);
+                return Ok(false);
+            }
+            event => event,
+        };
+        // The hover popup takes the keys that scroll editors, and is dismissed by escape
+        if let (Event::Key(key), Some(hover_popup)) = (&event, &mut self.hover_popup) {
+            match key {
+                key!("ctrl+d") => {
+                    hover_popup.scroll(Direction::End);
+                    return Ok(false);
+                }
+                key!("ctrl+u") => {
+                    hover_popup.scroll(Direction::Start);
+                    return Ok(false);
+                }
+                key!("esc") => self.hover_popup = None,
+                _ => {}
+            }
+        }
         match event {
             Event::Resize(columns, rows) => {
                 self.resize(Dimension {
@@ -227,24 +695,138 @@ impl<T: Frontend> App<T> {
                     width: columns,
                 });
             }
-            event => {
-                let dispatches = component.borrow_mut().handle_event(&self.context, event);
-                self.handle_dispatches_result(dispatches)
-                    .unwrap_or_else(|e| {
-                        self.show_global_info(Info::new("ERROR".to_string(), e.to_string()))
-                    });
-            }
+            Event::Key(key) if !self.keymap_config.is_empty() => self.handle_mapped_key(key)?,
+            event => self.handle_component_event(event),
         }
 
         Ok(false)
     }
 
+    /// Pass event to focused window
+    fn handle_component_event(&mut self, event: Event) {
+        let component = self.current_component();
+        self.context
+            .set_contextual_keymaps(component.borrow().contextual_keymaps());
+        let dispatches = component.borrow_mut().handle_event(&self.context, event);
+        self.sync_scroll_bound_windows();
+        self.handle_dispatches_result(dispatches)
+            .unwrap_or_else(|e| {
+                self.show_global_info(Info::new("ERROR".to_string(), e.to_string()))
+            });
+        self.cancel_orphaned_workspace_edit_review();
+    }
+
+    /// Handles `key` according to the user-defined mappings of the mode of the focused window.
+    fn handle_mapped_key(&mut self, key: event::KeyEvent) -> anyhow::Result<()> {
+        if !self.pending_keys.is_empty() {
+            self.hide_editor_info()
+        }
+        let mode = KeymapMode::from_mode(&self.current_component().borrow().editor().mode);
+        let Some(mode) = mode else {
+            self.flush_pending_keys();
+            self.handle_component_event(Event::Key(key));
+            return Ok(());
+        };
+        let mut keys = std::mem::take(&mut self.pending_keys);
+        keys.push(key);
+        match self.keymap_config.lookup(mode, &keys) {
+            Lookup::Pending => {
+                self.show_pending_keys_hint(mode, &keys)?;
+                self.pending_keys = keys;
+                self.pending_keys_id += 1;
+                let id = self.pending_keys_id;
+                let sender = self.sender.clone();
+                std::thread::spawn(move || {
+                    std::thread::sleep(KEY_SEQUENCE_TIMEOUT);
+                    let _ = sender.send(AppMessage::KeySequenceTimeout { id });
+                });
+            }
+            Lookup::Mapped(keys) => {
+                for key in keys {
+                    self.handle_component_event(Event::Key(key))
+                }
+            }
+            Lookup::Command(command) => self.run_command(command)?,
+            Lookup::Unbound => self.ring_bell()?,
+            Lookup::Unmapped if keys.len() > 1 => {
+                // The previously pending keys are handled as they are,
+                // while the last key might still begin another key sequence
+                let last = keys.split_off(keys.len() - 1);
+                self.pending_keys = keys;
+                self.flush_pending_keys();
+                for key in last {
+                    self.handle_mapped_key(key)?
+                }
+            }
+            Lookup::Unmapped => {
+                for key in keys {
+                    self.handle_component_event(Event::Key(key))
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Lists the continuations of the pending `keys`,
+    /// described by the keymap legends of the keys they are mapped to.
+    fn show_pending_keys_hint(
+        &mut self,
+        mode: KeymapMode,
+        keys: &[event::KeyEvent],
+    ) -> anyhow::Result<()> {
+        let display = |keys: &[event::KeyEvent]| keys.iter().map(|key| key.display()).join(" ");
+        let component = self.current_component();
+        let content = self
+            .keymap_config
+            .continuations(mode, keys)
+            .into_iter()
+            .map(|(rest, to)| match to {
+                Target::Keys(to) => {
+                    let description = component
+                        .borrow()
+                        .editor()
+                        .describe_keys(&self.context, to)
+                        .map(|description| format!(" ({description})"))
+                        .unwrap_or_default();
+                    format!("{} → {}{description}", display(rest), display(to))
+                }
+                Target::Command(command) => format!("{} → {command} (command)", display(rest)),
+                Target::Unbound => format!("{} → (unbound)", display(rest)),
+            })
+            .sorted()
+            .join("\n");
+        self.show_editor_info(Info::new(
+            format!("Pending keys: {}", display(keys)),
+            content,
+        ))
+    }
+
+    fn flush_pending_keys(&mut self) {
+        for key in std::mem::take(&mut self.pending_keys) {
+            self.handle_component_event(Event::Key(key))
+        }
+    }
+
+    fn handle_key_sequence_timeout(&mut self, id: usize) -> anyhow::Result<()> {
+        if id == self.pending_keys_id && !self.pending_keys.is_empty() {
+            self.hide_editor_info();
+            self.flush_pending_keys()
+        }
+        Ok(())
+    }
+
     /// Return true if there's no more windows
     fn should_quit(&mut self) -> bool {
         self.layout.components().is_empty()
     }
 
     pub(crate) fn render(&mut self) -> Result<(), anyhow::Error> {
+        self.cancel_orphaned_tasks();
+        self.cancel_orphaned_workspace_edit_review();
+        self.request_git_blame();
+        self.schedule_document_highlights();
+        self.request_large_file_syntax_highlights()?;
+        self.layout.refresh_terminal()?;
         let screen = self.get_screen()?;
         self.render_screen(screen)?;
         Ok(())
@@ -294,52 +876,41 @@ impl<T: Frontend> App<T> {
             .unzip();
         let borders = self.layout.borders();
         let cursor = cursors.into_iter().find_map(|cursor| cursor);
-        let screen = Screen::new(windows, borders, cursor, self.context.theme().ui.border);
+        self.dismiss_moved_hover_popup();
+        let hover_popup_window = match (&mut self.hover_popup, &cursor) {
+            (Some(hover_popup), Some(cursor)) => {
+                hover_popup.set_rectangle(*cursor.position(), dimension);
+                Some(Window::new(
+                    hover_popup.get_grid(self.context.theme()),
+                    hover_popup.rectangle().clone(),
+                ))
+            }
+            _ => None,
+        };
+        let screen = Screen::new(windows, borders, cursor, self.context.theme().ui.border)
+            .set_floating_window(hover_popup_window);
 
         // Set the global title
         let global_title_window = {
-            let title = self.global_title.clone().unwrap_or_else(|| {
-                self.status_line_components
-                    .iter()
-                    .filter_map(|component| match component {
-                        StatusLineComponent::CurrentWorkingDirectory => {
-                            Some(self.working_directory.display_absolute())
-                        }
-                        StatusLineComponent::GitBranch => self.current_branch(),
-                        StatusLineComponent::Mode => Some(
-                            self.context
-                                .mode()
-                                .map(|mode| mode.display())
-                                .unwrap_or_else(|| {
-                                    self.current_component().borrow().editor().display_mode()
-                                }),
-                        ),
-                        StatusLineComponent::SelectionMode => Some(
-                            self.current_component()
-                                .borrow()
-                                .editor()
-                                .display_selection_mode(),
-                        ),
-                        StatusLineComponent::LastDispatch => self.last_action_description.clone(),
-                    })
-                    .join(" │ ")
-            });
-            let title = format!(" {}", title);
-            let grid = Grid::new(Dimension {
-                height: 1,
-                width: dimension.width,
-            })
-            .render_content(
-                &title,
-                crate::grid::RenderContentLineNumber::NoLineNumber,
-                Vec::new(),
-                [LineUpdate {
-                    line_index: 0,
-                    style: self.context.theme().ui.global_title,
-                }]
-                .to_vec(),
-                self.context.theme(),
-            );
+            let segments = match self.global_title.clone() {
+                Some(title) => [(title, Style::new())].to_vec(),
+                None => {
+                    let styles = &self.context.theme().status_line;
+                    self.status_line_components
+                        .iter()
+                        .filter_map(|component| {
+                            let text = self.status_line_segment(component)?;
+                            Some((text, component.style(styles)))
+                        })
+                        .collect_vec()
+                }
+            };
+            let style = if self.is_bell_flashing {
+                self.context.theme().ui.global_title.reversed()
+            } else {
+                self.context.theme().ui.global_title
+            };
+            let grid = status_line::render(&segments, dimension.width, style, self.context.theme());
             Window::new(
                 grid,
                 crate::rectangle::Rectangle {
@@ -357,6 +928,64 @@ impl<T: Frontend> App<T> {
         Ok(screen)
     }
 
+    fn status_line_segment(&self, component: &StatusLineComponent) -> Option<String> {
+        let current_component = self.current_component();
+        let current_component = current_component.borrow();
+        let editor = current_component.editor();
+        match component {
+            StatusLineComponent::CurrentWorkingDirectory => {
+                Some(self.working_directory.display_absolute())
+            }
+            StatusLineComponent::GitBranch => self.current_branch(),
+            StatusLineComponent::Mode => Some(
+                self.context
+                    .mode()
+                    .map(|mode| mode.display())
+                    .unwrap_or_else(|| editor.display_mode()),
+            ),
+            StatusLineComponent::MacroRecording => self
+                .context
+                .recording_macro()
+                .map(|register| format!("Recording @{register}")),
+            StatusLineComponent::SelectionMode => Some(editor.display_selection_mode()),
+            StatusLineComponent::LastDispatch => self.last_action_description.clone(),
+            StatusLineComponent::WordCount => self.word_count(),
+            StatusLineComponent::DiagnosticCounts => self.diagnostic_counts(),
+            StatusLineComponent::FilePath => editor
+                .buffer()
+                .path()
+                .map(|path| path.try_display_relative()),
+            StatusLineComponent::Modified => editor.buffer().dirty().then(|| "[+]".to_string()),
+            StatusLineComponent::CursorPosition => {
+                let position = editor
+                    .buffer()
+                    .char_to_position(editor.get_cursor_char_index())
+                    .ok()?;
+                Some(format!("{}:{}", position.line + 1, position.column + 1))
+            }
+            StatusLineComponent::SelectionCount => {
+                let count = editor.selection_set.len();
+                (count > 1).then(|| format!("{count} selections"))
+            }
+            StatusLineComponent::LspStatus => {
+                self.lsp_manager.server_status(&editor.buffer().path()?)
+            }
+            StatusLineComponent::Indentation => editor
+                .buffer()
+                .path()
+                .map(|_| editor.buffer().indentation().display()),
+            StatusLineComponent::LineEnding => editor
+                .buffer()
+                .path()
+                .map(|_| editor.buffer().line_ending().display().to_string()),
+            StatusLineComponent::Charset => editor
+                .buffer()
+                .path()
+                .map(|_| editor.buffer().charset().display().to_string()),
+            StatusLineComponent::BackgroundTasks => self.tasks.status(),
+        }
+    }
+
     fn current_branch(&self) -> Option<String> {
         // Open the repository
         let repo = git2::Repository::open(self.working_directory.display_absolute()).ok()?;
@@ -367,14 +996,60 @@ impl<T: Frontend> App<T> {
         Some(branch.to_string())
     }
 
+    fn word_count(&self) -> Option<String> {
+        let component = self.current_component();
+        let borrow = component.borrow();
+        let buffer = borrow.editor().buffer();
+        if !crate::hard_wrap::is_prose(&buffer.path()?) {
+            return None;
+        }
+        let words = buffer.word_count();
+        let minutes = words.div_ceil(WORDS_PER_MINUTE);
+        Some(format!("{words} words, {minutes} min read"))
+    }
+
+    fn diagnostic_counts(&self) -> Option<String> {
+        let diagnostics = self
+            .current_component()
+            .borrow()
+            .editor()
+            .buffer()
+            .diagnostics();
+        let counts = [
+            ("✘", DiagnosticSeverityRange::Error),
+            ("▲", DiagnosticSeverityRange::Warning),
+            ("ℹ", DiagnosticSeverityRange::Information),
+            ("✎", DiagnosticSeverityRange::Hint),
+        ]
+        .into_iter()
+        .filter_map(|(icon, severity)| {
+            let count = diagnostics
+                .iter()
+                .filter(|diagnostic| severity.contains(diagnostic.severity))
+                .count();
+            (count > 0).then(|| format!("{icon} {count}"))
+        })
+        .join(" ");
+        (!counts.is_empty()).then_some(counts)
+    }
+
     fn render_screen(&mut self, screen: Screen) -> Result<(), anyhow::Error> {
         let mut frontend = self.frontend.lock().unwrap();
+        frontend.begin_synchronized_update()?;
         frontend.hide_cursor()?;
         let cursor = screen.cursor();
-        frontend.render_screen(screen)?;
+        let is_fully_rendered = frontend.render_screen(screen)?;
         if let Some(cursor) = cursor {
             frontend.show_cursor(&cursor)?;
         }
+        frontend.end_synchronized_update()?;
+        // Flushed at once, so that the remote frontend sends the frame as one message
+        frontend.flush()?;
+        if !is_fully_rendered {
+            // The remaining cells are rendered after the messages that are queued by then,
+            // so that keys are not delayed by a slow terminal
+            let _ = self.sender.send(AppMessage::RenderRemainingCells);
+        }
 
         Ok(())
     }
@@ -420,13 +1095,61 @@ impl<T: Frontend> App<T> {
                 scope,
                 if_current_not_found,
             } => self.open_search_prompt(scope, if_current_not_found)?,
-            Dispatch::OpenPipeToShellPrompt => self.open_pipe_to_shell_prompt()?,
-            Dispatch::OpenFile(path) => {
-                self.open_file(&path, OpenFileOption::Focus)?;
+            Dispatch::OpenPipeToShellPrompt(output) => self.open_pipe_to_shell_prompt(output)?,
+            Dispatch::PipeToShell { command, output } => self.pipe_to_shell(command, output),
+            Dispatch::OpenWriteSelectionsPrompt { append } => {
+                self.open_write_selections_prompt(append)?
+            }
+            Dispatch::WriteSelections { path, append } => self.write_selections(path, append),
+            Dispatch::OpenInsertBelowCursorPrompt => self.open_insert_below_cursor_prompt()?,
+            Dispatch::OpenSurroundWithTagPrompt => self.open_surround_with_tag_prompt()?,
+            Dispatch::OpenAlignPrompt => self.open_align_prompt()?,
+            Dispatch::OpenIncrementPrompt => self.open_increment_prompt()?,
+            Dispatch::OpenGitHunkRefPrompt {
+                scope,
+                if_current_not_found,
+            } => self.open_git_hunk_ref_prompt(scope, if_current_not_found)?,
+            Dispatch::OpenSpellingSuggestionsPrompt => self.open_spelling_suggestions_prompt()?,
+            Dispatch::OpenMarkdownPreview => self.open_markdown_preview()?,
+            Dispatch::AddToDictionary(word) => {
+                self.context.add_to_dictionary(&word)?;
+                self.show_global_info(Info::new(
+                    "Spelling".to_string(),
+                    format!("\"{word}\" is added to the dictionary."),
+                ))
+            }
+            Dispatch::InsertBelowCursor(source) => self.insert_below_cursor(source),
+            Dispatch::OpenBuildPrompt => self.open_build_prompt()?,
+            Dispatch::ShowLspLogs => self.show_lsp_logs()?,
+            Dispatch::RestartLspServers => self.restart_lsp_servers()?,
+            Dispatch::Build(command) => self.run_shell_task(ShellTask {
+                name: "build".to_string(),
+                command,
+            })?,
+            Dispatch::OpenShellTaskPrompt => self.open_shell_task_prompt()?,
+            Dispatch::RunShellTask(task) => self.run_shell_task(task)?,
+            Dispatch::RerunShellTask => self.rerun_shell_task()?,
+            Dispatch::OpenDebugPrompt => self.open_debug_prompt()?,
+            Dispatch::StartDebugging(name) => self.start_debugging(name)?,
+            Dispatch::ToggleBreakpoint => self.toggle_breakpoint()?,
+            Dispatch::Debug(command) => self.execute_debug_command(command)?,
+            Dispatch::OpenDebugWatchPrompt => self.open_debug_watch_prompt()?,
+            Dispatch::AddDebugWatch(expression) => self.add_debug_watch(expression)?,
+            Dispatch::AnswerPlugin {
+                callback_id,
+                answer,
+            } => self.answer_plugin(callback_id, answer)?,
+            #[cfg(test)]
+            Dispatch::LoadPlugins(directory) => {
+                if let Some(plugins) = Plugins::load(&directory)? {
+                    self.set_plugins(plugins)?
+                }
             }
+            Dispatch::OpenFile(path) => self.focus_file(&path)?,
 
-            Dispatch::OpenFileFromPathBuf(path) => {
-                self.open_file(&path.try_into()?, OpenFileOption::Focus)?;
+            Dispatch::OpenFileFromPathBuf(path) => self.focus_file(&path.try_into()?)?,
+            Dispatch::OpenEncryptedFile { path, passphrase } => {
+                self.open_file_with_passphrase(&path, OpenFileOption::Focus, Some(passphrase))?;
             }
 
             Dispatch::OpenFilePicker(kind) => {
@@ -442,10 +1165,11 @@ impl<T: Frontend> App<T> {
             }
             Dispatch::ResolveCompletionItem(completion_item) => {
                 if let Some(params) = self.get_request_params() {
-                    self.lsp_manager.send_message(
+                    self.lsp_manager.send_message_to(
                         params.path.clone(),
+                        completion_item.server.as_deref(),
                         FromEditor::CompletionItemResolve {
-                            completion_item,
+                            completion_item: completion_item.completion_item(),
                             params,
                         },
                     )?
@@ -539,6 +1263,7 @@ impl<T: Frontend> App<T> {
             }
             Dispatch::RenameSymbol { new_name } => {
                 if let Some(params) = self.get_request_params() {
+                    self.pending_refactor = Some(format!("Rename to {new_name:?}"));
                     self.lsp_manager.send_message(
                         params.path.clone(),
                         FromEditor::TextDocumentRename { params, new_name },
@@ -564,6 +1289,18 @@ impl<T: Frontend> App<T> {
                     )?;
                 }
             }
+            Dispatch::RequestHierarchy(kind) => {
+                if let Some(params) = self.get_request_params() {
+                    self.lsp_manager.send_message(
+                        params.path.clone(),
+                        FromEditor::PrepareHierarchy { params, kind },
+                    )?;
+                }
+            }
+            Dispatch::RequestHierarchyChildren { kind, item } => self.lsp_manager.send_message(
+                item.location.path.clone(),
+                FromEditor::HierarchyChildren { kind, item },
+            )?,
             Dispatch::DocumentDidChange {
                 path,
                 content,
@@ -575,6 +1312,9 @@ impl<T: Frontend> App<T> {
                     // let highlight_spans = self.context.highlight(language, &content)?;
                     // self.update_highlighted_spans(component_id, highlight_spans)?
                 }
+                if path.is_some() && path == self.markdown_preview_source() {
+                    self.debounce_markdown_preview_refresh()
+                }
                 if let Some(path) = path {
                     self.lsp_manager.send_message(
                         path.clone(),
@@ -587,6 +1327,9 @@ impl<T: Frontend> App<T> {
                 }
             }
             Dispatch::DocumentDidSave { path } => {
+                if let Some(recovery) = self.recovery.as_mut() {
+                    recovery.remove(path.as_ref())?;
+                }
                 self.lsp_manager.send_message(
                     path.clone(),
                     FromEditor::TextDocumentDidSave { file_path: path },
@@ -597,9 +1340,10 @@ impl<T: Frontend> App<T> {
                 self.set_quickfix_list_type(Default::default(), r#type)?;
             }
             Dispatch::GotoQuickfixListItem(movement) => self.goto_quickfix_list_item(movement)?,
-            Dispatch::ApplyWorkspaceEdit(workspace_edit) => {
-                self.apply_workspace_edit(workspace_edit)?;
-            }
+            Dispatch::ApplyWorkspaceEdit {
+                workspace_edit,
+                description,
+            } => self.apply_workspace_edit(workspace_edit, description)?,
             Dispatch::ShowKeymapLegend(keymap_legend_config) => {
                 self.show_keymap_legend(keymap_legend_config)
             }
@@ -610,19 +1354,66 @@ impl<T: Frontend> App<T> {
             Dispatch::ToEditor(dispatch_editor) => self.handle_dispatch_editor(dispatch_editor)?,
             Dispatch::GotoLocation(location) => self.go_to_location(&location)?,
             Dispatch::OpenMoveToIndexPrompt => self.open_move_to_index_prompt()?,
+            Dispatch::OpenGoToLinePrompt => self.open_go_to_line_prompt()?,
+            Dispatch::PreviewGoToLine(line) => self.preview_go_to_line(line)?,
             Dispatch::RunCommand(command) => self.run_command(command)?,
             Dispatch::QuitAll => self.quit_all()?,
             Dispatch::OpenCommandPrompt => self.open_command_prompt()?,
+            Dispatch::OpenCommandPalette => self.open_command_palette()?,
             Dispatch::SaveQuitAll => self.save_quit_all()?,
             Dispatch::RevealInExplorer(path) => self.reveal_path_in_explorer(&path)?,
             Dispatch::OpenYesNoPrompt(prompt) => self.open_yes_no_prompt(prompt)?,
+            Dispatch::OpenScratchBuffer(content) => self.open_scratch_buffer(content),
+            Dispatch::OpenDroppedFiles(paths) => self.open_dropped_files(paths)?,
+            Dispatch::PasteIgnoringFileDrop(content) => {
+                self.handle_component_event(Event::Paste(content))
+            }
+            Dispatch::IncrementalSearch {
+                search,
+                if_current_not_found,
+            } => self.incremental_search(search, if_current_not_found)?,
+            Dispatch::SetHardWrapWidth(width) => self.context.set_hard_wrap_width(width),
+            Dispatch::SetDisplayLineMovement(display_line_movement) => self
+                .context
+                .set_display_line_movement(display_line_movement),
+            Dispatch::SetLogicalOrderRendering(logical_order_rendering) => self
+                .context
+                .set_logical_order_rendering(logical_order_rendering),
+            Dispatch::SetWrapSelectionOnType(wrap_selection_on_type) => self
+                .context
+                .set_wrap_selection_on_type(wrap_selection_on_type),
+            Dispatch::SetShowGitBlame(show_git_blame) => {
+                self.context.set_show_git_blame(show_git_blame);
+                self.request_git_blame()
+            }
+            Dispatch::OpenGitBlamePanel => self.open_git_blame_panel()?,
+            Dispatch::OpenDiffView(base) => self.open_diff_view(base)?,
+            Dispatch::OpenClipboardDiffView {
+                use_system_clipboard,
+            } => self.open_clipboard_diff_view(use_system_clipboard)?,
+            Dispatch::OpenDiffBufferPrompt => self.open_diff_buffer_prompt()?,
+            Dispatch::PreviewFile(path) => self.preview_file(path)?,
+            Dispatch::CloseBuffer(path) => self.close_buffer(path)?,
+            Dispatch::OpenTerminal { main_panel } => self.layout.open_terminal(
+                &self.working_directory,
+                self.sender.clone(),
+                main_panel,
+            )?,
+            Dispatch::SaveBuffer(path) => self.save_buffer(path)?,
             Dispatch::OpenMoveFilePrompt(path) => self.open_move_file_prompt(path)?,
             Dispatch::OpenAddPathPrompt(path) => self.open_add_path_prompt(path)?,
             Dispatch::DeletePath(path) => self.delete_path(&path)?,
             Dispatch::Null => {
                 // do nothing
             }
-            Dispatch::MoveFile { from, to } => self.move_file(from, to)?,
+            Dispatch::MoveFile { from, to } => {
+                self.move_file(from.clone(), to.clone())?;
+                self.offer_reference_update(&from, &to.try_into()?)?
+            }
+            Dispatch::PreviewReferenceUpdate {
+                reference,
+                replacement,
+            } => self.preview_reference_update(reference, replacement)?,
             Dispatch::AddPath(path) => self.add_path(path)?,
             Dispatch::RefreshFileExplorer => {
                 self.layout.refresh_file_explorer(&self.working_directory)?
@@ -639,16 +1430,36 @@ impl<T: Frontend> App<T> {
             Dispatch::HandleKeyEvent(key_event) => {
                 self.handle_event(Event::Key(key_event))?;
             }
+            #[cfg(test)]
+            Dispatch::HandlePasteEvent(content) => {
+                self.handle_event(Event::Paste(content))?;
+            }
+            #[cfg(test)]
+            Dispatch::HandleImePreeditEvent(preedit) => {
+                self.handle_event(Event::ImePreedit(preedit))?;
+            }
+            #[cfg(test)]
+            Dispatch::HandleImeCommitEvent(text) => {
+                self.handle_event(Event::ImeCommit(text))?;
+            }
             Dispatch::GetRepoGitHunks(diff_mode) => self.get_repo_git_hunks(diff_mode)?,
             Dispatch::SaveAll => self.save_all()?,
             #[cfg(test)]
             Dispatch::TerminalDimensionChanged(dimension) => self.resize(dimension),
             #[cfg(test)]
             Dispatch::SetGlobalTitle(title) => self.set_global_title(title),
-            Dispatch::LspExecuteCommand { command } => {
+            #[cfg(test)]
+            Dispatch::SetBell(bell) => self.set_bell(bell),
+            #[cfg(test)]
+            Dispatch::SetKeymapConfig(keymap_config) => self.set_keymap_config(keymap_config),
+            Dispatch::RingBell => self.ring_bell()?,
+            Dispatch::AnimateScroll => self.animate_scroll(),
+            Dispatch::LspExecuteCommand { command, server } => {
                 if let Some(params) = self.get_request_params() {
-                    self.lsp_manager.send_message(
+                    self.pending_refactor = Some(format!("Command {}", command.command()));
+                    self.lsp_manager.send_message_to(
                         params.path.clone(),
+                        server.as_deref(),
                         FromEditor::WorkspaceExecuteCommand { params, command },
                     )?
                 };
@@ -694,16 +1505,50 @@ impl<T: Frontend> App<T> {
                 })?,
                 Scope::Global => self.global_replace()?,
             },
+            Dispatch::ShowGlobalReplacePreview => self.show_global_replace_preview(),
+            Dispatch::OpenRefactorLog => self.open_scratch_buffer_with_title(
+                self.refactor_log.display(),
+                "[Refactor log]".to_string(),
+            ),
+            Dispatch::ReviewRefactorLog => self.set_quickfix_list_type(
+                ResponseContext::default().set_description("Refactor log"),
+                QuickfixListType::Items(self.refactor_log.quickfix_list_items()),
+            )?,
+            Dispatch::ReloadConfig => {
+                self.set_config(self.config.reload()?)?;
+                self.show_global_info(Info::new(
+                    "Config".to_string(),
+                    "The config is reloaded.".to_string(),
+                ))
+            }
+            #[cfg(test)]
+            Dispatch::SetConfig(config) => self.set_config(config)?,
+            Dispatch::ExcludeCurrentQuickfixListItem => {
+                self.exclude_current_quickfix_list_item()?
+            }
+            Dispatch::ReplaceQuickfixListItems => self.replace_quickfix_list_items()?,
             #[cfg(test)]
             Dispatch::HandleLspNotification(notification) => {
                 self.handle_lsp_notification(notification)?
             }
-            Dispatch::SetTheme(theme) => {
-                let context = std::mem::take(&mut self.context);
-                self.context = context.set_theme(theme.clone());
-            }
+            Dispatch::SetTheme(theme) => self.set_theme(theme.clone()),
+            Dispatch::SetLanguage(language) => self.set_language(language)?,
+            Dispatch::SetIndentation(indentation) => self
+                .current_component()
+                .borrow_mut()
+                .editor_mut()
+                .buffer_mut()
+                .set_indentation(indentation),
             #[cfg(test)]
             Dispatch::HandleKeyEvents(key_events) => self.handle_key_events(key_events)?,
+            #[cfg(test)]
+            Dispatch::HandleQueuedKeyEvents(key_events) => {
+                for key_event in key_events {
+                    self.sender.send(AppMessage::Event(Event::Key(key_event)))?;
+                }
+                let message = self.receiver.recv()?;
+                self.handle_queued_messages(message)?
+            }
             Dispatch::CloseDropdown => self.layout.close_dropdown(),
             Dispatch::CloseEditorInfo => self.layout.close_editor_info(),
             Dispatch::RenderDropdown { render } => {
@@ -722,10 +1567,58 @@ impl<T: Frontend> App<T> {
                 self.open_code_actions_prompt(code_actions)?;
             }
             Dispatch::OtherWindow => self.layout.cycle_window(),
+            Dispatch::ToggleScrollBind => self.toggle_scroll_bind(),
             Dispatch::GoToPreviousFile => self.go_to_previous_file()?,
             Dispatch::GoToNextFile => self.go_to_next_file()?,
             Dispatch::PushPromptHistory { key, line } => self.push_history_prompt(key, line),
             Dispatch::OpenThemePrompt => self.open_theme_prompt()?,
+            Dispatch::OpenSetLanguagePrompt => self.open_set_language_prompt()?,
+            Dispatch::OpenSetIndentationPrompt => self.open_set_indentation_prompt()?,
+            Dispatch::OpenDiagnosticSeverityPrompt => self.open_diagnostic_severity_prompt()?,
+            Dispatch::SetMinimumDiagnosticSeverity(severity) => {
+                self.set_minimum_diagnostic_severity(severity)
+            }
+            Dispatch::MuteDiagnosticAtCursor => self.mute_diagnostic_at_cursor()?,
+            Dispatch::ReloadFile(path) => self.reload_file(&path)?,
+            Dispatch::OpenRecoveryPrompt => self.open_recovery_prompt()?,
+            Dispatch::ReviewWorkspaceEdit(decision) => {
+                self.decide_workspace_edit_review(decision)?
+            }
+            Dispatch::RestoreUnsavedChanges(file) => self.restore_unsaved_changes(file)?,
+            Dispatch::DiscardRecoveryFiles => self.discard_recovery_files()?,
+            #[cfg(test)]
+            Dispatch::SetRecovery(recovery) => self.recovery = Some(recovery),
+            #[cfg(test)]
+            Dispatch::SetSessionStore(session_store) => self.session_store = Some(session_store),
+            #[cfg(test)]
+            Dispatch::SaveSession => self.save_session()?,
+            #[cfg(test)]
+            Dispatch::RestoreSession => self.restore_session()?,
+            #[cfg(test)]
+            Dispatch::Render => self.render()?,
+            #[cfg(test)]
+            Dispatch::PersistUnsavedChanges => self.persist_unsaved_changes()?,
+            #[cfg(test)]
+            Dispatch::HandleFileChangedOnDisk(path) => self.handle_file_changed_on_disk(path)?,
+            Dispatch::OpenUnmuteDiagnosticsPrompt => self.open_unmute_diagnostics_prompt()?,
+            Dispatch::UnmuteDiagnostics(name) => self.unmute_diagnostics(&name),
+            Dispatch::OpenEditMacroPrompt => self.open_edit_macro_prompt()?,
+            Dispatch::EditMacro(name) => self.edit_macro(name),
+            Dispatch::WriteMacro => self.write_macro(None)?,
+            Dispatch::OpenSaveMacroPrompt => self.open_save_macro_prompt()?,
+            Dispatch::SaveMacro(name) => self.write_macro(Some(name))?,
+            Dispatch::OpenRunMacroPrompt => self.open_run_macro_prompt()?,
+            Dispatch::RunMacro {
+                name,
+                repeat,
+                each_selection,
+            } => self.run_macro(&name, repeat, each_selection)?,
+            Dispatch::StartMacroRecording(register) => self.start_macro_recording(register),
+            Dispatch::StopMacroRecording => self.stop_macro_recording()?,
+            Dispatch::SetNamedMark(name) => self.set_named_mark(name)?,
+            Dispatch::GoToNamedMark(name) => self.go_to_named_mark(name)?,
+            Dispatch::DeleteNamedMark(name) => self.delete_named_mark(name)?,
+            Dispatch::OpenNamedMarkPicker => self.open_named_mark_picker()?,
             Dispatch::SetLastNonContiguousSelectionMode(selection_mode) => self
                 .context
                 .set_last_non_contiguous_selection_mode(selection_mode),
@@ -747,19 +1640,62 @@ impl<T: Frontend> App<T> {
     }
 
     fn close_current_window(&mut self) {
+        let path = self.current_component().borrow().path();
+        if let Some(path) = path {
+            self.record_closed_file(path)
+        }
         self.layout.close_current_window()
     }
 
-    fn local_search(&mut self, if_current_not_found: IfCurrentNotFound) -> anyhow::Result<()> {
-        let config = self.context.local_search_config();
-        let search = config.search();
-        if !search.is_empty() {
+    /// Buffers with unsaved changes are not closed, because the changes would be lost.
+    fn close_buffer(&mut self, path: CanonicalizedPath) -> anyhow::Result<()> {
+        if self
+            .layout
+            .get_existing_editor(&path)
+            .is_some_and(|editor| editor.borrow().editor().buffer().dirty())
+        {
+            return Err(anyhow::anyhow!(
+                "{} has unsaved changes, save it before closing it",
+                path.display_relative_to(&self.working_directory)
+                    .unwrap_or_else(|_| path.display_absolute())
+            ));
+        }
+        // The global marks of the buffer would be left at where they were set otherwise
+        self.sync_global_marks()
+            .unwrap_or_else(|error| log::error!("App::close_buffer: {error:?}"));
+        self.layout.close_buffer(&path);
+        self.record_closed_file(path);
+        Ok(())
+    }
+
+    fn record_closed_file(&mut self, path: CanonicalizedPath) {
+        self.recently_closed_files.retain(|closed| closed != &path);
+        self.recently_closed_files.push(path);
+        if self.recently_closed_files.len() > RECENTLY_CLOSED_FILES_LIMIT {
+            self.recently_closed_files.remove(0);
+        }
+    }
+
+    fn save_buffer(&mut self, path: CanonicalizedPath) -> anyhow::Result<()> {
+        if let Some(editor) = self.layout.get_existing_editor(&path) {
+            // The dispatches returned by `Editor::save` are not handled,
+            // because they are meant for the current component, which might not be this editor
+            editor.borrow_mut().editor_mut().save()?;
+            self.handle_dispatch(Dispatch::DocumentDidSave { path })?;
+        }
+        Ok(())
+    }
+
+    fn local_search(&mut self, if_current_not_found: IfCurrentNotFound) -> anyhow::Result<()> {
+        let config = self.context.local_search_config();
+        let search = config.search();
+        if !search.is_empty() {
             self.handle_dispatch_editor_custom(
                 SetSelectionMode(
                     if_current_not_found,
                     SelectionMode::Find {
                         search: Search {
-                            mode: config.mode,
+                            mode: config.mode.smart_case(&search),
                             search,
                         },
                     },
@@ -771,6 +1707,30 @@ impl<T: Frontend> App<T> {
         Ok(())
     }
 
+    /// Highlight the matches of `search` in the editor below the search prompt,
+    /// without updating the local search config.
+    ///
+    /// The local search config is only updated when the search prompt is submitted.
+    fn incremental_search(
+        &mut self,
+        search: String,
+        if_current_not_found: IfCurrentNotFound,
+    ) -> anyhow::Result<()> {
+        let Some(component) = self.layout.current_component_parent() else {
+            return Ok(());
+        };
+        let mode = self.context.local_search_config().mode.smart_case(&search);
+        self.handle_dispatch_editor_custom(
+            SetSelectionMode(
+                if_current_not_found,
+                SelectionMode::Find {
+                    search: Search { mode, search },
+                },
+            ),
+            component,
+        )
+    }
+
     fn resize(&mut self, dimension: Dimension) {
         self.layout
             .set_terminal_dimension(dimension.decrement_height(GLOBAL_TITLE_BAR_HEIGHT));
@@ -791,6 +1751,49 @@ impl<T: Frontend> App<T> {
         )
     }
 
+    fn open_go_to_line_prompt(&mut self) -> anyhow::Result<()> {
+        let component = self.current_component();
+        let (current_line, selection_set, scroll_offset) = {
+            let component = component.borrow();
+            let editor = component.editor();
+            (
+                editor.get_cursor_position()?.line,
+                editor.selection_set.clone(),
+                editor.scroll_offset(),
+            )
+        };
+        self.open_prompt(
+            PromptConfig {
+                title: "Go to line (1-based, or +/- relative)".to_string(),
+                on_enter: DispatchPrompt::GoToLine { current_line },
+                items: vec![],
+                enter_selects_first_matching_item: false,
+                leaves_current_line_empty: true,
+                fire_dispatches_on_change: Some(Dispatches::one(Dispatch::ToEditor(
+                    DispatchEditor::RestoreView {
+                        selection_set,
+                        scroll_offset,
+                    },
+                ))),
+            },
+            PromptHistoryKey::GoToLine,
+            None,
+        )
+    }
+
+    fn preview_go_to_line(&mut self, line: usize) -> anyhow::Result<()> {
+        let Some(component) = self.layout.current_component_parent() else {
+            return Ok(());
+        };
+        self.handle_dispatch_editor_custom(
+            DispatchEditor::GoToLine {
+                line,
+                store_history: false,
+            },
+            component,
+        )
+    }
+
     fn open_rename_prompt(&mut self, current_name: Option<String>) -> anyhow::Result<()> {
         self.open_prompt(
             PromptConfig {
@@ -886,10 +1889,7 @@ impl<T: Frontend> App<T> {
             PromptConfig {
                 title: "Command".to_string(),
                 on_enter: DispatchPrompt::RunCommand,
-                items: crate::command::COMMANDS
-                    .iter()
-                    .flat_map(|command| command.to_dropdown_items())
-                    .collect(),
+                items: self.command_dropdown_items(),
                 enter_selects_first_matching_item: true,
                 leaves_current_line_empty: true,
                 fire_dispatches_on_change: None,
@@ -899,65 +1899,344 @@ impl<T: Frontend> App<T> {
         )
     }
 
+    /// The keybindings are taken from the keymap legends of the normal mode,
+    /// so that the palette executes exactly what their keys do.
+    fn open_command_palette(&mut self) -> anyhow::Result<()> {
+        let keybindings = self
+            .current_component()
+            .borrow()
+            .editor()
+            .normal_mode_keybindings(&self.context);
+        let items = keybindings
+            .into_iter()
+            .map(|(keys, keymap)| {
+                let keys = keys.iter().map(|key| key.display()).join(" ");
+                DropdownItem::new(format!("{} ({keys})", keymap.description()))
+                    .set_group(Some("Keybindings".to_string()))
+                    .set_dispatches(keymap.get_dispatches())
+            })
+            .chain(
+                self.command_dropdown_items()
+                    .into_iter()
+                    .map(|item| item.set_group(Some("Commands".to_string()))),
+            )
+            .collect_vec();
+        self.open_prompt(
+            PromptConfig {
+                title: "Command palette".to_string(),
+                on_enter: DispatchPrompt::Null,
+                items,
+                enter_selects_first_matching_item: true,
+                leaves_current_line_empty: true,
+                fire_dispatches_on_change: None,
+            },
+            PromptHistoryKey::CommandPalette,
+            None,
+        )
+    }
+
     fn open_file_picker(&mut self, kind: FilePickerKind) -> anyhow::Result<()> {
         let working_directory = self.working_directory.clone();
+        let paths = match &kind {
+            // Walking the workspace can take a while, so the files are filled in by a task
+            FilePickerKind::NonGitIgnored => Vec::new(),
+            FilePickerKind::GitStatus(diff_mode) => {
+                git::GitRepo::try_from(&self.working_directory)?
+                    .diff_entries(diff_mode.clone())?
+                    .into_iter()
+                    .map(|entry| entry.new_path().into_path_buf())
+                    .collect_vec()
+            }
+            FilePickerKind::Opened => self
+                .layout
+                .get_opened_files()
+                .into_iter()
+                .map(|path| path.into_path_buf())
+                .chain(
+                    self.recently_closed_files
+                        .iter()
+                        .rev()
+                        .map(|path| path.clone().into_path_buf()),
+                )
+                .collect_vec(),
+        };
         self.open_prompt(
             PromptConfig {
                 title: format!("Open file: {}", kind.display()),
                 on_enter: DispatchPrompt::OpenFile { working_directory },
-                items: {
-                    match kind {
-                        FilePickerKind::NonGitIgnored => {
-                            // Note: we should not use CanonicalizedPath here, as it is resource-intensive
-                            list::WalkBuilderConfig::non_git_ignored_files(
-                                self.working_directory.clone(),
-                            )?
-                        }
-                        FilePickerKind::GitStatus(diff_mode) => {
-                            git::GitRepo::try_from(&self.working_directory)?
-                                .diff_entries(diff_mode)?
-                                .into_iter()
-                                .map(|entry| entry.new_path().into_path_buf())
-                                .collect_vec()
-                        }
-                        FilePickerKind::Opened => self
-                            .layout
-                            .get_opened_files()
-                            .into_iter()
-                            .map(|path| path.into_path_buf())
-                            .collect_vec(),
-                    }
-                    .into_iter()
-                    .map(|path| {
-                        DropdownItem::new({
-                            let name = path
-                                .file_name()
-                                .unwrap_or_default()
-                                .to_string_lossy()
-                                .to_string();
-                            let icon = shared::canonicalized_path::get_path_icon(&path);
-                            format!("{icon} {name}")
-                        })
-                        .set_group(path.parent().map(|parent| {
-                            let relative = parent
-                                .strip_prefix(&self.working_directory)
-                                .map(|path| path.display().to_string())
-                                .unwrap_or_else(|_| parent.display().to_string());
-                            format!("{} {}", shared::icons::get_icon_config().folder, relative,)
-                        }))
-                        .set_dispatches(Dispatches::one(
-                            crate::app::Dispatch::OpenFileFromPathBuf(path),
-                        ))
-                    })
-                    .collect_vec()
-                },
+                items: self.file_picker_items(&kind, paths),
                 enter_selects_first_matching_item: true,
                 leaves_current_line_empty: true,
                 fire_dispatches_on_change: None,
             },
             PromptHistoryKey::OpenFile,
             None,
-        )
+        )?;
+        if kind == FilePickerKind::NonGitIgnored {
+            let component_id = self.layout.focused_component_id();
+            let working_directory = self.working_directory.clone();
+            // The task is cancelled if the picker is closed before the walk finishes
+            self.tasks.spawn(
+                "Index files".to_string(),
+                TaskOrigin::Component(component_id),
+                move || AppMessage::FilePickerFilesFound {
+                    component_id,
+                    // Note: we should not use CanonicalizedPath here, as it is resource-intensive
+                    result: list::WalkBuilderConfig::non_git_ignored_files(working_directory)
+                        .map_err(|error| error.to_string()),
+                },
+            );
+        }
+        Ok(())
+    }
+
+    fn file_picker_items(&self, kind: &FilePickerKind, paths: Vec<PathBuf>) -> Vec<DropdownItem> {
+        let modified_files = self
+            .layout
+            .buffers()
+            .into_iter()
+            .filter(|buffer| buffer.borrow().dirty())
+            .filter_map(|buffer| buffer.borrow().path())
+            .map(|path| path.into_path_buf())
+            .collect_vec();
+        let recently_closed_files = self
+            .recently_closed_files
+            .iter()
+            .map(|path| path.clone().into_path_buf())
+            .collect_vec();
+        paths
+            .into_iter()
+            .map(|path| {
+                let frecency = self.file_frecency.score(&path);
+                DropdownItem::new({
+                    let name = path
+                        .file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                        .to_string();
+                    let icon = shared::canonicalized_path::get_path_icon(&path);
+                    let marker = if modified_files.contains(&path) {
+                        " [+]"
+                    } else if kind == &FilePickerKind::Opened
+                        && recently_closed_files.contains(&path)
+                    {
+                        " (closed)"
+                    } else {
+                        ""
+                    };
+                    format!("{icon} {name}{marker}")
+                })
+                .set_group(path.parent().map(|parent| {
+                    let relative = parent
+                        .strip_prefix(&self.working_directory)
+                        .map(|path| path.display().to_string())
+                        .unwrap_or_else(|_| parent.display().to_string());
+                    format!("{} {}", shared::icons::get_icon_config().folder, relative,)
+                }))
+                // Frequently and recently opened files come first, then shorter paths
+                .set_rank(Some(Box::new([
+                    usize::MAX - frecency,
+                    path.components().count(),
+                    path.as_os_str().len(),
+                ])))
+                .set_group_rank(Some(usize::MAX - frecency))
+                .set_on_focused(Dispatches::one(Dispatch::PreviewFile(path.clone())))
+                .set_actions(
+                    if kind == &FilePickerKind::Opened && !recently_closed_files.contains(&path) {
+                        buffer_list_actions(&path)
+                    } else {
+                        Vec::new()
+                    },
+                )
+                .set_dispatches(Dispatches::one(
+                    crate::app::Dispatch::OpenFileFromPathBuf(path),
+                ))
+            })
+            .collect_vec()
+    }
+
+    fn handle_file_picker_files_found(
+        &mut self,
+        component_id: ComponentId,
+        result: Result<Vec<PathBuf>, String>,
+    ) -> anyhow::Result<()> {
+        let paths = result.map_err(|error| anyhow::anyhow!(error))?;
+        let items = self.file_picker_items(&FilePickerKind::NonGitIgnored, paths);
+        let Some(component) = self.layout.get_component_by_id(component_id) else {
+            return Ok(());
+        };
+        let dispatches = component
+            .borrow_mut()
+            .as_any_mut()
+            .downcast_mut::<Prompt>()
+            .ok_or_else(|| {
+                anyhow::anyhow!("App::handle_file_picker_files_found Failed to downcast")
+            })?
+            .set_items(items);
+        // The dropdown is only rendered for the focused prompt
+        if self.layout.focused_component_id() == component_id {
+            self.handle_dispatches(dispatches)?;
+        }
+        Ok(())
+    }
+
+    /// Show the first lines of the file at `path` beside the dropdown of the file picker.
+    fn preview_file(&mut self, path: PathBuf) -> anyhow::Result<()> {
+        // Opened files are previewed with their unsaved modifications
+        let opened_content = CanonicalizedPath::try_from(path.as_path())
+            .ok()
+            .and_then(|path| self.layout.get_existing_editor(&path))
+            .map(|editor| editor.borrow().editor().content());
+        // Binary or unreadable files are previewed as empty
+        let content = opened_content
+            .unwrap_or_else(|| std::fs::read_to_string(&path).unwrap_or_default())
+            .lines()
+            .take(FILE_PREVIEW_LINES_COUNT)
+            .join("\n");
+        let title = path
+            .strip_prefix(&self.working_directory)
+            .unwrap_or(&path)
+            .display()
+            .to_string();
+        self.layout.show_dropdown_info(Info::new(title, content))
+    }
+
+    /// Unlike `Self::open_yes_no_prompt`, answering no pastes `content` as usual.
+    fn open_dropped_files_prompt(
+        &mut self,
+        paths: Vec<CanonicalizedPath>,
+        content: String,
+    ) -> anyhow::Result<()> {
+        let title = match paths.as_slice() {
+            [path] => format!("Open the pasted file {}?", path.display_absolute()),
+            paths => format!("Open the {} pasted files?", paths.len()),
+        };
+        self.handle_dispatch(Dispatch::ShowKeymapLegend(KeymapLegendConfig {
+            title: "Prompt".to_string(),
+            body: KeymapLegendBody::MultipleSections {
+                sections: [KeymapLegendSection {
+                    title,
+                    keymaps: Keymaps::new(&[
+                        Keymap::new("y", "Yes".to_string(), Dispatch::OpenDroppedFiles(paths)),
+                        Keymap::new(
+                            "n",
+                            "No, paste them".to_string(),
+                            Dispatch::PasteIgnoringFileDrop(content),
+                        ),
+                    ]),
+                }]
+                .to_vec(),
+            },
+        }))
+    }
+
+    fn open_dropped_files(&mut self, paths: Vec<CanonicalizedPath>) -> anyhow::Result<()> {
+        // The last dropped file will be focused
+        for path in paths {
+            self.focus_file(&path)?;
+        }
+        Ok(())
+    }
+
+    /// The roots are opened in a new hierarchy view, whose children are requested right away.
+    fn handle_hierarchy(
+        &mut self,
+        HierarchyContext { kind, parent }: HierarchyContext,
+        items: Vec<HierarchyItem>,
+    ) -> anyhow::Result<()> {
+        match parent {
+            Some(parent) => {
+                self.with_hierarchy_view(|hierarchy_view| {
+                    if hierarchy_view.kind() == kind {
+                        hierarchy_view.set_children(&parent, items)
+                    } else {
+                        Ok(())
+                    }
+                })
+                .transpose()?;
+            }
+            None if items.is_empty() => self.show_global_info(Info::new(
+                kind.display().to_string(),
+                "No symbol is found at the cursor".to_string(),
+            )),
+            None => {
+                let hierarchy_view =
+                    HierarchyView::new(kind, items.clone(), &self.working_directory)?;
+                self.layout
+                    .open_hierarchy_view(Rc::new(RefCell::new(hierarchy_view)));
+                for item in items {
+                    self.handle_dispatch(Dispatch::RequestHierarchyChildren { kind, item })?
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Downcasts the opened hierarchy view, if any.
+    fn with_hierarchy_view<T>(&self, f: impl FnOnce(&mut HierarchyView) -> T) -> Option<T> {
+        let component = self
+            .layout
+            .get_component_by_kind(ComponentKind::HierarchyView)?;
+        let mut component = component.borrow_mut();
+        let hierarchy_view = component.as_any_mut().downcast_mut::<HierarchyView>()?;
+        Some(f(hierarchy_view))
+    }
+
+    fn show_lsp_logs(&mut self) -> anyhow::Result<()> {
+        let logs = self
+            .current_component()
+            .borrow()
+            .editor()
+            .buffer()
+            .path()
+            .and_then(|path| self.lsp_manager.logs(&path))
+            .ok_or_else(|| anyhow::anyhow!("No LSP server is running for the current file"))?;
+        self.open_scratch_buffer_with_title(logs, "[LSP logs]".to_string());
+        Ok(())
+    }
+
+    fn restart_lsp_servers(&mut self) -> anyhow::Result<()> {
+        let path = self.current_component().borrow().editor().buffer().path();
+        let restarted = match path {
+            Some(path) => self.lsp_manager.restart(path)?,
+            None => false,
+        };
+        if !restarted {
+            return Err(anyhow::anyhow!(
+                "No LSP server is running for the current file"
+            ));
+        }
+        self.show_global_info(Info::new(
+            "LSP".to_string(),
+            "The LSP servers are restarted.".to_string(),
+        ));
+        Ok(())
+    }
+
+    fn open_scratch_buffer(&mut self, content: String) {
+        self.open_scratch_buffer_with_title(content, "[Scratch]".to_string())
+    }
+
+    fn open_scratch_buffer_with_title(&mut self, content: String, title: String) {
+        let buffer = Rc::new(RefCell::new(Buffer::new(None, &content)));
+        let mut editor = SuggestiveEditor::from_buffer(buffer, SuggestiveEditorFilter::CurrentWord);
+        editor.set_title(title);
+        self.layout
+            .replace_and_focus_current_suggestive_editor(Rc::new(RefCell::new(editor)))
+    }
+
+    /// Opens and focuses `path`, asking for the passphrase first if it is encrypted.
+    fn focus_file(&mut self, path: &CanonicalizedPath) -> anyhow::Result<()> {
+        let opened = self.layout.get_existing_editor(path).is_some();
+        if Encryption::from_path(path).is_some() && !opened {
+            self.layout.add_and_focus_prompt(
+                ComponentKind::Prompt,
+                Rc::new(RefCell::new(PassphrasePrompt::new(path.clone()))),
+            );
+            return Ok(());
+        }
+        self.open_file(path, OpenFileOption::Focus)?;
+        Ok(())
     }
 
     /// This only opens the file in the background but does not focus it.
@@ -966,21 +2245,80 @@ impl<T: Frontend> App<T> {
         &mut self,
         path: &CanonicalizedPath,
         option: OpenFileOption,
+    ) -> anyhow::Result<Rc<RefCell<SuggestiveEditor>>> {
+        self.open_file_with_passphrase(path, option, None)
+    }
+
+    /// `passphrase` is required if `path` is encrypted, unless it is already opened.
+    fn open_file_with_passphrase(
+        &mut self,
+        path: &CanonicalizedPath,
+        option: OpenFileOption,
+        passphrase: Option<Passphrase>,
     ) -> anyhow::Result<Rc<RefCell<SuggestiveEditor>>> {
         if option.store_history() {
             self.file_path_history.push(path.clone())
         }
+        if option.is_focus() {
+            self.file_frecency.record(path.clone().into_path_buf())
+        }
+        self.recently_closed_files.retain(|closed| closed != path);
         // Check if the file is opened before
         // so that we won't notify the LSP twice
         if let Some(matching_editor) = self.layout.open_file(path, option.is_focus()) {
             return Ok(matching_editor);
         }
 
-        let buffer = Buffer::from_path(path, true)?;
+        let mut buffer = match (Encryption::from_path(path), passphrase) {
+            (Some(encryption), Some(passphrase)) => {
+                Buffer::from_encrypted_path(path, encryption, passphrase)?
+            }
+            (Some(_), None) => {
+                return Err(anyhow::anyhow!(
+                    "The passphrase of the encrypted file {} is required",
+                    path.display_absolute()
+                ))
+            }
+            (None, _) if is_large_file(path, self.config.large_file_threshold()) => {
+                Buffer::from_large_path(path)?
+            }
+            (None, _) => Buffer::from_path(path, true)?,
+        };
         let language = buffer.language();
-        let content = buffer.content();
+        let large_file = buffer.is_large_file();
+        // Large files are highlighted by their visible lines on render instead
+        let content = (!large_file).then(|| buffer.content());
+        buffer.reset_indentation(self.config.indentation(language.as_ref()));
+        buffer.set_insert_final_newline(self.config.insert_final_newline());
+        buffer.set_trim_trailing_whitespace(self.config.trim_trailing_whitespace());
+        buffer.set_diagnostic_filter(self.diagnostic_filter.clone());
+        for (name, mark) in self.global_marks.of_path(path.to_path_buf()) {
+            if let Ok(char_index) = buffer.position_to_char(Position::new(mark.line, mark.column)) {
+                buffer.set_named_mark(name, char_index)
+            }
+        }
+        if let Some(file_watcher) = &self.file_watcher {
+            file_watcher.watch(path)
+        }
+        let decode_warning = buffer.decode_warning();
         let buffer = Rc::new(RefCell::new(buffer));
-        let editor = SuggestiveEditor::from_buffer(buffer, SuggestiveEditorFilter::CurrentWord);
+        let mut editor = SuggestiveEditor::from_buffer(buffer, SuggestiveEditorFilter::CurrentWord);
+        editor.editor_mut().set_scroll_off(self.config.scroll_off());
+        editor.editor_mut().set_scrollbar(self.config.scrollbar());
+        editor.editor_mut().set_minimap(self.config.minimap());
+        editor.editor_mut().set_whitespace(self.config.whitespace());
+        editor
+            .editor_mut()
+            .set_indent_guides(self.config.indent_guides());
+        editor
+            .editor_mut()
+            .set_cursor_line(self.config.cursor_line());
+        editor
+            .editor_mut()
+            .set_cursor_column(self.config.cursor_column());
+        editor
+            .editor_mut()
+            .set_smooth_scroll(self.config.smooth_scroll());
         let component_id = editor.id();
         let component = Rc::new(RefCell::new(editor));
 
@@ -991,10 +2329,14 @@ impl<T: Frontend> App<T> {
                 .replace_and_focus_current_suggestive_editor(component.clone())
         }
 
-        if let Some(language) = language {
+        if let Some(warning) = decode_warning {
+            self.show_global_info(Info::new("Encoding".to_string(), warning));
+        }
+
+        if let Some((language, content)) = language.zip(content) {
             self.request_syntax_highlight(component_id, language, content)?;
         }
-        if self.enable_lsp {
+        if self.enable_lsp && !large_file {
             self.lsp_manager.open_file(path.clone())?;
         }
         Ok(component)
@@ -1005,10 +2347,10 @@ impl<T: Frontend> App<T> {
         notification: LspNotification,
     ) -> anyhow::Result<()> {
         match notification {
-            LspNotification::Hover(hover) => self.show_editor_info(Info::new(
-                "Hover Info".to_string(),
-                hover.contents.join("\n\n"),
-            )),
+            LspNotification::Hover(hover) => {
+                self.show_hover_popup(hover);
+                Ok(())
+            }
             LspNotification::Definition(context, response) => {
                 match response {
                     GotoDefinitionResponse::Single(location) => self.go_to_location(&location)?,
@@ -1037,16 +2379,18 @@ impl<T: Frontend> App<T> {
                     locations.into_iter().map(QuickfixListItem::from).collect(),
                 ),
             ),
-            LspNotification::Completion(_context, completion) => {
+            LspNotification::Completion(server, _context, completion) => {
+                let completion = self.lsp_manager.merge_completion(server, completion);
                 self.handle_dispatch_suggestive_editor(DispatchSuggestiveEditor::Completion(
                     completion,
                 ))?;
 
                 Ok(())
             }
-            LspNotification::Initialized(language) => {
+            LspNotification::Initialized(server, language) => {
                 // Need to notify LSP that the file is opened
                 self.lsp_manager.initialized(
+                    server,
                     language,
                     self.layout
                         .buffers()
@@ -1056,17 +2400,16 @@ impl<T: Frontend> App<T> {
                 );
                 Ok(())
             }
-            LspNotification::PublishDiagnostics(params) => {
-                self.update_diagnostics(
-                    params
-                        .uri
-                        .to_file_path()
-                        .map_err(|err| {
-                            anyhow::anyhow!("Couldn't convert URI to file path: {:?}", err)
-                        })?
-                        .try_into()?,
-                    params.diagnostics,
-                )?;
+            LspNotification::PublishDiagnostics(server, params) => {
+                let path: CanonicalizedPath = params
+                    .uri
+                    .to_file_path()
+                    .map_err(|err| anyhow::anyhow!("Couldn't convert URI to file path: {:?}", err))?
+                    .try_into()?;
+                let diagnostics =
+                    self.lsp_manager
+                        .merge_diagnostics(path.clone(), server, params.diagnostics);
+                self.update_diagnostics(path, diagnostics)?;
                 Ok(())
             }
             LspNotification::PrepareRenameResponse(response) => {
@@ -1095,9 +2438,14 @@ impl<T: Frontend> App<T> {
                 Ok(())
             }
             LspNotification::WorkspaceEdit(workspace_edit) => {
-                self.apply_workspace_edit(workspace_edit)
+                let description = self
+                    .pending_refactor
+                    .take()
+                    .unwrap_or_else(|| "Workspace edit".to_string());
+                self.apply_workspace_edit(workspace_edit, description)
             }
-            LspNotification::CodeAction(code_actions) => {
+            LspNotification::CodeAction(server, code_actions) => {
+                let code_actions = self.lsp_manager.merge_code_actions(server, code_actions);
                 self.handle_dispatch(Dispatch::ReceiveCodeActions(code_actions))?;
                 Ok(())
             }
@@ -1112,6 +2460,21 @@ impl<T: Frontend> App<T> {
             LspNotification::CompletionItemResolve(completion_item) => {
                 self.update_current_completion_item(completion_item.into())
             }
+            LspNotification::Progress(server, language, progress) => {
+                self.lsp_manager.update_progress(server, language, progress);
+                Ok(())
+            }
+            LspNotification::Hierarchy(context, items) => self.handle_hierarchy(context, items),
+            LspNotification::DocumentHighlight(highlights) => {
+                // Outdated if the cursor has moved since the request
+                if self.document_highlight_anchor == Some(self.cursor_anchor()) {
+                    self.current_component()
+                        .borrow_mut()
+                        .editor_mut()
+                        .set_document_highlights(highlights)
+                }
+                Ok(())
+            }
         }
     }
 
@@ -1130,10 +2493,98 @@ impl<T: Frontend> App<T> {
         Ok(())
     }
 
+    /// Reloads the buffer of the file if it has no unsaved changes,
+    /// otherwise asks whether to reload it, keep the unsaved changes,
+    /// or compare them with the file on disk.
+    fn handle_file_changed_on_disk(&mut self, path: CanonicalizedPath) -> anyhow::Result<()> {
+        let Some(component) = self.layout.get_existing_editor(&path) else {
+            if let Some(file_watcher) = &self.file_watcher {
+                file_watcher.unwatch(&path)
+            }
+            return Ok(());
+        };
+        let (changed_on_disk, dirty) = {
+            let component = component.borrow();
+            let buffer = component.editor().buffer();
+            (buffer.changed_on_disk(), buffer.dirty())
+        };
+        // The change is made by saving the buffer itself
+        if !changed_on_disk {
+            return Ok(());
+        }
+        let name = path
+            .display_relative_to(&self.working_directory)
+            .unwrap_or_else(|_| path.display_absolute());
+        if !path.is_file() {
+            self.show_global_info(Info::new(
+                "File deleted".to_string(),
+                format!("{name} is deleted on disk, and will be created again if it is saved"),
+            ));
+            return Ok(());
+        }
+        if !dirty {
+            return self.reload_file(&path);
+        }
+        let items = [
+            (
+                "Reload, discarding the unsaved changes",
+                Dispatches::one(Dispatch::ReloadFile(path.clone())),
+            ),
+            ("Keep the unsaved changes", Dispatches::empty()),
+            (
+                "Diff the unsaved changes against the file on disk",
+                Dispatches::new(
+                    [
+                        Dispatch::OpenFile(path.clone()),
+                        Dispatch::OpenDiffView(DiffBase::FileOnDisk),
+                    ]
+                    .to_vec(),
+                ),
+            ),
+        ];
+        self.open_prompt(
+            PromptConfig {
+                on_enter: DispatchPrompt::Null,
+                items: items
+                    .into_iter()
+                    .map(|(display, dispatches)| {
+                        DropdownItem::new(display.to_string()).set_dispatches(dispatches)
+                    })
+                    .collect_vec(),
+                title: format!("{name} is changed on disk"),
+                enter_selects_first_matching_item: true,
+                leaves_current_line_empty: true,
+                fire_dispatches_on_change: None,
+            },
+            PromptHistoryKey::FileChangedOnDisk,
+            None,
+        )
+    }
+
+    fn reload_file(&mut self, path: &CanonicalizedPath) -> anyhow::Result<()> {
+        let component = self.open_file(path, OpenFileOption::Background)?;
+        let dispatches = component.borrow_mut().editor_mut().reload_from_disk()?;
+        self.handle_dispatches(dispatches)
+    }
+
     pub(crate) fn get_quickfix_list(&self) -> Option<QuickfixList> {
         self.context.quickfix_list_state().as_ref().map(|state| {
+            let title = match state.source {
+                QuickfixListSource::Diagnostic(severity_range) => {
+                    let hidden = self
+                        .hidden_diagnostic_counts(severity_range)
+                        .into_values()
+                        .sum::<usize>();
+                    if hidden > 0 {
+                        format!("{} ({hidden} hidden by the diagnostic filter)", state.title)
+                    } else {
+                        state.title.clone()
+                    }
+                }
+                _ => state.title.clone(),
+            };
             QuickfixList::new(
-                state.title.clone(),
+                title,
                 self.layout.get_quickfix_list_items(&state.source),
                 self.layout.buffers(),
             )
@@ -1221,22 +2672,136 @@ impl<T: Frontend> App<T> {
         }
     }
 
-    fn apply_workspace_edit(&mut self, workspace_edit: WorkspaceEdit) -> Result<(), anyhow::Error> {
-        // TODO: should we wrap this in a transaction so that if one of the edit/operation fails, the whole transaction fails?
-        // Such that it won't leave the workspace in an half-edited messed up state
-        for edit in workspace_edit.edits {
-            let component = self.open_file(&edit.path, OpenFileOption::Background)?;
-            let dispatches = component
-                .borrow_mut()
+    /// The applied edits are recorded in the refactor log as `description`.
+    /// The workspace edits that edit more files than the configured threshold
+    /// are reviewed file by file before they are applied.
+    fn apply_workspace_edit(
+        &mut self,
+        workspace_edit: WorkspaceEdit,
+        description: String,
+    ) -> Result<(), anyhow::Error> {
+        let review = WorkspaceEditReview::new(workspace_edit, description);
+        if review.file_count() > self.config.workspace_edit_review_threshold() {
+            self.workspace_edit_review = Some(review);
+            return self.review_workspace_edit_file();
+        }
+        let (workspace_edit, description) = review.into_parts();
+        self.apply_workspace_edit_without_review(workspace_edit, description)
+    }
+
+    /// Shows the diff of the file under review, or applies the accepted files
+    /// once every file is decided.
+    fn review_workspace_edit_file(&mut self) -> anyhow::Result<()> {
+        let Some(review) = self.workspace_edit_review.as_ref() else {
+            return Ok(());
+        };
+        let Some((index, path)) = review.current().map(|(index, path)| (index, path.clone()))
+        else {
+            let review = self.workspace_edit_review.take();
+            self.workspace_edit_review_prompt = None;
+            self.layout.close_diff_view();
+            if let Some(review) = review {
+                let description = review.description.clone();
+                self.apply_workspace_edit_without_review(review.accepted(), description)?;
+            }
+            return Ok(());
+        };
+        let title = format!("{} ({index}/{})", review.description, review.file_count());
+        let edits = review.edits_of(&path);
+        let old = match self.layout.get_existing_editor(&path) {
+            Some(editor) => editor.borrow().content(),
+            None => path.read()?,
+        };
+        let mut preview = Editor::from_text(None, &old);
+        for edits in edits {
+            let _ = preview.apply_positional_edits(edits)?;
+        }
+        let name = path
+            .display_relative_to(&self.working_directory)
+            .unwrap_or_else(|_| path.display_absolute());
+        let diff_view = DiffView::new(
+            format!("{name} (current)"),
+            &old,
+            format!("{name} (edited)"),
+            &preview.content(),
+        )?;
+        self.layout.open_diff_view(Rc::new(RefCell::new(diff_view)));
+        self.open_prompt(
+            PromptConfig {
+                on_enter: DispatchPrompt::Null,
+                items: ReviewDecision::ALL
+                    .into_iter()
+                    .map(|decision| {
+                        DropdownItem::new(decision.display().to_string()).set_dispatches(
+                            Dispatches::one(Dispatch::ReviewWorkspaceEdit(decision)),
+                        )
+                    })
+                    .collect_vec(),
+                title: format!("{title}: {name}"),
+                enter_selects_first_matching_item: true,
+                leaves_current_line_empty: true,
+                fire_dispatches_on_change: None,
+            },
+            PromptHistoryKey::WorkspaceEditReview,
+            None,
+        )?;
+        self.workspace_edit_review_prompt = Some(self.layout.focused_component_id());
+        Ok(())
+    }
+
+    /// Cancels the workspace edit review whose prompt is closed (e.g. by escape),
+    /// so that its diff view does not linger and none of its files are edited.
+    fn cancel_orphaned_workspace_edit_review(&mut self) {
+        let Some(prompt_id) = self.workspace_edit_review_prompt else {
+            return;
+        };
+        if self.layout.get_component_by_id(prompt_id).is_none() {
+            self.workspace_edit_review = None;
+            self.workspace_edit_review_prompt = None;
+            self.layout.close_diff_view();
+        }
+    }
+
+    fn decide_workspace_edit_review(&mut self, decision: ReviewDecision) -> anyhow::Result<()> {
+        let review = self
+            .workspace_edit_review
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("No workspace edit is being reviewed"))?;
+        review.decide(decision);
+        self.review_workspace_edit_file()
+    }
+
+    fn apply_workspace_edit_without_review(
+        &mut self,
+        workspace_edit: WorkspaceEdit,
+        description: String,
+    ) -> Result<(), anyhow::Error> {
+        // Every file is opened and edited before any of them is saved,
+        // so that a file that cannot be opened or edited leaves no file half-edited on disk.
+        // The buffers that are edited already are left edited (and unsaved), which can be undone.
+        let components = workspace_edit
+            .edits
+            .iter()
+            .map(|edit| self.open_file(&edit.path, OpenFileOption::Background))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        for (edit, component) in workspace_edit.edits.iter().zip(&components) {
+            let dispatches = component
+                .borrow_mut()
                 .editor_mut()
-                .apply_positional_edits(edit.edits)?;
+                .apply_positional_edits(edit.edits.clone())?;
 
             self.handle_dispatches(dispatches)?;
-
+        }
+        for component in components
+            .iter()
+            .unique_by(|component| component.borrow().id())
+        {
             let dispatches = component.borrow_mut().editor_mut().save()?;
 
             self.handle_dispatches(dispatches)?;
         }
+        self.refactor_log
+            .record_workspace_edit(description, &workspace_edit);
         use crate::lsp::workspace_edit::ResourceOperation;
         for operation in workspace_edit.resource_operations {
             match operation {
@@ -1252,43 +2817,58 @@ impl<T: Frontend> App<T> {
         self.layout.show_keymap_legend(keymap_legend_config)
     }
 
-    fn global_replace(&mut self) -> anyhow::Result<()> {
-        let working_directory = self.working_directory.clone();
+    fn global_search_walk_builder_config(&self) -> WalkBuilderConfig {
         let global_search_config = self.context.global_search_config();
-        let walk_builder_config = WalkBuilderConfig {
-            root: working_directory.clone().into(),
+        WalkBuilderConfig {
+            root: self.working_directory.clone().into(),
             include: global_search_config.include_glob(),
             exclude: global_search_config.exclude_glob(),
-        };
+        }
+    }
+
+    fn global_replace(&mut self) -> anyhow::Result<()> {
+        let walk_builder_config = self.global_search_walk_builder_config();
         let config = self.context.global_search_config().local_config();
         let affected_paths = list::grep::replace(walk_builder_config, config.clone())?;
+        self.refactor_log
+            .record_files(replace_description(config), affected_paths.clone());
         self.layout.reload_buffers(affected_paths)
     }
 
-    fn global_search(&mut self) -> anyhow::Result<()> {
-        let working_directory = self.working_directory.clone();
+    /// The global search that is still running is cancelled,
+    /// so that its result does not replace the result of `job`.
+    fn spawn_global_search(
+        &mut self,
+        description: String,
+        job: impl FnOnce() -> AppMessage + Send + 'static,
+    ) {
+        if let Some(id) = self.global_search_task.take() {
+            self.tasks.cancel(id)
+        }
+        self.global_search_task = Some(self.tasks.spawn(description, TaskOrigin::App, job));
+    }
 
-        let global_search_config = self.context.global_search_config();
-        let walk_builder_config = WalkBuilderConfig {
-            root: working_directory.clone().into(),
-            include: global_search_config.include_glob(),
-            exclude: global_search_config.exclude_glob(),
-        };
-        let config = global_search_config.local_config();
+    /// The files are searched in a background task,
+    /// and the quickfix list is set when `AppMessage::GlobalSearchFinished` is received.
+    fn global_search(&mut self) {
+        let config = self.context.global_search_config().local_config().clone();
         if config.search().is_empty() {
-            return Ok(());
+            return;
         }
-        let locations = match config.mode {
-            LocalSearchConfigMode::Regex(regex) => {
-                list::grep::run(&config.search(), walk_builder_config, regex)
-            }
-            LocalSearchConfigMode::AstGrep => {
-                list::ast_grep::run(config.search().clone(), walk_builder_config)
-            }
-            LocalSearchConfigMode::NamingConventionAgnostic => {
-                list::naming_convention_agnostic::run(config.search().clone(), walk_builder_config)
-            }
-        }?;
+        let walk_builder_config = self.global_search_walk_builder_config();
+        self.spawn_global_search(format!("Search `{}`", config.search()), move || {
+            AppMessage::GlobalSearchFinished(
+                global_search_locations(walk_builder_config, &config)
+                    .map_err(|error| error.to_string()),
+            )
+        })
+    }
+
+    fn handle_global_search_finished(
+        &mut self,
+        result: Result<Vec<Location>, String>,
+    ) -> anyhow::Result<()> {
+        let locations = result.map_err(|error| anyhow::anyhow!(error))?;
         self.set_quickfix_list_type(
             ResponseContext::default().set_description("Global search"),
             QuickfixListType::Items(
@@ -1297,7 +2877,93 @@ impl<T: Frontend> App<T> {
                     .map(|location| QuickfixListItem::new(location, None))
                     .collect_vec(),
             ),
-        )?;
+        )
+    }
+
+    /// Show every pending change of the global replace as a quickfix list,
+    /// where the info of each item is the diff of the affected line.
+    ///
+    /// The diffs are computed in a background task,
+    /// and the quickfix list is set when `AppMessage::GlobalReplacePreviewFinished` is received.
+    fn show_global_replace_preview(&mut self) {
+        let config = self.context.global_search_config().local_config().clone();
+        if config.search().is_empty() {
+            return;
+        }
+        let walk_builder_config = self.global_search_walk_builder_config();
+        self.spawn_global_search(
+            format!("Preview replacing `{}`", config.search()),
+            move || {
+                AppMessage::GlobalReplacePreviewFinished(
+                    global_replace_preview_items(walk_builder_config, &config)
+                        .map_err(|error| error.to_string()),
+                )
+            },
+        )
+    }
+
+    fn handle_global_replace_preview_finished(
+        &mut self,
+        result: Result<Vec<QuickfixListItem>, String>,
+    ) -> anyhow::Result<()> {
+        let items = result.map_err(|error| anyhow::anyhow!(error))?;
+        self.set_quickfix_list_type(
+            ResponseContext::default().set_description("Replace preview"),
+            QuickfixListType::Items(items),
+        )
+    }
+
+    /// Toggle whether the current quickfix list item is excluded from
+    /// `Dispatch::ReplaceQuickfixListItems`.
+    /// Excluded items stay listed, but dimmed, so that they can be included again.
+    fn exclude_current_quickfix_list_item(&mut self) -> anyhow::Result<()> {
+        let Some(location) = self
+            .get_quickfix_list()
+            .and_then(|quickfix_list| quickfix_list.current_location())
+        else {
+            return Ok(());
+        };
+        for buffer in self.layout.buffers() {
+            buffer
+                .borrow_mut()
+                .toggle_quickfix_list_item_excluded(&location)
+        }
+        self.goto_quickfix_list_item(Movement::Current(IfCurrentNotFound::LookForward))
+    }
+
+    /// Apply the global replacement to the quickfix list items that are not excluded.
+    /// Each affected buffer is edited as one undoable change and then saved.
+    fn replace_quickfix_list_items(&mut self) -> anyhow::Result<()> {
+        let config = self.context.global_search_config().local_config().clone();
+        let mut affected_paths = Vec::new();
+        for buffer in self.layout.buffers() {
+            let (path, ranges) = {
+                let buffer = buffer.borrow();
+                let Some(path) = buffer.path() else { continue };
+                let ranges = buffer
+                    .quickfix_list_items()
+                    .into_iter()
+                    .filter(|item| !item.excluded())
+                    .map(|item| item.location().range.clone())
+                    .collect_vec();
+                (path, ranges)
+            };
+            if ranges.is_empty() {
+                continue;
+            }
+            let component = self.open_file(&path, OpenFileOption::Background)?;
+            let dispatches = component
+                .borrow_mut()
+                .editor_mut()
+                .replace_ranges_with_pattern(ranges, &config)?;
+            self.handle_dispatches(dispatches)?;
+            let dispatches = component.borrow_mut().editor_mut().save()?;
+            self.handle_dispatches(dispatches)?;
+            affected_paths.push(path);
+        }
+        self.refactor_log
+            .record_files(replace_description(&config), affected_paths);
+        self.layout.clear_quickfix_list_items();
         Ok(())
     }
 
@@ -1309,11 +2975,37 @@ impl<T: Frontend> App<T> {
         self.sender.clone()
     }
 
+    /// The built-in commands, followed by the ones of plugins
+    fn command_dropdown_items(&self) -> Vec<DropdownItem> {
+        let plugin_commands = self
+            .plugins
+            .iter()
+            .flat_map(|plugins| plugins.commands())
+            .map(|command| {
+                DropdownItem::new(command.name.clone())
+                    .set_info(Some(Info::new(
+                        "Description".to_string(),
+                        command.description,
+                    )))
+                    .set_dispatches(Dispatches::one(Dispatch::RunCommand(command.name)))
+            });
+        crate::command::COMMANDS
+            .iter()
+            .flat_map(|command| command.to_dropdown_items())
+            .chain(plugin_commands)
+            .collect()
+    }
+
     fn run_command(&mut self, command: String) -> anyhow::Result<()> {
-        let dispatch = crate::command::find(&command)
-            .map(|cmd| cmd.dispatch())
-            .ok_or_else(|| anyhow::anyhow!("Unknown command: {}", command))?;
-        self.handle_dispatch(dispatch)
+        if let Some(builtin) = crate::command::find(&command) {
+            return self.handle_dispatch(builtin.dispatch());
+        }
+        let actions = match &self.plugins {
+            Some(plugins) => plugins.run_command(&command, self.plugin_buffer())?,
+            None => None,
+        };
+        let actions = actions.ok_or_else(|| anyhow::anyhow!("Unknown command: {}", command))?;
+        self.apply_plugin_actions(actions)
     }
 
     fn save_quit_all(&mut self) -> anyhow::Result<()> {
@@ -1370,6 +3062,51 @@ impl<T: Frontend> App<T> {
         self.layout.remove_suggestive_editor(&from);
         Ok(())
     }
+    /// Offers to update the references to the moved path (see `crate::reference_rename`),
+    /// where the module path is joined by the module separator of the language, if any.
+    fn offer_reference_update(
+        &mut self,
+        from: &CanonicalizedPath,
+        to: &CanonicalizedPath,
+    ) -> anyhow::Result<()> {
+        let separator = language::from_path(to)
+            .and_then(|language| language.module_separator())
+            .unwrap_or("/");
+        let Some((reference, replacement)) =
+            reference_rename::renamed_reference(from.as_ref(), to.as_ref(), separator)
+        else {
+            return Ok(());
+        };
+        self.open_yes_no_prompt(YesNoPrompt {
+            title: format!("Update the references to {reference:?} with {replacement:?}?"),
+            yes: Box::new(Dispatch::PreviewReferenceUpdate {
+                reference,
+                replacement,
+            }),
+        })
+    }
+
+    fn preview_reference_update(
+        &mut self,
+        reference: String,
+        replacement: String,
+    ) -> anyhow::Result<()> {
+        for update in [
+            LocalSearchConfigUpdate::Mode(LocalSearchConfigMode::Regex(RegexConfig {
+                escaped: true,
+                case_sensitive: true,
+                match_whole_word: true,
+            })),
+            LocalSearchConfigUpdate::Search(reference),
+            LocalSearchConfigUpdate::Replacement(replacement),
+        ] {
+            self.context
+                .update_local_search_config(update, Scope::Global)
+        }
+        self.show_global_replace_preview();
+        Ok(())
+    }
+
     fn add_path_parent(&self, path: &Path) -> anyhow::Result<()> {
         if let Some(new_dir) = path.parent() {
             std::fs::create_dir_all(new_dir)?;
@@ -1405,6 +3142,7 @@ impl<T: Frontend> App<T> {
                 component_id,
                 language,
                 source_code: content,
+                byte_offset: 0,
             })?;
         }
         Ok(())
@@ -1443,11 +3181,75 @@ impl<T: Frontend> App<T> {
         let dispatches = component
             .borrow_mut()
             .handle_dispatch_editor(&mut self.context, dispatch_editor)?;
+        self.sync_scroll_bound_windows();
 
         self.handle_dispatches(dispatches)?;
         Ok(())
     }
 
+    fn toggle_scroll_bind(&mut self) {
+        let component = self.current_component();
+        let id = component.borrow().id();
+        if let Some(index) = self
+            .scroll_bound_windows
+            .iter()
+            .position(|(bound_id, _)| bound_id == &id)
+        {
+            self.scroll_bound_windows.remove(index);
+        } else {
+            let scroll_offset = component.borrow().editor().scroll_offset();
+            self.scroll_bound_windows.push((id, scroll_offset));
+        }
+    }
+
+    /// Scrolls the other bound windows by as many lines as a bound window has been scrolled,
+    /// so that the offsets between the windows at the time they were bound are kept.
+    ///
+    /// If several windows have been scrolled, the focused window takes precedence.
+    fn sync_scroll_bound_windows(&mut self) {
+        if self.scroll_bound_windows.is_empty() {
+            return;
+        }
+        let components = self
+            .components()
+            .into_iter()
+            .map(|component| component.component())
+            .collect_vec();
+        let find = |id: &ComponentId| {
+            components
+                .iter()
+                .find(|component| component.borrow().id() == *id)
+        };
+        // Closed windows are unbound
+        self.scroll_bound_windows
+            .retain(|(id, _)| find(id).is_some());
+        let focused_component_id = self.layout.focused_component_id();
+        let Some((source, delta)) = self
+            .scroll_bound_windows
+            .iter()
+            .filter_map(|(id, last_scroll_offset)| {
+                let scroll_offset = find(id)?.borrow().editor().scroll_offset();
+                (scroll_offset != *last_scroll_offset)
+                    .then(|| (*id, scroll_offset as i32 - *last_scroll_offset as i32))
+            })
+            .sorted_by_key(|(id, _)| id != &focused_component_id)
+            .next()
+        else {
+            return;
+        };
+        for (id, last_scroll_offset) in self.scroll_bound_windows.iter_mut() {
+            let Some(component) = find(id) else { continue };
+            let mut component = component.borrow_mut();
+            let editor = component.editor_mut();
+            if *id != source {
+                let max = editor.buffer().len_lines().saturating_sub(1) as i32;
+                let scroll_offset = (editor.scroll_offset() as i32 + delta).clamp(0, max);
+                editor.set_scroll_offset(scroll_offset as u16);
+            }
+            *last_scroll_offset = editor.scroll_offset();
+        }
+    }
+
     fn get_repo_git_hunks(&mut self, diff_mode: git::DiffMode) -> anyhow::Result<()> {
         let working_directory = self.working_directory.clone();
         let repo = git::GitRepo::try_from(&working_directory)?;
@@ -1494,100 +3296,529 @@ impl<T: Frontend> App<T> {
         self.syntax_highlight_request_sender = Some(sender);
     }
 
-    #[cfg(test)]
-    pub(crate) fn get_current_file_path(&self) -> Option<CanonicalizedPath> {
-        self.current_component().borrow().path()
+    pub(crate) fn set_theme(&mut self, theme: crate::themes::Theme) {
+        let context = std::mem::take(&mut self.context);
+        self.context = context.set_theme(theme);
     }
 
-    fn set_global_mode(&mut self, mode: Option<GlobalMode>) {
-        self.context.set_mode(mode);
+    pub(crate) fn set_bell(&mut self, bell: Bell) {
+        self.bell = bell
     }
 
-    #[cfg(test)]
-    pub(crate) fn context(&self) -> &Context {
-        &self.context
+    pub(crate) fn set_macros(&mut self, macros: Macros) {
+        self.macros = macros
     }
 
-    fn update_local_search_config(
-        &mut self,
-        update: LocalSearchConfigUpdate,
-        scope: Scope,
-        show_legend: bool,
-        if_current_not_found: IfCurrentNotFound,
-    ) -> Result<(), anyhow::Error> {
-        self.context.update_local_search_config(update, scope);
-        match scope {
-            Scope::Local => self.local_search(if_current_not_found)?,
-            Scope::Global => {
-                self.global_search()?;
-            }
+    pub(crate) fn set_global_marks(&mut self, global_marks: GlobalMarks) {
+        self.global_marks = global_marks
+    }
+
+    pub(crate) fn set_recovery(&mut self, recovery: Recovery) {
+        self.recovery = Some(recovery)
+    }
+
+    pub(crate) fn set_session_store(&mut self, session_store: SessionStore) {
+        self.session_store = Some(session_store)
+    }
+
+    fn session(&mut self) -> anyhow::Result<Session> {
+        let files = self
+            .layout
+            .get_opened_files()
+            .into_iter()
+            .filter_map(|path| {
+                let editor = self.layout.get_existing_editor(&path)?;
+                let editor = editor.borrow();
+                let editor = editor.editor();
+                if editor.buffer().is_encrypted() {
+                    return None;
+                }
+                Some(editor.get_cursor_position().map(|position| SessionFile {
+                    path: path.clone().into_path_buf(),
+                    line: position.line,
+                    column: position.column,
+                    scroll_offset: editor.scroll_offset(),
+                }))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let current_file = self
+            .layout
+            .get_component_by_kind(ComponentKind::SuggestiveEditor)
+            .and_then(|component| component.borrow().path())
+            .filter(|path| files.iter().any(|file| &file.path == path.to_path_buf()))
+            .map(|path| path.into_path_buf());
+        Ok(Session {
+            files,
+            current_file,
+            search_history: self
+                .context
+                .get_prompt_history(PromptHistoryKey::Search(Scope::Local), None),
+            global_search_history: self
+                .context
+                .get_prompt_history(PromptHistoryKey::Search(Scope::Global), None),
+        })
+    }
+
+    fn save_session(&mut self) -> anyhow::Result<()> {
+        if self.session_store.is_none() {
+            return Ok(());
+        }
+        let session = self.session()?;
+        if let Some(session_store) = &self.session_store {
+            session_store.save(self.working_directory.as_ref(), &session)?;
         }
+        Ok(())
+    }
 
-        if show_legend {
-            self.show_search_config(scope, if_current_not_found);
+    /// The files that no longer exist are skipped, and the cursors that are out of range
+    /// (e.g. the file is truncated by another program) are left at the start of the file.
+    fn restore_session(&mut self) -> anyhow::Result<()> {
+        let Some(session) = self
+            .session_store
+            .as_ref()
+            .map(|session_store| session_store.load(self.working_directory.as_ref()))
+            .transpose()?
+            .flatten()
+        else {
+            return Ok(());
+        };
+        for line in session.search_history {
+            self.context
+                .push_history_prompt(PromptHistoryKey::Search(Scope::Local), line)
+        }
+        for line in session.global_search_history {
+            self.context
+                .push_history_prompt(PromptHistoryKey::Search(Scope::Global), line)
+        }
+        for file in session.files {
+            let Ok(path) = CanonicalizedPath::try_from(file.path) else {
+                continue;
+            };
+            let editor = self.open_file(&path, OpenFileOption::Background)?;
+            let dispatches = {
+                let mut editor = editor.borrow_mut();
+                let editor = editor.editor_mut();
+                let position = Position::new(file.line, file.column);
+                let dispatches = editor
+                    .set_position_range(position..position)
+                    .unwrap_or_default();
+                editor.set_scroll_offset(file.scroll_offset);
+                dispatches
+            };
+            self.handle_dispatches(dispatches)?;
+        }
+        if let Some(path) = session
+            .current_file
+            .and_then(|path| CanonicalizedPath::try_from(path).ok())
+        {
+            self.open_file(&path, OpenFileOption::Focus)?;
         }
         Ok(())
     }
 
-    fn update_global_search_config(
-        &mut self,
-        update: GlobalSearchConfigUpdate,
-        if_current_not_found: IfCurrentNotFound,
-    ) -> anyhow::Result<()> {
-        self.context.update_global_search_config(update)?;
-        self.global_search()?;
-        self.show_search_config(Scope::Global, if_current_not_found);
+    /// Persists the unsaved changes of the buffers into their recovery files,
+    /// and removes the recovery files of the buffers that are saved or closed since.
+    fn persist_unsaved_changes(&mut self) -> anyhow::Result<()> {
+        let Some(recovery) = self.recovery.as_mut() else {
+            return Ok(());
+        };
+        let unsaved = self
+            .layout
+            .buffers()
+            .into_iter()
+            .filter_map(|buffer| {
+                let buffer = buffer.borrow();
+                let path = buffer.path()?;
+                (buffer.dirty() && !buffer.is_encrypted()).then(|| (path, buffer.content()))
+            })
+            .collect_vec();
+        for path in recovery.persisted_paths() {
+            if !unsaved.iter().any(|(unsaved, _)| unsaved == &path) {
+                recovery.remove(path.as_ref())?;
+            }
+        }
+        for (path, content) in unsaved {
+            recovery.persist(&path, &content)?;
+        }
         Ok(())
     }
 
-    fn open_set_global_search_filter_glob_prompt(
-        &mut self,
-        filter_glob: GlobalSearchFilterGlob,
-        if_current_not_found: IfCurrentNotFound,
-    ) -> anyhow::Result<()> {
+    fn recovery_files(&self) -> anyhow::Result<Vec<RecoveryFile>> {
+        match &self.recovery {
+            Some(recovery) => recovery.leftovers(),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    fn has_recovery_files(&self) -> bool {
+        self.recovery_files()
+            .is_ok_and(|recovery_files| !recovery_files.is_empty())
+    }
+
+    fn open_recovery_prompt(&mut self) -> anyhow::Result<()> {
+        let recovery_files = self.recovery_files()?;
+        if recovery_files.is_empty() {
+            return Err(anyhow::anyhow!(
+                "There are no unsaved changes to be restored"
+            ));
+        }
+        let items = recovery_files
+            .into_iter()
+            .map(|file| {
+                let display = CanonicalizedPath::try_from(file.path.as_path())
+                    .ok()
+                    .and_then(|path| path.display_relative_to(&self.working_directory).ok())
+                    .unwrap_or_else(|| file.path.display().to_string());
+                DropdownItem::new(format!("Restore {display}"))
+                    .set_dispatches(Dispatches::one(Dispatch::RestoreUnsavedChanges(file)))
+            })
+            .chain([DropdownItem::new("Discard all".to_string())
+                .set_dispatches(Dispatches::one(Dispatch::DiscardRecoveryFiles))])
+            .collect_vec();
         self.open_prompt(
             PromptConfig {
-                title: format!("Set global search {:?} files glob", filter_glob),
-                on_enter: DispatchPrompt::GlobalSearchConfigSetGlob {
-                    filter_glob,
-                    if_current_not_found,
-                },
-                items: Vec::new(),
-                enter_selects_first_matching_item: false,
-                leaves_current_line_empty: false,
+                on_enter: DispatchPrompt::Null,
+                items,
+                title: "Unsaved changes of a previous session".to_string(),
+                enter_selects_first_matching_item: true,
+                leaves_current_line_empty: true,
                 fire_dispatches_on_change: None,
             },
-            PromptHistoryKey::FilterGlob(filter_glob),
+            PromptHistoryKey::Recovery,
             None,
         )
     }
 
-    fn show_search_config(&mut self, scope: Scope, if_current_not_found: IfCurrentNotFound) {
-        fn show_checkbox(title: &str, checked: bool) -> String {
-            format!("[{}] {title}", if checked { "X" } else { " " })
+    /// The restored changes are left unsaved, so that they can be reviewed
+    /// (e.g. by diffing them against the file on disk) or undone before saving.
+    fn restore_unsaved_changes(&mut self, file: RecoveryFile) -> anyhow::Result<()> {
+        let path = CanonicalizedPath::try_from(file.path.as_path()).map_err(|_| {
+            anyhow::anyhow!(
+                "Unable to restore the unsaved changes of {}, because it no longer exists",
+                file.path.display()
+            )
+        })?;
+        let component = self.open_file(&path, OpenFileOption::Focus)?;
+        let dispatches = component
+            .borrow_mut()
+            .editor_mut()
+            .restore_unsaved_changes(&file.content)?;
+        if let Some(recovery) = self.recovery.as_mut() {
+            recovery.remove(&file.path)?;
         }
-        let global_search_confing = match scope {
-            Scope::Local => None,
-            Scope::Global => Some(self.context.global_search_config()),
-        };
-        let local_search_config = global_search_confing
-            .map(|config| config.local_config())
-            .unwrap_or_else(|| self.context.local_search_config());
-        let update_keymap =
-            |key: &'static str, description: String, update: LocalSearchConfigUpdate| -> Keymap {
-                Keymap::new(
-                    key,
-                    description,
-                    Dispatch::UpdateLocalSearchConfig {
-                        update,
-                        scope,
-                        show_config_after_enter: scope == Scope::Global,
-                        if_current_not_found: IfCurrentNotFound::LookForward,
-                    },
-                )
-            };
-        let update_mode_keymap = |key: &'static str,
-                                  name: String,
+        self.handle_dispatches(dispatches)
+    }
+
+    fn discard_recovery_files(&mut self) -> anyhow::Result<()> {
+        let recovery_files = self.recovery_files()?;
+        if let Some(recovery) = self.recovery.as_mut() {
+            for file in recovery_files {
+                recovery.remove(&file.path)?
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies the config to the opened buffers, and to the buffers opened afterwards,
+    /// except for the indentation that is detected or overridden by the EditorConfig.
+    pub(crate) fn set_config(&mut self, config: Config) -> anyhow::Result<()> {
+        if let Some(theme) = config.theme() {
+            self.set_theme(crate::themes::from_name(theme)?);
+        }
+        self.context.set_hard_wrap_width(config.hard_wrap_width());
+        self.context.set_spell_checker(
+            config
+                .spell_check_dictionary()
+                .map(|dictionary| {
+                    SpellChecker::load(&dictionary, SpellChecker::user_dictionary_path())
+                })
+                .transpose()?,
+        );
+        for editor in self.layout.suggestive_editors() {
+            let mut editor = editor.borrow_mut();
+            let editor = editor.editor_mut();
+            editor.set_scroll_off(config.scroll_off());
+            editor.set_scrollbar(config.scrollbar());
+            editor.set_minimap(config.minimap());
+            editor.set_whitespace(config.whitespace());
+            editor.set_indent_guides(config.indent_guides());
+            editor.set_cursor_line(config.cursor_line());
+            editor.set_cursor_column(config.cursor_column());
+            editor.set_smooth_scroll(config.smooth_scroll());
+            let mut buffer = editor.buffer_mut();
+            let indentation = config.indentation(buffer.language().as_ref());
+            buffer.reset_indentation(indentation);
+            buffer.set_insert_final_newline(config.insert_final_newline());
+            buffer.set_trim_trailing_whitespace(config.trim_trailing_whitespace());
+        }
+        self.set_diagnostic_filter(config.diagnostic_filter());
+        self.lsp_manager.set_config(config.clone());
+        self.config = config;
+        Ok(())
+    }
+
+    fn set_diagnostic_filter(&mut self, diagnostic_filter: DiagnosticFilter) {
+        for buffer in self.layout.buffers() {
+            buffer
+                .borrow_mut()
+                .set_diagnostic_filter(diagnostic_filter.clone())
+        }
+        self.diagnostic_filter = diagnostic_filter
+    }
+
+    fn set_minimum_diagnostic_severity(&mut self, minimum_severity: Severity) {
+        self.set_diagnostic_filter(DiagnosticFilter {
+            minimum_severity,
+            ..self.diagnostic_filter.clone()
+        })
+    }
+
+    /// Mutes the code or the source of the diagnostic under the cursor.
+    fn mute_diagnostic_at_cursor(&mut self) -> anyhow::Result<()> {
+        let diagnostic = {
+            let component = self.current_component();
+            let component = component.borrow();
+            let editor = component.editor();
+            let cursor_char_index = editor.get_cursor_char_index();
+            editor
+                .buffer()
+                .diagnostics_at(cursor_char_index)
+                .into_iter()
+                .find(|diagnostic| self.diagnostic_filter.shows(diagnostic))
+                .ok_or_else(|| anyhow::anyhow!("There is no diagnostic under the cursor"))?
+        };
+        let mut diagnostic_filter = self.diagnostic_filter.clone();
+        let name = diagnostic_filter.mute(&diagnostic)?;
+        self.set_diagnostic_filter(diagnostic_filter);
+        self.show_global_info(Info::new(
+            "Diagnostics".to_string(),
+            format!("Muted {name:?}, which can be unmuted with the `unmute-diagnostics` command"),
+        ));
+        Ok(())
+    }
+
+    fn unmute_diagnostics(&mut self, name: &str) {
+        let mut diagnostic_filter = self.diagnostic_filter.clone();
+        diagnostic_filter.unmute(name);
+        self.set_diagnostic_filter(diagnostic_filter)
+    }
+
+    /// The number of the diagnostics in `severity_range` of the opened buffers
+    /// that are hidden by the diagnostic filter, for each muted entry or `None` for those
+    /// that are less severe than the minimum severity.
+    fn hidden_diagnostic_counts(
+        &self,
+        severity_range: DiagnosticSeverityRange,
+    ) -> HashMap<Option<String>, usize> {
+        let mut counts = HashMap::new();
+        for buffer in self.layout.buffers() {
+            let buffer = buffer.borrow();
+            for diagnostic in buffer
+                .hidden_diagnostics()
+                .filter(|diagnostic| severity_range.contains(diagnostic.severity))
+            {
+                let muting = self
+                    .diagnostic_filter
+                    .muting(diagnostic)
+                    .map(str::to_string);
+                *counts.entry(muting).or_default() += 1;
+            }
+        }
+        counts
+    }
+
+    pub(crate) fn set_keymap_config(&mut self, keymap_config: KeymapConfig) {
+        self.keymap_config = keymap_config
+    }
+
+    pub(crate) fn set_wrap_selection_on_type(&mut self, wrap_selection_on_type: bool) {
+        self.context
+            .set_wrap_selection_on_type(wrap_selection_on_type)
+    }
+
+    pub(crate) fn set_copy_to_primary_selection(&mut self, copy_to_primary_selection: bool) {
+        self.context
+            .set_copy_to_primary_selection(copy_to_primary_selection)
+    }
+
+    fn ring_bell(&mut self) -> anyhow::Result<()> {
+        match self.bell {
+            Bell::None => {}
+            Bell::Visual => {
+                if !self.is_bell_flashing {
+                    self.is_bell_flashing = true;
+                    self.timer.schedule(
+                        TimerKey::BellFlash,
+                        BELL_FLASH_DURATION,
+                        AppMessage::BellFlashFinished,
+                    );
+                }
+            }
+            Bell::Audible => self.frontend.lock().unwrap().ring_bell()?,
+        }
+        Ok(())
+    }
+
+    /// Schedules the next tick of the scroll animations.
+    /// The animations share the ticks, which are scheduled again until every animation is finished,
+    /// because the frames of animations are determined by their elapsed time.
+    fn animate_scroll(&self) {
+        self.timer.schedule(
+            TimerKey::ScrollAnimation,
+            SMOOTH_SCROLL_FRAME_INTERVAL,
+            AppMessage::ScrollAnimationTicked,
+        )
+    }
+
+    #[cfg(test)]
+    pub(crate) fn is_bell_flashing(&self) -> bool {
+        self.is_bell_flashing
+    }
+
+    pub(crate) fn set_screen_reader(&mut self, screen_reader: Option<ScreenReader>) {
+        self.screen_reader = screen_reader
+    }
+
+    fn announce_to_screen_reader(&mut self) -> anyhow::Result<()> {
+        if self.screen_reader.is_none() {
+            return Ok(());
+        }
+        let state = self.screen_reader_state()?;
+        if let Some(screen_reader) = self.screen_reader.as_mut() {
+            screen_reader.update(state)?
+        }
+        Ok(())
+    }
+
+    fn screen_reader_state(&self) -> anyhow::Result<ScreenReaderState> {
+        let component = self.current_component();
+        let component = component.borrow();
+        let editor = component.editor();
+        let buffer = editor.buffer();
+        let cursor = editor.get_cursor_char_index();
+        let line = buffer.char_to_line(cursor)?;
+        let diagnostic = buffer
+            .diagnostics()
+            .into_iter()
+            .find(|diagnostic| diagnostic.range.contains(&cursor))
+            .map(|diagnostic| {
+                let severity = match diagnostic.severity {
+                    Some(lsp_types::DiagnosticSeverity::ERROR) => "Error",
+                    Some(lsp_types::DiagnosticSeverity::WARNING) => "Warning",
+                    Some(lsp_types::DiagnosticSeverity::INFORMATION) => "Information",
+                    Some(lsp_types::DiagnosticSeverity::HINT) => "Hint",
+                    _ => "Diagnostic",
+                };
+                format!("{severity}: {}", diagnostic.message)
+            });
+        Ok(ScreenReaderState {
+            mode: self
+                .context
+                .mode()
+                .map(|mode| mode.display())
+                .unwrap_or_else(|| editor.display_mode()),
+            title: component.title(&self.context),
+            line,
+            line_content: buffer
+                .get_line_by_line_index(line)
+                .map(|line| line.to_string())
+                .unwrap_or_default(),
+            diagnostic,
+        })
+    }
+
+    #[cfg(test)]
+    pub(crate) fn get_current_file_path(&self) -> Option<CanonicalizedPath> {
+        self.current_component().borrow().path()
+    }
+
+    fn set_global_mode(&mut self, mode: Option<GlobalMode>) {
+        self.context.set_mode(mode);
+    }
+
+    #[cfg(test)]
+    pub(crate) fn context(&self) -> &Context {
+        &self.context
+    }
+
+    fn update_local_search_config(
+        &mut self,
+        update: LocalSearchConfigUpdate,
+        scope: Scope,
+        show_legend: bool,
+        if_current_not_found: IfCurrentNotFound,
+    ) -> Result<(), anyhow::Error> {
+        self.context.update_local_search_config(update, scope);
+        match scope {
+            Scope::Local => self.local_search(if_current_not_found)?,
+            Scope::Global => self.global_search(),
+        }
+
+        if show_legend {
+            self.show_search_config(scope, if_current_not_found);
+        }
+        Ok(())
+    }
+
+    fn update_global_search_config(
+        &mut self,
+        update: GlobalSearchConfigUpdate,
+        if_current_not_found: IfCurrentNotFound,
+    ) -> anyhow::Result<()> {
+        self.context.update_global_search_config(update)?;
+        self.global_search();
+        self.show_search_config(Scope::Global, if_current_not_found);
+        Ok(())
+    }
+
+    fn open_set_global_search_filter_glob_prompt(
+        &mut self,
+        filter_glob: GlobalSearchFilterGlob,
+        if_current_not_found: IfCurrentNotFound,
+    ) -> anyhow::Result<()> {
+        self.open_prompt(
+            PromptConfig {
+                title: format!("Set global search {:?} files glob", filter_glob),
+                on_enter: DispatchPrompt::GlobalSearchConfigSetGlob {
+                    filter_glob,
+                    if_current_not_found,
+                },
+                items: Vec::new(),
+                enter_selects_first_matching_item: false,
+                leaves_current_line_empty: false,
+                fire_dispatches_on_change: None,
+            },
+            PromptHistoryKey::FilterGlob(filter_glob),
+            None,
+        )
+    }
+
+    fn show_search_config(&mut self, scope: Scope, if_current_not_found: IfCurrentNotFound) {
+        fn show_checkbox(title: &str, checked: bool) -> String {
+            format!("[{}] {title}", if checked { "X" } else { " " })
+        }
+        let global_search_confing = match scope {
+            Scope::Local => None,
+            Scope::Global => Some(self.context.global_search_config()),
+        };
+        let local_search_config = global_search_confing
+            .map(|config| config.local_config())
+            .unwrap_or_else(|| self.context.local_search_config());
+        let update_keymap =
+            |key: &'static str, description: String, update: LocalSearchConfigUpdate| -> Keymap {
+                Keymap::new(
+                    key,
+                    description,
+                    Dispatch::UpdateLocalSearchConfig {
+                        update,
+                        scope,
+                        show_config_after_enter: scope == Scope::Global,
+                        if_current_not_found: IfCurrentNotFound::LookForward,
+                    },
+                )
+            };
+        let update_mode_keymap = |key: &'static str,
+                                  name: String,
                                   mode: LocalSearchConfigMode,
                                   checked: bool|
          -> Keymap {
@@ -1754,11 +3985,34 @@ impl<T: Frontend> App<T> {
                 }))
                 .chain(Some(KeymapLegendSection {
                     title: "Actions".to_string(),
-                    keymaps: Keymaps::new(&[Keymap::new(
-                        "R",
-                        "Replace all".to_string(),
-                        Dispatch::Replace { scope },
-                    )]),
+                    keymaps: Keymaps::new(
+                        &[Keymap::new(
+                            "R",
+                            "Replace all".to_string(),
+                            Dispatch::Replace { scope },
+                        )]
+                        .into_iter()
+                        .chain(
+                            global_search_confing
+                                .map(|_| {
+                                    [
+                                        Keymap::new(
+                                            "p",
+                                            "Preview replace".to_string(),
+                                            Dispatch::ShowGlobalReplacePreview,
+                                        ),
+                                        Keymap::new(
+                                            "P",
+                                            "Replace previewed items".to_string(),
+                                            Dispatch::ReplaceQuickfixListItems,
+                                        ),
+                                    ]
+                                    .to_vec()
+                                })
+                                .unwrap_or_default(),
+                        )
+                        .collect_vec(),
+                    ),
                 }))
                 .collect(),
             },
@@ -1971,115 +4225,1873 @@ impl<T: Frontend> App<T> {
                 leaves_current_line_empty: true,
                 fire_dispatches_on_change: None,
             },
-            PromptHistoryKey::CodeAction,
-            None,
-        )?;
-        Ok(())
+            PromptHistoryKey::CodeAction,
+            None,
+        )?;
+        Ok(())
+    }
+
+    fn close_current_window_and_focus_parent(&mut self) {
+        self.layout.close_current_window_and_focus_parent()
+    }
+
+    #[cfg(test)]
+    pub(crate) fn opened_files_count(&self) -> usize {
+        self.layout.get_opened_files().len()
+    }
+
+    #[cfg(test)]
+    pub(crate) fn quickfix_list_info(&self) -> Option<String> {
+        self.layout.quickfix_list_info()
+    }
+
+    #[cfg(test)]
+    pub(crate) fn get_component_by_kind(
+        &self,
+        kind: ComponentKind,
+    ) -> Option<Rc<RefCell<dyn Component>>> {
+        self.layout.get_component_by_kind(kind)
+    }
+
+    fn hide_editor_info(&mut self) {
+        self.layout.hide_editor_info()
+    }
+
+    #[cfg(test)]
+    pub(crate) fn components_order(&self) -> Vec<ComponentKind> {
+        self.layout
+            .components()
+            .into_iter()
+            .map(|c| c.kind())
+            .collect_vec()
+    }
+
+    fn handle_signature_help(
+        &mut self,
+        signature_help: Option<crate::lsp::signature_help::SignatureHelp>,
+    ) -> anyhow::Result<()> {
+        if let Some(info) = signature_help.and_then(|s| s.into_info()) {
+            if self.current_component().borrow().editor().mode
+                == crate::components::editor::Mode::Insert
+            {
+                self.show_editor_info(info)?;
+            }
+        } else {
+            self.hide_editor_info()
+        }
+        Ok(())
+    }
+
+    fn push_history_prompt(&mut self, key: PromptHistoryKey, line: String) {
+        self.context.push_history_prompt(key, line)
+    }
+
+    fn open_theme_prompt(&mut self) -> anyhow::Result<()> {
+        self.open_prompt(
+            PromptConfig {
+                on_enter: DispatchPrompt::Null,
+                items: crate::themes::themes()?
+                    .into_iter()
+                    .map(|theme| {
+                        DropdownItem::new(theme.name.to_string())
+                            .set_dispatches(Dispatches::one(Dispatch::SetTheme(theme.clone())))
+                    })
+                    .collect_vec(),
+                title: "Theme".to_string(),
+                enter_selects_first_matching_item: true,
+                leaves_current_line_empty: true,
+                fire_dispatches_on_change: Some(Dispatches::one(Dispatch::SetTheme(
+                    self.context.theme().clone(),
+                ))),
+            },
+            PromptHistoryKey::Theme,
+            None,
+        )
+    }
+
+    fn open_set_language_prompt(&mut self) -> anyhow::Result<()> {
+        self.open_prompt(
+            PromptConfig {
+                on_enter: DispatchPrompt::Null,
+                items: language::all()
+                    .into_iter()
+                    .filter_map(|language| {
+                        Some(
+                            DropdownItem::new(language.name()?.to_string())
+                                .set_dispatches(Dispatches::one(Dispatch::SetLanguage(language))),
+                        )
+                    })
+                    .collect_vec(),
+                title: "Language".to_string(),
+                enter_selects_first_matching_item: true,
+                leaves_current_line_empty: true,
+                fire_dispatches_on_change: None,
+            },
+            PromptHistoryKey::Language,
+            None,
+        )
+    }
+
+    fn open_set_indentation_prompt(&mut self) -> anyhow::Result<()> {
+        let tabs = Indentation {
+            expand_tab: false,
+            ..Indentation::default()
+        };
+        let indentations = std::iter::once(tabs).chain([2, 4, 8].map(|width| Indentation {
+            width,
+            expand_tab: true,
+        }));
+        self.open_prompt(
+            PromptConfig {
+                on_enter: DispatchPrompt::Null,
+                items: indentations
+                    .map(|indentation| {
+                        DropdownItem::new(indentation.display())
+                            .set_dispatches(Dispatches::one(Dispatch::SetIndentation(indentation)))
+                    })
+                    .collect_vec(),
+                title: "Indentation".to_string(),
+                enter_selects_first_matching_item: true,
+                leaves_current_line_empty: true,
+                fire_dispatches_on_change: None,
+            },
+            PromptHistoryKey::Indentation,
+            None,
+        )
+    }
+
+    fn open_diagnostic_severity_prompt(&mut self) -> anyhow::Result<()> {
+        self.open_prompt(
+            PromptConfig {
+                on_enter: DispatchPrompt::Null,
+                items: Severity::ALL
+                    .into_iter()
+                    .map(|severity| {
+                        DropdownItem::new(severity.display().to_string()).set_dispatches(
+                            Dispatches::one(Dispatch::SetMinimumDiagnosticSeverity(severity)),
+                        )
+                    })
+                    .collect_vec(),
+                title: "Displayed diagnostics".to_string(),
+                enter_selects_first_matching_item: true,
+                leaves_current_line_empty: true,
+                fire_dispatches_on_change: None,
+            },
+            PromptHistoryKey::DiagnosticSeverity,
+            None,
+        )
+    }
+
+    /// Lists the muted entries with the number of diagnostics that each of them hides.
+    fn open_unmute_diagnostics_prompt(&mut self) -> anyhow::Result<()> {
+        if self.diagnostic_filter.muted.is_empty() {
+            return Err(anyhow::anyhow!("No diagnostics are muted"));
+        }
+        let counts = self.hidden_diagnostic_counts(DiagnosticSeverityRange::All);
+        self.open_prompt(
+            PromptConfig {
+                on_enter: DispatchPrompt::Null,
+                items: self
+                    .diagnostic_filter
+                    .muted
+                    .iter()
+                    .map(|name| {
+                        let count = counts.get(&Some(name.clone())).copied().unwrap_or(0);
+                        DropdownItem::new(format!("{name} ({count} hidden)")).set_dispatches(
+                            Dispatches::one(Dispatch::UnmuteDiagnostics(name.clone())),
+                        )
+                    })
+                    .collect_vec(),
+                title: "Unmute diagnostics".to_string(),
+                enter_selects_first_matching_item: true,
+                leaves_current_line_empty: true,
+                fire_dispatches_on_change: None,
+            },
+            PromptHistoryKey::UnmuteDiagnostics,
+            None,
+        )
+    }
+
+    fn macro_prompt_items(&self) -> Vec<DropdownItem> {
+        self.macros
+            .names()
+            .into_iter()
+            .map(|name| {
+                let text = self
+                    .macros
+                    .get(&name)
+                    .map(macros::to_text)
+                    .unwrap_or_default();
+                DropdownItem::new(name.display())
+                    .set_info(Some(Info::new("Keys".to_string(), text)))
+            })
+            .collect_vec()
+    }
+
+    /// Any name can be entered, where registers are prefixed with `@`.
+    fn open_edit_macro_prompt(&mut self) -> anyhow::Result<()> {
+        self.open_prompt(
+            PromptConfig {
+                on_enter: DispatchPrompt::EditMacro,
+                items: self.macro_prompt_items(),
+                title: "Edit macro (e.g. @q or a name)".to_string(),
+                enter_selects_first_matching_item: false,
+                leaves_current_line_empty: true,
+                fire_dispatches_on_change: None,
+            },
+            PromptHistoryKey::Macro,
+            None,
+        )
+    }
+
+    fn edit_macro(&mut self, name: MacroName) {
+        let text = self
+            .macros
+            .get(&name)
+            .map(macros::to_text)
+            .unwrap_or_default();
+        self.open_scratch_buffer_with_title(text, format!("[Macro {}]", name.display()));
+        let component_id = self.current_component().borrow().id();
+        self.macro_editors.insert(component_id, name);
+    }
+
+    /// Parses the current macro buffer and stores it as `name`,
+    /// or as the macro that the buffer was opened for if `name` is `None`.
+    fn write_macro(&mut self, name: Option<MacroName>) -> anyhow::Result<()> {
+        let component = self.current_component();
+        let component_id = component.borrow().id();
+        let Some(edited) = self.macro_editors.get(&component_id).cloned() else {
+            return Err(anyhow::anyhow!(
+                "The current buffer is not a macro, open one with the edit-macro command"
+            ));
+        };
+        let keys = macros::parse_text(&component.borrow().editor().content())?;
+        let name = name.unwrap_or(edited);
+        self.macros.set(name.clone(), keys)?;
+        component
+            .borrow_mut()
+            .set_title(format!("[Macro {}]", name.display()));
+        self.macro_editors.insert(component_id, name);
+        Ok(())
+    }
+
+    fn open_save_macro_prompt(&mut self) -> anyhow::Result<()> {
+        self.open_prompt(
+            PromptConfig {
+                on_enter: DispatchPrompt::SaveMacro,
+                items: self.macro_prompt_items(),
+                title: "Save macro as".to_string(),
+                enter_selects_first_matching_item: false,
+                leaves_current_line_empty: true,
+                fire_dispatches_on_change: None,
+            },
+            PromptHistoryKey::Macro,
+            None,
+        )
+    }
+
+    fn open_run_macro_prompt(&mut self) -> anyhow::Result<()> {
+        self.open_prompt(
+            PromptConfig {
+                on_enter: DispatchPrompt::RunMacro,
+                items: self.macro_prompt_items(),
+                title: "Run macro (e.g. @q, or @q 3 to run it 3 times)".to_string(),
+                enter_selects_first_matching_item: false,
+                leaves_current_line_empty: true,
+                fire_dispatches_on_change: None,
+            },
+            PromptHistoryKey::Macro,
+            None,
+        )
+    }
+
+    /// The keys of the macro are handled as if they were typed.
+    fn run_macro(
+        &mut self,
+        name: &MacroName,
+        repeat: usize,
+        each_selection: bool,
+    ) -> anyhow::Result<()> {
+        let Some(keys) = self.macros.get(name).map(|keys| keys.to_vec()) else {
+            return Err(anyhow::anyhow!("No macro is named {}", name.display()));
+        };
+        if self.running_macros.contains(name) {
+            return Err(anyhow::anyhow!(
+                "The macro {} cannot run itself",
+                name.display()
+            ));
+        }
+        if !each_selection {
+            self.running_macros.push(name.clone());
+            let result = self.handle_key_sequence(&keys, repeat);
+            self.running_macros.pop();
+            return result;
+        }
+        let component = self.current_component();
+        let (selections, mode) = {
+            let borrow = component.borrow();
+            let selection_set = &borrow.editor().selection_set;
+            // The later selections are run first, so that the edits of the macro
+            // do not shift the selections that are yet to be run
+            let selections = selection_set
+                .selections()
+                .iter()
+                .sorted_by_key(|selection| selection.extended_range().start)
+                .rev()
+                .cloned()
+                .collect_vec();
+            (selections, selection_set.mode.clone())
+        };
+        for selection in selections {
+            let selection_set = SelectionSet::new(NonEmpty::new(selection)).set_mode(mode.clone());
+            let dispatches = component
+                .borrow_mut()
+                .editor_mut()
+                .update_selection_set(selection_set, false);
+            self.handle_dispatches(dispatches)?;
+            self.running_macros.push(name.clone());
+            let result = self.handle_key_sequence(&keys, repeat);
+            self.running_macros.pop();
+            result?;
+        }
+        Ok(())
+    }
+
+    fn handle_key_sequence(
+        &mut self,
+        keys: &[event::KeyEvent],
+        repeat: usize,
+    ) -> anyhow::Result<()> {
+        for key in std::iter::repeat(keys).take(repeat).flatten() {
+            self.handle_event(Event::Key(key.clone()))?;
+        }
+        Ok(())
+    }
+
+    /// Restarts the recording if a macro is already being recorded.
+    fn start_macro_recording(&mut self, register: char) {
+        self.macro_recording = Some(MacroRecording {
+            register,
+            keys: Vec::new(),
+            sequence_start: 0,
+        });
+        self.context.set_recording_macro(Some(register))
+    }
+
+    fn stop_macro_recording(&mut self) -> anyhow::Result<()> {
+        let Some(mut recording) = self.macro_recording.take() else {
+            return Err(anyhow::anyhow!("No macro is being recorded"));
+        };
+        self.context.set_recording_macro(None);
+        recording.keys.truncate(recording.sequence_start);
+        self.macros
+            .set(MacroName::Register(recording.register), recording.keys)
+    }
+
+    /// A global mark is moved out of the buffer that it was set in before.
+    fn set_named_mark(&mut self, name: char) -> anyhow::Result<()> {
+        let component = self.current_component();
+        let (path, char_index) = {
+            let component = component.borrow();
+            let editor = component.editor();
+            (editor.buffer().path(), editor.get_cursor_char_index())
+        };
+        if named_mark::is_global(name) {
+            let Some(path) = path else {
+                return Err(anyhow::anyhow!("Global marks can only be set in files"));
+            };
+            for buffer in self.layout.buffers() {
+                buffer.borrow_mut().remove_named_mark(name);
+            }
+            let position = component
+                .borrow()
+                .editor()
+                .buffer()
+                .char_to_position(char_index)?;
+            self.global_marks.set(
+                name,
+                GlobalMark {
+                    path: path.into_path_buf(),
+                    line: position.line,
+                    column: position.column,
+                },
+            )?;
+        }
+        component
+            .borrow_mut()
+            .editor_mut()
+            .buffer_mut()
+            .set_named_mark(name, char_index);
+        Ok(())
+    }
+
+    fn go_to_named_mark(&mut self, name: char) -> anyhow::Result<()> {
+        let component: Rc<RefCell<dyn Component>> = if named_mark::is_global(name) {
+            let Some(mark) = self.global_marks.get(name) else {
+                return Err(anyhow::anyhow!("No mark is named {name}"));
+            };
+            let path = CanonicalizedPath::try_from(mark.path.clone())?;
+            self.open_file(&path, OpenFileOption::Focus)?
+        } else {
+            self.current_component()
+        };
+        let dispatches = {
+            let mut component = component.borrow_mut();
+            let editor = component.editor_mut();
+            let Some(char_index) = editor.buffer().named_mark(name) else {
+                return Err(anyhow::anyhow!("No mark is named {name}"));
+            };
+            let position = editor.buffer().char_to_position(char_index)?;
+            editor.set_position_range(position..position)?
+        };
+        self.handle_dispatches(dispatches)
+    }
+
+    fn delete_named_mark(&mut self, name: char) -> anyhow::Result<()> {
+        if named_mark::is_global(name) {
+            self.global_marks.remove(name)?;
+            for buffer in self.layout.buffers() {
+                buffer.borrow_mut().remove_named_mark(name);
+            }
+        } else {
+            self.current_component()
+                .borrow_mut()
+                .editor_mut()
+                .buffer_mut()
+                .remove_named_mark(name);
+        }
+        Ok(())
+    }
+
+    /// Refreshes the positions of the global marks from the buffers that follow their edits.
+    fn sync_global_marks(&mut self) -> anyhow::Result<()> {
+        for buffer in self.layout.buffers() {
+            let buffer = buffer.borrow();
+            let Some(path) = buffer.path() else {
+                continue;
+            };
+            for (name, char_index) in buffer.named_marks() {
+                if named_mark::is_global(name) {
+                    let position = buffer.char_to_position(char_index)?;
+                    self.global_marks.set(
+                        name,
+                        GlobalMark {
+                            path: path.clone().into_path_buf(),
+                            line: position.line,
+                            column: position.column,
+                        },
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The line of each mark is shown, which is read from the disk if its file is not opened.
+    fn open_named_mark_picker(&mut self) -> anyhow::Result<()> {
+        self.sync_global_marks()?;
+        let local_marks = {
+            let component = self.current_component();
+            let component = component.borrow();
+            let buffer = component.editor().buffer();
+            let marks = buffer
+                .named_marks()
+                .into_iter()
+                .filter(|(name, _)| !named_mark::is_global(*name))
+                .map(|(name, char_index)| {
+                    let position = buffer.char_to_position(char_index)?;
+                    let line = buffer.get_line_by_line_index(position.line);
+                    Ok((
+                        name,
+                        buffer.path(),
+                        position,
+                        line.map(|line| line.to_string()).unwrap_or_default(),
+                    ))
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            marks
+        };
+        let global_marks = self
+            .global_marks
+            .marks()
+            .iter()
+            .map(|(name, mark)| {
+                let path = CanonicalizedPath::try_from(mark.path.clone()).ok();
+                let line = match path
+                    .as_ref()
+                    .and_then(|path| self.layout.get_existing_editor(path))
+                {
+                    Some(editor) => {
+                        let editor = editor.borrow();
+                        let line = editor
+                            .editor()
+                            .buffer()
+                            .get_line_by_line_index(mark.line)
+                            .map(|line| line.to_string());
+                        line
+                    }
+                    None => std::fs::read_to_string(&mark.path)
+                        .ok()
+                        .and_then(|content| content.lines().nth(mark.line).map(String::from)),
+                };
+                (
+                    *name,
+                    path,
+                    Position::new(mark.line, mark.column),
+                    line.unwrap_or_default(),
+                )
+            })
+            .collect_vec();
+        let items = local_marks
+            .into_iter()
+            .map(|mark| ("Local", mark))
+            .chain(global_marks.into_iter().map(|mark| ("Global", mark)))
+            .map(|(group, (name, path, position, line))| {
+                let location = format!(
+                    "{}:{}:{}",
+                    path.map(|path| path.try_display_relative())
+                        .unwrap_or_default(),
+                    position.line + 1,
+                    position.column + 1
+                );
+                DropdownItem::new(format!("{name}  {}", line.trim()))
+                    .set_group(Some(group.to_string()))
+                    .set_info(Some(Info::new("Location".to_string(), location)))
+                    .set_dispatches(Dispatches::one(Dispatch::GoToNamedMark(name)))
+            })
+            .collect_vec();
+        self.open_prompt(
+            PromptConfig {
+                on_enter: DispatchPrompt::Null,
+                items,
+                title: "Marks".to_string(),
+                enter_selects_first_matching_item: true,
+                leaves_current_line_empty: true,
+                fire_dispatches_on_change: None,
+            },
+            PromptHistoryKey::NamedMark,
+            None,
+        )
+    }
+
+    /// Swaps the grammar of the current buffer and highlights it again,
+    /// and re-attaches it to the LSP server of the new language.
+    fn set_language(&mut self, language: Language) -> anyhow::Result<()> {
+        let component = self.current_component();
+        let component_id = component.borrow().id();
+        let (path, content) = {
+            let mut component = component.borrow_mut();
+            let mut buffer = component.editor_mut().buffer_mut();
+            buffer.switch_language(language.clone())?;
+            (buffer.path(), buffer.content())
+        };
+        self.request_syntax_highlight(component_id, language.clone(), content)?;
+        if let Some(path) = path.filter(|_| self.enable_lsp) {
+            self.lsp_manager.set_language(path, language)?;
+        }
+        Ok(())
+    }
+
+    fn update_current_completion_item(
+        &mut self,
+        completion_item: CompletionItem,
+    ) -> anyhow::Result<()> {
+        self.handle_dispatch_suggestive_editor(
+            DispatchSuggestiveEditor::UpdateCurrentCompletionItem(completion_item),
+        )
+    }
+
+    #[cfg(test)]
+    pub(crate) fn lsp_request_sent(&self, from_editor: &FromEditor) -> bool {
+        self.lsp_manager.lsp_request_sent(from_editor)
+    }
+
+    fn open_pipe_to_shell_prompt(&mut self, output: PipeToShellOutput) -> anyhow::Result<()> {
+        self.open_prompt(
+            PromptConfig {
+                title: match output {
+                    PipeToShellOutput::ReplaceSelections => "Pipe to shell".to_string(),
+                    PipeToShellOutput::ScratchBuffer => {
+                        "Pipe to shell (show output in scratch buffer)".to_string()
+                    }
+                },
+                items: Default::default(),
+                on_enter: DispatchPrompt::PipeToShell(output),
+                enter_selects_first_matching_item: false,
+                leaves_current_line_empty: true,
+                fire_dispatches_on_change: None,
+            },
+            PromptHistoryKey::PipeToShell,
+            None,
+        )
+    }
+
+    /// The command is run in a background thread so that the UI stays responsive,
+    /// and the output is handled when `AppMessage::PipeToShellFinished` is received.
+    fn pipe_to_shell(&mut self, command: String, output: PipeToShellOutput) {
+        let (component_id, inputs) = {
+            let component = self.current_component();
+            let component = component.borrow();
+            (component.id(), component.editor().get_selected_texts())
+        };
+        self.tasks.spawn(
+            format!("Pipe to `{command}`"),
+            TaskOrigin::Component(component_id),
+            move || {
+                let result = inputs
+                    .iter()
+                    .map(|input| {
+                        shared::process_command::ProcessCommand::new("bash", &["-c", &command])
+                            .run_with_input(input)
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()
+                    .map_err(|error| error.to_string());
+                AppMessage::PipeToShellFinished(PipeToShellFinished {
+                    component_id,
+                    command,
+                    inputs,
+                    output,
+                    result,
+                })
+            },
+        );
+    }
+
+    fn handle_pipe_to_shell_finished(
+        &mut self,
+        finished: PipeToShellFinished,
+    ) -> anyhow::Result<()> {
+        let PipeToShellFinished {
+            component_id,
+            command,
+            inputs,
+            output,
+            result,
+        } = finished;
+        let outputs = match result {
+            Ok(outputs) => outputs,
+            Err(error) => {
+                self.show_global_info(Info::new(format!("Pipe to shell `{command}`"), error));
+                return Ok(());
+            }
+        };
+        match output {
+            PipeToShellOutput::ScratchBuffer => {
+                self.open_scratch_buffer_with_title(
+                    outputs.join("\n"),
+                    format!("[Scratch] {command}"),
+                );
+                Ok(())
+            }
+            PipeToShellOutput::ReplaceSelections => {
+                let Some(component) = self.layout.get_component_by_id(component_id) else {
+                    return Ok(());
+                };
+                // Otherwise, unrelated texts will be replaced
+                if component.borrow().editor().get_selected_texts() != inputs {
+                    self.show_global_info(Info::new(
+                        format!("Pipe to shell `{command}`"),
+                        "The output is discarded because the selections were changed while the command was running".to_string(),
+                    ));
+                    return Ok(());
+                }
+                let Some(outputs) = NonEmpty::from_vec(outputs) else {
+                    return Ok(());
+                };
+                let dispatches = component.borrow_mut().handle_dispatch_editor(
+                    &mut self.context,
+                    Transform(Transformation::ReplaceWithCopiedText {
+                        copied_texts: CopiedTexts::new(outputs),
+                    }),
+                )?;
+                self.handle_dispatches(dispatches)
+            }
+        }
+    }
+
+    fn open_write_selections_prompt(&mut self, append: bool) -> anyhow::Result<()> {
+        self.open_prompt(
+            PromptConfig {
+                title: if append {
+                    "Append selections to file".to_string()
+                } else {
+                    "Write selections to file".to_string()
+                },
+                items: Default::default(),
+                on_enter: DispatchPrompt::WriteSelections { append },
+                enter_selects_first_matching_item: false,
+                leaves_current_line_empty: true,
+                fire_dispatches_on_change: None,
+            },
+            PromptHistoryKey::WriteSelections,
+            None,
+        )
+    }
+
+    /// Write the selections (separated by newlines) to `path`,
+    /// which is relative to the working directory.
+    ///
+    /// The file is written in a background thread,
+    /// and the result is shown when `AppMessage::WriteSelectionsFinished` is received.
+    fn write_selections(&mut self, path: PathBuf, append: bool) {
+        let path = self.working_directory.to_path_buf().join(path);
+        let content = self
+            .current_component()
+            .borrow()
+            .editor()
+            .get_selected_texts()
+            .into_iter()
+            .map(|text| format!("{}\n", text.trim_end_matches('\n')))
+            .collect::<String>();
+        self.tasks.spawn(
+            format!("Write selections to {}", path.display()),
+            TaskOrigin::App,
+            move || {
+                let result =
+                    write_to_file(&path, &content, append).map_err(|error| error.to_string());
+                AppMessage::WriteSelectionsFinished {
+                    path,
+                    append,
+                    result,
+                }
+            },
+        );
+    }
+
+    fn handle_write_selections_finished(
+        &mut self,
+        path: PathBuf,
+        append: bool,
+        result: Result<(), String>,
+    ) {
+        let title = if append {
+            "Append selections to file"
+        } else {
+            "Write selections to file"
+        };
+        let content = match result {
+            Ok(()) => format!(
+                "{} {}",
+                if append { "Appended to" } else { "Written to" },
+                path.display()
+            ),
+            Err(error) => error,
+        };
+        self.show_global_info(Info::new(title.to_string(), content))
+    }
+
+    fn open_insert_below_cursor_prompt(&mut self) -> anyhow::Result<()> {
+        self.open_prompt(
+            PromptConfig {
+                title: "Insert file below cursor (prefix with ! to insert the output of a command)"
+                    .to_string(),
+                items: Default::default(),
+                on_enter: DispatchPrompt::InsertBelowCursor,
+                enter_selects_first_matching_item: false,
+                leaves_current_line_empty: true,
+                fire_dispatches_on_change: None,
+            },
+            PromptHistoryKey::InsertBelowCursor,
+            None,
+        )
+    }
+
+    fn open_surround_with_tag_prompt(&mut self) -> anyhow::Result<()> {
+        self.open_prompt(
+            PromptConfig {
+                title: "Surround with tag (e.g. div class=\"foo\")".to_string(),
+                items: Default::default(),
+                on_enter: DispatchPrompt::SurroundWithTag,
+                enter_selects_first_matching_item: false,
+                leaves_current_line_empty: true,
+                fire_dispatches_on_change: None,
+            },
+            PromptHistoryKey::SurroundWithTag,
+            None,
+        )
+    }
+
+    fn open_align_prompt(&mut self) -> anyhow::Result<()> {
+        self.open_prompt(
+            PromptConfig {
+                title: "Align selections on (e.g. =)".to_string(),
+                items: Default::default(),
+                on_enter: DispatchPrompt::Align,
+                enter_selects_first_matching_item: false,
+                leaves_current_line_empty: true,
+                fire_dispatches_on_change: None,
+            },
+            PromptHistoryKey::Align,
+            None,
+        )
+    }
+
+    fn open_increment_prompt(&mut self) -> anyhow::Result<()> {
+        self.open_prompt(
+            PromptConfig {
+                title: "Increment by (negative to decrement)".to_string(),
+                items: Default::default(),
+                on_enter: DispatchPrompt::Increment,
+                enter_selects_first_matching_item: false,
+                leaves_current_line_empty: true,
+                fire_dispatches_on_change: None,
+            },
+            PromptHistoryKey::Increment,
+            None,
+        )
+    }
+
+    fn open_git_hunk_ref_prompt(
+        &mut self,
+        scope: Scope,
+        if_current_not_found: IfCurrentNotFound,
+    ) -> anyhow::Result<()> {
+        self.open_prompt(
+            PromptConfig {
+                title: "Git hunk against (e.g. a tag, a branch or a commit)".to_string(),
+                items: Default::default(),
+                on_enter: DispatchPrompt::GitHunkAgainstRef {
+                    scope,
+                    if_current_not_found,
+                },
+                enter_selects_first_matching_item: false,
+                leaves_current_line_empty: true,
+                fire_dispatches_on_change: None,
+            },
+            PromptHistoryKey::GitRef,
+            None,
+        )
+    }
+
+    fn open_spelling_suggestions_prompt(&mut self) -> anyhow::Result<()> {
+        let Some(spell_checker) = self.context.spell_checker() else {
+            return Err(anyhow::anyhow!(
+                "Spell checking is disabled, enable it by `spell-check = true` in the config"
+            ));
+        };
+        let misspelling = self
+            .current_component()
+            .borrow_mut()
+            .editor_mut()
+            .select_misspelling(spell_checker)?;
+        let Some((word, dispatches)) = misspelling else {
+            self.show_global_info(Info::new(
+                "Spelling".to_string(),
+                "No misspelled word is found on the current line.".to_string(),
+            ));
+            return Ok(());
+        };
+        let suggestions = spell_checker.suggestions(&word);
+        self.handle_dispatches(dispatches)?;
+        let items = suggestions
+            .into_iter()
+            .map(|suggestion| {
+                DropdownItem::new(suggestion.clone()).set_dispatches(Dispatches::one(
+                    Dispatch::ToEditor(ReplaceCurrentSelectionWith(suggestion)),
+                ))
+            })
+            .chain(Some(
+                DropdownItem::new(format!("Add \"{word}\" to dictionary"))
+                    .set_dispatches(Dispatches::one(Dispatch::AddToDictionary(word.clone()))),
+            ))
+            .enumerate()
+            .map(|(index, item)| item.set_rank(Some(Box::new([index]))))
+            .collect_vec();
+        self.open_prompt(
+            PromptConfig {
+                title: format!("Spelling suggestions for \"{word}\""),
+                items,
+                on_enter: DispatchPrompt::Null,
+                enter_selects_first_matching_item: true,
+                leaves_current_line_empty: true,
+                fire_dispatches_on_change: None,
+            },
+            PromptHistoryKey::SpellingSuggestions,
+            None,
+        )
+    }
+
+    /// `source` is either a path relative to the working directory,
+    /// or a shell command prefixed with `!`.
+    ///
+    /// The file is read (or the command is run) in a background task,
+    /// and its content is inserted when `AppMessage::InsertBelowCursorFinished` is received.
+    fn insert_below_cursor(&mut self, source: String) {
+        let component_id = self.current_component().borrow().id();
+        let working_directory = self.working_directory.clone().into_path_buf();
+        self.tasks.spawn(
+            format!("Insert `{source}` below cursor"),
+            TaskOrigin::Component(component_id),
+            move || {
+                let result = match source.strip_prefix('!') {
+                    Some(command) => {
+                        shared::process_command::ProcessCommand::new("bash", &["-c", command])
+                            .run_with_input("")
+                    }
+                    None => std::fs::read_to_string(working_directory.join(&source))
+                        .map_err(anyhow::Error::from),
+                }
+                .map_err(|error| error.to_string());
+                AppMessage::InsertBelowCursorFinished {
+                    component_id,
+                    source,
+                    result,
+                }
+            },
+        );
+    }
+
+    fn open_build_prompt(&mut self) -> anyhow::Result<()> {
+        self.open_prompt(
+            PromptConfig {
+                title: "Build command (e.g. cargo build)".to_string(),
+                items: Default::default(),
+                on_enter: DispatchPrompt::Build,
+                enter_selects_first_matching_item: false,
+                leaves_current_line_empty: true,
+                fire_dispatches_on_change: None,
+            },
+            PromptHistoryKey::Build,
+            None,
+        )
+    }
+
+    fn open_shell_task_prompt(&mut self) -> anyhow::Result<()> {
+        let items = self
+            .config
+            .tasks()
+            .iter()
+            .map(|(name, command)| {
+                let task = ShellTask {
+                    name: name.clone(),
+                    command: command.clone(),
+                };
+                DropdownItem::new(name.clone())
+                    .set_info(Some(Info::new("Command".to_string(), command.clone())))
+                    .set_dispatches(Dispatches::one(Dispatch::RunShellTask(task)))
+            })
+            .collect_vec();
+        if items.is_empty() {
+            return Err(anyhow::anyhow!(
+                "No tasks are configured, add them to [tasks] of the config"
+            ));
+        }
+        self.open_prompt(
+            PromptConfig {
+                on_enter: DispatchPrompt::Null,
+                items,
+                title: "Run task".to_string(),
+                enter_selects_first_matching_item: true,
+                leaves_current_line_empty: true,
+                fire_dispatches_on_change: None,
+            },
+            PromptHistoryKey::ShellTask,
+            None,
+        )
+    }
+
+    /// The task that is still running is killed, and its output pane is replaced.
+    fn run_shell_task(&mut self, task: ShellTask) -> anyhow::Result<()> {
+        if let Some(running) = self.shell_task.take() {
+            running.kill()
+        }
+        self.shell_task_id += 1;
+        self.last_shell_task = Some(task.clone());
+        let title = format!("[Task {}]", task.name);
+        self.shell_task = Some(RunningShellTask::spawn(
+            self.shell_task_id,
+            task,
+            &self.working_directory.to_path_buf(),
+            self.sender.clone(),
+        )?);
+        self.layout
+            .open_task_output(Rc::new(RefCell::new(TaskOutput::new(title))));
+        Ok(())
+    }
+
+    fn rerun_shell_task(&mut self) -> anyhow::Result<()> {
+        let Some(task) = self.last_shell_task.clone() else {
+            return Err(anyhow::anyhow!("No task is run yet"));
+        };
+        self.run_shell_task(task)
+    }
+
+    /// Downcasts the opened task output pane, if any.
+    fn with_task_output<T>(&self, f: impl FnOnce(&mut TaskOutput) -> T) -> Option<T> {
+        let component = self
+            .layout
+            .get_component_by_kind(ComponentKind::TaskOutput)?;
+        let mut component = component.borrow_mut();
+        let task_output = component.as_any_mut().downcast_mut::<TaskOutput>()?;
+        Some(f(task_output))
+    }
+
+    /// The output of the tasks that are killed is ignored.
+    fn handle_shell_task_output(&mut self, id: usize, line: String) -> anyhow::Result<()> {
+        let Some(running) = self
+            .shell_task
+            .as_mut()
+            .filter(|running| running.id() == id)
+        else {
+            return Ok(());
+        };
+        running.push_output(line.clone());
+        self.with_task_output(|task_output| task_output.append_line(&line))
+            .transpose()?;
+        Ok(())
+    }
+
+    /// The messages of the files that do not exist are skipped. A failing exit code is expected,
+    /// because that is how most build commands report errors.
+    fn handle_shell_task_exited(&mut self, id: usize) -> anyhow::Result<()> {
+        let Some(running) = self.shell_task.take() else {
+            return Ok(());
+        };
+        if running.id() != id {
+            self.shell_task = Some(running);
+            return Ok(());
+        }
+        let name = running.task().name.clone();
+        let (output, code) = running.wait()?;
+        let exit = code
+            .map(|code| format!("exited with {code}"))
+            .unwrap_or_else(|| "killed".to_string());
+        self.with_task_output(|task_output| task_output.set_title(format!("[Task {name}] {exit}")));
+        let items = compiler_output::parse(&output)
+            .into_iter()
+            .filter_map(|message| {
+                let path = self.working_directory.to_path_buf().join(&message.path);
+                Some(QuickfixListItem::new(
+                    Location {
+                        path: path.try_into().ok()?,
+                        range: message.position..message.position,
+                    },
+                    Some(Info::new(format!("Task {name}"), message.message)),
+                ))
+            })
+            .collect_vec();
+        if items.is_empty() {
+            return Ok(());
+        }
+        self.set_quickfix_list_type(
+            ResponseContext::default().set_description(&format!("Task {name}")),
+            QuickfixListType::Items(items),
+        )
+    }
+
+    fn open_debug_prompt(&mut self) -> anyhow::Result<()> {
+        let items = self
+            .config
+            .debug_configs()
+            .iter()
+            .map(|(name, config)| {
+                DropdownItem::new(name.clone())
+                    .set_info(Some(Info::new(
+                        "Debug adapter".to_string(),
+                        config.command.clone(),
+                    )))
+                    .set_dispatches(Dispatches::one(Dispatch::StartDebugging(name.clone())))
+            })
+            .collect_vec();
+        if items.is_empty() {
+            return Err(anyhow::anyhow!(
+                "No debug configurations are configured, add them to [debug] of the config"
+            ));
+        }
+        self.open_prompt(
+            PromptConfig {
+                on_enter: DispatchPrompt::Null,
+                items,
+                title: "Start debugging".to_string(),
+                enter_selects_first_matching_item: true,
+                leaves_current_line_empty: true,
+                fire_dispatches_on_change: None,
+            },
+            PromptHistoryKey::DebugConfig,
+            None,
+        )
+    }
+
+    /// The session that is still running is stopped, and its panel is replaced.
+    fn start_debugging(&mut self, name: String) -> anyhow::Result<()> {
+        let Some(config) = self.config.debug_configs().get(&name).cloned() else {
+            return Err(anyhow::anyhow!("No debug configuration is named {name}"));
+        };
+        self.stop_debugging();
+        self.debug_session_id += 1;
+        let breakpoints = self
+            .layout
+            .buffers()
+            .into_iter()
+            .filter_map(|buffer| {
+                let buffer = buffer.borrow();
+                let lines = buffer.breakpoint_lines();
+                let path = buffer.path()?;
+                Some((path.into_path_buf(), lines))
+            })
+            .filter(|(_, lines)| !lines.is_empty())
+            .collect();
+        let title = format!("[Debug {name}]");
+        self.debug_session = Some(DebugSession::start(
+            self.debug_session_id,
+            name,
+            config,
+            breakpoints,
+            self.working_directory.to_path_buf(),
+            self.sender.clone(),
+        )?);
+        self.layout
+            .open_debug_panel(Rc::new(RefCell::new(DebugPanel::new(title))));
+        self.refresh_debug_panel()
+    }
+
+    /// Kills the debug adapter of the running session, if any.
+    fn stop_debugging(&mut self) {
+        if let Some(session) = self.debug_session.take() {
+            session.kill()
+        }
+        self.context.set_execution_location(None)
+    }
+
+    /// The breakpoints of the file are sent to the running session, if any.
+    fn toggle_breakpoint(&mut self) -> anyhow::Result<()> {
+        let component = self.current_component();
+        let (path, lines) = {
+            let mut component = component.borrow_mut();
+            let editor = component.editor_mut();
+            let char_index = editor.get_cursor_char_index();
+            let mut buffer = editor.buffer_mut();
+            let Some(path) = buffer.path() else {
+                return Err(anyhow::anyhow!("Breakpoints can only be set in files"));
+            };
+            let line = buffer.char_to_line(char_index)?;
+            buffer.toggle_breakpoint(line)?;
+            let lines = buffer.breakpoint_lines();
+            (path, lines)
+        };
+        if let Some(session) = self.debug_session.as_mut() {
+            session.set_breakpoints(path.into_path_buf(), lines)?
+        }
+        Ok(())
+    }
+
+    fn execute_debug_command(&mut self, command: DebugCommand) -> anyhow::Result<()> {
+        let Some(session) = self.debug_session.as_mut() else {
+            return Err(anyhow::anyhow!("No debugging session is running"));
+        };
+        session.execute(command)?;
+        if matches!(
+            command,
+            DebugCommand::Continue
+                | DebugCommand::StepOver
+                | DebugCommand::StepIn
+                | DebugCommand::StepOut
+        ) {
+            self.context.set_execution_location(None)
+        }
+        self.refresh_debug_panel()
+    }
+
+    fn open_debug_watch_prompt(&mut self) -> anyhow::Result<()> {
+        self.open_prompt(
+            PromptConfig {
+                title: "Watch expression".to_string(),
+                items: Default::default(),
+                on_enter: DispatchPrompt::AddDebugWatch,
+                enter_selects_first_matching_item: false,
+                leaves_current_line_empty: true,
+                fire_dispatches_on_change: None,
+            },
+            PromptHistoryKey::DebugWatch,
+            None,
+        )
+    }
+
+    fn add_debug_watch(&mut self, expression: String) -> anyhow::Result<()> {
+        let Some(session) = self.debug_session.as_mut() else {
+            return Err(anyhow::anyhow!("No debugging session is running"));
+        };
+        session.add_watch(expression)?;
+        self.refresh_debug_panel()
+    }
+
+    /// The key sequences bound by plugins are added to the keymaps,
+    /// and bound either to commands of plugins or to built-in commands.
+    pub(crate) fn set_plugins(&mut self, plugins: Plugins) -> anyhow::Result<()> {
+        let commands = plugins.commands();
+        let errors = plugins
+            .keymaps()
+            .into_iter()
+            .filter_map(|keymap| {
+                let known = crate::command::find(&keymap.command).is_some()
+                    || commands
+                        .iter()
+                        .any(|command| command.name == keymap.command);
+                if !known {
+                    return Some(format!(
+                        "{:?} is bound to the unknown command {:?}",
+                        keymap.keys, keymap.command
+                    ));
+                }
+                self.keymap_config
+                    .add_command(&keymap.mode, &keymap.keys, keymap.command)
+                    .err()
+                    .map(|error| error.to_string())
+            })
+            .collect_vec();
+        self.plugins = Some(plugins);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!(
+                "Invalid keymaps of plugins:\n{}",
+                errors.join("\n")
+            ))
+        }
+    }
+
+    pub(crate) fn set_remote_clients(&mut self, remote_clients: RemoteClients) {
+        self.remote_clients = Some(remote_clients)
+    }
+
+    /// The errors of notifications are shown, since there is no response to report them.
+    fn handle_remote_request(
+        &mut self,
+        client_id: usize,
+        id: Option<serde_json::Value>,
+        request: RemoteRequest,
+    ) -> anyhow::Result<()> {
+        let result = self.execute_remote_request(request);
+        match (id, &self.remote_clients) {
+            (Some(id), Some(clients)) => {
+                clients.respond(client_id, id, result.map_err(|error| error.to_string()))
+            }
+            _ => {
+                if let Err(error) = result {
+                    self.show_global_info(Info::new("ERROR".to_string(), error.to_string()))
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn execute_remote_request(
+        &mut self,
+        request: RemoteRequest,
+    ) -> anyhow::Result<serde_json::Value> {
+        match request {
+            RemoteRequest::Input(keys) => {
+                for key in keys {
+                    self.handle_event(Event::Key(key))?;
+                }
+            }
+            RemoteRequest::Paste(text) => {
+                self.handle_event(Event::Paste(text))?;
+            }
+            RemoteRequest::Resize(dimension) => {
+                self.handle_event(Event::Resize(dimension.width, dimension.height))?;
+            }
+            RemoteRequest::OpenFile(path) => {
+                let path = self.working_directory.to_path_buf().join(path);
+                self.focus_file(&path.try_into()?)?
+            }
+            RemoteRequest::Command(name) => self.run_command(name)?,
+            RemoteRequest::Buffers => {
+                return Ok(self
+                    .layout
+                    .buffers()
+                    .into_iter()
+                    .filter_map(|buffer| buffer.borrow().path())
+                    .map(|path| path.display_absolute())
+                    .collect_vec()
+                    .into())
+            }
+            RemoteRequest::Content => {
+                return Ok(self.current_component().borrow().content().into())
+            }
+        }
+        Ok(serde_json::Value::Null)
+    }
+
+    fn plugin_buffer(&self) -> PluginBuffer {
+        let component = self.current_component();
+        let component = component.borrow();
+        let editor = component.editor();
+        PluginBuffer {
+            text: editor.content(),
+            path: editor.path().map(|path| path.display_absolute()),
+            selections: editor.get_selected_texts(),
+        }
+    }
+
+    fn answer_plugin(&mut self, callback_id: usize, answer: String) -> anyhow::Result<()> {
+        let Some(plugins) = &self.plugins else {
+            return Ok(());
+        };
+        let actions = plugins.answer(callback_id, answer, self.plugin_buffer())?;
+        self.apply_plugin_actions(actions)
+    }
+
+    fn apply_plugin_actions(&mut self, actions: Vec<PluginAction>) -> anyhow::Result<()> {
+        for action in actions {
+            match action {
+                PluginAction::SetText(text) => {
+                    self.handle_dispatch_editor(DispatchEditor::SelectAll)?;
+                    self.handle_dispatch_editor(DispatchEditor::ReplaceCurrentSelectionWith(text))?
+                }
+                PluginAction::ReplaceSelections(text) => {
+                    self.handle_dispatch_editor(DispatchEditor::ReplaceCurrentSelectionWith(text))?
+                }
+                PluginAction::ShowInfo { title, content } => {
+                    self.show_global_info(Info::new(title, content))
+                }
+                PluginAction::Prompt { title, callback_id } => self.open_prompt(
+                    PromptConfig {
+                        title,
+                        items: Default::default(),
+                        on_enter: DispatchPrompt::AnswerPlugin { callback_id },
+                        enter_selects_first_matching_item: false,
+                        leaves_current_line_empty: true,
+                        fire_dispatches_on_change: None,
+                    },
+                    PromptHistoryKey::Plugin,
+                    None,
+                )?,
+                PluginAction::Pick {
+                    title,
+                    items,
+                    callback_id,
+                } => self.open_prompt(
+                    PromptConfig {
+                        title,
+                        items: items
+                            .into_iter()
+                            .map(|item| {
+                                DropdownItem::new(item.clone()).set_dispatches(Dispatches::one(
+                                    Dispatch::AnswerPlugin {
+                                        callback_id,
+                                        answer: item,
+                                    },
+                                ))
+                            })
+                            .collect(),
+                        on_enter: DispatchPrompt::Null,
+                        enter_selects_first_matching_item: true,
+                        leaves_current_line_empty: true,
+                        fire_dispatches_on_change: None,
+                    },
+                    PromptHistoryKey::Plugin,
+                    None,
+                )?,
+                PluginAction::RunCommand(command) => self.run_command(command)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Downcasts the opened debug panel, if any.
+    fn with_debug_panel<T>(&self, f: impl FnOnce(&mut DebugPanel) -> T) -> Option<T> {
+        let component = self
+            .layout
+            .get_component_by_kind(ComponentKind::DebugPanel)?;
+        let mut component = component.borrow_mut();
+        let debug_panel = component.as_any_mut().downcast_mut::<DebugPanel>()?;
+        Some(f(debug_panel))
+    }
+
+    fn refresh_debug_panel(&mut self) -> anyhow::Result<()> {
+        let Some(content) = self.debug_session.as_ref().map(|session| session.display()) else {
+            return Ok(());
+        };
+        self.with_debug_panel(|debug_panel| debug_panel.set_content(content))
+            .transpose()?;
+        Ok(())
+    }
+
+    /// The messages of the sessions that are stopped are ignored.
+    /// The file where the debuggee stops is opened at the line where it stops.
+    fn handle_debug_adapter_message(
+        &mut self,
+        session_id: usize,
+        message: serde_json::Value,
+    ) -> anyhow::Result<()> {
+        let Some(session) = self
+            .debug_session
+            .as_mut()
+            .filter(|session| session.id() == session_id)
+        else {
+            return Ok(());
+        };
+        match session.handle_message(message)? {
+            None => Ok(()),
+            Some(DebugSessionUpdate::Refresh) => self.refresh_debug_panel(),
+            Some(DebugSessionUpdate::Stopped(location)) => {
+                if let Some((path, line)) = location {
+                    let path = CanonicalizedPath::try_from(path)?;
+                    self.context
+                        .set_execution_location(Some((path.clone(), line)));
+                    let position = Position::new(line, 0);
+                    self.go_to_location(&Location {
+                        path,
+                        range: position..position,
+                    })?;
+                }
+                self.refresh_debug_panel()
+            }
+            Some(DebugSessionUpdate::Continued) => {
+                self.context.set_execution_location(None);
+                self.refresh_debug_panel()
+            }
+            Some(DebugSessionUpdate::Terminated) => {
+                self.refresh_debug_panel()?;
+                self.stop_debugging();
+                Ok(())
+            }
+            Some(DebugSessionUpdate::Error(error)) => {
+                self.show_global_info(Info::new("Debug".to_string(), error));
+                Ok(())
+            }
+        }
+    }
+
+    fn handle_debug_adapter_exited(&mut self, session_id: usize) -> anyhow::Result<()> {
+        let Some(session) = self
+            .debug_session
+            .as_ref()
+            .filter(|session| session.id() == session_id)
+        else {
+            return Ok(());
+        };
+        let title = format!("[Debug {}] exited", session.name());
+        self.stop_debugging();
+        self.with_debug_panel(|debug_panel| debug_panel.set_title(title));
+        Ok(())
+    }
+
+    fn handle_insert_below_cursor_finished(
+        &mut self,
+        component_id: ComponentId,
+        source: String,
+        result: Result<String, String>,
+    ) -> anyhow::Result<()> {
+        let content = match result {
+            Ok(content) => content,
+            Err(error) => {
+                self.show_global_info(Info::new(format!("Insert `{source}` below cursor"), error));
+                return Ok(());
+            }
+        };
+        let Some(component) = self.layout.get_component_by_id(component_id) else {
+            return Ok(());
+        };
+        let dispatches = component
+            .borrow_mut()
+            .handle_dispatch_editor(&mut self.context, InsertLinesBelow(content))?;
+        self.handle_dispatches(dispatches)
+    }
+
+    /// Blames the current file in a background task if its blame is stale,
+    /// so that rendering and scrolling are never blocked by git.
+    fn request_git_blame(&mut self) {
+        if !self.context.show_git_blame() {
+            return;
+        }
+        let component = self.current_component();
+        let borrow = component.borrow();
+        let buffer = borrow.editor().buffer();
+        let Some(path) = buffer.path() else {
+            return;
+        };
+        if !buffer.git_blame_is_stale() || self.pending_git_blames.contains(&path) {
+            return;
+        }
+        self.pending_git_blames.insert(path.clone());
+        self.spawn_git_blame(path, buffer.content(), false)
+    }
+
+    /// Only the lines around the visible lines of large files are highlighted,
+    /// which are highlighted again once the visible lines go beyond them.
+    fn request_large_file_syntax_highlights(&self) -> anyhow::Result<()> {
+        let Some(sender) = &self.syntax_highlight_request_sender else {
+            return Ok(());
+        };
+        for component in self.layout.components() {
+            let component = component.component();
+            let mut borrow = component.borrow_mut();
+            let component_id = borrow.id();
+            let editor = borrow.editor_mut();
+            let visible_line_range = editor.visible_line_range();
+            let request = editor
+                .buffer_mut()
+                .large_file_highlight_request(visible_line_range);
+            if let Some((language, source_code, byte_offset)) = request {
+                sender.send(SyntaxHighlightRequest {
+                    component_id,
+                    language,
+                    source_code,
+                    byte_offset,
+                })?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Applying a large paste blocks the main loop for a while, e.g. to update the undo history,
+    /// so it is run as a task that does nothing but hand the paste back, which makes a frame
+    /// showing that the paste is in progress rendered before the main loop is blocked.
+    fn defer_large_paste(&mut self, component_id: ComponentId, content: String) {
+        let description = format!("Pasting {} lines", content.lines().count());
+        self.tasks.spawn(
+            description,
+            TaskOrigin::Component(component_id),
+            move || AppMessage::LargePasteDue {
+                component_id,
+                content,
+            },
+        );
+    }
+
+    fn spawn_git_blame(&mut self, path: CanonicalizedPath, content: String, open_panel: bool) {
+        let description = format!("Blame {}", path.try_display_relative());
+        self.tasks.spawn(description, TaskOrigin::App, move || {
+            let result = git::blame::blame(&path, &content).map_err(|error| error.to_string());
+            AppMessage::GitBlameFinished {
+                path,
+                content,
+                open_panel,
+                result,
+            }
+        });
+    }
+
+    fn show_hover_popup(&mut self, hover: Hover) {
+        let component = self.current_component();
+        let anchor = (
+            component.borrow().id(),
+            component.borrow().editor().get_cursor_char_index(),
+        );
+        let text = self.render_markdown(&hover.contents.join("\n\n"));
+        self.hover_popup = Some(HoverPopup::new(text, anchor));
+    }
+
+    fn render_markdown(&mut self, markdown: &str) -> markdown::StyledText {
+        let context = &mut self.context;
+        markdown::render(markdown, |label, code| {
+            // The label of a code block is either the name of a language or an extension
+            let language = language::all()
+                .into_iter()
+                .find(|language| language.name() == Some(label))
+                .or_else(|| language::from_extension(label))?;
+            context.highlight(language, code).ok().map(|spans| spans.0)
+        })
+    }
+
+    fn open_markdown_preview(&mut self) -> anyhow::Result<()> {
+        let component = self.current_component();
+        let path = component
+            .borrow()
+            .path()
+            .filter(markdown_preview::is_markdown)
+            .ok_or_else(|| anyhow::anyhow!("Only markdown files can be previewed"))?;
+        let content = component.borrow().content();
+        let name = path
+            .display_relative_to(&self.working_directory)
+            .unwrap_or_else(|_| path.display_absolute());
+        let text = self.render_markdown(&content);
+        let markdown_preview = MarkdownPreview::new(path, format!("Preview: {name}"), text)?;
+        self.layout
+            .open_markdown_preview(Rc::new(RefCell::new(markdown_preview)));
+        Ok(())
+    }
+
+    /// Downcasts the opened markdown preview, if any.
+    fn with_markdown_preview<T>(&self, f: impl FnOnce(&mut MarkdownPreview) -> T) -> Option<T> {
+        let component = self
+            .layout
+            .get_component_by_kind(ComponentKind::MarkdownPreview)?;
+        let mut component = component.borrow_mut();
+        let markdown_preview = component.as_any_mut().downcast_mut::<MarkdownPreview>()?;
+        Some(f(markdown_preview))
+    }
+
+    fn markdown_preview_source(&self) -> Option<CanonicalizedPath> {
+        self.with_markdown_preview(|markdown_preview| markdown_preview.source().clone())
+    }
+
+    fn debounce_markdown_preview_refresh(&mut self) {
+        self.markdown_preview_refresh_id += 1;
+        self.timer.schedule(
+            TimerKey::MarkdownPreviewRefresh,
+            MARKDOWN_PREVIEW_DEBOUNCE,
+            AppMessage::MarkdownPreviewRefreshDue {
+                id: self.markdown_preview_refresh_id,
+            },
+        );
     }
 
-    fn close_current_window_and_focus_parent(&mut self) {
-        self.layout.close_current_window_and_focus_parent()
+    fn refresh_markdown_preview(&mut self, id: usize) -> anyhow::Result<()> {
+        if id != self.markdown_preview_refresh_id {
+            return Ok(());
+        }
+        let Some(editor) = self
+            .markdown_preview_source()
+            .and_then(|source| self.layout.get_existing_editor(&source))
+        else {
+            return Ok(());
+        };
+        let content = editor.borrow().content();
+        let text = self.render_markdown(&content);
+        self.with_markdown_preview(|markdown_preview| markdown_preview.update(text))
+            .transpose()?;
+        Ok(())
     }
 
-    #[cfg(test)]
-    pub(crate) fn opened_files_count(&self) -> usize {
-        self.layout.get_opened_files().len()
+    fn cursor_anchor(&self) -> (ComponentId, CharIndex) {
+        let component = self.current_component();
+        let borrow = component.borrow();
+        (borrow.id(), borrow.editor().get_cursor_char_index())
     }
 
-    #[cfg(test)]
-    pub(crate) fn quickfix_list_info(&self) -> Option<String> {
-        self.layout.quickfix_list_info()
+    /// The document highlights are requested once the cursor rests for
+    /// `Config::document_highlight_delay`, see `AppMessage::DocumentHighlightDue`.
+    fn schedule_document_highlights(&mut self) {
+        let Some(delay) = self.config.document_highlight_delay() else {
+            return;
+        };
+        let anchor = self.cursor_anchor();
+        if self.document_highlight_anchor == Some(anchor) {
+            return;
+        }
+        self.document_highlight_anchor = Some(anchor);
+        self.document_highlight_id += 1;
+        if self.get_request_params().is_none() {
+            return;
+        }
+        self.timer.schedule(
+            TimerKey::DocumentHighlight,
+            delay,
+            AppMessage::DocumentHighlightDue {
+                id: self.document_highlight_id,
+            },
+        );
     }
 
-    #[cfg(test)]
-    pub(crate) fn get_component_by_kind(
-        &self,
-        kind: ComponentKind,
-    ) -> Option<Rc<RefCell<dyn Component>>> {
-        self.layout.get_component_by_kind(kind)
+    fn request_document_highlights(&mut self, id: usize) -> anyhow::Result<()> {
+        if id != self.document_highlight_id {
+            return Ok(());
+        }
+        if let Some(params) = self.get_request_params() {
+            self.lsp_manager.send_message(
+                params.path.clone(),
+                FromEditor::TextDocumentDocumentHighlight(params),
+            )?;
+        }
+        Ok(())
     }
 
-    fn hide_editor_info(&mut self) {
-        self.layout.hide_editor_info()
+    /// The hover popup is dismissed once the cursor moves away from where the hover is requested.
+    fn dismiss_moved_hover_popup(&mut self) {
+        let anchor = self.cursor_anchor();
+        if self
+            .hover_popup
+            .as_ref()
+            .is_some_and(|hover_popup| hover_popup.anchor() != anchor)
+        {
+            self.hover_popup = None
+        }
     }
 
     #[cfg(test)]
-    pub(crate) fn components_order(&self) -> Vec<ComponentKind> {
-        self.layout
+    pub(crate) fn hover_popup_content(&self) -> Option<String> {
+        Some(self.hover_popup.as_ref()?.content().to_string())
+    }
+
+    /// Cancels the tasks of the components that are closed, for example the shell command
+    /// that is piped to from a prompt that is closed.
+    fn cancel_orphaned_tasks(&mut self) {
+        let opened_components = self
+            .layout
             .components()
             .into_iter()
-            .map(|c| c.kind())
-            .collect_vec()
+            .map(|component| component.component().borrow().id())
+            .collect_vec();
+        self.tasks.cancel_orphans(&opened_components)
     }
 
-    fn handle_signature_help(
-        &mut self,
-        signature_help: Option<crate::lsp::signature_help::SignatureHelp>,
-    ) -> anyhow::Result<()> {
-        if let Some(info) = signature_help.and_then(|s| s.into_info()) {
-            if self.current_component().borrow().editor().mode
-                == crate::components::editor::Mode::Insert
-            {
-                self.show_editor_info(info)?;
+    fn open_git_blame_panel(&mut self) -> anyhow::Result<()> {
+        let component = self.current_component();
+        let borrow = component.borrow();
+        let buffer = borrow.editor().buffer();
+        let path = buffer
+            .path()
+            .ok_or_else(|| anyhow::anyhow!("Only files can be blamed"))?;
+        if buffer.is_encrypted() {
+            return Err(anyhow::anyhow!("Encrypted files cannot be blamed"));
+        }
+        let content = buffer.content();
+        match buffer.git_blame() {
+            Some(lines) => {
+                let lines = lines.to_vec();
+                drop(buffer);
+                drop(borrow);
+                self.show_git_blame_panel(&path, &content, &lines)
             }
-        } else {
-            self.hide_editor_info()
+            None => self.spawn_git_blame(path, content, true),
         }
         Ok(())
     }
 
-    fn push_history_prompt(&mut self, key: PromptHistoryKey, line: String) {
-        self.context.push_history_prompt(key, line)
+    fn handle_git_blame_finished(
+        &mut self,
+        path: CanonicalizedPath,
+        content: String,
+        open_panel: bool,
+        result: Result<Vec<BlameLine>, String>,
+    ) -> anyhow::Result<()> {
+        if !open_panel {
+            self.pending_git_blames.remove(&path);
+        }
+        let lines = match result {
+            Ok(lines) => lines,
+            Err(error) if open_panel => return Err(anyhow::anyhow!(error)),
+            // Files that cannot be blamed (e.g. untracked files) are stored with an empty blame,
+            // so that they are not blamed again until they are modified
+            Err(_) => Vec::new(),
+        };
+        if let Some(editor) = self.layout.get_existing_editor(&path) {
+            editor
+                .borrow_mut()
+                .editor_mut()
+                .buffer_mut()
+                .set_git_blame(content.clone(), lines.clone());
+        }
+        if open_panel {
+            self.show_git_blame_panel(&path, &content, &lines)
+        }
+        Ok(())
     }
 
-    fn open_theme_prompt(&mut self) -> anyhow::Result<()> {
-        self.open_prompt(
-            PromptConfig {
-                on_enter: DispatchPrompt::Null,
-                items: crate::themes::themes()?
-                    .into_iter()
-                    .map(|theme| {
-                        DropdownItem::new(theme.name.to_string())
-                            .set_dispatches(Dispatches::one(Dispatch::SetTheme(theme.clone())))
-                    })
-                    .collect_vec(),
-                title: "Theme".to_string(),
-                enter_selects_first_matching_item: true,
-                leaves_current_line_empty: true,
-                fire_dispatches_on_change: Some(Dispatches::one(Dispatch::SetTheme(
-                    self.context.theme().clone(),
-                ))),
-            },
-            PromptHistoryKey::Theme,
-            None,
-        )
+    fn show_git_blame_panel(
+        &mut self,
+        path: &CanonicalizedPath,
+        content: &str,
+        lines: &[BlameLine],
+    ) {
+        let author_width = lines
+            .iter()
+            .map(BlameLine::author_width)
+            .max()
+            .unwrap_or_default();
+        let panel = content
+            .lines()
+            .zip(lines)
+            .map(|(line, blame)| format!("{} │ {line}", blame.display_columns(author_width)))
+            .join("\n");
+        let path = path
+            .display_relative_to(&self.working_directory)
+            .unwrap_or_else(|_| path.display_absolute());
+        self.open_scratch_buffer_with_title(panel, format!("Git blame: {path}"))
     }
 
-    fn update_current_completion_item(
-        &mut self,
-        completion_item: CompletionItem,
-    ) -> anyhow::Result<()> {
-        self.handle_dispatch_suggestive_editor(
-            DispatchSuggestiveEditor::UpdateCurrentCompletionItem(completion_item),
-        )
+    fn open_diff_view(&mut self, base: DiffBase) -> anyhow::Result<()> {
+        let component = self.current_component();
+        let path = component
+            .borrow()
+            .path()
+            .ok_or_else(|| anyhow::anyhow!("Only files can be diffed"))?;
+        let content = component.borrow().content();
+        let display = |path: &CanonicalizedPath| {
+            path.display_relative_to(&self.working_directory)
+                .unwrap_or_else(|_| path.display_absolute())
+        };
+        let name = display(&path);
+        let encrypted = component.borrow().editor().buffer().is_encrypted();
+        let (old_title, old_content, new_title) = match &base {
+            DiffBase::FileOnDisk | DiffBase::Head if encrypted => {
+                return Err(anyhow::anyhow!(
+                    "Encrypted files can only be diffed against other buffers"
+                ));
+            }
+            DiffBase::FileOnDisk => (
+                format!("{name} (on disk)"),
+                path.read()?,
+                format!("{name} (unsaved)"),
+            ),
+            DiffBase::Head => {
+                let diff_provider = git::diff_provider::diff_provider(
+                    &path,
+                    &git::DiffMode::UnstagedAgainstCurrentBranch,
+                )
+                .ok_or_else(|| anyhow::anyhow!("{name} is not under version control"))?;
+                (
+                    format!("{name} (HEAD)"),
+                    diff_provider.base_content(&path)?,
+                    name,
+                )
+            }
+            DiffBase::Buffer(other) => (
+                display(other),
+                self.layout
+                    .get_existing_editor(other)
+                    .ok_or_else(|| anyhow::anyhow!("{} is not opened", display(other)))?
+                    .borrow()
+                    .content(),
+                name,
+            ),
+        };
+        let diff_view = DiffView::new(old_title, &old_content, new_title, &content)?;
+        self.layout.open_diff_view(Rc::new(RefCell::new(diff_view)));
+        Ok(())
     }
 
-    #[cfg(test)]
-    pub(crate) fn lsp_request_sent(&self, from_editor: &FromEditor) -> bool {
-        self.lsp_manager.lsp_request_sent(from_editor)
+    /// The copied text is the old side, so that the changes read as edits of the copy.
+    fn open_clipboard_diff_view(&mut self, use_system_clipboard: bool) -> anyhow::Result<()> {
+        let selection = self
+            .current_component()
+            .borrow()
+            .editor()
+            .primary_selection()?;
+        let copied = self
+            .context
+            .get_clipboard_content(use_system_clipboard, 0)?
+            .ok_or_else(|| anyhow::anyhow!("Nothing is copied yet"))?
+            .get(0);
+        let diff_view = DiffView::new(
+            "Clipboard".to_string(),
+            &copied,
+            "Selection".to_string(),
+            &selection,
+        )?;
+        self.layout.open_diff_view(Rc::new(RefCell::new(diff_view)));
+        Ok(())
     }
 
-    fn open_pipe_to_shell_prompt(&mut self) -> anyhow::Result<()> {
+    fn open_diff_buffer_prompt(&mut self) -> anyhow::Result<()> {
+        let current_path = self.current_component().borrow().path();
         self.open_prompt(
             PromptConfig {
-                title: "Pipe to shell".to_string(),
-                items: Default::default(),
-                on_enter: DispatchPrompt::PipeToShell,
-                enter_selects_first_matching_item: false,
+                title: "Diff against buffer".to_string(),
+                items: self
+                    .layout
+                    .get_opened_files()
+                    .into_iter()
+                    .filter(|path| Some(path) != current_path.as_ref())
+                    .map(|path| {
+                        DropdownItem::new(
+                            path.display_relative_to(&self.working_directory)
+                                .unwrap_or_else(|_| path.display_absolute()),
+                        )
+                        .set_dispatches(Dispatches::one(
+                            Dispatch::OpenDiffView(DiffBase::Buffer(path)),
+                        ))
+                    })
+                    .collect_vec(),
+                on_enter: DispatchPrompt::Null,
+                enter_selects_first_matching_item: true,
                 leaves_current_line_empty: true,
                 fire_dispatches_on_change: None,
             },
-            PromptHistoryKey::PipeToShell,
+            PromptHistoryKey::DiffBuffer,
             None,
         )
     }
@@ -2204,6 +6216,41 @@ impl Dispatches {
 /// Dispatch are for child component to request action from the root node
 pub(crate) enum Dispatch {
     SetTheme(crate::themes::Theme),
+    /// Changes the language of the current buffer, which is useful for files without extensions
+    SetLanguage(Language),
+    /// Overrides the detected indentation of the current buffer
+    SetIndentation(Indentation),
+    OpenDiagnosticSeverityPrompt,
+    /// Hides the diagnostics that are less severe than this in every buffer
+    SetMinimumDiagnosticSeverity(Severity),
+    MuteDiagnosticAtCursor,
+    OpenUnmuteDiagnosticsPrompt,
+    /// Shows the diagnostics with this source or code again
+    UnmuteDiagnostics(String),
+    /// Discards the unsaved changes of the buffer of the file
+    ReloadFile(CanonicalizedPath),
+    #[cfg(test)]
+    HandleFileChangedOnDisk(CanonicalizedPath),
+    /// Decides the file of the workspace edit under review
+    ReviewWorkspaceEdit(ReviewDecision),
+    /// Lists the unsaved changes that are left over from the previous sessions
+    OpenRecoveryPrompt,
+    RestoreUnsavedChanges(RecoveryFile),
+    /// Removes the recovery files that are left over from the previous sessions
+    DiscardRecoveryFiles,
+    #[cfg(test)]
+    SetRecovery(Recovery),
+    #[cfg(test)]
+    PersistUnsavedChanges,
+    #[cfg(test)]
+    SetSessionStore(SessionStore),
+    #[cfg(test)]
+    SaveSession,
+    #[cfg(test)]
+    RestoreSession,
+    /// The tests do not render after every dispatch like the event loop does
+    #[cfg(test)]
+    Render,
     CloseCurrentWindow,
     OpenFilePicker(FilePickerKind),
     OpenSearchPrompt {
@@ -2215,6 +6262,12 @@ pub(crate) enum Dispatch {
     ShowGlobalInfo(Info),
     RequestCompletion,
     RequestSignatureHelp,
+    /// Opens the hierarchy of the symbol under the cursor, see `HierarchyView`
+    RequestHierarchy(HierarchyKind),
+    RequestHierarchyChildren {
+        kind: HierarchyKind,
+        item: HierarchyItem,
+    },
     RequestHover,
     RequestDefinitions(Scope),
     RequestDeclarations(Scope),
@@ -2242,7 +6295,11 @@ pub(crate) enum Dispatch {
     },
     SetQuickfixList(QuickfixListType),
     GotoQuickfixListItem(Movement),
-    ApplyWorkspaceEdit(WorkspaceEdit),
+    ApplyWorkspaceEdit {
+        workspace_edit: WorkspaceEdit,
+        /// How the edit is recorded in the refactor log
+        description: String,
+    },
     ShowKeymapLegend(KeymapLegendConfig),
     RemainOnlyCurrentComponent,
 
@@ -2253,12 +6310,46 @@ pub(crate) enum Dispatch {
     RequestDocumentSymbols,
     GotoLocation(Location),
     OpenMoveToIndexPrompt,
+    OpenGoToLinePrompt,
+    /// 0-based
+    PreviewGoToLine(usize),
     RunCommand(String),
     QuitAll,
     OpenCommandPrompt,
+    /// Lists every command and keybinding, where the selected one is executed
+    OpenCommandPalette,
     SaveQuitAll,
     RevealInExplorer(CanonicalizedPath),
     OpenYesNoPrompt(YesNoPrompt),
+    OpenScratchBuffer(String),
+    OpenDroppedFiles(Vec<CanonicalizedPath>),
+    /// Pastes into the current component without checking whether the paste is a file drop
+    PasteIgnoringFileDrop(String),
+    IncrementalSearch {
+        search: String,
+        if_current_not_found: IfCurrentNotFound,
+    },
+    SetHardWrapWidth(Option<usize>),
+    SetDisplayLineMovement(bool),
+    SetShowGitBlame(bool),
+    SetLogicalOrderRendering(bool),
+    SetWrapSelectionOnType(bool),
+    OpenGitBlamePanel,
+    /// Diff the current buffer against the given base
+    OpenDiffView(DiffBase),
+    /// Diffs the primary selection against the copied text
+    OpenClipboardDiffView {
+        use_system_clipboard: bool,
+    },
+    /// Pick another opened buffer to diff the current buffer against
+    OpenDiffBufferPrompt,
+    PreviewFile(PathBuf),
+    CloseBuffer(CanonicalizedPath),
+    OpenTerminal {
+        /// Replace the windows with the terminal instead of opening it in the side panel
+        main_panel: bool,
+    },
+    SaveBuffer(CanonicalizedPath),
     OpenMoveFilePrompt(CanonicalizedPath),
     OpenAddPathPrompt(CanonicalizedPath),
     DeletePath(CanonicalizedPath),
@@ -2267,6 +6358,12 @@ pub(crate) enum Dispatch {
         from: CanonicalizedPath,
         to: PathBuf,
     },
+    /// Previews the replacement of the references to a moved path as a quickfix list,
+    /// which is applied by `Dispatch::ReplaceQuickfixListItems`
+    PreviewReferenceUpdate {
+        reference: String,
+        replacement: String,
+    },
     AddPath(String),
     RefreshFileExplorer,
     SetClipboardContent {
@@ -2278,14 +6375,33 @@ pub(crate) enum Dispatch {
     HandleKeyEvent(event::KeyEvent),
     #[cfg(test)]
     HandleKeyEvents(Vec<event::KeyEvent>),
+    /// Queues the key events before handling them, as if they are typed faster than rendered
+    #[cfg(test)]
+    HandleQueuedKeyEvents(Vec<event::KeyEvent>),
+    #[cfg(test)]
+    HandlePasteEvent(String),
+    #[cfg(test)]
+    HandleImePreeditEvent(String),
+    #[cfg(test)]
+    HandleImeCommitEvent(String),
     GetRepoGitHunks(git::DiffMode),
     SaveAll,
     #[cfg(test)]
     TerminalDimensionChanged(Dimension),
     #[cfg(test)]
     SetGlobalTitle(String),
+    #[cfg(test)]
+    SetBell(Bell),
+    #[cfg(test)]
+    SetKeymapConfig(KeymapConfig),
+    /// Notifies the user that an action is rejected
+    RingBell,
+    /// Renders the frames of the scroll animation that is just started
+    AnimateScroll,
     LspExecuteCommand {
         command: crate::lsp::code_action::Command,
+        /// The LSP server that sent the command, see `LspManager::send_message_to`
+        server: Option<String>,
     },
     UpdateLocalSearchConfig {
         update: LocalSearchConfigUpdate,
@@ -2316,6 +6432,17 @@ pub(crate) enum Dispatch {
     Replace {
         scope: Scope,
     },
+    ShowGlobalReplacePreview,
+    /// Opens the refactorings applied so far as text in a scratch buffer
+    OpenRefactorLog,
+    /// Shows the locations touched by the refactorings applied so far as a quickfix list
+    ReviewRefactorLog,
+    /// Reads the config files again, and applies them to the opened buffers
+    ReloadConfig,
+    #[cfg(test)]
+    SetConfig(Config),
+    ExcludeCurrentQuickfixListItem,
+    ReplaceQuickfixListItems,
     #[cfg(test)]
     HandleLspNotification(LspNotification),
     CloseDropdown,
@@ -2331,6 +6458,8 @@ pub(crate) enum Dispatch {
     ShowEditorInfo(Info),
     ReceiveCodeActions(Vec<crate::lsp::code_action::CodeAction>),
     OtherWindow,
+    /// Adds the current window to the windows that are scrolled together, or removes it
+    ToggleScrollBind,
     CloseCurrentWindowAndFocusParent,
     CloseEditorInfo,
     GoToPreviousFile,
@@ -2340,8 +6469,91 @@ pub(crate) enum Dispatch {
         line: String,
     },
     OpenThemePrompt,
-    ResolveCompletionItem(lsp_types::CompletionItem),
-    OpenPipeToShellPrompt,
+    OpenSetLanguagePrompt,
+    OpenSetIndentationPrompt,
+    OpenEditMacroPrompt,
+    /// Opens the macro as text in a scratch buffer, which is written back by `WriteMacro`
+    EditMacro(MacroName),
+    WriteMacro,
+    OpenSaveMacroPrompt,
+    /// Saves the content of the current macro buffer as the given macro
+    SaveMacro(MacroName),
+    OpenRunMacroPrompt,
+    RunMacro {
+        name: MacroName,
+        repeat: usize,
+        /// When true, the macro is run at each selection separately, as if it were the only one
+        each_selection: bool,
+    },
+    /// Records the typed keys into the register until `StopMacroRecording`
+    StartMacroRecording(char),
+    StopMacroRecording,
+    /// Marks the primary cursor, where uppercase names are global marks, see `named_mark`
+    SetNamedMark(char),
+    GoToNamedMark(char),
+    DeleteNamedMark(char),
+    /// Lists the local marks of the current buffer and the global marks
+    OpenNamedMarkPicker,
+    ResolveCompletionItem(CompletionItem),
+    OpenPipeToShellPrompt(PipeToShellOutput),
+    PipeToShell {
+        command: String,
+        output: PipeToShellOutput,
+    },
+    OpenWriteSelectionsPrompt {
+        append: bool,
+    },
+    WriteSelections {
+        path: PathBuf,
+        append: bool,
+    },
+    OpenInsertBelowCursorPrompt,
+    OpenSurroundWithTagPrompt,
+    OpenAlignPrompt,
+    OpenIncrementPrompt,
+    /// For the hunks against the revision that is entered
+    OpenGitHunkRefPrompt {
+        scope: Scope,
+        if_current_not_found: IfCurrentNotFound,
+    },
+    /// For the misspelled word under the cursor, or the first one after the cursor on its line
+    OpenSpellingSuggestionsPrompt,
+    /// Of the current markdown file, beside it
+    OpenMarkdownPreview,
+    AddToDictionary(String),
+    OpenEncryptedFile {
+        path: CanonicalizedPath,
+        passphrase: Passphrase,
+    },
+    /// A path, or a shell command prefixed with `!`
+    InsertBelowCursor(String),
+    OpenBuildPrompt,
+    /// Of the LSP servers of the current file
+    ShowLspLogs,
+    /// Of the current file, see `LspManager::restart`
+    RestartLspServers,
+    /// Runs the shell command as the shell task named `build`, see `shell_task`
+    Build(String),
+    /// Lists the tasks configured in `[tasks]` of the config
+    OpenShellTaskPrompt,
+    RunShellTask(ShellTask),
+    RerunShellTask,
+    /// Lists the debug configurations of `[debug]` of the config
+    OpenDebugPrompt,
+    /// Starts the debug configuration of the name, see `dap::session`
+    StartDebugging(String),
+    /// At the line of the cursor
+    ToggleBreakpoint,
+    Debug(DebugCommand),
+    OpenDebugWatchPrompt,
+    AddDebugWatch(String),
+    /// Calls the callback of the prompt or picker of a plugin, see `plugin`
+    AnswerPlugin {
+        callback_id: usize,
+        answer: String,
+    },
+    #[cfg(test)]
+    LoadPlugins(PathBuf),
     SetLastNonContiguousSelectionMode(Either<SelectionMode, GlobalMode>),
     UseLastNonContiguousSelectionMode(IfCurrentNotFound),
     SetLastActionDescription(String),
@@ -2385,11 +6597,120 @@ impl FilePickerKind {
         match self {
             FilePickerKind::NonGitIgnored => "Not Git Ignored".to_string(),
             FilePickerKind::GitStatus(diff_mode) => format!("Git Status ({})", diff_mode.display()),
-            FilePickerKind::Opened => "Opened".to_string(),
+            FilePickerKind::Opened => "Opened (alt+x close, alt+s save)".to_string(),
         }
     }
 }
 
+fn global_search_locations(
+    walk_builder_config: WalkBuilderConfig,
+    config: &LocalSearchConfig,
+) -> anyhow::Result<Vec<Location>> {
+    match config.mode {
+        LocalSearchConfigMode::Regex(regex) => {
+            list::grep::run(&config.search(), walk_builder_config, regex)
+        }
+        LocalSearchConfigMode::AstGrep => {
+            list::ast_grep::run(config.search().clone(), walk_builder_config)
+        }
+        LocalSearchConfigMode::NamingConventionAgnostic => {
+            list::naming_convention_agnostic::run(config.search().clone(), walk_builder_config)
+        }
+    }
+}
+
+fn global_replace_preview_items(
+    walk_builder_config: WalkBuilderConfig,
+    config: &LocalSearchConfig,
+) -> anyhow::Result<Vec<QuickfixListItem>> {
+    Ok(global_search_locations(walk_builder_config, config)?
+        .into_iter()
+        .sorted()
+        .group_by(|location| location.path.clone())
+        .into_iter()
+        .map(
+            |(path, locations)| -> anyhow::Result<Vec<QuickfixListItem>> {
+                let content = Rope::from_str(&path.read()?);
+                Ok(locations
+                    .map(|location| {
+                        let info = if location.range.start.line == location.range.end.line {
+                            content
+                                .get_line(location.range.start.line)
+                                .and_then(|line| {
+                                    list::grep::replace_preview(
+                                        line.to_string().trim_end_matches('\n'),
+                                        location.range.start.column..location.range.end.column,
+                                        config,
+                                    )
+                                    .ok()
+                                    .flatten()
+                                })
+                        } else {
+                            None
+                        };
+                        QuickfixListItem::new(location, info)
+                    })
+                    .collect_vec())
+            },
+        )
+        .collect::<anyhow::Result<Vec<_>>>()?
+        .into_iter()
+        .flatten()
+        .collect_vec())
+}
+
+fn replace_description(config: &LocalSearchConfig) -> String {
+    format!(
+        "Replace {:?} with {:?} ({})",
+        config.search(),
+        config.replacement(),
+        config.mode.display()
+    )
+}
+
+fn is_large_file(path: &CanonicalizedPath, threshold: u64) -> bool {
+    std::fs::metadata(path).is_ok_and(|metadata| metadata.len() > threshold)
+}
+
+fn write_to_file(path: &Path, content: &str, append: bool) -> anyhow::Result<()> {
+    use std::io::Write;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(append)
+        .truncate(!append)
+        .open(path)?
+        .write_all(content.as_bytes())?;
+    Ok(())
+}
+
+/// The actions of an item of the buffer list, which reopens the buffer list afterwards
+/// so that the user can manage multiple buffers in succession.
+fn buffer_list_actions(path: &Path) -> Vec<(event::KeyEvent, Dispatches)> {
+    let Ok(path) = CanonicalizedPath::try_from(path) else {
+        return Vec::new();
+    };
+    [
+        (key!("alt+x"), Dispatch::CloseBuffer(path.clone())),
+        (key!("alt+s"), Dispatch::SaveBuffer(path)),
+    ]
+    .into_iter()
+    .map(|(key, dispatch)| {
+        (
+            key,
+            Dispatches::new(vec![
+                Dispatch::CloseCurrentWindow,
+                dispatch,
+                Dispatch::OpenFilePicker(FilePickerKind::Opened),
+            ]),
+        )
+    })
+    .collect()
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub(crate) struct RequestParams {
     pub(crate) path: CanonicalizedPath,
@@ -2433,6 +6754,110 @@ pub(crate) enum AppMessage {
         component_id: ComponentId,
         highlighted_spans: HighlighedSpans,
     },
+    TerminalOutput(Vec<u8>),
+    /// Sent once the shell of the terminal of the component exits
+    TerminalExited(ComponentId),
+    PipeToShellFinished(PipeToShellFinished),
+    GlobalSearchFinished(Result<Vec<Location>, String>),
+    /// Sent once the files of the file picker of the component are walked
+    FilePickerFilesFound {
+        component_id: ComponentId,
+        result: Result<Vec<PathBuf>, String>,
+    },
+    GlobalReplacePreviewFinished(Result<Vec<QuickfixListItem>, String>),
+    WriteSelectionsFinished {
+        path: PathBuf,
+        append: bool,
+        result: Result<(), String>,
+    },
+    InsertBelowCursorFinished {
+        component_id: ComponentId,
+        source: String,
+        result: Result<String, String>,
+    },
+    /// A line of the output of the shell task of `id`, where stderr is merged into stdout
+    ShellTaskOutput {
+        id: usize,
+        line: String,
+    },
+    /// Sent once the output of the shell task of `id` is closed
+    ShellTaskExited {
+        id: usize,
+    },
+    DebugAdapterMessage {
+        session_id: usize,
+        message: serde_json::Value,
+    },
+    /// Sent once the stdout of the debug adapter of the session is closed
+    DebugAdapterExited {
+        session_id: usize,
+    },
+    GitBlameFinished {
+        path: CanonicalizedPath,
+        /// The content that is blamed, which might be outdated by the time it is received
+        content: String,
+        open_panel: bool,
+        result: Result<Vec<BlameLine>, String>,
+    },
+    BellFlashFinished,
+    /// Sent every `SMOOTH_SCROLL_FRAME_INTERVAL` while a scroll animation is running
+    ScrollAnimationTicked,
+    /// Sent when a screen is rendered progressively, to render the next frame
+    RenderRemainingCells,
+    /// Sent once `MARKDOWN_PREVIEW_DEBOUNCE` has passed since the edit of `id`
+    MarkdownPreviewRefreshDue {
+        id: usize,
+    },
+    /// Sent once `Config::document_highlight_delay` has passed since the cursor of `id` moved
+    DocumentHighlightDue {
+        id: usize,
+    },
+    /// Sent once `KEY_SEQUENCE_TIMEOUT` has passed since the pending key of `id` was typed
+    KeySequenceTimeout {
+        id: usize,
+    },
+    /// Sent by the file watcher, including when the file is saved by the editor itself
+    FileChangedOnDisk(CanonicalizedPath),
+    /// Sent every `recovery::RECOVERY_INTERVAL`
+    PersistUnsavedChanges,
+    /// Sent by the task of a paste that is longer than `LARGE_PASTE_THRESHOLD`
+    LargePasteDue {
+        component_id: ComponentId,
+        content: String,
+    },
+    /// The message that is returned by the task, which is dropped if the task is cancelled
+    TaskFinished {
+        id: TaskId,
+        message: Box<AppMessage>,
+    },
+    /// Sent every `task::SPINNER_INTERVAL` while tasks are running, to animate the spinner
+    TaskSpinnerTicked,
+    RemoteClientConnected,
+    RemoteRequest {
+        client_id: usize,
+        /// `None` if the request is a notification
+        id: Option<serde_json::Value>,
+        request: RemoteRequest,
+    },
+}
+
+#[derive(Debug)]
+pub(crate) struct PipeToShellFinished {
+    component_id: ComponentId,
+    command: String,
+    /// The selected texts when the command was started
+    inputs: Vec<String>,
+    output: PipeToShellOutput,
+    /// The error is stringified because `anyhow::Error` cannot be sent across threads
+    result: Result<Vec<String>, String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum PipeToShellOutput {
+    /// Each selection is replaced by the output of piping it to the command
+    ReplaceSelections,
+    /// The output is shown in a scratch buffer, and the selections are left untouched
+    ScratchBuffer,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -2442,6 +6867,10 @@ pub(crate) enum DispatchPrompt {
         if_current_not_found: IfCurrentNotFound,
     },
     MoveSelectionByIndex,
+    GoToLine {
+        /// 0-based, used for resolving relative offsets
+        current_line: usize,
+    },
     RenameSymbol,
     UpdateLocalSearchConfigSearch {
         scope: Scope,
@@ -2468,10 +6897,33 @@ pub(crate) enum DispatchPrompt {
     },
     #[cfg(test)]
     SetContent,
-    PipeToShell,
+    PipeToShell(PipeToShellOutput),
+    WriteSelections {
+        append: bool,
+    },
+    InsertBelowCursor,
+    Build,
+    AddDebugWatch,
+    AnswerPlugin {
+        callback_id: usize,
+    },
     FilterSelectionMatchingSearch {
         maintain: bool,
     },
+    /// The text is the content of the open tag, where the first word is the name of the tag
+    SurroundWithTag,
+    /// The text is the pattern to align the selections on
+    Align,
+    /// The text is the amount to increment by
+    Increment,
+    /// The text is the revision that the hunks are against
+    GitHunkAgainstRef {
+        scope: Scope,
+        if_current_not_found: IfCurrentNotFound,
+    },
+    EditMacro,
+    SaveMacro,
+    RunMacro,
 }
 impl DispatchPrompt {
     pub(crate) fn to_dispatches(&self, text: &str) -> anyhow::Result<Dispatches> {
@@ -2492,6 +6944,12 @@ impl DispatchPrompt {
                     [Dispatch::ToEditor(MoveSelection(Movement::Index(index)))].to_vec(),
                 ))
             }
+            DispatchPrompt::GoToLine { current_line } => Ok(Dispatches::one(Dispatch::ToEditor(
+                DispatchEditor::GoToLine {
+                    line: parse_go_to_line(text, current_line)?,
+                    store_history: true,
+                },
+            ))),
             DispatchPrompt::RenameSymbol => Ok(Dispatches::new(vec![Dispatch::RenameSymbol {
                 new_name: text.to_string(),
             }])),
@@ -2561,21 +7019,123 @@ impl DispatchPrompt {
                 [Dispatch::ToEditor(SetContent(text.to_string()))].to_vec(),
             )),
             DispatchPrompt::Null => Ok(Default::default()),
-            DispatchPrompt::PipeToShell => Ok(Dispatches::one(Dispatch::ToEditor(
-                DispatchEditor::PipeToShell {
-                    command: text.to_string(),
-                },
+            DispatchPrompt::PipeToShell(output) => Ok(Dispatches::one(Dispatch::PipeToShell {
+                command: text.to_string(),
+                output,
+            })),
+            DispatchPrompt::WriteSelections { append } => {
+                Ok(Dispatches::one(Dispatch::WriteSelections {
+                    path: text.into(),
+                    append,
+                }))
+            }
+            DispatchPrompt::InsertBelowCursor => Ok(Dispatches::one(Dispatch::InsertBelowCursor(
+                text.to_string(),
             ))),
+            DispatchPrompt::Build => Ok(Dispatches::one(Dispatch::Build(text.to_string()))),
+            DispatchPrompt::AddDebugWatch => {
+                Ok(Dispatches::one(Dispatch::AddDebugWatch(text.to_string())))
+            }
+            DispatchPrompt::AnswerPlugin { callback_id } => {
+                Ok(Dispatches::one(Dispatch::AnswerPlugin {
+                    callback_id,
+                    answer: text.to_string(),
+                }))
+            }
             DispatchPrompt::FilterSelectionMatchingSearch { maintain } => Ok(Dispatches::one(
                 Dispatch::ToEditor(DispatchEditor::FilterSelectionMatchingSearch {
                     maintain,
                     search: text.to_string(),
                 }),
             )),
+            DispatchPrompt::EditMacro => Ok(Dispatches::one(Dispatch::EditMacro(
+                MacroName::parse(text)?,
+            ))),
+            DispatchPrompt::SaveMacro => Ok(Dispatches::one(Dispatch::SaveMacro(
+                MacroName::parse(text)?,
+            ))),
+            DispatchPrompt::RunMacro => {
+                let (name, repeat) = macros::parse_run(text)?;
+                Ok(Dispatches::one(Dispatch::RunMacro {
+                    name,
+                    repeat,
+                    each_selection: false,
+                }))
+            }
+            DispatchPrompt::Align => Ok(Dispatches::one(Dispatch::ToEditor(
+                DispatchEditor::Align(text.to_string()),
+            ))),
+            DispatchPrompt::Increment => {
+                let amount = text
+                    .trim()
+                    .parse()
+                    .map_err(|_| anyhow::anyhow!("Invalid amount: {text:?}"))?;
+                Ok(Dispatches::one(Dispatch::ToEditor(
+                    DispatchEditor::Increment {
+                        amount,
+                        sequential: false,
+                    },
+                )))
+            }
+            DispatchPrompt::GitHunkAgainstRef {
+                scope,
+                if_current_not_found,
+            } => {
+                let diff_mode = git::DiffMode::UnstagedAgainstRef(text.trim().to_string());
+                Ok(Dispatches::one(match scope {
+                    Scope::Global => Dispatch::GetRepoGitHunks(diff_mode),
+                    Scope::Local => Dispatch::ToEditor(SetSelectionMode(
+                        *if_current_not_found,
+                        SelectionMode::GitHunk(diff_mode),
+                    )),
+                }))
+            }
+            DispatchPrompt::SurroundWithTag => {
+                let tag = text.trim();
+                let Some(name) = tag.split_whitespace().next() else {
+                    return Ok(Default::default());
+                };
+                Ok(Dispatches::one(Dispatch::ToEditor(
+                    DispatchEditor::Surround(format!("<{tag}>"), format!("</{name}>")),
+                )))
+            }
+        }
+    }
+
+    /// Dispatches to be fired whenever the current line of the prompt changes.
+    pub(crate) fn to_dispatches_on_change(&self, text: &str) -> Option<Dispatches> {
+        match self {
+            DispatchPrompt::UpdateLocalSearchConfigSearch {
+                scope: Scope::Local,
+                if_current_not_found,
+                ..
+            } if !text.is_empty() => Some(Dispatches::one(Dispatch::IncrementalSearch {
+                search: text.to_string(),
+                if_current_not_found: *if_current_not_found,
+            })),
+            DispatchPrompt::GoToLine { current_line } => parse_go_to_line(text, *current_line)
+                .ok()
+                .map(|line| Dispatches::one(Dispatch::PreviewGoToLine(line))),
+            _ => None,
         }
     }
 }
 
+/// Parses the input of the goto line prompt into a 0-based line index.
+///
+/// The input is either a 1-based line number, or an offset like `+5` or `-5`
+/// relative to `current_line`.
+fn parse_go_to_line(text: &str, current_line: usize) -> anyhow::Result<usize> {
+    let text = text.trim();
+    if let Some(offset) = text.strip_prefix('+') {
+        Ok(current_line.saturating_add(offset.parse()?))
+    } else if let Some(offset) = text.strip_prefix('-') {
+        Ok(current_line.saturating_sub(offset.parse()?))
+    } else {
+        Ok(text.parse::<usize>()?.saturating_sub(1))
+    }
+}
+
 #[derive(PartialEq)]
 enum OpenFileOption {
     Focus,
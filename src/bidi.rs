@@ -0,0 +1,196 @@
+//! A basic implementation of the Unicode Bidirectional Algorithm (UAX #9),
+//! which is only used for rendering, so that the buffer, selections and movements
+//! keep operating on the logical order of the characters.
+//!
+//! The paragraph direction is always left-to-right, because most of the lines are code,
+//! and explicit embeddings, isolates and overrides are not supported.
+
+use itertools::Itertools;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CharClass {
+    LeftToRight,
+    RightToLeft,
+    Digit,
+    Neutral,
+}
+
+impl CharClass {
+    fn of(c: char) -> CharClass {
+        match c as u32 {
+            // Hebrew, Arabic, Syriac, Thaana, NKo, Samaritan, Mandaic and their supplements
+            0x0590..=0x08FF
+            // Hebrew and Arabic presentation forms
+            | 0xFB1D..=0xFDFF
+            | 0xFE70..=0xFEFF => CharClass::RightToLeft,
+            _ if c.is_ascii_digit() => CharClass::Digit,
+            _ if c.is_alphabetic() => CharClass::LeftToRight,
+            _ => CharClass::Neutral,
+        }
+    }
+}
+
+/// The characters of a line in the order they are displayed.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct VisualOrder {
+    /// The line in visual order, where brackets within right-to-left text are mirrored.
+    line: String,
+    /// The `n`-th element is the visual column of the `n`-th character of the logical line.
+    visual_columns: Vec<usize>,
+}
+
+impl VisualOrder {
+    /// Returns None if the line contains no right-to-left characters,
+    /// because its visual order is the same as its logical order.
+    pub(crate) fn new(line: &str) -> Option<VisualOrder> {
+        let content = line.trim_end_matches(['\r', '\n']);
+        let chars = content.chars().collect_vec();
+        let classes = chars.iter().map(|c| CharClass::of(*c)).collect_vec();
+        if !classes.contains(&CharClass::RightToLeft) {
+            return None;
+        }
+        let levels = resolve_levels(&classes);
+
+        // Reverse every maximal run of characters at or above each odd level,
+        // from the highest level to the lowest odd level
+        let mut logical_indices = (0..chars.len()).collect_vec();
+        let max_level = levels.iter().copied().max().unwrap_or_default();
+        for level in (1..=max_level).rev() {
+            let mut start = 0;
+            while start < logical_indices.len() {
+                if levels[logical_indices[start]] < level {
+                    start += 1;
+                    continue;
+                }
+                let end = (start..logical_indices.len())
+                    .find(|index| levels[logical_indices[*index]] < level)
+                    .unwrap_or(logical_indices.len());
+                logical_indices[start..end].reverse();
+                start = end;
+            }
+        }
+
+        let mut visual_columns = vec![0; chars.len()];
+        for (visual_column, logical_index) in logical_indices.iter().enumerate() {
+            visual_columns[*logical_index] = visual_column;
+        }
+        let line = logical_indices
+            .iter()
+            .map(|index| {
+                if levels[*index] % 2 == 1 {
+                    mirror(chars[*index])
+                } else {
+                    chars[*index]
+                }
+            })
+            .chain(line[content.len()..].chars())
+            .collect();
+        Some(VisualOrder {
+            line,
+            visual_columns,
+        })
+    }
+
+    /// Includes the line ending of the original line.
+    pub(crate) fn line(&self) -> &str {
+        &self.line
+    }
+
+    /// Columns beyond the end of the line (e.g. the cursor after the last character) are unchanged.
+    pub(crate) fn visual_column(&self, logical_column: usize) -> usize {
+        self.visual_columns
+            .get(logical_column)
+            .copied()
+            .unwrap_or(logical_column)
+    }
+}
+
+/// Returns the embedding level of each character,
+/// where 0 is left-to-right, 1 is right-to-left, and 2 is a number within right-to-left text.
+fn resolve_levels(classes: &[CharClass]) -> Vec<u8> {
+    // Digits take the direction of the preceding strong character (rule W7),
+    // but they are still displayed left-to-right
+    let mut previous_strong = CharClass::LeftToRight;
+    let resolved = classes
+        .iter()
+        .map(|class| match class {
+            CharClass::LeftToRight | CharClass::RightToLeft => {
+                previous_strong = *class;
+                *class
+            }
+            CharClass::Digit => previous_strong,
+            CharClass::Neutral => CharClass::Neutral,
+        })
+        .collect_vec();
+
+    // Neutrals between two characters of the same direction take that direction,
+    // otherwise they take the paragraph direction (rules N1 and N2)
+    let mut levels = Vec::with_capacity(classes.len());
+    let mut previous = CharClass::LeftToRight;
+    for (index, class) in resolved.iter().enumerate() {
+        let direction = match class {
+            CharClass::Neutral => {
+                let next = resolved[index..]
+                    .iter()
+                    .find(|class| **class != CharClass::Neutral)
+                    .copied()
+                    .unwrap_or(CharClass::LeftToRight);
+                if previous == CharClass::RightToLeft && next == CharClass::RightToLeft {
+                    CharClass::RightToLeft
+                } else {
+                    CharClass::LeftToRight
+                }
+            }
+            _ => {
+                previous = *class;
+                *class
+            }
+        };
+        levels.push(match (direction, classes[index]) {
+            (CharClass::RightToLeft, CharClass::Digit) => 2,
+            (CharClass::RightToLeft, _) => 1,
+            _ => 0,
+        })
+    }
+    levels
+}
+
+fn mirror(c: char) -> char {
+    match c {
+        '(' => ')',
+        ')' => '(',
+        '[' => ']',
+        ']' => '[',
+        '{' => '}',
+        '}' => '{',
+        '<' => '>',
+        '>' => '<',
+        _ => c,
+    }
+}
+
+#[cfg(test)]
+mod test_bidi {
+    use super::VisualOrder;
+
+    #[test]
+    fn left_to_right_line_has_no_visual_order() {
+        assert_eq!(VisualOrder::new("let x = 1;\n"), None)
+    }
+
+    #[test]
+    fn reverse_right_to_left_runs_only() {
+        let order = VisualOrder::new("a שלום b\n").unwrap();
+        assert_eq!(order.line(), "a םולש b\n");
+        assert_eq!(order.visual_column(0), 0);
+        assert_eq!(order.visual_column(2), 5);
+        assert_eq!(order.visual_column(5), 2);
+        assert_eq!(order.visual_column(9), 9);
+    }
+
+    #[test]
+    fn numbers_and_brackets_within_right_to_left_text() {
+        let order = VisualOrder::new("x = אב (12) גד;").unwrap();
+        assert_eq!(order.line(), "x = דג (12) בא;");
+    }
+}
@@ -1,12 +1,18 @@
 use crate::history::History;
-use crate::lsp::diagnostic::Diagnostic;
-use crate::quickfix_list::QuickfixListItem;
+use crate::lsp::diagnostic::{Diagnostic, DiagnosticFilter};
+use crate::quickfix_list::{Location, QuickfixListItem};
 use crate::selection_mode::naming_convention_agnostic::NamingConventionAgnostic;
 use crate::{
     char_index_range::CharIndexRange,
     components::{editor::Movement, suggestive_editor::Decoration},
+    config::Indentation,
     context::{LocalSearchConfig, LocalSearchConfigMode},
-    edit::{Action, ActionGroup, Edit, EditTransaction},
+    edit::{Action, ActionGroup, ApplyOffset, Edit, EditTransaction},
+    editorconfig::{Charset, EditorConfig, EndOfLine},
+    encryption::{Encryption, Passphrase},
+    file_watcher,
+    git::{blame::BlameLine, diff_provider::gutter_base_content, gutter::GutterHunk},
+    named_mark,
     position::Position,
     selection::{CharIndex, Selection, SelectionSet},
     selection_mode::{AstGrep, ByteRange},
@@ -21,8 +27,13 @@ use shared::{
     canonicalized_path::CanonicalizedPath,
     language::{self, Language},
 };
-use std::{collections::HashSet, ops::Range};
-use tree_sitter::{Node, Parser, Tree};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashSet},
+    ops::Range,
+    time::SystemTime,
+};
+use tree_sitter::{InputEdit, Node, Parser, Point, Tree};
 use tree_sitter_traversal::{traverse, Order};
 
 #[derive(Clone)]
@@ -35,12 +46,81 @@ pub(crate) struct Buffer {
     path: Option<CanonicalizedPath>,
     highlighted_spans: HighlighedSpans,
     marks: Vec<CharIndexRange>,
+    /// Includes the global marks of this buffer, see `named_mark`
+    named_marks: BTreeMap<char, CharIndex>,
+    /// The starts of the lines of the breakpoints, which follow the edits like the named marks
+    breakpoints: Vec<CharIndex>,
+    /// Each fold spans whole lines, and is updated by edits like the marks.
+    folds: Vec<CharIndexRange>,
+    /// The closing brackets and quotes that are inserted automatically in insert mode,
+    /// which are skipped over instead of being inserted again when typed right before them.
+    auto_closings: Vec<CharIndexRange>,
+    /// Includes the diagnostics hidden by `diagnostic_filter`
     diagnostics: Vec<Diagnostic>,
+    diagnostic_filter: DiagnosticFilter,
     quickfix_list_items: Vec<QuickfixListItem>,
     decorations: Vec<Decoration>,
     selection_set_history: History<SelectionSet>,
+    /// True if the content is modified since it was last loaded or saved.
+    dirty: bool,
+    /// Defined if the file is encrypted, in which case the content only lives in memory,
+    /// and the file is re-encrypted with the same passphrase on save.
+    encryption: Option<(Encryption, Passphrase)>,
+    /// The content that the git gutter is diffed against,
+    /// which is refreshed whenever the file is loaded or saved.
+    git_gutter_base: Option<String>,
+    /// The hunks of the git gutter and the revision that they are computed for,
+    /// so that they are only diffed again once the content or the base content is changed.
+    git_gutter_hunks: RefCell<Option<(usize, Vec<GutterHunk>)>>,
+    /// The number of words and the revision that it is counted for, see `Buffer::word_count`.
+    word_count: RefCell<Option<(usize, usize)>>,
+    /// The blame of each line and the content that it is computed for,
+    /// which is ignored once the content is modified.
+    git_blame: Option<(String, Vec<BlameLine>)>,
+    /// The indentation of new lines and the Tab key, which is also used by indent and dedent.
+    indentation: Indentation,
+    /// Applied whenever the buffer is read from or written to its file
+    editor_config: EditorConfig,
+    /// The line ending that every line ending is converted to when the buffer is written,
+    /// which is detected from the content unless it is specified by the EditorConfig
+    line_ending: EndOfLine,
+    /// The config setting, which is overridden by the EditorConfig
+    insert_final_newline: bool,
+    /// The config setting, which is overridden by the EditorConfig
+    trim_trailing_whitespace: bool,
+    /// The charset that the file is decoded from and encoded to,
+    /// which is detected unless it is specified by the EditorConfig
+    charset: Charset,
+    /// True if the file contains bytes that are invalid in `charset`,
+    /// which are replaced with U+FFFD and thus lost when the buffer is saved
+    lossy_decoded: bool,
+    /// The modification time of the file when it was last loaded or saved,
+    /// which tells the changes made by other programs apart from those saved by this buffer
+    modified_time: Option<SystemTime>,
+    /// True if the buffer is loaded by `Buffer::from_large_path`
+    large_file: bool,
+    /// The lines of the large file that are last requested to be highlighted,
+    /// which is reset whenever the content is changed
+    highlighted_line_range: Option<Range<usize>>,
+    /// Incremented whenever the content is changed, so that what is computed from the content
+    /// can be cached by it
+    revision: usize,
 }
 
+/// The number of lines above and below the visible lines of a large file that are highlighted
+/// with them, so that the highlighting is not requested again on every scroll.
+const LARGE_FILE_HIGHLIGHT_MARGIN: usize = 200;
+
+/// The line ending of a large file is detected from its first lines only.
+const DETECT_LINE_ENDING_LINES: usize = 1000;
+
+/// The pairs of brackets that are matched by `Buffer::matching_bracket`
+const BRACKETS: [(char, char); 3] = [('(', ')'), ('[', ']'), ('{', '}')];
+
+/// A bracket is not matched by scanning farther than this number of characters,
+/// so that an unbalanced bracket in a huge file does not scan the whole file.
+const MATCHING_BRACKET_SCAN_LIMIT: usize = 100_000;
+
 #[derive(Clone, PartialEq, Eq, Hash, Debug)]
 pub(crate) struct Line {
     origin_position: Position,
@@ -67,11 +147,33 @@ impl Buffer {
             path: None,
             highlighted_spans: HighlighedSpans::default(),
             marks: Vec::new(),
+            named_marks: BTreeMap::new(),
+            breakpoints: Vec::new(),
+            folds: Vec::new(),
+            auto_closings: Vec::new(),
             decorations: Vec::new(),
             undo_tree: UndoTree::new(),
             diagnostics: Vec::new(),
+            diagnostic_filter: DiagnosticFilter::default(),
             quickfix_list_items: Vec::new(),
             selection_set_history: History::new(),
+            dirty: false,
+            encryption: None,
+            git_gutter_base: None,
+            git_gutter_hunks: RefCell::new(None),
+            word_count: RefCell::new(None),
+            git_blame: None,
+            indentation: Indentation::default(),
+            editor_config: EditorConfig::default(),
+            line_ending: EndOfLine::Lf,
+            insert_final_newline: false,
+            trim_trailing_whitespace: false,
+            revision: 0,
+            charset: Charset::Utf8,
+            lossy_decoded: false,
+            modified_time: None,
+            large_file: false,
+            highlighted_line_range: None,
         }
     }
     pub(crate) fn clear_quickfix_list_items(&mut self) {
@@ -83,11 +185,32 @@ impl Buffer {
     ) {
         self.quickfix_list_items = quickfix_list_items
     }
+    pub(crate) fn toggle_quickfix_list_item_excluded(&mut self, location: &Location) {
+        self.quickfix_list_items
+            .iter_mut()
+            .filter(|item| item.location() == location)
+            .for_each(|item| item.toggle_excluded())
+    }
     pub(crate) fn reload(&mut self) -> anyhow::Result<()> {
         if let Some(path) = self.path() {
-            let updated_content = path.read()?;
+            let updated_content = match &self.encryption {
+                Some((encryption, passphrase)) => {
+                    let (content, lossy_decoded) =
+                        self.charset.decode(&encryption.decrypt(&path, passphrase)?);
+                    self.lossy_decoded = lossy_decoded;
+                    content
+                }
+                None => {
+                    let (content, lossy_decoded) = self.charset.decode(&std::fs::read(&path)?);
+                    self.lossy_decoded = lossy_decoded;
+                    content
+                }
+            };
 
             self.update_content(&updated_content, SelectionSet::default())?;
+            self.dirty = false;
+            self.refresh_git_gutter_base();
+            self.modified_time = file_watcher::modified_time(&path);
         }
         Ok(())
     }
@@ -95,6 +218,197 @@ impl Buffer {
         self.rope.to_string()
     }
 
+    pub(crate) fn dirty(&self) -> bool {
+        self.dirty
+    }
+
+    pub(crate) fn revision(&self) -> usize {
+        self.revision
+    }
+
+    /// True if the file is modified or deleted by another program since it was last loaded
+    /// or saved.
+    pub(crate) fn changed_on_disk(&self) -> bool {
+        self.path
+            .as_ref()
+            .is_some_and(|path| file_watcher::modified_time(path) != self.modified_time)
+    }
+
+    pub(crate) fn editor_config(&self) -> &EditorConfig {
+        &self.editor_config
+    }
+
+    pub(crate) fn indentation(&self) -> Indentation {
+        self.indentation
+    }
+
+    pub(crate) fn set_indentation(&mut self, indentation: Indentation) {
+        self.indentation = indentation
+    }
+
+    /// Sets the indentation detected from the content, unless it is specified by the EditorConfig.
+    /// The settings that are neither detected nor specified are taken from `default`.
+    pub(crate) fn reset_indentation(&mut self, default: Indentation) {
+        let detected = Indentation::detect(&self.content(), default);
+        self.indentation = self.editor_config.indentation(detected)
+    }
+
+    pub(crate) fn charset(&self) -> Charset {
+        self.charset
+    }
+
+    /// The warning to be shown when the file is opened, if its content could not be decoded.
+    pub(crate) fn decode_warning(&self) -> Option<String> {
+        self.lossy_decoded.then(|| {
+            format!(
+                "The file contains bytes that are invalid in {}, which are replaced with U+FFFD. \
+                 They will be lost if the file is saved.",
+                self.charset.display()
+            )
+        })
+    }
+
+    pub(crate) fn line_ending(&self) -> EndOfLine {
+        self.line_ending
+    }
+
+    /// Converts the line endings of the content, so that the buffer is modified
+    /// even if the content is written with the new line ending anyway.
+    pub(crate) fn set_line_ending(
+        &mut self,
+        line_ending: EndOfLine,
+        current_selection_set: SelectionSet,
+    ) -> anyhow::Result<()> {
+        self.line_ending = line_ending;
+        let content = self.content();
+        let converted = line_ending.convert(&content);
+        if converted != content {
+            self.update_content(&converted, current_selection_set)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn set_insert_final_newline(&mut self, insert_final_newline: bool) {
+        self.insert_final_newline = insert_final_newline
+    }
+
+    pub(crate) fn set_trim_trailing_whitespace(&mut self, trim_trailing_whitespace: bool) {
+        self.trim_trailing_whitespace = trim_trailing_whitespace
+    }
+
+    /// The base content is not loaded for encrypted files,
+    /// because it is the ciphertext, which cannot be diffed.
+    fn refresh_git_gutter_base(&mut self) {
+        let base = match (&self.path, &self.encryption) {
+            (Some(path), None) if !self.large_file => gutter_base_content(path),
+            _ => None,
+        };
+        self.set_git_gutter_base(base)
+    }
+
+    fn set_git_gutter_base(&mut self, base: Option<String>) {
+        self.git_gutter_base = base;
+        *self.git_gutter_hunks.borrow_mut() = None;
+    }
+
+    pub(crate) fn git_gutter_hunks(&self) -> Vec<GutterHunk> {
+        if let Some((revision, hunks)) = self.git_gutter_hunks.borrow().as_ref() {
+            if revision == &self.revision {
+                return hunks.clone();
+            }
+        }
+        let hunks = self
+            .git_gutter_base
+            .as_ref()
+            .map(|base| GutterHunk::get(base, &self.content()))
+            .unwrap_or_default();
+        *self.git_gutter_hunks.borrow_mut() = Some((self.revision, hunks.clone()));
+        hunks
+    }
+
+    /// The words are separated by whitespaces, and are only counted again once the content
+    /// is changed.
+    pub(crate) fn word_count(&self) -> usize {
+        if let Some((revision, count)) = *self.word_count.borrow() {
+            if revision == self.revision {
+                return count;
+            }
+        }
+        let count = self.content().split_whitespace().count();
+        *self.word_count.borrow_mut() = Some((self.revision, count));
+        count
+    }
+
+    pub(crate) fn is_encrypted(&self) -> bool {
+        self.encryption.is_some()
+    }
+
+    pub(crate) fn is_large_file(&self) -> bool {
+        self.large_file
+    }
+
+    pub(crate) fn reset_highlighted_line_range(&mut self) {
+        self.highlighted_line_range = None
+    }
+
+    /// Returns the language, the source code and its byte offset that should be highlighted,
+    /// or `None` if the visible lines of this large file are highlighted already.
+    /// The lines around the visible lines are highlighted as well, so that the highlighting
+    /// is not requested again until the visible lines go beyond them.
+    pub(crate) fn large_file_highlight_request(
+        &mut self,
+        visible_line_range: Range<usize>,
+    ) -> Option<(Language, String, usize)> {
+        if !self.large_file {
+            return None;
+        }
+        let language = self.language.clone()?;
+        if self
+            .highlighted_line_range
+            .as_ref()
+            .is_some_and(|highlighted| {
+                highlighted.start <= visible_line_range.start
+                    && visible_line_range.end <= highlighted.end
+            })
+        {
+            return None;
+        }
+        let start = visible_line_range
+            .start
+            .saturating_sub(LARGE_FILE_HIGHLIGHT_MARGIN);
+        let end = (visible_line_range.end + LARGE_FILE_HIGHLIGHT_MARGIN).min(self.len_lines());
+        let byte_range = self.line_range_to_byte_range(&(start..end)).ok()?;
+        self.highlighted_line_range = Some(start..end);
+        Some((
+            language,
+            self.rope.byte_slice(byte_range.clone()).to_string(),
+            byte_range.start,
+        ))
+    }
+
+    /// Returns true if the blame is not computed for the current content yet.
+    /// Encrypted files are never blamed, because their history only contains the ciphertext.
+    pub(crate) fn git_blame_is_stale(&self) -> bool {
+        self.path.is_some()
+            && !self.is_encrypted()
+            && self
+                .git_blame
+                .as_ref()
+                .map(|(content, _)| *content != self.content())
+                .unwrap_or(true)
+    }
+
+    pub(crate) fn git_blame(&self) -> Option<&[BlameLine]> {
+        self.git_blame
+            .as_ref()
+            .filter(|(content, _)| *content == self.content())
+            .map(|(_, lines)| lines.as_slice())
+    }
+
+    pub(crate) fn set_git_blame(&mut self, content: String, lines: Vec<BlameLine>) {
+        self.git_blame = Some((content, lines))
+    }
+
     pub(crate) fn decorations(&self) -> &Vec<Decoration> {
         &self.decorations
     }
@@ -131,8 +445,33 @@ impl Buffer {
             .collect()
     }
 
+    /// The diagnostics that are not hidden by the diagnostic filter.
     pub(crate) fn diagnostics(&self) -> Vec<Diagnostic> {
-        self.diagnostics.clone()
+        self.diagnostics
+            .iter()
+            .filter(|diagnostic| self.diagnostic_filter.shows(diagnostic))
+            .cloned()
+            .collect()
+    }
+
+    /// The diagnostics that are hidden by the diagnostic filter.
+    pub(crate) fn hidden_diagnostics(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics
+            .iter()
+            .filter(|diagnostic| !self.diagnostic_filter.shows(diagnostic))
+    }
+
+    /// All diagnostics that contain the char index, including the hidden ones.
+    pub(crate) fn diagnostics_at(&self, char_index: CharIndex) -> Vec<Diagnostic> {
+        self.diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.range.contains(&char_index))
+            .cloned()
+            .collect()
+    }
+
+    pub(crate) fn set_diagnostic_filter(&mut self, diagnostic_filter: DiagnosticFilter) {
+        self.diagnostic_filter = diagnostic_filter
     }
 
     pub(crate) fn words(&self) -> Vec<String> {
@@ -252,11 +591,28 @@ impl Buffer {
             .unwrap_or(false)
     }
 
+    /// The given spans must be sorted by range
     pub(crate) fn update_highlighted_spans(&mut self, spans: HighlighedSpans) {
+        debug_assert!(
+            spans
+                .0
+                .iter()
+                .enumerate()
+                .sorted_by_key(|(_, span)| (span.byte_range.start, span.byte_range.end))
+                .map(|(index, _)| index)
+                .collect_vec()
+                == (0..spans.0.len()).collect_vec(),
+        );
         self.highlighted_spans = spans;
     }
 
+    /// The folds and auto closings are cleared,
+    /// because they cannot be mapped to the replaced content.
     pub(crate) fn update(&mut self, text: &str) {
+        self.dirty = true;
+        self.revision += 1;
+        self.folds.clear();
+        self.auto_closings.clear();
         (self.rope, self.tree) = Self::get_rope_and_tree(self.treesitter_language.clone(), text);
     }
 
@@ -438,6 +794,22 @@ impl Buffer {
         edit_transaction: &EditTransaction,
         current_selection_set: SelectionSet,
         reparse_tree: bool,
+    ) -> Result<SelectionSet, anyhow::Error> {
+        self.apply_reverting_edit_transaction(
+            edit_transaction,
+            current_selection_set,
+            reparse_tree,
+            None,
+        )
+    }
+
+    /// `reverts` is the index of the undo history entry that is reverted by this transaction.
+    fn apply_reverting_edit_transaction(
+        &mut self,
+        edit_transaction: &EditTransaction,
+        current_selection_set: SelectionSet,
+        reparse_tree: bool,
+        reverts: Option<usize>,
     ) -> Result<SelectionSet, anyhow::Error> {
         let before = self.rope.to_string();
         let new_selection_set = edit_transaction
@@ -449,17 +821,33 @@ impl Buffer {
             marks: self.marks.clone(),
         };
 
-        edit_transaction
+        let patch_edits = edit_transaction
             .edits()
             .into_iter()
-            .try_fold((), |_, edit| self.apply_edit(edit))?;
+            .map(|edit| {
+                let old = self.slice(&edit.range)?;
+                self.apply_edit(edit)?;
+                Ok(PatchEdit {
+                    range: edit.range,
+                    old,
+                    new: edit.new.clone(),
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
 
         let new_buffer_state = BufferState {
             selection_set: new_selection_set.clone(),
             marks: self.marks.clone(),
         };
 
-        self.add_undo_patch(current_buffer_state, new_buffer_state.clone(), &before);
+        self.add_undo_patch(
+            current_buffer_state,
+            new_buffer_state.clone(),
+            &before,
+            patch_edits,
+            reverts,
+        );
+        self.dirty = true;
         if reparse_tree {
             self.reparse_tree()?;
         }
@@ -481,11 +869,31 @@ impl Buffer {
                 })
                 .collect_vec();
 
+        let start_byte = self.rope.try_char_to_byte(edit.range.start.0)?;
+        let old_end_byte = self.rope.try_char_to_byte(edit.end().0)?;
+        let start_position = self.byte_to_point(start_byte)?;
+        let old_end_position = self.byte_to_point(old_end_byte)?;
+
         // Update the content
+        self.revision += 1;
         self.rope.try_remove(edit.range.start.0..edit.end().0)?;
         self.rope
             .try_insert(edit.range.start.0, edit.new.to_string().as_str())?;
 
+        // Let the syntax tree know about the edit, so that it can be reparsed incrementally
+        let new_end_byte = start_byte + edit.new.len_bytes();
+        let new_end_position = self.byte_to_point(new_end_byte)?;
+        if let Some(tree) = self.tree.as_mut() {
+            tree.edit(&InputEdit {
+                start_byte,
+                old_end_byte,
+                new_end_byte,
+                start_position,
+                old_end_position,
+                new_end_position,
+            })
+        }
+
         // Update all the positional spans (by using the char index ranges computed before the content is updated
         self.quickfix_list_items = quickfix_list_items_with_char_index_range
             .into_iter()
@@ -502,6 +910,20 @@ impl Buffer {
             .into_iter()
             .filter_map(|mark| mark.apply_edit(edit))
             .collect();
+        for char_index in self.named_marks.values_mut() {
+            *char_index = named_mark::apply_edit(*char_index, edit)
+        }
+        for char_index in self.breakpoints.iter_mut() {
+            *char_index = named_mark::apply_edit(*char_index, edit)
+        }
+        self.folds = std::mem::take(&mut self.folds)
+            .into_iter()
+            .filter_map(|fold| fold.apply_edit(edit))
+            .collect();
+        self.auto_closings = std::mem::take(&mut self.auto_closings)
+            .into_iter()
+            .filter_map(|auto_closing| auto_closing.apply_edit(edit))
+            .collect();
         self.diagnostics = std::mem::take(&mut self.diagnostics)
             .into_iter()
             .filter_map(|diagnostic| {
@@ -529,6 +951,8 @@ impl Buffer {
         old_buffer_state: BufferState,
         new_buffer_state: BufferState,
         before: &str,
+        edits: Vec<PatchEdit>,
+        reverts: Option<usize>,
     ) {
         let after = &self.rope.to_string();
         if before == after {
@@ -538,10 +962,14 @@ impl Buffer {
             old_to_new: Patch {
                 patch: diffy::create_patch(before, after).to_string(),
                 state: new_buffer_state,
+                edits,
+                reverts,
             },
             new_to_old: Patch {
                 patch: diffy::create_patch(after, before).to_string(),
                 state: old_buffer_state,
+                edits: Vec::new(),
+                reverts: None,
             },
         };
         self.undo_tree
@@ -574,6 +1002,87 @@ impl Buffer {
         }
     }
 
+    /// Reverts the most recent change that intersects `range`, while keeping the later changes,
+    /// which must not overlap with the reverted change.
+    ///
+    /// Changes that are already reverted this way are skipped, along with their reverts.
+    pub(crate) fn undo_within(
+        &mut self,
+        range: CharIndexRange,
+        current_selection_set: SelectionSet,
+    ) -> anyhow::Result<SelectionSet> {
+        let patches = self
+            .undo_tree
+            .applied_edits()
+            .map(|(index, old_new)| (index, &old_new.old_to_new))
+            .collect_vec();
+        let mut skipped = HashSet::new();
+        let mut range = range;
+        let Some(position) = patches.iter().position(|(index, patch)| {
+            if let Some(reverted) = patch.reverts {
+                skipped.insert(reverted);
+            }
+            let mut intersects = false;
+            for edit in patch.edits.iter().rev() {
+                let (previous, overlaps) = map_range_expanding(range, &edit.backward());
+                range = previous;
+                intersects |= overlaps
+            }
+            intersects && patch.reverts.is_none() && !skipped.contains(index)
+        }) else {
+            return Err(anyhow::anyhow!(
+                "There is no change within the selection to undo"
+            ));
+        };
+        let (index, patch) = patches[position];
+        let later_edits = patches[..position]
+            .iter()
+            .rev()
+            .flat_map(|(_, patch)| patch.edits.iter().map(PatchEdit::forward))
+            .collect_vec();
+        let primary_selection = current_selection_set.primary_selection();
+        let action_groups = patch
+            .edits
+            .iter()
+            .enumerate()
+            .map(|(edit_index, edit)| {
+                let range = patch.edits[edit_index + 1..]
+                    .iter()
+                    .map(PatchEdit::forward)
+                    .chain(later_edits.iter().cloned())
+                    .try_fold(edit.backward().range, |range, later_edit| {
+                        shift_range(range, &later_edit)
+                    })
+                    .ok_or_else(|| {
+                        anyhow::anyhow!("The change within the selection overlaps a later change")
+                    })?;
+                if self.slice(&range)? != edit.new {
+                    return Err(anyhow::anyhow!(
+                        "The change within the selection is modified outside of the undo history"
+                    ));
+                }
+                let restored_range = (range.start..range.start + edit.old.len_chars()).into();
+                Ok(ActionGroup::new(
+                    [
+                        Action::Edit(Edit {
+                            range,
+                            new: edit.old.clone(),
+                        }),
+                        Action::Select(primary_selection.clone().set_range(restored_range)),
+                    ]
+                    .to_vec(),
+                ))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let edit_transaction = EditTransaction::from_action_groups(action_groups);
+        self.apply_reverting_edit_transaction(
+            &edit_transaction,
+            current_selection_set,
+            true,
+            Some(index),
+        )
+    }
+
     pub(crate) fn has_syntax_error_at(&self, range: CharIndexRange) -> bool {
         let rope = &self.rope;
         if let Some(node) = self.tree.as_ref().and_then(|tree| {
@@ -588,11 +1097,74 @@ impl Buffer {
         }
     }
 
+    /// Returns the bracket that matches the bracket at `char_index`, or `None` if the character
+    /// is not a bracket or it is unbalanced.
+    ///
+    /// Brackets that delimit a syntax node are matched by the node, so that the brackets in
+    /// the strings and comments within are skipped, otherwise the characters are scanned.
+    pub(crate) fn matching_bracket(&self, char_index: CharIndex) -> Option<CharIndex> {
+        let character = self.rope.get_char(char_index.0)?;
+        let (open, close) = BRACKETS
+            .into_iter()
+            .find(|(open, close)| character == *open || character == *close)?;
+        let forward = character == open;
+        let byte = self.char_to_byte(char_index).ok()?;
+        let token = self.tree.as_ref().and_then(|tree| {
+            tree.root_node()
+                .descendant_for_byte_range(byte, byte + character.len_utf8())
+                .filter(|node| node.child_count() == 0 && node.start_byte() == byte)
+        });
+        if let Some(parent) = token.and_then(|token| token.parent()) {
+            let first = parent.child(0);
+            let last = parent.child(parent.child_count().saturating_sub(1));
+            let (delimiter, other, expected) = if forward {
+                (first, last, close)
+            } else {
+                (last, first, open)
+            };
+            if let Some((delimiter, other)) = delimiter.zip(other) {
+                let other_char_index = self.byte_to_char(other.start_byte()).ok()?;
+                if delimiter.start_byte() == byte
+                    && other.start_byte() != byte
+                    && self.rope.get_char(other_char_index.0) == Some(expected)
+                {
+                    return Some(other_char_index);
+                }
+            }
+        }
+        let (step_in, step_out) = if forward {
+            (open, close)
+        } else {
+            (close, open)
+        };
+        let mut chars = self.rope.chars_at(char_index.0 + usize::from(forward));
+        let mut depth = 0;
+        for offset in 0..MATCHING_BRACKET_SCAN_LIMIT {
+            let current = if forward { chars.next() } else { chars.prev() };
+            match current? {
+                c if c == step_in => depth += 1,
+                c if c == step_out && depth == 0 => {
+                    return Some(if forward {
+                        char_index + (offset + 1)
+                    } else {
+                        char_index - (offset + 1)
+                    })
+                }
+                c if c == step_out => depth -= 1,
+                _ => {}
+            }
+        }
+        None
+    }
+
     pub(crate) fn from_path(
         path: &CanonicalizedPath,
         enable_tree_sitter: bool,
     ) -> anyhow::Result<Buffer> {
-        let content = path.read()?;
+        let editor_config = EditorConfig::for_path(path.to_path_buf())?;
+        let bytes = std::fs::read(path)?;
+        let charset = editor_config.charset(&bytes);
+        let (content, lossy_decoded) = charset.decode(&bytes);
         let language = if enable_tree_sitter {
             language::from_path(path)
         } else {
@@ -608,20 +1180,118 @@ impl Buffer {
 
         buffer.path = Some(path.clone());
         buffer.language = language;
+        buffer.line_ending = editor_config
+            .end_of_line
+            .or_else(|| EndOfLine::detect(&content))
+            .unwrap_or(EndOfLine::Lf);
+        buffer.editor_config = editor_config;
+        buffer.charset = charset;
+        buffer.lossy_decoded = lossy_decoded;
+        buffer.refresh_git_gutter_base();
+        buffer.modified_time = file_watcher::modified_time(path);
+
+        Ok(buffer)
+    }
 
+    /// The large file mode, where the file is read into the rope chunk by chunk instead of being
+    /// decoded as a whole, and Tree-sitter, the git gutter, the formatter and the LSP servers
+    /// are disabled. Only the lines around the visible lines are highlighted (see
+    /// `Buffer::large_file_highlight_request`).
+    ///
+    /// Only UTF-8 can be read chunk by chunk, so the files in other charsets are read as usual,
+    /// except that Tree-sitter and the language are disabled.
+    pub(crate) fn from_large_path(path: &CanonicalizedPath) -> anyhow::Result<Buffer> {
+        let editor_config = EditorConfig::for_path(path.to_path_buf())?;
+        let rope = match editor_config.charset {
+            None | Some(Charset::Utf8) => {
+                match Rope::from_reader(std::io::BufReader::new(std::fs::File::open(path)?)) {
+                    Ok(rope) => Some(rope),
+                    Err(error) if error.kind() == std::io::ErrorKind::InvalidData => None,
+                    Err(error) => return Err(error.into()),
+                }
+            }
+            Some(_) => None,
+        };
+        let Some(rope) = rope else {
+            let mut buffer = Buffer::from_path(path, false)?;
+            buffer.large_file = true;
+            buffer.set_git_gutter_base(None);
+            return Ok(buffer);
+        };
+        let mut buffer = Buffer::new(None, "");
+        buffer.line_ending = editor_config
+            .end_of_line
+            .or_else(|| {
+                let sample_end = rope.line_to_char(rope.len_lines().min(DETECT_LINE_ENDING_LINES));
+                EndOfLine::detect(&rope.slice(..sample_end).to_string())
+            })
+            .unwrap_or(EndOfLine::Lf);
+        buffer.rope = rope;
+        buffer.path = Some(path.clone());
+        buffer.language = language::from_path(path);
+        buffer.editor_config = editor_config;
+        buffer.large_file = true;
+        buffer.modified_time = file_watcher::modified_time(path);
         Ok(buffer)
     }
 
+    /// Tree-sitter and the language (hence the LSP servers and the formatter) are disabled,
+    /// so that the decrypted content is not sent to any other process.
+    ///
+    /// The charset is detected from the decrypted content.
+    pub(crate) fn from_encrypted_path(
+        path: &CanonicalizedPath,
+        encryption: Encryption,
+        passphrase: Passphrase,
+    ) -> anyhow::Result<Buffer> {
+        let bytes = encryption.decrypt(path, &passphrase)?;
+        let charset = Charset::detect(&bytes);
+        let (content, lossy_decoded) = charset.decode(&bytes);
+        let mut buffer = Buffer::new(None, &content);
+        buffer.charset = charset;
+        buffer.lossy_decoded = lossy_decoded;
+        buffer.path = Some(path.clone());
+        buffer.encryption = Some((encryption, passphrase));
+        buffer.modified_time = file_watcher::modified_time(path);
+        Ok(buffer)
+    }
+
+    /// The parsing is incremental, because every edit of the rope is also applied to the tree
+    /// (see `Buffer::apply_edit`), so only the edited nodes are reparsed.
+    ///
+    /// The rope is read chunk by chunk, so that large files are not copied into a string.
     pub(crate) fn reparse_tree(&mut self) -> anyhow::Result<()> {
+        let Some(tree) = self.tree.as_ref() else {
+            return Ok(());
+        };
         let mut parser = tree_sitter::Parser::new();
-        if let Some(tree) = self.tree.as_ref() {
-            parser.set_language(&tree.language())?;
-            self.tree = parser.parse(self.rope.to_string(), None);
-        }
+        parser.set_language(&tree.language())?;
+        let rope = &self.rope;
+        let new_tree = parser.parse_with(
+            &mut |byte_index, _| {
+                rope.get_chunk_at_byte(byte_index)
+                    .map(|(chunk, chunk_byte_index, _, _)| {
+                        &chunk.as_bytes()[byte_index - chunk_byte_index..]
+                    })
+                    .unwrap_or_default()
+            },
+            Some(tree),
+        );
+        self.tree = new_tree;
         Ok(())
     }
 
+    /// The column of a tree-sitter `Point` is measured in bytes.
+    fn byte_to_point(&self, byte_index: usize) -> anyhow::Result<Point> {
+        let row = self.rope.try_byte_to_line(byte_index)?;
+        let column = byte_index - self.rope.try_line_to_byte(row)?;
+        Ok(Point { row, column })
+    }
+
     pub(crate) fn get_formatted_content(&self) -> Option<String> {
+        if self.large_file {
+            return None;
+        }
         if let Some(content) = self.language.as_ref().and_then(|language| {
             language.formatter().map(|formatter| {
                 log::info!("[FORMAT]: {}", formatter.command_string());
@@ -642,7 +1312,18 @@ impl Buffer {
 
     pub(crate) fn save_without_formatting(&mut self) -> anyhow::Result<Option<CanonicalizedPath>> {
         if let Some(path) = &self.path.clone() {
-            path.write(&self.content())?;
+            match &self.encryption {
+                Some((encryption, passphrase)) => {
+                    encryption.encrypt(path, &self.charset.encode(&self.content())?, passphrase)?
+                }
+                None => {
+                    let content = self.line_ending.convert(&self.content());
+                    std::fs::write(path, self.charset.encode(&content)?)?
+                }
+            }
+            self.dirty = false;
+            self.refresh_git_gutter_base();
+            self.modified_time = file_watcher::modified_time(path);
 
             Ok(Some(path.clone()))
         } else {
@@ -655,14 +1336,22 @@ impl Buffer {
         &mut self,
         current_selection_set: SelectionSet,
     ) -> anyhow::Result<Option<CanonicalizedPath>> {
-        if let Some(formatted_content) = self.get_formatted_content() {
-            self.update_content(&formatted_content, current_selection_set)?;
+        let content = self
+            .get_formatted_content()
+            .unwrap_or_else(|| self.content());
+        let content = self.editor_config.format(
+            &content,
+            self.insert_final_newline,
+            self.trim_trailing_whitespace,
+        );
+        if content != self.content() {
+            self.update_content(&content, current_selection_set)?;
         }
 
         self.save_without_formatting()
     }
 
-    fn update_content(
+    pub(crate) fn update_content(
         &mut self,
         new_content: &str,
         current_selection_set: SelectionSet,
@@ -671,25 +1360,35 @@ impl Buffer {
         self.apply_edit_transaction(&edit_transaction, current_selection_set, true)
     }
 
-    /// The resulting spans must be sorted by range
-    pub(crate) fn highlighted_spans(&self) -> Vec<HighlighedSpan> {
-        let spans = self.highlighted_spans.0.clone();
-        debug_assert!(
-            spans
-                .iter()
-                .enumerate()
-                .sorted_by_key(|(_, span)| (span.byte_range.start, span.byte_range.end))
-                .map(|(index, _)| index)
-                .collect_vec()
-                == (0..spans.len()).collect_vec(),
-        );
-        spans
+    /// The spans are sorted by range.
+    ///
+    /// They are borrowed instead of cloned, because this is called on every render,
+    /// and only the spans within the visible lines are needed.
+    pub(crate) fn highlighted_spans(&self) -> &[HighlighedSpan] {
+        &self.highlighted_spans.0
     }
 
     pub(crate) fn language(&self) -> Option<Language> {
         self.language.clone()
     }
 
+    /// Unlike `set_language`, the grammar is also swapped, so the tree is parsed from scratch,
+    /// and the stale highlighted spans are cleared until the content is highlighted again.
+    pub(crate) fn switch_language(&mut self, language: Language) -> anyhow::Result<()> {
+        if self.is_encrypted() {
+            return Err(anyhow::anyhow!(
+                "The language of an encrypted file cannot be changed, \
+                 because its content must not be sent to other processes."
+            ));
+        }
+        self.treesitter_language = language.tree_sitter_language();
+        self.language = Some(language);
+        (self.rope, self.tree) =
+            Self::get_rope_and_tree(self.treesitter_language.clone(), &self.rope.to_string());
+        self.highlighted_spans = HighlighedSpans::default();
+        Ok(())
+    }
+
     #[cfg(test)]
     pub(crate) fn set_language(&mut self, language: Language) -> anyhow::Result<()> {
         self.language = Some(language);
@@ -728,6 +1427,123 @@ impl Buffer {
         self.marks.clone()
     }
 
+    /// The char indices are clamped, because they are not updated when the content is replaced
+    /// as a whole (e.g. reloaded from disk).
+    pub(crate) fn named_marks(&self) -> Vec<(char, CharIndex)> {
+        let max_char_index = CharIndex(self.len_chars());
+        self.named_marks
+            .iter()
+            .map(|(name, char_index)| (*name, (*char_index).min(max_char_index)))
+            .collect_vec()
+    }
+
+    pub(crate) fn named_mark(&self, name: char) -> Option<CharIndex> {
+        self.named_marks
+            .get(&name)
+            .map(|char_index| (*char_index).min(CharIndex(self.len_chars())))
+    }
+
+    pub(crate) fn set_named_mark(&mut self, name: char, char_index: CharIndex) {
+        self.named_marks.insert(name, char_index);
+    }
+
+    pub(crate) fn remove_named_mark(&mut self, name: char) -> Option<CharIndex> {
+        self.named_marks.remove(&name)
+    }
+
+    /// The lines are sorted without duplicates, because the lines of breakpoints can be joined
+    /// by edits.
+    pub(crate) fn breakpoint_lines(&self) -> Vec<usize> {
+        let max_char_index = CharIndex(self.len_chars());
+        self.breakpoints
+            .iter()
+            .filter_map(|char_index| self.char_to_line((*char_index).min(max_char_index)).ok())
+            .sorted()
+            .dedup()
+            .collect_vec()
+    }
+
+    /// Returns `true` if the breakpoint is set, or `false` if it is removed.
+    pub(crate) fn toggle_breakpoint(&mut self, line: usize) -> anyhow::Result<bool> {
+        if self.breakpoint_lines().contains(&line) {
+            let max_char_index = CharIndex(self.len_chars());
+            self.breakpoints = std::mem::take(&mut self.breakpoints)
+                .into_iter()
+                .filter(|char_index| {
+                    self.char_to_line((*char_index).min(max_char_index)).ok() != Some(line)
+                })
+                .collect();
+            Ok(false)
+        } else {
+            self.breakpoints.push(self.line_to_char(line)?);
+            Ok(true)
+        }
+    }
+
+    /// Returns the line ranges (the end is exclusive) of the folds.
+    pub(crate) fn folds(&self) -> Vec<Range<usize>> {
+        self.folds
+            .iter()
+            .filter_map(|fold| {
+                let start = self.char_to_line(fold.start).ok()?;
+                let end = self.char_to_line(fold.end - 1).ok()? + 1;
+                Some(start..end)
+            })
+            .collect_vec()
+    }
+
+    pub(crate) fn hidden_line_ranges(&self) -> Vec<Range<usize>> {
+        crate::fold::hidden_line_ranges(&self.folds())
+    }
+
+    pub(crate) fn add_fold(&mut self, line_range: Range<usize>) -> anyhow::Result<()> {
+        let fold = self.line_range_to_char_index_range(line_range)?;
+        if !self.folds.contains(&fold) {
+            self.folds.push(fold)
+        }
+        Ok(())
+    }
+
+    /// Removes the folds that contain the given line, including the first line of the folds.
+    pub(crate) fn unfold_line(&mut self, line: usize) {
+        let folds = self.folds();
+        self.folds = std::mem::take(&mut self.folds)
+            .into_iter()
+            .zip(folds)
+            .filter(|(_, line_range)| !line_range.contains(&line))
+            .map(|(fold, _)| fold)
+            .collect_vec()
+    }
+
+    pub(crate) fn clear_folds(&mut self) {
+        self.folds.clear()
+    }
+
+    pub(crate) fn add_auto_closings(&mut self, char_indices: impl IntoIterator<Item = CharIndex>) {
+        self.auto_closings.extend(
+            char_indices
+                .into_iter()
+                .map(|char_index| CharIndexRange::from(char_index..char_index + 1)),
+        )
+    }
+
+    pub(crate) fn is_auto_closing(&self, char_index: CharIndex) -> bool {
+        self.auto_closings
+            .iter()
+            .any(|auto_closing| auto_closing.start == char_index)
+    }
+
+    /// Removes the auto closings that are skipped over.
+    pub(crate) fn remove_auto_closings(&mut self, char_indices: &[CharIndex]) {
+        self.auto_closings
+            .retain(|auto_closing| !char_indices.contains(&auto_closing.start))
+    }
+
+    /// Auto closings are only skipped over within the same insert session.
+    pub(crate) fn clear_auto_closings(&mut self) {
+        self.auto_closings.clear()
+    }
+
     pub(crate) fn byte_to_position(&self, byte_index: usize) -> anyhow::Result<Position> {
         let char_index = self.byte_to_char(byte_index)?;
         self.char_to_position(char_index)
@@ -972,11 +1788,85 @@ impl Buffer {
 #[cfg(test)]
 mod test_buffer {
     use itertools::Itertools;
+    use ropey::Rope;
 
-    use crate::selection::SelectionSet;
+    use crate::{
+        edit::Edit,
+        selection::{CharIndex, SelectionSet},
+    };
 
     use super::Buffer;
 
+    fn rust_buffer(content: &str) -> Buffer {
+        Buffer::new(
+            shared::language::from_extension("rs")
+                .unwrap()
+                .tree_sitter_language(),
+            content,
+        )
+    }
+
+    fn edit(buffer: &mut Buffer, start: usize, end: usize, new: &str) {
+        buffer
+            .apply_edit(&Edit {
+                range: (CharIndex(start)..CharIndex(end)).into(),
+                new: Rope::from_str(new),
+            })
+            .unwrap();
+        buffer.reparse_tree().unwrap();
+    }
+
+    fn sexp(buffer: &Buffer) -> String {
+        buffer.tree.as_ref().unwrap().root_node().to_sexp()
+    }
+
+    #[test]
+    fn incremental_reparse_matches_full_parse() {
+        let mut buffer = rust_buffer("fn main() {\n    let x = 1;\n}\n");
+        // Insert a multi-byte character, so that byte offsets differ from char offsets
+        edit(&mut buffer, 21, 21, "é");
+        edit(&mut buffer, 0, 0, "struct A;\n");
+        edit(&mut buffer, 10, 12, "pub fn");
+        let content = buffer.rope.to_string();
+        assert_eq!(content, "struct A;\npub fn main() {\n    let xé = 1;\n}\n");
+        assert_eq!(sexp(&buffer), sexp(&rust_buffer(&content)));
+    }
+
+    #[test]
+    fn git_gutter_hunks_are_diffed_again_once_the_content_or_the_base_is_changed() {
+        let mut buffer = Buffer::new(None, "a\nb\n");
+        buffer.set_git_gutter_base(Some("a\nb\n".to_string()));
+        assert!(buffer.git_gutter_hunks().is_empty());
+        edit(&mut buffer, 0, 1, "x");
+        assert_eq!(buffer.git_gutter_hunks().len(), 1);
+        buffer.set_git_gutter_base(Some("x\nb\n".to_string()));
+        assert!(buffer.git_gutter_hunks().is_empty());
+    }
+
+    #[test]
+    fn large_file_highlights_only_the_lines_around_the_visible_lines() -> anyhow::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let path = temp_dir.path().join("large.rs");
+        // Each line is 10 bytes
+        std::fs::write(&path, "fn x() {}\n".repeat(1000))?;
+        let mut buffer = Buffer::from_large_path(&path.try_into()?)?;
+        assert!(buffer.is_large_file());
+        assert!(buffer.tree.is_none());
+        assert_eq!(buffer.len_lines(), 1001);
+
+        let (_, source_code, byte_offset) = buffer.large_file_highlight_request(500..520).unwrap();
+        assert_eq!(byte_offset, 300 * 10);
+        assert_eq!(source_code.lines().count(), 420);
+
+        // The visible lines are highlighted already
+        assert!(buffer.large_file_highlight_request(510..530).is_none());
+        assert!(buffer.large_file_highlight_request(750..770).is_some());
+
+        buffer.reset_highlighted_line_range();
+        assert!(buffer.large_file_highlight_request(750..770).is_some());
+        Ok(())
+    }
+
     #[test]
     fn get_parent_lines_1() {
         let buffer = Buffer::new(
@@ -1040,6 +1930,29 @@ fn f(
         pretty_assertions::assert_eq!(actual, expected)
     }
 
+    #[test]
+    fn matching_bracket() {
+        let matching = |buffer: &Buffer, index: usize| {
+            buffer
+                .matching_bracket(CharIndex(index))
+                .map(|char_index| char_index.0)
+        };
+        // The brackets in strings are skipped by the syntax tree
+        let buffer = rust_buffer("f(\"(\", x)");
+        assert_eq!(matching(&buffer, 1), Some(8));
+        assert_eq!(matching(&buffer, 8), Some(1));
+
+        // Without a syntax tree, only the same kind of brackets are counted
+        let buffer = Buffer::new(None, "a(b[c)d(e)");
+        assert_eq!(matching(&buffer, 1), Some(5));
+        assert_eq!(matching(&buffer, 5), Some(1));
+        assert_eq!(matching(&buffer, 9), Some(7));
+        // Unbalanced
+        assert_eq!(matching(&buffer, 3), None);
+        // Not a bracket
+        assert_eq!(matching(&buffer, 0), None);
+    }
+
     mod replace {
 
         use crate::{
@@ -1343,6 +2256,67 @@ pub(crate) struct Patch {
     /// Why don't we store this is diffy::Patch? Because it requires a lifetime parameter
     pub(crate) patch: String,
     pub(crate) state: BufferState,
+    /// The edits of the patch in the order that they are applied,
+    /// which are only recorded for redoing, and used to undo the changes within a selection
+    edits: Vec<PatchEdit>,
+    /// The index of the undo history entry that is reverted by this patch
+    reverts: Option<usize>,
+}
+
+#[derive(Clone)]
+struct PatchEdit {
+    /// The range of `old` in the content right before this edit
+    range: CharIndexRange,
+    old: Rope,
+    new: Rope,
+}
+
+impl PatchEdit {
+    fn forward(&self) -> Edit {
+        Edit {
+            range: self.range,
+            new: self.new.clone(),
+        }
+    }
+
+    fn backward(&self) -> Edit {
+        Edit {
+            range: (self.range.start..self.range.start + self.new.len_chars()).into(),
+            new: self.old.clone(),
+        }
+    }
+}
+
+/// Returns `range` after `edit`, or `None` if they overlap.
+fn shift_range(range: CharIndexRange, edit: &Edit) -> Option<CharIndexRange> {
+    if edit.range.start >= range.end {
+        Some(range)
+    } else if edit.range.end <= range.start {
+        Some(range.apply_offset(edit.chars_offset()))
+    } else {
+        None
+    }
+}
+
+/// Returns `range` after `edit`, which is expanded to cover the new text of `edit` if they overlap,
+/// together with whether they overlap.
+fn map_range_expanding(range: CharIndexRange, edit: &Edit) -> (CharIndexRange, bool) {
+    let (start, end) = (edit.range.start, edit.range.end);
+    let new_end = start + edit.new.len_chars();
+    let map = |char_index: CharIndex, inside: CharIndex| {
+        if char_index <= start {
+            char_index
+        } else if char_index >= end {
+            char_index.apply_offset(edit.chars_offset())
+        } else {
+            inside
+        }
+    };
+    let overlaps = start < range.end && range.start < end;
+    (
+        (map(range.start, start)..map(range.end, new_end)).into(),
+        overlaps,
+    )
 }
 
 #[derive(Clone)]
@@ -37,6 +37,10 @@ impl CharIndexRange {
         self.end.0.saturating_sub(self.start.0)
     }
 
+    pub(crate) fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
     pub(crate) fn shift_left(&self, len: usize) -> CharIndexRange {
         CharIndexRange {
             start: self.start - len,
@@ -25,6 +25,10 @@ enum Commands {
     Log,
     /// Run Ki in the given path, treating the path as the working directory
     In(InArgs),
+    /// Run Ki without a terminal, driven by the clients connected to a Unix domain socket
+    Serve(ServeArgs),
+    /// Attach this terminal to a server started by `ki serve`
+    Attach(AttachArgs),
 }
 #[derive(Args)]
 struct EditArgs {
@@ -34,6 +38,20 @@ struct EditArgs {
 struct InArgs {
     path: String,
 }
+#[derive(Args)]
+struct ServeArgs {
+    /// The working directory, which defaults to the current directory
+    path: Option<String>,
+    /// Defaults to `server.sock` in the cache directory
+    #[arg(long)]
+    socket: Option<String>,
+}
+#[derive(Args)]
+struct AttachArgs {
+    /// Defaults to `server.sock` in the cache directory
+    #[arg(long)]
+    socket: Option<String>,
+}
 #[derive(Subcommand)]
 enum Grammar {
     Build,
@@ -90,8 +108,22 @@ pub(crate) fn cli() -> anyhow::Result<()> {
                 working_directory: Some(args.path.try_into()?),
                 ..Default::default()
             }),
+            Commands::Serve(args) => crate::serve(
+                crate::RunConfig {
+                    working_directory: args.path.map(TryInto::try_into).transpose()?,
+                    ..Default::default()
+                },
+                &socket_path(args.socket),
+            ),
+            Commands::Attach(args) => crate::client::attach(&socket_path(args.socket)),
         }
     } else {
         crate::run(Default::default())
     }
 }
+
+fn socket_path(socket: Option<String>) -> std::path::PathBuf {
+    socket
+        .map(Into::into)
+        .unwrap_or_else(crate::server::default_socket_path)
+}
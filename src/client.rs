@@ -0,0 +1,91 @@
+//! `ki attach`, the terminal client of the headless server mode, see `server`.
+//!
+//! The keys typed in the terminal are sent to the server, and the output of the server is written
+//! to the terminal as it is. The client exits once the server quits.
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    os::unix::net::UnixStream,
+    path::Path,
+};
+
+use anyhow::Context;
+use base64::{engine::general_purpose, Engine as _};
+use event::event::Event;
+
+use crate::frontend::{crossterm::Crossterm, Frontend};
+
+pub(crate) fn attach(socket_path: &Path) -> anyhow::Result<()> {
+    let stream = UnixStream::connect(socket_path).with_context(|| {
+        format!(
+            "Unable to connect to the server at {}",
+            socket_path.display()
+        )
+    })?;
+    let mut writer = stream.try_clone()?;
+    let mut terminal = Crossterm::new()?;
+    terminal.enter_alternate_screen()?;
+    terminal.enable_raw_mode()?;
+    let dimension = terminal.get_terminal_dimension()?;
+    send(
+        &mut writer,
+        "resize",
+        resize_params(dimension.width, dimension.height),
+    )?;
+
+    std::thread::spawn(move || {
+        let mut stdout = std::io::stdout();
+        for line in BufReader::new(stream).lines().map_while(Result::ok) {
+            let Ok(message) = serde_json::from_str::<serde_json::Value>(&line) else {
+                continue;
+            };
+            if message["method"] != "output" {
+                continue;
+            }
+            let Some(Ok(data)) = message["params"]["data"]
+                .as_str()
+                .map(|data| general_purpose::STANDARD.decode(data))
+            else {
+                continue;
+            };
+            if stdout
+                .write_all(&data)
+                .and_then(|_| stdout.flush())
+                .is_err()
+            {
+                break;
+            }
+        }
+        // The server has quit, while the main thread is still waiting for the next event
+        if let Ok(mut terminal) = Crossterm::new() {
+            let _ = terminal.leave_alternate_screen();
+            let _ = terminal.disable_raw_mode();
+        }
+        std::process::exit(0)
+    });
+
+    let mut events = terminal
+        .event_source()
+        .context("The terminal has no events")?;
+    loop {
+        let (method, params) = match events.read()? {
+            Event::Key(key) => ("input", serde_json::json!({ "keys": key.display() })),
+            Event::Paste(text) => ("paste", serde_json::json!({ "text": text })),
+            Event::ImeCommit(text) => ("paste", serde_json::json!({ "text": text })),
+            Event::Resize(columns, rows) => ("resize", resize_params(columns, rows)),
+            _ => continue,
+        };
+        send(&mut writer, method, params)?
+    }
+}
+
+fn resize_params(columns: u16, rows: u16) -> serde_json::Value {
+    serde_json::json!({ "columns": columns, "rows": rows })
+}
+
+/// Sent as notifications, because the results are not needed by the terminal client
+fn send(writer: &mut UnixStream, method: &str, params: serde_json::Value) -> anyhow::Result<()> {
+    let message = serde_json::json!({ "jsonrpc": "2.0", "method": method, "params": params });
+    writeln!(writer, "{message}")?;
+    Ok(())
+}
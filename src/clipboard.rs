@@ -6,6 +6,10 @@ use crate::osc52;
 #[derive(Clone)]
 pub(crate) struct Clipboard {
     history: RingHistory<CopiedTexts>,
+    /// When true, every copied text is also copied to the primary selection
+    /// (the selection that is pasted by middle-clicking on X11 and Wayland),
+    /// regardless of whether the system clipboard is used.
+    copy_to_primary_selection: bool,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -43,9 +47,14 @@ impl Clipboard {
     pub(crate) fn new() -> Clipboard {
         Clipboard {
             history: RingHistory::new(),
+            copy_to_primary_selection: false,
         }
     }
 
+    pub(crate) fn set_copy_to_primary_selection(&mut self, copy_to_primary_selection: bool) {
+        self.copy_to_primary_selection = copy_to_primary_selection
+    }
+
     pub(crate) fn get(&self, history_offset: isize) -> Option<CopiedTexts> {
         self.history.get(history_offset)
     }
@@ -54,6 +63,10 @@ impl Clipboard {
         Ok(arboard::Clipboard::new()?.get_text()?)
     }
 
+    pub(crate) fn get_from_primary_selection(&self) -> anyhow::Result<String> {
+        primary_selection::get()
+    }
+
     pub(crate) fn set(
         &mut self,
         copied_texts: CopiedTexts,
@@ -65,10 +78,62 @@ impl Clipboard {
                 .and_then(|mut clipboard| clipboard.set_text(copied_texts.join("\n")))
                 .or_else(|_| osc52::copy_to_clipboard(&copied_texts.join("\n")))?
         }
+        if self.copy_to_primary_selection {
+            // The failure is not propagated, because the copied text is already in the history
+            primary_selection::set(&copied_texts.join("\n"))
+                .unwrap_or_else(|error| log::error!("Failed to set primary selection: {:?}", error))
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(
+    unix,
+    not(any(target_os = "macos", target_os = "android", target_os = "emscripten"))
+))]
+mod primary_selection {
+    use arboard::{GetExtLinux, LinuxClipboardKind, SetExtLinux};
+
+    use crate::osc52;
+
+    pub(super) fn get() -> anyhow::Result<String> {
+        Ok(arboard::Clipboard::new()?
+            .get()
+            .clipboard(LinuxClipboardKind::Primary)
+            .text()?)
+    }
+
+    pub(super) fn set(text: &str) -> anyhow::Result<()> {
+        arboard::Clipboard::new()
+            .and_then(|mut clipboard| {
+                clipboard
+                    .set()
+                    .clipboard(LinuxClipboardKind::Primary)
+                    .text(text.to_string())
+            })
+            .or_else(|_| osc52::copy_to_primary_selection(text))?;
         Ok(())
     }
 }
 
+#[cfg(not(all(
+    unix,
+    not(any(target_os = "macos", target_os = "android", target_os = "emscripten"))
+)))]
+mod primary_selection {
+    pub(super) fn get() -> anyhow::Result<String> {
+        Err(anyhow::anyhow!(
+            "The primary selection is only supported on X11 and Wayland"
+        ))
+    }
+
+    pub(super) fn set(_text: &str) -> anyhow::Result<()> {
+        Err(anyhow::anyhow!(
+            "The primary selection is only supported on X11 and Wayland"
+        ))
+    }
+}
+
 #[derive(PartialEq, Clone, Debug, Eq, Hash, Default)]
 pub(crate) struct RingHistory<T: Clone> {
     items: Vec<T>,
@@ -1,6 +1,12 @@
 use crate::{
     app::{Dispatch, Dispatches},
-    components::{dropdown::DropdownItem, suggestive_editor::Info},
+    components::{
+        dropdown::DropdownItem,
+        editor::{Direction, DispatchEditor},
+        suggestive_editor::Info,
+    },
+    editorconfig::EndOfLine,
+    lsp::hierarchy::HierarchyKind,
 };
 
 pub(crate) struct Command {
@@ -50,4 +56,166 @@ pub const COMMANDS: &[Command] = &[
         description: "Save all buffers",
         dispatch: Dispatch::SaveAll,
     },
+    Command {
+        name: "set-language",
+        description: "Change the language of the current buffer",
+        dispatch: Dispatch::OpenSetLanguagePrompt,
+    },
+    Command {
+        name: "set-indentation",
+        description: "Override the detected indentation of the current buffer",
+        dispatch: Dispatch::OpenSetIndentationPrompt,
+    },
+    Command {
+        name: "filter-diagnostics",
+        description: "Hide the diagnostics that are less severe than the chosen severity",
+        dispatch: Dispatch::OpenDiagnosticSeverityPrompt,
+    },
+    Command {
+        name: "mute-diagnostic",
+        description: "Hide the diagnostics with the code or source of the one under the cursor",
+        dispatch: Dispatch::MuteDiagnosticAtCursor,
+    },
+    Command {
+        name: "unmute-diagnostics",
+        description: "Show the muted diagnostics of the chosen code or source again",
+        dispatch: Dispatch::OpenUnmuteDiagnosticsPrompt,
+    },
+    Command {
+        name: "recover-unsaved-changes",
+        description: "Restore the unsaved changes that are left over from a previous session",
+        dispatch: Dispatch::OpenRecoveryPrompt,
+    },
+    Command {
+        name: "compare-clipboard",
+        description: "Diff the primary selection against the system clipboard",
+        dispatch: Dispatch::OpenClipboardDiffView {
+            use_system_clipboard: true,
+        },
+    },
+    Command {
+        name: "edit-macro",
+        description: "Open a macro register or a named macro as text",
+        dispatch: Dispatch::OpenEditMacroPrompt,
+    },
+    Command {
+        name: "write-macro",
+        description: "Write the current macro buffer back to its macro",
+        dispatch: Dispatch::WriteMacro,
+    },
+    Command {
+        name: "save-macro",
+        description: "Save the current macro buffer as a named macro for later sessions",
+        dispatch: Dispatch::OpenSaveMacroPrompt,
+    },
+    Command {
+        name: "run-macro",
+        description: "Run a macro register or a named macro",
+        dispatch: Dispatch::OpenRunMacroPrompt,
+    },
+    Command {
+        name: "refactor-log",
+        description: "Open the log of the renames, code actions and replaces applied so far",
+        dispatch: Dispatch::OpenRefactorLog,
+    },
+    Command {
+        name: "review-refactors",
+        description: "Show the locations touched by the refactorings as a quickfix list",
+        dispatch: Dispatch::ReviewRefactorLog,
+    },
+    Command {
+        name: "reload-config",
+        description: "Read the config files again and apply them to the opened buffers",
+        dispatch: Dispatch::ReloadConfig,
+    },
+    Command {
+        name: "scroll-bind",
+        description: "Toggle scrolling the current window together with the other bound windows",
+        dispatch: Dispatch::ToggleScrollBind,
+    },
+    Command {
+        name: "promote-heading",
+        description: "Decrease the level of the markdown heading and its subheadings",
+        dispatch: Dispatch::ToEditor(DispatchEditor::PromoteHeading),
+    },
+    Command {
+        name: "demote-heading",
+        description: "Increase the level of the markdown heading and its subheadings",
+        dispatch: Dispatch::ToEditor(DispatchEditor::DemoteHeading),
+    },
+    Command {
+        name: "move-heading-up",
+        description: "Swap the markdown heading and its subheadings with the previous sibling",
+        dispatch: Dispatch::ToEditor(DispatchEditor::MoveHeadingSubtree(Direction::Start)),
+    },
+    Command {
+        name: "move-heading-down",
+        description: "Swap the markdown heading and its subheadings with the next sibling",
+        dispatch: Dispatch::ToEditor(DispatchEditor::MoveHeadingSubtree(Direction::End)),
+    },
+    Command {
+        name: "fold-headings",
+        description: "Fold the sections of the markdown headings to show the outline",
+        dispatch: Dispatch::ToEditor(DispatchEditor::FoldHeadings),
+    },
+    Command {
+        name: "markdown-preview",
+        description: "Open a preview of the markdown file beside it, which follows the edits",
+        dispatch: Dispatch::OpenMarkdownPreview,
+    },
+    Command {
+        name: "line-ending-lf",
+        description: "Convert the line endings of the current buffer to LF",
+        dispatch: Dispatch::ToEditor(DispatchEditor::SetLineEnding(EndOfLine::Lf)),
+    },
+    Command {
+        name: "line-ending-crlf",
+        description: "Convert the line endings of the current buffer to CRLF",
+        dispatch: Dispatch::ToEditor(DispatchEditor::SetLineEnding(EndOfLine::Crlf)),
+    },
+    Command {
+        name: "trim-trailing-whitespace",
+        description: "Remove the trailing spaces and tabs of every line of the current buffer",
+        dispatch: Dispatch::ToEditor(DispatchEditor::TrimTrailingWhitespace),
+    },
+    Command {
+        name: "append-terminator",
+        description: "Insert `;` or `,` at the end of the current statement or list element",
+        dispatch: Dispatch::ToEditor(DispatchEditor::AppendTerminator),
+    },
+    Command {
+        name: "build",
+        description: "Run a build command, and list the errors and warnings in its output",
+        dispatch: Dispatch::OpenBuildPrompt,
+    },
+    Command {
+        name: "lsp-logs",
+        description: "Show the messages logged by the LSP servers of the current buffer",
+        dispatch: Dispatch::ShowLspLogs,
+    },
+    Command {
+        name: "lsp-restart",
+        description: "Restart the LSP servers of the current buffer, for example when one is stuck",
+        dispatch: Dispatch::RestartLspServers,
+    },
+    Command {
+        name: "incoming-calls",
+        description: "Browse the callers of the function under the cursor as a tree",
+        dispatch: Dispatch::RequestHierarchy(HierarchyKind::IncomingCalls),
+    },
+    Command {
+        name: "outgoing-calls",
+        description: "Browse the callees of the function under the cursor as a tree",
+        dispatch: Dispatch::RequestHierarchy(HierarchyKind::OutgoingCalls),
+    },
+    Command {
+        name: "supertypes",
+        description: "Browse the supertypes of the type under the cursor as a tree",
+        dispatch: Dispatch::RequestHierarchy(HierarchyKind::Supertypes),
+    },
+    Command {
+        name: "subtypes",
+        description: "Browse the subtypes of the type under the cursor as a tree",
+        dispatch: Dispatch::RequestHierarchy(HierarchyKind::Subtypes),
+    },
 ];
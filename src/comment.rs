@@ -0,0 +1,95 @@
+use itertools::Itertools;
+
+/// Comments every non-blank line of `lines` with `prefix` at the smallest indentation among them,
+/// unless every non-blank line is already commented, in which case they are uncommented.
+///
+/// Blank lines are left untouched.
+pub(crate) fn toggle_line_comment(lines: &str, prefix: &str) -> String {
+    let non_blank_lines = || lines.split('\n').filter(|line| !line.trim().is_empty());
+    let is_commented = non_blank_lines().all(|line| line.trim_start().starts_with(prefix));
+    if non_blank_lines().next().is_none() {
+        return lines.to_string();
+    }
+    let indentation = non_blank_lines()
+        .map(|line| line.chars().take_while(|c| c.is_whitespace()).count())
+        .min()
+        .unwrap_or_default();
+    lines
+        .split('\n')
+        .map(|line| {
+            if line.trim().is_empty() {
+                line.to_string()
+            } else if is_commented {
+                let content = line.trim_start();
+                let content = &content[prefix.len()..];
+                let content = content.strip_prefix(' ').unwrap_or(content);
+                format!(
+                    "{}{}",
+                    &line[..line.len() - line.trim_start().len()],
+                    content
+                )
+            } else {
+                let index = line
+                    .char_indices()
+                    .nth(indentation)
+                    .map(|(index, _)| index)
+                    .unwrap_or(line.len());
+                format!("{}{} {}", &line[..index], prefix, &line[index..])
+            }
+        })
+        .join("\n")
+}
+
+/// Surrounds `text` (excluding its leading and trailing whitespaces) with `open` and `close`,
+/// unless it is already surrounded by them, in which case they are removed.
+pub(crate) fn toggle_block_comment(text: &str, (open, close): (&str, &str)) -> String {
+    let content = text.trim();
+    let leading = &text[..text.len() - text.trim_start().len()];
+    let trailing = &text[text.trim_end().len()..];
+    let new = match content
+        .strip_prefix(open)
+        .and_then(|content| content.strip_suffix(close))
+    {
+        Some(commented) => commented
+            .strip_prefix(' ')
+            .and_then(|commented| commented.strip_suffix(' '))
+            .unwrap_or(commented)
+            .to_string(),
+        None if content.is_empty() => String::new(),
+        None => format!("{open} {content} {close}"),
+    };
+    format!("{leading}{new}{trailing}")
+}
+
+#[cfg(test)]
+mod test_comment {
+    use super::*;
+
+    #[test]
+    fn comment_lines_at_the_smallest_indentation() {
+        assert_eq!(
+            toggle_line_comment("    if x {\n\n        y\n    }", "//"),
+            "    // if x {\n\n    //     y\n    // }"
+        );
+    }
+
+    #[test]
+    fn uncomment_lines_and_preserve_indentation() {
+        assert_eq!(
+            toggle_line_comment("    // if x {\n    //     y\n    //}", "//"),
+            "    if x {\n        y\n    }"
+        );
+    }
+
+    #[test]
+    fn comment_mixed_lines() {
+        assert_eq!(toggle_line_comment("# a\nb", "#"), "# # a\n# b");
+    }
+
+    #[test]
+    fn toggle_block() {
+        assert_eq!(toggle_block_comment(" a b\n", ("/*", "*/")), " /* a b */\n");
+        assert_eq!(toggle_block_comment(" /* a b */\n", ("/*", "*/")), " a b\n");
+        assert_eq!(toggle_block_comment("<!--a-->", ("<!--", "-->")), "a");
+    }
+}
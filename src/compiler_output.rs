@@ -0,0 +1,166 @@
+//! Parses the locations of the errors and warnings in the output of a build command,
+//! so that they can be navigated as a quickfix list.
+//!
+//! Two formats are recognized:
+//! - The format of rustc (e.g. `cargo build` and `cargo clippy`), where the location is on
+//!   the line that starts with `-->` after the message:
+//!   ```text
+//!   error[E0425]: cannot find value `x` in this scope
+//!    --> src/main.rs:2:5
+//!   ```
+//! - The `path:line:column: message` format of most other tools (e.g. gcc and tsc),
+//!   where the column is optional.
+
+use std::path::PathBuf;
+
+use regex::Regex;
+
+use crate::position::Position;
+
+/// The path cannot start with a whitespace, so that the indented lines of the rustc format
+/// (e.g. `  ::: src/lib.rs:1:1`) are not mistaken for messages.
+const PREFIXED_LOCATION: &str = r"^([^\s:][^:]*):(\d+):(?:(\d+):)?\s*(.*)$";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct CompilerMessage {
+    /// As written in the output, which is usually relative to the working directory
+    pub(crate) path: PathBuf,
+    pub(crate) position: Position,
+    pub(crate) message: String,
+}
+
+pub(crate) fn parse(output: &str) -> Vec<CompilerMessage> {
+    let prefixed_location = Regex::new(PREFIXED_LOCATION).unwrap();
+    let mut messages = Vec::new();
+    // The latest rustc message that is not located yet
+    let mut header: Option<&str> = None;
+    for line in output.lines() {
+        if let Some(location) = line.trim_start().strip_prefix("--> ") {
+            // Only the primary location of a message is taken
+            if let Some((header, (path, position))) = header.take().zip(parse_location(location)) {
+                messages.push(CompilerMessage {
+                    path,
+                    position,
+                    message: header.to_string(),
+                })
+            }
+        } else if is_rustc_header(line) {
+            header = Some(line)
+        } else if let Some(captures) = prefixed_location.captures(line) {
+            let number = |index: usize| {
+                captures
+                    .get(index)
+                    .and_then(|number| number.as_str().parse::<usize>().ok())
+            };
+            messages.push(CompilerMessage {
+                path: PathBuf::from(&captures[1]),
+                position: Position::new(
+                    number(2).unwrap_or(1).saturating_sub(1),
+                    number(3).unwrap_or(1).saturating_sub(1),
+                ),
+                message: captures[4].to_string(),
+            })
+        }
+    }
+    messages
+}
+
+/// For example `error[E0425]: ...` or `warning: ...`
+fn is_rustc_header(line: &str) -> bool {
+    ["error", "warning"].iter().any(|severity| {
+        line.strip_prefix(severity)
+            .is_some_and(|rest| rest.starts_with(':') || rest.starts_with('['))
+    })
+}
+
+/// Parses `path:line:column`, where the line and the column are 1-based.
+fn parse_location(location: &str) -> Option<(PathBuf, Position)> {
+    let mut parts = location.trim().rsplitn(3, ':');
+    let column = parts.next()?.parse::<usize>().ok()?;
+    let line = parts.next()?.parse::<usize>().ok()?;
+    let path = parts.next()?;
+    Some((
+        PathBuf::from(path),
+        Position::new(line.saturating_sub(1), column.saturating_sub(1)),
+    ))
+}
+
+#[cfg(test)]
+mod test_compiler_output {
+    use super::*;
+
+    fn parse_locations(output: &str) -> Vec<(String, usize, usize, String)> {
+        parse(output)
+            .into_iter()
+            .map(|message| {
+                (
+                    message.path.display().to_string(),
+                    message.position.line,
+                    message.position.column,
+                    message.message,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn rustc_format() {
+        let output = "
+   Compiling foo v0.1.0 (/home/me/foo)
+warning: unused variable: `y`
+ --> src/lib.rs:3:9
+  |
+3 |     let y = 1;
+  |         ^ help: if this is intentional, prefix it with an underscore: `_y`
+  |
+error[E0425]: cannot find value `x` in this scope
+  --> src/main.rs:12:5
+   |
+12 |     x
+   |     ^ not found in this scope
+   |
+  ::: src/other.rs:1:1
+error: could not compile `foo` (bin \"foo\") due to 1 previous error
+";
+        assert_eq!(
+            parse_locations(output),
+            [
+                (
+                    "src/lib.rs".to_string(),
+                    2,
+                    8,
+                    "warning: unused variable: `y`".to_string()
+                ),
+                (
+                    "src/main.rs".to_string(),
+                    11,
+                    4,
+                    "error[E0425]: cannot find value `x` in this scope".to_string()
+                )
+            ]
+        )
+    }
+
+    #[test]
+    fn prefixed_location_format() {
+        let output =
+            "main.c:4:10: error: expected ';'\nsrc/app.ts:7: warning: unused\nnot a location: 12";
+        assert_eq!(
+            parse_locations(output),
+            [
+                (
+                    "main.c".to_string(),
+                    3,
+                    9,
+                    "error: expected ';'".to_string()
+                ),
+                (
+                    "src/app.ts".to_string(),
+                    6,
+                    0,
+                    "warning: unused".to_string()
+                )
+            ]
+        )
+    }
+}
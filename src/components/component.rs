@@ -7,7 +7,7 @@ use shared::canonicalized_path::CanonicalizedPath;
 use crate::{context::Context, grid::Grid, position::Position, rectangle::Rectangle};
 
 use super::{
-    editor::{DispatchEditor, Editor},
+    editor::{DispatchEditor, Editor, Mode},
     keymap_legend::KeymapLegendSection,
 };
 
@@ -119,6 +119,8 @@ pub trait Component: Any + AnyComponent {
             Event::Key(event) => self.handle_key_event(context, event),
             Event::Paste(content) => self.handle_paste_event(content),
             Event::Mouse(event) => self.handle_mouse_event(event),
+            Event::ImePreedit(preedit) => self.handle_ime_preedit(preedit),
+            Event::ImeCommit(text) => self.handle_ime_commit(context, text),
             _ => Ok(Default::default()),
         }
     }
@@ -127,6 +129,27 @@ pub trait Component: Any + AnyComponent {
         self.editor_mut().handle_paste_event(content)
     }
 
+    fn handle_ime_preedit(&mut self, preedit: String) -> anyhow::Result<Dispatches> {
+        self.editor_mut().set_ime_preedit(preedit);
+        Ok(Default::default())
+    }
+
+    /// In insert mode, the composed text is inserted as a single edit,
+    /// so that it is undone at once.
+    /// Otherwise, each character is handled as a key.
+    fn handle_ime_commit(&mut self, context: &Context, text: String) -> anyhow::Result<Dispatches> {
+        if self.editor().mode == Mode::Insert {
+            return self.editor_mut().commit_ime(&text);
+        }
+        text.chars()
+            .try_fold(Dispatches::default(), |dispatches, c| {
+                Ok(
+                    dispatches
+                        .chain(self.handle_key_event(context, event::KeyEvent::from_char(c))?),
+                )
+            })
+    }
+
     fn handle_mouse_event(
         &mut self,
         _event: crossterm::event::MouseEvent,
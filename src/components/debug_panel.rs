@@ -0,0 +1,79 @@
+//! A read-only pane beside the main panel, which shows the state of the debugging session,
+//! including the call stack, the variables and the watches, see `dap::session`.
+
+use my_proc_macros::key;
+
+use crate::{
+    app::{Dispatch, Dispatches},
+    context::Context,
+};
+
+use super::{
+    component::Component,
+    editor::{Editor, Mode},
+};
+
+pub(crate) struct DebugPanel {
+    /// Contains the content, so that it can be navigated with the usual movements.
+    /// It is restored whenever it is modified, because the pane is read-only.
+    editor: Editor,
+    content: String,
+}
+
+impl DebugPanel {
+    pub(crate) fn new(title: String) -> Self {
+        let mut editor = Editor::from_text(None, "");
+        editor.set_title(title);
+        Self {
+            editor,
+            content: String::new(),
+        }
+    }
+
+    /// The cursor stays where it is, so that the variables being read are not scrolled away.
+    pub(crate) fn set_content(&mut self, content: String) -> anyhow::Result<()> {
+        self.content = content;
+        self.refresh_editor()
+    }
+
+    pub(crate) fn set_title(&mut self, title: String) {
+        self.editor.set_title(title)
+    }
+
+    fn refresh_editor(&mut self) -> anyhow::Result<()> {
+        let position = self.editor.get_cursor_position().unwrap_or_default();
+        self.editor.set_content(&self.content)?;
+        self.editor.set_position_range(position..position)?;
+        Ok(())
+    }
+}
+
+impl Component for DebugPanel {
+    fn editor(&self) -> &Editor {
+        &self.editor
+    }
+
+    fn editor_mut(&mut self) -> &mut Editor {
+        &mut self.editor
+    }
+
+    fn handle_key_event(
+        &mut self,
+        context: &Context,
+        event: event::KeyEvent,
+    ) -> anyhow::Result<Dispatches> {
+        match event {
+            key!("esc") if self.editor.mode == Mode::Normal => {
+                Ok(Dispatches::one(Dispatch::CloseCurrentWindow))
+            }
+            _ => {
+                let dispatches = self.editor.handle_key_event(context, event)?;
+                if self.editor.content() != self.content {
+                    self.editor.enter_normal_mode()?;
+                    self.refresh_editor()?;
+                }
+                Ok(dispatches)
+            }
+        }
+    }
+}
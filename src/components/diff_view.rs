@@ -0,0 +1,514 @@
+use std::ops::Range;
+
+use itertools::{EitherOrBoth, Itertools};
+use my_proc_macros::key;
+use shared::canonicalized_path::CanonicalizedPath;
+use similar::{DiffTag, TextDiff};
+
+use crate::{
+    app::{Dimension, Dispatch, Dispatches},
+    context::Context,
+    grid::{get_char_width, CellUpdate, Grid, LineUpdate, RenderContentLineNumber},
+    position::Position,
+    rectangle::Rectangle,
+    style::Style,
+    themes::{Color, Theme},
+};
+
+use super::{
+    component::{Component, Cursor, GetGridResult, SetCursorStyle},
+    editor::{Editor, Mode},
+};
+
+/// The content that the current buffer is diffed against.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum DiffBase {
+    /// Shows the unsaved changes
+    FileOnDisk,
+    /// Shows the uncommitted changes, using the diff provider of the project
+    Head,
+    /// The content of another opened buffer
+    Buffer(CanonicalizedPath),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Side {
+    Old,
+    New,
+}
+
+impl Side {
+    fn toggle(self) -> Self {
+        match self {
+            Side::Old => Side::New,
+            Side::New => Side::Old,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DiffRowKind {
+    Equal,
+    Deleted,
+    Added,
+    Modified,
+}
+
+/// A row of the diff view, which is a line of the old content, the new content, or both.
+///
+/// A side without a line is rendered as a filler,
+/// so that the unchanged lines of both sides are always on the same row.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct DiffRow {
+    kind: DiffRowKind,
+    old: Option<DiffLine>,
+    new: Option<DiffLine>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct DiffLine {
+    /// 0-based
+    line_index: usize,
+    /// Excludes the line ending
+    content: String,
+    /// The char ranges that are changed, only defined for modified rows
+    emphasized: Vec<Range<usize>>,
+}
+
+impl DiffRow {
+    fn line(&self, side: Side) -> Option<&DiffLine> {
+        match side {
+            Side::Old => self.old.as_ref(),
+            Side::New => self.new.as_ref(),
+        }
+    }
+
+    fn background(&self, side: Side, theme: &Theme) -> Option<Color> {
+        match (self.kind, side) {
+            (DiffRowKind::Equal, _) => None,
+            (DiffRowKind::Deleted | DiffRowKind::Modified, Side::Old) => {
+                Some(theme.hunk.old_background)
+            }
+            (DiffRowKind::Added | DiffRowKind::Modified, Side::New) => {
+                Some(theme.hunk.new_background)
+            }
+            (DiffRowKind::Deleted, Side::New) | (DiffRowKind::Added, Side::Old) => {
+                Some(theme.ui.parent_lines_background)
+            }
+        }
+    }
+}
+
+fn diff_rows(old: &str, new: &str) -> Vec<DiffRow> {
+    let diff = TextDiff::from_lines(old, new);
+    let line = |slices: &[&str], line_index: usize| DiffLine {
+        line_index,
+        content: slices[line_index]
+            .trim_end_matches(['\r', '\n'])
+            .to_string(),
+        emphasized: Vec::new(),
+    };
+    diff.ops()
+        .iter()
+        .flat_map(|diff_op| {
+            let (tag, old_range, new_range) = diff_op.as_tag_tuple();
+            let old_lines = old_range.map(|index| line(diff.old_slices(), index));
+            let new_lines = new_range.map(|index| line(diff.new_slices(), index));
+            old_lines
+                .zip_longest(new_lines)
+                .map(|lines| match lines {
+                    EitherOrBoth::Both(old, new) if tag == DiffTag::Equal => DiffRow {
+                        kind: DiffRowKind::Equal,
+                        old: Some(old),
+                        new: Some(new),
+                    },
+                    EitherOrBoth::Both(old, new) => modified_row(old, new),
+                    EitherOrBoth::Left(old) => DiffRow {
+                        kind: DiffRowKind::Deleted,
+                        old: Some(old),
+                        new: None,
+                    },
+                    EitherOrBoth::Right(new) => DiffRow {
+                        kind: DiffRowKind::Added,
+                        old: None,
+                        new: Some(new),
+                    },
+                })
+                .collect_vec()
+        })
+        .collect_vec()
+}
+
+fn modified_row(old: DiffLine, new: DiffLine) -> DiffRow {
+    let (old_emphasized, new_emphasized) = {
+        let diff = TextDiff::from_chars(&old.content, &new.content);
+        diff.ops()
+            .iter()
+            .filter(|diff_op| diff_op.tag() != DiffTag::Equal)
+            .map(|diff_op| (diff_op.old_range(), diff_op.new_range()))
+            .unzip()
+    };
+    DiffRow {
+        kind: DiffRowKind::Modified,
+        old: Some(DiffLine {
+            emphasized: old_emphasized,
+            ..old
+        }),
+        new: Some(DiffLine {
+            emphasized: new_emphasized,
+            ..new
+        }),
+    }
+}
+
+/// A side-by-side diff of two versions of a file.
+///
+/// Both panes are rendered from the same rows with the same scroll offset,
+/// so scrolling one pane always scrolls the other.
+pub(crate) struct DiffView {
+    /// Contains the rows of the focused side, so that the diff can be navigated with the usual
+    /// movements. It is restored whenever it is modified, because the diff is read-only.
+    editor: Editor,
+    rows: Vec<DiffRow>,
+    titles: [String; 2],
+    focused_side: Side,
+    rectangle: Rectangle,
+}
+
+impl DiffView {
+    pub(crate) fn new(
+        old_title: String,
+        old: &str,
+        new_title: String,
+        new: &str,
+    ) -> anyhow::Result<Self> {
+        let mut diff_view = Self {
+            editor: Editor::from_text(None, ""),
+            rows: diff_rows(old, new),
+            titles: [old_title, new_title],
+            focused_side: Side::New,
+            rectangle: Rectangle::default(),
+        };
+        diff_view.refresh_editor()?;
+        Ok(diff_view)
+    }
+
+    fn side_content(&self, side: Side) -> String {
+        self.rows
+            .iter()
+            .map(|row| {
+                row.line(side)
+                    .map(|line| line.content.as_str())
+                    .unwrap_or_default()
+            })
+            .join("\n")
+    }
+
+    fn refresh_editor(&mut self) -> anyhow::Result<()> {
+        let line = self
+            .editor
+            .get_cursor_position()
+            .map(|position| position.line)
+            .unwrap_or_default();
+        self.editor
+            .set_content(&self.side_content(self.focused_side))?;
+        let position = Position::new(line, 0);
+        self.editor.set_position_range(position..position)?;
+        Ok(())
+    }
+
+    fn side_title(&self, side: Side) -> &str {
+        match side {
+            Side::Old => &self.titles[0],
+            Side::New => &self.titles[1],
+        }
+    }
+
+    fn render_pane(
+        &self,
+        side: Side,
+        dimension: Dimension,
+        context: &Context,
+        focused: bool,
+    ) -> Grid {
+        let theme = context.theme();
+        let Dimension { height, width } = dimension;
+        let scroll_offset = self.editor.scroll_offset() as usize;
+        let line_number_width = self
+            .rows
+            .iter()
+            .flat_map(|row| [&row.old, &row.new])
+            .flatten()
+            .map(|line| line.line_index + 1)
+            .max()
+            .unwrap_or(1)
+            .to_string()
+            .len();
+        let content_width = (width as usize).saturating_sub(line_number_width + 1);
+        let visible_rows = self
+            .rows
+            .iter()
+            .skip(scroll_offset)
+            .take(height.saturating_sub(1) as usize)
+            .collect_vec();
+
+        // Lines are truncated instead of wrapped, so that the rows of both sides stay aligned
+        let content = visible_rows
+            .iter()
+            .map(|row| match row.line(side) {
+                Some(line) => format!(
+                    "{: >line_number_width$}│{}",
+                    line.line_index + 1,
+                    truncate(&line.content, content_width)
+                ),
+                None => format!("{: >line_number_width$}│", ""),
+            })
+            .join("\n");
+        let line_updates = visible_rows
+            .iter()
+            .enumerate()
+            .filter_map(|(line_index, row)| {
+                Some(LineUpdate {
+                    line_index,
+                    style: Style::new().background_color(row.background(side, theme)?),
+                })
+            })
+            .collect_vec();
+        let line_number_updates = (0..visible_rows.len()).flat_map(|line_index| {
+            (0..line_number_width + 1).map(move |column| CellUpdate {
+                style: if column == line_number_width {
+                    theme.ui.border
+                } else {
+                    theme.ui.line_number
+                },
+                ..CellUpdate::new(Position::new(line_index, column))
+            })
+        });
+        let emphasized_background = match side {
+            Side::Old => theme.hunk.old_emphasized_background,
+            Side::New => theme.hunk.new_emphasized_background,
+        };
+        let emphasized_updates = visible_rows
+            .iter()
+            .enumerate()
+            .flat_map(|(line_index, row)| {
+                row.line(side)
+                    .into_iter()
+                    .flat_map(|line| line.emphasized.iter().cloned().flatten())
+                    .filter(|column| *column < content_width)
+                    .map(move |column| CellUpdate {
+                        style: Style::new().background_color(emphasized_background),
+                        ..CellUpdate::new(Position::new(line_index, line_number_width + 1 + column))
+                    })
+            });
+        let focused_side = side == self.focused_side;
+        let cursor = self
+            .editor
+            .get_cursor_position()
+            .ok()
+            .filter(|_| focused && focused_side)
+            .and_then(|position| {
+                let line = position.line.checked_sub(scroll_offset)?;
+                let column = position.column.min(content_width.saturating_sub(1));
+                Some(
+                    CellUpdate::new(Position::new(line, line_number_width + 1 + column))
+                        .set_is_cursor(true),
+                )
+            });
+        let cell_updates = line_number_updates
+            .chain(emphasized_updates)
+            .chain(cursor)
+            .collect_vec();
+
+        let title_style = if focused && focused_side {
+            theme.ui.window_title_focused
+        } else {
+            theme.ui.window_title_unfocused
+        };
+        let title_grid = Grid::new(Dimension { height: 1, width }).render_content(
+            &truncate(self.side_title(side), width as usize),
+            RenderContentLineNumber::NoLineNumber,
+            Vec::new(),
+            [LineUpdate {
+                line_index: 0,
+                style: title_style,
+            }]
+            .to_vec(),
+            theme,
+        );
+        let content_grid = Grid::new(Dimension {
+            height: height.saturating_sub(1),
+            width,
+        })
+        .render_content(
+            &content,
+            RenderContentLineNumber::NoLineNumber,
+            cell_updates,
+            line_updates,
+            theme,
+        );
+        title_grid.merge_vertical(content_grid)
+    }
+}
+
+/// Truncates `content` such that its display width does not exceed `width`.
+fn truncate(content: &str, width: usize) -> String {
+    let mut remaining_width = width;
+    content
+        .chars()
+        .take_while(|c| {
+            let fits = get_char_width(*c) <= remaining_width;
+            remaining_width = remaining_width.saturating_sub(get_char_width(*c));
+            fits
+        })
+        .collect()
+}
+
+impl Component for DiffView {
+    fn editor(&self) -> &Editor {
+        &self.editor
+    }
+
+    fn editor_mut(&mut self) -> &mut Editor {
+        &mut self.editor
+    }
+
+    fn set_rectangle(&mut self, rectangle: Rectangle) {
+        // The editor only occupies a pane, so that its scroll offset follows the height of a pane
+        self.editor.set_rectangle(Rectangle {
+            width: rectangle.width.saturating_sub(1) / 2,
+            ..rectangle.clone()
+        });
+        self.rectangle = rectangle
+    }
+
+    fn rectangle(&self) -> &Rectangle {
+        &self.rectangle
+    }
+
+    fn title(&self, _: &Context) -> String {
+        format!(
+            "{} ↔ {}",
+            self.side_title(Side::Old),
+            self.side_title(Side::New)
+        )
+    }
+
+    fn get_grid(&self, context: &Context, focused: bool) -> GetGridResult {
+        let Dimension { height, width } = self.rectangle.dimension();
+        let old_width = width.saturating_sub(1) / 2;
+        let new_width = width.saturating_sub(1).saturating_sub(old_width);
+        let separator = Grid::new(Dimension { height, width: 1 }).render_content(
+            &vec!["│"; height as usize].join("\n"),
+            RenderContentLineNumber::NoLineNumber,
+            Vec::new(),
+            (0..height as usize)
+                .map(|line_index| LineUpdate {
+                    line_index,
+                    style: context.theme().ui.border,
+                })
+                .collect_vec(),
+            context.theme(),
+        );
+        let grid = self
+            .render_pane(
+                Side::Old,
+                Dimension {
+                    height,
+                    width: old_width,
+                },
+                context,
+                focused,
+            )
+            .merge_horizontal(separator)
+            .merge_horizontal(self.render_pane(
+                Side::New,
+                Dimension {
+                    height,
+                    width: new_width,
+                },
+                context,
+                focused,
+            ));
+        GetGridResult {
+            cursor: grid
+                .get_cursor_position()
+                .map(|position| Cursor::new(position, SetCursorStyle::BlinkingBlock)),
+            grid,
+        }
+    }
+
+    fn handle_key_event(
+        &mut self,
+        context: &Context,
+        event: event::KeyEvent,
+    ) -> anyhow::Result<Dispatches> {
+        match event {
+            key!("esc") if self.editor.mode == Mode::Normal => {
+                Ok(Dispatches::one(Dispatch::CloseCurrentWindow))
+            }
+            key!("tab") => {
+                self.focused_side = self.focused_side.toggle();
+                self.refresh_editor()?;
+                Ok(Default::default())
+            }
+            _ => {
+                let dispatches = self.editor.handle_key_event(context, event)?;
+                if self.editor.content() != self.side_content(self.focused_side) {
+                    self.editor.enter_normal_mode()?;
+                    self.refresh_editor()?;
+                }
+                Ok(dispatches)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_diff_view {
+    use super::*;
+
+    #[test]
+    fn align_rows() {
+        let rows = diff_rows("a\nb\nc\nd\n", "a\nB\nx\nc\n");
+        let sides = |side| {
+            rows.iter()
+                .map(|row| {
+                    row.line(side)
+                        .map(|line| (line.line_index, line.content.as_str()))
+                })
+                .collect_vec()
+        };
+        assert_eq!(
+            rows.iter().map(|row| row.kind).collect_vec(),
+            [
+                DiffRowKind::Equal,
+                DiffRowKind::Modified,
+                DiffRowKind::Added,
+                DiffRowKind::Equal,
+                DiffRowKind::Deleted,
+            ]
+        );
+        assert_eq!(
+            sides(Side::Old),
+            [
+                Some((0, "a")),
+                Some((1, "b")),
+                None,
+                Some((2, "c")),
+                Some((3, "d"))
+            ]
+        );
+        assert_eq!(
+            sides(Side::New),
+            [
+                Some((0, "a")),
+                Some((1, "B")),
+                Some((2, "x")),
+                Some((3, "c")),
+                None
+            ]
+        );
+        assert_eq!(rows[1].new.as_ref().unwrap().emphasized, [0..1]);
+    }
+}
@@ -1,4 +1,4 @@
-use std::cmp::Reverse;
+use std::{cmp::Reverse, ops::Range};
 
 use crate::{app::Dispatches, components::editor::Movement, position::Position};
 
@@ -18,10 +18,21 @@ pub(crate) struct DropdownItem {
     info: Option<Info>,
     /// Sorting will be based on `rank` if defined, otherwise sorting will be based on `display`
     rank: Option<Box<[usize]>>,
+    /// Groups are sorted by the best `group_rank` of their items (if defined),
+    /// after the fuzzy score
+    group_rank: Option<usize>,
 
     on_focused: Dispatches,
     /// Used to prevent spamming the LSP server with the same "completionItem/resolve" request
     resolved: bool,
+    /// Char range of `display` to be highlighted, for example the matched text of a search result
+    highlighted_char_range: Option<Range<usize>>,
+    /// Dimmed items are still listed and selectable,
+    /// for example a quickfix item that is excluded from the global replacement
+    dimmed: bool,
+    /// Dispatches to be fired when the corresponding key is pressed while this item is focused,
+    /// for example closing a buffer from the buffer list without switching to it
+    actions: Vec<(event::KeyEvent, Dispatches)>,
 }
 
 impl DropdownItem {
@@ -36,8 +47,12 @@ impl DropdownItem {
             group: Default::default(),
             info: Default::default(),
             rank: None,
+            group_rank: None,
             on_focused: Default::default(),
             resolved: false,
+            highlighted_char_range: None,
+            dimmed: false,
+            actions: Vec::new(),
         }
     }
 
@@ -57,10 +72,39 @@ impl DropdownItem {
         Self { rank, ..self }
     }
 
+    pub(crate) fn set_group_rank(self, group_rank: Option<usize>) -> DropdownItem {
+        Self { group_rank, ..self }
+    }
+
     pub(crate) fn set_on_focused(self, on_focused: Dispatches) -> DropdownItem {
         Self { on_focused, ..self }
     }
 
+    pub(crate) fn set_highlighted_char_range(
+        self,
+        highlighted_char_range: Option<Range<usize>>,
+    ) -> DropdownItem {
+        Self {
+            highlighted_char_range,
+            ..self
+        }
+    }
+
+    pub(crate) fn set_dimmed(self, dimmed: bool) -> DropdownItem {
+        Self { dimmed, ..self }
+    }
+
+    pub(crate) fn set_actions(self, actions: Vec<(event::KeyEvent, Dispatches)>) -> DropdownItem {
+        Self { actions, ..self }
+    }
+
+    pub(crate) fn action(&self, event: &event::KeyEvent) -> Option<Dispatches> {
+        self.actions
+            .iter()
+            .find(|(key, _)| key == event)
+            .map(|(_, dispatches)| dispatches.clone())
+    }
+
     pub(crate) fn on_focused(&self) -> Dispatches {
         self.on_focused.clone()
     }
@@ -363,6 +407,11 @@ impl Dropdown {
                             .max()
                             .unwrap_or_default(),
                     ),
+                    group
+                        .items
+                        .iter()
+                        .filter_map(|item| item.item.group_rank)
+                        .min(),
                     group.group_key.clone(),
                 )
             })
@@ -523,23 +572,51 @@ impl Dropdown {
                 let display_decorations = group.items.iter().flat_map(|item| {
                     let line_index = self.item_line_index(item.item_index as usize);
                     let pad_left = if item.item.group.is_some() { 4 } else { 0 };
-                    item.fuzzy_matched_char_indices
-                        .iter()
-                        .map(move |matched_char_index| {
-                            let column_index = (matched_char_index + pad_left) as usize;
-                            Decoration::new(
-                                crate::selection_range::SelectionRange::Position(
-                                    Position {
-                                        line: line_index,
-                                        column: column_index,
-                                    }..Position {
-                                        line: line_index,
-                                        column: column_index + 1,
-                                    },
-                                ),
-                                crate::grid::StyleKey::UiFuzzyMatchedChar,
-                            )
-                        })
+                    // The whole line of a dimmed item is dimmed instead of highlighted
+                    let highlighted_range_decoration = if item.item.dimmed {
+                        Some((
+                            0..item.item.display.chars().count(),
+                            crate::grid::StyleKey::UiDimmed,
+                        ))
+                    } else {
+                        item.item
+                            .highlighted_char_range
+                            .clone()
+                            .map(|range| (range, crate::grid::StyleKey::UiPossibleSelection))
+                    }
+                    .map(|(range, style_key)| {
+                        Decoration::new(
+                            crate::selection_range::SelectionRange::Position(
+                                Position {
+                                    line: line_index,
+                                    column: range.start + pad_left as usize,
+                                }..Position {
+                                    line: line_index,
+                                    column: range.end + pad_left as usize,
+                                },
+                            ),
+                            style_key,
+                        )
+                    });
+                    highlighted_range_decoration.into_iter().chain(
+                        item.fuzzy_matched_char_indices
+                            .iter()
+                            .map(move |matched_char_index| {
+                                let column_index = (matched_char_index + pad_left) as usize;
+                                Decoration::new(
+                                    crate::selection_range::SelectionRange::Position(
+                                        Position {
+                                            line: line_index,
+                                            column: column_index,
+                                        }..Position {
+                                            line: line_index,
+                                            column: column_index + 1,
+                                        },
+                                    ),
+                                    crate::grid::StyleKey::UiFuzzyMatchedChar,
+                                )
+                            }),
+                    )
                 });
                 group_decorations.chain(display_decorations)
             })
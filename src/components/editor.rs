@@ -3,9 +3,16 @@ use crate::{
     buffer::Line,
     char_index_range::CharIndexRange,
     clipboard::CopiedTexts,
-    context::{Context, GlobalMode, LocalSearchConfigMode, Search},
-    lsp::{completion::CompletionItemEdit, process::ResponseContext},
+    context::{Context, GlobalMode, LocalSearchConfig, LocalSearchConfigMode, Search},
+    fold, increment,
+    insert_assist::{self, AutoPair, NewLineIndentation},
+    lsp::{
+        completion::CompletionItemEdit, document_highlight::DocumentHighlight,
+        process::ResponseContext,
+    },
+    outline::{self, Outline},
     selection_mode::{self, regex::get_regex},
+    spell_check::SpellChecker,
     surround::EnclosureKind,
     transformation::{MyRegex, Transformation},
 };
@@ -14,6 +21,7 @@ use nonempty::NonEmpty;
 use shared::canonicalized_path::CanonicalizedPath;
 use std::{
     cell::{Ref, RefCell, RefMut},
+    collections::{BTreeSet, HashSet},
     ops::{Not, Range},
     rc::Rc,
 };
@@ -29,6 +37,8 @@ use crate::{
     buffer::Buffer,
     components::component::Component,
     edit::{Action, ActionGroup, Edit, EditTransaction},
+    editorconfig::EndOfLine,
+    indent_guide::IndentGuidesCache,
     lsp::completion::PositionalEdit,
     position::Position,
     rectangle::Rectangle,
@@ -40,7 +50,9 @@ use DispatchEditor::*;
 use super::{
     component::ComponentId,
     dropdown::DropdownRender,
+    minimap::{Minimap, MINIMAP_MIN_WINDOW_WIDTH, MINIMAP_WIDTH},
     render_editor::Source,
+    smooth_scroll::ScrollAnimation,
     suggestive_editor::{Decoration, Info},
 };
 
@@ -49,6 +61,12 @@ pub(crate) enum Mode {
     Normal,
     Insert,
     MultiCursor,
+    /// Movements move the `cursor` corner of the rectangle of characters that is selected
+    /// on every line between the `cursor` and the `anchor`, see `Editor::block_selection_set`
+    Block {
+        anchor: Position,
+        cursor: Position,
+    },
     FindOneChar(IfCurrentNotFound),
     Exchange,
     UndoTree,
@@ -141,7 +159,9 @@ impl Component for Editor {
                 self.apply_scroll(Direction::End, SCROLL_HEIGHT);
                 Ok(Default::default())
             }
-            MouseEventKind::Down(MouseButton::Left) => Ok(Default::default()),
+            MouseEventKind::Down(MouseButton::Left) => self
+                .handle_minimap_click(mouse_event.column, mouse_event.row)
+                .unwrap_or_else(|| Ok(Default::default())),
             _ => Ok(Default::default()),
         }
     }
@@ -168,11 +188,10 @@ impl Component for Editor {
     ) -> anyhow::Result<Dispatches> {
         match event {
             event::event::Event::Key(event) => self.handle_key_event(context, event),
-            event::event::Event::Paste(content) => self.paste_text(
-                Direction::End,
-                CopiedTexts::new(NonEmpty::singleton(content)),
-            ),
+            event::event::Event::Paste(content) => self.paste_from_terminal(content),
             event::event::Event::Mouse(event) => self.handle_mouse_event(event),
+            event::event::Event::ImePreedit(preedit) => self.handle_ime_preedit(preedit),
+            event::event::Event::ImeCommit(text) => self.handle_ime_commit(context, text),
             _ => Ok(Default::default()),
         }
     }
@@ -230,6 +249,7 @@ impl Component for Editor {
                 let dispatches = self.undo();
                 return dispatches;
             }
+            UndoWithinSelection => return self.undo_within_selection(),
             KillLine(direction) => return self.kill_line(direction),
             #[cfg(test)]
             Reset => self.reset(),
@@ -237,6 +257,10 @@ impl Component for Editor {
             Backspace => return self.backspace(),
             MoveToLineStart => return self.move_to_line_start(),
             MoveToLineEnd => return self.move_to_line_end(),
+            MoveVertically { direction, display } => {
+                return self.move_vertically(direction, display)
+            }
+            MoveToDisplayLineEdge(direction) => return self.move_to_display_line_edge(direction),
             SelectLine(movement) => return self.select_line(movement),
             Redo => return self.redo(),
             Change => return self.change(),
@@ -247,6 +271,8 @@ impl Component for Editor {
             SetRectangle(rectangle) => self.set_rectangle(rectangle),
             ScrollPageDown => return self.scroll_page_down(),
             ScrollPageUp => return self.scroll_page_up(),
+            ScrollFullPageDown => return self.scroll_full_page(Direction::End),
+            ScrollFullPageUp => return self.scroll_full_page(Direction::Start),
             ShowJumps {
                 use_current_selection_mode,
             } => self.show_jumps(use_current_selection_mode)?,
@@ -264,11 +290,25 @@ impl Component for Editor {
                 return self.replace_current_selection_with(|_| Some(Rope::from_str(&string)))
             }
             SelectLineAt(index) => return Ok(self.select_line_at(index)?.into_vec().into()),
+            GoToLine {
+                line,
+                store_history,
+            } => return self.go_to_line(line, store_history),
+            RestoreView {
+                selection_set,
+                scroll_offset,
+            } => self.restore_view(selection_set, scroll_offset),
+            ExpandSyntaxNode => return self.expand_syntax_node(),
+            ShrinkSyntaxNode => return self.shrink_syntax_node(),
+            MoveToSiblingSyntaxNode(direction) => {
+                return self.move_to_sibling_syntax_node(direction)
+            }
             EnterMultiCursorMode => self.enter_multicursor_mode(),
+            EnterBlockMode => return self.enter_block_mode(),
             Surround(open, close) => return self.enclose(open, close),
             ShowKeymapLegendInsertMode => {
                 return Ok([Dispatch::ShowKeymapLegend(
-                    self.insert_mode_keymap_legend_config(),
+                    self.insert_mode_keymap_legend_config(context),
                 )]
                 .to_vec()
                 .into())
@@ -292,6 +332,9 @@ impl Component for Editor {
                 direction,
                 use_system_clipboard,
             } => return self.paste(direction, context, use_system_clipboard),
+            PastePrimarySelection(direction) => {
+                return self.paste_text(direction, context.get_primary_selection_content()?)
+            }
             SwapCursorWithAnchor => self.swap_cursor_with_anchor(),
             SetDecorations(decorations) => self.buffer_mut().set_decorations(&decorations),
             MoveCharacterBack => self.selection_set.move_left(&self.cursor_direction),
@@ -301,15 +344,20 @@ impl Component for Editor {
                     .move_right(&self.cursor_direction, len_chars)
             }
             Open(direction) => return self.open(direction),
+            InsertLinesBelow(content) => return self.insert_lines_below(&content),
+            MoveToGitGutterHunk(direction) => return self.move_to_git_gutter_hunk(direction),
+            RevertGitGutterHunk => return self.revert_git_gutter_hunk(),
             TryReplaceCurrentLongWord(replacement) => {
                 return self.try_replace_current_long_word(replacement)
             }
             GoBack => self.go_back(),
             GoForward => self.go_forward(),
             SelectSurround { enclosure, kind } => return self.select_surround(enclosure, kind),
-            DeleteSurround(enclosure) => return self.delete_surround(enclosure),
-            ChangeSurround { from, to } => return self.change_surround(from, Some(to)),
-            ReplaceWithPattern => return self.replace_with_pattern(context),
+            DeleteSurround(enclosure) => return self.delete_surround(Some(enclosure)),
+            DeleteNearestSurround => return self.delete_surround(None),
+            ChangeSurround { from, to } => return self.change_surround(Some(from), Some(to)),
+            ChangeNearestSurround(to) => return self.change_surround(None, Some(to)),
+            ReplaceWithPattern => return self.replace_with_pattern(context.local_search_config()),
             Replace(movement) => return self.replace_with_movement(&movement),
             ApplyPositionalEdits(edits) => {
                 return self.apply_positional_edits(
@@ -330,13 +378,29 @@ impl Component for Editor {
                 return self.replace_with_copied_text(context, false, false, history_offset);
             }
             MoveToLastChar => return Ok(self.move_to_last_char()),
-            PipeToShell { command } => return self.pipe_to_shell(command),
             ShowCurrentTreeSitterNodeSexp => return self.show_current_tree_sitter_node_sexp(),
             Indent => return self.indent(),
+            ToggleComment => return self.toggle_comment(),
+            Fold => return self.fold(),
+            Unfold => self.unfold(),
+            FoldAll => self.fold_all(),
+            UnfoldAll => self.buffer_mut().clear_folds(),
+            PromoteHeading => return self.change_heading_level(true),
+            DemoteHeading => return self.change_heading_level(false),
+            MoveHeadingSubtree(direction) => return self.move_heading_subtree(direction),
+            FoldHeadings => return self.fold_headings(),
+            SetLineEnding(line_ending) => return self.set_line_ending(line_ending),
+            TrimTrailingWhitespace => return self.trim_trailing_whitespace(),
+            AppendTerminator => return self.append_terminator(),
             Dedent => return self.dedent(),
             CyclePrimarySelection(direction) => self.cycle_primary_selection(direction),
             SwapExtensionDirection => self.selection_set.swap_initial_range_direction(),
             CollapseSelection(direction) => return self.collapse_selection(context, direction),
+            Increment { amount, sequential } => return self.increment(amount, sequential),
+            GoToMatchingBracket => return Ok(self.go_to_matching_bracket()),
+            Align(pattern) => {
+                return self.align(context.get_local_search_config(Scope::Local), &pattern)
+            }
             FilterSelectionMatchingSearch { maintain, search } => {
                 self.mode = Mode::Normal;
                 return Ok(self.filter_selection_matching_search(
@@ -361,6 +425,16 @@ impl Clone for Editor {
             jumps: None,
             cursor_direction: self.cursor_direction.clone(),
             scroll_offset: self.scroll_offset,
+            scroll_off: self.scroll_off,
+            scrollbar: self.scrollbar,
+            minimap: self.minimap,
+            whitespace: self.whitespace,
+            indent_guides: self.indent_guides,
+            indent_guides_cache: IndentGuidesCache::default(),
+            cursor_line: self.cursor_line,
+            cursor_column: self.cursor_column,
+            smooth_scroll: self.smooth_scroll,
+            scroll_animation: None,
             rectangle: self.rectangle.clone(),
             buffer: self.buffer.clone(),
             title: self.title.clone(),
@@ -368,6 +442,9 @@ impl Clone for Editor {
             current_view_alignment: None,
             regex_highlight_rules: Vec::new(),
             copied_text_history_offset: Default::default(),
+            ime_preedit: None,
+            syntax_node_expansions: Vec::new(),
+            document_highlights: None,
         }
     }
 }
@@ -384,12 +461,42 @@ pub(crate) struct Editor {
     /// This means the number of lines to be skipped from the top during rendering.
     /// 2 means the first line to be rendered on the screen if the 3rd line of the text.
     scroll_offset: u16,
+    /// The number of lines that are kept visible above and below the cursor.
+    scroll_off: u16,
+    /// Whether a scrollbar is rendered in the rightmost column,
+    /// which is then left out of the render area
+    scrollbar: bool,
+    /// Whether a minimap is rendered beside the content, see `minimap_width`
+    minimap: bool,
+    /// Whether tabs, trailing spaces, non-breaking spaces and line endings are rendered
+    /// as dimmed symbols
+    whitespace: bool,
+    /// Whether a vertical guide is rendered at each indentation level
+    indent_guides: bool,
+    indent_guides_cache: IndentGuidesCache,
+    /// Whether the row of the cursor is highlighted, see `Grid::highlight_row_and_column`
+    cursor_line: bool,
+    /// Whether the column of the cursor is highlighted
+    cursor_column: bool,
+    /// Whether scrolling by pages is animated
+    smooth_scroll: bool,
+    /// The running animation, whose current frame is `scroll_offset`
+    scroll_animation: Option<ScrollAnimation>,
     rectangle: Rectangle,
     buffer: Rc<RefCell<Buffer>>,
     title: Option<String>,
     id: ComponentId,
     pub(crate) current_view_alignment: Option<ViewAlignment>,
     copied_text_history_offset: Counter,
+    /// The text being composed by an input method, which is rendered at the cursor
+    /// but not inserted into the buffer until it is committed.
+    ime_preedit: Option<String>,
+    /// Each element is the selection set before an expansion to the enclosing syntax nodes,
+    /// paired with the expanded selection set, so that shrinking can return to the previous child.
+    syntax_node_expansions: Vec<(SelectionSet, SelectionSet)>,
+    /// The occurrences of the symbol under the cursor, paired with whether they are written,
+    /// which are only rendered while the cursor stays at the char index where they were requested
+    document_highlights: Option<(CharIndex, Vec<(CharIndexRange, bool)>)>,
 }
 
 #[derive(Default)]
@@ -531,6 +638,16 @@ impl Editor {
             mode: Mode::Normal,
             cursor_direction: Direction::Start,
             scroll_offset: 0,
+            scroll_off: 0,
+            scrollbar: false,
+            minimap: false,
+            whitespace: false,
+            indent_guides: false,
+            indent_guides_cache: IndentGuidesCache::default(),
+            cursor_line: false,
+            cursor_column: false,
+            smooth_scroll: false,
+            scroll_animation: None,
             rectangle: Rectangle::default(),
             buffer: Rc::new(RefCell::new(Buffer::new(language, text))),
             title: None,
@@ -538,6 +655,9 @@ impl Editor {
             current_view_alignment: None,
             regex_highlight_rules: Vec::new(),
             copied_text_history_offset: Default::default(),
+            ime_preedit: None,
+            syntax_node_expansions: Vec::new(),
+            document_highlights: None,
         }
     }
 
@@ -548,6 +668,16 @@ impl Editor {
             mode: Mode::Normal,
             cursor_direction: Direction::Start,
             scroll_offset: 0,
+            scroll_off: 0,
+            scrollbar: false,
+            minimap: false,
+            whitespace: false,
+            indent_guides: false,
+            indent_guides_cache: IndentGuidesCache::default(),
+            cursor_line: false,
+            cursor_column: false,
+            smooth_scroll: false,
+            scroll_animation: None,
             rectangle: Rectangle::default(),
             buffer,
             title: None,
@@ -555,9 +685,50 @@ impl Editor {
             current_view_alignment: None,
             regex_highlight_rules: Vec::new(),
             copied_text_history_offset: Default::default(),
+            ime_preedit: None,
+            syntax_node_expansions: Vec::new(),
+            document_highlights: None,
+        }
+    }
+
+    /// An empty `preedit` means the composition is cancelled.
+    pub(crate) fn set_ime_preedit(&mut self, preedit: String) {
+        self.ime_preedit = (self.mode == Mode::Insert && !preedit.is_empty()).then_some(preedit)
+    }
+
+    pub(crate) fn ime_preedit(&self) -> Option<&str> {
+        self.ime_preedit.as_deref()
+    }
+
+    /// The highlights that cannot be converted (e.g. outdated by an edit) are ignored.
+    pub(crate) fn set_document_highlights(&mut self, highlights: Vec<DocumentHighlight>) {
+        let anchor = self.get_cursor_char_index();
+        let buffer = self.buffer.borrow();
+        let highlights = highlights
+            .into_iter()
+            .filter_map(|highlight| {
+                let range = buffer
+                    .position_range_to_char_index_range(&highlight.range)
+                    .ok()?;
+                Some((range, highlight.is_write))
+            })
+            .collect();
+        self.document_highlights = Some((anchor, highlights))
+    }
+
+    /// Empty once the cursor moves away from where the highlights were requested.
+    pub(crate) fn document_highlights(&self) -> &[(CharIndexRange, bool)] {
+        match &self.document_highlights {
+            Some((anchor, highlights)) if *anchor == self.get_cursor_char_index() => highlights,
+            _ => &[],
         }
     }
 
+    pub(crate) fn commit_ime(&mut self, text: &str) -> anyhow::Result<Dispatches> {
+        self.ime_preedit = None;
+        self.insert(text)
+    }
+
     pub(crate) fn current_line(&self) -> anyhow::Result<String> {
         let cursor = self.get_cursor_char_index();
         Ok(self
@@ -594,6 +765,83 @@ impl Editor {
         Ok(self.update_selection_set(selection_set, false))
     }
 
+    /// Selects the line at the given 0-based index (clamped to the last line),
+    /// and centers the view around it.
+    ///
+    /// `store_history` is false when previewing a line,
+    /// so that only the confirmed line is added to the selection history.
+    pub(crate) fn go_to_line(
+        &mut self,
+        line: usize,
+        store_history: bool,
+    ) -> anyhow::Result<Dispatches> {
+        let line = line.min(self.buffer().len_lines().saturating_sub(1));
+        let start = self.buffer().line_to_char(line)?;
+        let cursor = SelectionSet::new(NonEmpty::singleton(Selection::new((start..start).into())));
+        let selection_set = cursor
+            .generate(
+                &self.buffer.borrow(),
+                &SelectionMode::Line,
+                &Movement::Current(IfCurrentNotFound::LookForward),
+                &Direction::Start,
+            )?
+            .unwrap_or(cursor);
+        let dispatches = self.update_selection_set(selection_set, store_history);
+        self.align_cursor_to_center();
+        Ok(dispatches)
+    }
+
+    /// Used for undoing the previews of a prompt.
+    fn restore_view(&mut self, selection_set: SelectionSet, scroll_offset: u16) {
+        self.set_selection_set(selection_set);
+        self.scroll_offset = scroll_offset;
+    }
+
+    pub(crate) fn expand_syntax_node(&mut self) -> anyhow::Result<Dispatches> {
+        let Some(selection_set) =
+            self.get_selection_set(&SelectionMode::SyntaxNode, Movement::Up)?
+        else {
+            return Ok(Default::default());
+        };
+        if selection_set.map(|selection| selection.extended_range())
+            == self
+                .selection_set
+                .map(|selection| selection.extended_range())
+        {
+            return Ok(Default::default());
+        }
+        self.syntax_node_expansions
+            .push((self.selection_set.clone(), selection_set.clone()));
+        Ok(self.update_selection_set(selection_set, true))
+    }
+
+    /// Returns to the selections before the last expansion,
+    /// unless the selections were changed since then,
+    /// in which case the first named child of each syntax node is selected.
+    pub(crate) fn shrink_syntax_node(&mut self) -> anyhow::Result<Dispatches> {
+        match self.syntax_node_expansions.pop() {
+            Some((previous, expanded)) if expanded == self.selection_set => {
+                Ok(self.update_selection_set(previous, true))
+            }
+            _ => {
+                self.syntax_node_expansions.clear();
+                self.select(SelectionMode::SyntaxNode, Movement::Down)
+            }
+        }
+    }
+
+    /// Named nodes exclude anonymous nodes such as punctuations.
+    pub(crate) fn move_to_sibling_syntax_node(
+        &mut self,
+        direction: Direction,
+    ) -> anyhow::Result<Dispatches> {
+        let movement = match direction {
+            Direction::Start => Movement::Previous,
+            Direction::End => Movement::Next,
+        };
+        self.select(SelectionMode::SyntaxNode, movement)
+    }
+
     #[cfg(test)]
     pub(crate) fn reset(&mut self) {
         self.selection_set.escape_highlight_mode();
@@ -646,12 +894,157 @@ impl Editor {
             .line as u16
     }
 
+    /// The number of rows between the top of the view and the cursor,
+    /// where the lines hidden by folds are not counted.
+    fn cursor_row_in_view(&self) -> u16 {
+        let hidden_line_ranges = self.buffer().hidden_line_ranges();
+        fold::visible_lines(
+            &hidden_line_ranges,
+            self.scroll_offset as usize,
+            self.cursor_row() as usize,
+        )
+        .count() as u16
+    }
+
+    /// Returns the line that is `rows` visible lines above the cursor.
+    fn line_above_cursor(&self, rows: u16) -> u16 {
+        fold::visible_line_above(
+            &self.buffer().hidden_line_ranges(),
+            self.cursor_row() as usize,
+            rows as usize,
+        ) as u16
+    }
+
+    pub(crate) fn set_scroll_off(&mut self, scroll_off: usize) {
+        self.scroll_off = scroll_off as u16
+    }
+
+    pub(crate) fn set_scrollbar(&mut self, scrollbar: bool) {
+        self.scrollbar = scrollbar
+    }
+
+    pub(crate) fn scrollbar(&self) -> bool {
+        self.scrollbar
+    }
+
+    pub(crate) fn set_minimap(&mut self, minimap: bool) {
+        self.minimap = minimap
+    }
+
+    pub(crate) fn set_whitespace(&mut self, whitespace: bool) {
+        self.whitespace = whitespace
+    }
+
+    pub(crate) fn whitespace(&self) -> bool {
+        self.whitespace
+    }
+
+    pub(crate) fn set_indent_guides(&mut self, indent_guides: bool) {
+        self.indent_guides = indent_guides
+    }
+
+    pub(crate) fn indent_guides(&self) -> bool {
+        self.indent_guides
+    }
+
+    pub(crate) fn indent_guides_cache(&self) -> &IndentGuidesCache {
+        &self.indent_guides_cache
+    }
+
+    pub(crate) fn set_cursor_line(&mut self, cursor_line: bool) {
+        self.cursor_line = cursor_line
+    }
+
+    pub(crate) fn set_cursor_column(&mut self, cursor_column: bool) {
+        self.cursor_column = cursor_column
+    }
+
+    pub(crate) fn highlights_cursor_line(&self) -> bool {
+        self.cursor_line
+    }
+
+    pub(crate) fn highlights_cursor_column(&self) -> bool {
+        self.cursor_column
+    }
+
+    pub(crate) fn set_smooth_scroll(&mut self, smooth_scroll: bool) {
+        self.smooth_scroll = smooth_scroll
+    }
+
+    /// Moves `scroll_offset` to the current frame of the scroll animation, if any.
+    /// Returns true if the animation is not finished yet.
+    pub(crate) fn tick_scroll_animation(&mut self) -> bool {
+        let Some(animation) = &self.scroll_animation else {
+            return false;
+        };
+        match animation.scroll_offset() {
+            Some(scroll_offset) => {
+                self.scroll_offset = scroll_offset;
+                true
+            }
+            None => {
+                self.finish_scroll_animation();
+                false
+            }
+        }
+    }
+
+    fn finish_scroll_animation(&mut self) {
+        if let Some(animation) = self.scroll_animation.take() {
+            self.scroll_offset = animation.to()
+        }
+    }
+
+    /// Zero if the minimap is disabled, or if the window is too narrow for it.
+    pub(crate) fn minimap_width(&self) -> u16 {
+        if self.minimap && self.dimension().width >= MINIMAP_MIN_WINDOW_WIDTH {
+            MINIMAP_WIDTH
+        } else {
+            0
+        }
+    }
+
+    pub(crate) fn minimap(&self) -> Option<Minimap> {
+        (self.minimap_width() > 0).then(|| {
+            Minimap::new(
+                self.render_area().height as usize,
+                self.buffer().len_lines(),
+                &self.visible_line_range(),
+            )
+        })
+    }
+
+    /// Jumps to the line of the clicked row of the minimap,
+    /// or returns `None` if the minimap is not clicked.
+    fn handle_minimap_click(
+        &mut self,
+        column: u16,
+        row: u16,
+    ) -> Option<anyhow::Result<Dispatches>> {
+        let minimap = self.minimap()?;
+        let origin = &self.rectangle.origin;
+        let column = (column as usize).checked_sub(origin.column)?;
+        let row = (row as usize).checked_sub(origin.line + WINDOW_TITLE_HEIGHT)?;
+        let minimap_start = self.render_area().width as usize;
+        if !(minimap_start..minimap_start + MINIMAP_WIDTH as usize).contains(&column) {
+            return None;
+        }
+        let line = minimap.line_at_row(row)?;
+        Some(self.go_to_line(line, true).map(|dispatches| {
+            self.align_cursor_to_center();
+            dispatches
+        }))
+    }
+
     fn recalculate_scroll_offset(&mut self) {
-        // Update scroll_offset if primary selection is out of view.
+        // Update scroll_offset if primary selection is out of view,
+        // or within `scroll_off` lines of the top or bottom of the view.
         let cursor_row = self.cursor_row();
-        let render_area = self.render_area();
-        if cursor_row.saturating_sub(self.scroll_offset) > render_area.height.saturating_sub(1)
+        let last_row = self.render_area().height.saturating_sub(1);
+        let scroll_off = self.scroll_off.min(last_row / 2);
+        if self.cursor_row_in_view() > last_row - scroll_off
             || cursor_row < self.scroll_offset
+            || (self.scroll_offset > 0 && cursor_row < self.scroll_offset + scroll_off)
         {
             self.align_cursor_to_center();
             self.current_view_alignment = None;
@@ -659,7 +1052,7 @@ impl Editor {
     }
 
     pub(crate) fn align_cursor_to_bottom(&mut self) {
-        self.scroll_offset = self.cursor_row().saturating_sub(
+        self.scroll_offset = self.line_above_cursor(
             self.rectangle
                 .height
                 .saturating_sub(1)
@@ -672,9 +1065,8 @@ impl Editor {
     }
 
     fn align_cursor_to_center(&mut self) {
-        self.scroll_offset = self
-            .cursor_row()
-            .saturating_sub((self.rectangle.height as f64 / 2.0).ceil() as u16);
+        self.scroll_offset =
+            self.line_above_cursor((self.rectangle.height as f64 / 2.0).ceil() as u16);
     }
 
     pub(crate) fn select(
@@ -683,10 +1075,30 @@ impl Editor {
         movement: Movement,
     ) -> anyhow::Result<Dispatches> {
         //  There are a few selection modes where Current make sense.
-        if let Some(selection_set) = self.get_selection_set(&selection_mode, movement)? {
-            Ok(self.update_selection_set(selection_set, true))
-        } else {
-            Ok(Default::default())
+        let directional = matches!(
+            movement,
+            Movement::Left
+                | Movement::Right
+                | Movement::Next
+                | Movement::Previous
+                | Movement::Up
+                | Movement::Down
+        );
+        match self.get_selection_set(&selection_mode, movement)? {
+            // Moving beyond the boundaries of the buffer does not change the selections
+            Some(selection_set)
+                if directional
+                    && selection_set.mode == self.selection_set.mode
+                    && selection_set.map(|selection| selection.extended_range())
+                        == self
+                            .selection_set
+                            .map(|selection| selection.extended_range()) =>
+            {
+                Ok(Dispatches::one(Dispatch::RingBell))
+            }
+            Some(selection_set) => Ok(self.update_selection_set(selection_set, true)),
+            None if directional => Ok(Dispatches::one(Dispatch::RingBell)),
+            None => Ok(Default::default()),
         }
     }
 
@@ -847,6 +1259,8 @@ impl Editor {
     fn enter_newline(&mut self) -> anyhow::Result<Dispatches> {
         let edit_transaction = EditTransaction::from_action_groups({
             let buffer = self.buffer();
+            let content = buffer.content();
+            let indent_unit = buffer.indentation().unit();
             self.selection_set
                 .map(|selection| -> anyhow::Result<_> {
                     let cursor = selection.extended_range().start;
@@ -854,8 +1268,8 @@ impl Editor {
 
                     let current_line = buffer.get_line_by_line_index(current_line_index);
 
-                    let indent = "\n".to_string()
-                        + current_line
+                    let previous_line_indentation = || {
+                        current_line
                             .map(|line| {
                                 line.to_string()
                                     .chars()
@@ -863,14 +1277,48 @@ impl Editor {
                                     .join("")
                             })
                             .unwrap_or_default()
-                            .as_str();
+                    };
+
+                    // Fallback to the indentation of the current line
+                    // if the language has no indent queries
+                    let new_line_indentation = match (
+                        buffer.tree(),
+                        buffer
+                            .language()
+                            .and_then(|language| language.indents_query()),
+                    ) {
+                        (Some(tree), Some(query)) => crate::insert_assist::new_line_indentation(
+                            tree,
+                            query,
+                            &content,
+                            buffer.char_to_byte(cursor)?,
+                            &indent_unit,
+                        )
+                        .unwrap_or_else(|error| {
+                            log::error!("Failed to compute indentation: {error}");
+                            None
+                        }),
+                        _ => None,
+                    };
+                    let (indentation, outdent) = match new_line_indentation {
+                        Some(NewLineIndentation {
+                            indentation,
+                            outdent,
+                        }) => (indentation, outdent),
+                        None => (previous_line_indentation(), None),
+                    };
 
+                    let indent = format!("\n{indentation}");
                     let range_start = cursor + indent.chars().count();
+                    let new = match outdent {
+                        Some(outdent) => format!("{indent}\n{outdent}"),
+                        None => indent,
+                    };
                     Ok(ActionGroup::new(
                         [
                             Action::Edit(Edit {
                                 range: (cursor..cursor).into(),
-                                new: indent.into(),
+                                new: new.into(),
                             }),
                             Action::Select(
                                 selection
@@ -1044,6 +1492,19 @@ impl Editor {
         self.apply_edit_transaction(edit_transaction)
     }
 
+    /// Pastes the content of a bracketed paste as a single edit, so that it is undone at once,
+    /// and neither the auto pairs, the auto indentation nor the keymaps apply to its characters.
+    ///
+    /// Terminals usually send the line breaks of a paste as carriage returns,
+    /// so they are converted into the line ending of the buffer.
+    pub(crate) fn paste_from_terminal(&mut self, content: String) -> anyhow::Result<Dispatches> {
+        let content = self.buffer().line_ending().convert(&content);
+        self.paste_text(
+            Direction::End,
+            CopiedTexts::new(NonEmpty::singleton(content)),
+        )
+    }
+
     pub(crate) fn paste(
         &mut self,
         direction: Direction,
@@ -1102,21 +1563,28 @@ impl Editor {
         Ok(self.get_document_did_change_dispatch())
     }
 
+    /// Large files are neither highlighted as a whole nor attached to the LSP servers,
+    /// so only their highlighted lines are reset, which are highlighted again on render.
     pub(crate) fn get_document_did_change_dispatch(&mut self) -> Dispatches {
-        [Dispatch::DocumentDidChange {
-            component_id: self.id(),
-            path: self.buffer().path(),
-            content: self.buffer().rope().to_string(),
-            language: self.buffer().language(),
-        }]
-        .into_iter()
-        .chain(if self.mode == Mode::UndoTree {
-            Some(self.show_undo_tree_dispatch())
-        } else {
-            None
-        })
-        .collect_vec()
-        .into()
+        let large_file = self.buffer().is_large_file();
+        if large_file {
+            self.buffer_mut().reset_highlighted_line_range()
+        }
+        (!large_file)
+            .then(|| Dispatch::DocumentDidChange {
+                component_id: self.id(),
+                path: self.buffer().path(),
+                content: self.buffer().rope().to_string(),
+                language: self.buffer().language(),
+            })
+            .into_iter()
+            .chain(if self.mode == Mode::UndoTree {
+                Some(self.show_undo_tree_dispatch())
+            } else {
+                None
+            })
+            .collect_vec()
+            .into()
     }
 
     pub(crate) fn enter_undo_tree_mode(&mut self) -> Dispatches {
@@ -1140,6 +1608,15 @@ impl Editor {
         self.navigate_undo_tree(Movement::Right)
     }
 
+    fn undo_within_selection(&mut self) -> anyhow::Result<Dispatches> {
+        let range = self.selection_set.primary_selection().extended_range();
+        let selection_set = self.selection_set.clone();
+        let selection_set = self.buffer_mut().undo_within(range, selection_set)?;
+        Ok(self
+            .update_selection_set(selection_set, false)
+            .chain(self.get_document_did_change_dispatch()))
+    }
+
     pub(crate) fn swap_cursor_with_anchor(&mut self) {
         self.cursor_direction = match self.cursor_direction {
             Direction::Start => Direction::End,
@@ -1187,8 +1664,9 @@ impl Editor {
                 } else {
                     match &self.mode {
                         Mode::Normal => self.handle_normal_mode(context, key_event),
-                        Mode::Insert => self.handle_insert_mode(key_event),
+                        Mode::Insert => self.handle_insert_mode(context, key_event),
                         Mode::MultiCursor => self.handle_multi_cursor_mode(context, key_event),
+                        Mode::Block { .. } => self.handle_block_mode(context, key_event),
                         Mode::FindOneChar(if_current_not_found) => {
                             self.handle_find_one_char_mode(*if_current_not_found, key_event)
                         }
@@ -1309,6 +1787,249 @@ impl Editor {
         self.apply_edit_transaction(edit_transaction)
     }
 
+    /// Closes brackets and quotes, or skips over the closing ones that are inserted automatically,
+    /// according to the auto pairs of the language of the buffer.
+    ///
+    /// If `wrap_selection` is true, typing an opening character wraps each non-empty selection
+    /// in its pair, where the wrapped text remains selected.
+    pub(crate) fn insert_char(
+        &mut self,
+        c: char,
+        wrap_selection: bool,
+    ) -> anyhow::Result<Dispatches> {
+        let Some(pairs) = self
+            .buffer()
+            .language()
+            .map(|language| language.auto_pairs())
+        else {
+            return self.insert(&c.to_string());
+        };
+        let wrapping_close = pairs
+            .iter()
+            .find(|(open, _)| wrap_selection && *open == c)
+            .map(|(_, close)| *close);
+        // Each action group, and the auto closing that it skips over
+        let (action_groups, skipped_over): (Vec<_>, Vec<_>) = {
+            let buffer = self.buffer();
+            self.selection_set
+                .map(|selection| {
+                    let range = selection.extended_range();
+                    if let Some(close) = wrapping_close.filter(|_| !range.is_empty()) {
+                        let text = buffer.slice(&range).unwrap_or_default();
+                        let action_group = ActionGroup::new(
+                            [
+                                Action::Edit(Edit {
+                                    range,
+                                    new: Rope::from_str(&format!("{c}{text}{close}")),
+                                }),
+                                Action::Select(
+                                    selection
+                                        .clone()
+                                        .set_range((range.start + 1..range.end + 1).into()),
+                                ),
+                            ]
+                            .to_vec(),
+                        );
+                        return (action_group, None);
+                    }
+                    let start = selection.to_char_index(&Direction::End);
+                    let previous = start
+                        .0
+                        .checked_sub(1)
+                        .and_then(|index| buffer.rope().get_char(index));
+                    let next = buffer.rope().get_char(start.0);
+                    let auto_pair = crate::insert_assist::auto_pair(
+                        pairs,
+                        c,
+                        previous,
+                        next,
+                        buffer.is_auto_closing(start),
+                    );
+                    let new = match auto_pair {
+                        Some(AutoPair::SkipOver) => None,
+                        Some(AutoPair::Close(close)) => Some(format!("{c}{close}")),
+                        None => Some(c.to_string()),
+                    };
+                    let cursor = range.start + 1;
+                    let action_group = ActionGroup::new(
+                        new.map(|new| {
+                            Action::Edit(Edit {
+                                range: (start..start).into(),
+                                new: Rope::from_str(&new),
+                            })
+                        })
+                        .into_iter()
+                        .chain([Action::Select(
+                            selection.clone().set_range((cursor..cursor).into()),
+                        )])
+                        .collect_vec(),
+                    );
+                    (
+                        action_group,
+                        (auto_pair == Some(AutoPair::SkipOver)).then_some(start),
+                    )
+                })
+                .into_iter()
+                .unzip()
+        };
+        let skipped_over = skipped_over.into_iter().flatten().collect_vec();
+        let edit_transaction = EditTransaction::from_action_groups(action_groups);
+        // The edits that insert a pair are the only insertions of two characters,
+        // and they are not shifted by the edits after them
+        let auto_closings = edit_transaction
+            .edits()
+            .into_iter()
+            .filter(|edit| edit.range.is_empty() && edit.new.len_chars() == 2)
+            .map(|edit| edit.range.start + 1)
+            .collect_vec();
+        self.buffer_mut().remove_auto_closings(&skipped_over);
+        let dispatches = self.apply_edit_transaction(edit_transaction)?;
+        self.buffer_mut().add_auto_closings(auto_closings);
+        Ok(dispatches)
+    }
+
+    /// Break the current line at the last whitespace before `width`,
+    /// if the current buffer is prose and the line is longer than `width`.
+    ///
+    /// Only applies when there is only one cursor.
+    pub(crate) fn hard_wrap(&mut self, width: usize) -> anyhow::Result<Dispatches> {
+        let is_prose = self
+            .buffer()
+            .path()
+            .map(|path| crate::hard_wrap::is_prose(&path))
+            .unwrap_or(false);
+        if !is_prose || self.selection_set.len() > 1 {
+            return Ok(Default::default());
+        }
+        let cursor = self.get_cursor_char_index();
+        let line_index = self.buffer().char_to_line(cursor)?;
+        let line_start = self.buffer().line_to_char(line_index)?;
+        let Some(line) = self.buffer().get_line_by_line_index(line_index) else {
+            return Ok(Default::default());
+        };
+        let line = line.to_string();
+        let Some(wrap) = crate::hard_wrap::hard_wrap(line.trim_end_matches(['\n', '\r']), width)
+        else {
+            return Ok(Default::default());
+        };
+        let break_range =
+            (line_start + wrap.break_range.start)..(line_start + wrap.break_range.end);
+        // The line should not be broken after the cursor
+        if break_range.end > cursor {
+            return Ok(Default::default());
+        }
+        let new = format!("\n{}", wrap.continuation);
+        let new_cursor = cursor - wrap.break_range.len() + new.chars().count();
+        let selection = self.selection_set.primary_selection().clone();
+        self.apply_edit_transaction(EditTransaction::from_action_groups(
+            [ActionGroup::new(
+                [
+                    Action::Edit(Edit {
+                        range: break_range.into(),
+                        new: Rope::from_str(&new),
+                    }),
+                    Action::Select(selection.set_range((new_cursor..new_cursor).into())),
+                ]
+                .to_vec(),
+            )]
+            .to_vec(),
+        ))
+    }
+
+    /// Insert `content` as new lines below the line of the primary cursor,
+    /// and select the inserted content.
+    pub(crate) fn insert_lines_below(&mut self, content: &str) -> anyhow::Result<Dispatches> {
+        let content = content.trim_end_matches('\n');
+        let line_index = self.buffer().char_to_line(self.get_cursor_char_index())?;
+        let (insertion_index, new, inserted_start) = if line_index + 1 < self.buffer().len_lines() {
+            let start = self.buffer().line_to_char(line_index + 1)?;
+            (start, format!("{content}\n"), start)
+        } else {
+            // The cursor is on the last line, which is not terminated by a newline
+            let end = CharIndex(self.buffer().len_chars());
+            (end, format!("\n{content}"), end + 1)
+        };
+        let inserted_range = inserted_start..inserted_start + content.chars().count();
+        let selection = self.selection_set.primary_selection().clone();
+        self.apply_edit_transaction(EditTransaction::from_action_groups(
+            [ActionGroup::new(
+                [
+                    Action::Edit(Edit {
+                        range: (insertion_index..insertion_index).into(),
+                        new: Rope::from_str(&new),
+                    }),
+                    Action::Select(selection.set_range(inserted_range.into())),
+                ]
+                .to_vec(),
+            )]
+            .to_vec(),
+        ))
+    }
+
+    /// Select the lines of the closest hunk of the git gutter after (or before) the cursor.
+    ///
+    /// Deleted lines are represented by the line above them.
+    fn move_to_git_gutter_hunk(&mut self, direction: Direction) -> anyhow::Result<Dispatches> {
+        let cursor_line = self.get_cursor_position()?.line;
+        let hunks = self.buffer().git_gutter_hunks();
+        let hunk = match direction {
+            Direction::End => hunks
+                .iter()
+                .find(|hunk| hunk.marked_lines().start > cursor_line),
+            Direction::Start => hunks
+                .iter()
+                .rev()
+                .find(|hunk| hunk.marked_lines().end <= cursor_line),
+        };
+        let Some(lines) = hunk.map(|hunk| hunk.marked_lines()) else {
+            return Ok(Default::default());
+        };
+        let buffer = self.buffer();
+        let start = buffer.line_to_char(lines.start)?;
+        let end = buffer
+            .line_to_char(lines.end)
+            .unwrap_or(CharIndex(buffer.len_chars()));
+        drop(buffer);
+        let selection_set =
+            SelectionSet::new(NonEmpty::singleton(Selection::new((start..end).into())));
+        Ok(self.update_selection_set(selection_set, true))
+    }
+
+    /// Replace the hunk of the git gutter under the cursor with its base content.
+    fn revert_git_gutter_hunk(&mut self) -> anyhow::Result<Dispatches> {
+        let cursor_line = self.get_cursor_position()?.line;
+        let Some(hunk) = self
+            .buffer()
+            .git_gutter_hunks()
+            .into_iter()
+            .find(|hunk| hunk.contains_line(cursor_line))
+        else {
+            return Ok(Default::default());
+        };
+        let buffer = self.buffer();
+        let line_range = hunk.line_range();
+        let start = buffer.line_to_char(line_range.start)?;
+        let end = buffer
+            .line_to_char(line_range.end)
+            .unwrap_or(CharIndex(buffer.len_chars()));
+        drop(buffer);
+        let restored_end = start + hunk.old_content().chars().count();
+        let selection = self.selection_set.primary_selection().clone();
+        self.apply_edit_transaction(EditTransaction::from_action_groups(
+            [ActionGroup::new(
+                [
+                    Action::Edit(Edit {
+                        range: (start..end).into(),
+                        new: Rope::from_str(hunk.old_content()),
+                    }),
+                    Action::Select(selection.set_range((start..restored_end).into())),
+                ]
+                .to_vec(),
+            )]
+            .to_vec(),
+        ))
+    }
+
     pub(crate) fn get_request_params(&self) -> Option<RequestParams> {
         let position = self.get_cursor_position().ok()?;
         self.path().map(|path| RequestParams {
@@ -1317,6 +2038,7 @@ impl Editor {
             context: ResponseContext {
                 scope: None,
                 description: None,
+                hierarchy: None,
             },
         })
     }
@@ -1414,6 +2136,7 @@ impl Editor {
             Mode::Replace => self.replace_with_movement(&movement),
             Mode::UndoTree => self.navigate_undo_tree(movement),
             Mode::MultiCursor => self.add_cursor(&movement).map(|_| Default::default()),
+            Mode::Block { anchor, cursor } => self.move_block_cursor(anchor, cursor, movement),
             _ => Ok(Default::default()),
         }
     }
@@ -1450,7 +2173,9 @@ impl Editor {
     }
 
     pub(crate) fn enter_normal_mode(&mut self) -> anyhow::Result<()> {
+        self.ime_preedit = None;
         if self.mode == Mode::Insert {
+            self.buffer_mut().clear_auto_closings();
             // This is necessary for cursor to not overflow after exiting insert mode
             self.set_selection_set(self.selection_set.apply(
                 self.selection_set.mode.clone(),
@@ -2004,7 +2729,13 @@ impl Editor {
         self.buffer.borrow_mut().update(s)
     }
 
+    /// Moves the cursor and the view by `scroll_height` lines,
+    /// so that the cursor stays on the same row of the view.
     fn scroll(&mut self, direction: Direction, scroll_height: usize) -> anyhow::Result<Dispatches> {
+        let scroll_offset = self.scroll_offset;
+        // Scrolling during an animation continues from the destination of the animation
+        self.finish_scroll_animation();
+        let cursor_row_in_view = self.cursor_row_in_view();
         let dispatch = self.update_selection_set(
             self.selection_set
                 .apply(self.selection_set.mode.clone(), |selection| {
@@ -2020,8 +2751,12 @@ impl Editor {
                 })?,
             false,
         );
-        self.align_cursor_to_center();
-
+        self.scroll_offset = self.line_above_cursor(cursor_row_in_view);
+        if self.smooth_scroll && self.scroll_offset != scroll_offset {
+            self.scroll_animation = Some(ScrollAnimation::new(scroll_offset, self.scroll_offset));
+            self.scroll_offset = scroll_offset;
+            return Ok(dispatch.append(Dispatch::AnimateScroll));
+        }
         Ok(dispatch)
     }
 
@@ -2177,6 +2912,42 @@ impl Editor {
             }))
     }
 
+    fn set_line_ending(&mut self, line_ending: EndOfLine) -> anyhow::Result<Dispatches> {
+        let selection_set = self.selection_set.clone();
+        self.buffer_mut()
+            .set_line_ending(line_ending, selection_set)?;
+        self.clamp()?;
+        Ok(self.get_document_did_change_dispatch())
+    }
+
+    fn trim_trailing_whitespace(&mut self) -> anyhow::Result<Dispatches> {
+        let content = self.buffer().content();
+        let trimmed = crate::editorconfig::trim_trailing_whitespace(&content);
+        if trimmed == content {
+            return Ok(Dispatches::default());
+        }
+        let selection_set = self.selection_set.clone();
+        self.buffer_mut().update_content(&trimmed, selection_set)?;
+        self.clamp()?;
+        Ok(self.get_document_did_change_dispatch())
+    }
+
+    /// Discards the unsaved changes.
+    pub(crate) fn reload_from_disk(&mut self) -> anyhow::Result<Dispatches> {
+        self.buffer_mut().reload()?;
+        self.clamp()?;
+        Ok(self.get_document_did_change_dispatch())
+    }
+
+    /// Replaces the content with the unsaved changes of a previous session,
+    /// which can be undone to get back the content of the file.
+    pub(crate) fn restore_unsaved_changes(&mut self, content: &str) -> anyhow::Result<Dispatches> {
+        let selection_set = self.selection_set.clone();
+        self.buffer_mut().update_content(content, selection_set)?;
+        self.clamp()?;
+        Ok(self.get_document_did_change_dispatch())
+    }
+
     /// Clamp everything that might be out of bound after the buffer content is modified elsewhere
     fn clamp(&mut self) -> anyhow::Result<()> {
         let len_chars = self.buffer().len_chars();
@@ -2202,7 +2973,9 @@ impl Editor {
                             Action::Select(
                                 selection.clone().set_range(
                                     (selection.extended_range().start
-                                        ..selection.extended_range().end + 2)
+                                        ..selection.extended_range().end
+                                            + open.chars().count()
+                                            + close.chars().count())
                                         .into(),
                                 ),
                             ),
@@ -2218,6 +2991,298 @@ impl Editor {
         self.apply_edit_transaction(edit_transaction)
     }
 
+    /// Toggles line comments on the lines of each selection,
+    /// or a block comment around each selection if the language has no line comments.
+    fn toggle_comment(&mut self) -> anyhow::Result<Dispatches> {
+        let (line_comment_prefix, block_comment_affixes) = self
+            .buffer()
+            .language()
+            .map(|language| {
+                (
+                    language.line_comment_prefix(),
+                    language.block_comment_affixes(),
+                )
+            })
+            .unwrap_or_default();
+        if line_comment_prefix.is_none() && block_comment_affixes.is_none() {
+            return Err(anyhow::anyhow!(
+                "No comment tokens are defined for the language of this file."
+            ));
+        }
+        let edits = {
+            let buffer = self.buffer();
+            self.selection_set
+                .map(|selection| -> anyhow::Result<_> {
+                    let range = selection.extended_range();
+                    let range: CharIndexRange = match line_comment_prefix {
+                        Some(_) => {
+                            let start_line = buffer.char_to_line(range.start)?;
+                            let end_line = buffer.char_to_line((range.end - 1).max(range.start))?;
+                            let end_line_length = buffer
+                                .get_line_by_line_index(end_line)
+                                .map(|line| {
+                                    line.to_string()
+                                        .trim_end_matches(['\r', '\n'])
+                                        .chars()
+                                        .count()
+                                })
+                                .unwrap_or_default();
+                            (buffer.line_to_char(start_line)?
+                                ..buffer.line_to_char(end_line)? + end_line_length)
+                                .into()
+                        }
+                        None => range,
+                    };
+                    let old = buffer.slice(&range)?.to_string();
+                    let new = match (line_comment_prefix, block_comment_affixes) {
+                        (Some(prefix), _) => crate::comment::toggle_line_comment(&old, prefix),
+                        (None, Some(affixes)) => {
+                            crate::comment::toggle_block_comment(&old, affixes)
+                        }
+                        (None, None) => old,
+                    };
+                    Ok((selection.clone(), range, new))
+                })
+                .into_iter()
+                .collect::<anyhow::Result<Vec<_>>>()?
+        };
+        // Multiple selections on the same lines are only toggled once
+        let mut toggled_ranges = HashSet::new();
+        let edit_transaction = EditTransaction::from_action_groups(
+            edits
+                .into_iter()
+                .map(|(selection, range, new)| {
+                    let new_char_count = new.chars().count();
+                    let edit = toggled_ranges.insert(range).then(|| {
+                        Action::Edit(Edit {
+                            range,
+                            new: new.into(),
+                        })
+                    });
+                    ActionGroup::new(
+                        edit.into_iter()
+                            .chain([Action::Select(
+                                selection
+                                    .set_range((range.start..range.start + new_char_count).into()),
+                            )])
+                            .collect_vec(),
+                    )
+                })
+                .collect_vec(),
+        );
+        self.apply_edit_transaction(edit_transaction)
+    }
+
+    fn foldable_line_ranges(&self) -> Vec<Range<usize>> {
+        let buffer = self.buffer();
+        fold::foldable_line_ranges(
+            buffer.tree(),
+            buffer
+                .language()
+                .and_then(|language| language.folds_query()),
+            &buffer.content(),
+        )
+    }
+
+    fn cursor_lines(&self) -> anyhow::Result<Vec<usize>> {
+        let buffer = self.buffer();
+        self.selection_set
+            .map(|selection| buffer.char_to_line(selection.to_char_index(&self.cursor_direction)))
+            .into_iter()
+            .collect()
+    }
+
+    /// Folding repeatedly folds the enclosing ranges, because folded ranges are skipped.
+    fn fold(&mut self) -> anyhow::Result<Dispatches> {
+        let foldable_line_ranges = self.foldable_line_ranges();
+        let folds = self.buffer().folds();
+        let new_folds = self
+            .cursor_lines()?
+            .into_iter()
+            .filter_map(|line| {
+                foldable_line_ranges
+                    .iter()
+                    .filter(|range| range.contains(&line) && !folds.contains(range))
+                    .min_by_key(|range| range.len())
+                    .cloned()
+            })
+            .collect_vec();
+        if new_folds.is_empty() {
+            return Ok(Dispatches::one(Dispatch::RingBell));
+        }
+        for new_fold in new_folds {
+            self.buffer_mut().add_fold(new_fold)?
+        }
+        self.move_cursors_out_of_folds();
+        Ok(Default::default())
+    }
+
+    fn unfold(&mut self) {
+        if let Ok(lines) = self.cursor_lines() {
+            for line in lines {
+                self.buffer_mut().unfold_line(line)
+            }
+        }
+    }
+
+    fn fold_all(&mut self) {
+        for range in self.foldable_line_ranges() {
+            self.buffer_mut()
+                .add_fold(range)
+                .unwrap_or_else(|error| log::error!("fold_all: {:?}", error))
+        }
+        self.move_cursors_out_of_folds();
+    }
+
+    fn outline(&self) -> anyhow::Result<Outline> {
+        let buffer = self.buffer();
+        let is_markdown = buffer
+            .language()
+            .is_some_and(|language| language.name() == Some("markdown"));
+        match buffer.tree().filter(|_| is_markdown) {
+            Some(tree) => Ok(Outline::from_tree(tree, buffer.len_lines())),
+            None => Err(anyhow::anyhow!(
+                "The outline is only available for markdown buffers"
+            )),
+        }
+    }
+
+    fn current_heading(&self, outline: &Outline) -> anyhow::Result<usize> {
+        let line = self.buffer().char_to_line(self.get_cursor_char_index())?;
+        outline
+            .enclosing_heading(line)
+            .ok_or_else(|| anyhow::anyhow!("The cursor is not under a heading"))
+    }
+
+    /// The heading line is selected afterwards.
+    fn change_heading_level(&mut self, promote: bool) -> anyhow::Result<Dispatches> {
+        let outline = self.outline()?;
+        let index = self.current_heading(&outline)?;
+        let headings = outline.subtree_headings(index);
+        if promote && headings[0].level == 1 {
+            return Err(anyhow::anyhow!("A level 1 heading cannot be promoted"));
+        }
+        if !promote && headings.iter().any(|heading| heading.level == 6) {
+            return Err(anyhow::anyhow!("A level 6 heading cannot be demoted"));
+        }
+        let action_groups = {
+            let buffer = self.buffer();
+            headings
+                .iter()
+                .map(|heading| -> anyhow::Result<_> {
+                    let line = buffer
+                        .get_line_by_line_index(heading.line)
+                        .ok_or_else(|| anyhow::anyhow!("Invalid line {}", heading.line))?;
+                    // ATX headings can be indented by up to three spaces
+                    let indentation = line.chars().take_while(|char| char == &' ').count();
+                    let start = buffer.line_to_char(heading.line)? + indentation;
+                    let (range, new, length_change) = if promote {
+                        ((start..start + 1).into(), Rope::new(), -1)
+                    } else {
+                        ((start..start).into(), Rope::from_str("#"), 1)
+                    };
+                    let mut actions = [Action::Edit(Edit { range, new })].to_vec();
+                    if heading == &headings[0] {
+                        let line_start = buffer.line_to_char(heading.line)?;
+                        let line_length =
+                            line.chars().take_while(|char| char != &'\n').count() as isize;
+                        let end = line_start + (line_length + length_change) as usize;
+                        actions.push(Action::Select(
+                            self.selection_set
+                                .primary_selection()
+                                .clone()
+                                .set_range((line_start..end).into()),
+                        ))
+                    }
+                    Ok(ActionGroup::new(actions))
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?
+        };
+        self.apply_edit_transaction(EditTransaction::from_action_groups(action_groups))
+    }
+
+    /// The sibling headings are the nearest headings of the same level under the same parent.
+    fn move_heading_subtree(&mut self, direction: Direction) -> anyhow::Result<Dispatches> {
+        let outline = self.outline()?;
+        let index = self.current_heading(&outline)?;
+        let forward = direction == Direction::End;
+        let sibling = outline.sibling(index, forward).ok_or_else(|| {
+            anyhow::anyhow!(
+                "There is no sibling heading {} this heading",
+                if forward { "after" } else { "before" }
+            )
+        })?;
+        let (first, second) = if forward {
+            (index, sibling)
+        } else {
+            (sibling, index)
+        };
+        let action_group = {
+            let buffer = self.buffer();
+            let first_range = buffer.line_range_to_char_index_range(outline.subtree(first))?;
+            let second_range = buffer.line_range_to_char_index_range(outline.subtree(second))?;
+            let first_text = buffer.slice(&first_range)?.to_string();
+            let second_text = buffer.slice(&second_range)?.to_string();
+            let (moved_start, moved_text) = if forward {
+                let offset = second_text.chars().count() + !second_text.ends_with('\n') as usize;
+                (first_range.start + offset, &first_text)
+            } else {
+                (first_range.start, &second_text)
+            };
+            let heading_length = moved_text.chars().take_while(|char| char != &'\n').count();
+            ActionGroup::new(
+                [
+                    Action::Edit(Edit {
+                        range: (first_range.start..second_range.end).into(),
+                        new: outline::swap_adjacent(&first_text, &second_text).into(),
+                    }),
+                    Action::Select(
+                        self.selection_set
+                            .primary_selection()
+                            .clone()
+                            .set_range((moved_start..moved_start + heading_length).into()),
+                    ),
+                ]
+                .to_vec(),
+            )
+        };
+        self.apply_edit_transaction(EditTransaction::from_action_groups([action_group].to_vec()))
+    }
+
+    fn fold_headings(&mut self) -> anyhow::Result<Dispatches> {
+        let outline = self.outline()?;
+        for range in outline.section_line_ranges() {
+            self.buffer_mut().add_fold(range)?
+        }
+        self.move_cursors_out_of_folds();
+        Ok(Default::default())
+    }
+
+    /// The selections whose cursor is hidden by folds are collapsed to the start of the folds,
+    /// otherwise the folds would be opened again to reveal the primary cursor.
+    fn move_cursors_out_of_folds(&mut self) {
+        let selections = {
+            let buffer = self.buffer();
+            let hidden_line_ranges = buffer.hidden_line_ranges();
+            self.selection_set.map(|selection| {
+                let start = buffer
+                    .char_to_line(selection.to_char_index(&self.cursor_direction))
+                    .ok()
+                    .and_then(|line| {
+                        hidden_line_ranges
+                            .iter()
+                            .find(|range| range.contains(&line))
+                    })
+                    .and_then(|range| buffer.line_to_char(range.start - 1).ok());
+                match start {
+                    Some(start) => selection.clone().set_range((start..start).into()),
+                    None => selection.clone(),
+                }
+            })
+        };
+        self.set_selection_set(self.selection_set.clone().set_selections(selections))
+    }
+
     fn transform_selection(
         &mut self,
         transformation: Transformation,
@@ -2253,6 +3318,191 @@ impl Editor {
         self.apply_edit_transaction(edit_transaction)
     }
 
+    /// Pads the first match of `pattern` on every line of the selections with spaces,
+    /// so that the matches start at the same column, then the matches are selected.
+    ///
+    /// The pattern is a regex if the local search mode is regex, otherwise it is literal.
+    fn align(
+        &mut self,
+        local_search_config: &LocalSearchConfig,
+        pattern: &str,
+    ) -> anyhow::Result<Dispatches> {
+        let regex = match local_search_config.mode {
+            LocalSearchConfigMode::Regex(regex_config) => get_regex(pattern, regex_config)?,
+            _ => get_regex(
+                pattern,
+                crate::list::grep::RegexConfig {
+                    escaped: true,
+                    case_sensitive: true,
+                    match_whole_word: false,
+                },
+            )?,
+        };
+        let buffer = self.buffer();
+        let lines = self
+            .selection_set
+            .selections()
+            .iter()
+            .map(|selection| -> anyhow::Result<_> {
+                let range = selection.extended_range();
+                let start = buffer.char_to_line(range.start)?;
+                let end = buffer.char_to_line(range.end.max(range.start + 1) - 1)?;
+                Ok(start..=end.max(start))
+            })
+            .flatten_ok()
+            .collect::<anyhow::Result<BTreeSet<_>>>()?;
+        // The columns of the first match of each line, and the length of the match
+        let matches = lines
+            .into_iter()
+            .map(|line| -> anyhow::Result<_> {
+                let Some(content) = buffer.get_line_by_line_index(line) else {
+                    return Ok(None);
+                };
+                let content = content.to_string();
+                let Some(found) = regex.find(&content)? else {
+                    return Ok(None);
+                };
+                let column = content[..found.start()].chars().count();
+                Ok(Some((line, column, found.as_str().chars().count())))
+            })
+            .filter_map(Result::transpose)
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let Some(target) = matches.iter().map(|(_, column, _)| *column).max() else {
+            return Ok(Default::default());
+        };
+        let action_groups: Vec<_> = matches
+            .into_iter()
+            .map(|(line, column, length)| -> anyhow::Result<_> {
+                let start = buffer.line_to_char(line)? + column;
+                let padding = target - column;
+                let aligned = start + padding;
+                Ok(ActionGroup::new(
+                    (padding > 0)
+                        .then(|| {
+                            Action::Edit(Edit {
+                                range: (start..start).into(),
+                                new: " ".repeat(padding).into(),
+                            })
+                        })
+                        .into_iter()
+                        .chain(Some(Action::Select(Selection::new(
+                            (aligned..aligned + length).into(),
+                        ))))
+                        .collect(),
+                ))
+            })
+            .try_collect()?;
+        drop(buffer);
+        self.apply_edit_transaction(EditTransaction::from_action_groups(action_groups))
+    }
+
+    /// Moves each cursor that is on a bracket to its matching bracket, see
+    /// `Buffer::matching_bracket`, while the other cursors stay.
+    fn go_to_matching_bracket(&mut self) -> Dispatches {
+        let buffer = self.buffer();
+        let selections = self.selection_set.selections().clone().map(|selection| {
+            match buffer.matching_bracket(selection.to_char_index(&self.cursor_direction)) {
+                Some(matching) => (true, Selection::new((matching..matching + 1).into())),
+                None => (false, selection),
+            }
+        });
+        drop(buffer);
+        if selections.iter().all(|(moved, _)| !moved) {
+            return Default::default();
+        }
+        let selection_set = self
+            .selection_set
+            .clone()
+            .set_selections(selections.map(|(_, selection)| selection))
+            .set_mode(SelectionMode::Column);
+        self.update_selection_set(selection_set, true)
+    }
+
+    /// Selects the misspelled word under the primary cursor, or the first one after the cursor
+    /// on the same line, and returns it, so that its spelling suggestions can be shown.
+    pub(crate) fn select_misspelling(
+        &mut self,
+        spell_checker: &SpellChecker,
+    ) -> anyhow::Result<Option<(String, Dispatches)>> {
+        let buffer = self.buffer();
+        let cursor = self
+            .selection_set
+            .primary_selection()
+            .to_char_index(&self.cursor_direction);
+        let line = buffer.char_to_line(cursor)?;
+        let cursor = buffer.char_to_byte(cursor)?;
+        let Some(misspelling) = spell_checker
+            .buffer_misspellings(&buffer, buffer.line_range_to_byte_range(&(line..line + 1))?)
+            .into_iter()
+            .find(|misspelling| misspelling.end > cursor)
+        else {
+            return Ok(None);
+        };
+        let range = buffer.byte_range_to_char_index_range(&misspelling)?;
+        let word = buffer.slice(&range)?.to_string();
+        drop(buffer);
+        let selection_set = SelectionSet::new(NonEmpty::singleton(Selection::new(range)))
+            .set_mode(SelectionMode::Word);
+        Ok(Some((word, self.update_selection_set(selection_set, true))))
+    }
+
+    /// Increments the number or the date at (or after) the start of each selection on its line,
+    /// see `increment::increment`, then the new numbers are selected.
+    fn increment(&mut self, amount: i64, sequential: bool) -> anyhow::Result<Dispatches> {
+        let buffer = self.buffer();
+        let incremented = self
+            .selection_set
+            .selections()
+            .iter()
+            .sorted_by_key(|selection| selection.extended_range().start)
+            .enumerate()
+            .map(|(index, selection)| -> anyhow::Result<_> {
+                let cursor = selection.extended_range().start;
+                let line_index = buffer.char_to_line(cursor)?;
+                let line_start = buffer.line_to_char(line_index)?;
+                let line = buffer
+                    .get_line_by_line_index(line_index)
+                    .map(|line| line.to_string())
+                    .unwrap_or_default();
+                let amount = if sequential {
+                    amount.saturating_mul(index as i64 + 1)
+                } else {
+                    amount
+                };
+                let incremented = increment::increment(&line, cursor.0 - line_start.0, amount);
+                Ok((selection, line_start, incremented))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        if incremented
+            .iter()
+            .all(|(_, _, incremented)| incremented.is_none())
+        {
+            return Ok(Default::default());
+        }
+        let action_groups = incremented
+            .into_iter()
+            .map(|(selection, line_start, incremented)| {
+                let Some(incremented) = incremented else {
+                    return ActionGroup::new([Action::Select(selection.clone())].to_vec());
+                };
+                let start = line_start + incremented.range.start;
+                let end = start + incremented.new.chars().count();
+                ActionGroup::new(
+                    [
+                        Action::Edit(Edit {
+                            range: (start..line_start + incremented.range.end).into(),
+                            new: incremented.new.into(),
+                        }),
+                        Action::Select(selection.clone().set_range((start..end).into())),
+                    ]
+                    .to_vec(),
+                )
+            })
+            .collect_vec();
+        drop(buffer);
+        self.apply_edit_transaction(EditTransaction::from_action_groups(action_groups))
+    }
+
     pub(crate) fn display_mode(&self) -> String {
         let prefix = if self.selection_set.is_extended() {
             "+"
@@ -2263,6 +3513,7 @@ impl Editor {
             Mode::Normal => "MOVE",
             Mode::Insert => "INSERT",
             Mode::MultiCursor => "MULTI CURSOR",
+            Mode::Block { .. } => "BLOCK",
             Mode::FindOneChar(_) => "FIND ONE CHAR",
             Mode::Exchange => "EXCHANGE",
             Mode::UndoTree => "UNDO TREE",
@@ -2284,11 +3535,18 @@ impl Editor {
         }
     }
 
+    /// The lines hidden by folds are included, but they do not take up the height.
     pub(crate) fn visible_line_range(&self) -> Range<usize> {
-        let start = self.scroll_offset;
-        let end = (start as usize + self.rectangle.height as usize).min(self.buffer().len_lines());
+        let start = self.scroll_offset as usize;
+        let buffer = self.buffer();
+        let len_lines = buffer.len_lines();
+        let end = fold::visible_lines(&buffer.hidden_line_ranges(), start, len_lines)
+            .take(self.rectangle.height as usize)
+            .last()
+            .map(|line| line + 1)
+            .unwrap_or(len_lines);
 
-        start as usize..end
+        start..end
     }
 
     fn handle_multi_cursor_mode(
@@ -2414,6 +3672,133 @@ impl Editor {
         .into())
     }
 
+    /// Returns the char index ranges of the line at `line_index` (excluding the newline character),
+    /// which is split into display lines if `display` is true.
+    fn line_ranges(&self, line_index: usize, display: bool) -> anyhow::Result<Vec<Range<usize>>> {
+        let buffer = self.buffer();
+        let line_start = buffer.line_to_char(line_index)?.0;
+        let line = buffer
+            .get_line_by_line_index(line_index)
+            .map(|line| line.to_string())
+            .unwrap_or_default();
+        let line = line.trim_end_matches(['\n', '\r']);
+        let ranges = if display {
+            let gutter_width = crate::grid::line_number_gutter_width(
+                buffer.len_lines(),
+                !buffer.hidden_line_ranges().is_empty(),
+            );
+            let width = (self.render_area().width as usize).saturating_sub(gutter_width);
+            crate::soft_wrap::display_line_ranges(line, width)
+        } else {
+            [0..line.chars().count()].to_vec()
+        };
+        Ok(ranges
+            .into_iter()
+            .map(|range| line_start + range.start..line_start + range.end)
+            .collect())
+    }
+
+    /// Returns the index of the range containing `cursor`
+    fn cursor_range_index(ranges: &[Range<usize>], cursor: usize) -> usize {
+        ranges
+            .iter()
+            .position(|range| cursor < range.end)
+            .unwrap_or(ranges.len().saturating_sub(1))
+    }
+
+    /// Returns the last char index that the cursor can be placed at within the range at `index`.
+    ///
+    /// For non-last ranges, the cursor cannot be placed at the end of the range,
+    /// because it is the same char index as the start of the next range.
+    fn range_last_char_index(ranges: &[Range<usize>], index: usize) -> usize {
+        let range = &ranges[index];
+        if index + 1 == ranges.len() {
+            range.end
+        } else {
+            range.end.saturating_sub(1).max(range.start)
+        }
+    }
+
+    fn vertical_target(
+        &self,
+        cursor: usize,
+        direction: &Direction,
+        display: bool,
+    ) -> anyhow::Result<usize> {
+        let line_index = self.buffer().char_to_line(CharIndex(cursor))?;
+        let ranges = self.line_ranges(line_index, display)?;
+        let index = Self::cursor_range_index(&ranges, cursor);
+        let offset = cursor.saturating_sub(ranges[index].start);
+        let target = match direction {
+            Direction::Start if index > 0 => Some((ranges.clone(), index - 1)),
+            Direction::Start if line_index > 0 => {
+                let ranges = self.line_ranges(line_index - 1, display)?;
+                let index = ranges.len() - 1;
+                Some((ranges, index))
+            }
+            Direction::End if index + 1 < ranges.len() => Some((ranges.clone(), index + 1)),
+            Direction::End if line_index + 1 < self.buffer().len_lines() => {
+                Some((self.line_ranges(line_index + 1, display)?, 0))
+            }
+            _ => None,
+        };
+        Ok(target
+            .map(|(ranges, index)| {
+                (ranges[index].start + offset).min(Self::range_last_char_index(&ranges, index))
+            })
+            .unwrap_or(cursor))
+    }
+
+    /// Move the cursor of each selection to the char index returned by `get_target`
+    fn move_cursors(
+        &mut self,
+        get_target: impl Fn(&Editor, usize) -> anyhow::Result<usize>,
+    ) -> anyhow::Result<Dispatches> {
+        let edit_transaction = EditTransaction::from_action_groups(
+            self.selection_set
+                .map(|selection| -> anyhow::Result<_> {
+                    let cursor = selection.to_char_index(&self.cursor_direction);
+                    let char_index = CharIndex(get_target(self, cursor.0)?);
+                    Ok(ActionGroup::new(
+                        [Action::Select(
+                            selection
+                                .clone()
+                                .set_range((char_index..char_index).into())
+                                .set_initial_range(None),
+                        )]
+                        .to_vec(),
+                    ))
+                })
+                .into_iter()
+                .flatten()
+                .collect(),
+        );
+        self.apply_edit_transaction(edit_transaction)
+    }
+
+    pub(crate) fn move_vertically(
+        &mut self,
+        direction: Direction,
+        display: bool,
+    ) -> anyhow::Result<Dispatches> {
+        self.move_cursors(|editor, cursor| editor.vertical_target(cursor, &direction, display))
+    }
+
+    pub(crate) fn move_to_display_line_edge(
+        &mut self,
+        direction: Direction,
+    ) -> anyhow::Result<Dispatches> {
+        self.move_cursors(|editor, cursor| {
+            let line_index = editor.buffer().char_to_line(CharIndex(cursor))?;
+            let ranges = editor.line_ranges(line_index, true)?;
+            let index = Self::cursor_range_index(&ranges, cursor);
+            Ok(match direction {
+                Direction::Start => ranges[index].start,
+                Direction::End => Self::range_last_char_index(&ranges, index),
+            })
+        })
+    }
+
     pub(crate) fn select_all(&mut self, context: &mut Context) -> anyhow::Result<Dispatches> {
         self.handle_dispatch_editors(
             context,
@@ -2445,6 +3830,10 @@ impl Editor {
         self.scroll(Direction::Start, self.half_page_height())
     }
 
+    fn scroll_full_page(&mut self, direction: Direction) -> Result<Dispatches, anyhow::Error> {
+        self.scroll(direction, (self.render_area().height as usize).max(1))
+    }
+
     #[cfg(test)]
     pub(crate) fn current_view_alignment(&self) -> Option<ViewAlignment> {
         self.current_view_alignment
@@ -2476,8 +3865,8 @@ impl Editor {
             .chain(self.get_document_did_change_dispatch()))
     }
 
-    #[cfg(test)]
     pub(crate) fn set_scroll_offset(&mut self, scroll_offset: u16) {
+        self.scroll_animation = None;
         self.scroll_offset = scroll_offset
     }
 
@@ -2493,7 +3882,9 @@ impl Editor {
         let Dimension { height, width } = self.dimension();
         Dimension {
             height: height.saturating_sub(WINDOW_TITLE_HEIGHT as u16),
-            width,
+            width: width
+                .saturating_sub(self.scrollbar as u16)
+                .saturating_sub(self.minimap_width()),
         }
     }
 
@@ -2583,6 +3974,105 @@ impl Editor {
         self.mode = Mode::MultiCursor
     }
 
+    fn enter_block_mode(&mut self) -> anyhow::Result<Dispatches> {
+        let cursor = self.get_cursor_position()?;
+        self.set_block(cursor, cursor)
+    }
+
+    fn set_block(&mut self, anchor: Position, cursor: Position) -> anyhow::Result<Dispatches> {
+        let selection_set = self.block_selection_set(anchor, cursor)?;
+        self.mode = Mode::Block { anchor, cursor };
+        Ok(self.update_selection_set(selection_set, true))
+    }
+
+    /// The number of characters of the line, excluding its line break.
+    fn line_length(&self, line: usize) -> Option<usize> {
+        let line = self.buffer().get_line_by_line_index(line)?.to_string();
+        Some(line.trim_end_matches(['\n', '\r']).chars().count())
+    }
+
+    /// Selects the columns between the `anchor` and the `cursor` on each of their lines,
+    /// where the lines that end before the leftmost column are left out, except the line
+    /// of the cursor, so that inserting at the block does not pad the short lines.
+    fn block_selection_set(
+        &self,
+        anchor: Position,
+        cursor: Position,
+    ) -> anyhow::Result<SelectionSet> {
+        let (left, right) = (
+            anchor.column.min(cursor.column),
+            anchor.column.max(cursor.column),
+        );
+        let lines = if anchor.line <= cursor.line {
+            Either::Left(anchor.line..=cursor.line)
+        } else {
+            Either::Right((cursor.line..=anchor.line).rev())
+        };
+        let buffer = self.buffer();
+        let mut selections = Vec::new();
+        for line in lines {
+            let Some(length) = self.line_length(line) else {
+                continue;
+            };
+            if length < left && line != cursor.line {
+                continue;
+            }
+            let start = buffer.position_to_char(Position::new(line, left.min(length)))?;
+            let end = buffer.position_to_char(Position::new(line, (right + 1).min(length)))?;
+            selections.push(Selection::new((start..end).into()))
+        }
+        let selections = NonEmpty::from_vec(selections)
+            .ok_or_else(|| anyhow::anyhow!("The block does not contain any line"))?;
+        // The selection on the line of the cursor is the primary selection
+        let cursor_index = selections.len() - 1;
+        Ok(SelectionSet::new(selections)
+            .set_mode(SelectionMode::Column)
+            .set_cursor_index(cursor_index))
+    }
+
+    fn move_block_cursor(
+        &mut self,
+        anchor: Position,
+        cursor: Position,
+        movement: Movement,
+    ) -> anyhow::Result<Dispatches> {
+        let last_line = self.buffer().len_lines().saturating_sub(1);
+        let cursor = match movement {
+            Movement::Up => Position::new(cursor.line.saturating_sub(1), cursor.column),
+            Movement::Down => Position::new((cursor.line + 1).min(last_line), cursor.column),
+            Movement::Left | Movement::Previous => {
+                Position::new(cursor.line, cursor.column.saturating_sub(1))
+            }
+            Movement::Right | Movement::Next => {
+                // The block cannot be wider than the longest of its lines
+                let longest = (anchor.line.min(cursor.line)..=anchor.line.max(cursor.line))
+                    .filter_map(|line| self.line_length(line))
+                    .max()
+                    .unwrap_or_default();
+                Position::new(
+                    cursor.line,
+                    (cursor.column + 1).min(longest.saturating_sub(1).max(cursor.column)),
+                )
+            }
+            _ => return Ok(Default::default()),
+        };
+        self.set_block(anchor, cursor)
+    }
+
+    fn handle_block_mode(
+        &mut self,
+        context: &Context,
+        key_event: KeyEvent,
+    ) -> Result<Dispatches, anyhow::Error> {
+        match key_event {
+            key!("esc") => {
+                self.mode = Mode::Normal;
+                Ok(Default::default())
+            }
+            other => self.handle_normal_mode(context, other),
+        }
+    }
+
     fn enter_replace_mode(&mut self) {
         self.mode = Mode::Replace
     }
@@ -2612,8 +4102,14 @@ impl Editor {
         }
     }
 
+    /// The folds that hide the primary cursor are opened, so that the cursor is always visible.
     fn set_selection_set(&mut self, selection_set: SelectionSet) {
+        self.finish_scroll_animation();
         self.selection_set = selection_set;
+        let cursor_row = self.cursor_row() as usize;
+        if fold::is_hidden(&self.buffer().hidden_line_ranges(), cursor_row) {
+            self.buffer_mut().unfold_line(cursor_row)
+        }
         self.recalculate_scroll_offset()
     }
 
@@ -2663,73 +4159,115 @@ impl Editor {
         self.apply_edit_transaction(edit_transaction)
     }
 
-    fn delete_surround(&mut self, enclosure: EnclosureKind) -> Result<Dispatches, anyhow::Error> {
+    fn delete_surround(
+        &mut self,
+        enclosure: Option<EnclosureKind>,
+    ) -> Result<Dispatches, anyhow::Error> {
         self.change_surround(enclosure, None)
     }
 
+    /// `from` is None for the nearest surrounding pair of any kind,
+    /// and `to` is None for deleting the pair.
+    ///
+    /// A pair that surrounds multiple selections is only changed once.
     fn change_surround(
         &mut self,
-        from: EnclosureKind,
+        from: Option<EnclosureKind>,
         to: Option<EnclosureKind>,
     ) -> Result<Dispatches, anyhow::Error> {
-        let edit_transaction = EditTransaction::from_action_groups(
-            self.selection_set
-                .map(|selection| -> anyhow::Result<_> {
-                    let buffer = self.buffer();
-                    let cursor_char_index = selection.get_anchor(&self.cursor_direction);
-                    if let Some((open_index, close_index)) =
-                        crate::surround::get_surrounding_indices(
-                            &buffer.content(),
-                            from,
-                            cursor_char_index,
-                            true,
-                        )
-                    {
-                        let open_range = (open_index..open_index + 1).into();
-                        let close_range = (close_index..close_index + 1).into();
-                        let (new_open, new_close) = to
-                            .as_ref()
-                            .map(|to| to.open_close_symbols_str())
-                            .unwrap_or(("", ""));
-                        let select_range = (open_index + 1 - new_open.chars().count()
-                            ..(close_index + new_close.chars().count()))
-                            .into();
-                        Ok([
-                            ActionGroup::new(
-                                [Action::Edit(Edit {
-                                    range: open_range,
-                                    new: new_open.into(),
-                                })]
-                                .to_vec(),
-                            ),
-                            ActionGroup::new(
-                                [Action::Edit(Edit {
-                                    range: close_range,
-                                    new: new_close.into(),
-                                })]
-                                .to_vec(),
-                            ),
-                            ActionGroup::new(
-                                [Action::Select(selection.clone().set_range(select_range))]
-                                    .to_vec(),
-                            ),
-                        ]
-                        .to_vec())
-                    } else {
-                        Ok(Default::default())
-                    }
-                })
-                .into_iter()
-                .flatten()
-                .flatten()
-                .collect_vec(),
-        );
+        let content = self.buffer().content();
+        let mut changed_pairs = HashSet::new();
+        let action_groups = self
+            .selection_set
+            .map(|selection| {
+                let cursor_char_index = selection.get_anchor(&self.cursor_direction);
+                let indices = match from {
+                    Some(from) => crate::surround::get_surrounding_indices(
+                        &content,
+                        from,
+                        cursor_char_index,
+                        true,
+                    ),
+                    None => crate::surround::get_nearest_surrounding_indices(
+                        &content,
+                        cursor_char_index,
+                        true,
+                    )
+                    .map(|(_, open_index, close_index)| (open_index, close_index)),
+                };
+                (selection.clone(), indices)
+            })
+            .into_iter()
+            .flat_map(|(selection, indices)| {
+                let Some((open_index, close_index)) = indices else {
+                    return Vec::new();
+                };
+                let (new_open, new_close) = to
+                    .as_ref()
+                    .map(|to| to.open_close_symbols_str())
+                    .unwrap_or(("", ""));
+                let select_range = (open_index + 1 - new_open.chars().count()
+                    ..(close_index + new_close.chars().count()))
+                    .into();
+                let edits = if changed_pairs.insert((open_index, close_index)) {
+                    [
+                        ActionGroup::new(
+                            [Action::Edit(Edit {
+                                range: (open_index..open_index + 1).into(),
+                                new: new_open.into(),
+                            })]
+                            .to_vec(),
+                        ),
+                        ActionGroup::new(
+                            [Action::Edit(Edit {
+                                range: (close_index..close_index + 1).into(),
+                                new: new_close.into(),
+                            })]
+                            .to_vec(),
+                        ),
+                    ]
+                    .to_vec()
+                } else {
+                    Vec::new()
+                };
+                edits
+                    .into_iter()
+                    .chain([ActionGroup::new(
+                        [Action::Select(selection.set_range(select_range))].to_vec(),
+                    )])
+                    .collect_vec()
+            })
+            .collect_vec();
+        let edit_transaction = EditTransaction::from_action_groups(action_groups);
         let _ = self.set_selection_mode(IfCurrentNotFound::LookForward, SelectionMode::Custom);
         self.apply_edit_transaction(edit_transaction)
     }
 
-    fn replace_with_pattern(&mut self, context: &Context) -> Result<Dispatches, anyhow::Error> {
-        let config = context.local_search_config();
+    /// Replace the matches at the given `ranges` only, and leave the rest untouched.
+    pub(crate) fn replace_ranges_with_pattern(
+        &mut self,
+        ranges: Vec<Range<Position>>,
+        config: &LocalSearchConfig,
+    ) -> anyhow::Result<Dispatches> {
+        let selections: Vec<_> = ranges
+            .into_iter()
+            .map(|range| -> anyhow::Result<_> {
+                Ok(Selection::new(
+                    self.buffer().position_range_to_char_index_range(&range)?,
+                ))
+            })
+            .try_collect()?;
+        let Some(selections) = NonEmpty::from_vec(selections) else {
+            return Ok(Dispatches::default());
+        };
+        self.set_selection_set(self.selection_set.clone().set_selections(selections));
+        self.replace_with_pattern(config)
+    }
+
+    fn replace_with_pattern(
+        &mut self,
+        config: &LocalSearchConfig,
+    ) -> Result<Dispatches, anyhow::Error> {
         match config.mode {
             LocalSearchConfigMode::AstGrep => {
                 let edits = if let Some(language) = self.buffer().treesitter_language() {
@@ -2818,8 +4356,42 @@ impl Editor {
         .append(Dispatch::ToEditor(EnterInsertMode(Direction::Start)))
     }
 
-    fn pipe_to_shell(&mut self, command: String) -> Result<Dispatches, anyhow::Error> {
-        self.transform_selection(Transformation::PipeToShell { command })
+    fn append_terminator(&mut self) -> anyhow::Result<Dispatches> {
+        let action_groups = {
+            let buffer = self.buffer();
+            let terminators = self
+                .selection_set
+                .map(|selection| -> anyhow::Result<_> {
+                    let node = buffer
+                        .get_current_node(selection, false)?
+                        .ok_or_else(|| anyhow::anyhow!("The buffer has no syntax tree"))?;
+                    let (byte, terminator) = insert_assist::terminator(node)?;
+                    Ok((selection.clone(), buffer.byte_to_char(byte)?, terminator))
+                })
+                .into_iter()
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            let mut terminated = HashSet::new();
+            terminators
+                .into_iter()
+                .rev()
+                .map(|(selection, char_index, terminator)| {
+                    // Selections within the same statement are terminated once, along with the
+                    // last of them, so that the edit does not offset the other selections
+                    let edit = terminated.insert(char_index).then(|| {
+                        Action::Edit(Edit {
+                            range: (char_index..char_index).into(),
+                            new: Rope::from_str(&terminator.to_string()),
+                        })
+                    });
+                    ActionGroup::new(
+                        edit.into_iter()
+                            .chain([Action::Select(selection)])
+                            .collect_vec(),
+                    )
+                })
+                .collect_vec()
+        };
+        self.apply_edit_transaction(EditTransaction::from_action_groups(action_groups))
     }
 
     fn show_current_tree_sitter_node_sexp(&self) -> Result<Dispatches, anyhow::Error> {
@@ -2835,10 +4407,7 @@ impl Editor {
     }
 
     fn indent(&mut self) -> Result<Dispatches, anyhow::Error> {
-        let indentation: Rope = std::iter::repeat(INDENT_CHAR)
-            .take(INDENT_WIDTH)
-            .collect::<String>()
-            .into();
+        let indentation: Rope = self.buffer().indentation().unit().into();
         let edit_transaction = EditTransaction::from_action_groups(
             self.selection_set
                 .map(|selection| -> anyhow::Result<_> {
@@ -2871,7 +4440,7 @@ impl Editor {
                         .join("")
                         .into();
                     let select_range = {
-                        let offset: isize = INDENT_WIDTH as isize;
+                        let offset: isize = indentation.len_chars() as isize;
                         let start = original_range.start.apply_offset(offset);
                         let original_len = original_range.len();
                         let end =
@@ -2898,6 +4467,8 @@ impl Editor {
     }
 
     fn dedent(&mut self) -> Result<Dispatches, anyhow::Error> {
+        let indentation = self.buffer().indentation().unit();
+        let indent_char = indentation.chars().next().unwrap_or(' ');
         let edit_transaction = EditTransaction::from_action_groups(
             self.selection_set
                 .map(|selection| -> anyhow::Result<_> {
@@ -2911,8 +4482,8 @@ impl Editor {
                     let content = self.buffer().slice(&linewise_range)?;
                     let get_remove_leading_char_count = |line: &str| {
                         let leading_indent_count =
-                            line.chars().take_while(|c| c == &INDENT_CHAR).count();
-                        leading_indent_count.min(INDENT_WIDTH)
+                            line.chars().take_while(|c| c == &indent_char).count();
+                        leading_indent_count.min(indentation.len())
                     };
                     let modified_lines = content
                         .lines()
@@ -3223,6 +4794,8 @@ pub(crate) enum DispatchEditor {
     },
     ScrollPageDown,
     ScrollPageUp,
+    ScrollFullPageDown,
+    ScrollFullPageUp,
     #[cfg(test)]
     AlignViewTop,
     #[cfg(test)]
@@ -3262,6 +4835,13 @@ pub(crate) enum DispatchEditor {
     Insert(String),
     MoveToLineStart,
     MoveToLineEnd,
+    /// Move the cursors up (`Direction::Start`) or down (`Direction::End`) by one line,
+    /// where the line is a display line if `display` is true, otherwise a logical line
+    MoveVertically {
+        direction: Direction,
+        display: bool,
+    },
+    MoveToDisplayLineEdge(Direction),
     #[cfg(test)]
     MatchLiteral(String),
     SelectSurround {
@@ -3269,11 +4849,17 @@ pub(crate) enum DispatchEditor {
         kind: SurroundKind,
     },
     Open(Direction),
+    /// Insert the given content as new lines below the line of the primary cursor
+    InsertLinesBelow(String),
+    /// `Direction::End` means the next hunk, `Direction::Start` means the previous hunk
+    MoveToGitGutterHunk(Direction),
+    RevertGitGutterHunk,
     ToggleMark,
     EnterNormalMode,
     EnterExchangeMode,
     EnterReplaceMode,
     EnterMultiCursorMode,
+    EnterBlockMode,
     CursorAddToAllSelections,
     CyclePrimarySelection(Direction),
     CursorKeepPrimaryOnly,
@@ -3282,6 +4868,9 @@ pub(crate) enum DispatchEditor {
     },
     Undo,
     Redo,
+    /// Undo the most recent change within the primary selection,
+    /// while keeping the later changes elsewhere
+    UndoWithinSelection,
     KillLine(Direction),
     #[cfg(test)]
     Reset,
@@ -3295,38 +4884,85 @@ pub(crate) enum DispatchEditor {
     ReplaceCurrentSelectionWith(String),
     TryReplaceCurrentLongWord(String),
     SelectLineAt(usize),
+    /// 0-based
+    GoToLine {
+        line: usize,
+        store_history: bool,
+    },
+    RestoreView {
+        selection_set: SelectionSet,
+        scroll_offset: u16,
+    },
+    ExpandSyntaxNode,
+    ShrinkSyntaxNode,
+    MoveToSiblingSyntaxNode(Direction),
     ShowKeymapLegendNormalMode,
     ShowKeymapLegendInsertMode,
     Paste {
         direction: Direction,
         use_system_clipboard: bool,
     },
+    /// Paste the primary selection (the selection that is pasted by middle-clicking on Linux)
+    PastePrimarySelection(Direction),
     SwapCursorWithAnchor,
     MoveCharacterBack,
     MoveCharacterForward,
     ShowKeymapLegendHelp,
     DeleteSurround(EnclosureKind),
+    /// Delete the innermost surrounding pair of any kind
+    DeleteNearestSurround,
     ChangeSurround {
         from: EnclosureKind,
         to: EnclosureKind,
     },
+    /// Change the innermost surrounding pair of any kind
+    ChangeNearestSurround(EnclosureKind),
     Replace(Movement),
     ApplyPositionalEdits(Vec<CompletionItemEdit>),
     ReplaceWithPreviousCopiedText,
     ReplaceWithNextCopiedText,
     MoveToLastChar,
-    PipeToShell {
-        command: String,
-    },
     ShowCurrentTreeSitterNodeSexp,
     Indent,
     Dedent,
+    /// Toggle line comments (or a block comment) using the comment tokens of the language
+    ToggleComment,
+    /// Fold the innermost unfolded range that contains the line of each cursor
+    Fold,
+    /// Unfold the folds that contain the line of each cursor
+    Unfold,
+    FoldAll,
+    UnfoldAll,
+    /// Decrease the level of the markdown heading under the cursor and its subheadings
+    PromoteHeading,
+    /// Increase the level of the markdown heading under the cursor and its subheadings
+    DemoteHeading,
+    /// Swap the markdown heading under the cursor and its subheadings with the previous
+    /// (`Direction::Start`) or the next sibling heading
+    MoveHeadingSubtree(Direction),
+    /// Fold the sections of the markdown headings, so that only the headings are shown
+    FoldHeadings,
+    /// Convert the line endings of the buffer, which is then saved with this line ending
+    SetLineEnding(EndOfLine),
+    /// Remove the trailing spaces and tabs of every line of the buffer
+    TrimTrailingWhitespace,
+    /// Insert `;` or `,` at the end of the statement or the list element of each selection,
+    /// without moving the selections
+    AppendTerminator,
     SwapExtensionDirection,
     CollapseSelection(Direction),
     FilterSelectionMatchingSearch {
         search: String,
         maintain: bool,
     },
+    /// Aligns the first match of the pattern on each line of the selections
+    Align(String),
+    /// If `sequential`, the nth selection from the top is incremented by n times the `amount`
+    Increment {
+        amount: i64,
+        sequential: bool,
+    },
+    GoToMatchingBracket,
     EnterNewline,
     DeleteCurrentCursor(Direction),
     BreakSelection,
@@ -3346,6 +4982,3 @@ impl std::fmt::Display for SurroundKind {
         }
     }
 }
-
-const INDENT_CHAR: char = ' ';
-const INDENT_WIDTH: usize = 4;
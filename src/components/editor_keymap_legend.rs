@@ -6,18 +6,23 @@ use event::KeyEvent;
 use itertools::Itertools;
 
 use crate::{
-    app::{Dispatch, Dispatches, FilePickerKind, Scope},
+    app::{Dispatch, Dispatches, FilePickerKind, PipeToShellOutput, Scope},
     components::{editor::Movement, keymap_legend::KeymapLegendSection},
     context::{Context, LocalSearchConfigMode, Search},
+    dap::session::DebugCommand,
     git::DiffMode,
     list::grep::RegexConfig,
+    macros::MacroName,
+    named_mark::NAMED_MARKS,
     quickfix_list::{DiagnosticSeverityRange, QuickfixListType},
     selection::SelectionMode,
+    selection_mode::text_object::TextObjectKind,
     surround::EnclosureKind,
     transformation::Transformation,
 };
 
 use super::{
+    diff_view::DiffBase,
     editor::{
         Direction, DispatchEditor, Editor, HandleEventResult, IfCurrentNotFound, Mode, SurroundKind,
     },
@@ -79,6 +84,11 @@ impl Editor {
                     "To Index (1-based)".to_string(),
                     Dispatch::OpenMoveToIndexPrompt,
                 ),
+                Keymap::new(
+                    "#",
+                    "To Line (1-based or +/- relative)".to_string(),
+                    Dispatch::OpenGoToLinePrompt,
+                ),
             ]),
         }
     }
@@ -102,6 +112,31 @@ impl Editor {
                     Direction::End.format_action("Cycle primary selection"),
                     Dispatch::ToEditor(CyclePrimarySelection(Direction::End)),
                 ),
+                Keymap::new(
+                    "alt+m",
+                    "Go to matching bracket".to_string(),
+                    Dispatch::ToEditor(GoToMatchingBracket),
+                ),
+                Keymap::new(
+                    "alt+o",
+                    "Expand to enclosing syntax node".to_string(),
+                    Dispatch::ToEditor(ExpandSyntaxNode),
+                ),
+                Keymap::new(
+                    "alt+i",
+                    "Shrink to previous child syntax node".to_string(),
+                    Dispatch::ToEditor(ShrinkSyntaxNode),
+                ),
+                Keymap::new(
+                    "alt+n",
+                    Direction::End.format_action("Named syntax node"),
+                    Dispatch::ToEditor(MoveToSiblingSyntaxNode(Direction::End)),
+                ),
+                Keymap::new(
+                    "alt+b",
+                    Direction::Start.format_action("Named syntax node"),
+                    Dispatch::ToEditor(MoveToSiblingSyntaxNode(Direction::Start)),
+                ),
                 Keymap::new(
                     "ctrl+d",
                     "Scroll down".to_string(),
@@ -112,6 +147,16 @@ impl Editor {
                     "Scroll up".to_string(),
                     Dispatch::ToEditor(ScrollPageUp),
                 ),
+                Keymap::new(
+                    "ctrl+f",
+                    "Scroll down a page".to_string(),
+                    Dispatch::ToEditor(ScrollFullPageDown),
+                ),
+                Keymap::new(
+                    "ctrl+b",
+                    "Scroll up a page".to_string(),
+                    Dispatch::ToEditor(ScrollFullPageUp),
+                ),
                 Keymap::new("ctrl+o", "Go back".to_string(), Dispatch::ToEditor(GoBack)),
                 Keymap::new(
                     "tab",
@@ -303,6 +348,22 @@ impl Editor {
                     ),
                     Keymap::new("u", "Undo".to_string(), Dispatch::ToEditor(Undo)),
                     Keymap::new("U", "Redo".to_string(), Dispatch::ToEditor(Redo)),
+                    Keymap::new(
+                        "ctrl+a",
+                        "Increment".to_string(),
+                        Dispatch::ToEditor(Increment {
+                            amount: 1,
+                            sequential: false,
+                        }),
+                    ),
+                    Keymap::new(
+                        "ctrl+x",
+                        "Decrement".to_string(),
+                        Dispatch::ToEditor(Increment {
+                            amount: -1,
+                            sequential: false,
+                        }),
+                    ),
                     Keymap::new(
                         "ctrl+r",
                         "Replace with pattern".to_string(),
@@ -346,7 +407,7 @@ impl Editor {
                     Keymap::new(
                         "|",
                         "Pipe to shell".to_string(),
-                        Dispatch::OpenPipeToShellPrompt,
+                        Dispatch::OpenPipeToShellPrompt(PipeToShellOutput::ReplaceSelections),
                     ),
                     Keymap::new(
                         "/",
@@ -428,6 +489,23 @@ impl Editor {
                     ),
                 ]
                 .into_iter()
+                .chain(
+                    use_system_clipboard
+                        .then(|| {
+                            [Direction::End, Direction::Start].map(|direction| {
+                                Keymap::new(
+                                    match direction {
+                                        Direction::Start => "M",
+                                        Direction::End => "m",
+                                    },
+                                    direction.format_action("Paste primary selection"),
+                                    Dispatch::ToEditor(PastePrimarySelection(direction)),
+                                )
+                            })
+                        })
+                        .into_iter()
+                        .flatten(),
+                )
                 .chain(Some(if self.mode == Mode::MultiCursor {
                     Keymap::new(
                         "r",
@@ -476,7 +554,16 @@ impl Editor {
         }
     }
 
-    pub(crate) fn insert_mode_keymap_legend_config(&self) -> KeymapLegendConfig {
+    pub(crate) fn insert_mode_keymap_legend_config(&self, context: &Context) -> KeymapLegendConfig {
+        let display = context.display_line_movement();
+        let (line_start, line_end) = if display {
+            (
+                MoveToDisplayLineEdge(Direction::Start),
+                MoveToDisplayLineEdge(Direction::End),
+            )
+        } else {
+            (MoveToLineStart, MoveToLineEnd)
+        };
         KeymapLegendConfig {
             title: "Insert mode keymaps".to_string(),
             body: KeymapLegendBody::MultipleSections {
@@ -497,12 +584,12 @@ impl Editor {
                             Keymap::new(
                                 "ctrl+a",
                                 "Move to line start".to_string(),
-                                Dispatch::ToEditor(MoveToLineStart),
+                                Dispatch::ToEditor(line_start.clone()),
                             ),
                             Keymap::new(
                                 "ctrl+e",
                                 "Move to line end".to_string(),
-                                Dispatch::ToEditor(MoveToLineEnd),
+                                Dispatch::ToEditor(line_end.clone()),
                             ),
                             Keymap::new(
                                 "ctrl+k",
@@ -557,17 +644,33 @@ impl Editor {
                             Keymap::new(
                                 "tab",
                                 "Enter tab".to_string(),
-                                Dispatch::ToEditor(Insert("\t".to_string())),
+                                Dispatch::ToEditor(Insert(self.buffer().indentation().unit())),
                             ),
                             Keymap::new(
                                 "home",
                                 "Move to line start".to_string(),
-                                Dispatch::ToEditor(MoveToLineStart),
+                                Dispatch::ToEditor(line_start),
                             ),
                             Keymap::new(
                                 "end",
                                 "Move to line end".to_string(),
-                                Dispatch::ToEditor(MoveToLineEnd),
+                                Dispatch::ToEditor(line_end),
+                            ),
+                            Keymap::new(
+                                "up",
+                                "Move up a line".to_string(),
+                                Dispatch::ToEditor(MoveVertically {
+                                    direction: Direction::Start,
+                                    display,
+                                }),
+                            ),
+                            Keymap::new(
+                                "down",
+                                "Move down a line".to_string(),
+                                Dispatch::ToEditor(MoveVertically {
+                                    direction: Direction::End,
+                                    display,
+                                }),
                             ),
                         ]),
                     },
@@ -579,9 +682,13 @@ impl Editor {
         }
     }
 
-    pub(crate) fn handle_insert_mode(&mut self, event: KeyEvent) -> anyhow::Result<Dispatches> {
+    pub(crate) fn handle_insert_mode(
+        &mut self,
+        context: &Context,
+        event: KeyEvent,
+    ) -> anyhow::Result<Dispatches> {
         if let Some(dispatches) = self
-            .insert_mode_keymap_legend_config()
+            .insert_mode_keymap_legend_config(context)
             .keymaps()
             .iter()
             .find(|keymap| &event == keymap.event())
@@ -589,7 +696,11 @@ impl Editor {
         {
             Ok(dispatches)
         } else if let KeyCode::Char(c) = event.code {
-            return self.insert(&c.to_string());
+            let dispatches = self.insert_char(c, context.wrap_selection_on_type())?;
+            return Ok(match context.hard_wrap_width() {
+                Some(width) if !c.is_whitespace() => dispatches.chain(self.hard_wrap(width)?),
+                _ => dispatches,
+            });
         } else {
             Ok(Default::default())
         }
@@ -668,6 +779,11 @@ impl Editor {
                         Dispatch::ToEditor(EnterMultiCursorMode),
                     )
                 }))
+                .chain(Some(Keymap::new(
+                    "Q",
+                    "Enter Block mode".to_string(),
+                    Dispatch::ToEditor(EnterBlockMode),
+                )))
                 .collect_vec(),
             ),
             title: "Movement-action submodes".to_string(),
@@ -712,6 +828,65 @@ impl Editor {
             },
         }
     }
+    /// Every keybinding of the normal mode (including those of the nested keymap legends),
+    /// paired with the keys that trigger it, which is the registry of the command palette.
+    pub(crate) fn normal_mode_keybindings(
+        &self,
+        context: &Context,
+    ) -> Vec<(Vec<KeyEvent>, Keymap)> {
+        /// Legends nested deeper than this are listed as the keybindings that open them
+        const MAX_KEYS: usize = 3;
+        fn keybindings(
+            config: &KeymapLegendConfig,
+            prefix: &[KeyEvent],
+        ) -> Vec<(Vec<KeyEvent>, Keymap)> {
+            config
+                .keymaps()
+                .into_iter()
+                .flat_map(|keymap| {
+                    let keys = prefix
+                        .iter()
+                        .chain(Some(keymap.event()))
+                        .cloned()
+                        .collect_vec();
+                    match keymap.dispatch() {
+                        Dispatch::ShowKeymapLegend(nested) if keys.len() < MAX_KEYS => {
+                            keybindings(nested, &keys)
+                        }
+                        _ => [(keys, keymap.clone())].to_vec(),
+                    }
+                })
+                .collect_vec()
+        }
+        keybindings(&self.normal_mode_keymap_legend_config(context), &[])
+    }
+
+    /// Describes what typing `keys` does in the current mode,
+    /// by following them through the nested keymap legends.
+    pub(crate) fn describe_keys(&self, context: &Context, keys: &[KeyEvent]) -> Option<String> {
+        let mut config = match self.mode {
+            Mode::Normal => self.normal_mode_keymap_legend_config(context),
+            Mode::Insert => self.insert_mode_keymap_legend_config(context),
+            _ => return None,
+        };
+        let (last, prefix) = keys.split_last()?;
+        for key in prefix {
+            let keymap = config
+                .keymaps()
+                .into_iter()
+                .find(|keymap| keymap.event() == key)?;
+            let Dispatch::ShowKeymapLegend(next) = keymap.dispatch().clone() else {
+                return None;
+            };
+            config = next;
+        }
+        config
+            .keymaps()
+            .into_iter()
+            .find(|keymap| keymap.event() == last)
+            .map(|keymap| keymap.description().to_string())
+    }
+
     fn normal_mode_keymaps(&self, context: &Context) -> Keymaps {
         Keymaps::new(
             &self
@@ -782,10 +957,7 @@ impl Editor {
                     },
                     KeymapLegendSection {
                         title: "Surround".to_string(),
-                        keymaps: generate_enclosures_keymaps(|enclosure| {
-                            let (open, close) = enclosure.open_close_symbols_str();
-                            Dispatch::ToEditor(Surround(open.to_string(), close.to_string()))
-                        }),
+                        keymaps: surround_keymaps(),
                     },
                 ]
                 .to_vec(),
@@ -801,10 +973,38 @@ impl Editor {
             return Ok(keymap.get_dispatches());
         }
         log::info!("unhandled event: {:?}", event);
-        Ok(vec![].into())
+        Ok(Dispatches::one(Dispatch::RingBell))
+    }
+
+    /// Movements can be discarded when the input queue falls behind,
+    /// because they only change the selections.
+    pub(crate) fn is_movement_key(&self, context: &Context, event: &KeyEvent) -> bool {
+        self.mode == Mode::Normal
+            && self
+                .normal_mode_keymaps(context)
+                .get(event)
+                .is_some_and(|keymap| {
+                    matches!(
+                        keymap.dispatch(),
+                        Dispatch::ToEditor(
+                            MoveSelection(_)
+                                | MoveVertically { .. }
+                                | MoveCharacterBack
+                                | MoveCharacterForward
+                                | ScrollPageDown
+                                | ScrollPageUp
+                                | ScrollFullPageDown
+                                | ScrollFullPageUp
+                        )
+                    )
+                })
     }
 
     pub(crate) fn transform_keymap_legend_config(&self) -> KeymapLegendConfig {
+        let sort_lines = |numeric, case_sensitive| Transformation::SortLines {
+            numeric,
+            case_sensitive,
+        };
         KeymapLegendConfig {
             title: "Transform".to_string(),
 
@@ -836,13 +1036,65 @@ impl Editor {
                             .collect_vec(),
                         ),
                     },
+                    KeymapLegendSection {
+                        title: "Lines".to_string(),
+                        keymaps: Keymaps::new(
+                            &[
+                                ("o", "Sort", sort_lines(false, true)),
+                                ("i", "Sort (case-insensitive)", sort_lines(false, false)),
+                                ("n", "Sort numerically", sort_lines(true, true)),
+                                ("d", "Remove duplicates", Transformation::UniqueLines),
+                                ("v", "Reverse", Transformation::ReverseLines),
+                            ]
+                            .into_iter()
+                            .map(|(key, description, transformation)| {
+                                Keymap::new(
+                                    key,
+                                    description.to_string(),
+                                    Dispatch::ToEditor(Transform(transformation)),
+                                )
+                            })
+                            .collect_vec(),
+                        ),
+                    },
                     KeymapLegendSection {
                         title: "Other".to_string(),
-                        keymaps: Keymaps::new(&[Keymap::new(
-                            "w",
-                            "Wrap".to_string(),
-                            Dispatch::ToEditor(Transform(Transformation::Wrap)),
-                        )]),
+                        keymaps: Keymaps::new(&[
+                            Keymap::new(
+                                "w",
+                                "Wrap".to_string(),
+                                Dispatch::ToEditor(Transform(Transformation::Wrap)),
+                            ),
+                            Keymap::new(
+                                "r",
+                                "ROT13".to_string(),
+                                Dispatch::ToEditor(Transform(Transformation::Rot13)),
+                            ),
+                            Keymap::new(
+                                "/",
+                                "Toggle comment".to_string(),
+                                Dispatch::ToEditor(ToggleComment),
+                            ),
+                            Keymap::new("=", "Align".to_string(), Dispatch::OpenAlignPrompt),
+                            Keymap::new(
+                                "+",
+                                "Increment by".to_string(),
+                                Dispatch::OpenIncrementPrompt,
+                            ),
+                            Keymap::new(
+                                "#",
+                                "Increment sequentially (1, 2, 3, ...)".to_string(),
+                                Dispatch::ToEditor(Increment {
+                                    amount: 1,
+                                    sequential: true,
+                                }),
+                            ),
+                            Keymap::new(
+                                "|",
+                                "Pipe to shell (show output in scratch buffer)".to_string(),
+                                Dispatch::OpenPipeToShellPrompt(PipeToShellOutput::ScratchBuffer),
+                            ),
+                        ]),
                     },
                 ]
                 .to_vec(),
@@ -916,11 +1168,157 @@ impl Editor {
                                     context.current_working_directory().clone()
                                 })),
                             ),
+                            Keymap::new(
+                                "w",
+                                format!(
+                                    "Toggle hard wrap (width = {})",
+                                    crate::hard_wrap::DEFAULT_TEXT_WIDTH
+                                ),
+                                Dispatch::SetHardWrapWidth(
+                                    context
+                                        .hard_wrap_width()
+                                        .is_none()
+                                        .then_some(crate::hard_wrap::DEFAULT_TEXT_WIDTH),
+                                ),
+                            ),
+                            Keymap::new(
+                                "W",
+                                "Toggle display line movement (for soft-wrapped lines)".to_string(),
+                                Dispatch::SetDisplayLineMovement(!context.display_line_movement()),
+                            ),
+                            Keymap::new(
+                                "y",
+                                "Toggle logical order rendering (for right-to-left text)"
+                                    .to_string(),
+                                Dispatch::SetLogicalOrderRendering(
+                                    !context.logical_order_rendering(),
+                                ),
+                            ),
+                            Keymap::new(
+                                "a",
+                                "Toggle wrapping selections on typing a bracket or quote"
+                                    .to_string(),
+                                Dispatch::SetWrapSelectionOnType(!context.wrap_selection_on_type()),
+                            ),
+                            Keymap::new(
+                                "T",
+                                "Open terminal".to_string(),
+                                Dispatch::OpenTerminal { main_panel: false },
+                            ),
+                            Keymap::new(
+                                "alt+t",
+                                "Open terminal in the main panel".to_string(),
+                                Dispatch::OpenTerminal { main_panel: true },
+                            ),
+                            Keymap::new("k", "Run task".to_string(), Dispatch::OpenShellTaskPrompt),
+                            Keymap::new(
+                                "K",
+                                "Rerun last task".to_string(),
+                                Dispatch::RerunShellTask,
+                            ),
+                            Keymap::new(
+                                "o",
+                                "Write selections to file".to_string(),
+                                Dispatch::OpenWriteSelectionsPrompt { append: false },
+                            ),
+                            Keymap::new(
+                                "O",
+                                "Append selections to file".to_string(),
+                                Dispatch::OpenWriteSelectionsPrompt { append: true },
+                            ),
+                            Keymap::new(
+                                "i",
+                                "Insert file (or !command output) below cursor".to_string(),
+                                Dispatch::OpenInsertBelowCursorPrompt,
+                            ),
+                            Keymap::new(
+                                "[",
+                                "Previous git gutter hunk".to_string(),
+                                Dispatch::ToEditor(DispatchEditor::MoveToGitGutterHunk(
+                                    Direction::Start,
+                                )),
+                            ),
+                            Keymap::new(
+                                "]",
+                                "Next git gutter hunk".to_string(),
+                                Dispatch::ToEditor(DispatchEditor::MoveToGitGutterHunk(
+                                    Direction::End,
+                                )),
+                            ),
+                            Keymap::new(
+                                "R",
+                                "Revert git gutter hunk".to_string(),
+                                Dispatch::ToEditor(DispatchEditor::RevertGitGutterHunk),
+                            ),
+                            Keymap::new(
+                                "B",
+                                "Toggle git blame of current line".to_string(),
+                                Dispatch::SetShowGitBlame(!context.show_git_blame()),
+                            ),
+                            Keymap::new(
+                                "L",
+                                "Git blame panel".to_string(),
+                                Dispatch::OpenGitBlamePanel,
+                            ),
+                            Keymap::new(
+                                "d",
+                                "Diff against file on disk".to_string(),
+                                Dispatch::OpenDiffView(DiffBase::FileOnDisk),
+                            ),
+                            Keymap::new(
+                                "D",
+                                "Diff against HEAD".to_string(),
+                                Dispatch::OpenDiffView(DiffBase::Head),
+                            ),
+                            Keymap::new(
+                                "v",
+                                "Diff against another buffer".to_string(),
+                                Dispatch::OpenDiffBufferPrompt,
+                            ),
+                            Keymap::new(
+                                "C",
+                                "Diff selection against clipboard".to_string(),
+                                Dispatch::OpenClipboardDiffView {
+                                    use_system_clipboard: false,
+                                },
+                            ),
+                            Keymap::new(
+                                "F",
+                                "Fold".to_string(),
+                                Dispatch::ShowKeymapLegend(self.fold_keymap_legend_config()),
+                            ),
+                            Keymap::new(
+                                "m",
+                                "Macro".to_string(),
+                                Dispatch::ShowKeymapLegend(
+                                    self.macro_keymap_legend_config(context),
+                                ),
+                            ),
+                            Keymap::new(
+                                "M",
+                                "Named mark".to_string(),
+                                Dispatch::ShowKeymapLegend(self.named_mark_keymap_legend_config()),
+                            ),
+                            Keymap::new(
+                                "X",
+                                "Debugger".to_string(),
+                                Dispatch::ShowKeymapLegend(self.debugger_keymap_legend_config()),
+                            ),
                             Keymap::new(
                                 "z",
                                 "Undo Tree".to_string(),
                                 Dispatch::ToEditor(DispatchEditor::EnterUndoTreeMode),
                             ),
+                            Keymap::new(
+                                "u",
+                                "Undo within selection".to_string(),
+                                Dispatch::ToEditor(DispatchEditor::UndoWithinSelection),
+                            ),
+                            Keymap::new(
+                                "S",
+                                "Spelling suggestions".to_string(),
+                                Dispatch::OpenSpellingSuggestionsPrompt,
+                            ),
                             Keymap::new(
                                 "x",
                                 "Tree-sitter node S-expr".to_string(),
@@ -931,6 +1329,11 @@ impl Editor {
                                 "Help".to_string(),
                                 Dispatch::ToEditor(DispatchEditor::ShowKeymapLegendHelp),
                             ),
+                            Keymap::new(
+                                "p",
+                                "Command palette".to_string(),
+                                Dispatch::OpenCommandPalette,
+                            ),
                         ]),
                     }))
                     .collect(),
@@ -938,6 +1341,149 @@ impl Editor {
         }
     }
 
+    fn fold_keymap_legend_config(&self) -> KeymapLegendConfig {
+        KeymapLegendConfig {
+            title: "Fold".to_string(),
+            body: KeymapLegendBody::SingleSection {
+                keymaps: Keymaps::new(&[
+                    Keymap::new("f", "Fold".to_string(), Dispatch::ToEditor(Fold)),
+                    Keymap::new("u", "Unfold".to_string(), Dispatch::ToEditor(Unfold)),
+                    Keymap::new("F", "Fold all".to_string(), Dispatch::ToEditor(FoldAll)),
+                    Keymap::new("U", "Unfold all".to_string(), Dispatch::ToEditor(UnfoldAll)),
+                ]),
+            },
+        }
+    }
+
+    fn debugger_keymap_legend_config(&self) -> KeymapLegendConfig {
+        KeymapLegendConfig {
+            title: "Debugger".to_string(),
+            body: KeymapLegendBody::SingleSection {
+                keymaps: Keymaps::new(&[
+                    Keymap::new("s", "Start".to_string(), Dispatch::OpenDebugPrompt),
+                    Keymap::new(
+                        "b",
+                        "Toggle breakpoint".to_string(),
+                        Dispatch::ToggleBreakpoint,
+                    ),
+                    Keymap::new(
+                        "c",
+                        "Continue".to_string(),
+                        Dispatch::Debug(DebugCommand::Continue),
+                    ),
+                    Keymap::new(
+                        "n",
+                        "Step over".to_string(),
+                        Dispatch::Debug(DebugCommand::StepOver),
+                    ),
+                    Keymap::new(
+                        "i",
+                        "Step in".to_string(),
+                        Dispatch::Debug(DebugCommand::StepIn),
+                    ),
+                    Keymap::new(
+                        "o",
+                        "Step out".to_string(),
+                        Dispatch::Debug(DebugCommand::StepOut),
+                    ),
+                    Keymap::new(
+                        "p",
+                        "Pause".to_string(),
+                        Dispatch::Debug(DebugCommand::Pause),
+                    ),
+                    Keymap::new("w", "Add watch".to_string(), Dispatch::OpenDebugWatchPrompt),
+                    Keymap::new("x", "Stop".to_string(), Dispatch::Debug(DebugCommand::Stop)),
+                ]),
+            },
+        }
+    }
+
+    fn named_mark_keymap_legend_config(&self) -> KeymapLegendConfig {
+        KeymapLegendConfig {
+            title: "Named mark".to_string(),
+            body: KeymapLegendBody::SingleSection {
+                keymaps: Keymaps::new(&[
+                    Keymap::new(
+                        "s",
+                        "Set".to_string(),
+                        Dispatch::ShowKeymapLegend(named_mark_keymap_legend_config(
+                            "Set mark (A-Z are global)",
+                            Dispatch::SetNamedMark,
+                        )),
+                    ),
+                    Keymap::new(
+                        "g",
+                        "Go to".to_string(),
+                        Dispatch::ShowKeymapLegend(named_mark_keymap_legend_config(
+                            "Go to mark",
+                            Dispatch::GoToNamedMark,
+                        )),
+                    ),
+                    Keymap::new(
+                        "d",
+                        "Delete".to_string(),
+                        Dispatch::ShowKeymapLegend(named_mark_keymap_legend_config(
+                            "Delete mark",
+                            Dispatch::DeleteNamedMark,
+                        )),
+                    ),
+                    Keymap::new("p", "Pick".to_string(), Dispatch::OpenNamedMarkPicker),
+                ]),
+            },
+        }
+    }
+
+    fn macro_keymap_legend_config(&self, context: &Context) -> KeymapLegendConfig {
+        let record = match context.recording_macro() {
+            Some(register) => Keymap::new(
+                "r",
+                format!("Stop recording @{register}"),
+                Dispatch::StopMacroRecording,
+            ),
+            None => Keymap::new(
+                "r",
+                "Record".to_string(),
+                Dispatch::ShowKeymapLegend(macro_register_keymap_legend_config(
+                    "Record macro into",
+                    Dispatch::StartMacroRecording,
+                )),
+            ),
+        };
+        let run = |each_selection| {
+            move |register| Dispatch::RunMacro {
+                name: MacroName::Register(register),
+                repeat: 1,
+                each_selection,
+            }
+        };
+        KeymapLegendConfig {
+            title: "Macro".to_string(),
+            body: KeymapLegendBody::SingleSection {
+                keymaps: Keymaps::new(&[
+                    record,
+                    Keymap::new(
+                        "p",
+                        "Run".to_string(),
+                        Dispatch::ShowKeymapLegend(macro_register_keymap_legend_config(
+                            "Run macro",
+                            run(false),
+                        )),
+                    ),
+                    Keymap::new(
+                        "P",
+                        "Run at each selection".to_string(),
+                        Dispatch::ShowKeymapLegend(macro_register_keymap_legend_config(
+                            "Run macro at each selection",
+                            run(true),
+                        )),
+                    ),
+                    Keymap::new("n", "Run N times".to_string(), Dispatch::OpenRunMacroPrompt),
+                    Keymap::new("e", "Edit".to_string(), Dispatch::OpenEditMacroPrompt),
+                ]),
+            },
+        }
+    }
+
     fn search_current_selection_keymap(
         &self,
         scope: Scope,
@@ -1059,6 +1605,33 @@ impl Editor {
                         )
                     }),
                 )
+                .chain(Some(Keymap::new(
+                    "v",
+                    "Git hunk (against revision)".to_string(),
+                    Dispatch::OpenGitHunkRefPrompt {
+                        scope,
+                        if_current_not_found,
+                    },
+                )))
+                .chain(match scope {
+                    Scope::Global => None,
+                    Scope::Local => Some(Keymap::new(
+                        "u",
+                        format!("Hunk ({})", DiffMode::UnsavedAgainstFileOnDisk.display()),
+                        Dispatch::ToEditor(SetSelectionMode(
+                            if_current_not_found,
+                            GitHunk(DiffMode::UnsavedAgainstFileOnDisk),
+                        )),
+                    )),
+                })
+                .chain(match scope {
+                    Scope::Global => Some(Keymap::new(
+                        "x",
+                        "Toggle exclusion of current quickfix item".to_string(),
+                        Dispatch::ExcludeCurrentQuickfixListItem,
+                    )),
+                    Scope::Local => None,
+                })
                 .collect_vec(),
             ),
         };
@@ -1203,16 +1776,53 @@ impl Editor {
         &self,
         kind: SurroundKind,
     ) -> KeymapLegendConfig {
+        let select_text_object = |kind: TextObjectKind| {
+            Dispatch::ToEditor(SetSelectionMode(
+                IfCurrentNotFound::LookForward,
+                TextObject(kind),
+            ))
+        };
         KeymapLegendConfig {
             title: format!("Select Surround ({:?})", kind),
 
-            body: KeymapLegendBody::SingleSection {
-                keymaps: generate_enclosures_keymaps(|enclosure| {
-                    Dispatch::ToEditor(SelectSurround {
-                        enclosure,
-                        kind: kind.clone(),
-                    })
-                }),
+            body: KeymapLegendBody::MultipleSections {
+                sections: [
+                    KeymapLegendSection {
+                        title: "Enclosure".to_string(),
+                        keymaps: generate_enclosures_keymaps(|enclosure| {
+                            Dispatch::ToEditor(SelectSurround {
+                                enclosure,
+                                kind: kind.clone(),
+                            })
+                        }),
+                    },
+                    KeymapLegendSection {
+                        title: "Text object".to_string(),
+                        keymaps: Keymaps::new(&[
+                            Keymap::new(
+                                "f",
+                                "Function".to_string(),
+                                select_text_object(TextObjectKind::Function(kind.clone())),
+                            ),
+                            Keymap::new(
+                                "c",
+                                "Class".to_string(),
+                                select_text_object(TextObjectKind::Class(kind.clone())),
+                            ),
+                            Keymap::new(
+                                "a",
+                                "Argument".to_string(),
+                                select_text_object(TextObjectKind::Argument),
+                            ),
+                            Keymap::new(
+                                "/",
+                                "Comment".to_string(),
+                                select_text_object(TextObjectKind::Comment),
+                            ),
+                        ]),
+                    },
+                ]
+                .to_vec(),
             },
         }
     }
@@ -1222,9 +1832,16 @@ impl Editor {
             title: "Delete Surround".to_string(),
 
             body: KeymapLegendBody::SingleSection {
-                keymaps: generate_enclosures_keymaps(|enclosure| {
-                    Dispatch::ToEditor(DeleteSurround(enclosure))
-                }),
+                keymaps: Keymaps::new(
+                    &enclosures_keymaps(|enclosure| Dispatch::ToEditor(DeleteSurround(enclosure)))
+                        .into_iter()
+                        .chain([Keymap::new(
+                            "n",
+                            "Nearest".to_string(),
+                            Dispatch::ToEditor(DeleteNearestSurround),
+                        )])
+                        .collect_vec(),
+                ),
             },
         }
     }
@@ -1234,10 +1851,7 @@ impl Editor {
             title: "Surround".to_string(),
 
             body: KeymapLegendBody::SingleSection {
-                keymaps: generate_enclosures_keymaps(|enclosure| {
-                    let (open, close) = enclosure.open_close_symbols_str();
-                    Dispatch::ToEditor(Surround(open.to_string(), close.to_string()))
-                }),
+                keymaps: surround_keymaps(),
             },
         }
     }
@@ -1249,28 +1863,43 @@ impl Editor {
             title: "Change Surround from:".to_string(),
 
             body: KeymapLegendBody::SingleSection {
-                keymaps: generate_enclosures_keymaps(|enclosure| {
-                    Dispatch::ShowKeymapLegend(
-                        self.change_surround_to_keymap_legend_config(enclosure),
-                    )
-                }),
+                keymaps: Keymaps::new(
+                    &enclosures_keymaps(|enclosure| {
+                        Dispatch::ShowKeymapLegend(
+                            self.change_surround_to_keymap_legend_config(Some(enclosure)),
+                        )
+                    })
+                    .into_iter()
+                    .chain([Keymap::new(
+                        "n",
+                        "Nearest".to_string(),
+                        Dispatch::ShowKeymapLegend(
+                            self.change_surround_to_keymap_legend_config(None),
+                        ),
+                    )])
+                    .collect_vec(),
+                ),
             },
         }
     }
 
     pub(crate) fn change_surround_to_keymap_legend_config(
         &self,
-        from: EnclosureKind,
+        from: Option<EnclosureKind>,
     ) -> super::keymap_legend::KeymapLegendConfig {
         KeymapLegendConfig {
-            title: format!("Change Surround from {} to:", from.to_str()),
+            title: format!(
+                "Change Surround from {} to:",
+                from.map(|from| from.to_str()).unwrap_or("Nearest")
+            ),
 
             body: KeymapLegendBody::SingleSection {
-                keymaps: generate_enclosures_keymaps(|enclosure| {
-                    Dispatch::ToEditor(ChangeSurround {
+                keymaps: generate_enclosures_keymaps(|enclosure| match from {
+                    Some(from) => Dispatch::ToEditor(ChangeSurround {
                         from,
                         to: enclosure,
-                    })
+                    }),
+                    None => Dispatch::ToEditor(ChangeNearestSurround(enclosure)),
                 }),
             },
         }
@@ -1278,22 +1907,80 @@ impl Editor {
 }
 
 fn generate_enclosures_keymaps(get_dispatch: impl Fn(EnclosureKind) -> Dispatch) -> Keymaps {
-    Keymaps::new(
-        &[
-            EnclosureKind::AngularBrackets,
-            EnclosureKind::Parentheses,
-            EnclosureKind::SquareBrackets,
-            EnclosureKind::CurlyBraces,
-            EnclosureKind::DoubleQuotes,
-            EnclosureKind::SingleQuotes,
-            EnclosureKind::Backticks,
-        ]
+    Keymaps::new(&enclosures_keymaps(get_dispatch))
+}
+
+fn enclosures_keymaps(get_dispatch: impl Fn(EnclosureKind) -> Dispatch) -> Vec<Keymap> {
+    EnclosureKind::all()
         .into_iter()
         .map(|enclosure| {
             let (key, _) = enclosure.open_close_symbols_str();
             let description = enclosure.to_str();
             Keymap::new(key, description.to_string(), get_dispatch(enclosure))
         })
+        .collect_vec()
+}
+
+fn surround_keymaps() -> Keymaps {
+    Keymaps::new(
+        &enclosures_keymaps(|enclosure| {
+            let (open, close) = enclosure.open_close_symbols_str();
+            Dispatch::ToEditor(Surround(open.to_string(), close.to_string()))
+        })
+        .into_iter()
+        .chain([Keymap::new(
+            "t",
+            "Tag".to_string(),
+            Dispatch::OpenSurroundWithTagPrompt,
+        )])
         .collect_vec(),
     )
 }
+
+const MACRO_REGISTERS: &str = "abcdefghijklmnopqrstuvwxyz";
+
+fn macro_register_keymap_legend_config(
+    title: &str,
+    dispatch: impl Fn(char) -> Dispatch,
+) -> KeymapLegendConfig {
+    KeymapLegendConfig {
+        title: title.to_string(),
+        body: KeymapLegendBody::SingleSection {
+            keymaps: Keymaps::new(
+                &MACRO_REGISTERS
+                    .char_indices()
+                    .map(|(index, register)| {
+                        Keymap::new(
+                            &MACRO_REGISTERS[index..index + 1],
+                            format!("@{register}"),
+                            dispatch(register),
+                        )
+                    })
+                    .collect_vec(),
+            ),
+        },
+    }
+}
+
+fn named_mark_keymap_legend_config(
+    title: &str,
+    dispatch: impl Fn(char) -> Dispatch,
+) -> KeymapLegendConfig {
+    KeymapLegendConfig {
+        title: title.to_string(),
+        body: KeymapLegendBody::SingleSection {
+            keymaps: Keymaps::new(
+                &NAMED_MARKS
+                    .char_indices()
+                    .map(|(index, name)| {
+                        Keymap::new(
+                            &NAMED_MARKS[index..index + 1],
+                            name.to_string(),
+                            dispatch(name),
+                        )
+                    })
+                    .collect_vec(),
+            ),
+        },
+    }
+}
@@ -1,7 +1,12 @@
+use std::collections::HashMap;
+
 use itertools::Itertools;
 use my_proc_macros::key;
 
-use crate::app::{Dispatch, Dispatches, YesNoPrompt};
+use crate::{
+    app::{Dispatch, Dispatches, YesNoPrompt},
+    git::{DiffMode, GitRepo},
+};
 use shared::canonicalized_path::CanonicalizedPath;
 
 use super::{
@@ -13,24 +18,75 @@ use super::{
 pub(crate) struct FileExplorer {
     editor: Editor,
     tree: Tree,
+    working_directory: CanonicalizedPath,
+    git_statuses: GitStatuses,
+}
+
+type GitStatuses = HashMap<CanonicalizedPath, GitStatus>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum GitStatus {
+    /// Untracked or newly added
+    New,
+    Modified,
+}
+
+impl GitStatus {
+    fn marker(&self) -> &'static str {
+        match self {
+            GitStatus::New => "+",
+            GitStatus::Modified => "M",
+        }
+    }
+}
+
+/// Directories containing changed files are marked with this
+const CHANGED_DIRECTORY_MARKER: &str = "•";
+
+/// Errors are ignored (e.g. the working directory is not a Git repository),
+/// because Git status markers are merely supplementary.
+fn get_git_statuses(working_directory: &CanonicalizedPath) -> GitStatuses {
+    GitRepo::try_from(working_directory)
+        .and_then(|repo| repo.diff_entries(DiffMode::UnstagedAgainstCurrentBranch))
+        .map(|entries| {
+            entries
+                .into_iter()
+                .map(|entry| {
+                    let status = if entry.is_new() {
+                        GitStatus::New
+                    } else {
+                        GitStatus::Modified
+                    };
+                    (entry.new_path(), status)
+                })
+                .collect()
+        })
+        .unwrap_or_default()
 }
 
 impl FileExplorer {
     pub(crate) fn new(path: &CanonicalizedPath) -> anyhow::Result<Self> {
         let tree = Tree::new(path)?;
-        let text = tree.render();
+        let git_statuses = get_git_statuses(path);
+        let text = tree.render(&git_statuses);
         let mut editor = Editor::from_text(
             shared::language::from_extension("yaml")
                 .and_then(|language| language.tree_sitter_language()),
             &format!("{}\n", text),
         );
         editor.set_title("File Explorer".to_string());
-        Ok(Self { editor, tree })
+        Ok(Self {
+            editor,
+            tree,
+            working_directory: path.clone(),
+            git_statuses,
+        })
     }
 
     pub(crate) fn reveal(&mut self, path: &CanonicalizedPath) -> anyhow::Result<Dispatches> {
         let tree = std::mem::take(&mut self.tree);
         self.tree = tree.reveal(path)?;
+        self.git_statuses = get_git_statuses(&self.working_directory);
         self.refresh_editor()?;
         if let Some(index) = self.tree.find_index(path) {
             self.editor_mut().select_line_at(index)
@@ -42,12 +98,14 @@ impl FileExplorer {
     pub(crate) fn refresh(&mut self, working_directory: &CanonicalizedPath) -> anyhow::Result<()> {
         let tree = std::mem::take(&mut self.tree);
         self.tree = tree.refresh(working_directory)?;
+        self.working_directory = working_directory.clone();
+        self.git_statuses = get_git_statuses(working_directory);
         self.refresh_editor()?;
         Ok(())
     }
 
     fn refresh_editor(&mut self) -> anyhow::Result<()> {
-        let text = self.tree.render();
+        let text = self.tree.render(&self.git_statuses);
         self.editor_mut().set_content(&text)
     }
 
@@ -214,20 +272,34 @@ impl Tree {
         })
     }
 
-    fn render_with_indent(&self, indent: usize) -> String {
+    fn render_with_indent(&self, indent: usize, git_statuses: &GitStatuses) -> String {
         self.nodes
             .iter()
             .map(|node| {
                 let content = match &node.kind {
-                    NodeKind::File => format!("{}  {}", node.path.icon(), node.name),
+                    NodeKind::File => {
+                        let marker = git_statuses
+                            .get(&node.path)
+                            .map(|status| format!(" [{}]", status.marker()))
+                            .unwrap_or_default();
+                        format!("{}  {}{}", node.path.icon(), node.name, marker)
+                    }
                     NodeKind::Directory { open, children } => {
                         let icon = if *open { "📂" } else { "📁" };
-                        let head = format!("{}  {}{}", icon, node.name, "/");
+                        let marker = if git_statuses
+                            .keys()
+                            .any(|path| path.to_path_buf().starts_with(node.path.to_path_buf()))
+                        {
+                            format!(" [{}]", CHANGED_DIRECTORY_MARKER)
+                        } else {
+                            String::new()
+                        };
+                        let head = format!("{}  {}{}{}", icon, node.name, "/", marker);
 
                         let tail = if *open {
                             children
                                 .as_ref()
-                                .map(|tree| tree.render_with_indent(indent + 1))
+                                .map(|tree| tree.render_with_indent(indent + 1, git_statuses))
                                 .unwrap_or_default()
                         } else {
                             String::new()
@@ -245,8 +317,8 @@ impl Tree {
             .join("\n")
     }
 
-    fn render(&self) -> String {
-        self.render_with_indent(0)
+    fn render(&self, git_statuses: &GitStatuses) -> String {
+        self.render_with_indent(0, git_statuses)
     }
 
     fn reveal(self, path: &CanonicalizedPath) -> anyhow::Result<Self> {
@@ -419,6 +491,31 @@ mod test_file_explorer {
         })
     }
 
+    #[test]
+    fn git_status_markers() -> anyhow::Result<()> {
+        execute_test(|s| {
+            Box::new([
+                App(OpenFile(s.main_rs())),
+                Editor(Insert("// Hello".to_string())),
+                App(SaveAll),
+                App(RevealInExplorer(s.main_rs())),
+                Expect(FileExplorerContent(
+                    "
+ - 📁  .git/ :
+ - 🙈  .gitignore
+ - 🔒  Cargo.lock
+ - 📄  Cargo.toml
+ - 📂  src/ [•] :
+   - 🦀  foo.rs
+   - 🦀  main.rs [M]
+"
+                    .trim_matches('\n')
+                    .to_string(),
+                )),
+            ])
+        })
+    }
+
     #[test]
     fn move_path() -> anyhow::Result<()> {
         execute_test(|s| {
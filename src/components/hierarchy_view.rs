@@ -0,0 +1,250 @@
+//! A pane beside the main panel, which browses the call hierarchy or the type hierarchy of a
+//! symbol as a tree, see `lsp::hierarchy`.
+//!
+//! `tab` expands or collapses the item under the cursor, whose children are requested from the
+//! LSP server once it is expanded for the first time, and `enter` goes to the item.
+
+use itertools::Itertools;
+use my_proc_macros::key;
+use shared::canonicalized_path::CanonicalizedPath;
+
+use crate::{
+    app::{Dispatch, Dispatches},
+    context::Context,
+    lsp::hierarchy::{HierarchyItem, HierarchyKind},
+    position::Position,
+};
+
+use super::{
+    component::Component,
+    editor::{Editor, Mode},
+};
+
+pub(crate) struct HierarchyView {
+    /// Contains the visible items, one per line, so that they can be navigated with the usual
+    /// movements. It is restored whenever it is modified, because the pane is read-only.
+    editor: Editor,
+    kind: HierarchyKind,
+    roots: Vec<Node>,
+    /// The paths of the items are displayed relative to it
+    working_directory: CanonicalizedPath,
+}
+
+struct Node {
+    item: HierarchyItem,
+    open: bool,
+    /// `None` until they are received from the LSP server
+    children: Option<Vec<Node>>,
+}
+
+impl Node {
+    fn new(item: HierarchyItem, open: bool) -> Self {
+        Node {
+            item,
+            open,
+            children: None,
+        }
+    }
+}
+
+/// The nodes that are not under a collapsed node, in the order that they are rendered,
+/// paired with their depths.
+fn visible_nodes<'a>(nodes: &'a [Node], depth: usize, result: &mut Vec<(usize, &'a Node)>) {
+    for node in nodes {
+        result.push((depth, node));
+        if node.open {
+            visible_nodes(
+                node.children.as_deref().unwrap_or_default(),
+                depth + 1,
+                result,
+            )
+        }
+    }
+}
+
+/// `index` is the line of the node, which is decremented for every visible node skipped.
+fn visible_node_mut<'a>(nodes: &'a mut [Node], index: &mut usize) -> Option<&'a mut Node> {
+    for node in nodes {
+        if *index == 0 {
+            return Some(node);
+        }
+        *index -= 1;
+        if node.open {
+            if let Some(found) =
+                visible_node_mut(node.children.as_deref_mut().unwrap_or_default(), index)
+            {
+                return Some(found);
+            }
+        }
+    }
+    None
+}
+
+/// The same item might appear more than once, for example a function called by two callers,
+/// so the first one whose children are not received yet is taken.
+fn pending_node_mut<'a>(nodes: &'a mut [Node], item: &HierarchyItem) -> Option<&'a mut Node> {
+    for node in nodes {
+        if node.children.is_none() && node.item == *item {
+            return Some(node);
+        }
+        if let Some(found) =
+            pending_node_mut(node.children.as_deref_mut().unwrap_or_default(), item)
+        {
+            return Some(found);
+        }
+    }
+    None
+}
+
+impl HierarchyView {
+    /// The roots are expanded, so their children are expected to be requested right away.
+    pub(crate) fn new(
+        kind: HierarchyKind,
+        roots: Vec<HierarchyItem>,
+        working_directory: &CanonicalizedPath,
+    ) -> anyhow::Result<Self> {
+        let mut editor = Editor::from_text(None, "");
+        editor.set_title(format!(
+            "{} of {}",
+            kind.display(),
+            roots.iter().map(|root| &root.name).join(", ")
+        ));
+        let mut hierarchy_view = Self {
+            editor,
+            kind,
+            roots: roots
+                .into_iter()
+                .map(|root| Node::new(root, true))
+                .collect(),
+            working_directory: working_directory.clone(),
+        };
+        hierarchy_view.refresh_editor()?;
+        Ok(hierarchy_view)
+    }
+
+    pub(crate) fn kind(&self) -> HierarchyKind {
+        self.kind
+    }
+
+    /// Does nothing if no item is waiting for its children, e.g. the view is reopened.
+    pub(crate) fn set_children(
+        &mut self,
+        parent: &HierarchyItem,
+        children: Vec<HierarchyItem>,
+    ) -> anyhow::Result<()> {
+        let Some(node) = pending_node_mut(&mut self.roots, parent) else {
+            return Ok(());
+        };
+        node.children = Some(
+            children
+                .into_iter()
+                .map(|child| Node::new(child, false))
+                .collect(),
+        );
+        self.refresh_editor()
+    }
+
+    fn content(&self) -> String {
+        let mut nodes = Vec::new();
+        visible_nodes(&self.roots, 0, &mut nodes);
+        nodes
+            .into_iter()
+            .map(|(depth, node)| {
+                let marker = match &node.children {
+                    Some(children) if children.is_empty() => " ",
+                    _ if node.open => "▾",
+                    _ => "▸",
+                };
+                let location = &node.item.location;
+                format!(
+                    "{}{marker} {}  {}:{}",
+                    "  ".repeat(depth),
+                    node.item.name,
+                    location
+                        .path
+                        .display_relative_to(&self.working_directory)
+                        .unwrap_or_else(|_| location.path.display_absolute()),
+                    location.range.start.line + 1
+                )
+            })
+            .join("\n")
+    }
+
+    /// The cursor stays on the same line, so that expanding an item does not move it.
+    fn refresh_editor(&mut self) -> anyhow::Result<()> {
+        let line = self
+            .editor
+            .get_cursor_position()
+            .map(|position| position.line)
+            .unwrap_or_default();
+        self.editor.set_content(&self.content())?;
+        let position = Position::new(line, 0);
+        self.editor.set_position_range(position..position)?;
+        Ok(())
+    }
+
+    fn current_node(&self) -> anyhow::Result<Option<&Node>> {
+        let line = self.editor.get_cursor_position()?.line;
+        let mut nodes = Vec::new();
+        visible_nodes(&self.roots, 0, &mut nodes);
+        Ok(nodes.into_iter().nth(line).map(|(_, node)| node))
+    }
+
+    fn current_node_mut(&mut self) -> anyhow::Result<Option<&mut Node>> {
+        let mut line = self.editor.get_cursor_position()?.line;
+        Ok(visible_node_mut(&mut self.roots, &mut line))
+    }
+
+    fn toggle_current_node(&mut self) -> anyhow::Result<Dispatches> {
+        let kind = self.kind;
+        let Some(node) = self.current_node_mut()? else {
+            return Ok(Default::default());
+        };
+        node.open = !node.open;
+        let dispatches = if node.open && node.children.is_none() {
+            Dispatches::one(Dispatch::RequestHierarchyChildren {
+                kind,
+                item: node.item.clone(),
+            })
+        } else {
+            Default::default()
+        };
+        self.refresh_editor()?;
+        Ok(dispatches)
+    }
+}
+
+impl Component for HierarchyView {
+    fn editor(&self) -> &Editor {
+        &self.editor
+    }
+
+    fn editor_mut(&mut self) -> &mut Editor {
+        &mut self.editor
+    }
+
+    fn handle_key_event(
+        &mut self,
+        context: &Context,
+        event: event::KeyEvent,
+    ) -> anyhow::Result<Dispatches> {
+        match event {
+            key!("esc") if self.editor.mode == Mode::Normal => {
+                Ok(Dispatches::one(Dispatch::CloseCurrentWindow))
+            }
+            key!("enter") => Ok(self
+                .current_node()?
+                .map(|node| Dispatches::one(Dispatch::GotoLocation(node.item.location.clone())))
+                .unwrap_or_default()),
+            key!("tab") => self.toggle_current_node(),
+            _ => {
+                let dispatches = self.editor.handle_key_event(context, event)?;
+                if self.editor.content() != self.content() {
+                    self.editor.enter_normal_mode()?;
+                    self.refresh_editor()?;
+                }
+                Ok(dispatches)
+            }
+        }
+    }
+}
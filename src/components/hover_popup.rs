@@ -0,0 +1,263 @@
+//! The documentation of an LSP hover is shown in a popup that floats over the windows near the
+//! cursor, which is scrolled by the keys that scroll editors (`ctrl+d` and `ctrl+u`).
+//!
+//! The popup is dismissed by `esc`, or once the cursor moves away from where the hover is
+//! requested.
+
+use itertools::Itertools;
+
+use crate::{
+    app::Dimension,
+    components::{component::ComponentId, editor::Direction},
+    grid::{get_string_width, CellUpdate, Grid, RenderContentLineNumber},
+    markdown::StyledText,
+    position::Position,
+    rectangle::Rectangle,
+    selection::CharIndex,
+    soft_wrap::soft_wrap,
+    themes::Theme,
+};
+
+const MAX_WIDTH: usize = 80;
+const MAX_HEIGHT: usize = 15;
+
+pub(crate) struct HoverPopup {
+    text: StyledText,
+    /// The number of wrapped lines that are scrolled past
+    scroll_offset: usize,
+    /// The component and the cursor where the hover is requested
+    anchor: (ComponentId, CharIndex),
+    rectangle: Rectangle,
+}
+
+impl HoverPopup {
+    pub(crate) fn new(text: StyledText, anchor: (ComponentId, CharIndex)) -> HoverPopup {
+        HoverPopup {
+            text,
+            scroll_offset: 0,
+            anchor,
+            rectangle: Rectangle::default(),
+        }
+    }
+
+    pub(crate) fn anchor(&self) -> (ComponentId, CharIndex) {
+        self.anchor
+    }
+
+    pub(crate) fn rectangle(&self) -> &Rectangle {
+        &self.rectangle
+    }
+
+    /// Places the popup below the `cursor` (which is relative to the terminal),
+    /// or above the cursor if there is more space above when the popup does not fit below.
+    pub(crate) fn set_rectangle(&mut self, cursor: Position, terminal_dimension: Dimension) {
+        // One column is reserved, because lines are wrapped before the last column
+        let width = (self
+            .text
+            .content
+            .lines()
+            .map(get_string_width)
+            .max()
+            .unwrap_or_default()
+            + 1)
+        .min(MAX_WIDTH)
+        .min(terminal_dimension.width as usize);
+        let height = self.wrapped_lines_count(width).clamp(1, MAX_HEIGHT);
+        let above = cursor.line;
+        let below = (terminal_dimension.height as usize).saturating_sub(cursor.line + 1);
+        let (line, height) = if height <= below || below >= above {
+            (cursor.line + 1, height.min(below))
+        } else {
+            let height = height.min(above);
+            (cursor.line - height, height)
+        };
+        self.rectangle = Rectangle {
+            origin: Position::new(
+                line,
+                cursor
+                    .column
+                    .min((terminal_dimension.width as usize).saturating_sub(width)),
+            ),
+            width: width as u16,
+            height: height as u16,
+        };
+        self.scroll_offset = self.scroll_offset.min(self.max_scroll_offset());
+    }
+
+    fn wrapped_lines_count(&self, width: usize) -> usize {
+        soft_wrap(&self.text.content, width).wrapped_lines_count()
+    }
+
+    fn max_scroll_offset(&self) -> usize {
+        self.wrapped_lines_count(self.rectangle.width as usize)
+            .saturating_sub(self.rectangle.height as usize)
+    }
+
+    /// Scrolls by half of the height of the popup.
+    pub(crate) fn scroll(&mut self, direction: Direction) {
+        let step = (self.rectangle.height as usize / 2).max(1);
+        self.scroll_offset = match direction {
+            Direction::Start => self.scroll_offset.saturating_sub(step),
+            Direction::End => (self.scroll_offset + step).min(self.max_scroll_offset()),
+        }
+    }
+
+    pub(crate) fn get_grid(&self, theme: &Theme) -> Grid {
+        let Rectangle { width, height, .. } = self.rectangle;
+        let content = &self.text.content;
+        // The character positions of the content, sorted by byte
+        let mut positions = Vec::new();
+        let mut position = Position::default();
+        for (byte, character) in content.char_indices() {
+            positions.push((byte, position));
+            position = if character == '\n' {
+                Position::new(position.line + 1, 0)
+            } else {
+                Position::new(position.line, position.column + 1)
+            }
+        }
+        let cell_updates = self
+            .text
+            .spans
+            .iter()
+            .flat_map(|span| {
+                let start = positions.partition_point(|(byte, _)| *byte < span.byte_range.start);
+                let end = positions.partition_point(|(byte, _)| *byte < span.byte_range.end);
+                let style = theme.get_style(&span.style_key);
+                positions[start..end]
+                    .iter()
+                    .map(move |(_, position)| CellUpdate {
+                        style,
+                        source: Some(span.style_key.clone()),
+                        ..CellUpdate::new(*position)
+                    })
+            })
+            .collect_vec();
+        let mut grid = Grid::new(Dimension {
+            width,
+            height: self.wrapped_lines_count(width as usize) as u16,
+        })
+        .render_content(
+            content,
+            RenderContentLineNumber::NoLineNumber,
+            cell_updates,
+            Vec::new(),
+            theme,
+        )
+        .clamp_top(self.scroll_offset);
+        grid.rows.truncate(height as usize);
+        grid.rows
+            .iter_mut()
+            .flatten()
+            .for_each(|cell| cell.background_color = theme.ui.parent_lines_background);
+        grid
+    }
+
+    #[cfg(test)]
+    pub(crate) fn content(&self) -> &str {
+        &self.text.content
+    }
+}
+
+#[cfg(test)]
+mod test_hover_popup {
+    use crate::{grid::StyleKey, markdown};
+
+    use super::*;
+
+    fn popup(markdown: &str) -> HoverPopup {
+        HoverPopup::new(
+            markdown::render(markdown, |_, _| None),
+            (ComponentId::new(), CharIndex(0)),
+        )
+    }
+
+    fn rows(grid: &Grid) -> Vec<String> {
+        grid.rows
+            .iter()
+            .map(|row| row.iter().map(|cell| cell.symbol.as_str()).join(""))
+            .map(|row| row.trim_end().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn placed_below_the_cursor_unless_there_is_more_space_above() {
+        let terminal = Dimension {
+            width: 20,
+            height: 10,
+        };
+        let mut popup = popup("first\nsecond\nthird");
+
+        popup.set_rectangle(Position::new(2, 3), terminal);
+        assert_eq!(
+            popup.rectangle(),
+            &Rectangle {
+                origin: Position::new(3, 3),
+                width: 7,
+                height: 3,
+            }
+        );
+
+        // Not enough space below, and the column is clamped to fit the width
+        popup.set_rectangle(Position::new(8, 18), terminal);
+        assert_eq!(
+            popup.rectangle(),
+            &Rectangle {
+                origin: Position::new(5, 13),
+                width: 7,
+                height: 3,
+            }
+        );
+    }
+
+    #[test]
+    fn scroll_long_content() {
+        let markdown = (0..30).map(|line| format!("line {line}")).join("\n");
+        let mut popup = popup(&markdown);
+        popup.set_rectangle(
+            Position::new(0, 0),
+            Dimension {
+                width: 80,
+                height: 40,
+            },
+        );
+        let theme = Theme::default();
+        let grid = popup.get_grid(&theme);
+        assert_eq!(grid.rows.len(), MAX_HEIGHT);
+        assert_eq!(rows(&grid)[0], "line 0");
+        assert_eq!(
+            grid.rows[0][0].background_color,
+            theme.ui.parent_lines_background
+        );
+
+        popup.scroll(Direction::End);
+        assert_eq!(rows(&popup.get_grid(&theme))[0], "line 7");
+
+        // Scrolling stops at the last line
+        popup.scroll(Direction::End);
+        popup.scroll(Direction::End);
+        assert_eq!(rows(&popup.get_grid(&theme))[0], "line 15");
+        assert_eq!(rows(&popup.get_grid(&theme))[MAX_HEIGHT - 1], "line 29");
+
+        popup.scroll(Direction::Start);
+        assert_eq!(rows(&popup.get_grid(&theme))[0], "line 8");
+    }
+
+    #[test]
+    fn spans_are_styled() {
+        let mut popup = popup("foo `bar`");
+        popup.set_rectangle(
+            Position::new(0, 0),
+            Dimension {
+                width: 80,
+                height: 40,
+            },
+        );
+        let grid = popup.get_grid(&Theme::default());
+        assert_eq!(rows(&grid), ["foo bar"]);
+        let source = |column: usize| grid.rows[0][column].source.clone();
+        assert_eq!(source(0), None);
+        assert_eq!(source(4), Some(StyleKey::Syntax("markup.raw".to_string())));
+        assert_eq!(source(6), Some(StyleKey::Syntax("markup.raw".to_string())));
+    }
+}
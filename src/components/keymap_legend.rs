@@ -271,6 +271,14 @@ impl Keymap {
     pub(crate) fn event(&self) -> &KeyEvent {
         &self.event
     }
+
+    pub(crate) fn dispatch(&self) -> &Dispatch {
+        &self.dispatch
+    }
+
+    pub(crate) fn description(&self) -> &str {
+        &self.description
+    }
 }
 
 impl KeymapLegend {
@@ -0,0 +1,116 @@
+//! A read-only pane beside a markdown file, which shows the file rendered by
+//! `markdown::render`, and which is refreshed once the file has not been edited for
+//! `MARKDOWN_PREVIEW_DEBOUNCE`.
+
+use std::time::Duration;
+
+use my_proc_macros::key;
+use shared::canonicalized_path::CanonicalizedPath;
+
+use crate::{
+    app::{Dispatch, Dispatches},
+    context::Context,
+    markdown::StyledText,
+    position::Position,
+    syntax_highlight::HighlighedSpans,
+};
+
+use super::{
+    component::Component,
+    editor::{Editor, Mode},
+};
+
+pub(crate) const MARKDOWN_PREVIEW_DEBOUNCE: Duration = Duration::from_millis(300);
+
+pub(crate) fn is_markdown(path: &CanonicalizedPath) -> bool {
+    path.to_path_buf()
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| ["md", "mdx", "markdown"].contains(&extension))
+}
+
+pub(crate) struct MarkdownPreview {
+    /// Contains the rendered content, so that the preview can be navigated with the usual
+    /// movements. It is restored whenever it is modified, because the preview is read-only.
+    editor: Editor,
+    /// The markdown file that is previewed
+    source: CanonicalizedPath,
+    text: StyledText,
+}
+
+impl MarkdownPreview {
+    pub(crate) fn new(
+        source: CanonicalizedPath,
+        title: String,
+        text: StyledText,
+    ) -> anyhow::Result<Self> {
+        let mut editor = Editor::from_text(None, "");
+        editor.set_title(title);
+        let mut markdown_preview = Self {
+            editor,
+            source,
+            text,
+        };
+        markdown_preview.refresh_editor()?;
+        Ok(markdown_preview)
+    }
+
+    pub(crate) fn source(&self) -> &CanonicalizedPath {
+        &self.source
+    }
+
+    /// The cursor and the scroll offset are kept, so that the preview does not jump
+    /// while the file is edited.
+    pub(crate) fn update(&mut self, text: StyledText) -> anyhow::Result<()> {
+        self.text = text;
+        self.refresh_editor()
+    }
+
+    fn refresh_editor(&mut self) -> anyhow::Result<()> {
+        let line = self
+            .editor
+            .get_cursor_position()
+            .map(|position| position.line)
+            .unwrap_or_default();
+        let scroll_offset = self.editor.scroll_offset();
+        self.editor.set_content(&self.text.content)?;
+        self.editor
+            .buffer_mut()
+            .update_highlighted_spans(HighlighedSpans(self.text.flattened_spans()));
+        let line = line.min(self.text.content.lines().count().saturating_sub(1));
+        let position = Position::new(line, 0);
+        self.editor.set_position_range(position..position)?;
+        self.editor.set_scroll_offset(scroll_offset);
+        Ok(())
+    }
+}
+
+impl Component for MarkdownPreview {
+    fn editor(&self) -> &Editor {
+        &self.editor
+    }
+
+    fn editor_mut(&mut self) -> &mut Editor {
+        &mut self.editor
+    }
+
+    fn handle_key_event(
+        &mut self,
+        context: &Context,
+        event: event::KeyEvent,
+    ) -> anyhow::Result<Dispatches> {
+        match event {
+            key!("esc") if self.editor.mode == Mode::Normal => {
+                Ok(Dispatches::one(Dispatch::CloseCurrentWindow))
+            }
+            _ => {
+                let dispatches = self.editor.handle_key_event(context, event)?;
+                if self.editor.content() != self.text.content {
+                    self.editor.enter_normal_mode()?;
+                    self.refresh_editor()?;
+                }
+                Ok(dispatches)
+            }
+        }
+    }
+}
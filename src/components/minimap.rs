@@ -0,0 +1,198 @@
+//! The minimap is a condensed overview of the buffer that is rendered beside the content of a
+//! file window, where each row is a line and each column is `CHARS_PER_COLUMN` characters.
+//!
+//! A column is drawn as a block whose height tells how many of its characters are not
+//! whitespace, colored by the syntax highlight of the first of them. The rows of the visible
+//! lines are marked as the viewport, and clicking a row jumps to its line.
+
+use std::ops::Range;
+
+use crate::{
+    app::Dimension,
+    buffer::Buffer,
+    grid::{CellUpdate, Grid},
+    position::Position,
+    style::Style,
+    themes::Theme,
+};
+
+pub(crate) const MINIMAP_WIDTH: u16 = 10;
+
+/// The minimap is hidden in windows narrower than this, so that the content is not squeezed
+pub(crate) const MINIMAP_MIN_WINDOW_WIDTH: u16 = 60;
+
+const CHARS_PER_COLUMN: usize = 4;
+
+/// Indexed by the number of characters of a column that are not whitespace, minus one
+const BLOCKS: [&str; CHARS_PER_COLUMN] = ["▂", "▄", "▆", "█"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Minimap {
+    /// The line of the first row, which follows the viewport
+    /// once the buffer has more lines than the minimap has rows
+    first_line: usize,
+    height: usize,
+    len_lines: usize,
+}
+
+impl Minimap {
+    pub(crate) fn new(height: usize, len_lines: usize, visible_line_range: &Range<usize>) -> Self {
+        // The first row moves from the first line to the last page of lines
+        // as the viewport moves from the top to the bottom of the buffer
+        let scrollable_rows = len_lines.saturating_sub(height);
+        let scrollable_lines = len_lines.saturating_sub(visible_line_range.len()).max(1);
+        Self {
+            first_line: (visible_line_range.start * scrollable_rows / scrollable_lines)
+                .min(scrollable_rows),
+            height,
+            len_lines,
+        }
+    }
+
+    /// Returns `None` if the row is below the last line.
+    pub(crate) fn line_at_row(&self, row: usize) -> Option<usize> {
+        let line = self.first_line + row;
+        (row < self.height && line < self.len_lines).then_some(line)
+    }
+
+    pub(crate) fn render(
+        &self,
+        buffer: &Buffer,
+        visible_line_range: &Range<usize>,
+        theme: &Theme,
+    ) -> Grid {
+        let spans = buffer.highlighted_spans();
+        let color_at = |byte: usize| {
+            let index = spans.partition_point(|span| span.byte_range.end <= byte);
+            spans
+                .get(index)
+                .filter(|span| span.byte_range.start <= byte)
+                .and_then(|span| theme.get_style(&span.style_key).foreground_color)
+                .unwrap_or(theme.ui.text_foreground)
+        };
+        let cell_updates = (0..self.height)
+            .filter_map(|row| {
+                let line = self.line_at_row(row)?;
+                let byte_start = buffer.line_to_byte(line).ok()?;
+                let content = buffer.get_line_by_line_index(line)?.to_string();
+                let chars = content.char_indices().collect::<Vec<_>>();
+                let viewport = visible_line_range
+                    .contains(&line)
+                    .then_some(theme.ui.possible_selection_background);
+                Some((0..MINIMAP_WIDTH as usize).map(move |column| {
+                    let chunk = chars
+                        .iter()
+                        .skip(column * CHARS_PER_COLUMN)
+                        .take(CHARS_PER_COLUMN)
+                        .filter(|(_, c)| !c.is_whitespace())
+                        .collect::<Vec<_>>();
+                    let (symbol, foreground) = match chunk.first() {
+                        Some((byte, _)) => {
+                            (BLOCKS[chunk.len() - 1], Some(color_at(byte_start + byte)))
+                        }
+                        None => (" ", None),
+                    };
+                    CellUpdate {
+                        symbol: Some(symbol.to_string()),
+                        style: Style {
+                            foreground_color: foreground,
+                            background_color: viewport,
+                            ..Style::new()
+                        },
+                        ..CellUpdate::new(Position::new(row, column))
+                    }
+                }))
+            })
+            .flatten()
+            .collect();
+        Grid::new(Dimension {
+            height: self.height as u16,
+            width: MINIMAP_WIDTH,
+        })
+        .apply_cell_updates(cell_updates)
+    }
+}
+
+#[cfg(test)]
+mod test_minimap {
+    use super::*;
+
+    #[test]
+    fn first_row_follows_the_viewport() {
+        // The whole buffer fits
+        let minimap = Minimap::new(10, 5, &(0..5));
+        assert_eq!(minimap.line_at_row(0), Some(0));
+        assert_eq!(minimap.line_at_row(4), Some(4));
+        assert_eq!(minimap.line_at_row(5), None);
+
+        // 100 lines on 20 rows, where 10 lines are visible
+        assert_eq!(Minimap::new(20, 100, &(0..10)).line_at_row(0), Some(0));
+        assert_eq!(Minimap::new(20, 100, &(45..55)).line_at_row(0), Some(40));
+        assert_eq!(Minimap::new(20, 100, &(90..100)).line_at_row(0), Some(80));
+        assert_eq!(Minimap::new(20, 100, &(90..100)).line_at_row(19), Some(99));
+    }
+
+    #[test]
+    fn columns_are_drawn_by_the_number_of_non_whitespace_characters() {
+        let buffer = Buffer::new(None, "fn main() {\n    x\n");
+        let grid = Minimap::new(3, buffer.len_lines(), &(0..1)).render(
+            &buffer,
+            &(0..1),
+            &Theme::default(),
+        );
+        let row = |row: usize| {
+            grid.rows[row]
+                .iter()
+                .map(|cell| cell.symbol.as_str())
+                .collect::<String>()
+        };
+        // "fn m", "ain(", ") {"
+        assert_eq!(row(0), "▆█▄       ");
+        // "    ", "x"
+        assert_eq!(row(1), " ▂        ");
+        let theme = Theme::default();
+        assert_eq!(
+            grid.rows[0][0].background_color,
+            theme.ui.possible_selection_background
+        );
+        assert_ne!(
+            grid.rows[1][0].background_color,
+            theme.ui.possible_selection_background
+        );
+    }
+
+    #[test]
+    fn clicking_a_row_jumps_to_its_line() -> anyhow::Result<()> {
+        use crossterm::event::{KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+        use itertools::Itertools;
+
+        use crate::{
+            components::{component::Component, editor::Editor},
+            rectangle::Rectangle,
+        };
+
+        let text = (0..100).map(|line| format!("line {line}")).join("\n");
+        let mut editor = Editor::from_text(None, &text);
+        editor.set_rectangle(Rectangle {
+            origin: Position::new(0, 0),
+            width: 80,
+            height: 12,
+        });
+        editor.set_minimap(true);
+        let click = |column: u16, row: u16| MouseEvent {
+            kind: MouseEventKind::Down(MouseButton::Left),
+            column,
+            row,
+            modifiers: KeyModifiers::NONE,
+        };
+
+        // The content is clicked instead of the minimap
+        editor.handle_mouse_event(click(10, 6))?;
+        assert_eq!(editor.get_cursor_position()?.line, 0);
+
+        // The first row is below the window title
+        editor.handle_mouse_event(click(75, 6))?;
+        assert_eq!(editor.get_cursor_position()?.line, 5);
+        Ok(())
+    }
+}
@@ -1,12 +1,22 @@
 pub(crate) mod component;
+pub(crate) mod debug_panel;
+pub(crate) mod diff_view;
 pub(crate) mod dropdown;
 pub(crate) mod prompt;
 
 pub(crate) mod editor;
 mod editor_keymap_legend;
 pub(crate) mod file_explorer;
+pub(crate) mod hierarchy_view;
+pub(crate) mod hover_popup;
 pub(crate) mod keymap_legend;
+pub(crate) mod markdown_preview;
+pub(crate) mod minimap;
+pub(crate) mod passphrase_prompt;
 pub(crate) mod render_editor;
+pub(crate) mod smooth_scroll;
 pub(crate) mod suggestive_editor;
+pub(crate) mod task_output;
+pub(crate) mod terminal;
 #[cfg(test)]
 mod test_editor;
@@ -0,0 +1,107 @@
+use crossterm::event::KeyCode;
+use event::KeyModifiers;
+use my_proc_macros::key;
+use shared::canonicalized_path::CanonicalizedPath;
+
+use crate::{
+    app::{Dispatch, Dispatches},
+    encryption::Passphrase,
+    position::Position,
+};
+
+use super::{component::Component, editor::Editor};
+
+/// A prompt for the passphrase of an encrypted file.
+///
+/// Unlike `Prompt`, the passphrase is neither stored in the prompt history
+/// nor in the buffer of the editor, which only shows a mask of the passphrase.
+pub(crate) struct PassphrasePrompt {
+    editor: Editor,
+    path: CanonicalizedPath,
+    passphrase: String,
+}
+
+impl PassphrasePrompt {
+    pub(crate) fn new(path: CanonicalizedPath) -> Self {
+        let mut editor = Editor::from_text(None, "");
+        editor.set_title(format!("Passphrase of {}", path.display_absolute()));
+        Self {
+            editor,
+            path,
+            passphrase: String::new(),
+        }
+    }
+
+    fn refresh_mask(&mut self) -> anyhow::Result<()> {
+        let len = self.passphrase.chars().count();
+        self.editor.set_content(&"*".repeat(len))?;
+        let end = Position::new(0, len);
+        self.editor.set_position_range(end..end)?;
+        Ok(())
+    }
+}
+
+impl Component for PassphrasePrompt {
+    fn editor(&self) -> &Editor {
+        &self.editor
+    }
+
+    fn editor_mut(&mut self) -> &mut Editor {
+        &mut self.editor
+    }
+
+    fn handle_paste_event(&mut self, content: String) -> anyhow::Result<Dispatches> {
+        self.passphrase
+            .push_str(content.trim_end_matches(['\r', '\n']));
+        self.refresh_mask()?;
+        Ok(Default::default())
+    }
+
+    /// The text being composed is not shown, because it is part of the passphrase.
+    fn handle_ime_preedit(&mut self, _preedit: String) -> anyhow::Result<Dispatches> {
+        Ok(Default::default())
+    }
+
+    fn handle_ime_commit(
+        &mut self,
+        _: &crate::context::Context,
+        text: String,
+    ) -> anyhow::Result<Dispatches> {
+        self.passphrase.push_str(&text);
+        self.refresh_mask()?;
+        Ok(Default::default())
+    }
+
+    fn handle_key_event(
+        &mut self,
+        _: &crate::context::Context,
+        event: event::KeyEvent,
+    ) -> anyhow::Result<Dispatches> {
+        match event {
+            key!("enter") => {
+                return Ok(Dispatches::new(
+                    [
+                        Dispatch::CloseCurrentWindow,
+                        Dispatch::OpenEncryptedFile {
+                            path: self.path.clone(),
+                            passphrase: Passphrase::new(std::mem::take(&mut self.passphrase)),
+                        },
+                    ]
+                    .to_vec(),
+                ))
+            }
+            key!("esc") => return Ok(Dispatches::one(Dispatch::CloseCurrentWindow)),
+            key!("backspace") => {
+                self.passphrase.pop();
+            }
+            key!("ctrl+u") => self.passphrase.clear(),
+            event::KeyEvent {
+                code: KeyCode::Char(c),
+                modifiers: KeyModifiers::None | KeyModifiers::Shift,
+            } => self.passphrase.push(c),
+            _ => return Ok(Default::default()),
+        }
+        self.refresh_mask()?;
+        Ok(Default::default())
+    }
+}
@@ -3,7 +3,7 @@ use std::{cell::RefCell, rc::Rc};
 use my_proc_macros::key;
 
 use crate::{
-    app::{Dispatch, DispatchPrompt, Dispatches, GlobalSearchFilterGlob, Scope},
+    app::{Dispatch, DispatchPrompt, Dispatches, GlobalSearchFilterGlob, Scope, YesNoPrompt},
     buffer::Buffer,
     components::editor::DispatchEditor,
     context::Context,
@@ -25,6 +25,9 @@ pub(crate) struct Prompt {
     enter_selects_first_matching_item: bool,
     prompt_history_key: PromptHistoryKey,
     fire_dispatches_on_change: Option<Dispatches>,
+    /// When true, `enter` inserts a newline instead of submitting,
+    /// and the whole content is submitted with `alt+enter`.
+    multi_line: bool,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -42,12 +45,14 @@ pub(crate) struct PromptConfig {
 #[derive(Hash, PartialEq, Eq, Debug, Clone, Copy)]
 pub(crate) enum PromptHistoryKey {
     MoveToIndex,
+    GoToLine,
     Search(Scope),
     Rename,
     AddPath,
     MovePath,
     Symbol,
     Command,
+    CommandPalette,
     OpenFile,
     FilterGlob(GlobalSearchFilterGlob),
     Replacement(Scope),
@@ -55,7 +60,29 @@ pub(crate) enum PromptHistoryKey {
     #[cfg(test)]
     Null,
     Theme,
+    Language,
+    Indentation,
+    DiagnosticSeverity,
+    UnmuteDiagnostics,
+    FileChangedOnDisk,
+    Recovery,
+    WorkspaceEditReview,
+    Macro,
+    NamedMark,
     PipeToShell,
+    WriteSelections,
+    InsertBelowCursor,
+    Build,
+    ShellTask,
+    DebugConfig,
+    DebugWatch,
+    Plugin,
+    SurroundWithTag,
+    Align,
+    Increment,
+    GitRef,
+    SpellingSuggestions,
+    DiffBuffer,
     FilterSelectionsMatchingSearch {
         maintain: bool,
     },
@@ -117,12 +144,40 @@ impl Prompt {
                 enter_selects_first_matching_item: config.enter_selects_first_matching_item,
                 prompt_history_key,
                 fire_dispatches_on_change: config.fire_dispatches_on_change,
+                multi_line: false,
             },
             dispatches,
         )
     }
 }
 
+impl Prompt {
+    /// Expand the prompt into a multi-line editor that contains only the current line,
+    /// where the history is dropped so that it will not be submitted along.
+    fn expand_to_multi_line(&mut self, context: &Context) -> anyhow::Result<Dispatches> {
+        let current_line = self.editor().current_line()?;
+        let title = self.editor().title(context);
+        self.multi_line = true;
+        self.editor_mut()
+            .set_title(format!("{} (multi-line, alt+enter to submit)", title));
+        self.editor_mut()
+            .set_content(&format!("{}\n", current_line))?;
+        Ok(Dispatches::one(Dispatch::ToEditor(
+            DispatchEditor::MoveToLastChar,
+        )))
+    }
+
+    /// Replace the dropdown items, for example once the files of the file picker are walked.
+    /// The filter typed so far is kept.
+    pub(crate) fn set_items(&mut self, items: Vec<DropdownItem>) -> Dispatches {
+        self.editor.set_completion(Completion {
+            items,
+            trigger_characters: vec![" ".to_string()],
+        });
+        self.editor.render_completion_dropdown(true)
+    }
+}
+
 impl Component for Prompt {
     fn editor(&self) -> &Editor {
         self.editor.editor()
@@ -137,6 +192,21 @@ impl Component for Prompt {
     ) -> anyhow::Result<Dispatches> {
         self.editor.handle_dispatch_editor(context, dispatch)
     }
+    fn handle_paste_event(&mut self, content: String) -> anyhow::Result<Dispatches> {
+        // Pasting a huge content into the prompt is usually an accident,
+        // so we ask the user whether the content should be opened in a scratch buffer instead,
+        // unless the prompt is expanded into a multi-line editor for such content
+        if !self.multi_line && crate::file_drop::is_huge_paste(&content) {
+            return Ok(Dispatches::one(Dispatch::OpenYesNoPrompt(YesNoPrompt {
+                title: format!(
+                    "The pasted content is huge ({} characters), open it in a scratch buffer instead?",
+                    content.chars().count()
+                ),
+                yes: Box::new(Dispatch::OpenScratchBuffer(content)),
+            })));
+        }
+        self.editor_mut().handle_paste_event(content)
+    }
     fn handle_key_event(
         &mut self,
         context: &Context,
@@ -158,7 +228,15 @@ impl Component for Prompt {
                     self.editor_mut().handle_key_event(context, event)
                 }
             }
-            key!("enter") => {
+            key!("alt+enter") if self.multi_line => {
+                let content = self.editor().content();
+                let dispatches = self.on_enter.to_dispatches(&content)?;
+                // Multi-line input is not stored in the prompt history,
+                // because each history entry occupies exactly one line
+                Ok(Dispatches::one(Dispatch::CloseCurrentWindow).chain(dispatches))
+            }
+            key!("alt+enter") => self.expand_to_multi_line(context),
+            key!("enter") if !self.multi_line => {
                 let (line, dispatches) = if self.enter_selects_first_matching_item
                     && self.editor.completion_dropdown_current_item().is_some()
                 {
@@ -180,7 +258,25 @@ impl Component for Prompt {
                     }))
             }
             _ => {
+                if let Some(dispatches) = self
+                    .editor
+                    .completion_dropdown_current_item()
+                    .and_then(|item| item.action(&event))
+                {
+                    return Ok(dispatches);
+                }
+                let line_before = self.editor().current_line()?;
                 let dispatches = self.editor.handle_key_event(context, event)?;
+                let line_after = self.editor().current_line()?;
+                let dispatches = if line_before != line_after {
+                    dispatches.chain(
+                        self.on_enter
+                            .to_dispatches_on_change(&line_after)
+                            .unwrap_or_default(),
+                    )
+                } else {
+                    dispatches
+                };
                 Ok(if self.fire_dispatches_on_change.is_some() {
                     dispatches.chain(
                         self.editor
@@ -282,6 +378,70 @@ mod test_prompt {
         .unwrap();
     }
 
+    #[test]
+    fn multi_line_input() {
+        execute_test(|s| {
+            Box::new([
+                App(OpenFile(s.main_rs())),
+                Editor(SetContent("".to_string())),
+                App(OpenPrompt {
+                    key: PromptHistoryKey::Null,
+                    current_line: Some("previous".to_string()),
+                    config: PromptConfig {
+                        on_enter: DispatchPrompt::SetContent,
+                        items: Default::default(),
+                        title: "Content".to_string(),
+                        enter_selects_first_matching_item: false,
+                        leaves_current_line_empty: true,
+                        fire_dispatches_on_change: None,
+                    },
+                }),
+                App(HandleKeyEvents(keys!("h e l l o alt+enter").to_vec())),
+                Expect(CurrentComponentContent("hello\n")),
+                Expect(CurrentComponentTitle(
+                    "Content (multi-line, alt+enter to submit)",
+                )),
+                // Enter inserts newline instead of submitting
+                App(HandleKeyEvents(keys!("w o r l d enter b y e").to_vec())),
+                Expect(CurrentComponentContent("hello\nworld\nbye")),
+                App(HandleKeyEvent(key!("alt+enter"))),
+                Expect(CurrentPath(s.main_rs())),
+                Expect(CurrentComponentContent("hello\nworld\nbye")),
+            ])
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn multi_line_input_takes_huge_paste() {
+        execute_test(|s| {
+            let content = "hello\n".repeat(crate::file_drop::HUGE_PASTE_THRESHOLD);
+            Box::new([
+                App(OpenFile(s.main_rs())),
+                Editor(SetContent("".to_string())),
+                App(OpenPrompt {
+                    key: PromptHistoryKey::Null,
+                    current_line: None,
+                    config: PromptConfig {
+                        on_enter: DispatchPrompt::SetContent,
+                        items: Default::default(),
+                        title: "Content".to_string(),
+                        enter_selects_first_matching_item: false,
+                        leaves_current_line_empty: true,
+                        fire_dispatches_on_change: None,
+                    },
+                }),
+                App(HandleKeyEvent(key!("alt+enter"))),
+                App(HandlePasteEvent(content.clone())),
+                // The content is pasted instead of being offered to be opened in a scratch buffer
+                Expect(CurrentComponentTitle(
+                    "Content (multi-line, alt+enter to submit)",
+                )),
+            ])
+        })
+        .unwrap();
+    }
+
     #[test]
     fn current_line() {
         execute_test(|s| {
@@ -1,10 +1,11 @@
-use std::ops::Range;
+use std::{collections::HashMap, ops::Range};
 
 use itertools::Itertools;
 use lsp_types::DiagnosticSeverity;
 
 use crate::{
     app::Dimension,
+    bidi::VisualOrder,
     buffer::Buffer,
     char_index_range::CharIndexRange,
     components::{
@@ -12,11 +13,18 @@ use crate::{
         editor::Mode,
     },
     context::Context,
-    grid::{CellUpdate, Grid, LineUpdate, RenderContentLineNumber, StyleKey},
-    selection::{CharIndex, Selection},
+    fold,
+    git::gutter::GutterHunkKind,
+    grid::{
+        get_char_width, get_string_width, CellUpdate, Grid, GutterMarker, LineUpdate,
+        RenderContentLineNumber, StyleKey,
+    },
+    indent_guide,
+    position::Position,
+    selection::{CharIndex, Selection, SelectionMode},
     selection_mode::{self, ByteRange},
     style::Style,
-    themes::Theme,
+    themes::{Color, Theme},
 };
 
 use super::{component::GetGridResult, editor::Editor};
@@ -29,19 +37,44 @@ impl Editor {
         let Dimension { height, width } = editor.render_area();
         let buffer = editor.buffer();
         let rope = buffer.rope();
-        let content = rope.to_string();
-
-        let diagnostics = buffer.diagnostics();
+        // Only the visible lines of a large file are scanned by the regex highlights and
+        // decorated with diagnostics, so that rendering does not get slower with the file size
+        let (content, content_byte_offset, diagnostics) = if buffer.is_large_file() {
+            let visible_line_range = self.visible_line_range();
+            let byte_range = buffer
+                .line_range_to_byte_range(&visible_line_range)
+                .unwrap_or_default();
+            let char_range = buffer
+                .byte_range_to_char_index_range(&byte_range)
+                .unwrap_or_default();
+            let diagnostics = buffer
+                .diagnostics()
+                .into_iter()
+                .filter(|diagnostic| {
+                    diagnostic.range.start <= char_range.end
+                        && char_range.start <= diagnostic.range.end
+                })
+                .collect_vec();
+            (
+                rope.byte_slice(byte_range.clone()).to_string(),
+                byte_range.start,
+                diagnostics,
+            )
+        } else {
+            (rope.to_string(), 0, buffer.diagnostics())
+        };
 
         let len_lines = rope.len_lines().max(1) as u16;
         let (hidden_parent_lines, visible_parent_lines) =
             self.get_parent_lines().unwrap_or_default();
         let top_offset = hidden_parent_lines.len() as u16;
         let scroll_offset = self.scroll_offset();
+        let hidden_line_ranges = buffer.hidden_line_ranges();
         let visible_lines = rope
             .lines()
             .enumerate()
             .skip(scroll_offset as usize)
+            .filter(|(line_index, _)| !fold::is_hidden(&hidden_line_ranges, *line_index))
             .take(height as usize)
             .map(|(line_index, slice)| (line_index, slice.to_string()));
 
@@ -59,13 +92,30 @@ impl Editor {
             .unwrap_or_default()
             .into_iter()
             .map(|range| HighlightSpan {
+                layer: Layer::PossibleSelection,
                 set_symbol: None,
                 is_cursor: false,
                 range: HighlightSpanRange::ByteRange(range.range().clone()),
                 source: Source::StyleKey(UiPossibleSelection),
             });
 
+        let document_highlights = self.document_highlights().iter().map(|(range, is_write)| {
+            let style_key = if *is_write {
+                UiDocumentHighlightWrite
+            } else {
+                UiDocumentHighlightRead
+            };
+            HighlightSpan {
+                layer: Layer::DocumentHighlight,
+                set_symbol: None,
+                is_cursor: false,
+                range: HighlightSpanRange::CharIndexRange(*range),
+                source: Source::StyleKey(style_key),
+            }
+        });
+
         let marks = buffer.marks().into_iter().map(|mark| HighlightSpan {
+            layer: Layer::Mark,
             set_symbol: None,
             is_cursor: false,
             source: Source::StyleKey(UiMark),
@@ -73,6 +123,7 @@ impl Editor {
         });
         let secondary_selections = &editor.selection_set.secondary_selections();
         let primary_selection = HighlightSpan {
+            layer: Layer::Selection,
             set_symbol: None,
             is_cursor: false,
             range: HighlightSpanRange::CharIndexRange(selection.extended_range()),
@@ -81,6 +132,7 @@ impl Editor {
 
         let primary_selection_anchors =
             selection.anchors().into_iter().map(|anchor| HighlightSpan {
+                layer: Layer::Selection,
                 set_symbol: None,
                 is_cursor: false,
                 range: HighlightSpanRange::CharIndexRange(anchor),
@@ -95,6 +147,7 @@ impl Editor {
             None
         } else {
             Some(HighlightSpan {
+                layer: Layer::Cursor,
                 set_symbol: None,
                 is_cursor: false,
                 range: HighlightSpanRange::CharIndex(
@@ -108,6 +161,7 @@ impl Editor {
             secondary_selections
                 .iter()
                 .map(|secondary_selection| HighlightSpan {
+                    layer: Layer::Selection,
                     set_symbol: None,
                     is_cursor: false,
                     range: HighlightSpanRange::CharIndexRange(secondary_selection.extended_range()),
@@ -116,6 +170,7 @@ impl Editor {
 
         let seconday_selection_anchors = secondary_selections.iter().flat_map(|selection| {
             selection.anchors().into_iter().map(|anchor| HighlightSpan {
+                layer: Layer::Selection,
                 set_symbol: None,
                 is_cursor: false,
                 range: HighlightSpanRange::CharIndexRange(anchor),
@@ -126,6 +181,7 @@ impl Editor {
             secondary_selections.iter().flat_map(|secondary_selection| {
                 [
                     HighlightSpan {
+                        layer: Layer::Cursor,
                         set_symbol: None,
                         is_cursor: false,
                         range: HighlightSpanRange::CharIndex(
@@ -134,6 +190,7 @@ impl Editor {
                         source: Source::Style(theme.ui.secondary_selection_secondary_cursor),
                     },
                     HighlightSpan {
+                        layer: Layer::Cursor,
                         set_symbol: None,
                         is_cursor: false,
                         range: HighlightSpanRange::CharIndex(
@@ -150,16 +207,11 @@ impl Editor {
             .sorted_by(|a, b| a.severity.cmp(&b.severity))
             .rev()
             .map(|diagnostic| HighlightSpan {
+                layer: Layer::Diagnostic,
                 set_symbol: None,
                 is_cursor: false,
                 range: HighlightSpanRange::CharIndexRange(diagnostic.range),
-                source: Source::StyleKey(match diagnostic.severity {
-                    Some(DiagnosticSeverity::ERROR) => DiagnosticsError,
-                    Some(DiagnosticSeverity::WARNING) => DiagnosticsWarning,
-                    Some(DiagnosticSeverity::INFORMATION) => DiagnosticsInformation,
-                    Some(DiagnosticSeverity::HINT) => DiagnosticsHint,
-                    _ => DiagnosticsDefault,
-                }),
+                source: Source::StyleKey(diagnostic_style_key(diagnostic.severity)),
             });
 
         let jumps = editor.jumps().into_iter().enumerate().map(|(index, jump)| {
@@ -169,6 +221,7 @@ impl Editor {
                 theme.ui.jump_mark_odd
             };
             HighlightSpan {
+                layer: Layer::Jump,
                 set_symbol: Some(jump.character.to_string()),
                 is_cursor: false,
                 source: Source::Style(style),
@@ -177,8 +230,18 @@ impl Editor {
                 ),
             }
         });
+        let matching_bracket = buffer
+            .matching_bracket(selection.to_char_index(&editor.cursor_direction))
+            .map(|char_index| HighlightSpan {
+                layer: Layer::MatchingBracket,
+                set_symbol: None,
+                is_cursor: false,
+                range: HighlightSpanRange::CharIndex(char_index),
+                source: Source::StyleKey(UiMatchingBracket),
+            });
         let extra_decorations = buffer.decorations().iter().flat_map(|decoration| {
             Some(HighlightSpan {
+                layer: Layer::Decoration,
                 set_symbol: None,
                 is_cursor: false,
                 range: HighlightSpanRange::CharIndexRange(
@@ -198,10 +261,64 @@ impl Editor {
         let visible_line_byte_range = buffer
             .line_range_to_byte_range(&visible_line_range)
             .unwrap_or_default();
+        let misspellings = context
+            .spell_checker()
+            .map(|spell_checker| {
+                spell_checker.buffer_misspellings(&buffer, visible_line_byte_range.clone())
+            })
+            .unwrap_or_default()
+            .into_iter()
+            .map(|byte_range| HighlightSpan {
+                layer: Layer::Diagnostic,
+                set_symbol: None,
+                is_cursor: false,
+                range: HighlightSpanRange::ByteRange(byte_range),
+                source: Source::StyleKey(UiMisspelling),
+            });
+        // The trailing spaces are distinguished from the spaces between words,
+        // which are left as they are
+        let whitespace = editor
+            .whitespace()
+            .then(|| {
+                visible_line_range.clone().filter_map(|line_index| {
+                    let start = buffer.line_to_char(line_index).ok()?;
+                    let line = rope.get_line(line_index)?.to_string();
+                    let content = line.trim_end_matches(['\r', '\n']);
+                    let trailing_start = content.trim_end_matches([' ', '\t']).chars().count();
+                    let line_ending = (content.len() < line.len())
+                        .then_some((start + content.chars().count(), "¬"));
+                    Some(
+                        content
+                            .chars()
+                            .enumerate()
+                            .filter_map(|(column, character)| {
+                                let symbol = match character {
+                                    '\t' => "→",
+                                    '\u{a0}' => "⍽",
+                                    ' ' if column >= trailing_start => "·",
+                                    _ => return None,
+                                };
+                                Some((start + column, symbol))
+                            })
+                            .chain(line_ending)
+                            .collect_vec(),
+                    )
+                })
+            })
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|(char_index, symbol)| HighlightSpan {
+                layer: Layer::Whitespace,
+                set_symbol: Some(symbol.to_string()),
+                is_cursor: false,
+                range: HighlightSpanRange::CharIndex(char_index),
+                source: Source::StyleKey(UiWhitespace),
+            });
         let spans = buffer.highlighted_spans();
         let filtered_highlighted_spans = {
             filter_items_by_range(
-                &spans,
+                spans,
                 visible_line_byte_range.start,
                 visible_line_byte_range.end,
                 |span| span.byte_range.clone(),
@@ -211,11 +328,12 @@ impl Editor {
                 let byte_range = buffer
                     .line_range_to_byte_range(&line_range)
                     .unwrap_or_default();
-                filter_items_by_range(&spans, byte_range.start, byte_range.end, |span| {
+                filter_items_by_range(spans, byte_range.start, byte_range.end, |span| {
                     span.byte_range.clone()
                 })
             }))
             .map(|span| HighlightSpan {
+                layer: Layer::Syntax,
                 range: HighlightSpanRange::ByteRange(span.byte_range.clone()),
                 source: Source::StyleKey(span.style_key.clone()),
                 set_symbol: None,
@@ -224,10 +342,14 @@ impl Editor {
         };
         let custom_regex_highlights = lazy_regex::regex!("(?i)#[0-9a-f]{6}")
             .find_iter(&content)
-            .map(|m| (m.as_str().to_string(), m.range()))
+            .map(|m| {
+                let range = m.start() + content_byte_offset..m.end() + content_byte_offset;
+                (m.as_str().to_string(), range)
+            })
             .filter_map(|(hex, range)| {
                 let color = crate::themes::Color::from_hex(&hex).ok()?;
                 Some(HighlightSpan {
+                    layer: Layer::ColorPreview,
                     set_symbol: None,
                     is_cursor: false,
                     range: HighlightSpanRange::ByteRange(range),
@@ -247,8 +369,12 @@ impl Editor {
                 let get_highlight_span = |name: &'static str, source: Source| {
                     let match_ = captures.name(name)?;
                     Some(HighlightSpan {
+                        layer: Layer::RegexHighlight,
                         source,
-                        range: HighlightSpanRange::ByteRange(match_.range()),
+                        range: HighlightSpanRange::ByteRange(
+                            match_.start() + content_byte_offset
+                                ..match_.end() + content_byte_offset,
+                        ),
                         set_symbol: None,
                         is_cursor: false,
                     })
@@ -268,56 +394,252 @@ impl Editor {
             .flatten();
 
         let visible_parent_lines = visible_parent_lines.into_iter().map(|line| HighlightSpan {
+            layer: Layer::ParentLine,
             source: Source::StyleKey(StyleKey::ParentLine),
             range: HighlightSpanRange::Line(line.line),
             set_symbol: None,
             is_cursor: false,
         });
+        let execution_line = buffer
+            .path()
+            .and_then(|path| context.execution_line(&path))
+            .map(|line| HighlightSpan {
+                layer: Layer::ExecutionLine,
+                source: Source::StyleKey(StyleKey::UiExecutionLine),
+                range: HighlightSpanRange::Line(line),
+                set_symbol: None,
+                is_cursor: false,
+            });
         let updates = vec![]
             .into_iter()
             .chain(visible_parent_lines)
+            .chain(execution_line)
             .chain(filtered_highlighted_spans)
+            .chain(whitespace)
             .chain(extra_decorations)
+            .chain(document_highlights)
             .chain(possible_selections)
             .chain(Some(primary_selection))
             .chain(secondary_selection)
             .chain(primary_selection_anchors)
             .chain(seconday_selection_anchors)
             .chain(marks)
+            .chain(misspellings)
             .chain(diagnostics)
             .chain(jumps)
+            .chain(matching_bracket)
             .chain(primary_selection_secondary_cursor)
             .chain(secondary_selection_cursors)
             .chain(custom_regex_highlights)
             .chain(regex_highlight_rules)
+            // The sorting is stable, so the spans of the same layer keep their order
+            .sorted_by_key(|span| span.layer)
             .collect_vec();
+        let ime_preedit = editor
+            .ime_preedit()
+            .zip(primary_selection_primary_cursor.as_ref())
+            .map(|(text, cursor)| ImePreedit {
+                position: cursor.position,
+                text,
+            });
+        let git_blame = context
+            .show_git_blame()
+            .then(|| {
+                let line = primary_selection_primary_cursor.as_ref()?.position.line;
+                let blame = buffer.git_blame()?.get(line)?.display();
+                let line_content = rope.get_line(line)?.to_string();
+                let line_content = line_content.trim_end_matches(['\r', '\n']);
+                let line_content = match &ime_preedit {
+                    Some(ime_preedit) => ime_preedit.insert_into(line, line_content),
+                    None => line_content.to_string(),
+                };
+                let line_content = line_content.as_str();
+                let content_width =
+                    (width as usize).saturating_sub(len_lines.to_string().len() + 1);
+                GitBlameVirtualText::new(line, line_content, &blame, content_width)
+            })
+            .flatten();
+        let cursor_line = buffer
+            .char_to_line(editor.get_cursor_char_index())
+            .unwrap_or_default();
+        let indent_guides = if editor.indent_guides() {
+            editor.indent_guides_cache().get(
+                rope,
+                buffer.revision(),
+                visible_line_range.clone(),
+                cursor_line,
+                indent_guide::level_width(buffer.indentation()),
+            )
+        } else {
+            Vec::new()
+        };
+        // The blank lines are padded with spaces up to their guides when they are rendered,
+        // except the cursor line, which the IME preedit and the git blame are inserted into
+        let indent_guide_line_lengths: HashMap<usize, usize> = indent_guides
+            .iter()
+            .filter(|guide| guide.line != cursor_line)
+            .map(|guide| (guide.line, guide.column + 1))
+            .into_grouping_map()
+            .max();
+        let indent_guide_updates = indent_guides.iter().map(|guide| {
+            let key = if guide.is_active {
+                UiIndentGuideActive
+            } else {
+                UiIndentGuide
+            };
+            CellUpdate {
+                symbol: Some("│".to_string()),
+                style: theme.get_style(&key),
+                source: Some(key),
+                ..CellUpdate::new(Position::new(guide.line, guide.column))
+            }
+        });
         let visible_lines_updates = {
             let boundaries = [Boundary::new(&buffer, visible_line_range)];
             updates
                 .iter()
                 .flat_map(|span| span.to_cell_updates(&buffer, theme, &boundaries))
+                .chain(indent_guide_updates)
                 .chain(primary_selection_primary_cursor)
+                .map(|cell_update| match &ime_preedit {
+                    Some(ime_preedit) => ime_preedit.shift(cell_update),
+                    None => cell_update,
+                })
+                .chain(
+                    ime_preedit
+                        .iter()
+                        .flat_map(|ime_preedit| ime_preedit.cell_updates(theme)),
+                )
+                .chain(
+                    git_blame
+                        .iter()
+                        .flat_map(|git_blame| git_blame.cell_updates(theme)),
+                )
                 .collect_vec()
         };
 
+        // The breakpoints come first, so that they take precedence over the hunks
+        let breakpoint_style = theme.get_style(&StyleKey::UiBreakpoint);
+        let gutter_markers = buffer
+            .breakpoint_lines()
+            .into_iter()
+            .map(|line_index| GutterMarker {
+                line_index,
+                symbol: "●",
+                style: breakpoint_style,
+            })
+            .chain(buffer.git_gutter_hunks().into_iter().flat_map(|hunk| {
+                let (symbol, color) = match hunk.kind() {
+                    GutterHunkKind::Added => ("┃", theme.hunk.added_gutter),
+                    GutterHunkKind::Modified => ("┃", theme.hunk.modified_gutter),
+                    GutterHunkKind::Deleted => ("▁", theme.hunk.deleted_gutter),
+                };
+                hunk.marked_lines().map(move |line_index| GutterMarker {
+                    line_index,
+                    symbol,
+                    style: Style::new().foreground_color(color),
+                })
+            }))
+            .collect_vec();
+
+        let visible_lines = visible_lines
+            .map(|(line_index, line)| {
+                let line = match indent_guide_line_lengths.get(&line_index) {
+                    Some(length) => {
+                        let content = line.trim_end_matches(['\r', '\n']);
+                        let padding = length.saturating_sub(content.chars().count());
+                        format!("{content}{}{}", " ".repeat(padding), &line[content.len()..])
+                    }
+                    None => line,
+                };
+                let line = match &ime_preedit {
+                    Some(ime_preedit) => ime_preedit.insert_into(line_index, &line),
+                    None => line,
+                };
+                let line = match &git_blame {
+                    Some(git_blame) if git_blame.line == line_index => git_blame.insert_into(&line),
+                    _ => line,
+                };
+                (line_index, line)
+            })
+            .collect_vec();
+        // The lines hidden by folds are skipped, so each visible line is mapped to its row
+        let rows: HashMap<usize, usize> = visible_lines
+            .iter()
+            .enumerate()
+            .map(|(row, (line_index, _))| (*line_index, row))
+            .collect();
+        let to_row = |line: usize| {
+            if fold::is_hidden(&hidden_line_ranges, line) {
+                None
+            } else {
+                Some(
+                    rows.get(&line)
+                        .copied()
+                        .unwrap_or(line.saturating_sub(scroll_offset as usize)),
+                )
+            }
+        };
+        // Right-to-left text is reordered for rendering only,
+        // so the cell updates are moved from their logical columns to their visual columns
+        let visual_orders: HashMap<usize, VisualOrder> = if context.logical_order_rendering() {
+            Default::default()
+        } else {
+            visible_lines
+                .iter()
+                .filter_map(|(line_index, line)| Some((*line_index, VisualOrder::new(line)?)))
+                .collect()
+        };
+
         let visible_lines_grid = visible_lines_grid.render_content(
-            &visible_lines.map(|(_, line)| line).join(""),
+            &visible_lines
+                .iter()
+                .map(|(line_index, line)| match visual_orders.get(line_index) {
+                    Some(visual_order) => visual_order.line(),
+                    None => line.as_str(),
+                })
+                .join(""),
             RenderContentLineNumber::LineNumber {
                 start_line_index: scroll_offset as usize,
                 max_line_number: len_lines as usize,
+                gutter_markers: gutter_markers.clone(),
+                hidden_line_ranges: hidden_line_ranges.clone(),
             },
             visible_lines_updates
                 .clone()
                 .into_iter()
-                .map(|cell_update| CellUpdate {
-                    position: cell_update.position.move_up(scroll_offset as usize),
-                    ..cell_update
+                .filter_map(|cell_update| {
+                    let Position { line, column } = cell_update.position;
+                    let column = match visual_orders.get(&line) {
+                        Some(visual_order) => visual_order.visual_column(column),
+                        None => column,
+                    };
+                    Some(CellUpdate {
+                        position: Position::new(to_row(line)?, column),
+                        ..cell_update
+                    })
                 })
                 .collect_vec(),
             Vec::new(),
             theme,
         );
 
+        // The cursor row is the row of the grid, which is only a part of a wrapped line
+        let (highlights_line, highlights_column) = (
+            editor.highlights_cursor_line(),
+            editor.highlights_cursor_column(),
+        );
+        let visible_lines_grid = match visible_lines_grid.get_cursor_position() {
+            Some(cursor) if highlights_line || highlights_column => visible_lines_grid
+                .highlight_row_and_column(
+                    highlights_line.then_some(cursor.line),
+                    highlights_column.then_some(cursor.column),
+                    theme.ui.background_color,
+                    theme.ui.cursor_line_background,
+                ),
+            _ => visible_lines_grid,
+        };
+
         let hidden_parent_lines_grid = {
             let boundaries = hidden_parent_line_ranges
                 .into_iter()
@@ -326,6 +648,7 @@ impl Editor {
             let updates = hidden_parent_lines
                 .iter()
                 .map(|line| HighlightSpan {
+                    layer: Layer::ParentLine,
                     source: Source::StyleKey(StyleKey::ParentLine),
                     range: HighlightSpanRange::Line(line.line),
                     set_symbol: None,
@@ -352,6 +675,8 @@ impl Editor {
                         RenderContentLineNumber::LineNumber {
                             start_line_index: line.line,
                             max_line_number: len_lines as usize,
+                            gutter_markers: gutter_markers.clone(),
+                            hidden_line_ranges: hidden_line_ranges.clone(),
                         },
                         updates,
                         Default::default(),
@@ -381,6 +706,48 @@ impl Editor {
 
             hidden_parent_lines_grid.merge_vertical(bottom)
         };
+        // The minimap and the scrollbar take the columns that are left out of the render area
+        let grid = match self.minimap() {
+            Some(minimap) => {
+                grid.merge_horizontal(minimap.render(&buffer, &self.visible_line_range(), theme))
+            }
+            None => grid,
+        };
+        let grid = if self.scrollbar() {
+            let height = grid.rows.len();
+            let search_marks = self
+                .search_match_lines()
+                .unwrap_or_default()
+                .into_iter()
+                .map(|line| (line, theme.ui.possible_selection_background));
+            // The most severe diagnostic of each row is marked last, so that it takes precedence
+            let diagnostic_marks = buffer
+                .diagnostics()
+                .into_iter()
+                .sorted_by(|a, b| b.severity.cmp(&a.severity))
+                .filter_map(|diagnostic| {
+                    let color = theme
+                        .get_style(&diagnostic_style_key(diagnostic.severity))
+                        .line?
+                        .color;
+                    Some((buffer.char_to_line(diagnostic.range.start).ok()?, color))
+                });
+            let scrollbar = Scrollbar::new(
+                height,
+                len_lines as usize,
+                &self.visible_line_range(),
+                search_marks.chain(diagnostic_marks),
+            );
+            grid.merge_horizontal(
+                Grid::new(Dimension {
+                    height: height as u16,
+                    width: 1,
+                })
+                .apply_cell_updates(scrollbar.cell_updates(theme).collect_vec()),
+            )
+        } else {
+            grid
+        };
         let window_title_style = if focused {
             theme.ui.window_title_focused
         } else {
@@ -439,10 +806,45 @@ impl Editor {
             [line_range].to_vec(),
         )
     }
+
+    /// The lines of the matches of the current search, which are marked on the scrollbar.
+    ///
+    /// Large files are not searched, because that would scan the whole file on every render.
+    fn search_match_lines(&self) -> anyhow::Result<Vec<usize>> {
+        if !matches!(self.selection_set.mode, SelectionMode::Find { .. })
+            || self.buffer().is_large_file()
+        {
+            return Ok(Vec::new());
+        }
+        let selection = self.selection_set.primary_selection();
+        let object = self.get_selection_mode_trait_object(selection, true)?;
+        let buffer = self.buffer();
+        let lines = object
+            .iter_filtered(selection_mode::SelectionModeParams {
+                buffer: &buffer,
+                current_selection: selection,
+                cursor_direction: &self.cursor_direction,
+            })?
+            .filter_map(|range| buffer.byte_to_line(range.range().start).ok())
+            .dedup()
+            .collect_vec();
+        Ok(lines)
+    }
+}
+
+fn diagnostic_style_key(severity: Option<DiagnosticSeverity>) -> StyleKey {
+    match severity {
+        Some(DiagnosticSeverity::ERROR) => DiagnosticsError,
+        Some(DiagnosticSeverity::WARNING) => DiagnosticsWarning,
+        Some(DiagnosticSeverity::INFORMATION) => DiagnosticsInformation,
+        Some(DiagnosticSeverity::HINT) => DiagnosticsHint,
+        _ => DiagnosticsDefault,
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub(crate) struct HighlightSpan {
+    pub(crate) layer: Layer,
     pub(crate) source: Source,
     pub(crate) range: HighlightSpanRange,
     pub(crate) set_symbol: Option<String>,
@@ -493,10 +895,10 @@ impl HighlightSpan {
                             Some(CellUpdate {
                                 position,
                                 symbol: self.set_symbol.clone(),
-                                style: match &self.source {
+                                style: self.layer.blend(match &self.source {
                                     Source::StyleKey(key) => theme.get_style(key),
                                     Source::Style(style) => *style,
-                                },
+                                }),
                                 is_cursor: self.is_cursor,
                                 source: match &self.source {
                                     Source::StyleKey(key) => Some(key.clone()),
@@ -522,6 +924,61 @@ fn range_intersection<T: Ord + Copy>(a: &Range<T>, b: &Range<T>) -> Option<Range
     }
 }
 
+/// The layers that the highlight spans are composited in, from the bottom to the top,
+/// where each attribute of a cell is taken from the topmost span that sets it.
+///
+/// Each layer only sets the attributes that it is meant for (see `Layer::blend`),
+/// so that, for example, a selected word with a diagnostic is rendered with both
+/// the background of the selection and the underline of the diagnostic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub(crate) enum Layer {
+    ParentLine,
+    /// The line where the debuggee is stopped
+    ExecutionLine,
+    Syntax,
+    /// The highlights of `Editor::regex_highlight_rules`
+    RegexHighlight,
+    /// The hex colors, which are rendered in their own color
+    ColorPreview,
+    Whitespace,
+    /// See `Buffer::decorations`
+    Decoration,
+    /// The occurrences of the symbol under the cursor, see `Editor::document_highlights`
+    DocumentHighlight,
+    /// The search matches
+    PossibleSelection,
+    Mark,
+    Selection,
+    MatchingBracket,
+    /// The diagnostics and the misspellings
+    Diagnostic,
+    Jump,
+    Cursor,
+}
+
+impl Layer {
+    /// Keeps the attributes of `style` that this layer sets.
+    fn blend(self, style: Style) -> Style {
+        match self {
+            Layer::ParentLine | Layer::ExecutionLine | Layer::DocumentHighlight => Style {
+                background_color: style.background_color,
+                ..Style::new()
+            },
+            Layer::Whitespace => Style {
+                foreground_color: style.foreground_color,
+                ..Style::new()
+            },
+            // Bold is kept, because it tells the errors apart without colors
+            Layer::Diagnostic => Style {
+                line: style.line,
+                is_bold: style.is_bold,
+                ..Style::new()
+            },
+            _ => style,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub(crate) enum Source {
     StyleKey(StyleKey),
@@ -560,6 +1017,158 @@ impl Boundary {
     }
 }
 
+/// The blame of the current line, rendered as virtual text after the end of the line.
+///
+/// It is truncated to the remaining width instead of being wrapped,
+/// so that it never changes the layout of the lines.
+struct GitBlameVirtualText {
+    /// 0-based index.
+    line: usize,
+    /// The column where the virtual text starts, which is right after the end of the line.
+    column: usize,
+    text: String,
+}
+
+impl GitBlameVirtualText {
+    const PADDING: &'static str = "    ";
+
+    fn new(line: usize, line_content: &str, blame: &str, content_width: usize) -> Option<Self> {
+        // Leave one column, so that the virtual text never touches the edge of the window
+        let mut remaining_width = content_width
+            .saturating_sub(get_string_width(line_content))
+            .saturating_sub(Self::PADDING.len() + 1);
+        let text: String = blame
+            .chars()
+            .take_while(|c| {
+                let fits = get_char_width(*c) <= remaining_width;
+                remaining_width = remaining_width.saturating_sub(get_char_width(*c));
+                fits
+            })
+            .collect();
+        (!text.is_empty()).then(|| Self {
+            line,
+            column: line_content.chars().count(),
+            text: format!("{}{text}", Self::PADDING),
+        })
+    }
+
+    fn insert_into(&self, line: &str) -> String {
+        let content = line.trim_end_matches(['\r', '\n']);
+        format!("{content}{}{}", self.text, &line[content.len()..])
+    }
+
+    fn cell_updates<'a>(&'a self, theme: &'a Theme) -> impl Iterator<Item = CellUpdate> + 'a {
+        (0..self.text.chars().count()).map(move |offset| CellUpdate {
+            style: theme.ui.virtual_text,
+            ..CellUpdate::new(Position::new(self.line, self.column + offset))
+        })
+    }
+}
+
+/// The vertical scrollbar in the rightmost column of a window, where the thumb spans the rows
+/// of the visible lines relative to the whole buffer.
+///
+/// The lines with diagnostics or search matches are marked along the bar, by their own symbol,
+/// so that they are distinguishable from the thumb even without colors.
+struct Scrollbar {
+    height: usize,
+    /// The rows of the thumb
+    thumb: Range<usize>,
+    /// The color of each marked row
+    marks: HashMap<usize, Color>,
+}
+
+impl Scrollbar {
+    /// The later of the `marks` on the same row takes precedence.
+    fn new(
+        height: usize,
+        len_lines: usize,
+        visible_line_range: &Range<usize>,
+        marks: impl Iterator<Item = (usize, Color)>,
+    ) -> Self {
+        let len_lines = len_lines.max(1);
+        let to_row = |line: usize| (line * height / len_lines).min(height.saturating_sub(1));
+        let start = to_row(visible_line_range.start);
+        let end = (visible_line_range.end * height)
+            .div_ceil(len_lines)
+            .max(start + 1)
+            .min(height);
+        Self {
+            height,
+            thumb: start..end,
+            marks: marks.map(|(line, color)| (to_row(line), color)).collect(),
+        }
+    }
+
+    fn cell_updates<'a>(&'a self, theme: &'a Theme) -> impl Iterator<Item = CellUpdate> + 'a {
+        (0..self.height).map(move |row| {
+            let (symbol, style) = match self.marks.get(&row) {
+                Some(color) => ("━", Style::new().foreground_color(*color)),
+                None if self.thumb.contains(&row) => ("┃", theme.ui.line_number),
+                None => ("│", theme.ui.border),
+            };
+            CellUpdate {
+                symbol: Some(symbol.to_string()),
+                style,
+                ..CellUpdate::new(Position::new(row, 0))
+            }
+        })
+    }
+}
+
+/// The text being composed by an input method, rendered at the primary cursor.
+///
+/// The cells after the cursor on the same line are shifted to the right to make room for it.
+struct ImePreedit<'a> {
+    position: Position,
+    text: &'a str,
+}
+
+impl ImePreedit<'_> {
+    fn insert_into(&self, line_index: usize, line: &str) -> String {
+        if line_index != self.position.line {
+            return line.to_string();
+        }
+        let byte_index = line
+            .char_indices()
+            .nth(self.position.column)
+            .map(|(byte_index, _)| byte_index)
+            .unwrap_or(line.len());
+        format!(
+            "{}{}{}",
+            &line[..byte_index],
+            self.text,
+            &line[byte_index..]
+        )
+    }
+
+    fn shift(&self, cell_update: CellUpdate) -> CellUpdate {
+        let position = cell_update.position;
+        if position.line == self.position.line && position.column >= self.position.column {
+            CellUpdate {
+                position: Position::new(position.line, position.column + self.len()),
+                ..cell_update
+            }
+        } else {
+            cell_update
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.text.chars().count()
+    }
+
+    fn cell_updates<'a>(&'a self, theme: &'a Theme) -> impl Iterator<Item = CellUpdate> + 'a {
+        (0..self.len()).map(move |offset| CellUpdate {
+            style: Style::new().underline(theme.ui.text_foreground),
+            ..CellUpdate::new(Position::new(
+                self.position.line,
+                self.position.column + offset,
+            ))
+        })
+    }
+}
+
 #[cfg(test)]
 mod test_render_editor {
     use quickcheck::Arbitrary;
@@ -648,6 +1257,35 @@ where
     &items[safe_start..safe_end]
 }
 
+#[cfg(test)]
+mod test_scrollbar {
+    use super::Scrollbar;
+    use crate::themes::Color;
+
+    #[test]
+    fn thumb_and_marks_are_scaled_to_the_height() {
+        let red = Color::new(255, 0, 0);
+        let blue = Color::new(0, 0, 255);
+        // Lines 50..75 of 100 lines are visible on 10 rows
+        let scrollbar = Scrollbar::new(
+            10,
+            100,
+            &(50..75),
+            [(3, blue), (99, blue), (5, red)].into_iter(),
+        );
+        assert_eq!(scrollbar.thumb, 5..8);
+        assert_eq!(scrollbar.marks.get(&0), Some(&red));
+        assert_eq!(scrollbar.marks.get(&9), Some(&blue));
+        assert_eq!(scrollbar.marks.len(), 2);
+
+        // The thumb spans the whole bar if every line is visible
+        assert_eq!(
+            Scrollbar::new(10, 3, &(0..3), std::iter::empty()).thumb,
+            0..10
+        );
+    }
+}
+
 #[cfg(test)]
 mod test_range_search {
     use super::filter_items_by_range;
@@ -0,0 +1,69 @@
+//! When smooth scrolling is enabled, scrolling by pages moves the view across a few frames
+//! instead of jumping, easing out towards the destination.
+//!
+//! The cursor moves to its destination immediately, only the scroll offset is animated.
+
+use std::time::{Duration, Instant};
+
+pub(crate) const SMOOTH_SCROLL_DURATION: Duration = Duration::from_millis(120);
+
+/// The interval between the frames of an animation, which is about 60 frames per second
+pub(crate) const SMOOTH_SCROLL_FRAME_INTERVAL: Duration = Duration::from_millis(16);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct ScrollAnimation {
+    from: u16,
+    to: u16,
+    started_at: Instant,
+}
+
+impl ScrollAnimation {
+    pub(crate) fn new(from: u16, to: u16) -> ScrollAnimation {
+        ScrollAnimation {
+            from,
+            to,
+            started_at: Instant::now(),
+        }
+    }
+
+    /// The scroll offset that the animation ends at
+    pub(crate) fn to(&self) -> u16 {
+        self.to
+    }
+
+    /// Returns `None` once the animation is finished.
+    pub(crate) fn scroll_offset(&self) -> Option<u16> {
+        self.scroll_offset_at(self.started_at.elapsed())
+    }
+
+    fn scroll_offset_at(&self, elapsed: Duration) -> Option<u16> {
+        if elapsed >= SMOOTH_SCROLL_DURATION {
+            return None;
+        }
+        let progress = elapsed.as_secs_f64() / SMOOTH_SCROLL_DURATION.as_secs_f64();
+        let eased = 1.0 - (1.0 - progress).powi(2);
+        let distance = self.to as f64 - self.from as f64;
+        Some((self.from as f64 + distance * eased).round() as u16)
+    }
+}
+
+#[cfg(test)]
+mod test_smooth_scroll {
+    use super::*;
+
+    #[test]
+    fn eases_out_towards_the_destination() {
+        let at = |animation: &ScrollAnimation, percentage: u32| {
+            animation.scroll_offset_at(SMOOTH_SCROLL_DURATION * percentage / 100)
+        };
+        let down = ScrollAnimation::new(10, 30);
+        assert_eq!(at(&down, 0), Some(10));
+        assert_eq!(at(&down, 50), Some(25));
+        assert_eq!(at(&down, 90), Some(30));
+        assert_eq!(at(&down, 100), None);
+
+        let up = ScrollAnimation::new(30, 10);
+        assert_eq!(at(&up, 50), Some(15));
+        assert_eq!(at(&up, 100), None);
+    }
+}
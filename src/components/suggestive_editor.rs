@@ -43,9 +43,7 @@ impl From<CompletionItem> for DropdownItem {
         DropdownItem::new(format!("{} {}", item.emoji(), item.label()))
             .set_info(item.info())
             .set_dispatches(item.dispatches())
-            .set_on_focused(Dispatches::one(Dispatch::ResolveCompletionItem(
-                item.completion_item(),
-            )))
+            .set_on_focused(Dispatches::one(Dispatch::ResolveCompletionItem(item)))
     }
 }
 
@@ -611,6 +609,7 @@ mod test_suggestive_editor {
             kind: None,
             detail: None,
             completion_item: Default::default(),
+            server: None,
         };
         execute_test(|s| {
             Box::new([
@@ -664,6 +663,7 @@ mod test_suggestive_editor {
                         detail: None,
                         insert_text: None,
                         completion_item: Default::default(),
+                        server: None,
                     }]
                     .into_iter()
                     .map(|item| item.into())
@@ -843,6 +843,7 @@ mod test_suggestive_editor {
                         kind: Some(CompletionItemKind::FUNCTION),
                         detail: None,
                         completion_item: Default::default(),
+                        server: None,
                     }]
                     .into_iter()
                     .map(|item| item.into())
@@ -0,0 +1,86 @@
+//! A read-only pane beside the main panel, which shows the output of the shell task that is
+//! running or that ran last, see `shell_task`.
+
+use my_proc_macros::key;
+
+use crate::{
+    app::{Dispatch, Dispatches},
+    context::Context,
+    position::Position,
+};
+
+use super::{
+    component::Component,
+    editor::{Editor, Mode},
+};
+
+pub(crate) struct TaskOutput {
+    /// Contains the output, so that it can be navigated with the usual movements.
+    /// It is restored whenever it is modified, because the pane is read-only.
+    editor: Editor,
+    output: String,
+}
+
+impl TaskOutput {
+    pub(crate) fn new(title: String) -> Self {
+        let mut editor = Editor::from_text(None, "");
+        editor.set_title(title);
+        Self {
+            editor,
+            output: String::new(),
+        }
+    }
+
+    /// The cursor is moved to the last line, so that the latest output is visible.
+    pub(crate) fn append_line(&mut self, line: &str) -> anyhow::Result<()> {
+        if !self.output.is_empty() {
+            self.output.push('\n')
+        }
+        self.output.push_str(line);
+        self.refresh_editor()?;
+        let position = Position::new(self.output.lines().count().saturating_sub(1), 0);
+        self.editor.set_position_range(position..position)?;
+        Ok(())
+    }
+
+    pub(crate) fn set_title(&mut self, title: String) {
+        self.editor.set_title(title)
+    }
+
+    fn refresh_editor(&mut self) -> anyhow::Result<()> {
+        let position = self.editor.get_cursor_position().unwrap_or_default();
+        self.editor.set_content(&self.output)?;
+        self.editor.set_position_range(position..position)?;
+        Ok(())
+    }
+}
+
+impl Component for TaskOutput {
+    fn editor(&self) -> &Editor {
+        &self.editor
+    }
+
+    fn editor_mut(&mut self) -> &mut Editor {
+        &mut self.editor
+    }
+
+    fn handle_key_event(
+        &mut self,
+        context: &Context,
+        event: event::KeyEvent,
+    ) -> anyhow::Result<Dispatches> {
+        match event {
+            key!("esc") if self.editor.mode == Mode::Normal => {
+                Ok(Dispatches::one(Dispatch::CloseCurrentWindow))
+            }
+            _ => {
+                let dispatches = self.editor.handle_key_event(context, event)?;
+                if self.editor.content() != self.output {
+                    self.editor.enter_normal_mode()?;
+                    self.refresh_editor()?;
+                }
+                Ok(dispatches)
+            }
+        }
+    }
+}
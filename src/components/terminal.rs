@@ -0,0 +1,348 @@
+use std::{
+    io::{Read, Write},
+    sync::mpsc::Sender,
+};
+
+use crossterm::event::KeyCode;
+use event::KeyModifiers;
+use itertools::Itertools;
+use my_proc_macros::key;
+use portable_pty::{native_pty_system, Child, ChildKiller, CommandBuilder, MasterPty, PtySize};
+use shared::canonicalized_path::CanonicalizedPath;
+
+use crate::{
+    app::{AppMessage, Dispatches},
+    position::Position,
+    rectangle::Rectangle,
+};
+
+use super::{
+    component::{Component, ComponentId},
+    editor::Editor,
+};
+
+/// The number of lines that scrolled off the top of the terminal that are kept
+const SCROLLBACK_LEN: usize = 1000;
+
+/// An integrated terminal backed by a PTY running the default shell of the user.
+///
+/// The output of the shell is interpreted by a VT100 parser,
+/// and the scrollback together with the screen are rendered as the content of the editor,
+/// so that the scrollback can be navigated and copied like any other buffer.
+///
+/// The content is only rebuilt before a frame is rendered, and only if output is processed since
+/// it was last rebuilt, so that a burst of output is rebuilt once instead of once per chunk.
+pub(crate) struct Terminal {
+    editor: Editor,
+    parser: vt100::Parser,
+    master: Box<dyn MasterPty + Send>,
+    writer: Box<dyn Write + Send>,
+    child: Box<dyn Child + Send + Sync>,
+    /// When true, key events are forwarded to the shell,
+    /// otherwise they are handled by the editor for navigating the scrollback.
+    forward_keys: bool,
+    /// True if output is processed since the content of the editor was last rebuilt
+    stale: bool,
+}
+
+impl Terminal {
+    pub(crate) fn new(
+        working_directory: &CanonicalizedPath,
+        sender: Sender<AppMessage>,
+    ) -> anyhow::Result<Self> {
+        Self::spawn(
+            CommandBuilder::new_default_prog(),
+            working_directory,
+            sender,
+        )
+    }
+
+    fn spawn(
+        mut command: CommandBuilder,
+        working_directory: &CanonicalizedPath,
+        sender: Sender<AppMessage>,
+    ) -> anyhow::Result<Self> {
+        // The size will be updated when the rectangle of this component is set
+        let size = PtySize {
+            rows: 24,
+            cols: 80,
+            pixel_width: 0,
+            pixel_height: 0,
+        };
+        let pair = native_pty_system().openpty(size)?;
+        command.cwd(working_directory.to_path_buf());
+        let child = pair.slave.spawn_command(command)?;
+        let writer = pair.master.take_writer()?;
+        let mut reader = pair.master.try_clone_reader()?;
+        let mut editor = Editor::from_text(None, "");
+        editor.set_title(title(true));
+        let component_id = editor.id();
+        // The output is read to its end once the shell exits
+        std::thread::spawn(move || {
+            let mut buffer = [0; 4096];
+            loop {
+                match reader.read(&mut buffer) {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => {
+                        if sender
+                            .send(AppMessage::TerminalOutput(buffer[..n].to_vec()))
+                            .is_err()
+                        {
+                            break;
+                        }
+                    }
+                }
+            }
+            let _ = sender.send(AppMessage::TerminalExited(component_id));
+        });
+        Ok(Self {
+            editor,
+            parser: vt100::Parser::new(size.rows, size.cols, SCROLLBACK_LEN),
+            master: pair.master,
+            writer,
+            child,
+            forward_keys: true,
+            stale: false,
+        })
+    }
+
+    pub(crate) fn process_output(&mut self, bytes: &[u8]) {
+        self.parser.process(bytes);
+        self.stale = true;
+    }
+
+    pub(crate) fn refresh_editor_if_stale(&mut self) -> anyhow::Result<()> {
+        if self.stale {
+            self.refresh_editor()?;
+        }
+        Ok(())
+    }
+
+    fn refresh_editor(&mut self) -> anyhow::Result<()> {
+        let (_, cols) = self.parser.screen().size();
+
+        // Collect the scrollback from the oldest line to the newest line,
+        // by scrolling the screen up one line at a time
+        self.parser.set_scrollback(usize::MAX);
+        let scrollback_len = self.parser.screen().scrollback();
+        let scrollback = (1..=scrollback_len)
+            .rev()
+            .filter_map(|offset| {
+                self.parser.set_scrollback(offset);
+                self.parser.screen().rows(0, cols).next()
+            })
+            .collect_vec();
+        self.parser.set_scrollback(0);
+
+        let screen = self.parser.screen();
+        let (cursor_row, cursor_column) = screen.cursor_position();
+        let rows = screen.rows(0, cols).collect_vec();
+        // Trailing empty rows are omitted, unless the cursor is there
+        let rows_len = rows
+            .iter()
+            .rposition(|row| !row.is_empty())
+            .map(|index| index + 1)
+            .unwrap_or_default()
+            .max(cursor_row as usize + 1);
+        let content = scrollback
+            .into_iter()
+            .chain(rows.into_iter().take(rows_len))
+            .join("\n");
+        self.editor.set_content(&content)?;
+
+        if self.forward_keys {
+            let cursor =
+                Position::new(scrollback_len + cursor_row as usize, cursor_column as usize);
+            self.editor.set_position_range(cursor..cursor)?;
+        }
+        self.stale = false;
+        Ok(())
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> anyhow::Result<()> {
+        self.writer.write_all(bytes)?;
+        Ok(self.writer.flush()?)
+    }
+
+    fn set_forward_keys(&mut self, forward_keys: bool) -> anyhow::Result<()> {
+        self.forward_keys = forward_keys;
+        self.editor.set_title(title(forward_keys));
+        self.refresh_editor()
+    }
+}
+
+fn title(forward_keys: bool) -> String {
+    if forward_keys {
+        "Terminal (ctrl+backslash to scroll back)".to_string()
+    } else {
+        "Terminal (scrollback, i to resume)".to_string()
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+    }
+}
+
+impl Component for Terminal {
+    fn editor(&self) -> &Editor {
+        &self.editor
+    }
+
+    fn editor_mut(&mut self) -> &mut Editor {
+        &mut self.editor
+    }
+
+    fn set_rectangle(&mut self, rectangle: Rectangle) {
+        // The first row is occupied by the title
+        let rows = rectangle.height.saturating_sub(1).max(1);
+        let cols = rectangle.width.max(1);
+        // This is called on every render, so the shell is only notified when the size is changed
+        if self.parser.screen().size() != (rows, cols) {
+            self.parser.set_size(rows, cols);
+            let _ = self.master.resize(PtySize {
+                rows,
+                cols,
+                pixel_width: 0,
+                pixel_height: 0,
+            });
+        }
+        self.editor.set_rectangle(rectangle)
+    }
+
+    fn handle_paste_event(&mut self, content: String) -> anyhow::Result<Dispatches> {
+        if self.forward_keys {
+            self.write(content.as_bytes())?;
+            Ok(Default::default())
+        } else {
+            self.editor.handle_paste_event(content)
+        }
+    }
+
+    /// The shell receives the committed text only,
+    /// because its own line editor cannot render the text being composed.
+    fn handle_ime_preedit(&mut self, preedit: String) -> anyhow::Result<Dispatches> {
+        if !self.forward_keys {
+            self.editor.set_ime_preedit(preedit)
+        }
+        Ok(Default::default())
+    }
+
+    fn handle_ime_commit(
+        &mut self,
+        context: &crate::context::Context,
+        text: String,
+    ) -> anyhow::Result<Dispatches> {
+        if self.forward_keys {
+            self.write(text.as_bytes())?;
+            Ok(Default::default())
+        } else {
+            self.editor.handle_ime_commit(context, text)
+        }
+    }
+
+    fn handle_key_event(
+        &mut self,
+        context: &crate::context::Context,
+        event: event::KeyEvent,
+    ) -> anyhow::Result<Dispatches> {
+        match event {
+            key!("ctrl+backslash") if self.forward_keys => {
+                self.set_forward_keys(false)?;
+                Ok(Default::default())
+            }
+            key!("i") if !self.forward_keys => {
+                self.set_forward_keys(true)?;
+                Ok(Default::default())
+            }
+            event if self.forward_keys => {
+                if let Some(bytes) = key_event_to_bytes(&event) {
+                    self.write(&bytes)?;
+                }
+                Ok(Default::default())
+            }
+            event => self.editor.handle_key_event(context, event),
+        }
+    }
+}
+
+/// Encode `event` as the bytes a terminal would send to the shell.
+///
+/// Returns `None` if `event` has no conventional encoding.
+fn key_event_to_bytes(event: &event::KeyEvent) -> Option<Vec<u8>> {
+    let bytes = match (&event.modifiers, event.code) {
+        (KeyModifiers::None | KeyModifiers::Shift, KeyCode::Char(c)) => c.to_string().into_bytes(),
+        (KeyModifiers::Ctrl, KeyCode::Char(c)) if c.is_ascii_alphabetic() => {
+            vec![c.to_ascii_lowercase() as u8 & 0x1f]
+        }
+        (KeyModifiers::Alt, KeyCode::Char(c)) => format!("\x1b{c}").into_bytes(),
+        (_, KeyCode::Enter) => b"\r".to_vec(),
+        (_, KeyCode::Backspace) => b"\x7f".to_vec(),
+        (_, KeyCode::Tab) => b"\t".to_vec(),
+        (_, KeyCode::BackTab) => b"\x1b[Z".to_vec(),
+        (_, KeyCode::Esc) => b"\x1b".to_vec(),
+        (_, KeyCode::Up) => b"\x1b[A".to_vec(),
+        (_, KeyCode::Down) => b"\x1b[B".to_vec(),
+        (_, KeyCode::Right) => b"\x1b[C".to_vec(),
+        (_, KeyCode::Left) => b"\x1b[D".to_vec(),
+        (_, KeyCode::Home) => b"\x1b[H".to_vec(),
+        (_, KeyCode::End) => b"\x1b[F".to_vec(),
+        (_, KeyCode::Insert) => b"\x1b[2~".to_vec(),
+        (_, KeyCode::Delete) => b"\x1b[3~".to_vec(),
+        (_, KeyCode::PageUp) => b"\x1b[5~".to_vec(),
+        (_, KeyCode::PageDown) => b"\x1b[6~".to_vec(),
+        _ => return None,
+    };
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod test_terminal {
+    use std::time::Duration;
+
+    use my_proc_macros::key;
+    use portable_pty::CommandBuilder;
+    use shared::canonicalized_path::CanonicalizedPath;
+
+    use crate::{app::AppMessage, components::component::Component};
+
+    use super::{key_event_to_bytes, Terminal};
+
+    #[test]
+    fn output_is_rendered_once_the_editor_is_refreshed() -> anyhow::Result<()> {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let mut command = CommandBuilder::new("echo");
+        command.arg("hello");
+        let working_directory: CanonicalizedPath = ".".try_into()?;
+        let mut terminal = Terminal::spawn(command, &working_directory, sender)?;
+        // The channel is disconnected once the output is read to its end
+        let mut exited = None;
+        while let Ok(message) = receiver.recv_timeout(Duration::from_secs(10)) {
+            match message {
+                AppMessage::TerminalOutput(bytes) => terminal.process_output(&bytes),
+                AppMessage::TerminalExited(component_id) => exited = Some(component_id),
+                _ => unreachable!(),
+            }
+        }
+        assert_eq!(exited, Some(terminal.id()));
+        assert_eq!(terminal.content(), "");
+        terminal.refresh_editor_if_stale()?;
+        assert_eq!(terminal.content().lines().next(), Some("hello"));
+        Ok(())
+    }
+
+    #[test]
+    fn encode_key_events() {
+        let test = |event: event::KeyEvent, expected: &[u8]| {
+            assert_eq!(key_event_to_bytes(&event), Some(expected.to_vec()))
+        };
+        test(key!("a"), b"a");
+        test(key!("A"), b"A");
+        test(key!("ctrl+c"), &[3]);
+        test(key!("alt+b"), b"\x1bb");
+        test(key!("enter"), b"\r");
+        test(key!("up"), b"\x1b[A");
+        assert_eq!(key_event_to_bytes(&key!("ctrl+1")), None);
+    }
+}
@@ -1,4 +1,5 @@
 use crate::app::LocalSearchConfigUpdate;
+use crate::app::PipeToShellOutput;
 use crate::app::Scope;
 use crate::char_index_range::CharIndexRange;
 use crate::clipboard::CopiedTexts;
@@ -14,6 +15,7 @@ use crate::quickfix_list::QuickfixListItem;
 use crate::rectangle::Rectangle;
 
 use crate::selection::CharIndex;
+use crate::selection_mode::text_object::TextObjectKind;
 use crate::style::Style;
 use crate::test_app::*;
 
@@ -23,6 +25,7 @@ use crate::{
     position::Position,
     selection::SelectionMode,
     themes::Theme,
+    transformation::Transformation,
 };
 
 use itertools::Itertools;
@@ -365,15 +368,34 @@ fn test_pipe_to_shell_1() -> anyhow::Result<()> {
             App(OpenFile(s.main_rs())),
             Editor(SetContent("snake_case".to_string())),
             Editor(SetSelectionMode(IfCurrentNotFound::LookForward, Token)),
-            Editor(PipeToShell {
+            App(PipeToShell {
                 command: "tr '_' ' '".to_string(),
+                output: PipeToShellOutput::ReplaceSelections,
             }),
+            WaitForAppMessage,
             Expect(CurrentComponentContent("snake case")),
             Expect(CurrentSelectedTexts(&["snake case"])),
         ])
     })
 }
 
+#[test]
+fn test_pipe_to_shell_scratch_buffer() -> anyhow::Result<()> {
+    execute_test(|s| {
+        Box::new([
+            App(OpenFile(s.main_rs())),
+            Editor(SetContent("snake_case".to_string())),
+            Editor(SetSelectionMode(IfCurrentNotFound::LookForward, Token)),
+            App(PipeToShell {
+                command: "tr '_' '-'".to_string(),
+                output: PipeToShellOutput::ScratchBuffer,
+            }),
+            WaitForAppMessage,
+            Expect(CurrentComponentContent("snake-case")),
+        ])
+    })
+}
+
 #[test]
 fn kill_line_to_end() -> anyhow::Result<()> {
     let input = "lala\nfoo bar spam\nyoyo";
@@ -454,6 +476,35 @@ fn undo_tree() -> anyhow::Result<()> {
     })
 }
 
+#[test]
+fn undo_within_selection() -> anyhow::Result<()> {
+    execute_test(|s| {
+        Box::new([
+            App(OpenFile(s.main_rs())),
+            Editor(SetContent("foo bar baz\n".to_string())),
+            Editor(MatchLiteral("foo".to_string())),
+            Editor(ReplaceCurrentSelectionWith("xx".to_string())),
+            Editor(MatchLiteral("baz".to_string())),
+            Editor(ReplaceCurrentSelectionWith("qux".to_string())),
+            Expect(CurrentComponentContent("xx bar qux\n")),
+            // The later change to "baz" is kept
+            Editor(MatchLiteral("xx".to_string())),
+            Editor(UndoWithinSelection),
+            Expect(CurrentComponentContent("foo bar qux\n")),
+            Expect(CurrentSelectedTexts(&["foo"])),
+            // Undoing within a selection is a change, which can be undone as usual
+            Editor(Undo),
+            Expect(CurrentComponentContent("xx bar qux\n")),
+            Editor(MatchLiteral("xx".to_string())),
+            Editor(UndoWithinSelection),
+            Editor(MatchLiteral("qux".to_string())),
+            Editor(UndoWithinSelection),
+            Expect(CurrentComponentContent("foo bar baz\n")),
+            Expect(CurrentSelectedTexts(&["baz"])),
+        ])
+    })
+}
+
 #[test]
 fn multi_exchange_sibling() -> anyhow::Result<()> {
     execute_test(|s| {
@@ -1038,6 +1089,154 @@ fn enter_newline() -> anyhow::Result<()> {
     })
 }
 
+#[test]
+fn auto_pair_and_indent_with_indent_queries() -> anyhow::Result<()> {
+    execute_test(|s| {
+        Box::new([
+            App(OpenFile(s.main_rs())),
+            Editor(SetContent("fn f()".to_string())),
+            Editor(MatchLiteral("fn f()".to_string())),
+            Editor(EnterInsertMode(Direction::End)),
+            App(HandleKeyEvents(keys!("space { enter").to_vec())),
+            Expect(CurrentComponentContent("fn f() {\n    \n}")),
+            // The typed closing bracket skips over the inserted one
+            App(HandleKeyEvents(keys!("x ( )").to_vec())),
+            Editor(Insert(";".to_string())),
+            Expect(CurrentComponentContent("fn f() {\n    x();\n}")),
+        ])
+    })
+}
+
+#[test]
+fn wrap_selections_and_skip_over_auto_closings_on_type() -> anyhow::Result<()> {
+    execute_test(|s| {
+        Box::new([
+            App(OpenFile(s.main_rs())),
+            Editor(SetContent("foo foo".to_string())),
+            Editor(EnterInsertMode(Direction::Start)),
+            Editor(MatchLiteral("foo".to_string())),
+            Editor(CursorAddToAllSelections),
+            App(HandleKeyEvents(keys!("(").to_vec())),
+            Expect(CurrentComponentContent("(foo) (foo)")),
+            Expect(CurrentSelectedTexts(&["foo", "foo"])),
+            Editor(CursorKeepPrimaryOnly),
+            Editor(SetContent("a)".to_string())),
+            Editor(MatchLiteral("a".to_string())),
+            Editor(EnterInsertMode(Direction::End)),
+            // Only the closing characters that are inserted automatically are skipped over
+            App(HandleKeyEvents(keys!(") ( ) x").to_vec())),
+            Expect(CurrentComponentContent("a)()x)")),
+        ])
+    })
+}
+
+#[test]
+fn toggle_comment() -> anyhow::Result<()> {
+    execute_test(|s| {
+        Box::new([
+            App(OpenFile(s.main_rs())),
+            Editor(SetContent("fn f() {\n    a;\n    b;\n}".to_string())),
+            Editor(MatchLiteral("a;\n    b".to_string())),
+            Editor(ToggleComment),
+            Expect(CurrentComponentContent("fn f() {\n    // a;\n    // b;\n}")),
+            Expect(CurrentSelectedTexts(&["    // a;\n    // b;"])),
+            Editor(ToggleComment),
+            Expect(CurrentComponentContent("fn f() {\n    a;\n    b;\n}")),
+        ])
+    })
+}
+
+#[test]
+fn set_language_changes_comment_tokens() -> anyhow::Result<()> {
+    execute_test(|s| {
+        Box::new([
+            App(OpenFile(s.main_rs())),
+            Editor(SetContent("a = 1".to_string())),
+            App(SetLanguage(shared::language::from_extension("py").unwrap())),
+            Editor(ToggleComment),
+            Expect(CurrentComponentContent("# a = 1")),
+        ])
+    })
+}
+
+#[test]
+fn fold_and_unfold_by_indentation() -> anyhow::Result<()> {
+    execute_test(|s| {
+        Box::new([
+            App(OpenFile(s.main_rs())),
+            // Python has no fold queries, so the lines are folded by their indentation
+            App(SetLanguage(shared::language::from_extension("py").unwrap())),
+            Editor(SetContent("a:\n    b\n    c\nd".to_string())),
+            Editor(SetRectangle(Rectangle {
+                origin: Position::default(),
+                width: 100,
+                height: 3,
+            })),
+            Editor(Fold),
+            Expect(EditorGrid("🦀  src/main.rs\n1▸│█:\n4 │d")),
+            Editor(Unfold),
+            Expect(EditorGrid("🦀  src/main.rs\n1│█:\n2│    b")),
+            Editor(FoldAll),
+            Editor(SetRectangle(Rectangle {
+                origin: Position::default(),
+                width: 100,
+                height: 5,
+            })),
+            Expect(EditorGrid("🦀  src/main.rs\n1▸│█:\n4 │d\n\n")),
+            // Moving the cursor into a fold opens it
+            Editor(MatchLiteral("c".to_string())),
+            Expect(CurrentSelectedTexts(&["c"])),
+            Expect(EditorGrid("🦀  src/main.rs\n1│a:\n2│    b\n3│    █\n4│d")),
+        ])
+    })
+}
+
+#[test]
+fn restructure_markdown_outline() -> anyhow::Result<()> {
+    execute_test(|s| {
+        Box::new([
+            App(OpenFile(s.main_rs())),
+            App(SetLanguage(shared::language::from_extension("md").unwrap())),
+            Editor(SetContent("# A\n## B\nb\n### B.1\n## C\nc".to_string())),
+            Editor(MatchLiteral("C".to_string())),
+            Editor(MoveHeadingSubtree(Direction::Start)),
+            Expect(CurrentComponentContent("# A\n## C\nc\n## B\nb\n### B.1")),
+            Expect(CurrentSelectedTexts(&["## C"])),
+            Editor(MoveHeadingSubtree(Direction::End)),
+            Expect(CurrentComponentContent("# A\n## B\nb\n### B.1\n## C\nc")),
+            Expect(CurrentSelectedTexts(&["## C"])),
+            Editor(MatchLiteral("B".to_string())),
+            Editor(MoveSelection(First)),
+            Editor(DemoteHeading),
+            Expect(CurrentComponentContent("# A\n### B\nb\n#### B.1\n## C\nc")),
+            Expect(CurrentSelectedTexts(&["### B"])),
+            Editor(PromoteHeading),
+            Editor(PromoteHeading),
+            Expect(CurrentComponentContent("# A\n# B\nb\n## B.1\n## C\nc")),
+        ])
+    })
+}
+
+#[test]
+fn append_terminator_without_moving_the_selection() -> anyhow::Result<()> {
+    execute_test(|s| {
+        Box::new([
+            App(OpenFile(s.main_rs())),
+            Editor(SetContent("fn main() {\n    let x = f(a)\n}".to_string())),
+            Editor(MatchLiteral("x".to_string())),
+            Editor(AppendTerminator),
+            Expect(CurrentComponentContent("fn main() {\n    let x = f(a);\n}")),
+            Expect(CurrentSelectedTexts(&["x"])),
+            Editor(MatchLiteral("a".to_string())),
+            Editor(AppendTerminator),
+            Expect(CurrentComponentContent(
+                "fn main() {\n    let x = f(a,);\n}",
+            )),
+            Expect(CurrentSelectedTexts(&["a"])),
+        ])
+    })
+}
+
 #[test]
 fn insert_mode_start() -> anyhow::Result<()> {
     execute_test(|s| {
@@ -1170,6 +1369,39 @@ fn scroll_page() -> anyhow::Result<()> {
     })
 }
 
+#[test]
+fn scroll_keeps_the_cursor_on_the_same_row() -> anyhow::Result<()> {
+    execute_test(|s| {
+        Box::new([
+            App(OpenFile(s.main_rs())),
+            Editor(SetContent(
+                (0..30).map(|line| format!("line {line}")).join("\n"),
+            )),
+            Editor(SetRectangle(Rectangle {
+                origin: Position::default(),
+                width: 100,
+                height: 6,
+            })),
+            Editor(MatchLiteral("line 2".to_string())),
+            Expect(CurrentScrollOffset(0)),
+            // Half of the window height
+            Editor(ScrollPageDown),
+            Expect(CurrentLine("line 5")),
+            Expect(CurrentScrollOffset(3)),
+            // The height of the content, which excludes the window title
+            Editor(ScrollFullPageDown),
+            Expect(CurrentLine("line 10")),
+            Expect(CurrentScrollOffset(8)),
+            Editor(ScrollFullPageUp),
+            Expect(CurrentLine("line 5")),
+            Expect(CurrentScrollOffset(3)),
+            Editor(ScrollPageUp),
+            Expect(CurrentLine("line 2")),
+            Expect(CurrentScrollOffset(0)),
+        ])
+    })
+}
+
 #[test]
 fn scroll_offset() -> anyhow::Result<()> {
     execute_test(|s| {
@@ -1530,6 +1762,7 @@ fn diagnostics_range_updated_by_edit() -> anyhow::Result<()> {
             App(OpenFile(s.main_rs())),
             Editor(SetContent("fn main() { let x = 123 }".trim().to_string())),
             App(HandleLspNotification(LspNotification::PublishDiagnostics(
+                "rust-analyzer".to_string(),
                 lsp_types::PublishDiagnosticsParams {
                     uri: s.main_rs().to_url().unwrap(),
                     diagnostics: [lsp_types::Diagnostic {
@@ -2176,6 +2409,40 @@ fn change_surround_selection_on_enclosure() -> Result<(), anyhow::Error> {
     })
 }
 
+#[test]
+fn delete_and_change_nearest_surround_with_multiple_cursors() -> Result<(), anyhow::Error> {
+    execute_test(|s| {
+        Box::new([
+            App(OpenFile(s.main_rs())),
+            Editor(SetContent("(x) ['x'] (x x)".to_string())),
+            Editor(MatchLiteral("x".to_string())),
+            Editor(CursorAddToAllSelections),
+            // The nearest pair of a cursor is the innermost pair of any kind
+            App(HandleKeyEvents(keys!("v c n {").to_vec())),
+            // A pair that surrounds multiple cursors is only changed once
+            Expect(CurrentComponentContent("{x} [{x}] {x x}")),
+            App(HandleKeyEvents(keys!("v d n").to_vec())),
+            Expect(CurrentComponentContent("x [x] x x")),
+        ])
+    })
+}
+
+#[test]
+fn surround_with_tag() -> Result<(), anyhow::Error> {
+    execute_test(|s| {
+        Box::new([
+            App(OpenFile(s.main_rs())),
+            Editor(SetContent("hello world".to_string())),
+            Editor(MatchLiteral("world".to_string())),
+            App(HandleKeyEvents(keys!("v s t").to_vec())),
+            Editor(Insert("b class=\"x\"".to_string())),
+            App(HandleKeyEvent(key!("enter"))),
+            Expect(CurrentComponentContent("hello <b class=\"x\">world</b>")),
+            Expect(CurrentSelectedTexts(&["<b class=\"x\">world</b>"])),
+        ])
+    })
+}
+
 #[test]
 fn replace_with_pattern() -> Result<(), anyhow::Error> {
     fn run_test(
@@ -3105,6 +3372,70 @@ fn syntax_node_move_right_should_move_to_non_overlapping_node() -> anyhow::Resul
     })
 }
 
+#[test]
+fn expand_and_shrink_syntax_node() -> anyhow::Result<()> {
+    execute_test(|s| {
+        Box::new([
+            App(OpenFile(s.main_rs())),
+            Editor(SetContent("fn f() { g(x + y, z); }".to_string())),
+            Editor(MatchLiteral("y".to_string())),
+            Editor(ExpandSyntaxNode),
+            Expect(CurrentSelectedTexts(&["x + y"])),
+            Editor(ExpandSyntaxNode),
+            Expect(CurrentSelectedTexts(&["(x + y, z)"])),
+            Editor(ShrinkSyntaxNode),
+            Expect(CurrentSelectedTexts(&["x + y"])),
+            // Returns to the previously selected child instead of the first child
+            Editor(ShrinkSyntaxNode),
+            Expect(CurrentSelectedTexts(&["y"])),
+            Editor(MoveToSiblingSyntaxNode(Direction::Start)),
+            Expect(CurrentSelectedTexts(&["x"])),
+            Editor(ExpandSyntaxNode),
+            Editor(MoveToSiblingSyntaxNode(Direction::End)),
+            Expect(CurrentSelectedTexts(&["z"])),
+        ])
+    })
+}
+
+#[test]
+fn shrink_syntax_node_selects_first_named_child_after_selection_changed() -> anyhow::Result<()> {
+    execute_test(|s| {
+        Box::new([
+            App(OpenFile(s.main_rs())),
+            Editor(SetContent("fn f() { g(x + y, z); }".to_string())),
+            Editor(MatchLiteral("y".to_string())),
+            Editor(ExpandSyntaxNode),
+            Editor(MatchLiteral("x + y".to_string())),
+            Editor(ShrinkSyntaxNode),
+            Expect(CurrentSelectedTexts(&["x"])),
+        ])
+    })
+}
+
+#[test]
+fn change_text_objects() -> anyhow::Result<()> {
+    execute_test(|s| {
+        Box::new([
+            App(OpenFile(s.main_rs())),
+            Editor(SetContent("fn f() {\n    g(x, y);\n}".to_string())),
+            Editor(SetSelectionMode(
+                IfCurrentNotFound::LookForward,
+                TextObject(TextObjectKind::Argument),
+            )),
+            Expect(CurrentSelectedTexts(&["x"])),
+            Editor(MoveSelection(Next)),
+            Expect(CurrentSelectedTexts(&["y"])),
+            Editor(SetSelectionMode(
+                IfCurrentNotFound::LookForward,
+                TextObject(TextObjectKind::Function(SurroundKind::Inside)),
+            )),
+            Expect(CurrentSelectedTexts(&["g(x, y);"])),
+            Editor(Change),
+            Expect(CurrentComponentContent("fn f() {\n    \n}")),
+        ])
+    })
+}
+
 #[test]
 fn word_first_last_move_bounds_within_alphanumeric_sentence() -> anyhow::Result<()> {
     execute_test(|s| {
@@ -3186,3 +3517,233 @@ bam
         ])
     })
 }
+
+#[test]
+fn hard_wrap_prose_as_you_type() -> anyhow::Result<()> {
+    execute_test(|s| {
+        let path = s.new_path("prose.md");
+        std::fs::write(&path, "").unwrap();
+        let path: shared::canonicalized_path::CanonicalizedPath = path.try_into().unwrap();
+        Box::new([
+            App(SetHardWrapWidth(Some(10))),
+            App(OpenFile(path.clone())),
+            Editor(EnterInsertMode(Direction::Start)),
+            App(HandleKeyEvents(
+                keys!("- h e l l o space w o r l d").to_vec(),
+            )),
+            Expect(CurrentComponentContent("- hello\n  world")),
+            // Code should not be wrapped
+            App(OpenFile(s.main_rs())),
+            Editor(SetContent("".to_string())),
+            Editor(EnterInsertMode(Direction::Start)),
+            App(HandleKeyEvents(keys!("h e l l o space w o r l d").to_vec())),
+            Expect(CurrentComponentContent("hello world")),
+        ])
+    })
+}
+
+#[test]
+fn display_line_movement() -> anyhow::Result<()> {
+    execute_test(|s| {
+        Box::new([
+            App(OpenFile(s.main_rs())),
+            Editor(SetContent("hello world spam\nfoo".to_string())),
+            // The content width is 8, because 2 columns are taken by line number and separator,
+            // so "hello world spam" is displayed as "hello ", "world " and "spam"
+            Editor(SetRectangle(Rectangle {
+                origin: Position::default(),
+                width: 10,
+                height: 10,
+            })),
+            Editor(EnterInsertMode(Direction::Start)),
+            Editor(MoveVertically {
+                direction: Direction::End,
+                display: true,
+            }),
+            Expect(EditorCursorPosition(Position::new(0, 6))),
+            Editor(MoveToDisplayLineEdge(Direction::End)),
+            Expect(EditorCursorPosition(Position::new(0, 11))),
+            Editor(MoveVertically {
+                direction: Direction::End,
+                display: true,
+            }),
+            Expect(EditorCursorPosition(Position::new(0, 16))),
+            Editor(MoveVertically {
+                direction: Direction::End,
+                display: true,
+            }),
+            Expect(EditorCursorPosition(Position::new(1, 3))),
+            // Logical line movement
+            Editor(MoveVertically {
+                direction: Direction::Start,
+                display: false,
+            }),
+            Expect(EditorCursorPosition(Position::new(0, 3))),
+            // Default keys use display line movement when configured
+            App(SetDisplayLineMovement(true)),
+            App(HandleKeyEvents(keys!("down end").to_vec())),
+            Expect(EditorCursorPosition(Position::new(0, 11))),
+            App(SetDisplayLineMovement(false)),
+            App(HandleKeyEvent(key!("end"))),
+            Expect(EditorCursorPosition(Position::new(0, 16))),
+        ])
+    })
+}
+
+#[test]
+fn block_mode() -> anyhow::Result<()> {
+    execute_test(|s| {
+        Box::new([
+            App(OpenFile(s.main_rs())),
+            Editor(SetContent("abcd\nefgh\nx\nijkl".to_string())),
+            Editor(EnterBlockMode),
+            Expect(CurrentMode(Mode::Block {
+                anchor: Position::new(0, 0),
+                cursor: Position::new(0, 0),
+            })),
+            Editor(MoveSelection(Down)),
+            Editor(MoveSelection(Down)),
+            Editor(MoveSelection(Down)),
+            Editor(MoveSelection(Right)),
+            // The line shorter than the block is selected partially
+            Expect(CurrentSelectedTexts(&["ab", "ef", "ij", "x"])),
+            Editor(EnterInsertMode(Direction::Start)),
+            Editor(Insert("> ".to_string())),
+            Expect(CurrentComponentContent("> abcd\n> efgh\n> x\n> ijkl")),
+        ])
+    })
+}
+
+#[test]
+fn block_mode_skips_lines_that_end_before_the_block() -> anyhow::Result<()> {
+    execute_test(|s| {
+        Box::new([
+            App(OpenFile(s.main_rs())),
+            Editor(SetContent("abcd\nx\nefgh".to_string())),
+            Editor(MatchLiteral("cd".to_string())),
+            Editor(EnterBlockMode),
+            Editor(MoveSelection(Down)),
+            Editor(MoveSelection(Down)),
+            Editor(MoveSelection(Right)),
+            Expect(CurrentSelectedTexts(&["cd", "gh"])),
+            Editor(EnterInsertMode(Direction::End)),
+            Editor(Insert("!".to_string())),
+            Expect(CurrentComponentContent("abcd!\nx\nefgh!")),
+        ])
+    })
+}
+
+#[test]
+fn align_selections() -> anyhow::Result<()> {
+    execute_test(|s| {
+        Box::new([
+            App(OpenFile(s.main_rs())),
+            Editor(SetContent("a = 1\nbbb = 2\ncc: 3\nd=4".to_string())),
+            Editor(SelectAll),
+            Editor(Align("=".to_string())),
+            // Lines without a match are left as is
+            Expect(CurrentComponentContent("a   = 1\nbbb = 2\ncc: 3\nd   =4")),
+            Expect(CurrentSelectedTexts(&["=", "=", "="])),
+            // Already aligned
+            Editor(Align("=".to_string())),
+            Expect(CurrentComponentContent("a   = 1\nbbb = 2\ncc: 3\nd   =4")),
+        ])
+    })
+}
+
+#[test]
+fn transform_multiple_selections_in_one_undo_step() -> anyhow::Result<()> {
+    execute_test(|s| {
+        Box::new([
+            App(OpenFile(s.main_rs())),
+            Editor(SetContent("hello_world\nfoo_bar".to_string())),
+            Editor(EnterBlockMode),
+            Editor(MoveSelection(Down)),
+            Editor(MoveSelection(Right)),
+            Editor(MoveSelection(Right)),
+            Editor(Transform(Transformation::Rot13)),
+            Expect(CurrentComponentContent("urylo_world\nsbb_bar")),
+            Editor(Undo),
+            Expect(CurrentComponentContent("hello_world\nfoo_bar")),
+        ])
+    })
+}
+
+#[test]
+fn sort_lines_keeps_the_selection() -> anyhow::Result<()> {
+    execute_test(|s| {
+        Box::new([
+            App(OpenFile(s.main_rs())),
+            Editor(SetContent("c\nb\na\nb\n".to_string())),
+            Editor(SelectAll),
+            Editor(Transform(Transformation::SortLines {
+                numeric: false,
+                case_sensitive: true,
+            })),
+            Expect(CurrentComponentContent("a\nb\nb\nc\n")),
+            Expect(CurrentSelectedTexts(&["a\nb\nb\nc\n"])),
+            Editor(Transform(Transformation::UniqueLines)),
+            Expect(CurrentComponentContent("a\nb\nc\n")),
+            Expect(CurrentSelectedTexts(&["a\nb\nc\n"])),
+        ])
+    })
+}
+
+#[test]
+fn increment_numbers() -> anyhow::Result<()> {
+    execute_test(|s| {
+        Box::new([
+            App(OpenFile(s.main_rs())),
+            Editor(SetContent("item 9\nitem 0\nitem 0".to_string())),
+            Editor(Increment {
+                amount: 1,
+                sequential: false,
+            }),
+            Expect(CurrentComponentContent("item 10\nitem 0\nitem 0")),
+            Expect(CurrentSelectedTexts(&["10"])),
+            App(HandleKeyEvent(key!("ctrl+x"))),
+            Expect(CurrentComponentContent("item 9\nitem 0\nitem 0")),
+            Editor(MatchLiteral("0".to_string())),
+            Editor(EnterBlockMode),
+            Editor(MoveSelection(Down)),
+            Editor(Increment {
+                amount: 2,
+                sequential: true,
+            }),
+            Expect(CurrentComponentContent("item 9\nitem 2\nitem 4")),
+            Expect(CurrentSelectedTexts(&["2", "4"])),
+        ])
+    })
+}
+
+#[test]
+fn matching_bracket() -> anyhow::Result<()> {
+    execute_test(|s| {
+        Box::new([
+            App(OpenFile(s.main_rs())),
+            Editor(SetContent("fn f(a: (u8)) {}".to_string())),
+            Editor(SetRectangle(Rectangle {
+                origin: Position::default(),
+                width: 30,
+                height: 5,
+            })),
+            Editor(MatchLiteral("(".to_string())),
+            Expect(GridCellStyleKey(
+                Position::new(1, 14),
+                Some(StyleKey::UiMatchingBracket),
+            )),
+            Editor(GoToMatchingBracket),
+            Expect(EditorCursorPosition(Position::new(0, 12))),
+            Expect(GridCellStyleKey(
+                Position::new(1, 6),
+                Some(StyleKey::UiMatchingBracket),
+            )),
+            Editor(GoToMatchingBracket),
+            Expect(EditorCursorPosition(Position::new(0, 4))),
+            // Not on a bracket
+            Editor(MatchLiteral("a".to_string())),
+            Editor(GoToMatchingBracket),
+            Expect(CurrentSelectedTexts(&["a"])),
+        ])
+    })
+}
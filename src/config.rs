@@ -0,0 +1,755 @@
+//! The editor-wide configuration, which is loaded from `config.toml` of the config directory,
+//! and then from `.ki/config.toml` of the working directory, where the settings of the latter
+//! take precedence:
+//!
+//! ```toml
+//! tab-width = 4
+//! expand-tab = true
+//! scroll-off = 3
+//! wrap = true
+//! text-width = 100
+//! theme = "VS Code (Dark)"
+//! insert-final-newline = true
+//! trim-trailing-whitespace = true
+//! workspace-edit-review-threshold = 5
+//! large-file-threshold = 50 # MiB
+//! scrollbar = true
+//! minimap = true
+//! smooth-scroll = true
+//! whitespace = true
+//! indent-guides = true
+//! cursor-line = true
+//! cursor-column = true
+//! spell-check = true
+//! spell-check-dictionary = "/usr/share/dict/words"
+//! document-highlight-delay = 300 # milliseconds, 0 disables it
+//! # Prints the base content of a file, whose path (relative to the project) is appended
+//! diff-provider = ["svn", "cat", "--revision", "BASE"]
+//!
+//! [tasks]
+//! check = "cargo check"
+//! test = "npm test"
+//!
+//! [debug.ki]
+//! command = "lldb-dap"
+//! request = "launch"
+//! arguments = { program = "target/debug/ki", cwd = "." }
+//!
+//! [diagnostics]
+//! minimum-severity = "warning"
+//! muted = ["cspell", "clippy::needless_return"]
+//!
+//! [language.go]
+//! tab-width = 8
+//! expand-tab = false
+//!
+//! [language.rust.lsp]
+//! command = "rust-analyzer"
+//! initialization-options = { check = { command = "clippy" } }
+//! # Runs the server inside a container, whose `/workspace` is mounted from `/home/me/project`
+//! wrapper = ["docker", "exec", "-i", "dev"]
+//! path-mappings = [{ local = "/home/me/project", remote = "/workspace" }]
+//! # The root of the workspace is the nearest ancestor of the first opened file with one of these
+//! root-markers = ["Cargo.lock"]
+//!
+//! [language.python.lsp]
+//! # Connects to a server that is already running
+//! address = "tcp://localhost:2087"
+//!
+//! # Runs alongside the server above, whose diagnostics, completions and code actions are merged
+//! [language.python.lsp-servers.ruff]
+//! command = "ruff"
+//! args = ["server"]
+//! root-markers = ["pyproject.toml", "ruff.toml"]
+//! ```
+//!
+//! Languages are named by their Tree-sitter grammar, and their settings take precedence
+//! over the top-level settings. The config is reloaded with the `reload-config` command.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use itertools::Itertools;
+use serde::Deserialize;
+use shared::language::Language;
+
+use crate::lsp::{
+    connection::PathMapping,
+    diagnostic::{DiagnosticFilter, Severity},
+};
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+struct ConfigFile {
+    tab_width: Option<usize>,
+    expand_tab: Option<bool>,
+    /// The number of lines that are kept visible above and below the cursor
+    scroll_off: Option<usize>,
+    /// Whether lines of prose are broken automatically at `text_width` while typing
+    wrap: Option<bool>,
+    text_width: Option<usize>,
+    theme: Option<String>,
+    /// Whether a newline is appended to files that do not end with one when they are saved
+    insert_final_newline: Option<bool>,
+    /// Whether the trailing whitespace of each line is removed when files are saved,
+    /// unless the `.editorconfig` says otherwise
+    trim_trailing_whitespace: Option<bool>,
+    /// The workspace edits (e.g. renames) that edit more files than this are reviewed
+    /// file by file before they are applied
+    workspace_edit_review_threshold: Option<usize>,
+    /// The files larger than this (in MiB) are opened in the large file mode
+    /// (see `Buffer::from_large_path`)
+    large_file_threshold: Option<u64>,
+    /// Whether a scrollbar is rendered in the rightmost column of each file window
+    scrollbar: Option<bool>,
+    /// Whether a minimap of the buffer is rendered beside the content of each file window
+    minimap: Option<bool>,
+    /// Whether scrolling by pages is animated across a few frames instead of jumping
+    smooth_scroll: Option<bool>,
+    /// Whether tabs, trailing spaces, non-breaking spaces and line endings are rendered
+    /// as dimmed symbols
+    whitespace: Option<bool>,
+    /// Whether a vertical guide is rendered at each indentation level,
+    /// where the guide of the scope that contains the cursor is highlighted
+    indent_guides: Option<bool>,
+    /// Whether the row of the cursor is highlighted across the window
+    cursor_line: Option<bool>,
+    /// Whether the column of the cursor is highlighted across the window
+    cursor_column: Option<bool>,
+    /// Whether the comments and strings of code, and prose, are spell checked
+    spell_check: Option<bool>,
+    /// The word list (one word per line) that words are spell checked against
+    spell_check_dictionary: Option<PathBuf>,
+    /// The milliseconds that the cursor rests on a symbol before the occurrences of the symbol
+    /// are highlighted (see `textDocument/documentHighlight`), where 0 disables the highlights
+    document_highlight_delay: Option<u64>,
+    /// The command that prints the content that the files of the project are diffed against,
+    /// for projects whose version control system is not supported, see `git::diff_provider`
+    diff_provider: Option<Vec<String>>,
+    /// The shell commands of the project by their names, see `shell_task`
+    #[serde(default)]
+    tasks: BTreeMap<String, String>,
+    /// The debug configurations by their names, see `dap::session`
+    #[serde(default)]
+    debug: BTreeMap<String, DebugConfig>,
+    #[serde(default)]
+    diagnostics: DiagnosticsConfig,
+    #[serde(default)]
+    language: BTreeMap<String, LanguageConfig>,
+}
+
+impl ConfigFile {
+    /// The settings of `other` take precedence.
+    fn merge(self, other: ConfigFile) -> ConfigFile {
+        let mut tasks = self.tasks;
+        tasks.extend(other.tasks);
+        let mut debug = self.debug;
+        debug.extend(other.debug);
+        let mut language = self.language;
+        for (name, other_language) in other.language {
+            let merged = language
+                .remove(&name)
+                .unwrap_or_default()
+                .merge(other_language);
+            language.insert(name, merged);
+        }
+        ConfigFile {
+            tab_width: other.tab_width.or(self.tab_width),
+            expand_tab: other.expand_tab.or(self.expand_tab),
+            scroll_off: other.scroll_off.or(self.scroll_off),
+            wrap: other.wrap.or(self.wrap),
+            text_width: other.text_width.or(self.text_width),
+            theme: other.theme.or(self.theme),
+            insert_final_newline: other.insert_final_newline.or(self.insert_final_newline),
+            trim_trailing_whitespace: other
+                .trim_trailing_whitespace
+                .or(self.trim_trailing_whitespace),
+            workspace_edit_review_threshold: other
+                .workspace_edit_review_threshold
+                .or(self.workspace_edit_review_threshold),
+            large_file_threshold: other.large_file_threshold.or(self.large_file_threshold),
+            scrollbar: other.scrollbar.or(self.scrollbar),
+            minimap: other.minimap.or(self.minimap),
+            smooth_scroll: other.smooth_scroll.or(self.smooth_scroll),
+            whitespace: other.whitespace.or(self.whitespace),
+            indent_guides: other.indent_guides.or(self.indent_guides),
+            cursor_line: other.cursor_line.or(self.cursor_line),
+            cursor_column: other.cursor_column.or(self.cursor_column),
+            spell_check: other.spell_check.or(self.spell_check),
+            spell_check_dictionary: other.spell_check_dictionary.or(self.spell_check_dictionary),
+            document_highlight_delay: other
+                .document_highlight_delay
+                .or(self.document_highlight_delay),
+            diff_provider: other.diff_provider.or(self.diff_provider),
+            tasks,
+            debug,
+            diagnostics: self.diagnostics.merge(other.diagnostics),
+            language,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+struct DiagnosticsConfig {
+    /// The diagnostics that are less severe than this are hidden
+    minimum_severity: Option<Severity>,
+    /// The sources or codes of the hidden diagnostics
+    #[serde(default)]
+    muted: Vec<String>,
+}
+
+impl DiagnosticsConfig {
+    /// The muted entries of both configs are kept.
+    fn merge(self, other: DiagnosticsConfig) -> DiagnosticsConfig {
+        DiagnosticsConfig {
+            minimum_severity: other.minimum_severity.or(self.minimum_severity),
+            muted: self.muted.into_iter().chain(other.muted).unique().collect(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+struct LanguageConfig {
+    tab_width: Option<usize>,
+    expand_tab: Option<bool>,
+    lsp: Option<LspConfig>,
+    /// The additional servers of the language by their names, which have no defaults,
+    /// so each of them needs a `command` or an `address`
+    #[serde(default)]
+    lsp_servers: BTreeMap<String, LspConfig>,
+}
+
+impl LanguageConfig {
+    fn merge(self, other: LanguageConfig) -> LanguageConfig {
+        let mut lsp_servers = self.lsp_servers;
+        for (name, lsp) in other.lsp_servers {
+            let lsp = match lsp_servers.remove(&name) {
+                Some(previous) => previous.merge(lsp),
+                None => lsp,
+            };
+            lsp_servers.insert(name, lsp);
+        }
+        LanguageConfig {
+            tab_width: other.tab_width.or(self.tab_width),
+            expand_tab: other.expand_tab.or(self.expand_tab),
+            lsp: match (self.lsp, other.lsp) {
+                (Some(lsp), Some(other_lsp)) => Some(lsp.merge(other_lsp)),
+                (lsp, other_lsp) => other_lsp.or(lsp),
+            },
+            lsp_servers,
+        }
+    }
+}
+
+/// Overrides the default language server of a language.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub(crate) struct LspConfig {
+    pub(crate) command: Option<String>,
+    /// The arguments of `command`, which are empty by default
+    pub(crate) args: Option<Vec<String>>,
+    pub(crate) initialization_options: Option<serde_json::Value>,
+    /// Connects to a running server instead of spawning `command`,
+    /// for example `tcp://localhost:9257` or `unix:///tmp/rust-analyzer.sock`
+    pub(crate) address: Option<String>,
+    /// The command that `command` is run with, for example `["docker", "exec", "-i", "dev"]`
+    pub(crate) wrapper: Option<Vec<String>>,
+    /// Translates the paths between the editor and a server that sees the files under
+    /// different paths, for example a server that runs in a container
+    pub(crate) path_mappings: Option<Vec<PathMapping>>,
+    /// The files (e.g. `Cargo.toml`) that mark the root of the workspace of the server,
+    /// which is the working directory if no ancestor of the file has any of them
+    pub(crate) root_markers: Option<Vec<String>>,
+}
+
+impl LspConfig {
+    fn merge(self, other: LspConfig) -> LspConfig {
+        LspConfig {
+            command: other.command.or(self.command),
+            args: other.args.or(self.args),
+            initialization_options: other.initialization_options.or(self.initialization_options),
+            address: other.address.or(self.address),
+            wrapper: other.wrapper.or(self.wrapper),
+            path_mappings: other.path_mappings.or(self.path_mappings),
+            root_markers: other.root_markers.or(self.root_markers),
+        }
+    }
+}
+
+/// Launches (or attaches to) a debuggee with a debug adapter.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub(crate) struct DebugConfig {
+    /// The command of the debug adapter, for example `lldb-dap` or `debugpy-adapter`
+    pub(crate) command: String,
+    /// The arguments of `command`, which are empty by default
+    #[serde(default)]
+    pub(crate) args: Vec<String>,
+    #[serde(default)]
+    pub(crate) request: DebugRequest,
+    /// The arguments of the request, which are specific to the debug adapter,
+    /// for example `{ program = "target/debug/ki" }`
+    #[serde(default = "empty_object")]
+    pub(crate) arguments: serde_json::Value,
+}
+
+fn empty_object() -> serde_json::Value {
+    serde_json::Value::Object(Default::default())
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum DebugRequest {
+    /// Starts the debuggee
+    #[default]
+    Launch,
+    /// Attaches to a debuggee that is already running
+    Attach,
+}
+
+impl DebugRequest {
+    /// The command of the request in the Debug Adapter Protocol.
+    pub(crate) fn command(self) -> &'static str {
+        match self {
+            DebugRequest::Launch => "launch",
+            DebugRequest::Attach => "attach",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Indentation {
+    /// The number of spaces of an indentation level, if `expand_tab` is true
+    pub(crate) width: usize,
+    /// Indent with spaces instead of tabs
+    pub(crate) expand_tab: bool,
+}
+
+impl Default for Indentation {
+    fn default() -> Self {
+        Indentation {
+            width: 4,
+            expand_tab: true,
+        }
+    }
+}
+
+const DEFAULT_WORKSPACE_EDIT_REVIEW_THRESHOLD: usize = 5;
+
+/// In MiB
+const DEFAULT_LARGE_FILE_THRESHOLD: u64 = 50;
+
+/// In milliseconds
+const DEFAULT_DOCUMENT_HIGHLIGHT_DELAY: u64 = 500;
+
+/// Only the first lines are analyzed when detecting the indentation of large files.
+const DETECT_INDENTATION_MAX_LINES: usize = 10000;
+
+impl Indentation {
+    /// The text of one indentation level.
+    pub(crate) fn unit(&self) -> String {
+        if self.expand_tab {
+            " ".repeat(self.width)
+        } else {
+            "\t".to_string()
+        }
+    }
+
+    pub(crate) fn display(&self) -> String {
+        if self.expand_tab {
+            format!("Spaces: {}", self.width)
+        } else {
+            "Tabs".to_string()
+        }
+    }
+
+    /// Detects whether most of the indented lines of `content` are indented with tabs or spaces,
+    /// and the most common increase of indentation between consecutive lines as the width.
+    ///
+    /// The settings that cannot be detected, such as the width of a tab, are taken from `default`.
+    pub(crate) fn detect(content: &str, default: Indentation) -> Indentation {
+        let mut tab_lines = 0;
+        let mut space_lines = 0;
+        let mut width_counts = BTreeMap::<usize, usize>::new();
+        let mut previous_width = 0;
+        for line in content.lines().take(DETECT_INDENTATION_MAX_LINES) {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let leading = &line[..line.len() - line.trim_start_matches([' ', '\t']).len()];
+            if leading.starts_with('\t') {
+                tab_lines += 1;
+                previous_width = 0;
+            } else if !leading.contains('\t') {
+                if !leading.is_empty() {
+                    space_lines += 1;
+                }
+                let width = leading.len();
+                // Increases of one space are mostly the continuations of block comments
+                if (2..=8).contains(&width.saturating_sub(previous_width)) {
+                    *width_counts.entry(width - previous_width).or_default() += 1;
+                }
+                previous_width = width;
+            }
+        }
+        if tab_lines == 0 && space_lines == 0 {
+            return default;
+        }
+        if tab_lines > space_lines {
+            return Indentation {
+                expand_tab: false,
+                ..default
+            };
+        }
+        Indentation {
+            expand_tab: true,
+            width: width_counts
+                .into_iter()
+                .max_by_key(|(width, count)| (*count, std::cmp::Reverse(*width)))
+                .map(|(width, _)| width)
+                .unwrap_or(default.width),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct Config {
+    /// The config files in increasing precedence, which are read again on reload
+    paths: Vec<PathBuf>,
+    file: ConfigFile,
+}
+
+impl Config {
+    pub(crate) fn paths(working_directory: &Path) -> Vec<PathBuf> {
+        [
+            grammar::config_dir().join("config.toml"),
+            working_directory.join(".ki").join("config.toml"),
+        ]
+        .to_vec()
+    }
+
+    /// The files that do not exist are skipped.
+    pub(crate) fn load(paths: Vec<PathBuf>) -> anyhow::Result<Config> {
+        let mut file = ConfigFile::default();
+        for path in paths.iter().filter(|path| path.exists()) {
+            let content = std::fs::read_to_string(path)?;
+            let config = toml::from_str(&content).map_err(|error| {
+                anyhow::anyhow!("Invalid config in {}:\n{error}", path.display())
+            })?;
+            file = file.merge(config);
+        }
+        Ok(Config { paths, file })
+    }
+
+    pub(crate) fn reload(&self) -> anyhow::Result<Config> {
+        Config::load(self.paths.clone())
+    }
+
+    fn language(&self, language: Option<&Language>) -> Option<&LanguageConfig> {
+        self.file.language.get(language?.name()?)
+    }
+
+    pub(crate) fn indentation(&self, language: Option<&Language>) -> Indentation {
+        let language = self.language(language);
+        let default = Indentation::default();
+        Indentation {
+            width: language
+                .and_then(|language| language.tab_width)
+                .or(self.file.tab_width)
+                .unwrap_or(default.width),
+            expand_tab: language
+                .and_then(|language| language.expand_tab)
+                .or(self.file.expand_tab)
+                .unwrap_or(default.expand_tab),
+        }
+    }
+
+    pub(crate) fn scroll_off(&self) -> usize {
+        self.file.scroll_off.unwrap_or_default()
+    }
+
+    /// Returns `None` if lines should not be broken automatically.
+    pub(crate) fn hard_wrap_width(&self) -> Option<usize> {
+        self.file.wrap.unwrap_or(false).then(|| {
+            self.file
+                .text_width
+                .unwrap_or(crate::hard_wrap::DEFAULT_TEXT_WIDTH)
+        })
+    }
+
+    pub(crate) fn theme(&self) -> Option<&str> {
+        self.file.theme.as_deref()
+    }
+
+    pub(crate) fn insert_final_newline(&self) -> bool {
+        self.file.insert_final_newline.unwrap_or(false)
+    }
+
+    pub(crate) fn trim_trailing_whitespace(&self) -> bool {
+        self.file.trim_trailing_whitespace.unwrap_or(false)
+    }
+
+    pub(crate) fn workspace_edit_review_threshold(&self) -> usize {
+        self.file
+            .workspace_edit_review_threshold
+            .unwrap_or(DEFAULT_WORKSPACE_EDIT_REVIEW_THRESHOLD)
+    }
+
+    /// In bytes
+    pub(crate) fn large_file_threshold(&self) -> u64 {
+        self.file
+            .large_file_threshold
+            .unwrap_or(DEFAULT_LARGE_FILE_THRESHOLD)
+            * 1024
+            * 1024
+    }
+
+    pub(crate) fn scrollbar(&self) -> bool {
+        self.file.scrollbar.unwrap_or(false)
+    }
+
+    pub(crate) fn minimap(&self) -> bool {
+        self.file.minimap.unwrap_or(false)
+    }
+
+    pub(crate) fn smooth_scroll(&self) -> bool {
+        self.file.smooth_scroll.unwrap_or(false)
+    }
+
+    pub(crate) fn whitespace(&self) -> bool {
+        self.file.whitespace.unwrap_or(false)
+    }
+
+    pub(crate) fn indent_guides(&self) -> bool {
+        self.file.indent_guides.unwrap_or(false)
+    }
+
+    pub(crate) fn cursor_line(&self) -> bool {
+        self.file.cursor_line.unwrap_or(false)
+    }
+
+    pub(crate) fn cursor_column(&self) -> bool {
+        self.file.cursor_column.unwrap_or(false)
+    }
+
+    /// Returns `None` if the occurrences of the symbol under the cursor are not highlighted.
+    pub(crate) fn document_highlight_delay(&self) -> Option<Duration> {
+        match self
+            .file
+            .document_highlight_delay
+            .unwrap_or(DEFAULT_DOCUMENT_HIGHLIGHT_DELAY)
+        {
+            0 => None,
+            delay => Some(Duration::from_millis(delay)),
+        }
+    }
+
+    pub(crate) fn diff_provider(&self) -> Option<&[String]> {
+        self.file.diff_provider.as_deref()
+    }
+
+    /// The commands of the workspace config replace the ones of the same names.
+    pub(crate) fn tasks(&self) -> &BTreeMap<String, String> {
+        &self.file.tasks
+    }
+
+    pub(crate) fn debug_configs(&self) -> &BTreeMap<String, DebugConfig> {
+        &self.file.debug
+    }
+
+    /// Returns `None` if spell checking is disabled.
+    pub(crate) fn spell_check_dictionary(&self) -> Option<PathBuf> {
+        self.file.spell_check.unwrap_or(false).then(|| {
+            self.file
+                .spell_check_dictionary
+                .clone()
+                .unwrap_or_else(|| crate::spell_check::DEFAULT_DICTIONARY.into())
+        })
+    }
+
+    pub(crate) fn diagnostic_filter(&self) -> DiagnosticFilter {
+        let default = DiagnosticFilter::default();
+        DiagnosticFilter {
+            minimum_severity: self
+                .file
+                .diagnostics
+                .minimum_severity
+                .unwrap_or(default.minimum_severity),
+            muted: self.file.diagnostics.muted.clone(),
+        }
+    }
+
+    pub(crate) fn lsp(&self, language: &Language) -> Option<&LspConfig> {
+        self.language(Some(language))?.lsp.as_ref()
+    }
+
+    /// The additional LSP servers of the language, which run alongside its default server
+    pub(crate) fn lsp_servers(&self, language: &Language) -> Option<&BTreeMap<String, LspConfig>> {
+        Some(&self.language(Some(language))?.lsp_servers)
+    }
+}
+
+#[cfg(test)]
+mod test_config {
+    use super::*;
+
+    #[test]
+    fn merge_config_files() -> anyhow::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let user = temp_dir.path().join("user.toml");
+        let workspace = temp_dir.path().join("workspace.toml");
+        std::fs::write(
+            &user,
+            r#"
+tab-width = 2
+wrap = true
+
+[tasks]
+check = "cargo check"
+test = "cargo test"
+
+[diagnostics]
+minimum-severity = "information"
+muted = ["cspell"]
+
+[language.rust]
+expand-tab = false
+
+[language.rust.lsp]
+command = "ra-multiplex"
+wrapper = ["docker", "exec", "-i", "dev"]
+
+[language.rust.lsp-servers.typos]
+command = "typos-lsp"
+"#,
+        )?;
+        std::fs::write(
+            &workspace,
+            r#"
+tab-width = 3
+text-width = 72
+insert-final-newline = true
+
+[tasks]
+test = "cargo nextest run"
+
+[debug.ki]
+command = "lldb-dap"
+arguments = { program = "target/debug/ki" }
+
+[diagnostics]
+muted = ["clippy::needless_return"]
+
+[language.rust.lsp]
+initialization-options = { cargo = { features = "all" } }
+path-mappings = [{ local = "/home/me/project", remote = "/workspace" }]
+
+[language.rust.lsp-servers.typos]
+root-markers = [".typos.toml"]
+"#,
+        )?;
+        let missing = temp_dir.path().join("missing.toml");
+        let config = Config::load([user, missing, workspace].to_vec())?;
+        let rust = shared::language::from_extension("rs").unwrap();
+        assert_eq!(
+            config.indentation(Some(&rust)),
+            Indentation {
+                width: 3,
+                expand_tab: false
+            }
+        );
+        assert_eq!(config.indentation(None).unit(), "   ");
+        assert_eq!(config.hard_wrap_width(), Some(72));
+        assert_eq!(config.scroll_off(), 0);
+        assert!(config.insert_final_newline());
+        assert_eq!(
+            config.tasks(),
+            &BTreeMap::from([
+                ("check".to_string(), "cargo check".to_string()),
+                ("test".to_string(), "cargo nextest run".to_string())
+            ])
+        );
+        assert_eq!(
+            config.debug_configs().get("ki"),
+            Some(&DebugConfig {
+                command: "lldb-dap".to_string(),
+                args: Vec::new(),
+                request: DebugRequest::Launch,
+                arguments: serde_json::json!({"program": "target/debug/ki"}),
+            })
+        );
+        assert_eq!(
+            config.diagnostic_filter(),
+            DiagnosticFilter {
+                minimum_severity: Severity::Information,
+                muted: ["cspell".to_string(), "clippy::needless_return".to_string()].to_vec(),
+            }
+        );
+        assert_eq!(
+            config.lsp(&rust),
+            Some(&LspConfig {
+                command: Some("ra-multiplex".to_string()),
+                args: None,
+                initialization_options: Some(serde_json::json!({"cargo": {"features": "all"}})),
+                address: None,
+                wrapper: Some(["docker", "exec", "-i", "dev"].map(String::from).to_vec()),
+                path_mappings: Some(
+                    [PathMapping {
+                        local: "/home/me/project".to_string(),
+                        remote: "/workspace".to_string(),
+                    }]
+                    .to_vec()
+                ),
+                root_markers: None,
+            })
+        );
+        assert_eq!(
+            config.lsp_servers(&rust),
+            Some(&BTreeMap::from([(
+                "typos".to_string(),
+                LspConfig {
+                    command: Some("typos-lsp".to_string()),
+                    root_markers: Some([".typos.toml".to_string()].to_vec()),
+                    ..Default::default()
+                }
+            )]))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn detect_indentation() {
+        let default = Indentation::default();
+        let detect = |content: &str| Indentation::detect(content, default);
+        assert_eq!(
+            detect("fn main() {\n  if x {\n    y\n  }\n  /*\n   * z\n   */\n}"),
+            Indentation {
+                width: 2,
+                expand_tab: true
+            }
+        );
+        assert_eq!(
+            detect("func main() {\n\tif x {\n\t\ty\n\t}\n    // aligned\n}"),
+            Indentation {
+                width: 4,
+                expand_tab: false
+            }
+        );
+        assert_eq!(detect("foo\n\nbar"), default);
+    }
+
+    #[test]
+    fn reject_unknown_settings() -> anyhow::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let path = temp_dir.path().join("config.toml");
+        std::fs::write(&path, "tab-size = 2")?;
+        let error = Config::load([path].to_vec()).unwrap_err().to_string();
+        assert!(error.contains("unknown field `tab-size`"), "{error}");
+        Ok(())
+    }
+}
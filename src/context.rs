@@ -13,6 +13,7 @@ use crate::{
     list::grep::RegexConfig,
     quickfix_list::DiagnosticSeverityRange,
     selection::SelectionMode,
+    spell_check::SpellChecker,
     themes::Theme,
 };
 
@@ -20,8 +21,7 @@ pub(crate) struct Context {
     clipboard: Clipboard,
     mode: Option<GlobalMode>,
     theme: Theme,
-
-    #[cfg(test)]
+    /// Used for highlighting code that is not in a buffer, such as the code blocks of markdown
     highlight_configs: crate::syntax_highlight::HighlightConfigs,
     current_working_directory: CanonicalizedPath,
     local_search_config: LocalSearchConfig,
@@ -30,6 +30,24 @@ pub(crate) struct Context {
     contextual_keymaps: Vec<KeymapLegendSection>,
     prompt_histories: HashMap<PromptHistoryKey, IndexSet<String>>,
     last_non_contiguous_selection_mode: Option<Either<SelectionMode, GlobalMode>>,
+    /// When defined, lines of prose are automatically broken at this width while typing.
+    hard_wrap_width: Option<usize>,
+    /// When true, the default vertical and line-edge movements of insert mode
+    /// operate on display lines (i.e. soft-wrapped lines) instead of logical lines.
+    display_line_movement: bool,
+    /// When true, the blame of the current line is shown at the end of the line.
+    show_git_blame: bool,
+    /// When true, right-to-left text is rendered in logical order instead of visual order.
+    logical_order_rendering: bool,
+    /// The register of the macro being recorded, if any.
+    recording_macro: Option<char>,
+    /// When true, typing an opening bracket or quote in insert mode wraps the non-empty selections
+    /// in the pair, instead of replacing them.
+    wrap_selection_on_type: bool,
+    /// Defined when spell checking is enabled.
+    spell_checker: Option<SpellChecker>,
+    /// The file and the line (0-based) where the debuggee is stopped, which is highlighted.
+    execution_location: Option<(CanonicalizedPath, usize)>,
 }
 
 pub(crate) struct QuickfixListState {
@@ -68,7 +86,6 @@ impl Default for Context {
             clipboard: Clipboard::new(),
             theme: Theme::default(),
             mode: None,
-            #[cfg(test)]
             highlight_configs: crate::syntax_highlight::HighlightConfigs::new(),
             current_working_directory: CanonicalizedPath::try_from(".").unwrap(),
             local_search_config: LocalSearchConfig::default(),
@@ -77,6 +94,14 @@ impl Default for Context {
             contextual_keymaps: Default::default(),
             prompt_histories: Default::default(),
             last_non_contiguous_selection_mode: None,
+            hard_wrap_width: None,
+            display_line_movement: false,
+            show_git_blame: false,
+            logical_order_rendering: false,
+            recording_macro: None,
+            wrap_selection_on_type: true,
+            spell_checker: None,
+            execution_location: None,
         }
     }
 }
@@ -104,6 +129,17 @@ impl Context {
         })
     }
 
+    pub(crate) fn get_primary_selection_content(&self) -> anyhow::Result<CopiedTexts> {
+        Ok(CopiedTexts::new(nonempty::NonEmpty::singleton(
+            self.clipboard.get_from_primary_selection()?,
+        )))
+    }
+
+    pub(crate) fn set_copy_to_primary_selection(&mut self, copy_to_primary_selection: bool) {
+        self.clipboard
+            .set_copy_to_primary_selection(copy_to_primary_selection)
+    }
+
     pub(crate) fn set_clipboard_content(
         &mut self,
         contents: CopiedTexts,
@@ -129,7 +165,6 @@ impl Context {
         Self { theme, ..self }
     }
 
-    #[cfg(test)]
     pub(crate) fn highlight(
         &mut self,
         language: shared::language::Language,
@@ -265,6 +300,83 @@ impl Context {
     ) -> Option<&Either<crate::selection::SelectionMode, GlobalMode>> {
         self.last_non_contiguous_selection_mode.as_ref()
     }
+
+    pub(crate) fn hard_wrap_width(&self) -> Option<usize> {
+        self.hard_wrap_width
+    }
+
+    pub(crate) fn set_hard_wrap_width(&mut self, hard_wrap_width: Option<usize>) {
+        self.hard_wrap_width = hard_wrap_width
+    }
+
+    pub(crate) fn display_line_movement(&self) -> bool {
+        self.display_line_movement
+    }
+
+    pub(crate) fn set_display_line_movement(&mut self, display_line_movement: bool) {
+        self.display_line_movement = display_line_movement
+    }
+
+    pub(crate) fn show_git_blame(&self) -> bool {
+        self.show_git_blame
+    }
+
+    pub(crate) fn set_show_git_blame(&mut self, show_git_blame: bool) {
+        self.show_git_blame = show_git_blame
+    }
+
+    pub(crate) fn logical_order_rendering(&self) -> bool {
+        self.logical_order_rendering
+    }
+
+    pub(crate) fn set_logical_order_rendering(&mut self, logical_order_rendering: bool) {
+        self.logical_order_rendering = logical_order_rendering
+    }
+
+    pub(crate) fn wrap_selection_on_type(&self) -> bool {
+        self.wrap_selection_on_type
+    }
+
+    pub(crate) fn set_wrap_selection_on_type(&mut self, wrap_selection_on_type: bool) {
+        self.wrap_selection_on_type = wrap_selection_on_type
+    }
+
+    pub(crate) fn recording_macro(&self) -> Option<char> {
+        self.recording_macro
+    }
+
+    pub(crate) fn set_recording_macro(&mut self, recording_macro: Option<char>) {
+        self.recording_macro = recording_macro
+    }
+
+    pub(crate) fn spell_checker(&self) -> Option<&SpellChecker> {
+        self.spell_checker.as_ref()
+    }
+
+    pub(crate) fn set_spell_checker(&mut self, spell_checker: Option<SpellChecker>) {
+        self.spell_checker = spell_checker
+    }
+
+    pub(crate) fn execution_line(&self, path: &CanonicalizedPath) -> Option<usize> {
+        self.execution_location
+            .as_ref()
+            .filter(|(execution_path, _)| execution_path == path)
+            .map(|(_, line)| *line)
+    }
+
+    pub(crate) fn set_execution_location(
+        &mut self,
+        execution_location: Option<(CanonicalizedPath, usize)>,
+    ) {
+        self.execution_location = execution_location
+    }
+
+    pub(crate) fn add_to_dictionary(&mut self, word: &str) -> anyhow::Result<()> {
+        self.spell_checker
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("Spell checking is disabled"))?
+            .add_word(word)
+    }
 }
 
 #[derive(Default)]
@@ -314,6 +426,38 @@ impl LocalSearchConfigMode {
     }
 }
 
+impl LocalSearchConfigMode {
+    /// Smart case: a case-insensitive regex search becomes case-sensitive
+    /// if `search` contains any uppercase letter.
+    pub(crate) fn smart_case(self, search: &str) -> Self {
+        match self {
+            LocalSearchConfigMode::Regex(regex)
+                if !regex.case_sensitive && contains_uppercase(search, regex.escaped) =>
+            {
+                LocalSearchConfigMode::Regex(RegexConfig {
+                    case_sensitive: true,
+                    ..regex
+                })
+            }
+            mode => mode,
+        }
+    }
+}
+
+/// Characters escaped by backslash are ignored for unescaped pattern,
+/// so that character classes like `\W` do not count as uppercase letters.
+fn contains_uppercase(search: &str, escaped: bool) -> bool {
+    let mut chars = search.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' && !escaped {
+            chars.next();
+        } else if c.is_uppercase() {
+            return true;
+        }
+    }
+    false
+}
+
 impl Default for LocalSearchConfigMode {
     fn default() -> Self {
         Self::Regex(Default::default())
@@ -393,3 +537,31 @@ impl LocalSearchConfig {
         self.mode == LocalSearchConfigMode::AstGrep
     }
 }
+
+#[cfg(test)]
+mod test_context {
+    use crate::list::grep::RegexConfig;
+
+    use super::LocalSearchConfigMode;
+
+    #[test]
+    fn smart_case() {
+        let mode = LocalSearchConfigMode::Regex(RegexConfig {
+            escaped: false,
+            ..Default::default()
+        });
+        let case_sensitive = LocalSearchConfigMode::Regex(RegexConfig {
+            escaped: false,
+            case_sensitive: true,
+            ..Default::default()
+        });
+        assert_eq!(mode.smart_case("hello"), mode);
+        assert_eq!(mode.smart_case("Hello"), case_sensitive);
+        // Escaped characters are not considered
+        assert_eq!(mode.smart_case(r"\W+"), mode);
+        assert_eq!(
+            LocalSearchConfigMode::AstGrep.smart_case("Hello"),
+            LocalSearchConfigMode::AstGrep
+        );
+    }
+}
@@ -0,0 +1,139 @@
+//! The connection to a debug adapter, which speaks the Debug Adapter Protocol over the stdin and
+//! stdout of its process, see https://microsoft.github.io/debug-adapter-protocol/specification.
+//!
+//! The messages are framed by a `Content-Length` header, like the ones of LSP,
+//! see `crate::lsp::framing`.
+
+use std::{
+    io::{BufReader, Write},
+    path::Path,
+    process::{Child, Stdio},
+    sync::mpsc::Sender,
+};
+
+use anyhow::Context;
+
+use crate::{
+    app::AppMessage,
+    lsp::framing::{read_message, write_message},
+};
+
+pub(crate) struct DapClient {
+    writer: Box<dyn Write + Send>,
+    /// `None` in tests
+    child: Option<Child>,
+    next_seq: u64,
+}
+
+impl DapClient {
+    /// Each message of the adapter is sent as `AppMessage::DebugAdapterMessage`,
+    /// followed by `AppMessage::DebugAdapterExited` once its stdout is closed.
+    pub(crate) fn spawn(
+        session_id: usize,
+        command: &str,
+        args: &[String],
+        working_directory: &Path,
+        sender: Sender<AppMessage>,
+    ) -> anyhow::Result<DapClient> {
+        let mut child = std::process::Command::new(command)
+            .args(args)
+            .current_dir(working_directory)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .with_context(|| format!("Unable to start the debug adapter `{command}`"))?;
+        let stdin = child
+            .stdin
+            .take()
+            .ok_or_else(|| anyhow::anyhow!("Failed to open the stdin of `{command}`"))?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| anyhow::anyhow!("Failed to open the stdout of `{command}`"))?;
+        std::thread::spawn(move || {
+            let mut reader = BufReader::new(stdout);
+            loop {
+                match read_message(&mut reader) {
+                    Ok(Some(message)) => {
+                        if sender
+                            .send(AppMessage::DebugAdapterMessage {
+                                session_id,
+                                message,
+                            })
+                            .is_err()
+                        {
+                            return;
+                        }
+                    }
+                    Ok(None) => break,
+                    Err(error) => {
+                        log::error!("[DapClient] Error reading message: {:?}", error);
+                        break;
+                    }
+                }
+            }
+            let _ = sender.send(AppMessage::DebugAdapterExited { session_id });
+        });
+        Ok(DapClient {
+            writer: Box::new(stdin),
+            child: Some(child),
+            next_seq: 1,
+        })
+    }
+
+    #[cfg(test)]
+    pub(crate) fn from_writer(writer: Box<dyn Write + Send>) -> DapClient {
+        DapClient {
+            writer,
+            child: None,
+            next_seq: 1,
+        }
+    }
+
+    /// Returns the sequence number of the request, which its response refers to.
+    pub(crate) fn send_request(
+        &mut self,
+        command: &str,
+        arguments: serde_json::Value,
+    ) -> anyhow::Result<u64> {
+        let seq = self.next_seq();
+        self.send(serde_json::json!({
+            "seq": seq,
+            "type": "request",
+            "command": command,
+            "arguments": arguments,
+        }))?;
+        Ok(seq)
+    }
+
+    /// Answers the reverse requests of the adapter (e.g. `runInTerminal`), which are not supported.
+    pub(crate) fn reject_request(&mut self, request_seq: u64, command: &str) -> anyhow::Result<()> {
+        let seq = self.next_seq();
+        self.send(serde_json::json!({
+            "seq": seq,
+            "type": "response",
+            "request_seq": request_seq,
+            "success": false,
+            "command": command,
+            "message": "Not supported",
+        }))
+    }
+
+    pub(crate) fn kill(mut self) {
+        if let Some(mut child) = self.child.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+
+    fn next_seq(&mut self) -> u64 {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        seq
+    }
+
+    fn send(&mut self, message: serde_json::Value) -> anyhow::Result<()> {
+        write_message(&mut self.writer, &message)
+    }
+}
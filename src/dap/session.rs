@@ -0,0 +1,660 @@
+//! A debugging session, which drives a debug adapter (see `client`) for a debug configuration
+//! of `[debug]` of the config:
+//!
+//! ```toml
+//! [debug.ki]
+//! command = "lldb-dap"
+//! request = "launch" # or "attach"
+//! arguments = { program = "target/debug/ki", cwd = "." }
+//! ```
+//!
+//! The session is started by `initialize`, followed by `launch` (or `attach`) with the
+//! `arguments` of the configuration. Once the adapter is initialized, the breakpoints of each file
+//! are set, and then the configuration is done. Whenever the debuggee stops, the stack trace of
+//! the stopped thread is requested, followed by the variables of the scopes of the top frame,
+//! and the values of the watch expressions.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+    sync::mpsc::Sender,
+};
+
+use serde_json::json;
+
+use crate::{app::AppMessage, config::DebugConfig};
+
+use super::client::DapClient;
+
+/// The stack frames of the stopped thread that are displayed at most.
+const MAX_STACK_FRAMES: usize = 20;
+
+/// The lines of the output of the debuggee that are displayed at most.
+const MAX_OUTPUT_LINES: usize = 100;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum DebugCommand {
+    Continue,
+    StepOver,
+    StepIn,
+    StepOut,
+    Pause,
+    /// Terminates the debuggee, and then the adapter
+    Stop,
+}
+
+/// What the app should do after a message of the adapter is handled.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum DebugSessionUpdate {
+    /// The displayed state has changed
+    Refresh,
+    /// The debuggee is stopped at the line (0-based) of the file, if it is known
+    Stopped(Option<(PathBuf, usize)>),
+    Continued,
+    /// The adapter should be killed
+    Terminated,
+    Error(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum DebugState {
+    Initializing,
+    Running,
+    Stopped { reason: String },
+    Terminated,
+}
+
+struct StackFrame {
+    id: u64,
+    name: String,
+    /// The line is 0-based
+    location: Option<(PathBuf, usize)>,
+}
+
+struct Scope {
+    name: String,
+    /// The names and the values
+    variables: Vec<(String, String)>,
+}
+
+struct Watch {
+    expression: String,
+    /// Either the result or the error of the evaluation in the top frame,
+    /// which is `None` until the debuggee is stopped
+    value: Option<String>,
+}
+
+/// The requests whose responses need to be told apart by their requests.
+enum PendingRequest {
+    Variables { scope: usize },
+    Evaluate { watch: usize },
+}
+
+pub(crate) struct DebugSession {
+    /// Distinguishes the messages of this session from the ones of the sessions stopped before it
+    id: usize,
+    name: String,
+    config: DebugConfig,
+    client: DapClient,
+    working_directory: PathBuf,
+    /// By the absolute paths of the files, where the lines are 0-based
+    breakpoints: BTreeMap<PathBuf, Vec<usize>>,
+    state: DebugState,
+    /// The thread that is stopped, or that the commands are sent to
+    thread_id: Option<u64>,
+    /// The top frame is the first one
+    stack_frames: Vec<StackFrame>,
+    scopes: Vec<Scope>,
+    watches: Vec<Watch>,
+    output: Vec<String>,
+    pending_requests: HashMap<u64, PendingRequest>,
+}
+
+impl DebugSession {
+    pub(crate) fn start(
+        id: usize,
+        name: String,
+        config: DebugConfig,
+        breakpoints: BTreeMap<PathBuf, Vec<usize>>,
+        working_directory: &Path,
+        sender: Sender<AppMessage>,
+    ) -> anyhow::Result<DebugSession> {
+        let client =
+            DapClient::spawn(id, &config.command, &config.args, working_directory, sender)?;
+        DebugSession::new(id, name, config, breakpoints, working_directory, client)
+    }
+
+    fn new(
+        id: usize,
+        name: String,
+        config: DebugConfig,
+        breakpoints: BTreeMap<PathBuf, Vec<usize>>,
+        working_directory: &Path,
+        mut client: DapClient,
+    ) -> anyhow::Result<DebugSession> {
+        client.send_request(
+            "initialize",
+            json!({
+                "clientID": "ki",
+                "clientName": "Ki",
+                "adapterID": name,
+                "pathFormat": "path",
+                "linesStartAt1": true,
+                "columnsStartAt1": true,
+            }),
+        )?;
+        Ok(DebugSession {
+            id,
+            name,
+            config,
+            client,
+            working_directory: working_directory.to_path_buf(),
+            breakpoints,
+            state: DebugState::Initializing,
+            thread_id: None,
+            stack_frames: Vec::new(),
+            scopes: Vec::new(),
+            watches: Vec::new(),
+            output: Vec::new(),
+            pending_requests: HashMap::new(),
+        })
+    }
+
+    pub(crate) fn id(&self) -> usize {
+        self.id
+    }
+
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The lines of a file replace its previous breakpoints, so that they can be removed.
+    pub(crate) fn set_breakpoints(
+        &mut self,
+        path: PathBuf,
+        lines: Vec<usize>,
+    ) -> anyhow::Result<()> {
+        // The breakpoints are set once the adapter is initialized
+        if self.state != DebugState::Initializing {
+            self.send_breakpoints(&path, &lines)?;
+        }
+        self.breakpoints.insert(path, lines);
+        Ok(())
+    }
+
+    pub(crate) fn add_watch(&mut self, expression: String) -> anyhow::Result<()> {
+        self.watches.push(Watch {
+            expression,
+            value: None,
+        });
+        if let Some(frame_id) = self.top_frame_id() {
+            self.evaluate_watch(self.watches.len() - 1, frame_id)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn execute(&mut self, command: DebugCommand) -> anyhow::Result<()> {
+        let name = match command {
+            DebugCommand::Continue => "continue",
+            DebugCommand::StepOver => "next",
+            DebugCommand::StepIn => "stepIn",
+            DebugCommand::StepOut => "stepOut",
+            DebugCommand::Pause => {
+                let thread_id = self
+                    .thread_id
+                    .ok_or_else(|| anyhow::anyhow!("No thread of the debuggee is started yet"))?;
+                self.client
+                    .send_request("pause", json!({ "threadId": thread_id }))?;
+                return Ok(());
+            }
+            DebugCommand::Stop => {
+                self.client
+                    .send_request("disconnect", json!({ "terminateDebuggee": true }))?;
+                return Ok(());
+            }
+        };
+        let (DebugState::Stopped { .. }, Some(thread_id)) = (&self.state, self.thread_id) else {
+            return Err(anyhow::anyhow!("The debuggee is not stopped"));
+        };
+        self.client
+            .send_request(name, json!({ "threadId": thread_id }))?;
+        self.resumed();
+        Ok(())
+    }
+
+    pub(crate) fn handle_message(
+        &mut self,
+        message: serde_json::Value,
+    ) -> anyhow::Result<Option<DebugSessionUpdate>> {
+        match message["type"].as_str() {
+            Some("response") => self.handle_response(message),
+            Some("event") => self.handle_event(message),
+            Some("request") => {
+                self.client.reject_request(
+                    message["seq"].as_u64().unwrap_or_default(),
+                    message["command"].as_str().unwrap_or_default(),
+                )?;
+                Ok(None)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    pub(crate) fn kill(self) {
+        self.client.kill()
+    }
+
+    /// The state, followed by the call stack, the variables, the watches, and the output.
+    pub(crate) fn display(&self) -> String {
+        let state = match &self.state {
+            DebugState::Initializing => "Initializing".to_string(),
+            DebugState::Running => "Running".to_string(),
+            DebugState::Stopped { reason } => format!("Stopped ({reason})"),
+            DebugState::Terminated => "Terminated".to_string(),
+        };
+        let mut lines = vec![state];
+        if !self.stack_frames.is_empty() {
+            lines.push(String::new());
+            lines.push("Call stack".to_string());
+            lines.extend(self.stack_frames.iter().map(|frame| match &frame.location {
+                Some((path, line)) => format!(
+                    "  {} ({}:{})",
+                    frame.name,
+                    self.display_path(path),
+                    line + 1
+                ),
+                None => format!("  {}", frame.name),
+            }));
+        }
+        if !self.scopes.is_empty() {
+            lines.push(String::new());
+            lines.push("Variables".to_string());
+            for scope in &self.scopes {
+                lines.push(format!("  {}", scope.name));
+                lines.extend(
+                    scope
+                        .variables
+                        .iter()
+                        .map(|(name, value)| format!("    {name} = {value}")),
+                )
+            }
+        }
+        if !self.watches.is_empty() {
+            lines.push(String::new());
+            lines.push("Watches".to_string());
+            lines.extend(self.watches.iter().map(|watch| match &watch.value {
+                Some(value) => format!("  {} = {value}", watch.expression),
+                None => format!("  {}", watch.expression),
+            }));
+        }
+        if !self.output.is_empty() {
+            lines.push(String::new());
+            lines.push("Output".to_string());
+            lines.extend(self.output.iter().map(|line| format!("  {line}")));
+        }
+        lines.join("\n")
+    }
+
+    fn display_path<'a>(&self, path: &'a Path) -> std::path::Display<'a> {
+        path.strip_prefix(&self.working_directory)
+            .unwrap_or(path)
+            .display()
+    }
+
+    fn handle_response(
+        &mut self,
+        message: serde_json::Value,
+    ) -> anyhow::Result<Option<DebugSessionUpdate>> {
+        let pending_request = message["request_seq"]
+            .as_u64()
+            .and_then(|request_seq| self.pending_requests.remove(&request_seq));
+        let command = message["command"].as_str().unwrap_or_default();
+        let body = &message["body"];
+        if !message["success"].as_bool().unwrap_or_default() {
+            let error = body["error"]["format"]
+                .as_str()
+                .or(message["message"].as_str())
+                .unwrap_or("Unknown error")
+                .to_string();
+            if let Some(PendingRequest::Evaluate { watch }) = pending_request {
+                if let Some(watch) = self.watches.get_mut(watch) {
+                    watch.value = Some(error)
+                }
+                return Ok(Some(DebugSessionUpdate::Refresh));
+            }
+            return Ok(Some(DebugSessionUpdate::Error(format!(
+                "{command}: {error}"
+            ))));
+        }
+        match (command, pending_request) {
+            ("initialize", _) => {
+                self.client
+                    .send_request(self.config.request.command(), self.config.arguments.clone())?;
+                Ok(None)
+            }
+            ("stackTrace", _) => {
+                self.stack_frames = body["stackFrames"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .map(|frame| StackFrame {
+                        id: frame["id"].as_u64().unwrap_or_default(),
+                        name: frame["name"].as_str().unwrap_or_default().to_string(),
+                        location: frame["source"]["path"].as_str().map(|path| {
+                            let line = frame["line"].as_u64().unwrap_or(1) as usize;
+                            (PathBuf::from(path), line.saturating_sub(1))
+                        }),
+                    })
+                    .collect();
+                if let Some(frame_id) = self.top_frame_id() {
+                    self.client
+                        .send_request("scopes", json!({ "frameId": frame_id }))?;
+                    for watch in 0..self.watches.len() {
+                        self.evaluate_watch(watch, frame_id)?
+                    }
+                }
+                let location = self
+                    .stack_frames
+                    .first()
+                    .and_then(|frame| frame.location.clone());
+                Ok(Some(DebugSessionUpdate::Stopped(location)))
+            }
+            ("scopes", _) => {
+                let scopes = body["scopes"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    // The expensive scopes (e.g. the globals) are not fetched automatically
+                    .filter(|scope| !scope["expensive"].as_bool().unwrap_or_default())
+                    .collect::<Vec<_>>();
+                self.scopes = Vec::new();
+                for (index, scope) in scopes.into_iter().enumerate() {
+                    self.scopes.push(Scope {
+                        name: scope["name"].as_str().unwrap_or_default().to_string(),
+                        variables: Vec::new(),
+                    });
+                    let seq = self.client.send_request(
+                        "variables",
+                        json!({ "variablesReference": scope["variablesReference"] }),
+                    )?;
+                    self.pending_requests
+                        .insert(seq, PendingRequest::Variables { scope: index });
+                }
+                Ok(Some(DebugSessionUpdate::Refresh))
+            }
+            ("variables", Some(PendingRequest::Variables { scope })) => {
+                if let Some(scope) = self.scopes.get_mut(scope) {
+                    scope.variables = body["variables"]
+                        .as_array()
+                        .into_iter()
+                        .flatten()
+                        .map(|variable| {
+                            (
+                                variable["name"].as_str().unwrap_or_default().to_string(),
+                                variable["value"].as_str().unwrap_or_default().to_string(),
+                            )
+                        })
+                        .collect();
+                }
+                Ok(Some(DebugSessionUpdate::Refresh))
+            }
+            ("evaluate", Some(PendingRequest::Evaluate { watch })) => {
+                if let Some(watch) = self.watches.get_mut(watch) {
+                    watch.value = Some(body["result"].as_str().unwrap_or_default().to_string())
+                }
+                Ok(Some(DebugSessionUpdate::Refresh))
+            }
+            ("disconnect", _) => {
+                self.state = DebugState::Terminated;
+                Ok(Some(DebugSessionUpdate::Terminated))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn handle_event(
+        &mut self,
+        message: serde_json::Value,
+    ) -> anyhow::Result<Option<DebugSessionUpdate>> {
+        let body = &message["body"];
+        match message["event"].as_str().unwrap_or_default() {
+            "initialized" => {
+                self.state = DebugState::Running;
+                for (path, lines) in self.breakpoints.clone() {
+                    self.send_breakpoints(&path, &lines)?
+                }
+                self.client.send_request("configurationDone", json!({}))?;
+                Ok(Some(DebugSessionUpdate::Refresh))
+            }
+            "stopped" => {
+                self.thread_id = body["threadId"].as_u64().or(self.thread_id);
+                self.state = DebugState::Stopped {
+                    reason: body["reason"].as_str().unwrap_or("paused").to_string(),
+                };
+                if let Some(thread_id) = self.thread_id {
+                    self.client.send_request(
+                        "stackTrace",
+                        json!({
+                            "threadId": thread_id,
+                            "startFrame": 0,
+                            "levels": MAX_STACK_FRAMES,
+                        }),
+                    )?;
+                }
+                Ok(Some(DebugSessionUpdate::Refresh))
+            }
+            "continued" => {
+                self.resumed();
+                Ok(Some(DebugSessionUpdate::Continued))
+            }
+            "thread" => {
+                if self.thread_id.is_none() && body["reason"].as_str() == Some("started") {
+                    self.thread_id = body["threadId"].as_u64()
+                }
+                Ok(None)
+            }
+            "output" => {
+                if body["category"].as_str() == Some("telemetry") {
+                    return Ok(None);
+                }
+                self.output.extend(
+                    body["output"]
+                        .as_str()
+                        .unwrap_or_default()
+                        .lines()
+                        .map(|line| line.to_string()),
+                );
+                let excess = self.output.len().saturating_sub(MAX_OUTPUT_LINES);
+                self.output.drain(..excess);
+                Ok(Some(DebugSessionUpdate::Refresh))
+            }
+            "exited" => {
+                if let Some(exit_code) = body["exitCode"].as_i64() {
+                    self.output.push(format!("Exited with {exit_code}"))
+                }
+                Ok(Some(DebugSessionUpdate::Refresh))
+            }
+            "terminated" => {
+                self.resumed();
+                self.state = DebugState::Terminated;
+                Ok(Some(DebugSessionUpdate::Terminated))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// The stack, the variables and the values of the watches are only valid while stopped.
+    fn resumed(&mut self) {
+        self.state = DebugState::Running;
+        self.stack_frames.clear();
+        self.scopes.clear();
+        for watch in &mut self.watches {
+            watch.value = None
+        }
+        self.pending_requests.clear();
+    }
+
+    fn top_frame_id(&self) -> Option<u64> {
+        self.stack_frames.first().map(|frame| frame.id)
+    }
+
+    fn evaluate_watch(&mut self, watch: usize, frame_id: u64) -> anyhow::Result<()> {
+        let Some(expression) = self
+            .watches
+            .get(watch)
+            .map(|watch| watch.expression.clone())
+        else {
+            return Ok(());
+        };
+        let seq = self.client.send_request(
+            "evaluate",
+            json!({
+                "expression": expression,
+                "frameId": frame_id,
+                "context": "watch",
+            }),
+        )?;
+        self.pending_requests
+            .insert(seq, PendingRequest::Evaluate { watch });
+        Ok(())
+    }
+
+    fn send_breakpoints(&mut self, path: &Path, lines: &[usize]) -> anyhow::Result<()> {
+        self.client.send_request(
+            "setBreakpoints",
+            json!({
+                "source": { "path": path },
+                "breakpoints": lines
+                    .iter()
+                    .map(|line| json!({ "line": line + 1 }))
+                    .collect::<Vec<_>>(),
+            }),
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test_session {
+    use super::*;
+
+    use crate::config::DebugRequest;
+
+    fn session() -> anyhow::Result<DebugSession> {
+        DebugSession::new(
+            1,
+            "ki".to_string(),
+            DebugConfig {
+                command: "lldb-dap".to_string(),
+                args: Vec::new(),
+                request: DebugRequest::Launch,
+                arguments: json!({ "program": "target/debug/ki" }),
+            },
+            BTreeMap::new(),
+            Path::new("/project"),
+            DapClient::from_writer(Box::new(std::io::sink())),
+        )
+    }
+
+    #[test]
+    fn stopped_state_is_displayed() -> anyhow::Result<()> {
+        let mut session = session()?;
+        session.add_watch("x + 1".to_string())?;
+        for message in [
+            json!({"type": "response", "request_seq": 1, "command": "initialize", "success": true}),
+            json!({"type": "event", "event": "initialized"}),
+            json!({"type": "event", "event": "output", "body": {"output": "hello\n"}}),
+        ] {
+            session.handle_message(message)?;
+        }
+        assert_eq!(
+            session.display(),
+            "Running\n\nWatches\n  x + 1\n\nOutput\n  hello"
+        );
+
+        assert_eq!(
+            session.handle_message(json!({
+                "type": "event",
+                "event": "stopped",
+                "body": {"reason": "breakpoint", "threadId": 7}
+            }))?,
+            Some(DebugSessionUpdate::Refresh)
+        );
+        assert_eq!(
+            session.handle_message(json!({
+                "type": "response",
+                "request_seq": 4,
+                "command": "stackTrace",
+                "success": true,
+                "body": {"stackFrames": [
+                    {
+                        "id": 3,
+                        "name": "main",
+                        "source": {"path": "/project/src/main.rs"},
+                        "line": 4
+                    },
+                    {"id": 2, "name": "start"}
+                ]}
+            }))?,
+            Some(DebugSessionUpdate::Stopped(Some((
+                PathBuf::from("/project/src/main.rs"),
+                3
+            ))))
+        );
+        // The scopes are requested (5), and then the watch is evaluated (6)
+        for message in [
+            json!({
+                "type": "response",
+                "request_seq": 6,
+                "command": "evaluate",
+                "success": true,
+                "body": {"result": "2"}
+            }),
+            json!({
+                "type": "response",
+                "request_seq": 5,
+                "command": "scopes",
+                "success": true,
+                "body": {"scopes": [
+                    {"name": "Locals", "variablesReference": 10},
+                    {"name": "Globals", "variablesReference": 11, "expensive": true}
+                ]}
+            }),
+            json!({
+                "type": "response",
+                "request_seq": 7,
+                "command": "variables",
+                "success": true,
+                "body": {"variables": [{"name": "x", "value": "1"}]}
+            }),
+        ] {
+            session.handle_message(message)?;
+        }
+        assert_eq!(
+            session.display(),
+            "Stopped (breakpoint)
+
+Call stack
+  main (src/main.rs:4)
+  start
+
+Variables
+  Locals
+    x = 1
+
+Watches
+  x + 1 = 2
+
+Output
+  hello"
+        );
+
+        session.execute(DebugCommand::StepOver)?;
+        assert_eq!(
+            session.display(),
+            "Running\n\nWatches\n  x + 1\n\nOutput\n  hello"
+        );
+        assert!(session.execute(DebugCommand::StepOver).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,509 @@
+//! Support of [EditorConfig](https://editorconfig.org), where the `.editorconfig` files
+//! of the directory of a file and of its ancestors are applied to the buffer of the file,
+//! until a file with `root = true` is found.
+//!
+//! The settings of a nearer file take precedence, and so do the later sections of a file.
+//! They override the settings of the config file (see `crate::config`).
+
+use std::path::Path;
+
+use globset::GlobBuilder;
+
+use crate::config::Indentation;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum EndOfLine {
+    Lf,
+    Crlf,
+    Cr,
+}
+
+impl EndOfLine {
+    fn as_str(&self) -> &'static str {
+        match self {
+            EndOfLine::Lf => "\n",
+            EndOfLine::Crlf => "\r\n",
+            EndOfLine::Cr => "\r",
+        }
+    }
+
+    pub(crate) fn display(&self) -> &'static str {
+        match self {
+            EndOfLine::Lf => "LF",
+            EndOfLine::Crlf => "CRLF",
+            EndOfLine::Cr => "CR",
+        }
+    }
+
+    /// The most common line ending of `content`, or `None` if it has no line breaks.
+    ///
+    /// Ties are broken in favour of LF.
+    pub(crate) fn detect(content: &str) -> Option<EndOfLine> {
+        let crlf = content.matches("\r\n").count();
+        let lf = content.matches('\n').count() - crlf;
+        let cr = content.matches('\r').count() - crlf;
+        [
+            (EndOfLine::Lf, lf),
+            (EndOfLine::Crlf, crlf),
+            (EndOfLine::Cr, cr),
+        ]
+        .into_iter()
+        .filter(|(_, count)| *count > 0)
+        .rev()
+        .max_by_key(|(_, count)| *count)
+        .map(|(end_of_line, _)| end_of_line)
+    }
+
+    /// Replaces every line ending of `content` with this line ending.
+    pub(crate) fn convert(&self, content: &str) -> String {
+        content
+            .replace("\r\n", "\n")
+            .replace('\r', "\n")
+            .replace('\n', self.as_str())
+    }
+}
+
+/// The encoding of a file, which is decoded into the UTF-8 of the buffer when the file is read,
+/// and encoded again when the buffer is written.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Charset {
+    Utf8,
+    Utf8Bom,
+    Latin1,
+    Utf16Be,
+    Utf16Le,
+    /// Not a value of the EditorConfig specification, which is accepted as `shift_jis`
+    ShiftJis,
+}
+
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+
+impl Charset {
+    /// Detects the charset from the byte order mark, otherwise the charset is the first of UTF-8
+    /// and Shift-JIS that decodes the bytes without errors, or else Latin-1,
+    /// which decodes any bytes.
+    ///
+    /// The detection can be overridden by the `charset` of the EditorConfig.
+    pub(crate) fn detect(bytes: &[u8]) -> Charset {
+        if bytes.starts_with(UTF8_BOM) {
+            Charset::Utf8Bom
+        } else if bytes.starts_with(&[0xFE, 0xFF]) {
+            Charset::Utf16Be
+        } else if bytes.starts_with(&[0xFF, 0xFE]) {
+            Charset::Utf16Le
+        } else if std::str::from_utf8(bytes).is_ok() {
+            Charset::Utf8
+        } else if encoding_rs::SHIFT_JIS
+            .decode_without_bom_handling_and_without_replacement(bytes)
+            .is_some()
+        {
+            Charset::ShiftJis
+        } else {
+            Charset::Latin1
+        }
+    }
+
+    pub(crate) fn display(&self) -> &'static str {
+        match self {
+            Charset::Utf8 => "UTF-8",
+            Charset::Utf8Bom => "UTF-8 with BOM",
+            Charset::Latin1 => "Latin-1",
+            Charset::Utf16Be => "UTF-16 BE",
+            Charset::Utf16Le => "UTF-16 LE",
+            Charset::ShiftJis => "Shift-JIS",
+        }
+    }
+
+    /// The invalid sequences are replaced with U+FFFD, in which case the returned boolean is true.
+    pub(crate) fn decode(&self, bytes: &[u8]) -> (String, bool) {
+        match self {
+            Charset::Utf8 => decode_utf8(bytes),
+            Charset::Utf8Bom => decode_utf8(bytes.strip_prefix(UTF8_BOM).unwrap_or(bytes)),
+            Charset::Latin1 => (bytes.iter().copied().map(char::from).collect(), false),
+            Charset::Utf16Be | Charset::Utf16Le => {
+                let units = bytes.chunks(2).map(|pair| match (pair, self) {
+                    ([first, second], Charset::Utf16Be) => u16::from_be_bytes([*first, *second]),
+                    ([first, second], _) => u16::from_le_bytes([*first, *second]),
+                    // The trailing byte of an odd number of bytes is invalid
+                    _ => 0xDC00,
+                });
+                let mut lossy = false;
+                let text = char::decode_utf16(units)
+                    .map(|result| {
+                        result.unwrap_or_else(|_| {
+                            lossy = true;
+                            char::REPLACEMENT_CHARACTER
+                        })
+                    })
+                    .collect::<String>();
+                (text.trim_start_matches('\u{FEFF}').to_string(), lossy)
+            }
+            Charset::ShiftJis => {
+                let (text, lossy) = encoding_rs::SHIFT_JIS.decode_without_bom_handling(bytes);
+                (text.into_owned(), lossy)
+            }
+        }
+    }
+
+    pub(crate) fn encode(&self, text: &str) -> anyhow::Result<Vec<u8>> {
+        let unencodable = |char: char| {
+            anyhow::anyhow!(
+                "The character {char:?} cannot be encoded in {}",
+                self.display()
+            )
+        };
+        Ok(match self {
+            Charset::Utf8 => text.as_bytes().to_vec(),
+            Charset::Utf8Bom => UTF8_BOM.iter().chain(text.as_bytes()).copied().collect(),
+            Charset::Latin1 => text
+                .chars()
+                .map(|char| u8::try_from(char).map_err(|_| unencodable(char)))
+                .collect::<anyhow::Result<_>>()?,
+            Charset::Utf16Be => text.encode_utf16().flat_map(u16::to_be_bytes).collect(),
+            Charset::Utf16Le => text.encode_utf16().flat_map(u16::to_le_bytes).collect(),
+            Charset::ShiftJis => {
+                let mut encoder = encoding_rs::SHIFT_JIS.new_encoder();
+                let mut bytes = Vec::with_capacity(text.len());
+                let mut remaining = text;
+                loop {
+                    let capacity = encoder
+                        .max_buffer_length_from_utf8_without_replacement(remaining.len())
+                        .unwrap_or(remaining.len() * 2);
+                    bytes.reserve(capacity);
+                    let (result, read) = encoder
+                        .encode_from_utf8_to_vec_without_replacement(remaining, &mut bytes, true);
+                    remaining = &remaining[read..];
+                    match result {
+                        encoding_rs::EncoderResult::InputEmpty => break bytes,
+                        encoding_rs::EncoderResult::Unmappable(char) => {
+                            return Err(unencodable(char))
+                        }
+                        encoding_rs::EncoderResult::OutputFull => {}
+                    }
+                }
+            }
+        })
+    }
+}
+
+fn decode_utf8(bytes: &[u8]) -> (String, bool) {
+    match String::from_utf8_lossy(bytes) {
+        std::borrow::Cow::Borrowed(text) => (text.to_string(), false),
+        std::borrow::Cow::Owned(text) => (text, true),
+    }
+}
+
+/// The settings that are left out are `None`, so that the defaults are used.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct EditorConfig {
+    /// True if the indentation is `space`, false if it is `tab`
+    expand_tab: Option<bool>,
+    /// `None` if it is `tab`, which means the indentation width is `tab_width`
+    indent_size: Option<usize>,
+    tab_width: Option<usize>,
+    pub(crate) end_of_line: Option<EndOfLine>,
+    pub(crate) charset: Option<Charset>,
+    pub(crate) trim_trailing_whitespace: Option<bool>,
+    /// False means the final newline is removed
+    pub(crate) insert_final_newline: Option<bool>,
+}
+
+impl EditorConfig {
+    pub(crate) fn for_path(path: &Path) -> anyhow::Result<EditorConfig> {
+        let mut files = Vec::new();
+        for directory in path.ancestors().skip(1) {
+            let file = directory.join(".editorconfig");
+            if file.is_file() {
+                let content = std::fs::read_to_string(&file)?;
+                let is_root = is_root(&content);
+                files.push((directory, content));
+                if is_root {
+                    break;
+                }
+            }
+        }
+        let mut config = EditorConfig::default();
+        for (directory, content) in files.into_iter().rev() {
+            if let Ok(relative) = path.strip_prefix(directory) {
+                config.apply(&content, relative);
+            }
+        }
+        Ok(config)
+    }
+
+    /// Applies the properties of the sections of `content` that match `path`,
+    /// which is relative to the directory of the `.editorconfig` file.
+    ///
+    /// Invalid lines and unknown properties are ignored as required by the specification.
+    fn apply(&mut self, content: &str, path: &Path) {
+        let mut is_matching = false;
+        for line in content.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+            if let Some(section) = line.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+                is_matching = matches(section, path);
+            } else if let Some((key, value)) = line.split_once('=') {
+                if is_matching {
+                    self.set(&key.trim().to_lowercase(), &value.trim().to_lowercase())
+                }
+            }
+        }
+    }
+
+    /// Invalid values, including `unset`, remove the effect of the previous sections.
+    fn set(&mut self, key: &str, value: &str) {
+        let boolean = || match value {
+            "true" => Some(true),
+            "false" => Some(false),
+            _ => None,
+        };
+        match key {
+            "indent_style" => {
+                self.expand_tab = match value {
+                    "space" => Some(true),
+                    "tab" => Some(false),
+                    _ => None,
+                }
+            }
+            "indent_size" => self.indent_size = value.parse().ok(),
+            "tab_width" => self.tab_width = value.parse().ok(),
+            "end_of_line" => {
+                self.end_of_line = match value {
+                    "lf" => Some(EndOfLine::Lf),
+                    "crlf" => Some(EndOfLine::Crlf),
+                    "cr" => Some(EndOfLine::Cr),
+                    _ => None,
+                }
+            }
+            "charset" => {
+                self.charset = match value {
+                    "utf-8" => Some(Charset::Utf8),
+                    "utf-8-bom" => Some(Charset::Utf8Bom),
+                    "latin1" => Some(Charset::Latin1),
+                    "utf-16be" => Some(Charset::Utf16Be),
+                    "utf-16le" => Some(Charset::Utf16Le),
+                    "shift_jis" => Some(Charset::ShiftJis),
+                    _ => None,
+                }
+            }
+            "trim_trailing_whitespace" => self.trim_trailing_whitespace = boolean(),
+            "insert_final_newline" => self.insert_final_newline = boolean(),
+            _ => {}
+        }
+    }
+
+    /// The settings that are left out are taken from `indentation`.
+    pub(crate) fn indentation(&self, indentation: Indentation) -> Indentation {
+        Indentation {
+            width: self
+                .indent_size
+                .or(self.tab_width)
+                .unwrap_or(indentation.width),
+            expand_tab: self.expand_tab.unwrap_or(indentation.expand_tab),
+        }
+    }
+
+    /// Trims the trailing whitespaces and inserts or removes the final newline,
+    /// which is applied to the buffer before it is saved.
+    ///
+    /// `insert_final_newline` and `trim_trailing_whitespace` are the settings of the config,
+    /// which only apply if the EditorConfig leaves them out.
+    pub(crate) fn format(
+        &self,
+        content: &str,
+        insert_final_newline: bool,
+        trim_trailing_whitespace: bool,
+    ) -> String {
+        let mut content = if self
+            .trim_trailing_whitespace
+            .or(trim_trailing_whitespace.then_some(true))
+            == Some(true)
+        {
+            self::trim_trailing_whitespace(content)
+        } else {
+            content.to_string()
+        };
+        match self
+            .insert_final_newline
+            .or(insert_final_newline.then_some(true))
+        {
+            Some(true) if !content.is_empty() && !content.ends_with('\n') => content.push('\n'),
+            Some(false) => {
+                let trimmed = content.trim_end_matches(['\r', '\n']).len();
+                content.truncate(trimmed)
+            }
+            _ => {}
+        }
+        content
+    }
+
+    /// The charset is detected from `bytes` if it is left out.
+    pub(crate) fn charset(&self, bytes: &[u8]) -> Charset {
+        self.charset.unwrap_or_else(|| Charset::detect(bytes))
+    }
+}
+
+/// Trims the trailing spaces and tabs of each line, where the carriage returns of CRLF line
+/// endings are kept.
+pub(crate) fn trim_trailing_whitespace(content: &str) -> String {
+    content
+        .split('\n')
+        .map(|line| match line.strip_suffix('\r') {
+            Some(line) => format!("{}\r", line.trim_end_matches([' ', '\t'])),
+            None => line.trim_end_matches([' ', '\t']).to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// `root = true` must be in the preamble, which is before the first section.
+fn is_root(content: &str) -> bool {
+    content
+        .lines()
+        .map(str::trim)
+        .take_while(|line| !line.starts_with('['))
+        .filter_map(|line| line.split_once('='))
+        .any(|(key, value)| {
+            key.trim().eq_ignore_ascii_case("root") && value.trim().eq_ignore_ascii_case("true")
+        })
+}
+
+/// A section without `/` matches the file name in any directory,
+/// otherwise it is matched against the path relative to the `.editorconfig` file.
+fn matches(section: &str, path: &Path) -> bool {
+    let pattern = if section.contains('/') {
+        section.trim_start_matches('/').to_string()
+    } else {
+        format!("**/{section}")
+    };
+    GlobBuilder::new(&pattern)
+        .literal_separator(true)
+        .build()
+        .map(|glob| glob.compile_matcher().is_match(path))
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod test_editorconfig {
+    use super::*;
+
+    #[test]
+    fn nearer_files_take_precedence() -> anyhow::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let root = temp_dir.path();
+        std::fs::create_dir_all(root.join("project/src"))?;
+        std::fs::write(
+            root.join(".editorconfig"),
+            "[*]\nindent_style = tab\ncharset = latin1",
+        )?;
+        std::fs::write(
+            root.join("project/.editorconfig"),
+            "root = true\n\n[*]\nindent_style = space\nindent_size = 2\n\n\
+             [src/*.{rs,toml}]\nindent_size = 4\nend_of_line = CRLF\n\n\
+             [*.md]\ntrim_trailing_whitespace = true",
+        )?;
+        std::fs::write(
+            root.join("project/src/.editorconfig"),
+            "[*.rs]\nend_of_line = unset\ninsert_final_newline = true",
+        )?;
+        let config = EditorConfig::for_path(&root.join("project/src/main.rs"))?;
+        assert_eq!(
+            config,
+            EditorConfig {
+                expand_tab: Some(true),
+                indent_size: Some(4),
+                insert_final_newline: Some(true),
+                ..Default::default()
+            }
+        );
+        // `src/*.rs` does not match the files of the subdirectories of `src`
+        let config = EditorConfig::for_path(&root.join("project/src/foo/main.rs"))?;
+        assert_eq!(config.indent_size, Some(2));
+        assert_eq!(config.end_of_line, None);
+        let config = EditorConfig::for_path(&root.join("project/docs/readme.md"))?;
+        assert_eq!(config.trim_trailing_whitespace, Some(true));
+        assert_eq!(
+            config.indentation(Indentation::default()),
+            Indentation {
+                width: 2,
+                expand_tab: true
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn format_on_save() {
+        let config = EditorConfig {
+            trim_trailing_whitespace: Some(true),
+            insert_final_newline: Some(true),
+            ..Default::default()
+        };
+        assert_eq!(config.format("a  \r\nb\t\nc ", false, false), "a\r\nb\nc\n");
+        let config = EditorConfig {
+            insert_final_newline: Some(false),
+            trim_trailing_whitespace: Some(false),
+            ..Default::default()
+        };
+        assert_eq!(config.format("a \n\n", true, true), "a ");
+        assert_eq!(EditorConfig::default().format("a", true, false), "a\n");
+        assert_eq!(EditorConfig::default().format("a", false, false), "a");
+        assert_eq!(EditorConfig::default().format("a \nb", false, true), "a\nb");
+    }
+
+    #[test]
+    fn detect_decode_and_encode_charsets() -> anyhow::Result<()> {
+        let shift_jis = b"\x93\xfa\x96\x7b\x8c\xea"; // 日本語
+        assert_eq!(Charset::detect(b"\xEF\xBB\xBFa"), Charset::Utf8Bom);
+        assert_eq!(Charset::detect(b"\xFF\xFEa\x00"), Charset::Utf16Le);
+        assert_eq!(Charset::detect("é".as_bytes()), Charset::Utf8);
+        assert_eq!(Charset::detect(shift_jis), Charset::ShiftJis);
+        assert_eq!(Charset::detect(b"caf\xe9 "), Charset::Latin1);
+        assert_eq!(
+            EditorConfig {
+                charset: Some(Charset::Latin1),
+                ..Default::default()
+            }
+            .charset(shift_jis),
+            Charset::Latin1
+        );
+
+        assert_eq!(
+            Charset::Utf8Bom.decode(b"\xEF\xBB\xBFa\r\n"),
+            ("a\r\n".to_string(), false)
+        );
+        assert_eq!(Charset::Utf8Bom.encode("a")?, b"\xEF\xBB\xBFa");
+        assert_eq!(
+            Charset::ShiftJis.decode(shift_jis),
+            ("日本語".to_string(), false)
+        );
+        assert_eq!(Charset::ShiftJis.encode("日本語")?, shift_jis);
+        assert!(Charset::ShiftJis.encode("😀").is_err());
+        let bytes = Charset::Utf16Le.encode("é")?;
+        assert_eq!(Charset::Utf16Le.decode(&bytes), ("é".to_string(), false));
+        assert_eq!(Charset::Latin1.decode(&Charset::Latin1.encode("é")?).0, "é");
+        assert!(Charset::Latin1.encode("😀").is_err());
+
+        // Invalid bytes are replaced
+        assert_eq!(
+            Charset::Utf8.decode(b"a\xff"),
+            ("a\u{FFFD}".to_string(), true)
+        );
+        assert_eq!(
+            Charset::Utf16Be.decode(b"\x00a\x00"),
+            ("a\u{FFFD}".to_string(), true)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn detect_and_convert_line_endings() {
+        assert_eq!(EndOfLine::detect("a\r\nb\r\nc\n"), Some(EndOfLine::Crlf));
+        assert_eq!(EndOfLine::detect("a\r\nb\n"), Some(EndOfLine::Lf));
+        assert_eq!(EndOfLine::detect("a\rb"), Some(EndOfLine::Cr));
+        assert_eq!(EndOfLine::detect("a"), None);
+        assert_eq!(EndOfLine::Crlf.convert("a\nb\r\nc\r"), "a\r\nb\r\nc\r\n");
+        assert_eq!(EndOfLine::Lf.convert("a\r\nb\n"), "a\nb\n");
+    }
+}
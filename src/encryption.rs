@@ -0,0 +1,185 @@
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+use anyhow::Context;
+use shared::canonicalized_path::CanonicalizedPath;
+
+/// The tool that an encrypted file is decrypted (and re-encrypted on save) with.
+///
+/// The plaintext is only ever piped through the tool, so it never touches the disk.
+/// The plaintext is bytes, so that it can be decoded from (and encoded to) any `Charset`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Encryption {
+    /// Requires age 1.3 or later, which ships the `batchpass` plugin
+    /// that reads the passphrase from the environment instead of the terminal.
+    Age,
+    Gpg,
+}
+
+/// The `Debug` implementation is redacted so that the passphrase never ends up in the logs.
+#[derive(Clone, PartialEq, Eq)]
+pub(crate) struct Passphrase(String);
+
+impl Passphrase {
+    pub(crate) fn new(passphrase: String) -> Self {
+        Self(passphrase)
+    }
+}
+
+impl std::fmt::Debug for Passphrase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Passphrase(<redacted>)")
+    }
+}
+
+impl Encryption {
+    pub(crate) fn from_path(path: &CanonicalizedPath) -> Option<Self> {
+        match path.to_path_buf().extension()?.to_str()? {
+            "age" => Some(Encryption::Age),
+            "gpg" => Some(Encryption::Gpg),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn decrypt(
+        self,
+        path: &CanonicalizedPath,
+        passphrase: &Passphrase,
+    ) -> anyhow::Result<Vec<u8>> {
+        let input = match self {
+            Encryption::Age => Vec::new(),
+            Encryption::Gpg => format!("{}\n", passphrase.0).into_bytes(),
+        };
+        let mut command = self.command(passphrase, &["--decrypt"]);
+        command.arg(path.to_path_buf());
+        run(&mut command, &input)
+    }
+
+    /// Files encrypted with a passphrase are re-encrypted with the same passphrase.
+    ///
+    /// The ciphertext is written to a temporary file that is renamed over `path`,
+    /// so that the file is not left truncated if the editor is interrupted while writing.
+    pub(crate) fn encrypt(
+        self,
+        path: &CanonicalizedPath,
+        content: &[u8],
+        passphrase: &Passphrase,
+    ) -> anyhow::Result<()> {
+        let (args, input) = match self {
+            Encryption::Age => (&["--encrypt"][..], content.to_vec()),
+            // gpg reads the passphrase from the first line of stdin, and the content from the rest
+            Encryption::Gpg => (
+                &["--symmetric", "--output", "-"][..],
+                format!("{}\n", passphrase.0)
+                    .into_bytes()
+                    .into_iter()
+                    .chain(content.iter().copied())
+                    .collect(),
+            ),
+        };
+        let output = run(&mut self.command(passphrase, args), &input)?;
+        let path = path.to_path_buf();
+        let directory = path
+            .parent()
+            .with_context(|| format!("{} has no parent directory", path.display()))?;
+        let mut file = tempfile::NamedTempFile::new_in(directory)?;
+        file.write_all(&output)?;
+        file.as_file().sync_all()?;
+        std::fs::set_permissions(file.path(), std::fs::metadata(path)?.permissions())?;
+        file.persist(path)?;
+        Ok(())
+    }
+
+    fn command(self, passphrase: &Passphrase, args: &[&str]) -> Command {
+        match self {
+            Encryption::Age => {
+                let mut command = Command::new("age");
+                command
+                    .env("AGE_PASSPHRASE", &passphrase.0)
+                    .args(args)
+                    .args(["-j", "batchpass"]);
+                command
+            }
+            Encryption::Gpg => {
+                let mut command = Command::new("gpg");
+                command
+                    .args([
+                        "--batch",
+                        "--yes",
+                        "--quiet",
+                        "--pinentry-mode",
+                        "loopback",
+                        "--passphrase-fd",
+                        "0",
+                    ])
+                    .args(args);
+                command
+            }
+        }
+    }
+}
+
+fn run(command: &mut Command, input: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to spawn {:?}", command.get_program()))?;
+    let mut stdin = child.stdin.take().context("Failed to open stdin")?;
+    let input = input.to_vec();
+    // Written in a separate thread, otherwise both processes would be blocked
+    // if the child fills its stdout before its stdin is fully written
+    let writer = std::thread::spawn(move || stdin.write_all(&input));
+    let output = child.wait_with_output()?;
+    writer
+        .join()
+        .map_err(|_| anyhow::anyhow!("Failed to write to stdin"))??;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "{:?} failed: {}",
+            command.get_program(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(output.stdout)
+}
+
+#[cfg(test)]
+mod test_encryption {
+    use super::*;
+
+    #[test]
+    fn passphrase_is_redacted_in_debug_output() {
+        let passphrase = Passphrase::new("hunter2".to_string());
+        assert!(!format!("{passphrase:?}").contains("hunter2"))
+    }
+
+    #[test]
+    fn gpg_roundtrip() -> anyhow::Result<()> {
+        if Command::new("gpg").arg("--version").output().is_err() {
+            return Ok(());
+        }
+        let temp_dir = tempfile::tempdir()?;
+        let path = temp_dir.path().join("secret.txt.gpg");
+        std::fs::write(&path, "")?;
+        let path: CanonicalizedPath = path.as_path().try_into()?;
+        assert_eq!(Encryption::from_path(&path), Some(Encryption::Gpg));
+        let passphrase = Passphrase::new("hunter2".to_string());
+        Encryption::Gpg.encrypt(&path, b"hello\nworld", &passphrase)?;
+        assert_ne!(std::fs::read(path.to_path_buf())?, b"hello\nworld");
+        assert_eq!(
+            Encryption::Gpg.decrypt(&path, &passphrase)?,
+            b"hello\nworld"
+        );
+        // The plaintext is not necessarily UTF-8
+        Encryption::Gpg.encrypt(&path, b"caf\xe9", &passphrase)?;
+        assert_eq!(Encryption::Gpg.decrypt(&path, &passphrase)?, b"caf\xe9");
+        assert!(Encryption::Gpg
+            .decrypt(&path, &Passphrase::new("wrong".to_string()))
+            .is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,136 @@
+use shared::canonicalized_path::CanonicalizedPath;
+
+/// Pasted content that has more characters than this is considered a huge paste.
+pub(crate) const HUGE_PASTE_THRESHOLD: usize = 1000;
+
+/// Returns true if `content` is too large to be pasted into a single-line input (e.g. prompt).
+pub(crate) fn is_huge_paste(content: &str) -> bool {
+    content.chars().count() > HUGE_PASTE_THRESHOLD
+}
+
+/// Most terminals implement file-drop by pasting the path(s) of the dropped files,
+/// where each path might be quoted, backslash-escaped, or prefixed with `file://`.
+///
+/// Returns `None` if any of the pasted token is not an existing file,
+/// so that ordinary pastes are not mistaken as file drops.
+pub(crate) fn parse_dropped_paths(content: &str) -> Option<Vec<CanonicalizedPath>> {
+    let paths = tokenize(content.trim())
+        .into_iter()
+        .map(|token| {
+            let token = match token.strip_prefix("file://") {
+                Some(path) => percent_decode(path.strip_prefix("localhost").unwrap_or(path))?,
+                None => token,
+            };
+            CanonicalizedPath::try_from(token.as_str())
+                .ok()
+                .filter(|path| path.is_file())
+        })
+        .collect::<Option<Vec<_>>>()?;
+    if paths.is_empty() {
+        None
+    } else {
+        Some(paths)
+    }
+}
+
+/// Decodes every `%XX` of a `file://` URI, which might encode any byte of the path.
+///
+/// Returns `None` if an escape is malformed or the decoded path is not UTF-8.
+fn percent_decode(path: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(path.len());
+    let mut rest = path.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = tail
+                .get(..2)
+                .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))?;
+            bytes.push(u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+/// Split `content` by unquoted and unescaped whitespaces
+fn tokenize(content: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut quote: Option<char> = None;
+    let mut chars = content.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (None, '\\') => {
+                if let Some(next) = chars.next() {
+                    current.push(next)
+                }
+            }
+            (None, '\'' | '"') => quote = Some(c),
+            (Some(q), c) if q == c => quote = None,
+            (None, c) if c.is_whitespace() => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current))
+                }
+            }
+            (_, c) => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(current)
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod test_file_drop {
+    use super::*;
+
+    #[test]
+    fn tokenize_quoted_and_escaped() {
+        assert_eq!(
+            tokenize(r#"'/tmp/a b.rs' "/tmp/c d.rs" /tmp/e\ f.rs"#),
+            ["/tmp/a b.rs", "/tmp/c d.rs", "/tmp/e f.rs"]
+        );
+    }
+
+    #[test]
+    fn parse_dropped_paths_of_existing_files() {
+        let paths = parse_dropped_paths(
+            "'./tests/mock_repos/rust1/src/main.rs'\nfile://./tests/mock_repos/rust1/src/foo.rs",
+        )
+        .unwrap();
+        assert_eq!(
+            paths,
+            [
+                CanonicalizedPath::try_from("./tests/mock_repos/rust1/src/main.rs").unwrap(),
+                CanonicalizedPath::try_from("./tests/mock_repos/rust1/src/foo.rs").unwrap(),
+            ]
+        );
+    }
+
+    #[test]
+    fn percent_decode_file_uri() {
+        assert_eq!(
+            percent_decode("/tmp/a%20b%23c%C3%A9.rs").as_deref(),
+            Some("/tmp/a b#cé.rs")
+        );
+        assert_eq!(percent_decode("/tmp/100%"), None);
+        assert_eq!(percent_decode("/tmp/%zz"), None);
+    }
+
+    #[test]
+    fn ordinary_paste_is_not_file_drop() {
+        assert_eq!(parse_dropped_paths("hello world"), None);
+        assert_eq!(parse_dropped_paths("./tests/mock_repos/rust1/src"), None);
+        assert_eq!(parse_dropped_paths(""), None);
+    }
+
+    #[test]
+    fn huge_paste() {
+        assert!(!is_huge_paste("hello"));
+        assert!(!is_huge_paste("hello\nworld"));
+        assert!(is_huge_paste(&"x".repeat(HUGE_PASTE_THRESHOLD + 1)));
+    }
+}
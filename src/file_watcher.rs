@@ -0,0 +1,73 @@
+//! Watches the opened files for changes made by other programs.
+//!
+//! The modification times of the watched files are polled by a background thread,
+//! which sends `AppMessage::FileChangedOnDisk` whenever one of them changes.
+//! Polling is used instead of the notification API of each platform,
+//! because only the opened files are watched, which are few,
+//! and some filesystems (e.g. network mounts) do not notify changes anyway.
+
+use std::{
+    collections::HashMap,
+    sync::{mpsc::Sender, Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+
+use shared::canonicalized_path::CanonicalizedPath;
+
+use crate::app::AppMessage;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// The modification time of each watched file when it was last polled,
+/// which is `None` if the file does not exist.
+type ModifiedTimes = HashMap<CanonicalizedPath, Option<SystemTime>>;
+
+pub(crate) struct FileWatcher {
+    modified_times: Arc<Mutex<ModifiedTimes>>,
+}
+
+impl FileWatcher {
+    /// The polling thread stops once `sender` is disconnected.
+    pub(crate) fn start(sender: Sender<AppMessage>) -> FileWatcher {
+        let modified_times = Arc::new(Mutex::new(ModifiedTimes::new()));
+        let watched = modified_times.clone();
+        std::thread::spawn(move || loop {
+            std::thread::sleep(POLL_INTERVAL);
+            let changed_paths = {
+                let mut watched = watched.lock().unwrap();
+                watched
+                    .iter_mut()
+                    .filter_map(|(path, last_modified)| {
+                        let modified = modified_time(path);
+                        (std::mem::replace(last_modified, modified) != modified)
+                            .then(|| path.clone())
+                    })
+                    .collect::<Vec<_>>()
+            };
+            for path in changed_paths {
+                if sender.send(AppMessage::FileChangedOnDisk(path)).is_err() {
+                    return;
+                }
+            }
+        });
+        FileWatcher { modified_times }
+    }
+
+    /// Does nothing if the file is already watched, so that a change
+    /// that has not been polled yet is not missed.
+    pub(crate) fn watch(&self, path: &CanonicalizedPath) {
+        self.modified_times
+            .lock()
+            .unwrap()
+            .entry(path.clone())
+            .or_insert_with(|| modified_time(path));
+    }
+
+    pub(crate) fn unwatch(&self, path: &CanonicalizedPath) {
+        self.modified_times.lock().unwrap().remove(path);
+    }
+}
+
+pub(crate) fn modified_time(path: &CanonicalizedPath) -> Option<SystemTime> {
+    std::fs::metadata(path).ok()?.modified().ok()
+}
@@ -0,0 +1,169 @@
+//! Folding hides the lines of a range except its first line,
+//! which is marked by the fold indicator in the gutter.
+//!
+//! The foldable ranges are captured by the `@fold` queries of the language,
+//! or derived from the indentation if the language has no fold queries.
+
+use std::ops::Range;
+
+use itertools::Itertools;
+
+/// Returns the foldable line ranges (the end is exclusive) sorted by their start,
+/// where each range spans at least two lines.
+pub(crate) fn foldable_line_ranges(
+    tree: Option<&tree_sitter::Tree>,
+    folds_query: Option<&str>,
+    content: &str,
+) -> Vec<Range<usize>> {
+    let ranges = match tree.zip(folds_query) {
+        Some((tree, folds_query)) => {
+            query_line_ranges(tree, folds_query, content).unwrap_or_else(|error| {
+                log::error!("foldable_line_ranges: {:?}", error);
+                Vec::new()
+            })
+        }
+        None => indentation_line_ranges(content),
+    };
+    ranges
+        .into_iter()
+        .filter(|range| range.len() >= 2)
+        .sorted_by_key(|range| (range.start, std::cmp::Reverse(range.end)))
+        .dedup()
+        .collect_vec()
+}
+
+fn query_line_ranges(
+    tree: &tree_sitter::Tree,
+    folds_query: &str,
+    content: &str,
+) -> anyhow::Result<Vec<Range<usize>>> {
+    let query = tree_sitter::Query::new(&tree.language(), folds_query)?;
+    let Some(capture_index) = query.capture_index_for_name("fold") else {
+        return Ok(Vec::new());
+    };
+    Ok(tree_sitter::QueryCursor::new()
+        .matches(&query, tree.root_node(), content.as_bytes())
+        .flat_map(|query_match| {
+            query_match
+                .captures
+                .iter()
+                .filter(|capture| capture.index == capture_index)
+                .map(|capture| capture.node)
+                .collect_vec()
+        })
+        .map(|node| {
+            let end = node.end_position();
+            // Nodes that include their trailing newline (e.g. line comments) end at the next line
+            let end_line = if end.column == 0 && end.row > node.start_position().row {
+                end.row
+            } else {
+                end.row + 1
+            };
+            node.start_position().row..end_line
+        })
+        .collect_vec())
+}
+
+/// Each non-blank line followed by more indented lines is foldable,
+/// until the next non-blank line that is not more indented.
+fn indentation_line_ranges(content: &str) -> Vec<Range<usize>> {
+    let indentations = content
+        .lines()
+        .map(|line| {
+            (!line.trim().is_empty())
+                .then(|| line.chars().take_while(|c| c.is_whitespace()).count())
+        })
+        .collect_vec();
+    indentations
+        .iter()
+        .enumerate()
+        .filter_map(|(start, indentation)| {
+            let indentation = (*indentation)?;
+            let last = (start + 1..indentations.len())
+                .take_while(|index| indentations[*index].map_or(true, |other| other > indentation))
+                .filter(|index| indentations[*index].is_some())
+                .last()?;
+            Some(start..last + 1)
+        })
+        .collect_vec()
+}
+
+/// Returns the merged line ranges hidden by `folds`, sorted by their start.
+pub(crate) fn hidden_line_ranges(folds: &[Range<usize>]) -> Vec<Range<usize>> {
+    folds
+        .iter()
+        .map(|fold| fold.start + 1..fold.end)
+        .filter(|range| !range.is_empty())
+        .sorted_by_key(|range| range.start)
+        .coalesce(|previous, current| {
+            if current.start <= previous.end {
+                Ok(previous.start..previous.end.max(current.end))
+            } else {
+                Err((previous, current))
+            }
+        })
+        .collect_vec()
+}
+
+pub(crate) fn is_hidden(hidden_line_ranges: &[Range<usize>], line: usize) -> bool {
+    hidden_line_ranges.iter().any(|range| range.contains(&line))
+}
+
+/// Returns the visible lines from `start` (inclusive) until `end` (exclusive).
+pub(crate) fn visible_lines(
+    hidden_line_ranges: &[Range<usize>],
+    start: usize,
+    end: usize,
+) -> impl Iterator<Item = usize> + '_ {
+    (start..end).filter(|line| !is_hidden(hidden_line_ranges, *line))
+}
+
+/// Returns the line that is `count` visible lines above `line`, or the first line.
+pub(crate) fn visible_line_above(
+    hidden_line_ranges: &[Range<usize>],
+    line: usize,
+    count: usize,
+) -> usize {
+    (0..line)
+        .rev()
+        .filter(|line| !is_hidden(hidden_line_ranges, *line))
+        .take(count)
+        .last()
+        .unwrap_or(line)
+}
+
+#[cfg(test)]
+mod test_fold {
+    use crate::buffer::Buffer;
+
+    use super::*;
+
+    #[test]
+    fn fold_by_queries() {
+        let content = "fn f() {\n    g(\n        x,\n    );\n}\n";
+        let buffer = Buffer::new(Some(tree_sitter_rust::language()), content);
+        let query = include_str!("../contrib/folds/rust.scm");
+        assert_eq!(
+            foldable_line_ranges(buffer.tree(), Some(query), content),
+            [0..5, 1..4]
+        );
+    }
+
+    #[test]
+    fn fold_by_indentation() {
+        let content = "a\n  b\n\n  c\nd\n  e\n    f\ng";
+        assert_eq!(
+            foldable_line_ranges(None, None, content),
+            [0..4, 4..7, 5..7]
+        );
+    }
+
+    #[test]
+    fn hidden_lines_of_nested_folds() {
+        let hidden = hidden_line_ranges(&[0..4, 1..3, 5..7]);
+        assert_eq!(hidden, [1..4, 6..7]);
+        assert_eq!(visible_lines(&hidden, 0, 8).collect_vec(), [0, 4, 5, 7]);
+        assert_eq!(visible_line_above(&hidden, 7, 2), 4);
+        assert_eq!(visible_line_above(&hidden, 7, 10), 0);
+    }
+}
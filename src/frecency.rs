@@ -0,0 +1,84 @@
+use std::{collections::HashMap, hash::Hash};
+
+/// Ranks items by how frequently and how recently they are accessed.
+///
+/// Recency is measured by the number of accesses since the last access of an item,
+/// instead of wall-clock time, so that the ranking is deterministic.
+pub(crate) struct Frecency<T> {
+    entries: HashMap<T, Entry>,
+    tick: usize,
+}
+
+struct Entry {
+    count: usize,
+    last_access: usize,
+}
+
+impl<T: Eq + Hash> Frecency<T> {
+    pub(crate) fn new() -> Self {
+        Self {
+            entries: HashMap::new(),
+            tick: 0,
+        }
+    }
+
+    pub(crate) fn record(&mut self, item: T) {
+        self.tick += 1;
+        let entry = self.entries.entry(item).or_insert(Entry {
+            count: 0,
+            last_access: 0,
+        });
+        entry.count += 1;
+        entry.last_access = self.tick;
+    }
+
+    /// Higher is better, 0 means the item is never accessed.
+    pub(crate) fn score(&self, item: &T) -> usize {
+        self.entries
+            .get(item)
+            .map(|entry| {
+                let weight = match self.tick - entry.last_access {
+                    0..=4 => 8,
+                    5..=19 => 4,
+                    20..=99 => 2,
+                    _ => 1,
+                };
+                entry.count * weight
+            })
+            .unwrap_or(0)
+    }
+}
+
+impl<T: Eq + Hash> Default for Frecency<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test_frecency {
+    use super::Frecency;
+
+    #[test]
+    fn frequent_and_recent_items_rank_higher() {
+        let mut frecency = Frecency::new();
+        frecency.record("a");
+        frecency.record("b");
+        frecency.record("a");
+        assert!(frecency.score(&"a") > frecency.score(&"b"));
+        assert_eq!(frecency.score(&"c"), 0);
+
+        // Enough accesses outrank a more recent access
+        (0..100).for_each(|_| frecency.record("b"));
+        assert!(frecency.score(&"b") > frecency.score(&"a"));
+    }
+
+    #[test]
+    fn recent_item_outranks_more_frequent_older_item() {
+        let mut frecency = Frecency::new();
+        (0..3).for_each(|_| frecency.record("old"));
+        (0..100).for_each(|_| frecency.record("other"));
+        frecency.record("recent");
+        assert!(frecency.score(&"recent") > frecency.score(&"old"));
+    }
+}
@@ -1,15 +1,24 @@
 use crate::{components::component::Cursor, screen::Screen};
-use std::io::{self};
+use std::io::{self, BufWriter, Stdout};
 
-use super::{Frontend, MyWriter};
+use event::{event::Event, reader::EventReader};
+
+use super::{EventSource, Frontend, MyWriter};
 
 pub(crate) struct Crossterm {
     stdout: Box<dyn MyWriter>,
     /// Used for diffing to reduce unnecessary re-painting.
     previous_screen: Screen,
+    no_color: bool,
+    /// Whether the terminal speaks the kitty keyboard protocol,
+    /// see `Crossterm::negotiate_keyboard_enhancement`
+    keyboard_enhancement: bool,
 }
 
-impl MyWriter for std::io::Stdout {
+/// Large enough for most frames, which are flushed at once, see `App::render_screen`
+const WRITE_BUFFER_CAPACITY: usize = 64 * 1024;
+
+impl MyWriter for BufWriter<Stdout> {
     #[cfg(test)]
     fn as_any(&self) -> &dyn std::any::Any {
         self
@@ -19,16 +28,48 @@ impl MyWriter for std::io::Stdout {
 impl Crossterm {
     pub(crate) fn new() -> anyhow::Result<Crossterm> {
         Ok(Crossterm {
-            stdout: Box::new(io::stdout()),
+            stdout: Box::new(BufWriter::with_capacity(
+                WRITE_BUFFER_CAPACITY,
+                io::stdout(),
+            )),
             previous_screen: Screen::default(),
+            no_color: crate::themes::no_color(),
+            keyboard_enhancement: false,
         })
     }
+
+    /// Queries whether the terminal speaks the kitty keyboard protocol, in which case it is
+    /// enabled, so that key combos like `ctrl+shift+a`, `ctrl+enter` and `ctrl+i` (as opposed to
+    /// `tab`) are distinguishable.
+    ///
+    /// Otherwise the keys are parsed as legacy escape sequences, where such combos are reported
+    /// as their plain forms. modifyOtherKeys is not used as the fallback, because crossterm does
+    /// not parse the `CSI 27 ; modifiers ; key ~` sequences that it sends.
+    ///
+    /// It is called before the events are read (see `Frontend::event_source`),
+    /// because the reply of the terminal is read from the same input.
+    fn negotiate_keyboard_enhancement(&mut self) {
+        self.keyboard_enhancement =
+            terminal::supports_keyboard_enhancement().unwrap_or_else(|error| {
+                log::error!("Crossterm::negotiate_keyboard_enhancement: {error:?}");
+                false
+            });
+    }
+}
+
+impl EventSource for EventReader {
+    fn read(&mut self) -> anyhow::Result<Event> {
+        Ok(EventReader::read(self)?)
+    }
 }
 
 use crossterm::{
     cursor::{Hide, MoveTo, SetCursorStyle, Show},
-    event::{DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture},
-    execute, queue,
+    event::{
+        DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
+        KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+    },
+    queue,
     terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen},
     ExecutableCommand,
 };
@@ -39,8 +80,16 @@ impl Frontend for Crossterm {
         Ok(crate::app::Dimension { width, height })
     }
     fn enter_alternate_screen(&mut self) -> anyhow::Result<()> {
+        self.negotiate_keyboard_enhancement();
         self.stdout.execute(EnterAlternateScreen)?;
         self.stdout.execute(EnableBracketedPaste)?;
+        // The flags are pushed onto the stack of the alternate screen,
+        // so they are popped before leaving it
+        if self.keyboard_enhancement {
+            self.stdout.execute(PushKeyboardEnhancementFlags(
+                KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES,
+            ))?;
+        }
         Ok(())
     }
 
@@ -55,6 +104,9 @@ impl Frontend for Crossterm {
     }
 
     fn leave_alternate_screen(&mut self) -> anyhow::Result<()> {
+        if self.keyboard_enhancement {
+            self.stdout.execute(PopKeyboardEnhancementFlags)?;
+        }
         self.stdout.execute(LeaveAlternateScreen)?;
         self.stdout.execute(DisableBracketedPaste)?;
         Ok(())
@@ -72,9 +124,10 @@ impl Frontend for Crossterm {
 
     fn show_cursor(&mut self, cursor: &Cursor) -> anyhow::Result<()> {
         let style: SetCursorStyle = cursor.style().into();
-        queue!(self.stdout, Show, style)?;
-        execute!(
+        queue!(
             self.stdout,
+            Show,
+            style,
             MoveTo(
                 cursor.position().column as u16,
                 cursor.position().line as u16
@@ -104,4 +157,16 @@ impl Frontend for Crossterm {
     fn set_previous_screen(&mut self, previous_screen: Screen) {
         self.previous_screen = previous_screen
     }
+
+    fn no_color(&self) -> bool {
+        self.no_color
+    }
+
+    fn renders_progressively(&self) -> bool {
+        true
+    }
+
+    fn event_source(&mut self) -> Option<Box<dyn EventSource>> {
+        Some(Box::new(EventReader::default()))
+    }
 }
@@ -1,6 +1,11 @@
 #! #[cfg(test)]
 
-use crate::{components::component::Cursor, screen::Screen};
+use crate::{
+    components::component::Cursor,
+    grid::{Cell, Grid, PositionedCell},
+    position::Position,
+    screen::Screen,
+};
 
 use super::{MyWriter, StringWriter};
 
@@ -8,6 +13,10 @@ pub(crate) struct MockFrontend {
     /// Used for diffing to reduce unnecessary re-painting.
     previous_screen: Screen,
     writer: Box<dyn MyWriter>,
+    /// What a terminal would show, which is updated by the cells drawn since the screen is
+    /// cleared, see `test_script`
+    drawn: Grid,
+    cursor: Option<Position>,
 }
 
 const WIDTH: u16 = 80;
@@ -22,6 +31,8 @@ impl MockFrontend {
         Self {
             previous_screen: Default::default(),
             writer,
+            drawn: Grid::new(DIMENSION),
+            cursor: None,
         }
     }
 }
@@ -55,15 +66,18 @@ impl super::Frontend for MockFrontend {
         Ok(())
     }
 
-    fn show_cursor(&mut self, _: &Cursor) -> anyhow::Result<()> {
+    fn show_cursor(&mut self, cursor: &Cursor) -> anyhow::Result<()> {
+        self.cursor = Some(*cursor.position());
         Ok(())
     }
 
     fn hide_cursor(&mut self) -> anyhow::Result<()> {
+        self.cursor = None;
         Ok(())
     }
 
     fn clear_screen(&mut self) -> anyhow::Result<()> {
+        self.drawn = Grid::new(Default::default());
         Ok(())
     }
 
@@ -78,6 +92,27 @@ impl super::Frontend for MockFrontend {
     fn writer(&mut self) -> &mut Box<dyn MyWriter> {
         &mut self.writer
     }
+
+    /// The cells are still written, so that the recipes keep their escape sequences.
+    fn draw_cells(&mut self, cells: Vec<PositionedCell>) -> anyhow::Result<()> {
+        super::write_cells(&mut self.writer, cells.clone(), false)?;
+        for PositionedCell { cell, position } in cells {
+            let rows = &mut self.drawn.rows;
+            if rows.len() <= position.line {
+                rows.resize(position.line + 1, Vec::new());
+            }
+            let row = &mut rows[position.line];
+            if row.len() <= position.column {
+                row.resize(position.column + 1, Cell::default());
+            }
+            row[position.column] = Cell {
+                symbol: super::reveal(&cell.symbol),
+                ..cell
+            };
+            self.drawn.width = self.drawn.width.max(position.column + 1);
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -88,4 +123,12 @@ impl MockFrontend {
             .downcast_ref::<StringWriter>()
             .map(|writer| writer.get_string())
     }
+
+    pub(crate) fn drawn(&self) -> &Grid {
+        &self.drawn
+    }
+
+    pub(crate) fn cursor(&self) -> Option<Position> {
+        self.cursor
+    }
 }
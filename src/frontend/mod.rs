@@ -1,13 +1,23 @@
+//! The backends that the editor is rendered to and receives its events from, where `Crossterm`
+//! is the terminal, `Remote` is the headless server mode, and `MockFrontend` is for the tests.
+
 pub(crate) mod crossterm;
 #[cfg(test)]
 pub(crate) mod mock;
+pub(crate) mod remote;
 
 use std::any::Any;
 use std::io::Write;
 #[cfg(test)]
 use std::io::{self};
 
-use crate::{app::Dimension, components::component::Cursor, screen::Screen};
+use crate::{
+    app::Dimension,
+    components::component::Cursor,
+    grid::{Cell, CellLineStyle, PositionedCell},
+    position::Position,
+    screen::Screen,
+};
 use ::crossterm::{
     cursor::MoveTo,
     queue,
@@ -15,8 +25,11 @@ use ::crossterm::{
         Attribute, Color, Print, SetAttribute, SetBackgroundColor, SetForegroundColor,
         SetUnderlineColor,
     },
+    terminal::{BeginSynchronizedUpdate, EndSynchronizedUpdate},
 };
+use event::event::Event;
 use itertools::Itertools;
+use unicode_width::UnicodeWidthStr;
 
 pub(crate) trait Frontend {
     fn get_terminal_dimension(&self) -> anyhow::Result<Dimension>;
@@ -32,64 +45,203 @@ pub(crate) trait Frontend {
     fn writer(&mut self) -> &mut Box<dyn MyWriter>;
     fn previous_screen(&mut self) -> Screen;
     fn set_previous_screen(&mut self, previous_screen: Screen);
-    fn render_screen(&mut self, mut screen: Screen) -> anyhow::Result<()> {
-        let cells = {
+    /// When true, only attributes (e.g. bold, underline and reverse) are rendered,
+    /// refer https://no-color.org
+    fn no_color(&self) -> bool {
+        false
+    }
+    /// When true, large repaints are rendered across several frames, see `Screen::take_frame`
+    fn renders_progressively(&self) -> bool {
+        false
+    }
+    /// The terminals that support synchronized output (e.g. kitty, WezTerm and iTerm2) hold
+    /// the screen until the update ends, so that a frame is never shown half-drawn,
+    /// whereas the other terminals ignore it.
+    fn begin_synchronized_update(&mut self) -> anyhow::Result<()> {
+        queue!(self.writer(), BeginSynchronizedUpdate)?;
+        Ok(())
+    }
+    fn end_synchronized_update(&mut self) -> anyhow::Result<()> {
+        queue!(self.writer(), EndSynchronizedUpdate)?;
+        Ok(())
+    }
+    /// The events of this frontend, which are read on a thread of their own, see `App::run`.
+    ///
+    /// `None` if the events are sent to the app in another way,
+    /// e.g. by the clients of the headless server mode.
+    fn event_source(&mut self) -> Option<Box<dyn EventSource>> {
+        None
+    }
+    /// Draws the cells that are changed since the previous frame, see `render_screen`
+    fn draw_cells(&mut self, cells: Vec<PositionedCell>) -> anyhow::Result<()> {
+        write_cells(self.writer(), cells, self.no_color())
+    }
+    /// Shows what is drawn since the previous call at once
+    fn flush(&mut self) -> anyhow::Result<()> {
+        self.writer().flush()?;
+        Ok(())
+    }
+    fn ring_bell(&mut self) -> anyhow::Result<()> {
+        let writer = self.writer();
+        writer.write_all(b"\x07")?;
+        writer.flush()?;
+        Ok(())
+    }
+    /// Returns false if some cells are left for the next call, see `Screen::take_frame`.
+    fn render_screen(&mut self, mut screen: Screen) -> anyhow::Result<bool> {
+        let (cells, is_fully_rendered) = {
             // Only perform diff if the dimension is the same
             let mut previous_screen = self.previous_screen();
-            let diff = if previous_screen.dimension() == screen.dimension() {
-                screen.diff(&mut previous_screen)
+            let (diff, is_continuation) = if previous_screen.dimension() == screen.dimension() {
+                (
+                    screen.diff(&mut previous_screen),
+                    !previous_screen.is_fully_rendered(),
+                )
             } else {
                 self.clear_screen()?;
-                screen.get_positioned_cells()
+                (screen.get_positioned_cells(), false)
+            };
+            let cells = if self.renders_progressively() {
+                screen.take_frame(diff, is_continuation)
+            } else {
+                diff
             };
+            let is_fully_rendered = screen.is_fully_rendered();
             self.set_previous_screen(screen);
 
-            diff
+            (cells, is_fully_rendered)
         };
 
-        debug_assert_eq!(
-            cells,
-            cells
-                .clone()
-                .into_iter()
-                .sorted_by_key(|cell| (cell.position.line, -(cell.position.column as isize)))
-                .collect_vec(),
-            "Cells should be sorted in reverse order by column to ensure proper rendering of
- multi-width characters in terminal displays"
-        );
-        for cell in cells {
+        self.draw_cells(cells)?;
+        Ok(is_fully_rendered)
+    }
+}
+pub(crate) trait EventSource: Send {
+    /// Blocks until the next event, where an error means that no more events will come.
+    fn read(&mut self) -> anyhow::Result<Event>;
+}
+
+/// Writes `cells` row by row, where the adjacent cells of a row are written as one run,
+/// which is preceded by a single cursor movement, and only the attributes that differ from
+/// the previous cell are set, to reduce the bytes written on slow connections (e.g. SSH).
+fn write_cells(
+    writer: &mut impl Write,
+    cells: Vec<PositionedCell>,
+    no_color: bool,
+) -> anyhow::Result<()> {
+    let mut pen: Option<Pen> = None;
+    // Where the terminal cursor is after the previous symbol, `None` if it is unknown
+    let mut cursor: Option<Position> = None;
+    for cell in cells.into_iter().sorted_by_key(|cell| cell.position) {
+        let position = cell.position;
+        // The cell after a multi-width character is a blank cell, which is covered by it
+        let is_covered = cursor.map_or(false, |cursor| {
+            cursor.line == position.line && cursor.column > position.column
+        });
+        if is_covered {
+            continue;
+        }
+        if cursor != Some(position) {
+            queue!(writer, MoveTo(position.column as u16, position.line as u16))?;
+        }
+        let next_pen = Pen::new(&cell.cell, no_color);
+        next_pen.set(writer, pen)?;
+        pen = Some(next_pen);
+        let symbol = reveal(&cell.cell.symbol);
+        let width = UnicodeWidthStr::width(symbol.as_str());
+        queue!(writer, Print(symbol))?;
+        cursor = (width > 0).then(|| Position::new(position.line, position.column + width));
+    }
+    if pen.is_some() {
+        queue!(writer, SetAttribute(Attribute::Reset))?;
+    }
+    Ok(())
+}
+
+/// The attributes that a cell is printed with
+#[derive(Clone, Copy, PartialEq)]
+struct Pen {
+    is_bold: bool,
+    is_reversed: bool,
+    line: Option<(Color, CellLineStyle)>,
+    background_color: Color,
+    foreground_color: Color,
+}
+
+impl Pen {
+    fn new(cell: &Cell, no_color: bool) -> Pen {
+        let color = |color: crate::themes::Color| {
+            if no_color {
+                Color::Reset
+            } else {
+                color.into()
+            }
+        };
+        Pen {
+            is_bold: cell.is_bold,
+            is_reversed: cell.is_reversed,
+            line: cell.line.map(|line| (color(line.color), line.style)),
+            background_color: color(cell.background_color),
+            foreground_color: color(cell.foreground_color),
+        }
+    }
+
+    fn bold(&self) -> Attribute {
+        if self.is_bold {
+            Attribute::Bold
+        } else {
+            Attribute::NoBold
+        }
+    }
+
+    fn reverse(&self) -> Attribute {
+        if self.is_reversed {
+            Attribute::Reverse
+        } else {
+            Attribute::NoReverse
+        }
+    }
+
+    fn underline(&self) -> Attribute {
+        match self.line {
+            Some((_, CellLineStyle::Undercurl)) => Attribute::Undercurled,
+            Some((_, CellLineStyle::Underline)) => Attribute::Underlined,
+            None => Attribute::NoUnderline,
+        }
+    }
+
+    /// Only sets the attributes that differ from `previous`, or all of them if it is `None`.
+    fn set(&self, writer: &mut impl Write, previous: Option<Pen>) -> anyhow::Result<()> {
+        let changed = |attribute: fn(&Pen) -> Attribute| {
+            previous.map_or(true, |previous| attribute(&previous) != attribute(self))
+        };
+        if changed(Pen::bold) {
+            queue!(writer, SetAttribute(self.bold()))?;
+        }
+        if changed(Pen::reverse) {
+            queue!(writer, SetAttribute(self.reverse()))?;
+        }
+        if previous.map_or(true, |previous| previous.line != self.line) {
             queue!(
-                self.writer(),
-                MoveTo(cell.position.column as u16, cell.position.line as u16),
-                SetAttribute(if cell.cell.is_bold {
-                    Attribute::Bold
-                } else {
-                    Attribute::NoBold
-                }),
-                SetUnderlineColor(
-                    cell.cell
-                        .line
-                        .map(|line| line.color.into())
-                        .unwrap_or(Color::Reset),
-                ),
-                SetAttribute(
-                    cell.cell
-                        .line
-                        .map(|line| match line.style {
-                            crate::grid::CellLineStyle::Undercurl => Attribute::Undercurled,
-                            crate::grid::CellLineStyle::Underline => Attribute::Underlined,
-                        })
-                        .unwrap_or(Attribute::NoUnderline),
-                ),
-                SetBackgroundColor(cell.cell.background_color.into()),
-                SetForegroundColor(cell.cell.foreground_color.into()),
-                Print(reveal(&cell.cell.symbol)),
-                SetAttribute(Attribute::Reset),
+                writer,
+                SetUnderlineColor(self.line.map(|(color, _)| color).unwrap_or(Color::Reset)),
+                SetAttribute(self.underline()),
             )?;
         }
+        if previous.map_or(true, |previous| {
+            previous.background_color != self.background_color
+        }) {
+            queue!(writer, SetBackgroundColor(self.background_color))?;
+        }
+        if previous.map_or(true, |previous| {
+            previous.foreground_color != self.foreground_color
+        }) {
+            queue!(writer, SetForegroundColor(self.foreground_color))?;
+        }
         Ok(())
     }
 }
+
 /// Convert invisible character to visible character
 fn reveal(s: &str) -> String {
     match s {
@@ -159,3 +311,59 @@ impl Write for NullWriter {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test_frontend {
+    use my_proc_macros::hex;
+
+    use crate::{
+        grid::{Cell, PositionedCell},
+        position::Position,
+    };
+
+    use super::{write_cells, StringWriter};
+
+    fn cell(line: usize, column: usize, symbol: &str) -> PositionedCell {
+        PositionedCell {
+            cell: Cell {
+                symbol: symbol.to_string(),
+                ..Default::default()
+            },
+            position: Position::new(line, column),
+        }
+    }
+
+    #[test]
+    fn adjacent_cells_are_written_as_runs() {
+        let mut writer = StringWriter::new();
+        let highlighted = PositionedCell {
+            cell: Cell {
+                background_color: hex!("#ff0000"),
+                ..cell(0, 5, "c").cell
+            },
+            ..cell(0, 5, "c")
+        };
+        // Sorted reversed by column, like `Screen::diff`
+        let cells = [
+            highlighted,
+            cell(0, 1, "b"),
+            cell(0, 0, "a"),
+            cell(1, 2, "d"),
+            cell(1, 1, " "),
+            cell(1, 0, "🦀"),
+        ]
+        .to_vec();
+        write_cells(&mut writer, cells, false).unwrap();
+        let output = writer.get_string();
+        // One cursor movement for each run
+        assert_eq!(output.matches('H').count(), 3);
+        assert!(output.contains("\x1b[1;1H"));
+        assert!(output.contains("\x1b[1;6H"));
+        assert!(output.contains("\x1b[2;1H"));
+        assert!(output.contains("ab"));
+        // The blank cell after the multi-width character is covered by it
+        assert!(output.contains("🦀d"));
+        // The background is only set when it changes
+        assert_eq!(output.matches("\x1b[48;").count(), 3);
+    }
+}
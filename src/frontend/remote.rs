@@ -0,0 +1,134 @@
+use std::io::{self, Write};
+
+use base64::{engine::general_purpose, Engine as _};
+use crossterm::{
+    cursor::{Hide, MoveTo, SetCursorStyle, Show},
+    queue,
+    terminal::{Clear, ClearType},
+};
+
+use crate::{components::component::Cursor, screen::Screen, server::RemoteClients};
+
+use super::{Frontend, MyWriter};
+
+/// The frontend of the headless server mode, which sends what is rendered to the clients,
+/// see `server`. The terminals of the clients are set up by the clients themselves.
+pub(crate) struct Remote {
+    writer: Box<dyn MyWriter>,
+    /// Used for diffing to reduce unnecessary re-painting.
+    previous_screen: Screen,
+}
+
+impl Remote {
+    pub(crate) fn new(clients: RemoteClients) -> Remote {
+        Remote {
+            writer: Box::new(Broadcaster {
+                clients,
+                buffer: Vec::new(),
+            }),
+            previous_screen: Screen::default(),
+        }
+    }
+}
+
+/// Collects the bytes of a frame, which are sent as one `output` notification once flushed
+struct Broadcaster {
+    clients: RemoteClients,
+    buffer: Vec<u8>,
+}
+
+impl Write for Broadcaster {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.buffer.is_empty() {
+            let data = general_purpose::STANDARD.encode(std::mem::take(&mut self.buffer));
+            self.clients.broadcast(&serde_json::json!({
+                "jsonrpc": "2.0",
+                "method": "output",
+                "params": { "data": data },
+            }));
+        }
+        Ok(())
+    }
+}
+
+impl MyWriter for Broadcaster {
+    #[cfg(test)]
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+impl Frontend for Remote {
+    /// The clients resize the editor once they are connected
+    fn get_terminal_dimension(&self) -> anyhow::Result<crate::app::Dimension> {
+        Ok(crate::app::Dimension {
+            width: 80,
+            height: 24,
+        })
+    }
+
+    fn enter_alternate_screen(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn enable_mouse_capture(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn disable_mouse_capture(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn leave_alternate_screen(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn enable_raw_mode(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn disable_raw_mode(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn show_cursor(&mut self, cursor: &Cursor) -> anyhow::Result<()> {
+        let style: SetCursorStyle = cursor.style().into();
+        queue!(
+            self.writer,
+            Show,
+            style,
+            MoveTo(
+                cursor.position().column as u16,
+                cursor.position().line as u16
+            )
+        )?;
+        Ok(())
+    }
+
+    fn hide_cursor(&mut self) -> anyhow::Result<()> {
+        queue!(self.writer, Hide)?;
+        Ok(())
+    }
+
+    fn clear_screen(&mut self) -> anyhow::Result<()> {
+        queue!(self.writer, Clear(ClearType::All))?;
+        Ok(())
+    }
+
+    fn writer(&mut self) -> &mut Box<dyn MyWriter> {
+        &mut self.writer
+    }
+
+    fn previous_screen(&mut self) -> Screen {
+        std::mem::take(&mut self.previous_screen)
+    }
+
+    fn set_previous_screen(&mut self, previous_screen: Screen) {
+        self.previous_screen = previous_screen
+    }
+}
@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+
+use anyhow::Context;
+use git2::{Oid, Repository};
+use shared::canonicalized_path::CanonicalizedPath;
+
+/// The commit that last changed a line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct BlameLine {
+    /// None if the line is not committed yet
+    commit: Option<BlameCommit>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct BlameCommit {
+    short_id: String,
+    author: String,
+    /// Formatted as `YYYY-MM-DD` in the timezone of the author
+    date: String,
+    summary: String,
+}
+
+impl BlameLine {
+    /// Used as the virtual text at the end of the current line.
+    pub(crate) fn display(&self) -> String {
+        match &self.commit {
+            Some(commit) => format!("{}, {} • {}", commit.author, commit.date, commit.summary),
+            None => "Not committed yet".to_string(),
+        }
+    }
+
+    /// Used as the prefix of each line in the blame panel.
+    pub(crate) fn display_columns(&self, author_width: usize) -> String {
+        match &self.commit {
+            Some(commit) => format!(
+                "{} {: <author_width$} {}",
+                commit.short_id, commit.author, commit.date
+            ),
+            None => format!(
+                "{: <8}{: <author_width$} {: <10}",
+                "", "Not committed yet", ""
+            ),
+        }
+    }
+
+    pub(crate) fn author_width(&self) -> usize {
+        self.commit
+            .as_ref()
+            .map(|commit| commit.author.chars().count())
+            .unwrap_or("Not committed yet".len())
+    }
+}
+
+/// Blames `content` instead of the file on disk,
+/// so that the lines match the buffer even if it is not saved.
+///
+/// This is slow for files with a long history, so it should not be run on the main thread.
+pub(crate) fn blame(path: &CanonicalizedPath, content: &str) -> anyhow::Result<Vec<BlameLine>> {
+    let repo = Repository::discover(path.to_path_buf())?;
+    let workdir: CanonicalizedPath = repo
+        .workdir()
+        .context("Cannot blame files of a bare repository")?
+        .try_into()?;
+    let relative_path = path.display_relative_to(&workdir)?;
+    let blame = repo.blame_file(std::path::Path::new(&relative_path), None)?;
+    let blame = blame.blame_buffer(content.as_bytes())?;
+    let mut commits: HashMap<Oid, Option<BlameCommit>> = HashMap::new();
+    let mut lines = Vec::new();
+    for hunk in blame.iter() {
+        let id = hunk.final_commit_id();
+        let commit = match commits.get(&id) {
+            Some(commit) => commit.clone(),
+            None => {
+                let commit = if id.is_zero() {
+                    None
+                } else {
+                    let commit = repo.find_commit(id)?;
+                    let author = commit.author();
+                    Some(BlameCommit {
+                        short_id: id.to_string().chars().take(7).collect(),
+                        author: author.name().unwrap_or_default().to_string(),
+                        date: format_date(author.when()),
+                        summary: commit.summary().unwrap_or_default().to_string(),
+                    })
+                };
+                commits.insert(id, commit.clone());
+                commit
+            }
+        };
+        lines.extend(std::iter::repeat(BlameLine { commit }).take(hunk.lines_in_hunk()))
+    }
+    Ok(lines)
+}
+
+fn format_date(time: git2::Time) -> String {
+    let seconds = time.seconds() + time.offset_minutes() as i64 * 60;
+    let (year, month, day) = civil_from_days(seconds.div_euclid(86400));
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// Converts days since 1970-01-01 to a (year, month, day) of the proleptic Gregorian calendar.
+///
+/// Reference: http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+pub(crate) fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = (if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    }) as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod test_blame {
+    use super::*;
+
+    #[test]
+    fn format_date_in_author_timezone() {
+        assert_eq!(format_date(git2::Time::new(0, 0)), "1970-01-01");
+        // 2024-02-29T23:30:00Z, which is already March 1st in UTC+1
+        assert_eq!(format_date(git2::Time::new(1709249400, 0)), "2024-02-29");
+        assert_eq!(format_date(git2::Time::new(1709249400, 60)), "2024-03-01");
+    }
+}
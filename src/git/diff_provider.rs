@@ -0,0 +1,266 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::anyhow;
+use shared::canonicalized_path::CanonicalizedPath;
+
+use crate::config::Config;
+
+use super::{DiffMode, GitOperation, GitRepo};
+
+/// A source of the base content that the current content of a file is diffed against,
+/// so that hunks work regardless of the version control system used by the project.
+pub(crate) trait DiffProvider {
+    /// Returns an error if `path` is untracked, or it cannot be found at the base.
+    fn base_content(&self, path: &CanonicalizedPath) -> anyhow::Result<String>;
+
+    /// The content that the git gutter is diffed against,
+    /// so that changes that are already staged are not marked.
+    fn gutter_base_content(&self, path: &CanonicalizedPath) -> anyhow::Result<String> {
+        self.base_content(path)
+    }
+}
+
+/// Returns `None` if `path` is not under any supported version control system,
+/// or it is untracked.
+pub(crate) fn gutter_base_content(path: &CanonicalizedPath) -> Option<String> {
+    diff_provider(path, &DiffMode::UnstagedAgainstCurrentBranch)?
+        .gutter_base_content(path)
+        .ok()
+}
+
+/// Returns `None` if `path` is not under any supported version control system.
+///
+/// The provider is chosen per project, based on the nearest repository that contains `path`,
+/// or the nearest project whose `.ki/config.toml` configures a `diff-provider` command.
+pub(crate) fn diff_provider(
+    path: &CanonicalizedPath,
+    diff_mode: &DiffMode,
+) -> Option<Box<dyn DiffProvider>> {
+    if diff_mode == &DiffMode::UnsavedAgainstFileOnDisk {
+        return Some(Box::new(FileOnDisk));
+    }
+    path.to_path_buf()
+        .ancestors()
+        .find_map(|directory| -> Option<Box<dyn DiffProvider>> {
+            let root = directory.to_path_buf();
+            if let Some(command) = configured_command(directory) {
+                return Some(Box::new(Configured { root, command }));
+            }
+            // Jujutsu repositories can be colocated with Git repositories,
+            // in which case Jujutsu is preferred because it is the one being used
+            if directory.join(".jj").is_dir() {
+                Some(Box::new(Jujutsu {
+                    root,
+                    revision: match diff_mode {
+                        DiffMode::UnstagedAgainstMainBranch => "trunk()".to_string(),
+                        DiffMode::UnstagedAgainstRef(reference) => reference.clone(),
+                        _ => "@-".to_string(),
+                    },
+                }))
+            } else if directory.join(".hg").is_dir() {
+                Some(Box::new(Mercurial {
+                    root,
+                    revision: match diff_mode {
+                        DiffMode::UnstagedAgainstMainBranch => "default".to_string(),
+                        DiffMode::UnstagedAgainstRef(reference) => reference.clone(),
+                        _ => ".".to_string(),
+                    },
+                }))
+            } else if directory.join(".git").exists() {
+                Some(Box::new(Git {
+                    repo: directory.try_into().ok()?,
+                    diff_mode: diff_mode.clone(),
+                }))
+            } else {
+                None
+            }
+        })
+}
+
+struct Git {
+    repo: CanonicalizedPath,
+    diff_mode: DiffMode,
+}
+
+impl DiffProvider for Git {
+    fn base_content(&self, path: &CanonicalizedPath) -> anyhow::Result<String> {
+        path.content_at_last_commit(&self.diff_mode, &GitRepo::try_from(&self.repo)?)
+    }
+
+    fn gutter_base_content(&self, path: &CanonicalizedPath) -> anyhow::Result<String> {
+        path.content_in_index(&GitRepo::try_from(&self.repo)?)
+    }
+}
+
+/// The `diff-provider` of the config of the project at `directory`, if any.
+fn configured_command(directory: &Path) -> Option<Vec<String>> {
+    let path = directory.join(".ki").join("config.toml");
+    if !path.exists() {
+        return None;
+    }
+    Some(Config::load(vec![path]).ok()?.diff_provider()?.to_vec())
+}
+
+/// The command of the config of the project, which is used for every diff mode,
+/// because it is only given the path of the file
+struct Configured {
+    root: PathBuf,
+    command: Vec<String>,
+}
+
+impl DiffProvider for Configured {
+    fn base_content(&self, path: &CanonicalizedPath) -> anyhow::Result<String> {
+        let (program, args) = self
+            .command
+            .split_first()
+            .ok_or_else(|| anyhow!("The diff-provider command is empty"))?;
+        run(
+            &self.root,
+            program,
+            &args.iter().map(String::as_str).collect::<Vec<_>>(),
+            path,
+        )
+    }
+}
+
+struct Mercurial {
+    root: PathBuf,
+    revision: String,
+}
+
+impl DiffProvider for Mercurial {
+    fn base_content(&self, path: &CanonicalizedPath) -> anyhow::Result<String> {
+        run(&self.root, "hg", &["cat", "--rev", &self.revision], path)
+    }
+}
+
+struct Jujutsu {
+    root: PathBuf,
+    revision: String,
+}
+
+impl DiffProvider for Jujutsu {
+    fn base_content(&self, path: &CanonicalizedPath) -> anyhow::Result<String> {
+        run(
+            &self.root,
+            "jj",
+            &["file", "show", "--revision", &self.revision],
+            path,
+        )
+    }
+}
+
+/// Used for reviewing the unsaved changes of a buffer
+struct FileOnDisk;
+
+impl DiffProvider for FileOnDisk {
+    fn base_content(&self, path: &CanonicalizedPath) -> anyhow::Result<String> {
+        path.read()
+    }
+}
+
+/// Run `program` with `args` followed by `path` (relative to `root`), and returns its stdout.
+fn run(
+    root: &Path,
+    program: &str,
+    args: &[&str],
+    path: &CanonicalizedPath,
+) -> anyhow::Result<String> {
+    let relative_path = path.to_path_buf().strip_prefix(root)?.to_path_buf();
+    let output = Command::new(program)
+        .args(args)
+        .arg(relative_path)
+        .current_dir(root)
+        .output()?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "{program} failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+#[cfg(test)]
+mod test_diff_provider {
+    use super::*;
+
+    #[test]
+    fn file_on_disk() -> anyhow::Result<()> {
+        let path: CanonicalizedPath = "./tests/mock_repos/rust1/src/main.rs".try_into()?;
+        let provider = diff_provider(&path, &DiffMode::UnsavedAgainstFileOnDisk).unwrap();
+        assert_eq!(provider.base_content(&path)?, path.read()?);
+        Ok(())
+    }
+
+    fn run_in(directory: &Path, program: &str, args: &[&str]) -> anyhow::Result<()> {
+        let status = Command::new(program)
+            .args(args)
+            .current_dir(directory)
+            .status()?;
+        anyhow::ensure!(status.success(), "{program} {args:?} failed");
+        Ok(())
+    }
+
+    #[test]
+    fn configured_diff_provider() -> anyhow::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let root = temp_dir.path();
+        std::fs::create_dir(root.join(".ki"))?;
+        std::fs::write(
+            root.join(".ki").join("config.toml"),
+            r#"diff-provider = ["sed", "s/world/hello/"]"#,
+        )?;
+        std::fs::create_dir(root.join("src"))?;
+        std::fs::write(root.join("src").join("notes.txt"), "world\n")?;
+
+        let path: CanonicalizedPath = root.join("src").join("notes.txt").try_into()?;
+        let provider = diff_provider(&path, &DiffMode::UnstagedAgainstCurrentBranch).unwrap();
+        assert_eq!(provider.base_content(&path)?, "hello\n");
+        Ok(())
+    }
+
+    /// The base of the current branch is the parent of the working copy
+    #[test]
+    #[ignore = "requires hg, run with `cargo test -- --ignored`"]
+    fn mercurial() -> anyhow::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let root = temp_dir.path();
+        run_in(root, "hg", &["init"])?;
+        std::fs::write(root.join("notes.txt"), "hello\n")?;
+        run_in(root, "hg", &["add", "notes.txt"])?;
+        run_in(
+            root,
+            "hg",
+            &["commit", "--message", "init", "--user", "tester"],
+        )?;
+        std::fs::write(root.join("notes.txt"), "world\n")?;
+
+        let path: CanonicalizedPath = root.join("notes.txt").try_into()?;
+        let provider = diff_provider(&path, &DiffMode::UnstagedAgainstCurrentBranch).unwrap();
+        assert_eq!(provider.base_content(&path)?, "hello\n");
+        assert_eq!(provider.gutter_base_content(&path)?, "hello\n");
+        Ok(())
+    }
+
+    /// Jujutsu is preferred over the Git repository that it is colocated with
+    #[test]
+    #[ignore = "requires jj, run with `cargo test -- --ignored`"]
+    fn jujutsu() -> anyhow::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let root = temp_dir.path();
+        run_in(root, "jj", &["git", "init", "--colocate"])?;
+        std::fs::write(root.join("notes.txt"), "hello\n")?;
+        // The working copy is snapshotted into a change, which becomes the parent of a new change
+        run_in(root, "jj", &["new"])?;
+        std::fs::write(root.join("notes.txt"), "world\n")?;
+
+        let path: CanonicalizedPath = root.join("notes.txt").try_into()?;
+        let provider = diff_provider(&path, &DiffMode::UnstagedAgainstCurrentBranch).unwrap();
+        assert_eq!(provider.base_content(&path)?, "hello\n");
+        Ok(())
+    }
+}
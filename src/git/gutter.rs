@@ -0,0 +1,106 @@
+use std::ops::Range;
+
+use itertools::Itertools;
+use similar::{DiffTag, TextDiff};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum GutterHunkKind {
+    Added,
+    Modified,
+    Deleted,
+}
+
+/// A change between the base content of the git gutter and the content of a buffer.
+///
+/// Unlike `Hunk`, this does not contain the word-level changes,
+/// because it is recomputed on every render.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct GutterHunk {
+    /// 0-based line range in the buffer, which is empty if the lines are deleted
+    new_line_range: Range<usize>,
+    /// The lines of the base content that are replaced, including their line endings
+    old_content: String,
+    kind: GutterHunkKind,
+}
+
+impl GutterHunk {
+    pub(crate) fn get(base: &str, content: &str) -> Vec<GutterHunk> {
+        let diff = TextDiff::from_lines(base, content);
+        diff.ops()
+            .iter()
+            .filter_map(|diff_op| {
+                let (tag, old_range, new_range) = diff_op.as_tag_tuple();
+                let kind = match tag {
+                    DiffTag::Equal => None,
+                    DiffTag::Delete => Some(GutterHunkKind::Deleted),
+                    DiffTag::Insert => Some(GutterHunkKind::Added),
+                    DiffTag::Replace => Some(GutterHunkKind::Modified),
+                }?;
+                Some(GutterHunk {
+                    new_line_range: new_range,
+                    old_content: diff.old_slices()[old_range].concat(),
+                    kind,
+                })
+            })
+            .collect_vec()
+    }
+
+    pub(crate) fn line_range(&self) -> &Range<usize> {
+        &self.new_line_range
+    }
+
+    pub(crate) fn old_content(&self) -> &str {
+        &self.old_content
+    }
+
+    pub(crate) fn kind(&self) -> GutterHunkKind {
+        self.kind
+    }
+
+    /// Deleted lines have no line of their own,
+    /// so they are marked on the line above them (or the first line if there is none).
+    pub(crate) fn marked_lines(&self) -> Range<usize> {
+        match self.kind {
+            GutterHunkKind::Deleted => {
+                let line = self.new_line_range.start.saturating_sub(1);
+                line..line + 1
+            }
+            GutterHunkKind::Added | GutterHunkKind::Modified => self.new_line_range.clone(),
+        }
+    }
+
+    /// Returns true if the cursor at `line` is considered to be on this hunk.
+    pub(crate) fn contains_line(&self, line: usize) -> bool {
+        self.marked_lines().contains(&line)
+    }
+}
+
+#[cfg(test)]
+mod test_gutter {
+    use super::*;
+
+    #[test]
+    fn classify_hunks() {
+        let base = "a\nb\nc\nd\ne\n";
+        let content = "a\nB\nc\ne\nf\n";
+        let hunks = GutterHunk::get(base, content)
+            .into_iter()
+            .map(|hunk| {
+                (
+                    hunk.kind(),
+                    hunk.line_range().clone(),
+                    hunk.marked_lines(),
+                    hunk.old_content().to_string(),
+                )
+            })
+            .collect_vec();
+        assert_eq!(
+            hunks,
+            [
+                (GutterHunkKind::Modified, 1..2, 1..2, "b\n".to_string()),
+                (GutterHunkKind::Deleted, 3..3, 2..3, "d\n".to_string()),
+                (GutterHunkKind::Added, 4..5, 4..5, "".to_string()),
+            ]
+        )
+    }
+}
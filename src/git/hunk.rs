@@ -53,47 +53,36 @@ impl Hunk {
                     )),
                 })?;
 
-                #[derive(PartialEq)]
-                enum LineKind {
-                    Delete,
-                    Insert,
-                }
                 let (lines, decorations): (Vec<_>, Vec<_>) = group
                     .iter()
                     .flat_map(|diff_op| {
-                        diff.iter_inline_changes(diff_op).enumerate().filter_map(
-                            |(line_index, change)| {
-                                let kind = match change.tag() {
-                                    ChangeTag::Equal => None,
-                                    ChangeTag::Delete => Some(LineKind::Delete),
-                                    ChangeTag::Insert => Some(LineKind::Insert),
-                                }?;
-                                let (words, decorations): (Vec<_>, Vec<_>) = change
-                                    .iter_strings_lossy()
-                                    .scan(0, |column_index, (emphasized, value)| {
-                                        let selection_range = SelectionRange::Position(
-                                            Position::new(line_index, *column_index)
-                                                ..Position::new(
-                                                    line_index,
-                                                    *column_index + value.len(),
-                                                ),
-                                        );
-                                        *column_index += value.len();
-                                        let style_key = match (&kind, emphasized) {
-                                            (LineKind::Delete, true) => StyleKey::HunkOldEmphasized,
-                                            (LineKind::Delete, false) => StyleKey::HunkOld,
-                                            (LineKind::Insert, true) => StyleKey::HunkNewEmphasized,
-                                            (LineKind::Insert, false) => StyleKey::HunkNew,
-                                        };
-                                        let decoration =
-                                            Decoration::new(selection_range, style_key);
-                                        Some((value.to_string(), decoration))
+                        let changes: Vec<(LineKind, Vec<(bool, String)>)> =
+                            match word_diff_line_pairs(&diff, diff_op) {
+                                Some(changes) => changes,
+                                None => diff
+                                    .iter_inline_changes(diff_op)
+                                    .filter_map(|change| {
+                                        let kind = match change.tag() {
+                                            ChangeTag::Equal => None,
+                                            ChangeTag::Delete => Some(LineKind::Delete),
+                                            ChangeTag::Insert => Some(LineKind::Insert),
+                                        }?;
+                                        let words = change
+                                            .iter_strings_lossy()
+                                            .map(|(emphasized, value)| {
+                                                (emphasized, value.to_string())
+                                            })
+                                            .collect_vec();
+                                        Some((kind, words))
                                     })
-                                    .unzip();
-                                let content = words.join("").to_string();
-                                Some(((content, kind), decorations))
-                            },
-                        )
+                                    .collect_vec(),
+                            };
+                        changes
+                            .into_iter()
+                            .enumerate()
+                            .map(|(line_index, (kind, words))| {
+                                decorate_line(line_index, kind, words)
+                            })
                     })
                     .unzip();
                 let content = lines.iter().map(|(line, _)| line.trim_end()).join("\n");
@@ -129,12 +118,104 @@ impl Hunk {
     }
 
     pub(crate) fn to_info(&self) -> Option<crate::components::suggestive_editor::Info> {
-        let info = Info::new("Git Hunk Diff".to_string(), self.content.clone())
-            .set_decorations(self.decorations.clone());
-        Some(info)
+        Some(self.to_info_with_title("Git Hunk Diff"))
+    }
+
+    pub(crate) fn to_info_with_title(&self, title: &str) -> Info {
+        Info::new(title.to_string(), self.content.clone()).set_decorations(self.decorations.clone())
     }
 }
 
+#[derive(PartialEq, Clone, Copy)]
+enum LineKind {
+    Delete,
+    Insert,
+}
+
+/// Returns the word-level changes of `diff_op` if it replaces the same number of lines,
+/// where each old line is diffed against its corresponding new line.
+///
+/// This is preferred over `TextDiff::iter_inline_changes`, because the latter
+/// does not emphasize any word when the old line and the new line are too dissimilar,
+/// which is common when a long identifier is replaced during refactoring.
+fn word_diff_line_pairs(
+    diff: &TextDiff<'_, '_, '_, str>,
+    diff_op: &similar::DiffOp,
+) -> Option<Vec<(LineKind, Vec<(bool, String)>)>> {
+    let similar::DiffOp::Replace {
+        old_index,
+        old_len,
+        new_index,
+        new_len,
+    } = *diff_op
+    else {
+        return None;
+    };
+    if old_len != new_len {
+        return None;
+    }
+    let pairs = diff.old_slices()[old_index..old_index + old_len]
+        .iter()
+        .zip(&diff.new_slices()[new_index..new_index + new_len])
+        .map(|(old, new)| TextDiff::from_unicode_words(*old, *new))
+        .collect_vec();
+    let lines = |kind: LineKind, excluded: ChangeTag| {
+        pairs
+            .iter()
+            .map(|word_diff| {
+                let words = word_diff
+                    .iter_all_changes()
+                    .filter(|change| change.tag() != excluded)
+                    .map(|change| (change.tag() != ChangeTag::Equal, change.value().to_string()))
+                    // Merge adjacent words of the same emphasis, like `TextDiff::iter_inline_changes`
+                    .coalesce(|(previous_emphasized, previous), (emphasized, value)| {
+                        if previous_emphasized == emphasized {
+                            Ok((emphasized, previous + &value))
+                        } else {
+                            Err(((previous_emphasized, previous), (emphasized, value)))
+                        }
+                    })
+                    .collect_vec();
+                (kind, words)
+            })
+            .collect_vec()
+    };
+    Some(
+        lines(LineKind::Delete, ChangeTag::Insert)
+            .into_iter()
+            .chain(lines(LineKind::Insert, ChangeTag::Delete))
+            .collect_vec(),
+    )
+}
+
+/// `words` are the segments of the line, where emphasized segments are the changed words.
+fn decorate_line(
+    line_index: usize,
+    kind: LineKind,
+    words: Vec<(bool, String)>,
+) -> ((String, LineKind), Vec<Decoration>) {
+    let (words, decorations): (Vec<_>, Vec<_>) = words
+        .into_iter()
+        .scan(0, |column_index, (emphasized, value)| {
+            // Position column is char-based, so that non-ASCII words are decorated correctly
+            let len = value.chars().count();
+            let selection_range = SelectionRange::Position(
+                Position::new(line_index, *column_index)
+                    ..Position::new(line_index, *column_index + len),
+            );
+            *column_index += len;
+            let style_key = match (&kind, emphasized) {
+                (LineKind::Delete, true) => StyleKey::HunkOldEmphasized,
+                (LineKind::Delete, false) => StyleKey::HunkOld,
+                (LineKind::Insert, true) => StyleKey::HunkNewEmphasized,
+                (LineKind::Insert, false) => StyleKey::HunkNew,
+            };
+            Some((value, Decoration::new(selection_range, style_key)))
+        })
+        .unzip();
+    ((words.join(""), kind), decorations)
+}
+
 fn leading_whitespace_count(s: &str) -> usize {
     s.chars().take_while(|c| c.is_whitespace()).count()
 }
@@ -226,6 +307,26 @@ mod test_hunk {
             .collect_vec();
         assert_eq!(words, vec!["Hello(", "world", ")", "Hello(", "bumi", ")"]);
     }
+    #[test]
+    fn emphasize_changed_words_of_dissimilar_lines() {
+        let hunks = Hunk::get("let très_long_identifier = 1;", "let x = 1;");
+        assert_eq!(hunks.len(), 1);
+        let buffer = Buffer::new(None, &hunks[0].content);
+        let emphasized_words = hunks[0]
+            .decorations
+            .iter()
+            .filter(|decoration| {
+                [StyleKey::HunkOldEmphasized, StyleKey::HunkNewEmphasized]
+                    .contains(decoration.style_key())
+            })
+            .flat_map(|decoration| -> Result<String, anyhow::Error> {
+                let range = decoration.selection_range().to_char_index_range(&buffer)?;
+                Ok(buffer.slice(&range)?.to_string())
+            })
+            .collect_vec();
+        assert_eq!(emphasized_words, vec!["très_long_identifier", "x"]);
+    }
+
     #[test]
     fn to_info_insertion() {
         let hunk = Hunk::get("a\nd", "a\nb\nc\nd")[0].clone();
@@ -1,3 +1,6 @@
+pub(crate) mod blame;
+pub(crate) mod diff_provider;
+pub(crate) mod gutter;
 pub(crate) mod hunk;
 
 use rayon::prelude::*;
@@ -121,6 +124,14 @@ impl GitRepo {
             DiffMode::UnstagedAgainstCurrentBranch => {
                 Ok(self.repo.head()?.peel_to_commit()?.tree()?)
             }
+            DiffMode::UnstagedAgainstRef(reference) => Ok(self
+                .repo
+                .revparse_single(reference)?
+                .peel_to_commit()?
+                .tree()?),
+            DiffMode::UnsavedAgainstFileOnDisk => Err(anyhow::anyhow!(
+                "The file on disk is not a Git tree, use `diff_provider` instead"
+            )),
         }
     }
 }
@@ -140,39 +151,18 @@ impl FileDiff {
 }
 
 pub trait GitOperation {
-    fn file_diff(&self, diff_mode: &DiffMode, repo: &CanonicalizedPath)
-        -> anyhow::Result<FileDiff>;
     fn content_at_last_commit(
         &self,
         diff_mode: &DiffMode,
         repo: &GitRepo,
     ) -> anyhow::Result<String>;
+
+    /// Returns the content of the file that is staged, which is the same as
+    /// the content at the last commit if nothing is staged.
+    fn content_in_index(&self, repo: &GitRepo) -> anyhow::Result<String>;
 }
 
 impl GitOperation for CanonicalizedPath {
-    fn file_diff(
-        &self,
-        diff_mode: &DiffMode,
-        repo_path: &CanonicalizedPath,
-    ) -> anyhow::Result<FileDiff> {
-        if let Ok(latest_committed_content) =
-            self.content_at_last_commit(diff_mode, &repo_path.try_into()?)
-        {
-            let current_content = self.read()?;
-            let hunks = Hunk::get(&latest_committed_content, &current_content);
-
-            Ok(FileDiff {
-                path: self.clone(),
-                hunks,
-            })
-        } else {
-            Ok(FileDiff {
-                path: self.clone(),
-                hunks: [Hunk::one_insert("[This file is untracked or renamed]")].to_vec(),
-            })
-        }
-    }
-
     fn content_at_last_commit(
         &self,
         diff_mode: &DiffMode,
@@ -186,6 +176,17 @@ impl GitOperation for CanonicalizedPath {
         let content = blob.content().to_vec();
         Ok(String::from_utf8(content)?)
     }
+
+    fn content_in_index(&self, repo: &GitRepo) -> anyhow::Result<String> {
+        let relative_path = self.display_relative_to(repo.path())?;
+        let entry = repo
+            .repo
+            .index()?
+            .get_path(std::path::Path::new(&relative_path), 0)
+            .ok_or_else(|| anyhow::anyhow!("{relative_path} is not in the index"))?;
+        let blob = repo.repo.find_blob(entry.id)?;
+        Ok(String::from_utf8(blob.content().to_vec())?)
+    }
 }
 use git2::DiffOptions;
 
@@ -217,12 +218,21 @@ impl DiffEntry {
     pub(crate) fn new_path(&self) -> CanonicalizedPath {
         self.new_path.clone()
     }
+
+    /// Returns true if this file is untracked, or newly added
+    pub(crate) fn is_new(&self) -> bool {
+        self.old_content.is_none()
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub(crate) enum DiffMode {
     UnstagedAgainstMainBranch,
     UnstagedAgainstCurrentBranch,
+    /// Any revision of the version control system, e.g. a tag, a branch or a commit
+    UnstagedAgainstRef(String),
+    /// Only applicable to buffers
+    UnsavedAgainstFileOnDisk,
 }
 
 impl DiffMode {
@@ -230,6 +240,8 @@ impl DiffMode {
         match self {
             DiffMode::UnstagedAgainstMainBranch => "against main branch".to_string(),
             DiffMode::UnstagedAgainstCurrentBranch => "against current branch".to_string(),
+            DiffMode::UnstagedAgainstRef(reference) => format!("against {reference}"),
+            DiffMode::UnsavedAgainstFileOnDisk => "unsaved against file on disk".to_string(),
         }
     }
 }
@@ -337,6 +349,10 @@ mod test_git {
             super::DiffMode::UnstagedAgainstCurrentBranch,
             "hello\nworld\nlook",
         )?;
+        test(
+            super::DiffMode::UnstagedAgainstRef("HEAD~1".to_string()),
+            "hello\nworld",
+        )?;
         Ok(())
     }
 }
@@ -10,6 +10,7 @@ use itertools::Itertools;
 use my_proc_macros::hex;
 #[cfg(test)]
 use ropey::Rope;
+use std::ops::Range;
 use unicode_width::UnicodeWidthChar;
 
 #[derive(Clone, Debug, PartialEq)]
@@ -31,6 +32,7 @@ pub(crate) struct Cell {
     /// decoration, e.g. Diagnostic
     pub(crate) source: Option<StyleKey>,
     pub(crate) is_bold: bool,
+    pub(crate) is_reversed: bool,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Hash, Copy, PartialOrd, Ord)]
@@ -69,6 +71,7 @@ impl Cell {
             is_cursor: update.is_cursor,
             source: update.source.or(self.source.clone()),
             is_bold: update.style.is_bold || self.is_bold,
+            is_reversed: update.style.is_reversed || self.is_reversed,
         }
     }
 }
@@ -83,6 +86,7 @@ impl Default for Cell {
             is_cursor: false,
             source: None,
             is_bold: false,
+            is_reversed: false,
         }
     }
 }
@@ -170,9 +174,44 @@ pub(crate) enum RenderContentLineNumber {
         /// 0-based
         start_line_index: usize,
         max_line_number: usize,
+        gutter_markers: Vec<GutterMarker>,
+        /// The line ranges hidden by folds, which are skipped by the line numbers.
+        /// If there is any, a fold indicator column is rendered before the border,
+        /// where the line before each hidden range is marked.
+        hidden_line_ranges: Vec<Range<usize>>,
     },
 }
 
+impl RenderContentLineNumber {
+    /// The width of the columns before the content, see `line_number_gutter_width`
+    fn gutter_width(&self) -> usize {
+        match self {
+            RenderContentLineNumber::NoLineNumber => 0,
+            RenderContentLineNumber::LineNumber {
+                max_line_number,
+                hidden_line_ranges,
+                ..
+            } => line_number_gutter_width(*max_line_number, !hidden_line_ranges.is_empty()),
+        }
+    }
+}
+
+/// The width of the line numbers up to `max_line_number`, followed by the fold indicator
+/// column if any line is hidden by folds, and the border before the content.
+pub(crate) fn line_number_gutter_width(max_line_number: usize, has_hidden_lines: bool) -> usize {
+    max_line_number.max(1).to_string().len() + usize::from(has_hidden_lines) + 1
+}
+
+/// A symbol rendered in place of the border between the line numbers and the content,
+/// for example, the change markers of the git gutter.
+#[derive(Clone, Debug)]
+pub(crate) struct GutterMarker {
+    /// 0-based
+    pub(crate) line_index: usize,
+    pub(crate) symbol: &'static str,
+    pub(crate) style: Style,
+}
+
 impl Grid {
     pub(crate) fn new(dimension: Dimension) -> Grid {
         let mut cells: Vec<Vec<Cell>> = vec![];
@@ -248,12 +287,50 @@ impl Grid {
             .fold(self, |grid, update| grid.apply_cell_update(update))
     }
 
+    /// Sets the background of the cells of `row` and of `column` to `background`,
+    /// except the cells whose background is not `default_background`,
+    /// so that the highlights of the cells (e.g. selections) are kept.
+    pub(crate) fn highlight_row_and_column(
+        mut self,
+        row: Option<usize>,
+        column: Option<usize>,
+        default_background: Color,
+        background: Color,
+    ) -> Grid {
+        for (row_index, cells) in self.rows.iter_mut().enumerate() {
+            for (column_index, cell) in cells.iter_mut().enumerate() {
+                if (row == Some(row_index) || column == Some(column_index))
+                    && cell.background_color == default_background
+                {
+                    cell.background_color = background
+                }
+            }
+        }
+        self
+    }
+
     pub(crate) fn merge_vertical(self, bottom: Grid) -> Grid {
         let mut top = self;
         top.rows.extend(bottom.rows);
         top
     }
 
+    /// Both grids are expected to have the same height.
+    pub(crate) fn merge_horizontal(self, right: Grid) -> Grid {
+        Grid {
+            width: self.width + right.width,
+            rows: self
+                .rows
+                .into_iter()
+                .zip(right.rows)
+                .map(|(mut left, right)| {
+                    left.extend(right);
+                    left
+                })
+                .collect(),
+        }
+    }
+
     pub(crate) fn clamp_bottom(self, by: u16) -> Grid {
         let mut grid = self;
         let dimension = grid.dimension();
@@ -333,21 +410,22 @@ impl Grid {
         theme: &Theme,
     ) -> Grid {
         let Dimension { height, width } = self.dimension();
-        let (line_index_start, max_line_number_len, line_number_separator_width) = match line_number
-        {
-            RenderContentLineNumber::NoLineNumber => (0, 0, 0),
+        let (line_index_start, max_line_number_len, hidden_line_ranges) = match &line_number {
+            RenderContentLineNumber::NoLineNumber => (0, 0, Vec::new()),
             RenderContentLineNumber::LineNumber {
                 start_line_index: start_line_number,
                 max_line_number,
+                gutter_markers: _,
+                hidden_line_ranges,
             } => (
-                start_line_number,
+                *start_line_number,
                 max_line_number.max(1).to_string().len(),
-                1,
+                hidden_line_ranges.clone(),
             ),
         };
-        let content_container_width = (width as usize)
-            .saturating_sub(max_line_number_len)
-            .saturating_sub(line_number_separator_width);
+        let fold_column_width = usize::from(!hidden_line_ranges.is_empty());
+        let gutter_width = line_number.gutter_width();
+        let content_container_width = (width as usize).saturating_sub(gutter_width);
 
         let wrapped_lines = soft_wrap::soft_wrap(content, content_container_width);
         let content_cell_updates = {
@@ -405,16 +483,24 @@ impl Grid {
             line_number: usize,
             wrapped: bool,
         }
+        // The line numbers skip the lines hidden by folds
+        let line_indices =
+            crate::fold::visible_lines(&hidden_line_ranges, line_index_start, usize::MAX)
+                .take(wrapped_lines.lines().len())
+                .collect_vec();
         let line_numbers = wrapped_lines
             .lines()
             .iter()
             .flat_map(|line| {
-                let line_number = line.line_number();
+                let line_number = line_indices
+                    .get(line.line_number())
+                    .copied()
+                    .unwrap_or(line.line_number() + line_index_start);
                 line.lines()
                     .into_iter()
                     .enumerate()
                     .map(|(index, _)| LineNumber {
-                        line_number: line_number + line_index_start,
+                        line_number,
                         wrapped: index > 0,
                     })
                     .collect_vec()
@@ -444,6 +530,8 @@ impl Grid {
                 RenderContentLineNumber::LineNumber {
                     start_line_index: _,
                     max_line_number: _,
+                    gutter_markers,
+                    hidden_line_ranges: _,
                 } => line_numbers
                     .into_iter()
                     .enumerate()
@@ -475,13 +563,39 @@ impl Grid {
                                 &theme.ui.line_number,
                             )
                             .into_iter()
-                            .chain(grid.get_row_cell_updates(
-                                line_index,
-                                Some(max_line_number_len),
-                                Some(max_line_number_len + 1),
-                                "│",
-                                &theme.ui.border,
-                            ))
+                            .chain(
+                                (fold_column_width > 0)
+                                    .then(|| {
+                                        let is_folded = !wrapped
+                                            && hidden_line_ranges
+                                                .iter()
+                                                .any(|range| range.start == line_number + 1);
+                                        grid.get_row_cell_updates(
+                                            line_index,
+                                            Some(max_line_number_len),
+                                            Some(max_line_number_len + fold_column_width),
+                                            if is_folded { "▸" } else { " " },
+                                            &theme.ui.line_number,
+                                        )
+                                    })
+                                    .unwrap_or_default(),
+                            )
+                            .chain({
+                                // Wrapped lines are not marked,
+                                // so that the marker of each line is rendered only once
+                                let (symbol, style) = gutter_markers
+                                    .iter()
+                                    .find(|marker| !wrapped && marker.line_index == line_number)
+                                    .map(|marker| (marker.symbol, marker.style))
+                                    .unwrap_or(("│", theme.ui.border));
+                                grid.get_row_cell_updates(
+                                    line_index,
+                                    Some(max_line_number_len + fold_column_width),
+                                    Some(max_line_number_len + fold_column_width + 1),
+                                    symbol,
+                                    &style,
+                                )
+                            })
                             .map(|cell_update| {
                                 CalibratableCellUpdate {
                                     cell_update,
@@ -505,9 +619,7 @@ impl Grid {
                     {
                         Box::new(calibrated_position.into_iter().enumerate().map(
                             move |(index, position)| CellUpdate {
-                                position: position.move_right(
-                                    (max_line_number_len + line_number_separator_width) as u16,
-                                ),
+                                position: position.move_right(gutter_width as u16),
                                 symbol: if index == 0 {
                                     update.cell_update.symbol.clone()
                                 } else {
@@ -583,6 +695,16 @@ pub(crate) enum StyleKey {
     DiagnosticsInformation,
     UiMark,
     UiPossibleSelection,
+    UiMatchingBracket,
+    UiMisspelling,
+    UiWhitespace,
+    UiDimmed,
+    UiIndentGuide,
+    UiIndentGuideActive,
+    UiBreakpoint,
+    UiExecutionLine,
+    UiDocumentHighlightRead,
+    UiDocumentHighlightWrite,
 
     DiagnosticsDefault,
     HunkOld,
@@ -650,6 +772,8 @@ mod test_grid {
                 RenderContentLineNumber::LineNumber {
                     max_line_number: 1,
                     start_line_index: 1,
+                    gutter_markers: Vec::new(),
+                    hidden_line_ranges: Vec::new(),
                 },
                 Vec::new(),
                 Vec::new(),
@@ -659,6 +783,36 @@ mod test_grid {
             assert_eq!(actual, "2│hello")
         }
 
+        #[test]
+        /// The line numbers skip the hidden lines, and the folded lines are marked
+        fn folded_lines() {
+            let actual = Grid::new(Dimension {
+                height: 2,
+                width: 10,
+            })
+            .render_content(
+                "a\nd",
+                RenderContentLineNumber::LineNumber {
+                    max_line_number: 4,
+                    start_line_index: 0,
+                    gutter_markers: Vec::new(),
+                    hidden_line_ranges: [1..3].to_vec(),
+                },
+                Vec::new(),
+                Vec::new(),
+                &Theme::default(),
+            )
+            .to_string();
+            assert_eq!(
+                actual,
+                "
+1▸│a
+4 │d
+"
+                .trim()
+            )
+        }
+
         #[test]
         /// No wrap, no multi-width unicode, multiline
         fn case_1b() {
@@ -671,6 +825,8 @@ mod test_grid {
                 RenderContentLineNumber::LineNumber {
                     max_line_number: 10,
                     start_line_index: 10,
+                    gutter_markers: Vec::new(),
+                    hidden_line_ranges: Vec::new(),
                 },
                 Vec::new(),
                 Vec::new(),
@@ -699,6 +855,8 @@ mod test_grid {
                 RenderContentLineNumber::LineNumber {
                     max_line_number: 0,
                     start_line_index: 0,
+                    gutter_markers: Vec::new(),
+                    hidden_line_ranges: Vec::new(),
                 },
                 Vec::new(),
                 Vec::new(),
@@ -731,6 +889,8 @@ mod test_grid {
                 RenderContentLineNumber::LineNumber {
                     max_line_number: 1,
                     start_line_index: 1,
+                    gutter_markers: Vec::new(),
+                    hidden_line_ranges: Vec::new(),
                 },
                 [CellUpdate {
                     symbol: Some(cursor.to_string()),
@@ -770,6 +930,8 @@ mod test_grid {
                 RenderContentLineNumber::LineNumber {
                     max_line_number: 1,
                     start_line_index: 1,
+                    gutter_markers: Vec::new(),
+                    hidden_line_ranges: Vec::new(),
                 },
                 [CellUpdate {
                     symbol: Some(cursor.to_string()),
@@ -805,6 +967,8 @@ mod test_grid {
                 RenderContentLineNumber::LineNumber {
                     max_line_number: 100,
                     start_line_index: 1,
+                    gutter_markers: Vec::new(),
+                    hidden_line_ranges: Vec::new(),
                 },
                 [].to_vec(),
                 Vec::new(),
@@ -829,6 +993,8 @@ mod test_grid {
                 RenderContentLineNumber::LineNumber {
                     max_line_number: 1,
                     start_line_index: 1,
+                    gutter_markers: Vec::new(),
+                    hidden_line_ranges: Vec::new(),
                 },
                 [].to_vec(),
                 [LineUpdate {
@@ -869,6 +1035,8 @@ mod test_grid {
                     RenderContentLineNumber::LineNumber {
                         max_line_number: 0,
                         start_line_index: 0,
+                        gutter_markers: Vec::new(),
+                        hidden_line_ranges: Vec::new(),
                     },
                     Vec::new(),
                     Vec::new(),
@@ -1008,6 +1176,13 @@ x
     fn test_get_string_width() {
         assert_eq!(get_string_width("\t\t"), 8)
     }
+
+    #[test]
+    fn line_number_gutter_width_includes_the_fold_column() {
+        assert_eq!(super::line_number_gutter_width(0, false), 2);
+        assert_eq!(super::line_number_gutter_width(9, false), 2);
+        assert_eq!(super::line_number_gutter_width(10, true), 4);
+    }
 }
 
 #[cfg(test)]
@@ -1026,6 +1201,7 @@ mod test_cell {
             is_cursor: true,
             source: Some(StyleKey::HunkNew),
             is_bold: true,
+            is_reversed: false,
         };
         let cell = cell.apply_update(CellUpdate {
             position: Position::default(),
@@ -0,0 +1,128 @@
+use std::ops::Range;
+
+use itertools::Itertools;
+use shared::canonicalized_path::CanonicalizedPath;
+
+/// The text width used when hard wrap is toggled on without a specified width.
+pub(crate) const DEFAULT_TEXT_WIDTH: usize = 80;
+
+/// Hard wrap only applies to prose, because breaking lines of code is usually not desired.
+pub(crate) fn is_prose(path: &CanonicalizedPath) -> bool {
+    let path = path.to_path_buf();
+    path.file_name().and_then(|name| name.to_str()) == Some("COMMIT_EDITMSG")
+        || path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| ["md", "mdx", "markdown", "txt"].contains(&extension))
+            .unwrap_or(false)
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct HardWrap {
+    /// The char range (relative to the start of the line) of the whitespaces
+    /// to be replaced with a newline.
+    pub(crate) break_range: Range<usize>,
+    /// The prefix of the continuation line, which preserves the indentation of the line,
+    /// and aligns the continuation with the content of the list item or block quote.
+    pub(crate) continuation: String,
+}
+
+/// Returns `None` if `line` fits `width`, or it cannot be broken
+/// (e.g. the first word after the prefix is longer than `width`)
+pub(crate) fn hard_wrap(line: &str, width: usize) -> Option<HardWrap> {
+    let chars = line.chars().collect_vec();
+    if chars.len() <= width {
+        return None;
+    }
+    let (prefix_len, continuation) = prefix(line);
+
+    // Find the last whitespace such that the broken line fits `width`
+    let break_index = (prefix_len + 1..=width.min(chars.len() - 1))
+        .rev()
+        .find(|index| chars[*index].is_whitespace())?;
+    let start = (prefix_len + 1..=break_index)
+        .rev()
+        .take_while(|index| chars[*index - 1].is_whitespace())
+        .last()
+        .unwrap_or(break_index);
+    let end = (break_index..chars.len())
+        .take_while(|index| chars[*index].is_whitespace())
+        .last()
+        .map(|index| index + 1)
+        .unwrap_or(break_index + 1);
+    Some(HardWrap {
+        break_range: start..end,
+        continuation,
+    })
+}
+
+/// Returns the char length of the indentation plus list marker (or block quote) of `line`,
+/// and the prefix to be used by the continuation line.
+fn prefix(line: &str) -> (usize, String) {
+    let indentation = line
+        .chars()
+        .take_while(|c| c.is_whitespace())
+        .collect::<String>();
+    let rest = &line[indentation.len()..];
+    let bullet_len = ["- ", "* ", "+ "]
+        .into_iter()
+        .find(|bullet| rest.starts_with(bullet))
+        .map(|bullet| bullet.len())
+        .or_else(|| {
+            let digits = rest.chars().take_while(|c| c.is_ascii_digit()).count();
+            let after_digits = &rest[digits..];
+            (digits > 0 && (after_digits.starts_with(". ") || after_digits.starts_with(") ")))
+                .then_some(digits + 2)
+        });
+    let indentation_len = indentation.chars().count();
+    if let Some(bullet_len) = bullet_len {
+        (
+            indentation_len + bullet_len,
+            format!("{}{}", indentation, " ".repeat(bullet_len)),
+        )
+    } else if rest.starts_with("> ") {
+        (indentation_len + 2, format!("{}> ", indentation))
+    } else {
+        (indentation_len, indentation)
+    }
+}
+
+#[cfg(test)]
+mod test_hard_wrap {
+    use super::*;
+
+    #[test]
+    fn fits_width() {
+        assert_eq!(hard_wrap("hello world", 11), None);
+    }
+
+    #[test]
+    fn break_at_last_whitespace_within_width() {
+        assert_eq!(
+            hard_wrap("hello world  spam", 14),
+            Some(HardWrap {
+                break_range: 11..13,
+                continuation: "".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn unbreakable_word() {
+        assert_eq!(hard_wrap("  - helloworld", 8), None);
+    }
+
+    #[test]
+    fn preserve_indentation_and_list_marker() {
+        let test = |line: &str, expected_continuation: &str| {
+            assert_eq!(
+                hard_wrap(line, 12).map(|wrap| wrap.continuation),
+                Some(expected_continuation.to_string())
+            )
+        };
+        test("  hello world spam", "  ");
+        test("  - hello world", "    ");
+        test("12. hello world", "    ");
+        test("> hello world spam", "> ");
+    }
+}
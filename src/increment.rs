@@ -0,0 +1,227 @@
+//! Increments (or decrements) the number or the date under the cursor, or the first one after
+//! the cursor on the same line.
+//!
+//! Hexadecimal (`0xff`) and binary (`0b101`) numbers keep their prefix, width and letter case,
+//! and decimal numbers keep their leading zeros. For dates (`YYYY-MM-DD`), the year, month or
+//! day under the cursor is bumped, while the day is bumped if the cursor is before the date.
+
+use std::ops::Range;
+
+pub(crate) struct Incremented {
+    /// The character range of the number within the line
+    pub(crate) range: Range<usize>,
+    pub(crate) new: String,
+}
+
+/// `column` is the character index of the cursor within the `line`.
+pub(crate) fn increment(line: &str, column: usize, amount: i64) -> Option<Incremented> {
+    let cursor = line
+        .char_indices()
+        .nth(column)
+        .map_or(line.len(), |(byte, _)| byte);
+    let number = lazy_regex::regex!(r"0[xX][0-9a-fA-F]+|0[bB][01]+|-?\d+")
+        .find_iter(line)
+        .find(|found| found.end() > cursor);
+    let date = lazy_regex::regex!(r"\d{4}-\d{2}-\d{2}")
+        .find_iter(line)
+        .find(|found| found.end() > cursor)
+        .and_then(|found| Some((found, Date::parse(found.as_str())?)));
+    let (start, end, new) = match (date, number) {
+        (Some((found, date)), number)
+            if number.map_or(true, |number| found.start() <= number.start()) =>
+        {
+            let new = match cursor.checked_sub(found.start()) {
+                Some(0..=4) => date.add_months(amount.checked_mul(12)?),
+                Some(5..=7) => date.add_months(amount),
+                _ => date.add_days(amount),
+            }?;
+            (found.start(), found.end(), new.to_string())
+        }
+        (_, number) => {
+            let number = number?;
+            let mut start = number.start();
+            let mut text = number.as_str();
+            // A dash after a word is not a minus sign, for example `foo-1`
+            if let Some(digits) = text.strip_prefix('-').filter(|_| {
+                line[..start]
+                    .chars()
+                    .next_back()
+                    .is_some_and(char::is_alphanumeric)
+            }) {
+                start += 1;
+                text = digits
+            }
+            (start, number.end(), increment_number(text, amount)?)
+        }
+    };
+    let to_column = |byte: usize| line[..byte].chars().count();
+    Some(Incremented {
+        range: to_column(start)..to_column(end),
+        new,
+    })
+}
+
+fn increment_number(text: &str, amount: i64) -> Option<String> {
+    let (prefix, digits) = text.split_at(text.len().min(2));
+    match prefix {
+        "0x" | "0X" => {
+            let value = u64::from_str_radix(digits, 16)
+                .ok()?
+                .wrapping_add_signed(amount);
+            let width = digits.len();
+            Some(if digits.chars().any(|c| c.is_ascii_uppercase()) {
+                format!("{prefix}{value:0width$X}")
+            } else {
+                format!("{prefix}{value:0width$x}")
+            })
+        }
+        "0b" | "0B" => {
+            let value = u64::from_str_radix(digits, 2)
+                .ok()?
+                .wrapping_add_signed(amount);
+            Some(format!("{prefix}{value:0width$b}", width = digits.len()))
+        }
+        _ => {
+            let value = text.parse::<i64>().ok()?.checked_add(amount)?;
+            let digits = text.trim_start_matches('-');
+            if digits.len() > 1 && digits.starts_with('0') {
+                let sign = if value < 0 { "-" } else { "" };
+                Some(format!(
+                    "{sign}{:0width$}",
+                    value.unsigned_abs(),
+                    width = digits.len()
+                ))
+            } else {
+                Some(value.to_string())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Date {
+    year: i64,
+    month: i64,
+    day: i64,
+}
+
+impl Date {
+    fn parse(text: &str) -> Option<Date> {
+        let mut parts = text.splitn(3, '-').map(|part| part.parse::<i64>().ok());
+        let (year, month, day) = (parts.next()??, parts.next()??, parts.next()??);
+        ((1..=12).contains(&month) && (1..=days_in_month(year, month)).contains(&day))
+            .then_some(Date { year, month, day })
+    }
+
+    /// The day is clamped to the last day of the new month, e.g. `2024-01-31` plus a month is
+    /// `2024-02-29`.
+    fn add_months(self, months: i64) -> Option<Date> {
+        let months = (self.year * 12 + self.month - 1).checked_add(months)?;
+        let (year, month) = (months.div_euclid(12), months.rem_euclid(12) + 1);
+        (0..=9999).contains(&year).then(|| Date {
+            year,
+            month,
+            day: self.day.min(days_in_month(year, month)),
+        })
+    }
+
+    fn add_days(self, days: i64) -> Option<Date> {
+        let date = Date::from_days(self.to_days().checked_add(days)?);
+        (0..=9999).contains(&date.year).then_some(date)
+    }
+
+    /// The number of days since `1970-01-01`, see
+    /// <https://howardhinnant.github.io/date_algorithms.html#days_from_civil>.
+    fn to_days(self) -> i64 {
+        let year = if self.month <= 2 {
+            self.year - 1
+        } else {
+            self.year
+        };
+        let era = year.div_euclid(400);
+        let year_of_era = year.rem_euclid(400);
+        let day_of_year = (153 * ((self.month + 9) % 12) + 2) / 5 + self.day - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        era * 146097 + day_of_era - 719468
+    }
+
+    /// The inverse of `to_days`.
+    fn from_days(days: i64) -> Date {
+        let days = days + 719468;
+        let era = days.div_euclid(146097);
+        let day_of_era = days.rem_euclid(146097);
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month_index = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+        let month = if month_index < 10 {
+            month_index + 3
+        } else {
+            month_index - 9
+        };
+        let year = year_of_era + era * 400 + i64::from(month <= 2);
+        Date { year, month, day }
+    }
+}
+
+impl std::fmt::Display for Date {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+#[cfg(test)]
+mod test_increment {
+    use super::*;
+
+    fn run(line: &str, column: usize, amount: i64) -> Option<String> {
+        let incremented = increment(line, column, amount)?;
+        let chars = line.chars().collect::<Vec<_>>();
+        Some(format!(
+            "{}{}{}",
+            chars[..incremented.range.start].iter().collect::<String>(),
+            incremented.new,
+            chars[incremented.range.end..].iter().collect::<String>()
+        ))
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(run("x = 9;", 0, 1).as_deref(), Some("x = 10;"));
+        assert_eq!(run("x = 9; y = 1", 6, 1).as_deref(), Some("x = 9; y = 2"));
+        assert_eq!(run("x = 1", 4, -3).as_deref(), Some("x = -2"));
+        assert_eq!(run("x = -1", 4, 3).as_deref(), Some("x = 2"));
+        assert_eq!(run("foo-1", 0, 1).as_deref(), Some("foo-2"));
+        assert_eq!(run("007", 0, 1).as_deref(), Some("008"));
+        assert_eq!(run("0x0f 0xFF", 0, 1).as_deref(), Some("0x10 0xFF"));
+        assert_eq!(run("0x0f 0xFF", 5, 1).as_deref(), Some("0x0f 0x100"));
+        assert_eq!(run("0b011", 0, 1).as_deref(), Some("0b100"));
+        assert_eq!(run("αβ 1", 0, 1).as_deref(), Some("αβ 2"));
+        assert_eq!(run("x = 1; y", 6, 1), None);
+    }
+
+    #[test]
+    fn dates() {
+        // The day is bumped when the cursor is before the date
+        assert_eq!(run("on 2023-12-31", 0, 1).as_deref(), Some("on 2024-01-01"));
+        assert_eq!(run("2024-02-28", 9, 1).as_deref(), Some("2024-02-29"));
+        assert_eq!(run("2024-03-01", 8, -1).as_deref(), Some("2024-02-29"));
+        // Month, clamped to the last day of the month
+        assert_eq!(run("2024-01-31", 5, 1).as_deref(), Some("2024-02-29"));
+        assert_eq!(run("2024-01-15", 6, -1).as_deref(), Some("2023-12-15"));
+        // Year
+        assert_eq!(run("2024-02-29", 2, 1).as_deref(), Some("2025-02-28"));
+        // Not a valid date
+        assert_eq!(run("2024-13-01", 0, 1).as_deref(), Some("2025-13-01"));
+    }
+}
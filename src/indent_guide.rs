@@ -0,0 +1,252 @@
+//! Vertical guides at each indentation level of the visible lines, where the guide of the scope
+//! that contains the cursor is highlighted.
+//!
+//! The levels are computed from the leading whitespaces of the lines, and blank lines take the
+//! levels of the lines around them, so that the guides are not broken by blank lines.
+
+use std::{cell::RefCell, collections::HashMap, ops::Range};
+
+use ropey::Rope;
+
+use crate::{config::Indentation, grid::get_char_width};
+
+/// The levels of a blank line are taken from the nearest non-blank lines within this number of
+/// lines above and below it.
+const BLANK_LINE_SCAN_LIMIT: usize = 100;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct IndentGuide {
+    /// 0-based
+    pub(crate) line: usize,
+    /// The char column, which is beyond the end of blank lines that are shallower than
+    /// the lines around them
+    pub(crate) column: usize,
+    /// True if this is the guide of the scope that contains the cursor
+    pub(crate) is_active: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Key {
+    revision: usize,
+    line_range: Range<usize>,
+    cursor_line: usize,
+    width: usize,
+}
+
+/// The guides of the visible lines are only computed again once the content, the visible lines
+/// or the cursor line are changed.
+#[derive(Default)]
+pub(crate) struct IndentGuidesCache(RefCell<Option<(Key, Vec<IndentGuide>)>>);
+
+impl IndentGuidesCache {
+    /// `revision` is the revision of the content of `rope`, see `Buffer::revision`,
+    /// and `width` is the number of columns of an indentation level.
+    pub(crate) fn get(
+        &self,
+        rope: &Rope,
+        revision: usize,
+        line_range: Range<usize>,
+        cursor_line: usize,
+        width: usize,
+    ) -> Vec<IndentGuide> {
+        let key = Key {
+            revision,
+            line_range,
+            cursor_line,
+            width,
+        };
+        if let Some((cached_key, guides)) = self.0.borrow().as_ref() {
+            if cached_key == &key {
+                return guides.clone();
+            }
+        }
+        let guides = indent_guides(rope, key.line_range.clone(), cursor_line, width);
+        *self.0.borrow_mut() = Some((key, guides.clone()));
+        guides
+    }
+}
+
+/// The number of columns of an indentation level, where a tab takes the columns that it is
+/// rendered in.
+pub(crate) fn level_width(indentation: Indentation) -> usize {
+    if indentation.expand_tab {
+        indentation.width
+    } else {
+        get_char_width('\t')
+    }
+}
+
+pub(crate) fn indent_guides(
+    rope: &Rope,
+    line_range: Range<usize>,
+    cursor_line: usize,
+    width: usize,
+) -> Vec<IndentGuide> {
+    let width = width.max(1);
+    let mut levels = Levels {
+        rope,
+        width,
+        cache: HashMap::new(),
+    };
+    // The scope of a line that opens a block (e.g. `fn main() {`) is the block,
+    // otherwise it is the scope that the line is indented in
+    let active = {
+        let level = levels.get(cursor_line);
+        let next_level = levels.get(cursor_line + 1);
+        if next_level > level {
+            Some((cursor_line + 1, level))
+        } else {
+            level.checked_sub(1).map(|index| (cursor_line, index))
+        }
+    };
+    let active_lines = active
+        .filter(|(anchor, _)| line_range.contains(anchor))
+        .map(|(anchor, index)| {
+            let start = (line_range.start..anchor)
+                .rev()
+                .take_while(|line| levels.get(*line) > index)
+                .last()
+                .unwrap_or(anchor);
+            let end = (anchor..line_range.end)
+                .take_while(|line| levels.get(*line) > index)
+                .last()
+                .map(|line| line + 1)
+                .unwrap_or(anchor);
+            (index, start..end)
+        });
+    line_range
+        .flat_map(|line| {
+            let count = levels.get(line);
+            let content = levels.content(line);
+            level_columns(&content, width, count)
+                .into_iter()
+                .enumerate()
+                .map(|(index, column)| IndentGuide {
+                    line,
+                    column,
+                    is_active: active_lines.as_ref().is_some_and(|(active_index, lines)| {
+                        index == *active_index && lines.contains(&line)
+                    }),
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+struct Levels<'a> {
+    rope: &'a Rope,
+    width: usize,
+    /// The number of levels of each line that is computed
+    cache: HashMap<usize, usize>,
+}
+
+impl Levels<'_> {
+    /// Without the line ending
+    fn content(&self, line: usize) -> String {
+        self.rope
+            .get_line(line)
+            .map(|line| line.to_string().trim_end_matches(['\r', '\n']).to_string())
+            .unwrap_or_default()
+    }
+
+    /// `None` if the line is blank
+    fn non_blank(&self, line: usize) -> Option<usize> {
+        let content = self.content(line);
+        (!content.trim().is_empty()).then(|| level_columns(&content, self.width, usize::MAX).len())
+    }
+
+    fn get(&mut self, line: usize) -> usize {
+        if let Some(count) = self.cache.get(&line) {
+            return *count;
+        }
+        let count = self.non_blank(line).unwrap_or_else(|| {
+            let above = (line.saturating_sub(BLANK_LINE_SCAN_LIMIT)..line)
+                .rev()
+                .find_map(|line| self.non_blank(line));
+            let below = (line + 1..(line + 1 + BLANK_LINE_SCAN_LIMIT).min(self.rope.len_lines()))
+                .find_map(|line| self.non_blank(line));
+            above.unwrap_or(0).min(below.unwrap_or(0))
+        });
+        self.cache.insert(line, count);
+        count
+    }
+}
+
+/// The char columns of at most `count` levels of the leading whitespaces of `content`,
+/// which is extended with spaces if it is shorter.
+fn level_columns(content: &str, width: usize, count: usize) -> Vec<usize> {
+    let mut columns = Vec::new();
+    let mut indentation = 0;
+    for (column, character) in content.chars().chain(std::iter::repeat(' ')).enumerate() {
+        if columns.len() == count || !matches!(character, ' ' | '\t') {
+            break;
+        }
+        if indentation % width == 0 {
+            columns.push(column)
+        }
+        indentation += get_char_width(character)
+    }
+    columns
+}
+
+#[cfg(test)]
+mod test_indent_guide {
+    use super::*;
+
+    fn render(content: &str, cursor_line: usize) -> String {
+        let rope = Rope::from_str(content);
+        let guides = indent_guides(&rope, 0..rope.len_lines(), cursor_line, 2);
+        content
+            .lines()
+            .enumerate()
+            .map(|(line_index, line)| {
+                let guides = guides
+                    .iter()
+                    .filter(|guide| guide.line == line_index)
+                    .collect::<Vec<_>>();
+                let length = guides
+                    .iter()
+                    .map(|guide| guide.column + 1)
+                    .max()
+                    .unwrap_or(0)
+                    .max(line.chars().count());
+                line.chars()
+                    .chain(std::iter::repeat(' '))
+                    .take(length)
+                    .enumerate()
+                    .map(|(column, character)| {
+                        match guides.iter().find(|guide| guide.column == column) {
+                            Some(guide) if guide.is_active => '┃',
+                            Some(_) => '│',
+                            None => character,
+                        }
+                    })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn blank_lines_take_the_levels_around_them() {
+        let content = "a {\n  b {\n    c\n\n    d\n  }\n}";
+        assert_eq!(render(content, 2), "a {\n│ b {\n│ ┃ c\n│ ┃\n│ ┃ d\n│ }\n}");
+    }
+
+    #[test]
+    fn the_scope_of_a_block_opener_is_its_block() {
+        let content = "a {\n  b {\n    c\n  }\n}";
+        assert_eq!(render(content, 1), "a {\n│ b {\n│ ┃ c\n│ }\n}");
+        assert_eq!(render(content, 0), "a {\n┃ b {\n┃ │ c\n┃ }\n}");
+    }
+
+    #[test]
+    fn tabs_are_levels() {
+        let rope = Rope::from_str("\t\ta");
+        let columns = indent_guides(&rope, 0..1, 1, 4)
+            .into_iter()
+            .map(|guide| guide.column)
+            .collect::<Vec<_>>();
+        assert_eq!(columns, [0, 1]);
+    }
+}
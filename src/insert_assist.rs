@@ -0,0 +1,270 @@
+//! Assistance for typing in Insert mode, namely closing brackets and quotes automatically,
+//! indenting new lines, and appending the terminator of statements and list elements.
+
+use itertools::Itertools;
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum AutoPair {
+    /// Insert the given closing character after the typed opening character
+    Close(char),
+    /// Move over the auto-inserted closing character after the cursor instead of inserting it again
+    SkipOver,
+}
+
+/// `previous` and `next` are the characters before and after the cursor, where
+/// `next_is_auto_closing` is true if `next` is a closing character inserted by [`AutoPair::Close`].
+pub(crate) fn auto_pair(
+    pairs: &[(char, char)],
+    typed: char,
+    previous: Option<char>,
+    next: Option<char>,
+    next_is_auto_closing: bool,
+) -> Option<AutoPair> {
+    let is_closing = |c: char| pairs.iter().any(|(_, close)| *close == c);
+    if next_is_auto_closing && next == Some(typed) && is_closing(typed) {
+        return Some(AutoPair::SkipOver);
+    }
+    let close = pairs
+        .iter()
+        .find(|(open, _)| *open == typed)
+        .map(|(_, close)| *close)?;
+
+    // Only close the pair if it does not enclose the text after the cursor
+    let next_allows_pair = next.map_or(true, |next| next.is_whitespace() || is_closing(next));
+
+    // Quotes are not paired after a word, e.g. the apostrophe of "don't"
+    let previous_allows_pair = typed != close
+        || !previous.is_some_and(|previous| previous.is_alphanumeric() || previous == '_');
+
+    (next_allows_pair && previous_allows_pair).then_some(AutoPair::Close(close))
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct NewLineIndentation {
+    pub(crate) indentation: String,
+    /// If the cursor is right before the closing bracket of the `@indent` node,
+    /// the closing bracket is moved to another new line with this indentation.
+    pub(crate) outdent: Option<String>,
+}
+
+/// Computes the indentation of the new line inserted at `byte` from the `@indent` captures,
+/// where the new line is indented one level deeper than the line
+/// where the innermost `@indent` node that contains the cursor starts.
+///
+/// Returns `None` if the cursor is not within any `@indent` node.
+pub(crate) fn new_line_indentation(
+    tree: &tree_sitter::Tree,
+    indents_query: &str,
+    content: &str,
+    byte: usize,
+    indent_unit: &str,
+) -> anyhow::Result<Option<NewLineIndentation>> {
+    let query = tree_sitter::Query::new(&tree.language(), indents_query)?;
+    let Some(capture_index) = query.capture_index_for_name("indent") else {
+        return Ok(None);
+    };
+    let Some(node) = tree_sitter::QueryCursor::new()
+        .matches(&query, tree.root_node(), content.as_bytes())
+        .flat_map(|query_match| {
+            query_match
+                .captures
+                .iter()
+                .filter(|capture| capture.index == capture_index)
+                .map(|capture| capture.node)
+                .collect_vec()
+        })
+        .filter(|node| node.start_byte() < byte && byte < node.end_byte())
+        .max_by_key(|node| (node.start_byte(), std::cmp::Reverse(node.end_byte())))
+    else {
+        return Ok(None);
+    };
+    let line_start = content[..node.start_byte()]
+        .rfind('\n')
+        .map(|index| index + 1)
+        .unwrap_or(0);
+    let base: String = content[line_start..]
+        .chars()
+        .take_while(|c| *c == ' ' || *c == '\t')
+        .collect();
+    let is_before_closing_bracket =
+        byte + 1 == node.end_byte() && content[byte..].starts_with([')', ']', '}']);
+    Ok(Some(NewLineIndentation {
+        indentation: format!("{base}{indent_unit}"),
+        outdent: is_before_closing_bracket.then_some(base),
+    }))
+}
+
+/// The kinds of the nodes whose children are separated by commas, even if there is only one child.
+const LIST_KINDS: &[&str] = &[
+    "arguments",
+    "argument_list",
+    "parameters",
+    "formal_parameters",
+    "parameter_list",
+    "type_arguments",
+    "type_parameters",
+    "array",
+    "array_expression",
+    "list",
+    "tuple",
+    "tuple_expression",
+    "object",
+    "dictionary",
+    "field_initializer_list",
+    "field_declaration_list",
+    "enum_variant_list",
+];
+
+/// Returns the byte where the terminator of the innermost statement or list element that contains
+/// `node` is inserted, namely the end of that node, and the terminator, which is `,` for list
+/// elements and `;` for statements.
+///
+/// Statements are the nodes whose kind ends with `statement` or `declaration`, and the children of
+/// blocks and of the root node, except for those ending with a body, such as function definitions.
+pub(crate) fn terminator(node: tree_sitter::Node) -> anyhow::Result<(usize, char)> {
+    let mut node = node;
+    while let Some(parent) = node.parent() {
+        if node.is_named() && !node.kind().contains("comment") {
+            let is_list_element = LIST_KINDS.contains(&parent.kind())
+                || children(parent).any(|child| child.kind() == ",");
+            if is_list_element {
+                return terminate(node, ',');
+            }
+            let is_statement = node.kind().ends_with("statement")
+                || node.kind().ends_with("declaration")
+                || (parent.kind().contains("block") || parent.parent().is_none())
+                    && !ends_with_body(node);
+            if is_statement {
+                return terminate(node, ';');
+            }
+        }
+        node = parent;
+    }
+    Err(anyhow::anyhow!(
+        "The cursor is not within a statement or a list element"
+    ))
+}
+
+fn children(node: tree_sitter::Node) -> impl Iterator<Item = tree_sitter::Node> {
+    (0..node.child_count()).filter_map(move |index| node.child(index))
+}
+
+fn ends_with_body(node: tree_sitter::Node) -> bool {
+    node.child(node.child_count().saturating_sub(1))
+        .is_some_and(|child| {
+            ["block", "body", "declaration_list"]
+                .iter()
+                .any(|kind| child.kind().contains(kind))
+        })
+}
+
+/// The terminator that is missing is parsed by tree-sitter as a zero-width node at the end.
+fn terminate(node: tree_sitter::Node, terminator: char) -> anyhow::Result<(usize, char)> {
+    let terminator_kind = terminator.to_string();
+    let is_terminated = |node: Option<tree_sitter::Node>| {
+        node.is_some_and(|node| node.kind() == terminator_kind && !node.is_missing())
+    };
+    let last_child = node.child(node.child_count().saturating_sub(1));
+    if is_terminated(last_child) || is_terminated(node.next_sibling()) {
+        return Err(anyhow::anyhow!("The {} is already terminated", node.kind()));
+    }
+    let byte = last_child
+        .filter(|child| child.is_missing())
+        .map(|child| child.start_byte())
+        .unwrap_or(node.end_byte());
+    Ok((byte, terminator))
+}
+
+#[cfg(test)]
+mod test_insert_assist {
+    use crate::buffer::Buffer;
+
+    use super::*;
+
+    const PAIRS: &[(char, char)] = &[('(', ')'), ('"', '"')];
+
+    #[test]
+    fn close_and_skip_over_pairs() {
+        assert_eq!(
+            auto_pair(PAIRS, '(', None, None, false),
+            Some(AutoPair::Close(')'))
+        );
+        assert_eq!(
+            auto_pair(PAIRS, ')', Some('('), Some(')'), true),
+            Some(AutoPair::SkipOver)
+        );
+        // Closing characters that are typed by hand are not skipped over
+        assert_eq!(auto_pair(PAIRS, ')', Some('('), Some(')'), false), None);
+        assert_eq!(auto_pair(PAIRS, '(', Some(' '), Some('x'), false), None);
+        assert_eq!(
+            auto_pair(PAIRS, '"', Some(' '), Some(')'), false),
+            Some(AutoPair::Close('"'))
+        );
+        assert_eq!(auto_pair(PAIRS, '"', Some('n'), None, false), None);
+        assert_eq!(auto_pair(PAIRS, 'x', None, None, false), None);
+    }
+
+    #[test]
+    fn indent_within_innermost_node() {
+        let content = "fn f() {\n    if x {}\n}";
+        let buffer = Buffer::new(Some(tree_sitter_rust::language()), content);
+        let tree = buffer.tree().unwrap();
+        let query = include_str!("../contrib/indents/rust.scm");
+        let indentation = |byte| new_line_indentation(tree, query, content, byte, "    ").unwrap();
+
+        let cursor = content.find("if").unwrap();
+        assert_eq!(
+            indentation(cursor),
+            Some(NewLineIndentation {
+                indentation: "    ".to_string(),
+                outdent: None
+            })
+        );
+        let cursor = content.find("{}").unwrap() + 1;
+        assert_eq!(
+            indentation(cursor),
+            Some(NewLineIndentation {
+                indentation: "        ".to_string(),
+                outdent: Some("    ".to_string())
+            })
+        );
+        assert_eq!(indentation(content.len()), None);
+    }
+
+    #[test]
+    fn terminate_innermost_statement_or_list_element() {
+        let find_terminator = |body: &str, text: &str| {
+            let content = format!("fn f() {{\n    {body}\n}}");
+            let buffer = Buffer::new(Some(tree_sitter_rust::language()), &content);
+            let byte = content.find(text).unwrap();
+            let node = buffer
+                .tree()
+                .unwrap()
+                .root_node()
+                .descendant_for_byte_range(byte, byte + text.len())
+                .unwrap();
+            terminator(node)
+                .map(|(byte, terminator)| (content[..byte].to_string(), terminator))
+                .map_err(|error| error.to_string())
+        };
+        let before = |text: &str| format!("fn f() {{\n    {text}");
+        assert_eq!(
+            find_terminator("let x = g(a, b)", "x"),
+            Ok((before("let x = g(a, b)"), ';'))
+        );
+        assert_eq!(find_terminator("g(a, b)", "b"), Ok((before("g(a, b"), ',')));
+        assert_eq!(
+            find_terminator("g(a, b)", "a"),
+            Err("The identifier is already terminated".to_string())
+        );
+        // The tail expression of a block is a statement
+        assert_eq!(find_terminator("h(c)", "h"), Ok((before("h(c)"), ';')));
+        assert_eq!(find_terminator("h(c)", "c"), Ok((before("h(c"), ',')));
+        assert_eq!(
+            find_terminator("i();", "i"),
+            Err("The expression_statement is already terminated".to_string())
+        );
+        // Function definitions end with their body
+        assert!(find_terminator("", "fn").is_err());
+    }
+}
@@ -1,6 +1,3 @@
-use std::sync::mpsc::{channel, Receiver};
-
-use crate::app::AppMessage;
 use shared::canonicalized_path::CanonicalizedPath;
 
 pub(crate) struct TestRunner {
@@ -17,11 +14,12 @@ impl TestRunner {
     pub(crate) fn run(
         callback: impl Fn(CanonicalizedPath) -> anyhow::Result<Option<String>>,
     ) -> anyhow::Result<Option<String>> {
-        let (runner, _) = Self::new()?;
+        let runner = Self::new()?;
         let output = callback(runner.temp_dir.clone())?;
         Ok(output)
     }
-    fn new() -> anyhow::Result<(Self, Receiver<AppMessage>)> {
+    /// The mock repo is removed once the runner is dropped.
+    pub(crate) fn new() -> anyhow::Result<Self> {
         const MOCK_REPO_PATH: &str = "tests/mock_repos/rust1";
 
         let path = tempfile::tempdir()?.into_path();
@@ -34,8 +32,10 @@ impl TestRunner {
 
         // Initialize the repo as a Git repo, so that we can test Git related features
         Self::git_init(temp_dir.clone())?;
-        let (_, receiver) = channel();
-        Ok((Self { temp_dir }, receiver))
+        Ok(Self { temp_dir })
+    }
+    pub(crate) fn temp_dir(&self) -> &CanonicalizedPath {
+        &self.temp_dir
     }
     fn git_init(path: CanonicalizedPath) -> anyhow::Result<()> {
         use git2::{Repository, RepositoryInitOptions};
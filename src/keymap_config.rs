@@ -0,0 +1,334 @@
+//! User-defined key mappings, loaded from `keymaps.json` in the config directory.
+//!
+//! The mappings are grouped by mode, where each key sequence (keys separated by spaces)
+//! is either mapped to another key sequence, which is handled as if it were typed,
+//! or to `null`, which unbinds the default binding of the key sequence:
+//!
+//! ```json5
+//! {
+//!   insert: { "j k": "esc" },
+//!   normal: { "ctrl+s": null, "space w": "space f" },
+//! }
+//! ```
+//!
+//! Mapped keys are not mapped again, so mappings can never loop.
+//!
+//! Key sequences can also be bound to the commands of plugins, see `plugin`.
+
+use std::{collections::BTreeMap, path::Path};
+
+use event::KeyEvent;
+use itertools::Itertools;
+
+use crate::components::editor::Mode;
+
+/// How long the keys typed so far wait for the rest of a key sequence,
+/// after which they are handled as they are.
+pub(crate) const KEY_SEQUENCE_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(1000);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum KeymapMode {
+    Normal,
+    Insert,
+}
+
+impl KeymapMode {
+    fn parse(name: &str) -> anyhow::Result<KeymapMode> {
+        Ok(match name {
+            "normal" => KeymapMode::Normal,
+            "insert" => KeymapMode::Insert,
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "Unknown mode {name:?}, expected \"normal\" or \"insert\""
+                ))
+            }
+        })
+    }
+
+    pub(crate) fn from_mode(mode: &Mode) -> Option<KeymapMode> {
+        match mode {
+            Mode::Normal => Some(KeymapMode::Normal),
+            Mode::Insert => Some(KeymapMode::Insert),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            KeymapMode::Normal => "normal",
+            KeymapMode::Insert => "insert",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Mapping {
+    mode: KeymapMode,
+    /// The key sequence as written in the config, for error messages
+    name: String,
+    from: Vec<KeyEvent>,
+    to: Target,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Target {
+    Keys(Vec<KeyEvent>),
+    /// The name of a command of a plugin
+    Command(String),
+    /// The default binding of the key sequence is removed
+    Unbound,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Lookup {
+    /// The keys are the beginning of at least one mapped key sequence
+    Pending,
+    Mapped(Vec<KeyEvent>),
+    Command(String),
+    Unbound,
+    /// The keys are not the beginning of any mapped key sequence
+    Unmapped,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct KeymapConfig {
+    mappings: Vec<Mapping>,
+}
+
+impl KeymapConfig {
+    pub(crate) fn file_path() -> std::path::PathBuf {
+        grammar::config_dir().join("keymaps.json")
+    }
+
+    /// Returns an empty config if `path` does not exist.
+    pub(crate) fn load(path: &Path) -> anyhow::Result<KeymapConfig> {
+        if !path.exists() {
+            return Ok(KeymapConfig::default());
+        }
+        let content = std::fs::read_to_string(path)?;
+        KeymapConfig::parse(&content)
+            .map_err(|error| anyhow::anyhow!("Invalid keymaps in {}:\n{error}", path.display()))
+    }
+
+    /// Every invalid key and conflicting key sequence is reported at once.
+    pub(crate) fn parse(content: &str) -> anyhow::Result<KeymapConfig> {
+        let config: BTreeMap<String, BTreeMap<String, Option<String>>> =
+            serde_json5::from_str(content)?;
+        let mut errors = Vec::new();
+        let mut mappings = Vec::new();
+        for (mode, entries) in config {
+            let mode = match KeymapMode::parse(&mode) {
+                Ok(mode) => mode,
+                Err(error) => {
+                    errors.push(error.to_string());
+                    continue;
+                }
+            };
+            for (from, to) in entries {
+                let parsed = parse_key_sequence(&from).and_then(|from_keys| {
+                    Ok(Mapping {
+                        mode,
+                        name: from.clone(),
+                        from: from_keys,
+                        to: match to.as_deref() {
+                            Some(to) => Target::Keys(parse_key_sequence(to)?),
+                            None => Target::Unbound,
+                        },
+                    })
+                });
+                match parsed {
+                    Ok(mapping) => mappings.push(mapping),
+                    Err(error) => errors.push(format!("{} mode {from:?}: {error}", mode.name())),
+                }
+            }
+        }
+        errors.extend(conflicts(&mappings));
+        if errors.is_empty() {
+            Ok(KeymapConfig { mappings })
+        } else {
+            Err(anyhow::anyhow!("{}", errors.join("\n")))
+        }
+    }
+
+    /// Binds `keys` of `mode` (`"normal"` or `"insert"`) to the plugin command of `command`,
+    /// which must not conflict with the other mappings.
+    pub(crate) fn add_command(
+        &mut self,
+        mode: &str,
+        keys: &str,
+        command: String,
+    ) -> anyhow::Result<()> {
+        let mode = KeymapMode::parse(mode)?;
+        let from = parse_key_sequence(keys)
+            .map_err(|error| anyhow::anyhow!("{} mode {keys:?}: {error}", mode.name()))?;
+        let mut mappings = self.mappings.clone();
+        mappings.push(Mapping {
+            mode,
+            name: keys.to_string(),
+            from,
+            to: Target::Command(command),
+        });
+        let errors = conflicts(&mappings);
+        if errors.is_empty() {
+            self.mappings = mappings;
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!("{}", errors.join("\n")))
+        }
+    }
+
+    /// Returns the rest of every key sequence that begins with `keys`,
+    /// together with what the key sequence is mapped to.
+    pub(crate) fn continuations(
+        &self,
+        mode: KeymapMode,
+        keys: &[KeyEvent],
+    ) -> Vec<(&[KeyEvent], &Target)> {
+        self.mappings
+            .iter()
+            .filter(|mapping| mapping.mode == mode && mapping.from.starts_with(keys))
+            .map(|mapping| (&mapping.from[keys.len()..], &mapping.to))
+            .collect_vec()
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.mappings.is_empty()
+    }
+
+    pub(crate) fn lookup(&self, mode: KeymapMode, keys: &[KeyEvent]) -> Lookup {
+        let mappings = || self.mappings.iter().filter(|mapping| mapping.mode == mode);
+        if let Some(mapping) = mappings().find(|mapping| mapping.from == keys) {
+            return match &mapping.to {
+                Target::Keys(to) => Lookup::Mapped(to.clone()),
+                Target::Command(command) => Lookup::Command(command.clone()),
+                Target::Unbound => Lookup::Unbound,
+            };
+        }
+        if mappings().any(|mapping| mapping.from.starts_with(keys)) {
+            Lookup::Pending
+        } else {
+            Lookup::Unmapped
+        }
+    }
+}
+
+fn parse_key_sequence(keys: &str) -> anyhow::Result<Vec<KeyEvent>> {
+    let keys = keys
+        .split_whitespace()
+        .map(|key| {
+            event::parse_key_event(key)
+                .map_err(|error| anyhow::anyhow!("Invalid key {key:?} ({error})"))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    if keys.is_empty() {
+        return Err(anyhow::anyhow!("Empty key sequence"));
+    }
+    Ok(keys)
+}
+
+/// A key sequence conflicts with another of the same mode if they are identical
+/// (e.g. `"A"` and `"shift+A"`), or if it is the beginning of the other,
+/// in which case the other could never be typed.
+fn conflicts(mappings: &[Mapping]) -> Vec<String> {
+    mappings
+        .iter()
+        .tuple_combinations()
+        .filter(|(a, b)| a.mode == b.mode)
+        .filter_map(|(a, b)| {
+            let mode = a.mode.name();
+            if a.from == b.from {
+                Some(format!(
+                    "{mode} mode: {:?} and {:?} are the same key sequence",
+                    a.name, b.name
+                ))
+            } else {
+                let (prefix, sequence) = if b.from.starts_with(&a.from) {
+                    (a, b)
+                } else if a.from.starts_with(&b.from) {
+                    (b, a)
+                } else {
+                    return None;
+                };
+                Some(format!(
+                    "{mode} mode: {:?} is the beginning of {:?}, which could never be typed",
+                    prefix.name, sequence.name
+                ))
+            }
+        })
+        .collect_vec()
+}
+
+#[cfg(test)]
+mod test_keymap_config {
+    use super::*;
+
+    fn keys(keys: &str) -> Vec<KeyEvent> {
+        event::parse_key_events(keys).unwrap()
+    }
+
+    #[test]
+    fn lookup_key_sequences() {
+        let config = KeymapConfig::parse(
+            r#"{ insert: { "j k": "esc" }, normal: { "ctrl+s": null, "space w": "space f" } }"#,
+        )
+        .unwrap();
+        let insert = KeymapMode::Insert;
+        assert_eq!(config.lookup(insert, &keys("j")), Lookup::Pending);
+        assert_eq!(
+            config.lookup(insert, &keys("j k")),
+            Lookup::Mapped(keys("esc"))
+        );
+        assert_eq!(config.lookup(insert, &keys("j x")), Lookup::Unmapped);
+        assert_eq!(config.lookup(insert, &keys("ctrl+s")), Lookup::Unmapped);
+        let normal = KeymapMode::Normal;
+        assert_eq!(config.lookup(normal, &keys("ctrl+s")), Lookup::Unbound);
+        assert_eq!(
+            config.lookup(normal, &keys("space w")),
+            Lookup::Mapped(keys("space f"))
+        );
+    }
+
+    #[test]
+    fn add_plugin_commands() {
+        let mut config = KeymapConfig::parse(r#"{ normal: { "space w": "space f" } }"#).unwrap();
+        config
+            .add_command("normal", "space u", "upcase".to_string())
+            .unwrap();
+        assert_eq!(
+            config.lookup(KeymapMode::Normal, &keys("space u")),
+            Lookup::Command("upcase".to_string())
+        );
+        let error = config
+            .add_command("normal", "space w x", "other".to_string())
+            .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("\"space w\" is the beginning of \"space w x\""));
+        assert_eq!(
+            config.lookup(KeymapMode::Normal, &keys("space w")),
+            Lookup::Mapped(keys("space f"))
+        );
+    }
+
+    #[test]
+    fn report_every_error() {
+        let error = KeymapConfig::parse(
+            r#"{
+              visual: { "a": "b" },
+              normal: { "ctrl+foo": "a", "b": "", "g": "x", "g g": "y", "A": "z", "shift+A": "z" },
+            }"#,
+        )
+        .unwrap_err()
+        .to_string();
+        assert_eq!(
+            error.lines().sorted().collect_vec(),
+            [
+                "Unknown mode \"visual\", expected \"normal\" or \"insert\"",
+                "normal mode \"b\": Empty key sequence",
+                "normal mode \"ctrl+foo\": Invalid key \"ctrl+foo\" (UnknownKeyCode(\"foo\"))",
+                "normal mode: \"A\" and \"shift+A\" are the same key sequence",
+                "normal mode: \"g\" is the beginning of \"g g\", which could never be typed",
+            ]
+        );
+    }
+}
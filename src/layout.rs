@@ -1,15 +1,20 @@
 use crate::quickfix_list::QuickfixList;
 use crate::ui_tree::{ComponentKind, KindedComponent, UiTree};
 use crate::{
-    app::{Dimension, Dispatches},
+    app::{AppMessage, Dimension, Dispatches},
     buffer::Buffer,
     components::{
         component::{Component, ComponentId},
+        debug_panel::DebugPanel,
+        diff_view::DiffView,
         editor::Editor,
         file_explorer::FileExplorer,
+        hierarchy_view::HierarchyView,
         keymap_legend::{KeymapLegend, KeymapLegendConfig},
-        prompt::Prompt,
+        markdown_preview::MarkdownPreview,
         suggestive_editor::{Info, SuggestiveEditor},
+        task_output::TaskOutput,
+        terminal::Terminal,
     },
     context::QuickfixListSource,
     quickfix_list::{Location, QuickfixListItem},
@@ -20,7 +25,7 @@ use indexmap::IndexMap;
 use itertools::Itertools;
 use nary_tree::NodeId;
 use shared::canonicalized_path::CanonicalizedPath;
-use std::{cell::RefCell, rc::Rc};
+use std::{cell::RefCell, rc::Rc, sync::mpsc::Sender};
 
 /// The layout of the app is split into multiple sections: the main panel, info panel, quickfix
 /// lists, prompts, and etc.
@@ -30,6 +35,7 @@ pub(crate) struct Layout {
     background_suggestive_editors: IndexMap<CanonicalizedPath, Rc<RefCell<SuggestiveEditor>>>,
     background_file_explorer: Rc<RefCell<FileExplorer>>,
     background_quickfix_list: Option<Rc<RefCell<Editor>>>,
+    background_terminal: Option<Rc<RefCell<Terminal>>>,
 
     rectangles: Vec<Rectangle>,
     borders: Vec<Border>,
@@ -48,6 +54,7 @@ impl Layout {
         let tree = UiTree::new();
         Ok(Layout {
             background_quickfix_list: None,
+            background_terminal: None,
             background_suggestive_editors: IndexMap::new(),
             background_file_explorer: Rc::new(RefCell::new(FileExplorer::new(working_directory)?)),
             rectangles,
@@ -61,6 +68,16 @@ impl Layout {
         self.tree.components()
     }
 
+    pub(crate) fn get_component_by_id(
+        &self,
+        id: ComponentId,
+    ) -> Option<Rc<RefCell<dyn Component>>> {
+        self.components()
+            .into_iter()
+            .map(|component| component.component())
+            .find(|component| component.borrow().id() == id)
+    }
+
     pub(crate) fn get_current_component(&self) -> Rc<RefCell<dyn Component>> {
         self.get_component(self.tree.focused_component_id())
     }
@@ -105,7 +122,7 @@ impl Layout {
     pub(crate) fn add_and_focus_prompt(
         &mut self,
         kind: ComponentKind,
-        component: Rc<RefCell<Prompt>>,
+        component: Rc<RefCell<dyn Component>>,
     ) {
         self.tree
             .append_component_to_current(KindedComponent::new(kind, component), true);
@@ -212,6 +229,10 @@ impl Layout {
         )
     }
 
+    pub(crate) fn current_component_is_keymap_legend(&self) -> bool {
+        self.tree.get_current_node().data().kind() == ComponentKind::KeymapLegend
+    }
+
     pub(crate) fn remain_only_current_component(&mut self) {
         self.tree.remain_only_current_component()
     }
@@ -245,6 +266,16 @@ impl Layout {
         self.background_suggestive_editors.shift_remove(path);
     }
 
+    /// If the buffer of `path` is shown in the current window, the next buffer will be shown instead.
+    pub(crate) fn close_buffer(&mut self, path: &CanonicalizedPath) {
+        let current_path = self.get_current_component().borrow().path();
+        if current_path.as_ref() == Some(path) {
+            self.remove_current_component()
+        } else {
+            self.remove_suggestive_editor(path)
+        }
+    }
+
     pub(crate) fn refresh_file_explorer(
         &self,
         working_directory: &CanonicalizedPath,
@@ -254,6 +285,124 @@ impl Layout {
             .refresh(working_directory)
     }
 
+    /// The terminal is spawned when it is opened for the first time (or after its shell exits),
+    /// and it keeps running in the background after its window is closed.
+    ///
+    /// The terminal is opened in the side panel, unless `main_panel` is true,
+    /// in which case it replaces the windows like the file explorer does,
+    /// and it is moved there if it is opened in the other panel already.
+    pub(crate) fn open_terminal(
+        &mut self,
+        working_directory: &CanonicalizedPath,
+        sender: Sender<AppMessage>,
+        main_panel: bool,
+    ) -> anyhow::Result<()> {
+        let terminal = match &self.background_terminal {
+            Some(terminal) => terminal.clone(),
+            None => {
+                let terminal = Rc::new(RefCell::new(Terminal::new(working_directory, sender)?));
+                self.background_terminal = Some(terminal.clone());
+                terminal
+            }
+        };
+        if main_panel {
+            self.tree.remove_all_root_children();
+        }
+        self.tree
+            .replace_root_node_child(ComponentKind::Terminal, terminal, true);
+        self.recalculate_layout();
+        Ok(())
+    }
+
+    /// The window of the terminal is closed, and a new terminal is spawned when it is opened again.
+    ///
+    /// Does nothing if `component_id` is not the current terminal,
+    /// e.g. the terminal that exits after it is replaced.
+    pub(crate) fn handle_terminal_exited(&mut self, component_id: ComponentId) {
+        if !self
+            .background_terminal
+            .as_ref()
+            .is_some_and(|terminal| terminal.borrow().id() == component_id)
+        {
+            return;
+        }
+        self.background_terminal = None;
+        let root_id = self.tree.root_id();
+        if let Some(node_id) = self
+            .tree
+            .get_node_child_id(root_id, ComponentKind::Terminal)
+        {
+            let focused_component_id = self.focused_component_id();
+            let is_focused = self.tree.get(node_id).is_some_and(|node| {
+                node.traverse_pre_order()
+                    .any(|node| node.data().component().borrow().id() == focused_component_id)
+            });
+            self.tree.remove(node_id, is_focused);
+            self.recalculate_layout();
+        }
+    }
+
+    pub(crate) fn open_diff_view(&mut self, diff_view: Rc<RefCell<DiffView>>) {
+        self.tree
+            .replace_root_node_child(ComponentKind::DiffView, diff_view, true);
+    }
+
+    /// The preview is not focused, so that the markdown file can be edited while it is previewed.
+    pub(crate) fn open_markdown_preview(&mut self, markdown_preview: Rc<RefCell<MarkdownPreview>>) {
+        self.tree
+            .replace_root_node_child(ComponentKind::MarkdownPreview, markdown_preview, false);
+    }
+
+    /// The output is not focused, so that editing can go on while the task is running.
+    pub(crate) fn open_task_output(&mut self, task_output: Rc<RefCell<TaskOutput>>) {
+        self.tree
+            .replace_root_node_child(ComponentKind::TaskOutput, task_output, false);
+    }
+
+    /// The panel is not focused, so that the commands of the debugger can be used in the editor.
+    pub(crate) fn open_debug_panel(&mut self, debug_panel: Rc<RefCell<DebugPanel>>) {
+        self.tree
+            .replace_root_node_child(ComponentKind::DebugPanel, debug_panel, false);
+    }
+
+    /// The view is focused, so that the hierarchy can be browsed right away.
+    pub(crate) fn open_hierarchy_view(&mut self, hierarchy_view: Rc<RefCell<HierarchyView>>) {
+        self.tree
+            .replace_root_node_child(ComponentKind::HierarchyView, hierarchy_view, true);
+    }
+
+    /// Does nothing if no diff view is open.
+    pub(crate) fn close_diff_view(&mut self) {
+        let root_id = self.tree.root_id();
+        if let Some(node_id) = self
+            .tree
+            .get_node_child_id(root_id, ComponentKind::DiffView)
+        {
+            self.tree.remove(node_id, true);
+            self.recalculate_layout();
+        }
+    }
+
+    pub(crate) fn process_terminal_output(&self, bytes: &[u8]) {
+        if let Some(terminal) = &self.background_terminal {
+            terminal.borrow_mut().process_output(bytes)
+        }
+    }
+
+    /// The output of the terminal is only rendered while it is opened.
+    pub(crate) fn refresh_terminal(&self) -> anyhow::Result<()> {
+        match &self.background_terminal {
+            Some(terminal)
+                if self
+                    .get_component_by_kind(ComponentKind::Terminal)
+                    .is_some() =>
+            {
+                terminal.borrow_mut().refresh_editor_if_stale()
+            }
+            _ => Ok(()),
+        }
+    }
+
     pub(crate) fn open_file_explorer(&mut self) {
         self.tree.remove_all_root_children();
         self.tree.replace_root_node_child(
@@ -285,6 +434,14 @@ impl Layout {
         Ok(())
     }
 
+    /// The editors of the opened files, including those that are not shown.
+    pub(crate) fn suggestive_editors(&self) -> Vec<Rc<RefCell<SuggestiveEditor>>> {
+        self.background_suggestive_editors
+            .values()
+            .cloned()
+            .collect_vec()
+    }
+
     pub(crate) fn buffers(&self) -> Vec<Rc<RefCell<Buffer>>> {
         self.background_suggestive_editors
             .iter()
@@ -534,6 +691,11 @@ impl Layout {
         )
     }
 
+    /// Returns the component that the current component (e.g. prompt) is opened from
+    pub(crate) fn current_component_parent(&self) -> Option<Rc<RefCell<dyn Component>>> {
+        self.tree.current_component_parent()
+    }
+
     pub(crate) fn get_component_by_kind(
         &self,
         kind: ComponentKind,
@@ -2,10 +2,15 @@ use grep_regex::RegexMatcher;
 use grep_searcher::{sinks, SearcherBuilder};
 
 use fancy_regex::Regex;
+use std::ops::Range;
 
 use crate::{
-    buffer::Buffer, context::LocalSearchConfig, quickfix_list::Location,
-    selection_mode::regex::get_regex,
+    buffer::Buffer,
+    components::suggestive_editor::Info,
+    context::{LocalSearchConfig, LocalSearchConfigMode},
+    git::hunk::Hunk,
+    quickfix_list::Location,
+    selection_mode::{naming_convention_agnostic::NamingConventionAgnostic, regex::get_regex},
 };
 use shared::canonicalized_path::CanonicalizedPath;
 
@@ -56,6 +61,52 @@ pub(crate) fn replace(
         .collect())
 }
 
+/// Returns the diff of `line` before and after replacing the match at `column_range`.
+///
+/// Returns `None` if the preview cannot be computed for the given search mode,
+/// for example AST Grep, which requires the whole file,
+/// or if the regex does not match at `column_range` in the context of the whole line.
+pub(crate) fn replace_preview(
+    line: &str,
+    column_range: Range<usize>,
+    local_search_config: &LocalSearchConfig,
+) -> anyhow::Result<Option<Info>> {
+    let to_byte = |column: usize| {
+        line.char_indices()
+            .nth(column)
+            .map(|(byte, _)| byte)
+            .unwrap_or(line.len())
+    };
+    let (start, end) = (to_byte(column_range.start), to_byte(column_range.end));
+    let (range, replaced) = match local_search_config.mode {
+        LocalSearchConfigMode::Regex(regex_config) => {
+            // The regex is matched against the whole line rather than the matched text,
+            // so that anchors, word boundaries and lookarounds see the same context
+            // as they do when the replacement is applied
+            let regex = regex_config.to_regex(&local_search_config.search())?;
+            let Some(captures) = regex
+                .captures_from_pos(line, start)?
+                .filter(|captures| captures.get(0).map(|m| m.start()) == Some(start))
+            else {
+                return Ok(None);
+            };
+            let mut replaced = String::new();
+            captures.expand(&local_search_config.replacement(), &mut replaced);
+            (captures.get(0).map_or(start..end, |m| m.range()), replaced)
+        }
+        LocalSearchConfigMode::NamingConventionAgnostic => (
+            start..end,
+            NamingConventionAgnostic::new(local_search_config.search())
+                .replace_all(&line[start..end], local_search_config.replacement()),
+        ),
+        LocalSearchConfigMode::AstGrep => return Ok(None),
+    };
+    let new_line = format!("{}{}{}", &line[..range.start], replaced, &line[range.end..]);
+    Ok(Hunk::get(line, &new_line)
+        .first()
+        .map(|hunk| hunk.to_info_with_title("Replace Preview")))
+}
+
 pub(crate) fn run(
     pattern: &str,
     walk_builder_config: WalkBuilderConfig,
@@ -121,3 +172,48 @@ fn to_location(
 
     Ok(locations)
 }
+
+#[cfg(test)]
+mod test_replace_preview {
+    use crate::context::{LocalSearchConfig, LocalSearchConfigMode};
+
+    use super::{replace_preview, RegexConfig};
+
+    #[test]
+    fn regex_capture_group() -> anyhow::Result<()> {
+        let config = LocalSearchConfig::new(LocalSearchConfigMode::Regex(RegexConfig {
+            escaped: false,
+            ..Default::default()
+        }))
+        .set_search(r"f\((\w+)\)".to_string())
+        .set_replacment("g($1)".to_string())
+        .to_owned();
+        let info = replace_preview("let x = f(a) + f(b);", 15..19, &config)?.unwrap();
+        // Only the match at the given range is replaced
+        assert_eq!(info.content(), "let x = f(a) + f(b);\nlet x = f(a) + g(b);");
+        Ok(())
+    }
+
+    #[test]
+    fn ast_grep_has_no_preview() -> anyhow::Result<()> {
+        let config = LocalSearchConfig::new(LocalSearchConfigMode::AstGrep);
+        assert!(replace_preview("f(a)", 0..4, &config)?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn regex_sees_the_whole_line() -> anyhow::Result<()> {
+        let config = LocalSearchConfig::new(LocalSearchConfigMode::Regex(RegexConfig {
+            escaped: false,
+            ..Default::default()
+        }))
+        .set_search(r"(?<=\.)\bfoo$".to_string())
+        .set_replacment("bar".to_string())
+        .to_owned();
+        let info = replace_preview("x.foo", 2..5, &config)?.unwrap();
+        assert_eq!(info.content(), "x.foo\nx.bar");
+        // The match at the range is not a match in the context of the line
+        assert!(replace_preview("foo foo", 0..3, &config)?.is_none());
+        Ok(())
+    }
+}
@@ -11,6 +11,8 @@ pub(crate) struct CodeAction {
     pub(crate) kind: Option<String>,
     pub(crate) edit: Option<WorkspaceEdit>,
     pub(crate) command: Option<Command>,
+    /// The name of the LSP server that sent this code action, which executes its command
+    pub(crate) server: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -41,6 +43,7 @@ impl Eq for Command {}
 
 impl From<CodeAction> for DropdownItem {
     fn from(value: CodeAction) -> DropdownItem {
+        let description = format!("Code action {:?}", value.title);
         DropdownItem::new(value.title)
             .set_group(Some(
                 value
@@ -51,17 +54,19 @@ impl From<CodeAction> for DropdownItem {
             .set_dispatches(
                 value
                     .edit
-                    .map(Dispatch::ApplyWorkspaceEdit)
+                    .map(|workspace_edit| Dispatch::ApplyWorkspaceEdit {
+                        workspace_edit,
+                        description,
+                    })
                     .into_iter()
                     // A command this code action executes. If a code action
                     // provides an edit and a command, first the edit is
                     // executed and then the command.
                     // Refer https://microsoft.github.io/language-server-protocol/specifications/lsp/3.17/specification/#codeAction
-                    .chain(
-                        value
-                            .command
-                            .map(|command| Dispatch::LspExecuteCommand { command }),
-                    )
+                    .chain(value.command.map(|command| Dispatch::LspExecuteCommand {
+                        command,
+                        server: value.server,
+                    }))
                     .collect_vec()
                     .into(),
             )
@@ -92,6 +97,54 @@ impl TryFrom<lsp_types::CodeAction> for CodeAction {
             kind: value.kind.map(|kind| kind.as_str().to_string()),
             edit: value.edit.map(WorkspaceEdit::try_from).transpose()?,
             command: value.command.map(Command),
+            server: None,
         })
     }
 }
+
+#[cfg(test)]
+mod test_code_action {
+    use crate::lsp::completion::CompletionItem;
+
+    use super::*;
+
+    #[test]
+    fn commands_are_sent_to_the_server_of_their_items() {
+        let command = lsp_types::Command::new("Fix".to_string(), "fix".to_string(), None);
+        let code_action = CodeAction {
+            title: "Fix".to_string(),
+            kind: None,
+            edit: None,
+            command: Some(command.clone().into()),
+            server: Some("typos-lsp".to_string()),
+        };
+        assert_eq!(
+            DropdownItem::from(code_action).dispatches.into_vec(),
+            [Dispatch::LspExecuteCommand {
+                command: command.clone().into(),
+                server: Some("typos-lsp".to_string()),
+            }]
+            .to_vec()
+        );
+
+        let completion_item = CompletionItem::from(lsp_types::CompletionItem {
+            label: "fix".to_string(),
+            command: Some(command.clone()),
+            ..Default::default()
+        })
+        .set_server("typos-lsp".to_string());
+        assert_eq!(
+            DropdownItem::from(completion_item.clone())
+                .on_focused()
+                .into_vec(),
+            [Dispatch::ResolveCompletionItem(completion_item.clone())].to_vec()
+        );
+        assert!(completion_item
+            .dispatches()
+            .into_vec()
+            .contains(&Dispatch::LspExecuteCommand {
+                command: command.into(),
+                server: Some("typos-lsp".to_string()),
+            }));
+    }
+}
@@ -28,6 +28,9 @@ pub(crate) struct CompletionItem {
     pub(crate) insert_text: Option<String>,
     pub(crate) edit: Option<CompletionItemEdit>,
     pub(crate) completion_item: lsp_types::CompletionItem,
+    /// The name of the LSP server that sent this item, which is the only server that can
+    /// resolve it and execute its command, see `LspManager::send_message_to`
+    pub(crate) server: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -101,6 +104,7 @@ impl CompletionItem {
             edit: None,
             insert_text: None,
             completion_item: Default::default(),
+            server: None,
         }
     }
 
@@ -174,6 +178,7 @@ impl CompletionItem {
                 .clone()
                 .map(|command| Dispatch::LspExecuteCommand {
                     command: command.into(),
+                    server: self.server.clone(),
                 }),
         )
     }
@@ -182,6 +187,13 @@ impl CompletionItem {
         self.completion_item.clone()
     }
 
+    pub(crate) fn set_server(self, server: String) -> CompletionItem {
+        CompletionItem {
+            server: Some(server),
+            ..self
+        }
+    }
+
     fn command(&self) -> Option<lsp_types::Command> {
         self.completion_item.command.clone()
     }
@@ -206,6 +218,7 @@ impl From<lsp_types::CompletionItem> for CompletionItem {
                 lsp_types::CompletionTextEdit::InsertAndReplace(_) => None,
             }),
             completion_item: item,
+            server: None,
         }
     }
 }
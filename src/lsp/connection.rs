@@ -0,0 +1,291 @@
+//! How the editor talks to an LSP server, which is either a spawned child process (optionally
+//! wrapped by a command such as `docker exec -i` or `ssh`), or a server that is already listening
+//! on a TCP or Unix socket.
+//!
+//! Servers that run inside containers or on remote hosts see the files under different paths,
+//! so the paths of the messages are translated by the configured path mappings, from the local
+//! paths to the remote paths when sending, and the other way around when receiving.
+
+use std::{
+    io::{Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    path::PathBuf,
+    time::Duration,
+};
+
+use serde::Deserialize;
+use shared::process_command::{ProcessCommand, SpawnCommandResult};
+
+/// A server that is still starting (e.g. its container is booting) might not accept
+/// connections yet, so connecting is retried a few times before giving up.
+const CONNECT_ATTEMPTS: usize = 3;
+const CONNECT_RETRY_INTERVAL: Duration = Duration::from_millis(300);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug)]
+pub(crate) enum Transport {
+    /// Talks to the child process over its stdin and stdout
+    Stdio(ProcessCommand),
+    /// For example `tcp://localhost:9257`
+    Tcp(String),
+    /// For example `unix:///tmp/rust-analyzer.sock`
+    Unix(PathBuf),
+}
+
+impl Transport {
+    /// Parses an address of the form `tcp://<host>:<port>` or `unix://<path>`.
+    pub(crate) fn from_address(address: &str) -> anyhow::Result<Transport> {
+        if let Some(address) = address.strip_prefix("tcp://") {
+            Ok(Transport::Tcp(address.to_string()))
+        } else if let Some(path) = address.strip_prefix("unix://") {
+            Ok(Transport::Unix(PathBuf::from(path)))
+        } else {
+            Err(anyhow::anyhow!(
+                "Invalid LSP server address {address:?}, expected `tcp://<host>:<port>` \
+                 or `unix://<path>`"
+            ))
+        }
+    }
+
+    /// Returns `None` if the command of the child process is not installed.
+    pub(crate) fn connect(&self) -> anyhow::Result<Option<Connection>> {
+        match self {
+            Transport::Stdio(process_command) => {
+                let mut process = match process_command.spawn() {
+                    SpawnCommandResult::Spawned(result) => result?,
+                    SpawnCommandResult::CommandNotFound { .. } => return Ok(None),
+                };
+                let writer = process
+                    .stdin
+                    .take()
+                    .ok_or_else(|| anyhow::anyhow!("Unable to obtain stdin"))?;
+                let stderr = process
+                    .stderr
+                    .take()
+                    .ok_or_else(|| anyhow::anyhow!("Unable to obtain stderr"))?;
+                let reader = process
+                    .stdout
+                    .take()
+                    .ok_or_else(|| anyhow::anyhow!("Unable to obtain stdout"))?;
+                Ok(Some(Connection {
+                    writer: Box::new(writer),
+                    reader: Box::new(reader),
+                    stderr: Box::new(stderr),
+                    child: Some(process),
+                }))
+            }
+            Transport::Tcp(address) => {
+                let stream = retry(|| {
+                    let mut error = anyhow::anyhow!("{address:?} is not resolved to any address");
+                    for socket_address in address.to_socket_addrs()? {
+                        match TcpStream::connect_timeout(&socket_address, CONNECT_TIMEOUT) {
+                            Ok(stream) => return Ok(stream),
+                            Err(err) => error = err.into(),
+                        }
+                    }
+                    Err(error)
+                })?;
+                stream.set_nodelay(true)?;
+                Ok(Some(Connection {
+                    writer: Box::new(stream.try_clone()?),
+                    reader: Box::new(stream),
+                    stderr: Box::new(std::io::empty()),
+                    child: None,
+                }))
+            }
+            #[cfg(unix)]
+            Transport::Unix(path) => {
+                let stream = retry(|| Ok(std::os::unix::net::UnixStream::connect(path)?))?;
+                Ok(Some(Connection {
+                    writer: Box::new(stream.try_clone()?),
+                    reader: Box::new(stream),
+                    stderr: Box::new(std::io::empty()),
+                    child: None,
+                }))
+            }
+            #[cfg(not(unix))]
+            Transport::Unix(_) => Err(anyhow::anyhow!(
+                "Unix sockets are not supported on this platform"
+            )),
+        }
+    }
+
+    /// The name of the LSP server, for example `rust-analyzer` or `tcp://localhost:9257`
+    pub(crate) fn name(&self) -> String {
+        match self {
+            Transport::Stdio(process_command) => process_command.command().to_string(),
+            Transport::Tcp(address) => format!("tcp://{address}"),
+            Transport::Unix(path) => format!("unix://{}", path.display()),
+        }
+    }
+}
+
+impl std::fmt::Display for Transport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Transport::Stdio(process_command) => write!(f, "{process_command}"),
+            _ => write!(f, "{}", self.name()),
+        }
+    }
+}
+
+fn retry<T>(connect: impl Fn() -> anyhow::Result<T>) -> anyhow::Result<T> {
+    let mut result = connect();
+    for _ in 1..CONNECT_ATTEMPTS {
+        if result.is_ok() {
+            break;
+        }
+        std::thread::sleep(CONNECT_RETRY_INTERVAL);
+        result = connect();
+    }
+    result
+}
+
+pub(crate) struct Connection {
+    pub(crate) writer: Box<dyn Write + Send>,
+    pub(crate) reader: Box<dyn Read + Send>,
+    /// Empty for sockets
+    pub(crate) stderr: Box<dyn Read + Send>,
+    /// `None` for sockets
+    pub(crate) child: Option<std::process::Child>,
+}
+
+/// Maps a local directory to the path that the LSP server sees it as,
+/// for example `{ local = "/home/me/project", remote = "/workspace" }`.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct PathMapping {
+    pub(crate) local: String,
+    pub(crate) remote: String,
+}
+
+/// The fields that hold the content of documents, for example the `text` of `didOpen`
+/// and the `newText` of text edits
+const CONTENT_KEYS: [&str; 2] = ["text", "newText"];
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct PathTranslation {
+    mappings: Vec<PathMapping>,
+}
+
+impl PathTranslation {
+    pub(crate) fn new(mappings: Vec<PathMapping>) -> PathTranslation {
+        PathTranslation { mappings }
+    }
+
+    /// Translates the paths and `file://` URIs in the message that is sent to the server.
+    pub(crate) fn to_remote(&self, value: &mut serde_json::Value) {
+        self.translate(value, |mapping| (&mapping.local, &mapping.remote))
+    }
+
+    /// Translates the paths and `file://` URIs in the message that is received from the server.
+    pub(crate) fn to_local(&self, value: &mut serde_json::Value) {
+        self.translate(value, |mapping| (&mapping.remote, &mapping.local))
+    }
+
+    fn translate(
+        &self,
+        value: &mut serde_json::Value,
+        from_to: impl Fn(&PathMapping) -> (&String, &String) + Copy,
+    ) {
+        if self.mappings.is_empty() {
+            return;
+        }
+        match value {
+            serde_json::Value::String(string) => {
+                if let Some(translated) = self.mappings.iter().find_map(|mapping| {
+                    let (from, to) = from_to(mapping);
+                    replace_path_prefix(string, from, to)
+                }) {
+                    *string = translated
+                }
+            }
+            serde_json::Value::Array(values) => values
+                .iter_mut()
+                .for_each(|value| self.translate(value, from_to)),
+            serde_json::Value::Object(map) => {
+                // Keys are translated as well, because the `changes` of a workspace edit
+                // are keyed by URIs, but the content of the documents is left as is
+                let entries = std::mem::take(map);
+                *map = entries
+                    .into_iter()
+                    .map(|(mut key, mut value)| {
+                        if !CONTENT_KEYS.contains(&key.as_str()) {
+                            self.translate(&mut value, from_to);
+                        }
+                        let mut key_value = serde_json::Value::String(key.clone());
+                        self.translate(&mut key_value, from_to);
+                        if let serde_json::Value::String(translated) = key_value {
+                            key = translated
+                        }
+                        (key, value)
+                    })
+                    .collect();
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Replaces the `from` directory (as a path or a `file://` URI) at the start of `string`,
+/// or returns `None` if `string` is not under `from`.
+fn replace_path_prefix(string: &str, from: &str, to: &str) -> Option<String> {
+    let from = from.trim_end_matches('/');
+    let to = to.trim_end_matches('/');
+    let (scheme, path) = match string.strip_prefix("file://") {
+        Some(path) => ("file://", path),
+        None => ("", string),
+    };
+    let rest = path.strip_prefix(from)?;
+    (rest.is_empty() || rest.starts_with('/')).then(|| format!("{scheme}{to}{rest}"))
+}
+
+#[cfg(test)]
+mod test_connection {
+    use super::*;
+
+    #[test]
+    fn translate_paths_between_local_and_remote() {
+        let translation = PathTranslation::new(
+            [PathMapping {
+                local: "/home/me/project".to_string(),
+                remote: "/workspace/".to_string(),
+            }]
+            .to_vec(),
+        );
+        let local = serde_json::json!({
+            "rootUri": "file:///home/me/project",
+            "rootPath": "/home/me/project",
+            "textDocument": { "uri": "file:///home/me/project/src/main.rs" },
+            "changes": { "file:///home/me/project/a.rs": [{ "newText": "/home/me/project" }] },
+            "unrelated": ["file:///home/me/project-other/a.rs", "/usr/lib", 1],
+        });
+        let remote = serde_json::json!({
+            "rootUri": "file:///workspace",
+            "rootPath": "/workspace",
+            "textDocument": { "uri": "file:///workspace/src/main.rs" },
+            "changes": { "file:///workspace/a.rs": [{ "newText": "/home/me/project" }] },
+            "unrelated": ["file:///home/me/project-other/a.rs", "/usr/lib", 1],
+        });
+
+        let mut value = local.clone();
+        translation.to_remote(&mut value);
+        assert_eq!(value, remote);
+        translation.to_local(&mut value);
+        assert_eq!(value, local);
+    }
+
+    #[test]
+    fn parse_address() -> anyhow::Result<()> {
+        assert_eq!(
+            Transport::from_address("tcp://localhost:9257")?.name(),
+            "tcp://localhost:9257"
+        );
+        assert_eq!(
+            Transport::from_address("unix:///tmp/ra.sock")?.name(),
+            "unix:///tmp/ra.sock"
+        );
+        assert!(Transport::from_address("localhost:9257").is_err());
+        Ok(())
+    }
+}
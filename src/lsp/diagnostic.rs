@@ -2,7 +2,9 @@ use crate::{
     buffer::Buffer, char_index_range::CharIndexRange, position::Position, quickfix_list::Location,
 };
 
-use lsp_types::DiagnosticSeverity;
+use itertools::Itertools;
+use lsp_types::{DiagnosticSeverity, NumberOrString};
+use serde::Deserialize;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub(crate) struct Diagnostic {
@@ -56,3 +58,170 @@ impl TryFrom<lsp_types::DiagnosticRelatedInformation> for DiagnosticRelatedInfor
         })
     }
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum Severity {
+    Error,
+    Warning,
+    Information,
+    Hint,
+}
+
+impl Severity {
+    pub(crate) const ALL: [Severity; 4] = [
+        Severity::Error,
+        Severity::Warning,
+        Severity::Information,
+        Severity::Hint,
+    ];
+
+    /// Diagnostics without a severity are treated as errors.
+    fn from_lsp(severity: Option<DiagnosticSeverity>) -> Severity {
+        match severity {
+            Some(DiagnosticSeverity::WARNING) => Severity::Warning,
+            Some(DiagnosticSeverity::INFORMATION) => Severity::Information,
+            Some(DiagnosticSeverity::HINT) => Severity::Hint,
+            _ => Severity::Error,
+        }
+    }
+
+    /// Describes the diagnostics that are shown if this is the minimum severity.
+    pub(crate) fn display(&self) -> &'static str {
+        match self {
+            Severity::Error => "Errors",
+            Severity::Warning => "Errors and warnings",
+            Severity::Information => "Errors, warnings and information",
+            Severity::Hint => "All",
+        }
+    }
+}
+
+/// Decides which diagnostics are displayed, while the hidden ones are still kept by the buffer,
+/// so that they are shown again once the filter is relaxed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct DiagnosticFilter {
+    /// The diagnostics that are less severe than this are hidden
+    pub(crate) minimum_severity: Severity,
+    /// The sources (e.g. `cspell`) or codes (e.g. `clippy::needless_return`)
+    /// of the hidden diagnostics
+    pub(crate) muted: Vec<String>,
+}
+
+impl Default for DiagnosticFilter {
+    fn default() -> Self {
+        DiagnosticFilter {
+            minimum_severity: Severity::Hint,
+            muted: Vec::new(),
+        }
+    }
+}
+
+impl DiagnosticFilter {
+    pub(crate) fn shows(&self, diagnostic: &Diagnostic) -> bool {
+        Severity::from_lsp(diagnostic.severity) <= self.minimum_severity
+            && self.muting(diagnostic).is_none()
+    }
+
+    /// The muted entry that hides the diagnostic, if any.
+    pub(crate) fn muting(&self, diagnostic: &Diagnostic) -> Option<&str> {
+        let names = mute_names(diagnostic);
+        self.muted
+            .iter()
+            .find(|muted| names.contains(muted))
+            .map(String::as_str)
+    }
+
+    /// Mutes the code of the diagnostic, or its source if it has no code.
+    pub(crate) fn mute(&mut self, diagnostic: &Diagnostic) -> anyhow::Result<String> {
+        let name = mute_names(diagnostic).pop().ok_or_else(|| {
+            anyhow::anyhow!("The diagnostic has neither a code nor a source to be muted by")
+        })?;
+        if !self.muted.contains(&name) {
+            self.muted.push(name.clone())
+        }
+        Ok(name)
+    }
+
+    pub(crate) fn unmute(&mut self, name: &str) {
+        self.muted.retain(|muted| muted != name)
+    }
+}
+
+/// The source and the code of the diagnostic, in this order.
+fn mute_names(diagnostic: &Diagnostic) -> Vec<String> {
+    let Some(original) = diagnostic.original_value.as_ref() else {
+        return Vec::new();
+    };
+    let code = original.code.as_ref().map(|code| match code {
+        NumberOrString::Number(number) => number.to_string(),
+        NumberOrString::String(string) => string.clone(),
+    });
+    original
+        .source
+        .clone()
+        .into_iter()
+        .chain(code)
+        .collect_vec()
+}
+
+#[cfg(test)]
+mod test_diagnostic {
+    use super::*;
+
+    fn diagnostic(
+        severity: DiagnosticSeverity,
+        source: Option<&str>,
+        code: Option<NumberOrString>,
+    ) -> Diagnostic {
+        let original = lsp_types::Diagnostic {
+            severity: Some(severity),
+            source: source.map(str::to_string),
+            code,
+            ..Default::default()
+        };
+        Diagnostic {
+            range: CharIndexRange::default(),
+            message: String::new(),
+            severity: Some(severity),
+            related_information: None,
+            code_description: None,
+            original_value: Some(original),
+        }
+    }
+
+    #[test]
+    fn filter_by_severity_source_and_code() -> anyhow::Result<()> {
+        let lint = diagnostic(
+            DiagnosticSeverity::WARNING,
+            Some("clippy"),
+            Some(NumberOrString::String("needless_return".to_string())),
+        );
+        let typo = diagnostic(DiagnosticSeverity::INFORMATION, Some("cspell"), None);
+        let error = diagnostic(
+            DiagnosticSeverity::ERROR,
+            None,
+            Some(NumberOrString::Number(308)),
+        );
+        let mut filter = DiagnosticFilter {
+            minimum_severity: Severity::Warning,
+            muted: Vec::new(),
+        };
+        assert!(filter.shows(&lint));
+        assert!(!filter.shows(&typo));
+
+        assert_eq!(filter.mute(&lint)?, "needless_return");
+        assert_eq!(filter.mute(&error)?, "308");
+        assert_eq!(filter.muting(&lint), Some("needless_return"));
+        assert!(!filter.shows(&lint));
+        assert!(!filter.shows(&error));
+
+        filter.minimum_severity = Severity::Hint;
+        assert_eq!(filter.mute(&typo)?, "cspell");
+        filter.unmute("needless_return");
+        assert!(filter.shows(&lint));
+        assert!(!filter.shows(&typo));
+        assert_eq!(filter.muted, ["308", "cspell"]);
+        Ok(())
+    }
+}
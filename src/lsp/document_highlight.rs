@@ -0,0 +1,21 @@
+use std::ops::Range;
+
+use crate::position::Position;
+
+/// An occurrence of the symbol under the cursor,
+/// refer https://microsoft.github.io/language-server-protocol/specifications/lsp/3.17/specification/#textDocument_documentHighlight
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct DocumentHighlight {
+    pub(crate) range: Range<Position>,
+    /// Whether the symbol is written (e.g. assigned to) rather than read
+    pub(crate) is_write: bool,
+}
+
+impl From<lsp_types::DocumentHighlight> for DocumentHighlight {
+    fn from(value: lsp_types::DocumentHighlight) -> Self {
+        DocumentHighlight {
+            range: value.range.start.into()..value.range.end.into(),
+            is_write: value.kind == Some(lsp_types::DocumentHighlightKind::WRITE),
+        }
+    }
+}
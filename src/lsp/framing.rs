@@ -0,0 +1,95 @@
+//! The `Content-Length` framing of the messages of LSP, which is shared by the Debug Adapter
+//! Protocol, see https://microsoft.github.io/language-server-protocol/specifications/lsp/3.17/specification/#headerPart.
+
+use std::io::{BufRead, Write};
+
+use anyhow::Context;
+
+/// Returns `None` once the reader is closed before a message starts.
+/// The headers other than `Content-Length` are skipped.
+pub(crate) fn read_message(reader: &mut impl BufRead) -> anyhow::Result<Option<serde_json::Value>> {
+    let mut content_length = None;
+    let content_length = loop {
+        let mut line = String::new();
+        if reader
+            .read_line(&mut line)
+            .with_context(|| "Failed to read the header.")?
+            == 0
+        {
+            return Ok(None);
+        }
+        let header = line.trim();
+        // The headers are terminated by an empty line, which immediately precedes the content
+        if header.is_empty() {
+            if let Some(content_length) = content_length {
+                break content_length;
+            }
+        } else if let Some(length) = header.strip_prefix("Content-Length:") {
+            content_length = Some(
+                length
+                    .trim()
+                    .parse::<usize>()
+                    .with_context(|| "Parsing Content-Length: Failed to parse number.")?,
+            )
+        }
+    };
+    let mut buffer = vec![0; content_length];
+    reader
+        .read_exact(&mut buffer)
+        .with_context(|| "Failed to read the content of the message.")?;
+    Ok(Some(serde_json::from_slice(&buffer).with_context(
+        || "Failed to convert the content into JSON value",
+    )?))
+}
+
+pub(crate) fn write_message(
+    writer: &mut impl Write,
+    message: &serde_json::Value,
+) -> anyhow::Result<()> {
+    let json = serde_json::to_string(message)?;
+    write!(writer, "Content-Length: {}\r\n\r\n{}", json.len(), json)?;
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test_framing {
+    use super::*;
+
+    #[test]
+    fn read_messages() -> anyhow::Result<()> {
+        let first = r#"{"seq":1,"type":"event","event":"initialized"}"#;
+        let second = r#"{"seq":2,"type":"event","event":"terminated"}"#;
+        let input = format!(
+            "Content-Length: {}\r\n\r\n{first}\
+             Content-Type: utf-8\r\nContent-Length: {}\r\n\r\n{second}",
+            first.len(),
+            second.len()
+        );
+        let mut reader = std::io::Cursor::new(input);
+        assert_eq!(
+            read_message(&mut reader)?,
+            Some(serde_json::from_str(first)?)
+        );
+        assert_eq!(
+            read_message(&mut reader)?,
+            Some(serde_json::from_str(second)?)
+        );
+        assert_eq!(read_message(&mut reader)?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn written_messages_can_be_read() -> anyhow::Result<()> {
+        // The length is in bytes, not in characters
+        let message = serde_json::json!({"jsonrpc": "2.0", "method": "exit", "params": "café"});
+        let mut output = Vec::new();
+        write_message(&mut output, &message)?;
+        write_message(&mut output, &message)?;
+        let mut reader = std::io::Cursor::new(output);
+        assert_eq!(read_message(&mut reader)?, Some(message.clone()));
+        assert_eq!(read_message(&mut reader)?, Some(message));
+        assert_eq!(read_message(&mut reader)?, None);
+        Ok(())
+    }
+}
@@ -0,0 +1,97 @@
+//! The call hierarchy and the type hierarchy of a symbol, which are browsed as trees,
+//! see `HierarchyView`, refer
+//! https://microsoft.github.io/language-server-protocol/specifications/lsp/3.17/specification/#textDocument_prepareCallHierarchy
+
+use lsp_types::{CallHierarchyItem, TypeHierarchyItem};
+
+use crate::quickfix_list::Location;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum HierarchyKind {
+    /// The callers of a function
+    IncomingCalls,
+    /// The callees of a function
+    OutgoingCalls,
+    Supertypes,
+    Subtypes,
+}
+
+impl HierarchyKind {
+    pub(crate) fn display(&self) -> &'static str {
+        match self {
+            HierarchyKind::IncomingCalls => "Incoming calls",
+            HierarchyKind::OutgoingCalls => "Outgoing calls",
+            HierarchyKind::Supertypes => "Supertypes",
+            HierarchyKind::Subtypes => "Subtypes",
+        }
+    }
+
+    pub(crate) fn is_call(&self) -> bool {
+        matches!(
+            self,
+            HierarchyKind::IncomingCalls | HierarchyKind::OutgoingCalls
+        )
+    }
+}
+
+/// Tells which node of a hierarchy a response belongs to, see `ResponseContext`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct HierarchyContext {
+    pub(crate) kind: HierarchyKind,
+    /// `None` for the roots, which are the items of the symbol under the cursor
+    pub(crate) parent: Option<HierarchyItem>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct HierarchyItem {
+    pub(crate) name: String,
+    /// The range of the name of the item
+    pub(crate) location: Location,
+    /// The item as it is received, which is sent back to the server to get its children
+    raw: serde_json::Value,
+}
+
+impl HierarchyItem {
+    pub(crate) fn call_hierarchy_item(&self) -> anyhow::Result<CallHierarchyItem> {
+        Ok(serde_json::from_value(self.raw.clone())?)
+    }
+
+    pub(crate) fn type_hierarchy_item(&self) -> anyhow::Result<TypeHierarchyItem> {
+        Ok(serde_json::from_value(self.raw.clone())?)
+    }
+}
+
+impl TryFrom<CallHierarchyItem> for HierarchyItem {
+    type Error = anyhow::Error;
+
+    fn try_from(value: CallHierarchyItem) -> Result<Self, Self::Error> {
+        Ok(HierarchyItem {
+            raw: serde_json::to_value(&value)?,
+            location: lsp_types::Location::new(value.uri, value.selection_range).try_into()?,
+            name: value.name,
+        })
+    }
+}
+
+impl TryFrom<TypeHierarchyItem> for HierarchyItem {
+    type Error = anyhow::Error;
+
+    fn try_from(value: TypeHierarchyItem) -> Result<Self, Self::Error> {
+        Ok(HierarchyItem {
+            raw: serde_json::to_value(&value)?,
+            location: lsp_types::Location::new(value.uri, value.selection_range).try_into()?,
+            name: value.name,
+        })
+    }
+}
+
+#[cfg(test)]
+impl HierarchyItem {
+    pub(crate) fn new(name: &str, location: Location) -> Self {
+        HierarchyItem {
+            name: name.to_string(),
+            raw: serde_json::json!({ "name": name }),
+            location,
+        }
+    }
+}
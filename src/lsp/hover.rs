@@ -22,6 +22,9 @@ impl From<lsp_types::Hover> for Hover {
 pub(crate) fn marked_string_to_string(marked_string: lsp_types::MarkedString) -> String {
     match marked_string {
         lsp_types::MarkedString::String(string) => string,
-        lsp_types::MarkedString::LanguageString(language_string) => language_string.value,
+        lsp_types::MarkedString::LanguageString(language_string) => format!(
+            "```{}\n{}\n```",
+            language_string.language, language_string.value
+        ),
     }
 }
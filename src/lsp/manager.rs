@@ -1,17 +1,50 @@
-use std::{collections::HashMap, sync::mpsc::Sender};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::mpsc::Sender,
+};
 
-use crate::app::AppMessage;
+use crate::{
+    app::AppMessage,
+    config::{Config, LspConfig},
+    utils::consolidate_errors,
+};
 
-use super::process::{FromEditor, LspServerProcessChannel};
+use super::{
+    code_action::CodeAction,
+    completion::Completion,
+    connection::{PathTranslation, Transport},
+    process::{FromEditor, LspServerProcessChannel},
+    progress::Progress,
+};
+use itertools::Itertools;
 use shared::{
     canonicalized_path::CanonicalizedPath,
     language::{self, Language, LanguageId},
+    process_command::ProcessCommand,
 };
 
+/// Runs the LSP servers of each language, which are its default server (see `Language`)
+/// followed by the additional servers of the config (see `LanguageConfig::lsp_servers`).
+///
+/// The changes of the documents, and the requests for completions and code actions, are sent
+/// to every server of the language, so their diagnostics, completions and code actions are
+/// merged here. Resolving a completion item and executing a command are sent to the server
+/// that sent the item, see `send_message_to`. The other requests (e.g. hover) are sent to the
+/// first server only.
 pub(crate) struct LspManager {
-    lsp_server_process_channels: HashMap<LanguageId, LspServerProcessChannel>,
+    lsp_server_process_channels: HashMap<LanguageId, Vec<LspServerProcessChannel>>,
     sender: Sender<AppMessage>,
     current_working_directory: CanonicalizedPath,
+    /// The languages that are set at runtime, which take precedence over the file extensions
+    language_overrides: HashMap<CanonicalizedPath, Language>,
+    /// Overrides the LSP servers of the languages, which applies to the servers started afterwards
+    config: Config,
+    /// The diagnostics of each file by the names of the servers that published them
+    diagnostics: HashMap<CanonicalizedPath, BTreeMap<String, Vec<lsp_types::Diagnostic>>>,
+    /// The responses of the servers to the latest completion request
+    completions: BTreeMap<String, Completion>,
+    /// The responses of the servers to the latest code action request
+    code_actions: BTreeMap<String, Vec<CodeAction>>,
     #[cfg(test)]
     /// Used for testing the correctness of LSP requests
     /// We use HashMap instead of Vec because we only one to store the latest
@@ -34,6 +67,11 @@ impl LspManager {
             lsp_server_process_channels: HashMap::new(),
             sender,
             current_working_directory,
+            language_overrides: HashMap::new(),
+            config: Config::default(),
+            diagnostics: HashMap::new(),
+            completions: BTreeMap::new(),
+            code_actions: BTreeMap::new(),
             #[cfg(test)]
             history: Default::default(),
         }
@@ -42,69 +80,391 @@ impl LspManager {
     fn invoke_channels(
         &self,
         path: &CanonicalizedPath,
-        _error: &str,
+        error: &str,
+        recipients: Recipients,
         f: impl Fn(&LspServerProcessChannel) -> anyhow::Result<()>,
     ) -> anyhow::Result<()> {
-        language::from_path(path)
+        let Some(channels) = self
+            .language(path)
             .and_then(|language| self.lsp_server_process_channels.get(&language.id()?))
-            .map(f)
-            .unwrap_or_else(|| Ok(()))
+        else {
+            return Ok(());
+        };
+        let channels = match recipients {
+            Recipients::Every => channels.iter().collect_vec(),
+            Recipients::First => channels.iter().take(1).collect_vec(),
+            Recipients::Named(name) => channels
+                .iter()
+                .filter(|channel| channel.name() == name)
+                .collect_vec(),
+        };
+        consolidate_errors(error, channels.into_iter().map(f).collect())
     }
 
     pub(crate) fn send_message(
         &mut self,
         path: CanonicalizedPath,
         from_editor: FromEditor,
+    ) -> anyhow::Result<()> {
+        let every_server = matches!(
+            from_editor,
+            FromEditor::TextDocumentDidOpen { .. }
+                | FromEditor::TextDocumentDidChange { .. }
+                | FromEditor::TextDocumentDidSave { .. }
+                | FromEditor::TextDocumentDidClose { .. }
+                | FromEditor::WorkspaceDidRenameFiles { .. }
+                | FromEditor::TextDocumentCompletion(_)
+                | FromEditor::TextDocumentCodeAction { .. }
+                | FromEditor::Shutdown
+        );
+        let recipients = if every_server {
+            Recipients::Every
+        } else {
+            Recipients::First
+        };
+        self.send(path, recipients, from_editor)
+    }
+
+    /// Sends the message only to `server`, which is the server that sent the item that
+    /// the message is about, for example the completion item to be resolved, or the code
+    /// action whose command is to be executed, because the other servers do not know the item.
+    ///
+    /// The message is sent to the first server if `server` is `None`,
+    /// e.g. the item is not sent by any server.
+    pub(crate) fn send_message_to(
+        &mut self,
+        path: CanonicalizedPath,
+        server: Option<&str>,
+        from_editor: FromEditor,
+    ) -> anyhow::Result<()> {
+        let recipients = server.map_or(Recipients::First, Recipients::Named);
+        self.send(path, recipients, from_editor)
+    }
+
+    fn send(
+        &mut self,
+        path: CanonicalizedPath,
+        recipients: Recipients,
+        from_editor: FromEditor,
     ) -> anyhow::Result<()> {
         #[cfg(test)]
         self.history
             .insert(from_editor.variant(), from_editor.clone());
 
+        match &from_editor {
+            FromEditor::TextDocumentCompletion(_) => self.completions.clear(),
+            FromEditor::TextDocumentCodeAction { .. } => self.code_actions.clear(),
+            _ => {}
+        }
         self.invoke_channels(
             &path,
             &format!("Failed to send message '{}'", from_editor.variant()),
+            recipients,
             |channel| channel.send_from_editor(from_editor.clone()),
         )
     }
 
     /// Open file can do one of the following:
-    /// 1. Start a new LSP server process if it is not started yet.
-    /// 2. Notify the LSP server process that a new file is opened.
-    /// 3. Do nothing if the LSP server process is spawned but not yet initialized.
+    /// 1. Start the LSP server processes of the language if they are not started yet.
+    /// 2. Notify the initialized LSP server processes that a new file is opened.
+    /// 3. Do nothing for the LSP server processes that are spawned but not yet initialized.
 
     pub(crate) fn open_file(&mut self, path: CanonicalizedPath) -> Result<(), anyhow::Error> {
-        let Some(language) = language::from_path(&path) else {
+        let Some(language) = self.language(&path) else {
             return Ok(());
         };
         let Some(language_id) = language.id() else {
             return Ok(());
         };
 
-        if let Some(channel) = self.lsp_server_process_channels.get(&language_id) {
-            if channel.is_initialized() {
-                channel.document_did_open(path.clone())
-            } else {
+        if let Some(channels) = self.lsp_server_process_channels.get(&language_id) {
+            return channels
+                .iter()
+                .filter(|channel| channel.is_initialized())
+                .try_for_each(|channel| channel.document_did_open(path.clone()));
+        }
+        // A server that fails to start does not prevent the other servers from starting
+        let mut channels: Vec<LspServerProcessChannel> = Vec::new();
+        let mut results = Vec::new();
+        for server in self.servers(&language) {
+            let result = server.and_then(|server| {
+                if channels.iter().any(|channel| channel.name() == server.name) {
+                    return Err(anyhow::anyhow!(
+                        "Another LSP server of {language_id} is named `{}`",
+                        server.name
+                    ));
+                }
+                let root_directory = self.root_directory(&path, &server.root_markers);
+                if let Some(channel) = LspServerProcessChannel::new(
+                    language.clone(),
+                    server.name,
+                    server.transport,
+                    server.initialization_options,
+                    server.path_translation,
+                    self.sender.clone(),
+                    root_directory,
+                )? {
+                    channels.push(channel)
+                }
                 Ok(())
+            });
+            results.push(result)
+        }
+        if !channels.is_empty() {
+            self.lsp_server_process_channels
+                .insert(language_id, channels);
+        }
+        results.into_iter().collect()
+    }
+
+    /// Describes the LSP servers of the file, for example
+    /// `rust-analyzer (Indexing 42%: 120/286), typos-lsp (starting)`, where only the
+    /// oldest ongoing progress of each server is shown,
+    /// or returns `None` if no LSP server is running for the file.
+    pub(crate) fn server_status(&self, path: &CanonicalizedPath) -> Option<String> {
+        let channels = self.channels(path)?;
+        Some(
+            channels
+                .iter()
+                .map(|channel| {
+                    if !channel.is_initialized() {
+                        format!("{} (starting)", channel.command())
+                    } else if let Some(progress) = channel.progress().first() {
+                        format!("{} ({})", channel.command(), progress.display())
+                    } else {
+                        channel.command().to_string()
+                    }
+                })
+                .join(", "),
+        )
+    }
+
+    fn channels(&self, path: &CanonicalizedPath) -> Option<&Vec<LspServerProcessChannel>> {
+        let language = self.language(path)?;
+        self.lsp_server_process_channels.get(&language.id()?)
+    }
+
+    pub(crate) fn update_progress(
+        &mut self,
+        server: String,
+        language: Language,
+        progress: Vec<Progress>,
+    ) {
+        let Some(language_id) = language.id() else {
+            return;
+        };
+        if let Some(channel) = self
+            .lsp_server_process_channels
+            .get_mut(&language_id)
+            .and_then(|channels| channels.iter_mut().find(|channel| channel.name() == server))
+        {
+            channel.set_progress(progress)
+        }
+    }
+
+    /// The logs of each LSP server of the file under the command of the server,
+    /// or `None` if no LSP server is running for the file.
+    pub(crate) fn logs(&self, path: &CanonicalizedPath) -> Option<String> {
+        let channels = self.channels(path)?;
+        Some(
+            channels
+                .iter()
+                .map(|channel| {
+                    format!("# {}\n\n{}\n", channel.command(), channel.logs().join("\n"))
+                })
+                .join("\n"),
+        )
+    }
+
+    /// Kills the LSP servers of the language of the file, and then starts them again,
+    /// so that a stuck server can be recovered without restarting the editor.
+    /// Returns false if no LSP server is running for the file.
+    pub(crate) fn restart(&mut self, path: CanonicalizedPath) -> anyhow::Result<bool> {
+        let Some(language_id) = self.language(&path).and_then(|language| language.id()) else {
+            return Ok(false);
+        };
+        let Some(channels) = self.lsp_server_process_channels.remove(&language_id) else {
+            return Ok(false);
+        };
+        for channel in channels {
+            // The diagnostics of the killed servers are published again by the new servers
+            for diagnostics_by_server in self.diagnostics.values_mut() {
+                diagnostics_by_server.remove(channel.name());
             }
+            channel
+                .kill()
+                .unwrap_or_else(|error| log::error!("{:?}", error));
+        }
+        self.open_file(path)?;
+        Ok(true)
+    }
+
+    /// Returns the diagnostics of the file by every server,
+    /// after replacing the diagnostics of the file by the given server.
+    pub(crate) fn merge_diagnostics(
+        &mut self,
+        path: CanonicalizedPath,
+        server: String,
+        diagnostics: Vec<lsp_types::Diagnostic>,
+    ) -> Vec<lsp_types::Diagnostic> {
+        let diagnostics_by_server = self.diagnostics.entry(path).or_default();
+        diagnostics_by_server.insert(server, diagnostics);
+        diagnostics_by_server.values().flatten().cloned().collect()
+    }
+
+    /// Returns the completion of every server that has responded to the latest completion request.
+    pub(crate) fn merge_completion(
+        &mut self,
+        server: String,
+        completion: Completion,
+    ) -> Completion {
+        self.completions.insert(server, completion);
+        Completion {
+            items: self
+                .completions
+                .values()
+                .flat_map(|completion| completion.items.clone())
+                .collect(),
+            trigger_characters: self
+                .completions
+                .values()
+                .flat_map(|completion| completion.trigger_characters.clone())
+                .unique()
+                .collect(),
+        }
+    }
+
+    /// Returns the code actions of every server that has responded to
+    /// the latest code action request.
+    pub(crate) fn merge_code_actions(
+        &mut self,
+        server: String,
+        code_actions: Vec<CodeAction>,
+    ) -> Vec<CodeAction> {
+        self.code_actions.insert(server, code_actions);
+        self.code_actions.values().flatten().cloned().collect()
+    }
+
+    pub(crate) fn set_config(&mut self, config: Config) {
+        self.config = config
+    }
+
+    /// The default server of the language (if any) followed by the additional servers
+    /// of the config, where the default server is named by its command, and the additional
+    /// servers are named by their keys in the config.
+    fn servers(&self, language: &Language) -> Vec<anyhow::Result<Server>> {
+        let default = Self::server(None, self.config.lsp(language), Some(language)).transpose();
+        let additional =
+            self.config
+                .lsp_servers(language)
+                .into_iter()
+                .flatten()
+                .map(|(name, config)| {
+                    let server = Self::server(Some(name.clone()), Some(config), None)?;
+                    server.ok_or_else(|| {
+                        anyhow::anyhow!("The LSP server `{name}` needs a `command` or an `address`")
+                    })
+                });
+        default.into_iter().chain(additional).collect()
+    }
+
+    /// The command and initialization options of the config take precedence over
+    /// the defaults of the language (if any), and the address of the config takes precedence
+    /// over the command.
+    fn server(
+        name: Option<String>,
+        config: Option<&LspConfig>,
+        language: Option<&Language>,
+    ) -> anyhow::Result<Option<Server>> {
+        let initialization_options = config
+            .and_then(|config| config.initialization_options.clone())
+            .or_else(|| language?.initialization_options());
+        let path_translation = PathTranslation::new(
+            config
+                .and_then(|config| config.path_mappings.clone())
+                .unwrap_or_default(),
+        );
+        let root_markers = config
+            .and_then(|config| config.root_markers.clone())
+            .unwrap_or_default();
+        let transport = if let Some(address) = config.and_then(|config| config.address.as_ref()) {
+            Transport::from_address(address)?
         } else {
-            LspServerProcessChannel::new(
-                language.clone(),
-                self.sender.clone(),
-                self.current_working_directory.clone(),
-            )
-            .map(|channel| {
-                if let Some(channel) = channel {
-                    self.lsp_server_process_channels
-                        .insert(language.id()?, channel);
+            let process_command = match config.and_then(|config| config.command.as_ref()) {
+                Some(command) => {
+                    let args = config
+                        .and_then(|config| config.args.as_ref())
+                        .map(|args| args.iter().map(String::as_str).collect_vec())
+                        .unwrap_or_default();
+                    ProcessCommand::new(command, &args)
                 }
-                Some(())
-            })?;
-            Ok(())
-        }
+                None => match language.and_then(|language| language.lsp_process_command()) {
+                    Some(process_command) => process_command,
+                    None => return Ok(None),
+                },
+            };
+            let process_command = match config.and_then(|config| config.wrapper.as_ref()) {
+                Some(wrapper) => process_command.wrap(wrapper)?,
+                None => process_command,
+            };
+            Transport::Stdio(process_command)
+        };
+        Ok(Some(Server {
+            name: name.unwrap_or_else(|| transport.name()),
+            transport,
+            initialization_options,
+            path_translation,
+            root_markers,
+        }))
+    }
+
+    /// The nearest ancestor of the file that contains any of the root markers,
+    /// or the working directory if there is none.
+    fn root_directory(
+        &self,
+        path: &CanonicalizedPath,
+        root_markers: &[String],
+    ) -> CanonicalizedPath {
+        path.as_ref()
+            .ancestors()
+            .skip(1)
+            .find(|directory| {
+                root_markers
+                    .iter()
+                    .any(|marker| directory.join(marker).exists())
+            })
+            .and_then(|directory| CanonicalizedPath::try_from(directory).ok())
+            .unwrap_or_else(|| self.current_working_directory.clone())
+    }
+
+    fn language(&self, path: &CanonicalizedPath) -> Option<Language> {
+        self.language_overrides
+            .get(path)
+            .cloned()
+            .or_else(|| language::from_path(path))
+    }
+
+    /// Detaches the file from the LSP servers of its previous language,
+    /// and attaches it to the LSP servers of the given language.
+    pub(crate) fn set_language(
+        &mut self,
+        path: CanonicalizedPath,
+        language: Language,
+    ) -> anyhow::Result<()> {
+        self.send_message(
+            path.clone(),
+            FromEditor::TextDocumentDidClose {
+                file_path: path.clone(),
+            },
+        )?;
+        self.diagnostics.remove(&path);
+        self.language_overrides.insert(path.clone(), language);
+        self.open_file(path)
     }
 
     pub(crate) fn initialized(
         &mut self,
+        server: String,
         language: Language,
         opened_documents: Vec<CanonicalizedPath>,
     ) {
@@ -113,6 +473,7 @@ impl LspManager {
         };
         self.lsp_server_process_channels
             .get_mut(&language_id)
+            .and_then(|channels| channels.iter_mut().find(|channel| channel.name() == server))
             .map(|channel| {
                 channel.initialized();
                 channel.documents_did_open(opened_documents)
@@ -120,7 +481,11 @@ impl LspManager {
     }
 
     pub(crate) fn shutdown(&mut self) {
-        for (_, channel) in self.lsp_server_process_channels.drain() {
+        for channel in self
+            .lsp_server_process_channels
+            .drain()
+            .flat_map(|(_, channels)| channels)
+        {
             channel
                 .shutdown()
                 .unwrap_or_else(|error| log::error!("{:?}", error));
@@ -132,3 +497,19 @@ impl LspManager {
         self.history.get(from_editor.variant()) == Some(from_editor)
     }
 }
+
+/// The servers of the language that a message is sent to
+enum Recipients<'a> {
+    Every,
+    First,
+    /// The server of the name
+    Named(&'a str),
+}
+
+struct Server {
+    name: String,
+    transport: Transport,
+    initialization_options: Option<serde_json::Value>,
+    path_translation: PathTranslation,
+    root_markers: Vec<String>,
+}
@@ -1,13 +1,18 @@
 pub(crate) mod code_action;
 pub(crate) mod completion;
+pub(crate) mod connection;
 pub(crate) mod diagnostic;
+pub(crate) mod document_highlight;
 pub(crate) mod documentation;
+pub(crate) mod framing;
 
 pub(crate) mod goto_definition_response;
+pub(crate) mod hierarchy;
 pub(crate) mod hover;
 pub(crate) mod manager;
 pub(crate) mod prepare_rename_response;
 pub(crate) mod process;
+pub(crate) mod progress;
 pub(crate) mod signature_help;
 pub(crate) mod symbols;
 pub(crate) mod workspace_edit;
@@ -1,6 +1,7 @@
 use crate::app::{RequestParams, Scope};
 use anyhow::Context;
 use debounce::EventDebouncer;
+use indexmap::IndexMap;
 use lsp_types::notification::Notification;
 use lsp_types::request::{
     GotoDeclarationParams, GotoImplementationParams, GotoTypeDefinitionParams, Request,
@@ -9,12 +10,11 @@ use lsp_types::*;
 use name_variant::NamedVariant;
 use shared::canonicalized_path::CanonicalizedPath;
 use shared::language::Language;
-use shared::process_command::SpawnCommandResult;
-use std::collections::HashMap;
-use std::io::{BufRead, BufReader, Read, Write};
+use std::collections::{HashMap, VecDeque};
+use std::io::{BufReader, Read, Write};
 
-use std::process::{self};
 use std::sync::mpsc::{Receiver, Sender};
+use std::sync::{Arc, Mutex};
 use std::thread::{self, JoinHandle};
 use std::time::{Duration, Instant};
 
@@ -23,24 +23,46 @@ use crate::utils::consolidate_errors;
 
 use super::code_action::CodeAction;
 use super::completion::{Completion, CompletionItem};
+use super::connection::{PathTranslation, Transport};
+use super::document_highlight::DocumentHighlight;
 use super::goto_definition_response::GotoDefinitionResponse;
+use super::hierarchy::{HierarchyContext, HierarchyItem, HierarchyKind};
 use super::hover::Hover;
 use super::prepare_rename_response::PrepareRenameResponse;
+use super::progress::Progress;
 use super::signature_help::SignatureHelp;
 use super::symbols::Symbols;
 use super::workspace_edit::WorkspaceEdit;
 use crate::quickfix_list::Location;
 
+/// The number of the latest log messages of a server that are kept
+const LOG_CAPACITY: usize = 1000;
+
+type Logs = Arc<Mutex<VecDeque<String>>>;
+
 struct LspServerProcess {
     language: Language,
-    stdin: process::ChildStdin,
+    /// Identifies the server among the servers of its language, see `LspManager`
+    name: String,
+    transport: Transport,
+    initialization_options: Option<serde_json::Value>,
+    path_translation: PathTranslation,
+    stdin: Box<dyn Write + Send>,
 
     /// This is hacky, but we need to keep the stdout around so that it doesn't get dropped
-    stdout: Option<process::ChildStdout>,
-    stderr: Option<process::ChildStderr>,
+    stdout: Option<Box<dyn Read + Send>>,
+    stderr: Option<Box<dyn Read + Send>>,
 
     server_capabilities: Option<ServerCapabilities>,
-    current_working_directory: CanonicalizedPath,
+    /// The root of the workspace of the server
+    root_directory: CanonicalizedPath,
+    /// The items of the latest completion, which are the only items resolved by this server,
+    /// because the other servers of the language might have sent the resolved item
+    completion_items: Vec<lsp_types::CompletionItem>,
+    /// The ongoing work done progress by their tokens, in the order that they began
+    progress: IndexMap<ProgressToken, Progress>,
+    /// Shared with the channel, so the logs are read without messaging the app
+    logs: Logs,
     next_request_id: RequestId,
     pending_response_requests: HashMap<RequestId, PendingResponseRequest>,
     app_message_sender: Sender<AppMessage>,
@@ -56,27 +78,35 @@ struct PendingResponseRequest {
     context: ResponseContext,
 }
 
+/// The `String` of the notifications that are merged across the servers of a language
+/// is the name of the server that sent them, see `LspManager`.
 #[derive(Debug, Clone, PartialEq)]
 pub(crate) enum LspNotification {
-    Initialized(Language),
-    PublishDiagnostics(PublishDiagnosticsParams),
-    Completion(ResponseContext, Completion),
+    Initialized(String, Language),
+    PublishDiagnostics(String, PublishDiagnosticsParams),
+    Completion(String, ResponseContext, Completion),
     Hover(Hover),
     Definition(ResponseContext, GotoDefinitionResponse),
     References(ResponseContext, Vec<Location>),
     PrepareRenameResponse(PrepareRenameResponse),
     Error(String),
     WorkspaceEdit(WorkspaceEdit),
-    CodeAction(Vec<CodeAction>),
+    CodeAction(String, Vec<CodeAction>),
     SignatureHelp(Option<SignatureHelp>),
     Symbols(Symbols),
     CompletionItemResolve(lsp_types::CompletionItem),
+    /// The ongoing progress of a server, which is empty once the server is idle
+    Progress(String, Language, Vec<Progress>),
+    DocumentHighlight(Vec<DocumentHighlight>),
+    /// The roots or the children of a hierarchy, depending on the parent of the context
+    Hierarchy(HierarchyContext, Vec<HierarchyItem>),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
 pub(crate) struct ResponseContext {
     pub(crate) scope: Option<Scope>,
     pub(crate) description: Option<String>,
+    pub(crate) hierarchy: Option<HierarchyContext>,
 }
 impl ResponseContext {
     pub(crate) fn set_description(self, descrption: &str) -> Self {
@@ -121,6 +151,9 @@ pub(crate) enum FromEditor {
     TextDocumentDidSave {
         file_path: CanonicalizedPath,
     },
+    TextDocumentDidClose {
+        file_path: CanonicalizedPath,
+    },
     TextDocumentPrepareRename(RequestParams),
     TextDocumentRename {
         params: RequestParams,
@@ -135,6 +168,16 @@ pub(crate) enum FromEditor {
     TextDocumentImplementation(RequestParams),
     TextDocumentTypeDefinition(RequestParams),
     TextDocumentDocumentSymbol(RequestParams),
+    TextDocumentDocumentHighlight(RequestParams),
+    /// Requests the roots of the hierarchy of the symbol under the cursor
+    PrepareHierarchy {
+        params: RequestParams,
+        kind: HierarchyKind,
+    },
+    HierarchyChildren {
+        kind: HierarchyKind,
+        item: HierarchyItem,
+    },
     WorkspaceDidRenameFiles {
         old: CanonicalizedPath,
         new: CanonicalizedPath,
@@ -157,18 +200,47 @@ impl FromEditor {
 
 pub(crate) struct LspServerProcessChannel {
     language: Language,
+    name: String,
+    /// The name of the command of the LSP server, for example `rust-analyzer`,
+    /// or its address if it is connected over a socket
+    command: String,
     join_handle: JoinHandle<JoinHandle<()>>,
     sender: Sender<LspServerProcessMessage>,
     is_initialized: bool,
+    progress: Vec<Progress>,
+    logs: Logs,
+    /// `None` if the server is connected over a socket
+    child: Option<std::process::Child>,
 }
 
 impl LspServerProcessChannel {
+    /// Returns `None` if the command of the LSP server is not installed.
     pub(crate) fn new(
         language: Language,
+        name: String,
+        transport: Transport,
+        initialization_options: Option<serde_json::Value>,
+        path_translation: PathTranslation,
         screen_message_sender: Sender<AppMessage>,
-        current_working_directory: CanonicalizedPath,
+        root_directory: CanonicalizedPath,
     ) -> Result<Option<LspServerProcessChannel>, anyhow::Error> {
-        LspServerProcess::start(language, screen_message_sender, current_working_directory)
+        LspServerProcess::start(
+            language,
+            name,
+            transport,
+            initialization_options,
+            path_translation,
+            screen_message_sender,
+            root_directory,
+        )
+    }
+
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub(crate) fn command(&self) -> &str {
+        &self.command
     }
 
     pub(crate) fn shutdown(self) -> anyhow::Result<()> {
@@ -180,6 +252,18 @@ impl LspServerProcessChannel {
             .map_err(|err| anyhow::anyhow!("Unable to join lsp server process [2]: {:?}", err))
     }
 
+    /// Unlike `shutdown`, this does not wait for the server, which might be stuck.
+    /// The server is killed if it is a child process, otherwise only its connection is dropped.
+    pub(crate) fn kill(mut self) -> anyhow::Result<()> {
+        if let Some(child) = self.child.as_mut() {
+            child.kill()?;
+            child.wait()?;
+        }
+        // The process might have stopped already, when the server exited by itself
+        let _ = self.send(LspServerProcessMessage::Shutdown);
+        Ok(())
+    }
+
     fn send(&self, message: LspServerProcessMessage) -> anyhow::Result<()> {
         self.sender
             .send(message)
@@ -222,6 +306,19 @@ impl LspServerProcessChannel {
         self.is_initialized = true
     }
 
+    pub(crate) fn progress(&self) -> &[Progress] {
+        &self.progress
+    }
+
+    pub(crate) fn set_progress(&mut self, progress: Vec<Progress>) {
+        self.progress = progress
+    }
+
+    /// The latest messages of `window/logMessage` and `window/showMessage`, oldest first
+    pub(crate) fn logs(&self) -> Vec<String> {
+        self.logs.lock().unwrap().iter().cloned().collect()
+    }
+
     pub(crate) fn send_from_editor(&self, from_editor: FromEditor) -> Result<(), anyhow::Error> {
         self.send(LspServerProcessMessage::FromEditor(from_editor))
     }
@@ -230,41 +327,35 @@ impl LspServerProcessChannel {
 impl LspServerProcess {
     fn start(
         language: Language,
+        name: String,
+        transport: Transport,
+        initialization_options: Option<serde_json::Value>,
+        path_translation: PathTranslation,
         app_message_sender: Sender<AppMessage>,
-        current_working_directory: CanonicalizedPath,
+        root_directory: CanonicalizedPath,
     ) -> anyhow::Result<Option<LspServerProcessChannel>> {
-        let process_command = match language.lsp_process_command() {
-            Some(result) => result,
-            None => return Ok(None),
-        };
-
-        let mut process = match process_command.spawn() {
-            SpawnCommandResult::Spawned(result) => result?,
-            SpawnCommandResult::CommandNotFound { .. } => {
-                return Ok(None);
-            }
+        let Some(connection) = transport
+            .connect()
+            .with_context(|| format!("Unable to connect to the LSP server `{transport}`"))?
+        else {
+            return Ok(None);
         };
-        let stdin = process
-            .stdin
-            .take()
-            .ok_or_else(|| anyhow::anyhow!("Unable to obtain stdin"))?;
-
-        let stderr = process
-            .stderr
-            .take()
-            .ok_or_else(|| anyhow::anyhow!("Unable to obtain stderr"))?;
-
-        let stdout = process
-            .stdout
-            .take()
-            .ok_or_else(|| anyhow::anyhow!("Unable to obtain stdout"))?;
         let (sender, receiver) = std::sync::mpsc::channel::<LspServerProcessMessage>();
+        let command = transport.name();
+        let logs = Logs::default();
         let mut lsp_server_process = LspServerProcess {
             language: language.clone(),
-            stdin,
-            stdout: Some(stdout),
-            stderr: Some(stderr),
-            current_working_directory,
+            name: name.clone(),
+            transport,
+            initialization_options,
+            path_translation,
+            stdin: connection.writer,
+            stdout: Some(connection.reader),
+            stderr: Some(connection.stderr),
+            root_directory,
+            completion_items: Vec::new(),
+            progress: IndexMap::new(),
+            logs: logs.clone(),
             next_request_id: 0,
             pending_response_requests: HashMap::new(),
             server_capabilities: None,
@@ -279,9 +370,14 @@ impl LspServerProcess {
 
         Ok(Some(LspServerProcessChannel {
             language,
+            name,
+            command,
             join_handle,
             sender,
             is_initialized: false,
+            progress: Vec::new(),
+            logs,
+            child: connection.child,
         }))
     }
 
@@ -292,9 +388,9 @@ impl LspServerProcess {
                 process_id: None,
                 root_uri: Some(Url::parse(&format!(
                     "file://{}",
-                    self.current_working_directory.display_absolute()
+                    self.root_directory.display_absolute()
                 ))?),
-                initialization_options: self.language.initialization_options(),
+                initialization_options: self.initialization_options.clone(),
 
                 capabilities: ClientCapabilities {
                     workspace: Some(WorkspaceClientCapabilities {
@@ -371,6 +467,7 @@ impl LspServerProcess {
                             }),
                             ..Default::default()
                         }),
+                        call_hierarchy: Some(Default::default()),
                         rename: Some(RenameClientCapabilities {
                             prepare_support: Some(true),
                             ..Default::default()
@@ -391,6 +488,10 @@ impl LspServerProcess {
                         }),
                         ..TextDocumentClientCapabilities::default()
                     }),
+                    window: Some(WindowClientCapabilities {
+                        work_done_progress: Some(true),
+                        ..Default::default()
+                    }),
                     ..ClientCapabilities::default()
                 },
                 workspace_folders: None,
@@ -419,6 +520,7 @@ impl LspServerProcess {
         let stdout_handle = self.spawn_stdout_reader(
             stdout_reader,
             stderr_reader,
+            self.path_translation.clone(),
             sender.clone(),
             app_message_sender.clone(),
             lsp_command,
@@ -437,8 +539,9 @@ impl LspServerProcess {
     /// Sends shutdown signal if too many errors occur
     fn spawn_stdout_reader(
         &self,
-        mut stdout_reader: BufReader<process::ChildStdout>,
-        mut stderr_reader: BufReader<process::ChildStderr>,
+        mut stdout_reader: BufReader<Box<dyn Read + Send>>,
+        mut stderr_reader: BufReader<Box<dyn Read + Send>>,
+        path_translation: PathTranslation,
         sender: Sender<LspServerProcessMessage>,
         app_message_sender: Sender<AppMessage>,
         lsp_command: String,
@@ -448,7 +551,7 @@ impl LspServerProcess {
 
             // The stdout reader loop
             loop {
-                match Self::read_response(&mut stdout_reader, &sender) {
+                match Self::read_response(&mut stdout_reader, &path_translation, &sender) {
                     Ok(()) => error_tracker.handle_success(),
                     Err(error) => {
                         if !error_tracker.handle_error(error, &mut stderr_reader, &sender) {
@@ -539,44 +642,17 @@ impl LspServerProcess {
     }
 
     /// Handles low-level LSP protocol message parsing:
-    /// 1. Reads Content-Length header
-    /// 2. Reads message content
-    /// 3. Parses JSON
-    /// 4. Sends parsed message back via channel
+    /// 1. Reads the message framed by the Content-Length header, see `super::framing`
+    /// 2. Translates the paths of the LSP server to the local paths
+    /// 3. Sends parsed message back via channel
     fn read_response(
-        reader: &mut BufReader<process::ChildStdout>,
+        reader: &mut BufReader<Box<dyn Read + Send>>,
+        path_translation: &PathTranslation,
         sender: &Sender<LspServerProcessMessage>,
     ) -> anyhow::Result<()> {
-        let mut line = String::new();
-        reader
-            .read_line(&mut line)
-            .with_context(|| "Failed to read Content-Length")?;
-
-        let content_length = line
-            .split(':')
-            .nth(1)
-            .ok_or_else(|| anyhow::anyhow!("Parsing Content-Length: Unable to split line."))?
-            .trim()
-            .parse::<usize>()
-            .with_context(|| "Parsing Content-Length: Failed to parse number.")?;
-
-        // According to https://microsoft.github.io/language-server-protocol/specifications/lsp/3.17/specification/#headerPart
-        //
-        // ... this means that TWO '\r\n' sequences always immediately precede the content part of a message.
-        reader
-            .read_line(&mut line)
-            .with_context(|| "Failed to read content.")?;
-
-        let mut buffer = vec![0; content_length];
-        reader
-            .read_exact(&mut buffer)
-            .with_context(|| "Failed to read buffer into vector.")?;
-
-        let reply = String::from_utf8(buffer)
-            .with_context(|| "Failed to convert content buffer into String.")?;
-
-        let reply: serde_json::Value = serde_json::from_str(&reply)
-            .with_context(|| "Failed to convert content string into JSON value")?;
+        let mut reply = super::framing::read_message(reader)?
+            .ok_or_else(|| anyhow::anyhow!("The stdout of the LSP server is closed."))?;
+        path_translation.to_local(&mut reply);
 
         sender
             .send(LspServerProcessMessage::FromLspServer(reply))
@@ -646,7 +722,7 @@ impl LspServerProcess {
                         )?;
 
                         self.app_message_sender.send(AppMessage::LspNotification(
-                            LspNotification::Initialized(self.language.clone()),
+                            LspNotification::Initialized(self.name.clone(), self.language.clone()),
                         ))?;
                     }
                     "textDocument/completion" => {
@@ -654,19 +730,26 @@ impl LspServerProcess {
                             serde_json::from_value(response)?;
 
                         if let Some(payload) = payload {
+                            self.completion_items = match payload {
+                                CompletionResponse::Array(items) => items,
+                                CompletionResponse::List(list) => list.items,
+                            };
                             self.app_message_sender
                                 .send(AppMessage::LspNotification(LspNotification::Completion(
+                                    self.name.clone(),
                                     response_context,
                                     Completion {
                                         trigger_characters: self.trigger_characters(),
-                                        items: match payload {
-                                            CompletionResponse::Array(items) => items,
-                                            CompletionResponse::List(list) => list.items,
-                                        }
-                                        .into_iter()
-                                        .map(CompletionItem::from)
-                                        .map(|item| item.into())
-                                        .collect(),
+                                        items: self
+                                            .completion_items
+                                            .iter()
+                                            .cloned()
+                                            .map(|item| {
+                                                CompletionItem::from(item)
+                                                    .set_server(self.name.clone())
+                                                    .into()
+                                            })
+                                            .collect(),
                                     },
                                 )))
                                 .unwrap();
@@ -783,12 +866,18 @@ impl LspServerProcess {
                         if let Some(payload) = payload {
                             self.app_message_sender
                                 .send(AppMessage::LspNotification(LspNotification::CodeAction(
+                                    self.name.clone(),
                                     payload
                                         .into_iter()
                                         .map(|r| match r {
                                             CodeActionOrCommand::Command(_) => todo!(),
                                             CodeActionOrCommand::CodeAction(code_action) => {
-                                                code_action.try_into()
+                                                CodeAction::try_from(code_action).map(
+                                                    |code_action| CodeAction {
+                                                        server: Some(self.name.clone()),
+                                                        ..code_action
+                                                    },
+                                                )
                                             }
                                         })
                                         .collect::<Result<Vec<_>, _>>()?,
@@ -806,6 +895,58 @@ impl LspServerProcess {
                             )))
                             .unwrap();
                     }
+                    "textDocument/prepareCallHierarchy" => {
+                        let payload: <lsp_request!("textDocument/prepareCallHierarchy") as Request>::Result =
+                            serde_json::from_value(response)?;
+                        self.send_hierarchy(response_context, payload.unwrap_or_default())?
+                    }
+                    "callHierarchy/incomingCalls" => {
+                        let payload: <lsp_request!("callHierarchy/incomingCalls") as Request>::Result =
+                            serde_json::from_value(response)?;
+                        let items = payload
+                            .unwrap_or_default()
+                            .into_iter()
+                            .map(|call| call.from);
+                        self.send_hierarchy(response_context, items.collect())?
+                    }
+                    "callHierarchy/outgoingCalls" => {
+                        let payload: <lsp_request!("callHierarchy/outgoingCalls") as Request>::Result =
+                            serde_json::from_value(response)?;
+                        let items = payload.unwrap_or_default().into_iter().map(|call| call.to);
+                        self.send_hierarchy(response_context, items.collect())?
+                    }
+                    "textDocument/prepareTypeHierarchy" => {
+                        let payload: <lsp_request!("textDocument/prepareTypeHierarchy") as Request>::Result =
+                            serde_json::from_value(response)?;
+                        self.send_hierarchy(response_context, payload.unwrap_or_default())?
+                    }
+                    "typeHierarchy/supertypes" => {
+                        let payload: <lsp_request!("typeHierarchy/supertypes") as Request>::Result =
+                            serde_json::from_value(response)?;
+                        self.send_hierarchy(response_context, payload.unwrap_or_default())?
+                    }
+                    "typeHierarchy/subtypes" => {
+                        let payload: <lsp_request!("typeHierarchy/subtypes") as Request>::Result =
+                            serde_json::from_value(response)?;
+                        self.send_hierarchy(response_context, payload.unwrap_or_default())?
+                    }
+                    "textDocument/documentHighlight" => {
+                        let payload: <lsp_request!("textDocument/documentHighlight") as Request>::Result =
+                            serde_json::from_value(response)?;
+
+                        if let Some(payload) = payload {
+                            self.app_message_sender
+                                .send(AppMessage::LspNotification(
+                                    LspNotification::DocumentHighlight(
+                                        payload
+                                            .into_iter()
+                                            .map(|highlight| highlight.into())
+                                            .collect(),
+                                    ),
+                                ))
+                                .unwrap();
+                        }
+                    }
                     "textDocument/documentSymbol" => {
                         let payload: <lsp_request!("textDocument/documentSymbol") as Request>::Result =
                             serde_json::from_value(response)?;
@@ -856,7 +997,7 @@ impl LspServerProcess {
 
                         self.app_message_sender
                             .send(AppMessage::LspNotification(
-                                LspNotification::PublishDiagnostics(params),
+                                LspNotification::PublishDiagnostics(self.name.clone(), params),
                             ))
                             .unwrap();
                     }
@@ -876,21 +1017,47 @@ impl LspServerProcess {
 
                         self.send_reply(request.id, serde_json::Value::Null)?;
                     }
+                    "window/workDoneProgress/create" => {
+                        self.send_reply(request.id, serde_json::Value::Null)?;
+                    }
+                    "$/progress" => {
+                        let params: <lsp_notification!("$/progress") as Notification>::Params =
+                            serde_json::from_value(
+                                request
+                                    .params
+                                    .ok_or_else(|| anyhow::anyhow!("Missing params"))?,
+                            )?;
+                        let ProgressParamsValue::WorkDone(progress) = params.value;
+                        match progress {
+                            WorkDoneProgress::Begin(begin) => {
+                                self.progress.insert(params.token, begin.into());
+                            }
+                            WorkDoneProgress::Report(report) => {
+                                if let Some(progress) = self.progress.get_mut(&params.token) {
+                                    progress.report(report)
+                                }
+                            }
+                            WorkDoneProgress::End(_) => {
+                                self.progress.shift_remove(&params.token);
+                            }
+                        }
+                        self.app_message_sender
+                            .send(AppMessage::LspNotification(LspNotification::Progress(
+                                self.name.clone(),
+                                self.language.clone(),
+                                self.progress.values().cloned().collect(),
+                            )))
+                            .unwrap();
+                    }
+                    "window/showMessage" => {
+                        let params: <lsp_notification!("window/showMessage") as Notification>::Params =
+                            serde_json::from_value(request.params.ok_or_else(|| anyhow::anyhow!("Missing params"))?)?;
+                        self.log(params.typ, &params.message);
+                    }
                     "window/logMessage" => {
-                        let command = self.lsp_command();
                         let params: <lsp_notification!("window/logMessage") as Notification>::Params =
                             serde_json::from_value(request.params.ok_or_else(|| anyhow::anyhow!("Missing params"))?)?;
-                        let typ = match params.typ {
-                            MessageType::LOG => "LOG".to_string(),
-                            MessageType::ERROR => "ERROR".to_string(),
-                            MessageType::WARNING => "WARNING".to_string(),
-                            MessageType::INFO => "INFO".to_string(),
-                            _ => format!("[Unknown message type {:?}]", params.typ),
-                        };
-                        log::info!(
-                            "LSP(window/logMessage)({command})[{typ}]: '{}'",
-                            params.message
-                        )
+                        self.log(params.typ, &params.message);
                     }
 
                     _ => log::info!("unhandled Incoming Notification: {}", method),
@@ -901,6 +1068,23 @@ impl LspServerProcess {
         Ok(())
     }
 
+    /// The message is kept for the `lsp-logs` command, besides being logged.
+    fn log(&self, typ: MessageType, message: &str) {
+        let typ = match typ {
+            MessageType::LOG => "LOG".to_string(),
+            MessageType::ERROR => "ERROR".to_string(),
+            MessageType::WARNING => "WARNING".to_string(),
+            MessageType::INFO => "INFO".to_string(),
+            _ => format!("[Unknown message type {:?}]", typ),
+        };
+        log::info!("LSP({})[{typ}]: '{message}'", self.lsp_command());
+        let mut logs = self.logs.lock().unwrap();
+        if logs.len() == LOG_CAPACITY {
+            logs.pop_front();
+        }
+        logs.push_back(format!("[{typ}] {message}"));
+    }
+
     fn trigger_characters(&self) -> Vec<String> {
         self.server_capabilities
             .as_ref()
@@ -956,18 +1140,12 @@ impl LspServerProcess {
         Ok(())
     }
 
-    /// Send JSON to the LSP server by writing to the server's stdin
+    /// Send JSON to the LSP server by writing to the server's stdin,
+    /// where the paths are translated to the paths of the LSP server
     fn send_json<T: serde::Serialize>(&mut self, value: T) -> anyhow::Result<()> {
-        let json = serde_json::to_string(&value)?;
-
-        // The message format is according to https://microsoft.github.io/language-server-protocol/specifications/lsp/3.17/specification/#contentPart
-        write!(
-            &mut self.stdin,
-            "Content-Length: {}\r\n\r\n{}",
-            json.len(),
-            json
-        )?;
-        Ok(())
+        let mut value = serde_json::to_value(&value)?;
+        self.path_translation.to_remote(&mut value);
+        super::framing::write_message(&mut self.stdin, &value)
     }
 
     /// Returns the request ID
@@ -1057,6 +1235,17 @@ impl LspServerProcess {
         )
     }
 
+    fn text_document_did_close(
+        &mut self,
+        file_path: CanonicalizedPath,
+    ) -> Result<(), anyhow::Error> {
+        self.send_notification::<lsp_notification!("textDocument/didClose")>(
+            DidCloseTextDocumentParams {
+                text_document: path_buf_to_text_document_identifier(file_path)?,
+            },
+        )
+    }
+
     fn workspace_did_rename_files(
         &mut self,
         old: CanonicalizedPath,
@@ -1318,6 +1507,132 @@ impl LspServerProcess {
         )
     }
 
+    /// The servers without type hierarchies reply with an error, because their capabilities
+    /// are not checked.
+    fn prepare_hierarchy(
+        &mut self,
+        params: RequestParams,
+        kind: HierarchyKind,
+    ) -> Result<(), anyhow::Error> {
+        let context = ResponseContext {
+            hierarchy: Some(HierarchyContext { kind, parent: None }),
+            ..params.context
+        };
+        let text_document_position_params = TextDocumentPositionParams {
+            position: params.position.into(),
+            text_document: path_buf_to_text_document_identifier(params.path)?,
+        };
+        if !kind.is_call() {
+            return self.send_request::<lsp_request!("textDocument/prepareTypeHierarchy")>(
+                context,
+                TypeHierarchyPrepareParams {
+                    text_document_position_params,
+                    work_done_progress_params: Default::default(),
+                },
+            );
+        }
+        if !self.has_capability(|c| c.call_hierarchy_provider.is_some()) {
+            return Ok(());
+        }
+        self.send_request::<lsp_request!("textDocument/prepareCallHierarchy")>(
+            context,
+            CallHierarchyPrepareParams {
+                text_document_position_params,
+                work_done_progress_params: Default::default(),
+            },
+        )
+    }
+
+    fn hierarchy_children(
+        &mut self,
+        kind: HierarchyKind,
+        item: HierarchyItem,
+    ) -> Result<(), anyhow::Error> {
+        let context = ResponseContext {
+            hierarchy: Some(HierarchyContext {
+                kind,
+                parent: Some(item.clone()),
+            }),
+            ..Default::default()
+        };
+        match kind {
+            HierarchyKind::IncomingCalls => self
+                .send_request::<lsp_request!("callHierarchy/incomingCalls")>(
+                    context,
+                    CallHierarchyIncomingCallsParams {
+                        item: item.call_hierarchy_item()?,
+                        work_done_progress_params: Default::default(),
+                        partial_result_params: Default::default(),
+                    },
+                ),
+            HierarchyKind::OutgoingCalls => self
+                .send_request::<lsp_request!("callHierarchy/outgoingCalls")>(
+                    context,
+                    CallHierarchyOutgoingCallsParams {
+                        item: item.call_hierarchy_item()?,
+                        work_done_progress_params: Default::default(),
+                        partial_result_params: Default::default(),
+                    },
+                ),
+            HierarchyKind::Supertypes => self
+                .send_request::<lsp_request!("typeHierarchy/supertypes")>(
+                    context,
+                    TypeHierarchySupertypesParams {
+                        item: item.type_hierarchy_item()?,
+                        work_done_progress_params: Default::default(),
+                        partial_result_params: Default::default(),
+                    },
+                ),
+            HierarchyKind::Subtypes => self.send_request::<lsp_request!("typeHierarchy/subtypes")>(
+                context,
+                TypeHierarchySubtypesParams {
+                    item: item.type_hierarchy_item()?,
+                    work_done_progress_params: Default::default(),
+                    partial_result_params: Default::default(),
+                },
+            ),
+        }
+    }
+
+    /// `context` tells which node of the hierarchy the items belong to, see `hierarchy_children`.
+    fn send_hierarchy<T>(&self, context: ResponseContext, items: Vec<T>) -> anyhow::Result<()>
+    where
+        T: TryInto<HierarchyItem, Error = anyhow::Error>,
+    {
+        let Some(hierarchy) = context.hierarchy else {
+            return Ok(());
+        };
+        let items = items
+            .into_iter()
+            .map(|item| item.try_into())
+            .collect::<Result<Vec<_>, _>>()?;
+        self.app_message_sender
+            .send(AppMessage::LspNotification(LspNotification::Hierarchy(
+                hierarchy, items,
+            )))?;
+        Ok(())
+    }
+
+    fn text_document_document_highlight(
+        &mut self,
+        params: RequestParams,
+    ) -> Result<(), anyhow::Error> {
+        if !self.has_capability(|c| c.document_highlight_provider.is_some()) {
+            return Ok(());
+        }
+        self.send_request::<lsp_request!("textDocument/documentHighlight")>(
+            params.context,
+            DocumentHighlightParams {
+                text_document_position_params: TextDocumentPositionParams {
+                    position: params.position.into(),
+                    text_document: path_buf_to_text_document_identifier(params.path)?,
+                },
+                work_done_progress_params: Default::default(),
+                partial_result_params: Default::default(),
+            },
+        )
+    }
+
     fn text_document_document_symbol(
         &mut self,
         params: RequestParams,
@@ -1340,7 +1655,13 @@ impl LspServerProcess {
         params: RequestParams,
         command: super::code_action::Command,
     ) -> Result<(), anyhow::Error> {
-        if !self.has_capability(|c| c.execute_command_provider.is_some()) {
+        // The command is executed by the server that provides it only
+        if !self.has_capability(|c| {
+            c.execute_command_provider
+                .as_ref()
+                .map(|provider| provider.commands.contains(&command.command()))
+                .unwrap_or(false)
+        }) {
             return Ok(());
         }
         self.send_request::<lsp_request!("workspace/executeCommand")>(
@@ -1365,7 +1686,8 @@ impl LspServerProcess {
                 .as_ref()
                 .map(|p| p.resolve_provider.unwrap_or(false))
                 .unwrap_or(false)
-        }) {
+        }) || !self.completion_items.contains(&completion_item)
+        {
             return Ok(());
         }
         self.send_request::<lsp_request!("completionItem/resolve")>(params.context, completion_item)
@@ -1418,9 +1740,17 @@ impl LspServerProcess {
                 content,
             } => self.text_document_did_change(file_path, version, content),
             FromEditor::TextDocumentDidSave { file_path } => self.text_document_did_save(file_path),
+            FromEditor::TextDocumentDidClose { file_path } => {
+                self.text_document_did_close(file_path)
+            }
             FromEditor::TextDocumentSignatureHelp(params) => {
                 self.text_document_signature_help(params)
             }
+            FromEditor::TextDocumentDocumentHighlight(params) => {
+                self.text_document_document_highlight(params)
+            }
+            FromEditor::PrepareHierarchy { params, kind } => self.prepare_hierarchy(params, kind),
+            FromEditor::HierarchyChildren { kind, item } => self.hierarchy_children(kind, item),
             FromEditor::WorkspaceDidRenameFiles { old, new } => {
                 self.workspace_did_rename_files(old, new)
             }
@@ -1438,10 +1768,7 @@ impl LspServerProcess {
     }
 
     fn lsp_command(&self) -> String {
-        self.language
-            .lsp_process_command()
-            .map(|command| command.to_string())
-            .unwrap_or_default()
+        self.transport.to_string()
     }
 }
 
@@ -1489,7 +1816,7 @@ impl ErrorTracker {
     fn handle_error(
         &mut self,
         error: anyhow::Error,
-        stderr_reader: &mut BufReader<process::ChildStderr>,
+        stderr_reader: &mut BufReader<Box<dyn Read + Send>>,
         sender: &Sender<LspServerProcessMessage>,
     ) -> bool {
         let mut stderr = String::new();
@@ -1534,6 +1861,7 @@ impl ErrorTracker {
 #[cfg(test)]
 mod test_lsp_server_process {
     use super::*;
+    use shared::process_command::ProcessCommand;
     use std::process::Command;
     use std::sync::mpsc;
 
@@ -1556,11 +1884,18 @@ mod test_lsp_server_process {
 
         let lsp_process = LspServerProcess {
             language: Language::default(),
-            stdin,
-            stdout: Some(stdout),
-            stderr: Some(stderr),
+            name: "sh".to_string(),
+            transport: Transport::Stdio(ProcessCommand::new("sh", &[])),
+            initialization_options: None,
+            path_translation: PathTranslation::default(),
+            stdin: Box::new(stdin),
+            stdout: Some(Box::new(stdout)),
+            stderr: Some(Box::new(stderr)),
             server_capabilities: None,
-            current_working_directory: std::env::current_dir()?.try_into()?,
+            root_directory: std::env::current_dir()?.try_into()?,
+            completion_items: Vec::new(),
+            progress: IndexMap::new(),
+            logs: Logs::default(),
             next_request_id: 0,
             pending_response_requests: HashMap::new(),
             app_message_sender: app_sender.clone(),
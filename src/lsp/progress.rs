@@ -0,0 +1,72 @@
+//! The progress that LSP servers report while they are busy, for example indexing or building,
+//! refer https://microsoft.github.io/language-server-protocol/specifications/lsp/3.17/specification/#workDoneProgress
+
+use lsp_types::{WorkDoneProgressBegin, WorkDoneProgressReport};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Progress {
+    title: String,
+    message: Option<String>,
+    percentage: Option<u32>,
+}
+
+impl From<WorkDoneProgressBegin> for Progress {
+    fn from(value: WorkDoneProgressBegin) -> Self {
+        Progress {
+            title: value.title,
+            message: value.message,
+            percentage: value.percentage,
+        }
+    }
+}
+
+impl Progress {
+    /// The message and the percentage that are not reported are kept.
+    pub(crate) fn report(&mut self, report: WorkDoneProgressReport) {
+        self.message = report.message.or(self.message.take());
+        self.percentage = report.percentage.or(self.percentage);
+    }
+
+    /// For example `Indexing 42%: 120/286 (serde)`
+    pub(crate) fn display(&self) -> String {
+        format!(
+            "{}{}{}",
+            self.title,
+            self.percentage
+                .map(|percentage| format!(" {percentage}%"))
+                .unwrap_or_default(),
+            self.message
+                .as_ref()
+                .map(|message| format!(": {message}"))
+                .unwrap_or_default()
+        )
+    }
+}
+
+#[cfg(test)]
+mod test_progress {
+    use super::*;
+
+    #[test]
+    fn report_progress() {
+        let mut progress = Progress::from(WorkDoneProgressBegin {
+            title: "Indexing".to_string(),
+            cancellable: None,
+            message: None,
+            percentage: Some(0),
+        });
+        assert_eq!(progress.display(), "Indexing 0%");
+        progress.report(WorkDoneProgressReport {
+            cancellable: None,
+            message: Some("120/286 (serde)".to_string()),
+            percentage: Some(42),
+        });
+        assert_eq!(progress.display(), "Indexing 42%: 120/286 (serde)");
+        progress.report(WorkDoneProgressReport {
+            cancellable: None,
+            message: None,
+            percentage: Some(43),
+        });
+        assert_eq!(progress.display(), "Indexing 43%: 120/286 (serde)");
+    }
+}
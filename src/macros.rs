@@ -0,0 +1,206 @@
+//! Macros are sequences of keys, which are stored either in registers named by a character,
+//! or by a name in `macros.json` of the config directory for reuse across sessions.
+//!
+//! A macro is edited as text, where the keys are separated by whitespaces and written
+//! in the same notation as the keymaps, for example `i h e l l o space shift+W esc`.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use event::KeyEvent;
+use itertools::Itertools;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum MacroName {
+    Register(char),
+    Named(String),
+}
+
+impl MacroName {
+    /// Registers are written as `@` followed by their character, for example `@q`.
+    pub(crate) fn parse(text: &str) -> anyhow::Result<MacroName> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Err(anyhow::anyhow!("The name of a macro cannot be empty"));
+        }
+        Ok(match text.strip_prefix('@') {
+            Some(register) => match register.chars().collect_vec()[..] {
+                [register] => MacroName::Register(register),
+                _ => return Err(anyhow::anyhow!("Invalid macro register: {text:?}")),
+            },
+            None => MacroName::Named(text.to_string()),
+        })
+    }
+
+    pub(crate) fn display(&self) -> String {
+        match self {
+            MacroName::Register(register) => format!("@{register}"),
+            MacroName::Named(name) => name.clone(),
+        }
+    }
+}
+
+/// Parses a macro name optionally followed by how many times it is run, for example `@q 3`.
+pub(crate) fn parse_run(text: &str) -> anyhow::Result<(MacroName, usize)> {
+    let text = text.trim();
+    if let Some((name, repeat)) = text.rsplit_once(char::is_whitespace) {
+        if let Ok(repeat) = repeat.parse::<usize>() {
+            if repeat == 0 {
+                return Err(anyhow::anyhow!("A macro must run at least once"));
+            }
+            return Ok((MacroName::parse(name)?, repeat));
+        }
+    }
+    Ok((MacroName::parse(text)?, 1))
+}
+
+pub(crate) fn to_text(keys: &[KeyEvent]) -> String {
+    keys.iter().map(KeyEvent::display).join(" ")
+}
+
+pub(crate) fn parse_text(text: &str) -> anyhow::Result<Vec<KeyEvent>> {
+    text.split_whitespace()
+        .map(|key| {
+            event::parse_key_event(key)
+                .map_err(|error| anyhow::anyhow!("Invalid key {key:?} in macro ({error})"))
+        })
+        .collect()
+}
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Macros {
+    registers: BTreeMap<char, Vec<KeyEvent>>,
+    named: BTreeMap<String, Vec<KeyEvent>>,
+    /// The file where the named macros are saved, which is `None` in tests
+    path: Option<PathBuf>,
+}
+
+impl Macros {
+    pub(crate) fn file_path() -> PathBuf {
+        grammar::config_dir().join("macros.json")
+    }
+
+    /// Loads the named macros of `path`, which is created once a named macro is saved.
+    pub(crate) fn load(path: PathBuf) -> anyhow::Result<Macros> {
+        let named = if path.exists() {
+            let content = std::fs::read_to_string(&path)?;
+            let named: BTreeMap<String, String> =
+                serde_json5::from_str(&content).map_err(|error| {
+                    anyhow::anyhow!("Invalid macros in {}: {error}", path.display())
+                })?;
+            named
+                .into_iter()
+                .map(|(name, text)| Ok((name, parse_text(&text)?)))
+                .collect::<anyhow::Result<_>>()?
+        } else {
+            BTreeMap::new()
+        };
+        Ok(Macros {
+            registers: BTreeMap::new(),
+            named,
+            path: Some(path),
+        })
+    }
+
+    pub(crate) fn get(&self, name: &MacroName) -> Option<&[KeyEvent]> {
+        match name {
+            MacroName::Register(register) => self.registers.get(register),
+            MacroName::Named(name) => self.named.get(name),
+        }
+        .map(|keys| keys.as_slice())
+    }
+
+    /// Named macros are saved to the file immediately.
+    pub(crate) fn set(&mut self, name: MacroName, keys: Vec<KeyEvent>) -> anyhow::Result<()> {
+        match name {
+            MacroName::Register(register) => {
+                self.registers.insert(register, keys);
+                Ok(())
+            }
+            MacroName::Named(name) => {
+                self.named.insert(name, keys);
+                match &self.path {
+                    Some(path) => self.save(path),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+
+    /// The registers come before the named macros.
+    pub(crate) fn names(&self) -> Vec<MacroName> {
+        self.registers
+            .keys()
+            .map(|register| MacroName::Register(*register))
+            .chain(self.named.keys().cloned().map(MacroName::Named))
+            .collect_vec()
+    }
+
+    fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let named: BTreeMap<_, _> = self
+            .named
+            .iter()
+            .map(|(name, keys)| (name, to_text(keys)))
+            .collect();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(&named)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test_macros {
+    use super::*;
+
+    #[test]
+    fn text_representation() {
+        let keys = parse_text("i h  i\nspace shift+W ctrl+alt+x esc").unwrap();
+        assert_eq!(to_text(&keys), "i h i space shift+W ctrl+alt+x esc");
+        assert_eq!(parse_text(&to_text(&keys)).unwrap(), keys);
+        assert!(parse_text("i foo").is_err());
+    }
+
+    #[test]
+    fn parse_names() {
+        assert_eq!(MacroName::parse("@q").unwrap(), MacroName::Register('q'));
+        assert_eq!(
+            MacroName::parse(" wrap ").unwrap(),
+            MacroName::Named("wrap".to_string())
+        );
+        assert!(MacroName::parse("@qq").is_err());
+        assert!(MacroName::parse("").is_err());
+    }
+
+    #[test]
+    fn parse_runs() {
+        assert_eq!(parse_run("@q").unwrap(), (MacroName::Register('q'), 1));
+        assert_eq!(parse_run("@q 3").unwrap(), (MacroName::Register('q'), 3));
+        assert_eq!(
+            parse_run("wrap lines 2").unwrap(),
+            (MacroName::Named("wrap lines".to_string()), 2)
+        );
+        assert!(parse_run("@q 0").is_err());
+    }
+
+    #[test]
+    fn save_and_load_named_macros() -> anyhow::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let path = temp_dir.path().join("macros.json");
+        let mut macros = Macros::load(path.clone())?;
+        let keys = parse_text("i x esc")?;
+        macros.set(MacroName::Named("x".to_string()), keys.clone())?;
+        macros.set(MacroName::Register('q'), keys.clone())?;
+        // Only the named macros are kept across sessions
+        let loaded = Macros::load(path)?;
+        assert_eq!(
+            loaded.get(&MacroName::Named("x".to_string())),
+            Some(keys.as_slice())
+        );
+        assert_eq!(loaded.get(&MacroName::Register('q')), None);
+        Ok(())
+    }
+}
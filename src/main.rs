@@ -1,21 +1,41 @@
+mod bidi;
 mod buffer;
 mod git;
 
 pub(crate) mod char_index_range;
 mod cli;
+mod client;
 mod clipboard;
 pub(crate) mod command;
+mod comment;
+mod compiler_output;
 mod components;
+mod config;
 mod context;
+mod dap;
 mod edit;
+mod editorconfig;
+mod encryption;
+mod file_drop;
+mod file_watcher;
+mod fold;
+mod frecency;
 pub(crate) mod frontend;
 mod grid;
+pub(crate) mod hard_wrap;
+mod increment;
+mod indent_guide;
+mod insert_assist;
 #[cfg(test)]
 mod integration_test;
+mod keymap_config;
 
 mod layout;
 pub(crate) mod list;
 mod lsp;
+mod macros;
+mod markdown;
+mod named_mark;
 mod position;
 
 mod app;
@@ -24,37 +44,51 @@ mod generate_recipes;
 pub(crate) mod history;
 mod non_empty_extensions;
 mod osc52;
+mod outline;
+mod plugin;
 mod quickfix_list;
 #[cfg(test)]
 mod recipes;
+mod recovery;
 mod rectangle;
+mod refactor_log;
+mod reference_rename;
 mod screen;
+mod screen_reader;
 mod selection;
 pub(crate) mod selection_mode;
 pub(crate) mod selection_range;
+mod server;
+mod session;
+mod shell_task;
 pub(crate) mod soft_wrap;
+mod spell_check;
+mod status_line;
 pub(crate) mod style;
 pub(crate) mod surround;
 pub(crate) mod syntax_highlight;
-mod terminal;
+mod task;
 #[cfg(test)]
 mod test_app;
+#[cfg(test)]
+mod test_script;
 pub(crate) mod themes;
+mod timer;
 pub(crate) mod transformation;
 pub(crate) mod ui_tree;
 pub(crate) mod undo_tree;
 mod utils;
+mod workspace_edit_review;
 
 use std::{rc::Rc, sync::Mutex};
 
 use anyhow::Context;
-use frontend::crossterm::Crossterm;
+use frontend::{crossterm::Crossterm, Frontend};
 use log::LevelFilter;
 use shared::canonicalized_path::CanonicalizedPath;
 
-use app::{App, StatusLineComponent};
-
-use crate::app::AppMessage;
+use app::{App, Bell};
+use status_line::StatusLineComponent;
 
 fn main() {
     cli::cli().unwrap();
@@ -66,43 +100,133 @@ pub(crate) struct RunConfig {
     pub(crate) working_directory: Option<CanonicalizedPath>,
 }
 
-pub(crate) fn run(config: RunConfig) -> anyhow::Result<()> {
+/// The status line can be configured with a comma-separated list of segments,
+/// for example `KI_STATUS_LINE=mode,file-path,modified,cursor-position,lsp-status`.
+/// The word count and the diagnostic counts are only shown if they are configured.
+fn status_line_components() -> anyhow::Result<Vec<StatusLineComponent>> {
+    match std::env::var("KI_STATUS_LINE") {
+        Ok(config) => StatusLineComponent::parse_config(&config),
+        Err(_) => Ok([
+            StatusLineComponent::CurrentWorkingDirectory,
+            StatusLineComponent::GitBranch,
+            StatusLineComponent::Mode,
+            StatusLineComponent::MacroRecording,
+            StatusLineComponent::SelectionMode,
+            StatusLineComponent::SelectionCount,
+            StatusLineComponent::CursorPosition,
+            StatusLineComponent::Indentation,
+            StatusLineComponent::LineEnding,
+            StatusLineComponent::LspStatus,
+            StatusLineComponent::BackgroundTasks,
+            StatusLineComponent::LastDispatch,
+        ]
+        .to_vec()),
+    }
+}
+
+/// The bell that is rung on rejected actions can be configured as `KI_BELL=none|visual|audible`.
+fn bell() -> anyhow::Result<Bell> {
+    match std::env::var("KI_BELL") {
+        Ok(config) => Bell::parse_config(&config),
+        Err(_) => Ok(Bell::Visual),
+    }
+}
+
+/// Copied texts can also be copied to the primary selection (the middle-click selection of X11
+/// and Wayland) regardless of the system clipboard, with `KI_PRIMARY_SELECTION=copy|none`.
+fn copy_to_primary_selection() -> anyhow::Result<bool> {
+    match std::env::var("KI_PRIMARY_SELECTION") {
+        Ok(config) => match config.trim() {
+            "copy" => Ok(true),
+            "none" => Ok(false),
+            name => Err(anyhow::anyhow!(
+                "Unknown primary selection config: {name:?}"
+            )),
+        },
+        Err(_) => Ok(false),
+    }
+}
+
+/// Typing an opening bracket or quote wraps the selections in the pair by default,
+/// which can be configured with `KI_WRAP_SELECTION_ON_TYPE=wrap|replace`.
+fn wrap_selection_on_type() -> anyhow::Result<bool> {
+    match std::env::var("KI_WRAP_SELECTION_ON_TYPE") {
+        Ok(config) => match config.trim() {
+            "wrap" => Ok(true),
+            "replace" => Ok(false),
+            name => Err(anyhow::anyhow!(
+                "Unknown wrap selection on type config: {name:?}"
+            )),
+        },
+        Err(_) => Ok(true),
+    }
+}
+
+/// Configured by the config files and the environment variables.
+fn create_app<T: Frontend>(
+    frontend: T,
+    working_directory: Option<CanonicalizedPath>,
+) -> anyhow::Result<App<T>> {
     std::fs::create_dir_all(grammar::cache_dir()).context("Failed to create cache_dir")?;
     simple_logging::log_to_file(grammar::default_log_file(), LevelFilter::Info)?;
     let (sender, receiver) = std::sync::mpsc::channel();
     let syntax_highlighter_sender = syntax_highlight::start_thread(sender.clone());
+    let working_directory = working_directory.unwrap_or(".".try_into()?);
+    let editor_config = config::Config::load(config::Config::paths(working_directory.as_ref()))?;
     let mut app = App::from_channel(
-        Rc::new(Mutex::new(Crossterm::new()?)),
-        config.working_directory.unwrap_or(".".try_into()?),
+        Rc::new(Mutex::new(frontend)),
+        working_directory,
         sender,
         receiver,
-        [
-            StatusLineComponent::CurrentWorkingDirectory,
-            StatusLineComponent::GitBranch,
-            StatusLineComponent::Mode,
-            StatusLineComponent::SelectionMode,
-            StatusLineComponent::LastDispatch,
-        ]
-        .to_vec(),
+        status_line_components()?,
     )?;
     app.set_syntax_highlight_request_sender(syntax_highlighter_sender);
+    app.set_config(editor_config)?;
+    if themes::no_color() {
+        app.set_theme(themes::monochrome());
+    }
+    app.set_screen_reader(screen_reader::ScreenReader::from_env()?);
+    app.set_bell(bell()?);
+    app.set_copy_to_primary_selection(copy_to_primary_selection()?);
+    app.set_wrap_selection_on_type(wrap_selection_on_type()?);
+    app.set_keymap_config(keymap_config::KeymapConfig::load(
+        &keymap_config::KeymapConfig::file_path(),
+    )?);
+    if let Some(plugins) = plugin::Plugins::load(&plugin::Plugins::directory())? {
+        app.set_plugins(plugins)?;
+    }
+    app.set_macros(macros::Macros::load(macros::Macros::file_path())?);
+    app.set_global_marks(named_mark::GlobalMarks::load(
+        named_mark::GlobalMarks::file_path(),
+    )?);
+    app.set_recovery(recovery::Recovery::new(recovery::Recovery::directory()));
+    app.set_session_store(session::SessionStore::new(
+        session::SessionStore::directory(),
+    ));
+    Ok(app)
+}
 
-    let sender = app.sender();
-
-    let crossterm_join_handle = std::thread::spawn(move || loop {
-        if crossterm::event::read()
-            .map_err(|error| anyhow::anyhow!("{:?}", error))
-            .and_then(|event| Ok(sender.send(AppMessage::Event(event.into()))?))
-            .is_err()
-        {
-            break;
-        }
-    });
-
+pub(crate) fn run(config: RunConfig) -> anyhow::Result<()> {
+    let app = create_app(Crossterm::new()?, config.working_directory)?;
     app.run(config.entry_path)
         .map_err(|error| anyhow::anyhow!("screen.run {:?}", error))?;
 
-    crossterm_join_handle.join().unwrap();
-
     Ok(())
 }
+
+/// Runs the editor without a terminal, driven by the clients connected to `socket_path`,
+/// see `server`.
+pub(crate) fn serve(config: RunConfig, socket_path: &std::path::Path) -> anyhow::Result<()> {
+    let clients = server::RemoteClients::default();
+    let mut app = create_app(
+        frontend::remote::Remote::new(clients.clone()),
+        config.working_directory,
+    )?;
+    app.set_remote_clients(clients.clone());
+    server::listen(socket_path, app.sender(), clients)?;
+    let result = app
+        .run(config.entry_path)
+        .map_err(|error| anyhow::anyhow!("screen.run {:?}", error));
+    let _ = std::fs::remove_file(socket_path);
+    result
+}
@@ -0,0 +1,475 @@
+//! Renders markdown (e.g. the documentation of LSP hovers) into plain text with style spans,
+//! where the markup is removed, for example `**foo**` becomes `foo` styled as `markup.strong`.
+//!
+//! Only the subset of markdown that is commonly used by LSP servers and READMEs is supported,
+//! namely headings, fenced code blocks (highlighted by the language of their info string), lists,
+//! block quotes, rules, tables, inline code, emphasis and links.
+
+use itertools::Itertools;
+
+use crate::{
+    grid::{get_string_width, StyleKey},
+    syntax_highlight::HighlighedSpan,
+};
+
+/// The number of characters of a rendered rule (e.g. `---`)
+const RULE_WIDTH: usize = 20;
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct StyledText {
+    pub(crate) content: String,
+    /// Later spans take precedence over earlier spans that overlap with them
+    pub(crate) spans: Vec<HighlighedSpan>,
+}
+
+impl StyledText {
+    fn push(&mut self, text: &str, style_key: Option<StyleKey>) {
+        let start = self.content.len();
+        self.content.push_str(text);
+        if let Some(style_key) = style_key {
+            self.spans.push(HighlighedSpan {
+                byte_range: start..self.content.len(),
+                style_key,
+            })
+        }
+    }
+
+    /// Pushes the text that is pushed by `push`, styled by `style_key` below its own spans.
+    fn push_styled(&mut self, style_key: StyleKey, push: impl FnOnce(&mut StyledText)) {
+        let index = self.spans.len();
+        let start = self.content.len();
+        push(self);
+        self.spans.insert(
+            index,
+            HighlighedSpan {
+                byte_range: start..self.content.len(),
+                style_key,
+            },
+        )
+    }
+
+    /// Appends `other`, whose spans are placed above the spans of `self`.
+    fn append(&mut self, other: StyledText) {
+        let start = self.content.len();
+        self.content.push_str(&other.content);
+        self.spans
+            .extend(other.spans.into_iter().map(|span| HighlighedSpan {
+                byte_range: span.byte_range.start + start..span.byte_range.end + start,
+                ..span
+            }))
+    }
+
+    /// The spans without overlaps and sorted by range (as required by
+    /// `Buffer::update_highlighted_spans`), where each byte keeps the style of the latest span.
+    pub(crate) fn flattened_spans(&self) -> Vec<HighlighedSpan> {
+        let mut style_keys = vec![None; self.content.len()];
+        for span in &self.spans {
+            for style_key in &mut style_keys[span.byte_range.clone()] {
+                *style_key = Some(&span.style_key)
+            }
+        }
+        style_keys
+            .into_iter()
+            .enumerate()
+            .filter_map(|(byte, style_key)| Some((byte..byte + 1, style_key?)))
+            .coalesce(|(previous, previous_key), (current, current_key)| {
+                if previous.end == current.start && previous_key == current_key {
+                    Ok((previous.start..current.end, previous_key))
+                } else {
+                    Err(((previous, previous_key), (current, current_key)))
+                }
+            })
+            .map(|(byte_range, style_key)| HighlighedSpan {
+                byte_range,
+                style_key: style_key.clone(),
+            })
+            .collect()
+    }
+
+    /// The header (the first row) is emphasized, and the columns are padded to the same width.
+    fn push_table(&mut self, alignments: &[Alignment], rows: &[Vec<&str>]) {
+        let rows = rows
+            .iter()
+            .map(|row| {
+                (0..alignments.len())
+                    .map(|column| {
+                        let mut cell = StyledText::default();
+                        cell.push_inline(row.get(column).copied().unwrap_or_default());
+                        cell
+                    })
+                    .collect_vec()
+            })
+            .collect_vec();
+        let widths = (0..alignments.len())
+            .map(|column| {
+                rows.iter()
+                    .map(|row| get_string_width(&row[column].content))
+                    .max()
+                    .unwrap_or_default()
+            })
+            .collect_vec();
+        for (index, row) in rows.into_iter().enumerate() {
+            if index > 0 {
+                self.push("\n", None)
+            }
+            for (column, cell) in row.into_iter().enumerate() {
+                if column > 0 {
+                    self.push(" │ ", Some(syntax("comment")))
+                }
+                let padding = widths[column] - get_string_width(&cell.content);
+                let left = match alignments[column] {
+                    Alignment::Left => 0,
+                    Alignment::Center => padding / 2,
+                    Alignment::Right => padding,
+                };
+                self.push(&" ".repeat(left), None);
+                if index == 0 {
+                    self.push_styled(syntax("markup.strong"), |text| text.append(cell))
+                } else {
+                    self.append(cell)
+                }
+                self.push(&" ".repeat(padding - left), None);
+            }
+            if index == 0 {
+                let separator = widths.iter().map(|width| "─".repeat(*width)).join("─┼─");
+                self.push("\n", None);
+                self.push(&separator, Some(syntax("comment")))
+            }
+        }
+    }
+
+    fn push_inline(&mut self, text: &str) {
+        let mut rest = text;
+        while let Some(character) = rest.chars().next() {
+            rest = match self.push_markup(rest) {
+                Some(rest) => rest,
+                None => {
+                    self.push(&rest[..character.len_utf8()], None);
+                    &rest[character.len_utf8()..]
+                }
+            }
+        }
+    }
+
+    /// Returns the text after the markup at the start of `text`,
+    /// or `None` if `text` does not start with any markup.
+    fn push_markup<'a>(&mut self, text: &'a str) -> Option<&'a str> {
+        if let Some(rest) = text.strip_prefix('\\') {
+            let escaped = rest.chars().next().filter(char::is_ascii_punctuation)?;
+            self.push(&rest[..escaped.len_utf8()], None);
+            return Some(&rest[escaped.len_utf8()..]);
+        }
+        if text.starts_with('`') {
+            let ticks = &text[..text.len() - text.trim_start_matches('`').len()];
+            let (code, rest) = text[ticks.len()..].split_once(ticks)?;
+            self.push(code.trim(), Some(syntax("markup.raw")));
+            return Some(rest);
+        }
+        if let Some((label, rest)) = text
+            .strip_prefix('[')
+            .and_then(|text| text.split_once("]("))
+            .and_then(|(label, rest)| Some((label, rest.split_once(')')?.1)))
+        {
+            self.push_styled(syntax("markup.link.label"), |text| text.push_inline(label));
+            return Some(rest);
+        }
+        [
+            ("**", "markup.strong"),
+            ("__", "markup.strong"),
+            ("*", "markup.italic"),
+        ]
+        .into_iter()
+        .find_map(|(delimiter, style_key)| {
+            let (emphasized, rest) = text.strip_prefix(delimiter)?.split_once(delimiter)?;
+            if emphasized.is_empty() || emphasized.starts_with(' ') {
+                return None;
+            }
+            self.push_styled(syntax(style_key), |text| text.push_inline(emphasized));
+            Some(rest)
+        })
+    }
+}
+
+fn syntax(style_key: &str) -> StyleKey {
+    StyleKey::Syntax(style_key.to_string())
+}
+
+/// `highlight` returns the highlight spans of the code of a fenced code block, given the
+/// language of the block (e.g. `rust`), or `None` if the language is not supported,
+/// in which case the block is styled as `markup.raw.block`.
+pub(crate) fn render(
+    markdown: &str,
+    mut highlight: impl FnMut(&str, &str) -> Option<Vec<HighlighedSpan>>,
+) -> StyledText {
+    let mut text = StyledText::default();
+    let mut lines = markdown.lines().peekable();
+    let mut is_first_line = true;
+    while let Some(line) = lines.next() {
+        if !is_first_line {
+            text.push("\n", None)
+        }
+        is_first_line = false;
+        let trimmed = line.trim_start();
+        if let Some(fence) = ["```", "~~~"]
+            .into_iter()
+            .find(|fence| trimmed.starts_with(fence))
+        {
+            let language = trimmed.trim_start_matches(fence).trim();
+            let code = std::iter::from_fn(|| lines.next_if(|line| !line.trim().starts_with(fence)))
+                .collect::<Vec<_>>()
+                .join("\n");
+            // The closing fence
+            lines.next();
+            let start = text.content.len();
+            text.push(&code, None);
+            let spans = Some(language)
+                .filter(|language| !language.is_empty())
+                .and_then(|language| highlight(language, &code))
+                .filter(|spans| !spans.is_empty());
+            match spans {
+                Some(spans) => text
+                    .spans
+                    .extend(spans.into_iter().map(|span| HighlighedSpan {
+                        byte_range: span.byte_range.start + start..span.byte_range.end + start,
+                        ..span
+                    })),
+                None => text.spans.push(HighlighedSpan {
+                    byte_range: start..text.content.len(),
+                    style_key: syntax("markup.raw.block"),
+                }),
+            }
+        } else if let Some(alignments) = trimmed
+            .starts_with('|')
+            .then(|| lines.peek().and_then(|line| parse_table_delimiter(line)))
+            .flatten()
+        {
+            // The delimiter row
+            lines.next();
+            let rows = std::iter::once(line)
+                .chain(std::iter::from_fn(|| {
+                    lines.next_if(|line| line.trim_start().starts_with('|'))
+                }))
+                .map(parse_table_row)
+                .collect_vec();
+            text.push_table(&alignments, &rows)
+        } else if let Some((level, heading)) = parse_heading(trimmed) {
+            text.push_styled(syntax(&format!("markup.heading.{level}")), |text| {
+                text.push_inline(heading)
+            })
+        } else if is_rule(trimmed) {
+            text.push(&"─".repeat(RULE_WIDTH), Some(syntax("comment")))
+        } else if let Some(quote) = trimmed.strip_prefix('>') {
+            text.push_styled(syntax("markup.quote"), |text| {
+                text.push("│ ", None);
+                text.push_inline(quote.trim_start())
+            })
+        } else if let Some((marker, item)) = parse_list_item(trimmed) {
+            text.push(&line[..line.len() - trimmed.len()], None);
+            text.push(&marker, Some(syntax("markup.list")));
+            text.push(" ", None);
+            text.push_inline(item)
+        } else {
+            text.push_inline(line)
+        }
+    }
+    text
+}
+
+/// Returns the level and the text of an ATX heading, for example `(2, "Foo")` for `## Foo`.
+fn parse_heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.len() - line.trim_start_matches('#').len();
+    let heading = line[level..].strip_prefix(' ')?;
+    (1..=6).contains(&level).then(|| (level, heading.trim()))
+}
+
+fn is_rule(line: &str) -> bool {
+    let line = line.trim_end();
+    line.len() >= 3
+        && ['-', '*', '_']
+            .into_iter()
+            .any(|character| line.chars().all(|c| c == character))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Alignment {
+    Left,
+    Center,
+    Right,
+}
+
+/// The cells of a row of a table, for example `["a", "b"]` for `| a | b |`.
+fn parse_table_row(line: &str) -> Vec<&str> {
+    let line = line.trim();
+    let line = line.strip_prefix('|').unwrap_or(line);
+    let line = line.strip_suffix('|').unwrap_or(line);
+    line.split('|').map(str::trim).collect()
+}
+
+/// Returns the alignments of the columns, if `line` is the delimiter row of a table,
+/// for example `| :-- | :-: | --: |`.
+fn parse_table_delimiter(line: &str) -> Option<Vec<Alignment>> {
+    if !line.trim_start().starts_with('|') {
+        return None;
+    }
+    parse_table_row(line)
+        .into_iter()
+        .map(|cell| {
+            let dashes = cell.trim_start_matches(':').trim_end_matches(':');
+            if dashes.is_empty() || !dashes.chars().all(|c| c == '-') {
+                return None;
+            }
+            Some(match (cell.starts_with(':'), cell.ends_with(':')) {
+                (true, true) => Alignment::Center,
+                (false, true) => Alignment::Right,
+                _ => Alignment::Left,
+            })
+        })
+        .collect()
+}
+
+/// Unordered markers are rendered as bullets, while ordered markers (e.g. `1.`) are kept.
+fn parse_list_item(line: &str) -> Option<(String, &str)> {
+    if let Some(item) = ["- ", "* ", "+ "]
+        .into_iter()
+        .find_map(|marker| line.strip_prefix(marker))
+    {
+        return Some(("•".to_string(), item));
+    }
+    let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let item = line[digits..]
+        .strip_prefix(". ")
+        .or_else(|| line[digits..].strip_prefix(") "))?;
+    (digits > 0).then(|| (line[..digits + 1].to_string(), item))
+}
+
+#[cfg(test)]
+mod test_markdown {
+    use super::*;
+
+    fn styled(text: &StyledText) -> Vec<(&str, String)> {
+        text.spans
+            .iter()
+            .map(|span| {
+                (
+                    &text.content[span.byte_range.clone()],
+                    match &span.style_key {
+                        StyleKey::Syntax(key) => key.clone(),
+                        key => format!("{key:?}"),
+                    },
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn markup_is_removed_and_styled() {
+        let text = render(
+            "# Foo\nUse `bar` **now**, see [docs](https://x.y)\n\n---\n- one\n  2. two\n> quote",
+            |_, _| None,
+        );
+        assert_eq!(
+            text.content,
+            format!(
+                "Foo\nUse bar now, see docs\n\n{}\n• one\n  2. two\n│ quote",
+                "─".repeat(RULE_WIDTH)
+            )
+        );
+        assert_eq!(
+            styled(&text),
+            [
+                ("Foo", "markup.heading.1".to_string()),
+                ("bar", "markup.raw".to_string()),
+                ("now", "markup.strong".to_string()),
+                ("docs", "markup.link.label".to_string()),
+                (&"─".repeat(RULE_WIDTH) as &str, "comment".to_string()),
+                ("•", "markup.list".to_string()),
+                ("2.", "markup.list".to_string()),
+                ("│ quote", "markup.quote".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn nested_spans_take_precedence() {
+        let text = render("**a `b` c** 2 * 3 * 4 snake_case_name", |_, _| None);
+        assert_eq!(text.content, "a b c 2 * 3 * 4 snake_case_name");
+        assert_eq!(
+            styled(&text),
+            [
+                ("a b c", "markup.strong".to_string()),
+                ("b", "markup.raw".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn tables_are_aligned() {
+        let text = render(
+            "| Key | Action |\n| :-: | --: |\n| `a` | Add |\n| b | Backspace |\nafter",
+            |_, _| None,
+        );
+        assert_eq!(
+            text.content,
+            [
+                "Key │    Action",
+                "────┼──────────",
+                " a  │       Add",
+                " b  │ Backspace",
+                "after"
+            ]
+            .join("\n")
+        );
+        assert_eq!(
+            styled(&text)
+                .into_iter()
+                .filter(|(_, key)| key != "comment")
+                .collect_vec(),
+            [
+                ("Key", "markup.strong".to_string()),
+                ("Action", "markup.strong".to_string()),
+                ("a", "markup.raw".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn flattened_spans_do_not_overlap() {
+        let text = render("**a `b` c**", |_, _| None);
+        assert_eq!(
+            text.flattened_spans()
+                .into_iter()
+                .map(|span| (span.byte_range, span.style_key))
+                .collect_vec(),
+            [
+                (0..2, syntax("markup.strong")),
+                (2..3, syntax("markup.raw")),
+                (3..5, syntax("markup.strong")),
+            ]
+        );
+    }
+
+    #[test]
+    fn code_blocks_are_highlighted_by_their_language() {
+        let text = render(
+            "```rust\nfn foo()\n```\ndocs\n```\nplain\n```",
+            |language, code| {
+                assert_eq!((language, code), ("rust", "fn foo()"));
+                Some(
+                    [HighlighedSpan {
+                        byte_range: 0..2,
+                        style_key: syntax("keyword"),
+                    }]
+                    .to_vec(),
+                )
+            },
+        );
+        assert_eq!(text.content, "fn foo()\ndocs\nplain");
+        assert_eq!(
+            styled(&text),
+            [
+                ("fn", "keyword".to_string()),
+                ("plain", "markup.raw.block".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,164 @@
+//! Named marks are positions named by a letter, which follow the edits of their buffers,
+//! unlike the anonymous marks that are toggled on the selections.
+//!
+//! The lowercase marks are local to their buffers, so each buffer can have its own `a`,
+//! whereas each uppercase mark is global: it can be jumped to from any buffer, and it is saved in
+//! `marks.json` of the cache directory for reuse across sessions.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{edit::Edit, selection::CharIndex};
+
+pub(crate) const NAMED_MARKS: &str = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+pub(crate) fn is_global(name: char) -> bool {
+    name.is_ascii_uppercase()
+}
+
+/// The mark is moved by the edits before it, and to the start of the edit that replaces it,
+/// so that marks are never removed by edits.
+pub(crate) fn apply_edit(char_index: CharIndex, edit: &Edit) -> CharIndex {
+    if edit.range.end <= char_index {
+        CharIndex((char_index.0 as isize + edit.chars_offset()).max(0) as usize)
+    } else if edit.range.start < char_index {
+        edit.range.start
+    } else {
+        char_index
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct GlobalMark {
+    pub(crate) path: PathBuf,
+    /// 0-based
+    pub(crate) line: usize,
+    /// 0-based
+    pub(crate) column: usize,
+}
+
+/// The positions of the global marks are only refreshed from their buffers when the marks
+/// are listed, jumped to, or saved, because the buffers are the ones that follow the edits.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct GlobalMarks {
+    marks: BTreeMap<char, GlobalMark>,
+    /// The file where the marks are saved, which is `None` in tests
+    path: Option<PathBuf>,
+}
+
+impl GlobalMarks {
+    pub(crate) fn file_path() -> PathBuf {
+        grammar::cache_dir().join("marks.json")
+    }
+
+    /// Loads the marks of `path`, which is created once a global mark is set.
+    pub(crate) fn load(path: PathBuf) -> anyhow::Result<GlobalMarks> {
+        let marks = if path.exists() {
+            serde_json::from_str(&std::fs::read_to_string(&path)?)
+                .map_err(|error| anyhow::anyhow!("Invalid marks in {}: {error}", path.display()))?
+        } else {
+            BTreeMap::new()
+        };
+        Ok(GlobalMarks {
+            marks,
+            path: Some(path),
+        })
+    }
+
+    pub(crate) fn get(&self, name: char) -> Option<&GlobalMark> {
+        self.marks.get(&name)
+    }
+
+    pub(crate) fn marks(&self) -> &BTreeMap<char, GlobalMark> {
+        &self.marks
+    }
+
+    /// The marks are saved to the file immediately if they are changed.
+    pub(crate) fn set(&mut self, name: char, mark: GlobalMark) -> anyhow::Result<()> {
+        if self.marks.get(&name) == Some(&mark) {
+            return Ok(());
+        }
+        self.marks.insert(name, mark);
+        self.save()
+    }
+
+    pub(crate) fn remove(&mut self, name: char) -> anyhow::Result<Option<GlobalMark>> {
+        let removed = self.marks.remove(&name);
+        if removed.is_some() {
+            self.save()?
+        }
+        Ok(removed)
+    }
+
+    /// The marks of `path`
+    pub(crate) fn of_path<'a>(
+        &'a self,
+        path: &'a Path,
+    ) -> impl Iterator<Item = (char, &'a GlobalMark)> + 'a {
+        self.marks
+            .iter()
+            .filter(move |(_, mark)| mark.path == path)
+            .map(|(name, mark)| (*name, mark))
+    }
+
+    fn save(&self) -> anyhow::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(&self.marks)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test_named_mark {
+    use ropey::Rope;
+
+    use super::*;
+
+    fn edit(start: usize, end: usize, new: &str) -> Edit {
+        Edit {
+            range: (CharIndex(start)..CharIndex(end)).into(),
+            new: Rope::from_str(new),
+        }
+    }
+
+    #[test]
+    fn marks_follow_the_edits() {
+        let mark = CharIndex(5);
+        // Before
+        assert_eq!(apply_edit(mark, &edit(0, 2, "abcd")), CharIndex(7));
+        assert_eq!(apply_edit(mark, &edit(1, 4, "")), CharIndex(2));
+        // At
+        assert_eq!(apply_edit(mark, &edit(5, 5, "x")), CharIndex(5));
+        // Replaced
+        assert_eq!(apply_edit(mark, &edit(3, 8, "")), CharIndex(3));
+        // After
+        assert_eq!(apply_edit(mark, &edit(6, 8, "")), CharIndex(5));
+    }
+
+    #[test]
+    fn save_and_load_global_marks() -> anyhow::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let path = temp_dir.path().join("marks.json");
+        let mut marks = GlobalMarks::load(path.clone())?;
+        let mark = GlobalMark {
+            path: PathBuf::from("/home/me/main.rs"),
+            line: 3,
+            column: 4,
+        };
+        marks.set('A', mark.clone())?;
+        marks.set('B', mark.clone())?;
+        marks.remove('B')?;
+        let loaded = GlobalMarks::load(path)?;
+        assert_eq!(loaded.marks(), &BTreeMap::from([('A', mark)]));
+        Ok(())
+    }
+}
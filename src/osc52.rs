@@ -9,14 +9,54 @@ impl ClipboardContext {
     }
 
     pub fn set_contents(&self, content: &str) -> io::Result<()> {
-        let base64_content = general_purpose::STANDARD.encode(content);
-        let osc52 = format!("\x1b]52;c;{}\x07", base64_content);
-        io::stdout().write_all(osc52.as_bytes())?;
+        self.set(Target::Clipboard, content)
+    }
+
+    pub fn set_primary_selection_contents(&self, content: &str) -> io::Result<()> {
+        self.set(Target::PrimarySelection, content)
+    }
+
+    fn set(&self, target: Target, content: &str) -> io::Result<()> {
+        io::stdout().write_all(sequence(target, content).as_bytes())?;
         io::stdout().flush()?;
         Ok(())
     }
 }
 
+#[derive(Clone, Copy)]
+enum Target {
+    Clipboard,
+    /// The selection that is pasted by middle-clicking on X11 and Wayland
+    PrimarySelection,
+}
+
+fn sequence(target: Target, content: &str) -> String {
+    let target = match target {
+        Target::Clipboard => 'c',
+        Target::PrimarySelection => 'p',
+    };
+    let base64_content = general_purpose::STANDARD.encode(content);
+    format!("\x1b]52;{};{}\x07", target, base64_content)
+}
+
 pub fn copy_to_clipboard(content: &str) -> io::Result<()> {
     ClipboardContext::new().set_contents(content)
 }
+
+pub fn copy_to_primary_selection(content: &str) -> io::Result<()> {
+    ClipboardContext::new().set_primary_selection_contents(content)
+}
+
+#[cfg(test)]
+mod test_osc52 {
+    use super::*;
+
+    #[test]
+    fn sequence_of_each_target() {
+        assert_eq!(sequence(Target::Clipboard, "hi"), "\x1b]52;c;aGk=\x07");
+        assert_eq!(
+            sequence(Target::PrimarySelection, "hi"),
+            "\x1b]52;p;aGk=\x07"
+        );
+    }
+}
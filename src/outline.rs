@@ -0,0 +1,171 @@
+//! The outline of a markdown buffer consists of its ATX headings (e.g. `## Foo`) as parsed by
+//! tree-sitter, where the subtree of a heading spans its lines until the next heading of the same
+//! or a higher level.
+
+use std::ops::Range;
+
+use itertools::Itertools;
+use tree_sitter_traversal::{traverse, Order};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Heading {
+    /// From 1 (`#`) to 6 (`######`)
+    pub(crate) level: usize,
+    pub(crate) line: usize,
+}
+
+pub(crate) struct Outline {
+    /// Sorted by line
+    headings: Vec<Heading>,
+    len_lines: usize,
+}
+
+impl Outline {
+    pub(crate) fn new(headings: Vec<Heading>, len_lines: usize) -> Outline {
+        Outline {
+            headings,
+            len_lines,
+        }
+    }
+
+    pub(crate) fn from_tree(tree: &tree_sitter::Tree, len_lines: usize) -> Outline {
+        let headings = traverse(tree.root_node().walk(), Order::Pre)
+            .filter(|node| node.kind() == "atx_heading")
+            .filter_map(|node| {
+                let level = node
+                    .child(0)?
+                    .kind()
+                    .strip_prefix("atx_h")?
+                    .strip_suffix("_marker")?
+                    .parse()
+                    .ok()?;
+                Some(Heading {
+                    level,
+                    line: node.start_position().row,
+                })
+            })
+            .collect_vec();
+        Outline::new(headings, len_lines)
+    }
+
+    /// The index of the nearest heading at or above `line`.
+    pub(crate) fn enclosing_heading(&self, line: usize) -> Option<usize> {
+        self.headings
+            .iter()
+            .rposition(|heading| heading.line <= line)
+    }
+
+    /// The line range (the end is exclusive) of the heading and its subheadings.
+    pub(crate) fn subtree(&self, index: usize) -> Range<usize> {
+        let level = self.headings[index].level;
+        let end = self.headings[index + 1..]
+            .iter()
+            .find(|heading| heading.level <= level)
+            .map(|heading| heading.line)
+            .unwrap_or(self.len_lines);
+        self.headings[index].line..end
+    }
+
+    /// The heading and its subheadings.
+    pub(crate) fn subtree_headings(&self, index: usize) -> &[Heading] {
+        let subtree = self.subtree(index);
+        let count = self.headings[index..]
+            .iter()
+            .take_while(|heading| subtree.contains(&heading.line))
+            .count();
+        &self.headings[index..index + count]
+    }
+
+    /// The nearest heading of the same level before or after the heading,
+    /// without crossing their parent heading.
+    pub(crate) fn sibling(&self, index: usize, forward: bool) -> Option<usize> {
+        let level = self.headings[index].level;
+        let is_sibling = |(_, heading): &(usize, &Heading)| heading.level <= level;
+        let sibling = if forward {
+            self.headings
+                .iter()
+                .enumerate()
+                .skip(index + 1)
+                .find(is_sibling)
+        } else {
+            self.headings
+                .iter()
+                .enumerate()
+                .take(index)
+                .rev()
+                .find(is_sibling)
+        };
+        sibling
+            .filter(|(_, heading)| heading.level == level)
+            .map(|(index, _)| index)
+    }
+
+    /// The lines of the sections below each heading until the next heading, so that only the
+    /// headings remain visible when they are folded.
+    pub(crate) fn section_line_ranges(&self) -> Vec<Range<usize>> {
+        self.headings
+            .iter()
+            .map(|heading| heading.line)
+            .chain([self.len_lines])
+            .tuple_windows()
+            .map(|(start, end)| start..end)
+            .filter(|range| range.len() >= 2)
+            .collect_vec()
+    }
+}
+
+/// Swaps two adjacent texts, where `first` is before `second`.
+///
+/// If `second` is not terminated by a newline (i.e. it is at the end of the buffer),
+/// the newline is moved to the end of `first`.
+pub(crate) fn swap_adjacent(first: &str, second: &str) -> String {
+    match second.strip_suffix('\n') {
+        Some(_) => format!("{second}{first}"),
+        None => format!("{second}\n{}", first.strip_suffix('\n').unwrap_or(first)),
+    }
+}
+
+#[cfg(test)]
+mod test_outline {
+    use super::*;
+
+    fn outline() -> Outline {
+        // # A        (line 0)
+        // ## A.1     (line 2)
+        // ### A.1.a  (line 3)
+        // ## A.2     (line 5)
+        // # B        (line 7)
+        Outline::new(
+            [(1, 0), (2, 2), (3, 3), (2, 5), (1, 7)]
+                .into_iter()
+                .map(|(level, line)| Heading { level, line })
+                .collect_vec(),
+            9,
+        )
+    }
+
+    #[test]
+    fn subtrees_and_siblings() {
+        let outline = outline();
+        assert_eq!(outline.enclosing_heading(4), Some(2));
+        assert_eq!(outline.subtree(0), 0..7);
+        assert_eq!(outline.subtree(1), 2..5);
+        assert_eq!(outline.subtree(4), 7..9);
+        assert_eq!(
+            outline.subtree_headings(1),
+            &[Heading { level: 2, line: 2 }, Heading { level: 3, line: 3 }]
+        );
+        assert_eq!(outline.sibling(1, true), Some(3));
+        assert_eq!(outline.sibling(3, false), Some(1));
+        // The siblings of A.2 do not include the headings under B
+        assert_eq!(outline.sibling(3, true), None);
+        assert_eq!(outline.sibling(0, true), Some(4));
+        assert_eq!(outline.section_line_ranges(), [0..2, 3..5, 5..7, 7..9]);
+    }
+
+    #[test]
+    fn swap_adjacent_texts() {
+        assert_eq!(swap_adjacent("a\n", "b\n"), "b\na\n");
+        assert_eq!(swap_adjacent("a\n", "b"), "b\na");
+    }
+}
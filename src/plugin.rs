@@ -0,0 +1,489 @@
+//! Plugins are Lua scripts in `plugins/` of the config directory, which are loaded in the order of
+//! their file names at startup. They extend the editor through the `ki` table:
+//!
+//! ```lua
+//! ki.command("upcase", "Uppercase the buffer", function()
+//!   ki.buffer.set_text(ki.buffer.text():upper())
+//! end)
+//! ki.keymap("normal", "space u", "upcase")
+//!
+//! ki.command("greet", "Greet someone", function()
+//!   ki.pick("Greet", { "world", "someone else" }, function(name)
+//!     ki.info("Greeting", "Hello " .. name)
+//!   end)
+//! end)
+//! ```
+//!
+//! | Function                              | Description                                           |
+//! | ------------------------------------- | ----------------------------------------------------- |
+//! | `ki.command(name, description, fn)`   | Adds a command to the command prompt and palette      |
+//! | `ki.keymap(mode, keys, command)`      | Binds a key sequence of `"normal"` or `"insert"` mode |
+//! | `ki.run(command)`                     | Runs a built-in or plugin command                     |
+//! | `ki.info(title, content)`             | Shows an info                                         |
+//! | `ki.prompt(title, fn)`                | Asks for a text, which `fn` is called with            |
+//! | `ki.pick(title, items, fn)`           | Asks for one of `items`, which `fn` is called with    |
+//! | `ki.buffer.text()`                    | The content of the current buffer                     |
+//! | `ki.buffer.path()`                    | The path of the current buffer, `nil` if it has none  |
+//! | `ki.buffer.selections()`              | The texts of the selections                           |
+//! | `ki.buffer.set_text(text)`            | Replaces the content of the current buffer            |
+//! | `ki.buffer.replace_selections(text)`  | Replaces every selection with `text`                  |
+//!
+//! The buffer is read from a snapshot that is taken before a command or callback is called,
+//! and the actions of a plugin (e.g. `set_text` or `info`) are only applied once it returns,
+//! so a plugin never observes the editor halfway through an update.
+//!
+//! The callbacks of prompts that are cancelled are never called.
+
+use std::{cell::RefCell, collections::HashMap, path::Path, rc::Rc};
+
+use itertools::Itertools;
+use mlua::{Function, Lua, RegistryKey};
+
+/// The current buffer as seen by plugins
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct PluginBuffer {
+    pub(crate) text: String,
+    pub(crate) path: Option<String>,
+    pub(crate) selections: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum PluginAction {
+    SetText(String),
+    ReplaceSelections(String),
+    ShowInfo {
+        title: String,
+        content: String,
+    },
+    /// Answered by `Plugins::answer` with the entered text
+    Prompt {
+        title: String,
+        callback_id: usize,
+    },
+    /// Answered by `Plugins::answer` with the picked item
+    Pick {
+        title: String,
+        items: Vec<String>,
+        callback_id: usize,
+    },
+    RunCommand(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct PluginCommand {
+    pub(crate) name: String,
+    pub(crate) description: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct PluginKeymap {
+    pub(crate) mode: String,
+    pub(crate) keys: String,
+    pub(crate) command: String,
+}
+
+#[derive(Default)]
+struct State {
+    commands: Vec<(PluginCommand, RegistryKey)>,
+    keymaps: Vec<PluginKeymap>,
+    /// The callbacks of the prompts and pickers that are not answered yet
+    callbacks: HashMap<usize, RegistryKey>,
+    next_callback_id: usize,
+    /// `None` while the plugins are loaded
+    buffer: Option<PluginBuffer>,
+    actions: Vec<PluginAction>,
+}
+
+pub(crate) struct Plugins {
+    lua: Lua,
+    state: Rc<RefCell<State>>,
+}
+
+impl Plugins {
+    pub(crate) fn directory() -> std::path::PathBuf {
+        grammar::config_dir().join("plugins")
+    }
+
+    /// Returns `None` if `directory` does not exist.
+    pub(crate) fn load(directory: &Path) -> anyhow::Result<Option<Plugins>> {
+        if !directory.exists() {
+            return Ok(None);
+        }
+        let paths = std::fs::read_dir(directory)?
+            .map(|entry| Ok(entry?.path()))
+            .collect::<anyhow::Result<Vec<_>>>()?
+            .into_iter()
+            .filter(|path| path.extension().is_some_and(|extension| extension == "lua"))
+            .sorted()
+            .collect_vec();
+        let plugins = Plugins::new().map_err(lua_error)?;
+        for path in paths {
+            let source = std::fs::read_to_string(&path)?;
+            plugins
+                .lua
+                .load(&source)
+                .set_name(path.display().to_string())
+                .exec()
+                .map_err(|error| {
+                    anyhow::anyhow!("Failed to load the plugin {}:\n{error}", path.display())
+                })?;
+        }
+        Ok(Some(plugins))
+    }
+
+    fn new() -> mlua::Result<Plugins> {
+        let lua = Lua::new();
+        let state = Rc::new(RefCell::new(State::default()));
+        let ki = lua.create_table()?;
+
+        let command_state = state.clone();
+        ki.set(
+            "command",
+            lua.create_function(
+                move |lua, (name, description, function): (String, String, Function)| {
+                    let key = lua.create_registry_value(function)?;
+                    let mut state = command_state.borrow_mut();
+                    state.commands.retain(|(command, _)| command.name != name);
+                    state
+                        .commands
+                        .push((PluginCommand { name, description }, key));
+                    Ok(())
+                },
+            )?,
+        )?;
+
+        let keymap_state = state.clone();
+        ki.set(
+            "keymap",
+            lua.create_function(move |_, (mode, keys, command): (String, String, String)| {
+                keymap_state.borrow_mut().keymaps.push(PluginKeymap {
+                    mode,
+                    keys,
+                    command,
+                });
+                Ok(())
+            })?,
+        )?;
+
+        let run_state = state.clone();
+        ki.set(
+            "run",
+            lua.create_function(move |_, command: String| {
+                run_state
+                    .borrow_mut()
+                    .actions
+                    .push(PluginAction::RunCommand(command));
+                Ok(())
+            })?,
+        )?;
+
+        let info_state = state.clone();
+        ki.set(
+            "info",
+            lua.create_function(move |_, (title, content): (String, String)| {
+                info_state
+                    .borrow_mut()
+                    .actions
+                    .push(PluginAction::ShowInfo { title, content });
+                Ok(())
+            })?,
+        )?;
+
+        let prompt_state = state.clone();
+        ki.set(
+            "prompt",
+            lua.create_function(move |lua, (title, callback): (String, Function)| {
+                let callback_id = add_callback(lua, &prompt_state, callback)?;
+                prompt_state
+                    .borrow_mut()
+                    .actions
+                    .push(PluginAction::Prompt { title, callback_id });
+                Ok(())
+            })?,
+        )?;
+
+        let pick_state = state.clone();
+        ki.set(
+            "pick",
+            lua.create_function(
+                move |lua, (title, items, callback): (String, Vec<String>, Function)| {
+                    let callback_id = add_callback(lua, &pick_state, callback)?;
+                    pick_state.borrow_mut().actions.push(PluginAction::Pick {
+                        title,
+                        items,
+                        callback_id,
+                    });
+                    Ok(())
+                },
+            )?,
+        )?;
+
+        let buffer = lua.create_table()?;
+        let text_state = state.clone();
+        buffer.set(
+            "text",
+            lua.create_function(move |_, ()| {
+                with_buffer(&text_state, |buffer| buffer.text.clone())
+            })?,
+        )?;
+        let path_state = state.clone();
+        buffer.set(
+            "path",
+            lua.create_function(move |_, ()| {
+                with_buffer(&path_state, |buffer| buffer.path.clone())
+            })?,
+        )?;
+        let selections_state = state.clone();
+        buffer.set(
+            "selections",
+            lua.create_function(move |_, ()| {
+                with_buffer(&selections_state, |buffer| buffer.selections.clone())
+            })?,
+        )?;
+        let set_text_state = state.clone();
+        buffer.set(
+            "set_text",
+            lua.create_function(move |_, text: String| {
+                with_buffer(&set_text_state, |buffer| buffer.text = text.clone())?;
+                set_text_state
+                    .borrow_mut()
+                    .actions
+                    .push(PluginAction::SetText(text));
+                Ok(())
+            })?,
+        )?;
+        let replace_selections_state = state.clone();
+        buffer.set(
+            "replace_selections",
+            lua.create_function(move |_, text: String| {
+                with_buffer(&replace_selections_state, |buffer| {
+                    buffer
+                        .selections
+                        .iter_mut()
+                        .for_each(|selection| *selection = text.clone())
+                })?;
+                replace_selections_state
+                    .borrow_mut()
+                    .actions
+                    .push(PluginAction::ReplaceSelections(text));
+                Ok(())
+            })?,
+        )?;
+        ki.set("buffer", buffer)?;
+
+        lua.globals().set("ki", ki)?;
+        Ok(Plugins { lua, state })
+    }
+
+    /// Sorted by name
+    pub(crate) fn commands(&self) -> Vec<PluginCommand> {
+        self.state
+            .borrow()
+            .commands
+            .iter()
+            .map(|(command, _)| command.clone())
+            .sorted_by(|a, b| a.name.cmp(&b.name))
+            .collect_vec()
+    }
+
+    pub(crate) fn keymaps(&self) -> Vec<PluginKeymap> {
+        self.state.borrow().keymaps.clone()
+    }
+
+    /// Returns `None` if no plugin has a command of `name`.
+    pub(crate) fn run_command(
+        &self,
+        name: &str,
+        buffer: PluginBuffer,
+    ) -> anyhow::Result<Option<Vec<PluginAction>>> {
+        let function = self
+            .state
+            .borrow()
+            .commands
+            .iter()
+            .find(|(command, _)| command.name == name)
+            .map(|(_, key)| self.lua.registry_value::<Function>(key))
+            .transpose()
+            .map_err(lua_error)?;
+        let Some(function) = function else {
+            return Ok(None);
+        };
+        self.call(function, buffer, ()).map(Some)
+    }
+
+    /// Calls the callback of the prompt or picker of `callback_id`, which is then forgotten.
+    pub(crate) fn answer(
+        &self,
+        callback_id: usize,
+        answer: String,
+        buffer: PluginBuffer,
+    ) -> anyhow::Result<Vec<PluginAction>> {
+        let key = self
+            .state
+            .borrow_mut()
+            .callbacks
+            .remove(&callback_id)
+            .ok_or_else(|| anyhow::anyhow!("The prompt of the plugin is already answered"))?;
+        let function = self
+            .lua
+            .registry_value::<Function>(&key)
+            .map_err(lua_error)?;
+        self.lua.remove_registry_value(key).map_err(lua_error)?;
+        self.call(function, buffer, answer)
+    }
+
+    /// The actions queued before an error are discarded.
+    fn call<'lua>(
+        &self,
+        function: Function<'lua>,
+        buffer: PluginBuffer,
+        args: impl mlua::IntoLuaMulti<'lua>,
+    ) -> anyhow::Result<Vec<PluginAction>> {
+        {
+            let mut state = self.state.borrow_mut();
+            state.buffer = Some(buffer);
+            // The actions queued while the plugins are loaded are meaningless
+            state.actions.clear();
+        }
+        let result = function.call::<_, ()>(args);
+        let mut state = self.state.borrow_mut();
+        state.buffer = None;
+        let actions = std::mem::take(&mut state.actions);
+        result.map_err(lua_error)?;
+        Ok(actions)
+    }
+}
+
+fn add_callback(lua: &Lua, state: &Rc<RefCell<State>>, callback: Function) -> mlua::Result<usize> {
+    let key = lua.create_registry_value(callback)?;
+    let mut state = state.borrow_mut();
+    let callback_id = state.next_callback_id;
+    state.next_callback_id += 1;
+    state.callbacks.insert(callback_id, key);
+    Ok(callback_id)
+}
+
+fn with_buffer<T>(
+    state: &Rc<RefCell<State>>,
+    f: impl FnOnce(&mut PluginBuffer) -> T,
+) -> mlua::Result<T> {
+    let mut state = state.borrow_mut();
+    let buffer = state.buffer.as_mut().ok_or_else(|| {
+        mlua::Error::RuntimeError(
+            "The buffer is only available to commands and callbacks".to_string(),
+        )
+    })?;
+    Ok(f(buffer))
+}
+
+/// The message includes the traceback of Lua.
+fn lua_error(error: mlua::Error) -> anyhow::Error {
+    anyhow::anyhow!("{error}")
+}
+
+#[cfg(test)]
+mod test_plugin {
+    use super::*;
+
+    fn load(source: &str) -> anyhow::Result<Plugins> {
+        let directory = tempfile::tempdir()?;
+        std::fs::write(directory.path().join("plugin.lua"), source)?;
+        Ok(Plugins::load(directory.path())?.unwrap())
+    }
+
+    #[test]
+    fn commands_read_and_write_the_buffer() -> anyhow::Result<()> {
+        let plugins = load(
+            r#"
+            ki.command("upcase", "Uppercase the buffer", function()
+              ki.buffer.set_text(ki.buffer.text():upper())
+              ki.info("Upcased", ki.buffer.text() .. " " .. ki.buffer.path())
+            end)
+            ki.keymap("normal", "space u", "upcase")
+            "#,
+        )?;
+        assert_eq!(
+            plugins.commands(),
+            [PluginCommand {
+                name: "upcase".to_string(),
+                description: "Uppercase the buffer".to_string()
+            }]
+        );
+        assert_eq!(
+            plugins.keymaps(),
+            [PluginKeymap {
+                mode: "normal".to_string(),
+                keys: "space u".to_string(),
+                command: "upcase".to_string()
+            }]
+        );
+        let buffer = PluginBuffer {
+            text: "hello".to_string(),
+            path: Some("/a.txt".to_string()),
+            selections: Vec::new(),
+        };
+        assert_eq!(
+            plugins.run_command("upcase", buffer)?,
+            Some(vec![
+                PluginAction::SetText("HELLO".to_string()),
+                PluginAction::ShowInfo {
+                    title: "Upcased".to_string(),
+                    content: "HELLO /a.txt".to_string()
+                }
+            ])
+        );
+        assert_eq!(
+            plugins.run_command("unknown", PluginBuffer::default())?,
+            None
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn picked_items_are_answered_once() -> anyhow::Result<()> {
+        let plugins = load(
+            r#"
+            ki.command("greet", "Greet someone", function()
+              ki.pick("Greet", { "world" }, function(name)
+                ki.buffer.replace_selections("Hello " .. name)
+              end)
+            end)
+            "#,
+        )?;
+        let actions = plugins.run_command("greet", PluginBuffer::default())?;
+        assert_eq!(
+            actions,
+            Some(vec![PluginAction::Pick {
+                title: "Greet".to_string(),
+                items: vec!["world".to_string()],
+                callback_id: 0
+            }])
+        );
+        assert_eq!(
+            plugins.answer(0, "world".to_string(), PluginBuffer::default())?,
+            [PluginAction::ReplaceSelections("Hello world".to_string())]
+        );
+        assert!(plugins
+            .answer(0, "world".to_string(), PluginBuffer::default())
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn errors_discard_the_actions() -> anyhow::Result<()> {
+        let plugins = load(
+            r#"
+            ki.command("fail", "Fail", function()
+              ki.info("Unseen", "")
+              error("failed on purpose")
+            end)
+            "#,
+        )?;
+        let error = plugins
+            .run_command("fail", PluginBuffer::default())
+            .unwrap_err();
+        assert!(error.to_string().contains("failed on purpose"));
+        assert!(load(r#"ki.buffer.text()"#).is_err());
+        Ok(())
+    }
+}
@@ -19,14 +19,38 @@ impl QuickfixListItem {
     fn into_dropdown_item(self: QuickfixListItem, buffers: &[Rc<RefCell<Buffer>>]) -> DropdownItem {
         let location = self.location();
         let Position { line, column } = location.range.start;
+        let line_content = location.read_from_buffers(buffers);
+        let prefix = format!("{}:{}  ", line + 1, column + 1);
+        // Highlight the matched range, so that the user can tell which part of the line matched
+        let highlighted_char_range = line_content.as_ref().and_then(|line_content| {
+            let leading_whitespaces_count = line_content
+                .chars()
+                .take_while(|c| c.is_whitespace())
+                .count();
+            let trimmed_len = line_content.trim().chars().count();
+            let start = column.checked_sub(leading_whitespaces_count)?;
+            let end = if location.range.end.line == line {
+                location
+                    .range
+                    .end
+                    .column
+                    .saturating_sub(leading_whitespaces_count)
+            } else {
+                trimmed_len
+            }
+            .min(trimmed_len);
+            let offset = prefix.chars().count();
+            (start < end).then_some(start + offset..end + offset)
+        });
         DropdownItem::new({
-            let content = location
-                .read_from_buffers(buffers)
+            let content = line_content
                 .unwrap_or_else(|| "[Failed to read file]".to_string())
                 .trim_matches(|c: char| c.is_whitespace())
                 .to_string();
-            format!("{}:{}  {}", line + 1, column + 1, content)
+            format!("{}{}", prefix, content)
         })
+        .set_highlighted_char_range(highlighted_char_range)
+        .set_dimmed(self.excluded)
         .set_info(self.info.clone())
         .set_group({
             let path = self.location().path.clone();
@@ -45,10 +69,12 @@ impl QuickfixListItem {
         let QuickfixListItem {
             location: Location { path, .. },
             info,
+            excluded,
         } = self;
         QuickfixListItem {
             info,
             location: Location { path, range },
+            excluded,
         }
     }
 }
@@ -75,12 +101,16 @@ impl QuickfixList {
             .sorted_by_key(|item| item.location.clone())
             .group_by(|item| item.location.clone())
             .into_iter()
-            .map(|(location, items)| QuickfixListItem {
-                location,
-                info: items
-                    .into_iter()
-                    .flat_map(|item| item.info)
-                    .reduce(Info::join),
+            .map(|(location, items)| {
+                let items = items.collect_vec();
+                QuickfixListItem {
+                    location,
+                    excluded: items.iter().any(|item| item.excluded),
+                    info: items
+                        .into_iter()
+                        .flat_map(|item| item.info)
+                        .reduce(Info::join),
+                }
             })
             .collect_vec();
         dropdown.set_items(
@@ -115,6 +145,19 @@ impl QuickfixList {
         ))
     }
 
+    /// Returns the location of the currently focused item
+    pub(crate) fn current_location(&self) -> Option<Location> {
+        self.dropdown
+            .current_item()?
+            .dispatches
+            .into_vec()
+            .into_iter()
+            .find_map(|dispatch| match dispatch {
+                crate::app::Dispatch::GotoLocation(location) => Some(location),
+                _ => None,
+            })
+    }
+
     pub(crate) fn set_current_item_index(mut self, item_index: usize) -> Self {
         self.dropdown.set_current_item_index(item_index);
         self
@@ -125,6 +168,8 @@ impl QuickfixList {
 pub(crate) struct QuickfixListItem {
     location: Location,
     info: Option<Info>,
+    /// Excluded items are not affected by `Dispatch::ReplaceQuickfixListItems`
+    excluded: bool,
 }
 
 impl PartialOrd for QuickfixListItem {
@@ -144,13 +189,18 @@ impl From<Location> for QuickfixListItem {
         QuickfixListItem {
             location: value,
             info: None,
+            excluded: false,
         }
     }
 }
 
 impl QuickfixListItem {
     pub(crate) fn new(location: Location, info: Option<Info>) -> QuickfixListItem {
-        QuickfixListItem { location, info }
+        QuickfixListItem {
+            location,
+            info,
+            excluded: false,
+        }
     }
 
     pub(crate) fn location(&self) -> &Location {
@@ -161,6 +211,14 @@ impl QuickfixListItem {
         &self.info
     }
 
+    pub(crate) fn excluded(&self) -> bool {
+        self.excluded
+    }
+
+    pub(crate) fn toggle_excluded(&mut self) {
+        self.excluded = !self.excluded
+    }
+
     #[cfg(test)]
     pub(crate) fn set_info(self, info: Option<Info>) -> Self {
         Self { info, ..self }
@@ -268,7 +326,16 @@ impl DiagnosticSeverityRange {
 
 #[cfg(test)]
 mod test_quickfix_list {
-    use crate::{components::suggestive_editor::Info, position::Position};
+    use std::{cell::RefCell, rc::Rc};
+
+    use crate::{
+        buffer::Buffer,
+        components::suggestive_editor::{Decoration, Info},
+        grid::StyleKey,
+        position::Position,
+        selection_range::SelectionRange,
+    };
+    use shared::canonicalized_path::CanonicalizedPath;
 
     use super::{Location, QuickfixList, QuickfixListItem};
     use pretty_assertions::assert_eq;
@@ -281,6 +348,7 @@ mod test_quickfix_list {
                 range: Position { line: 1, column: 2 }..Position { line: 1, column: 3 },
             },
             info: None,
+            excluded: false,
         };
         let bar = QuickfixListItem {
             location: Location {
@@ -288,6 +356,7 @@ mod test_quickfix_list {
                 range: Position { line: 1, column: 1 }..Position { line: 1, column: 2 },
             },
             info: None,
+            excluded: false,
         };
         let spam = QuickfixListItem {
             location: Location {
@@ -295,6 +364,7 @@ mod test_quickfix_list {
                 range: Position { line: 1, column: 1 }..Position { line: 1, column: 2 },
             },
             info: None,
+            excluded: false,
         };
         let quickfix_list = QuickfixList::new(
             "".to_string(),
@@ -304,6 +374,77 @@ mod test_quickfix_list {
         assert_eq!(quickfix_list.items(), vec![spam, foo, bar])
     }
 
+    #[test]
+    fn should_highlight_matched_range() {
+        let buffer = Buffer::new(None, "hello\n  foo bar spam");
+        let path: CanonicalizedPath = "readme.md".try_into().unwrap();
+        let buffer = {
+            let mut buffer = buffer;
+            buffer.set_path(path.clone());
+            Rc::new(RefCell::new(buffer))
+        };
+        let item = QuickfixListItem {
+            location: Location {
+                path,
+                range: Position { line: 1, column: 6 }..Position { line: 1, column: 9 },
+            },
+            info: None,
+            excluded: false,
+        };
+        let quickfix_list = QuickfixList::new("".to_string(), vec![item], vec![buffer]);
+        let render = quickfix_list.render();
+        assert_eq!(render.content.lines().nth(1), Some(" └─ 2:7  foo bar spam"));
+        // "bar" starts at column 13 of the rendered line
+        assert_eq!(
+            render.decorations,
+            vec![Decoration::new(
+                SelectionRange::Position(
+                    Position {
+                        line: 1,
+                        column: 13
+                    }..Position {
+                        line: 1,
+                        column: 16
+                    }
+                ),
+                StyleKey::UiPossibleSelection
+            )]
+        )
+    }
+
+    #[test]
+    fn should_dim_excluded_item() {
+        let path: CanonicalizedPath = "readme.md".try_into().unwrap();
+        let buffer = {
+            let mut buffer = Buffer::new(None, "hello\n  foo bar spam");
+            buffer.set_path(path.clone());
+            Rc::new(RefCell::new(buffer))
+        };
+        let mut item = QuickfixListItem::new(
+            Location {
+                path,
+                range: Position { line: 1, column: 6 }..Position { line: 1, column: 9 },
+            },
+            None,
+        );
+        item.toggle_excluded();
+        let quickfix_list = QuickfixList::new("".to_string(), vec![item], vec![buffer]);
+        let render = quickfix_list.render();
+        // The whole display "2:7  foo bar spam" is dimmed, instead of highlighting "bar"
+        assert_eq!(
+            render.decorations,
+            vec![Decoration::new(
+                SelectionRange::Position(
+                    Position { line: 1, column: 4 }..Position {
+                        line: 1,
+                        column: 21
+                    }
+                ),
+                StyleKey::UiDimmed
+            )]
+        )
+    }
+
     #[test]
     fn should_merge_items_of_same_location() {
         let items = [
@@ -313,6 +454,7 @@ mod test_quickfix_list {
                     range: Position { line: 1, column: 1 }..Position { line: 1, column: 2 },
                 },
                 info: Some(Info::new("Title 1".to_string(), "spongebob".to_string())),
+                excluded: false,
             },
             QuickfixListItem {
                 location: Location {
@@ -320,6 +462,7 @@ mod test_quickfix_list {
                     range: Position { line: 1, column: 1 }..Position { line: 1, column: 2 },
                 },
                 info: Some(Info::new("Title 2".to_string(), "squarepants".to_string())),
+                excluded: false,
             },
         ]
         .to_vec();
@@ -336,7 +479,8 @@ mod test_quickfix_list {
                 info: Some(Info::new(
                     "Title 1".to_string(),
                     ["spongebob", "squarepants"].join("\n==========\n")
-                ))
+                )),
+                excluded: false,
             }]
         )
     }
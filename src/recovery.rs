@@ -0,0 +1,161 @@
+//! The unsaved changes of the buffers are persisted periodically into the recovery directory,
+//! so that they can be restored if the editor is not exited cleanly (e.g. it crashes,
+//! or the terminal is closed).
+//!
+//! Each buffer has one recovery file, which is a JSON of its path and unsaved content,
+//! and is removed once the buffer is saved, its changes are discarded, or the editor is exited.
+//! The recovery files that are left over from previous sessions are offered to be restored
+//! on startup.
+//!
+//! Encrypted buffers are never persisted, because their content would be written in plaintext.
+
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use shared::canonicalized_path::CanonicalizedPath;
+
+/// How often the unsaved changes are persisted
+pub(crate) const RECOVERY_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct RecoveryFile {
+    pub(crate) path: PathBuf,
+    pub(crate) content: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Recovery {
+    directory: PathBuf,
+    /// The hash of the content that is last persisted for each buffer of this session,
+    /// so that unchanged content is not written again
+    persisted: HashMap<CanonicalizedPath, u64>,
+}
+
+impl Recovery {
+    pub(crate) fn directory() -> PathBuf {
+        grammar::cache_dir().join("recovery")
+    }
+
+    pub(crate) fn new(directory: PathBuf) -> Recovery {
+        Recovery {
+            directory,
+            persisted: HashMap::new(),
+        }
+    }
+
+    /// The recovery file is named after the path of the buffer, where the path separators are
+    /// replaced with `%`, for example `%home%user%notes.txt.json`.
+    fn file_path(&self, path: &Path) -> PathBuf {
+        let name = path.display().to_string().replace(['/', '\\', ':'], "%");
+        self.directory.join(format!("{name}.json"))
+    }
+
+    pub(crate) fn persist(
+        &mut self,
+        path: &CanonicalizedPath,
+        content: &str,
+    ) -> anyhow::Result<()> {
+        let mut hasher = DefaultHasher::new();
+        content.hash(&mut hasher);
+        let hash = hasher.finish();
+        if self.persisted.get(path) == Some(&hash) {
+            return Ok(());
+        }
+        std::fs::create_dir_all(&self.directory)?;
+        let file = RecoveryFile {
+            path: path.clone().into_path_buf(),
+            content: content.to_string(),
+        };
+        std::fs::write(self.file_path(path.as_ref()), serde_json::to_string(&file)?)?;
+        self.persisted.insert(path.clone(), hash);
+        Ok(())
+    }
+
+    /// Does nothing if the buffer has no recovery file.
+    pub(crate) fn remove(&mut self, path: &Path) -> anyhow::Result<()> {
+        self.persisted
+            .retain(|persisted, _| persisted.as_ref() != path);
+        let file_path = self.file_path(path);
+        if file_path.exists() {
+            std::fs::remove_file(file_path)?;
+        }
+        Ok(())
+    }
+
+    /// Removes the recovery files of this session, which is called when the editor is exited.
+    pub(crate) fn remove_persisted(&mut self) -> anyhow::Result<()> {
+        for path in self.persisted_paths() {
+            self.remove(path.as_ref())?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn persisted_paths(&self) -> Vec<CanonicalizedPath> {
+        self.persisted.keys().cloned().collect_vec()
+    }
+
+    /// The recovery files that are not written by this session, sorted by path.
+    /// The files that cannot be parsed are skipped.
+    pub(crate) fn leftovers(&self) -> anyhow::Result<Vec<RecoveryFile>> {
+        if !self.directory.exists() {
+            return Ok(Vec::new());
+        }
+        Ok(std::fs::read_dir(&self.directory)?
+            .filter_map(|entry| {
+                let content = std::fs::read_to_string(entry.ok()?.path()).ok()?;
+                serde_json::from_str::<RecoveryFile>(&content).ok()
+            })
+            .filter(|file| {
+                !self
+                    .persisted
+                    .keys()
+                    .any(|persisted| persisted.as_ref() == file.path)
+            })
+            .sorted_by(|a, b| a.path.cmp(&b.path))
+            .collect_vec())
+    }
+}
+
+#[cfg(test)]
+mod test_recovery {
+    use super::*;
+
+    #[test]
+    fn persist_and_remove_recovery_files() -> anyhow::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let notes = temp_dir.path().join("notes.txt");
+        std::fs::write(&notes, "saved")?;
+        let notes: CanonicalizedPath = notes.try_into()?;
+        let directory = temp_dir.path().join("recovery");
+
+        let mut previous_session = Recovery::new(directory.clone());
+        previous_session.persist(&notes, "unsaved")?;
+
+        let mut recovery = Recovery::new(directory.clone());
+        let leftover = RecoveryFile {
+            path: notes.clone().into_path_buf(),
+            content: "unsaved".to_string(),
+        };
+        assert_eq!(recovery.leftovers()?, [leftover]);
+
+        // The recovery files of the current session are not leftovers
+        recovery.persist(&notes, "unsaved again")?;
+        assert_eq!(recovery.persisted_paths(), [notes.clone()]);
+        assert!(recovery.leftovers()?.is_empty());
+        assert_eq!(
+            Recovery::new(directory.clone()).leftovers()?[0].content,
+            "unsaved again"
+        );
+
+        recovery.remove_persisted()?;
+        assert!(recovery.persisted_paths().is_empty());
+        assert!(Recovery::new(directory).leftovers()?.is_empty());
+        Ok(())
+    }
+}
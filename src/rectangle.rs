@@ -389,6 +389,12 @@ impl Rectangle {
         }
     }
 
+    pub(crate) fn contains(&self, position: &Position) -> bool {
+        (self.origin.line..self.origin.line + self.height as usize).contains(&position.line)
+            && (self.origin.column..self.origin.column + self.width as usize)
+                .contains(&position.column)
+    }
+
     /// Split the rectangle horizontally at the given line.
     pub(crate) fn split_horizontally_at(&self, line: usize) -> (Rectangle, Rectangle) {
         let up = Rectangle {
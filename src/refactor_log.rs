@@ -0,0 +1,230 @@
+//! The refactor log records the refactorings applied to the project, such as renames,
+//! code actions and global replaces, so that the locations that they touched
+//! can be reviewed afterwards.
+
+use std::{ops::Range, time::SystemTime};
+
+use itertools::Itertools;
+use shared::canonicalized_path::CanonicalizedPath;
+
+use crate::{
+    components::editor::Info,
+    lsp::{completion::PositionalEdit, workspace_edit::WorkspaceEdit},
+    position::Position,
+    quickfix_list::{Location, QuickfixListItem},
+};
+
+struct Entry {
+    time: SystemTime,
+    description: String,
+    locations: Vec<Location>,
+}
+
+#[derive(Default)]
+pub(crate) struct RefactorLog {
+    entries: Vec<Entry>,
+}
+
+impl RefactorLog {
+    pub(crate) fn record(&mut self, description: String, locations: Vec<Location>) {
+        self.entries.push(Entry {
+            time: SystemTime::now(),
+            description,
+            locations,
+        })
+    }
+
+    /// Records the ranges of the new texts of `workspace_edit`, after it is applied.
+    pub(crate) fn record_workspace_edit(
+        &mut self,
+        description: String,
+        workspace_edit: &WorkspaceEdit,
+    ) {
+        let locations = workspace_edit
+            .edits
+            .iter()
+            .flat_map(|edit| {
+                edited_ranges(&edit.edits)
+                    .into_iter()
+                    .map(|range| Location {
+                        path: edit.path.clone(),
+                        range,
+                    })
+            })
+            .collect_vec();
+        if !locations.is_empty() {
+            self.record(description, locations)
+        }
+    }
+
+    /// Records the start of each file, for refactorings whose ranges are unknown.
+    pub(crate) fn record_files(&mut self, description: String, paths: Vec<CanonicalizedPath>) {
+        let locations = paths
+            .into_iter()
+            .map(|path| Location {
+                path,
+                range: Position::default()..Position::default(),
+            })
+            .collect_vec();
+        if !locations.is_empty() {
+            self.record(description, locations)
+        }
+    }
+
+    /// The most recent refactoring comes first.
+    pub(crate) fn display(&self) -> String {
+        if self.entries.is_empty() {
+            return "No refactorings have been applied yet.".to_string();
+        }
+        self.entries
+            .iter()
+            .rev()
+            .map(|entry| {
+                let locations = entry
+                    .locations
+                    .iter()
+                    .map(|location| format!("  {}", display_location(location)))
+                    .join("\n");
+                format!(
+                    "{}  {}\n{locations}",
+                    format_time(entry.time),
+                    entry.description
+                )
+            })
+            .join("\n\n")
+    }
+
+    /// Every touched location, where the most recent refactoring comes first.
+    pub(crate) fn quickfix_list_items(&self) -> Vec<QuickfixListItem> {
+        self.entries
+            .iter()
+            .rev()
+            .flat_map(|entry| {
+                let info = Info::new(
+                    "Refactoring".to_string(),
+                    format!("{}  {}", format_time(entry.time), entry.description),
+                );
+                entry.locations.iter().map(move |location| {
+                    QuickfixListItem::new(location.clone(), Some(info.clone()))
+                })
+            })
+            .collect_vec()
+    }
+}
+
+fn display_location(location: &Location) -> String {
+    format!(
+        "{}:{}:{}",
+        location.path.try_display_relative(),
+        location.range.start.line + 1,
+        location.range.start.column + 1
+    )
+}
+
+/// Formats the time as `YYYY-MM-DD hh:mm:ss` in UTC.
+fn format_time(time: SystemTime) -> String {
+    let seconds = time
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as i64)
+        .unwrap_or_default();
+    let (year, month, day) = crate::git::blame::civil_from_days(seconds.div_euclid(86400));
+    let seconds_of_day = seconds.rem_euclid(86400);
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02}",
+        seconds_of_day / 3600,
+        seconds_of_day % 3600 / 60,
+        seconds_of_day % 60
+    )
+}
+
+/// Returns the range of the new text of each edit after all of the edits are applied,
+/// given that the edits do not overlap, as required by LSP.
+fn edited_ranges(edits: &[PositionalEdit]) -> Vec<Range<Position>> {
+    let mut line_delta = 0isize;
+    // The original line where the previous edit ended, and how far it shifted the columns after it
+    let mut previous_end: Option<(usize, isize)> = None;
+    edits
+        .iter()
+        .sorted_by_key(|edit| edit.range.start)
+        .map(|edit| {
+            let Range { start, end } = edit.range;
+            let column_delta = match previous_end {
+                Some((line, column_delta)) if line == start.line => column_delta,
+                _ => 0,
+            };
+            let new_start = Position::new(
+                start.line.saturating_add_signed(line_delta),
+                start.column.saturating_add_signed(column_delta),
+            );
+            let new_lines = edit.new_text.matches('\n').count();
+            let last_line_length = edit
+                .new_text
+                .split('\n')
+                .last()
+                .unwrap_or_default()
+                .chars()
+                .count();
+            let new_end = Position::new(
+                new_start.line + new_lines,
+                if new_lines == 0 {
+                    new_start.column + last_line_length
+                } else {
+                    last_line_length
+                },
+            );
+            line_delta += new_lines as isize - (end.line - start.line) as isize;
+            previous_end = Some((end.line, new_end.column as isize - end.column as isize));
+            new_start..new_end
+        })
+        .collect_vec()
+}
+
+#[cfg(test)]
+mod test_refactor_log {
+    use super::*;
+
+    fn edit(start: (usize, usize), end: (usize, usize), new_text: &str) -> PositionalEdit {
+        PositionalEdit {
+            range: Position::new(start.0, start.1)..Position::new(end.0, end.1),
+            new_text: new_text.to_string(),
+        }
+    }
+
+    #[test]
+    fn ranges_after_edits() {
+        // "foo(foo)\nfoo" with each "foo" renamed to "x", given in an unsorted order
+        let edits = [
+            edit((1, 0), (1, 3), "x"),
+            edit((0, 0), (0, 3), "x"),
+            edit((0, 4), (0, 7), "x"),
+        ];
+        assert_eq!(
+            edited_ranges(&edits),
+            [
+                Position::new(0, 0)..Position::new(0, 1),
+                Position::new(0, 2)..Position::new(0, 3),
+                Position::new(1, 0)..Position::new(1, 1),
+            ]
+        );
+        // Inserting lines shifts the edits below them
+        let edits = [
+            edit((0, 2), (0, 2), "a\nbc"),
+            edit((0, 5), (1, 1), ""),
+            edit((3, 0), (3, 0), "d"),
+        ];
+        assert_eq!(
+            edited_ranges(&edits),
+            [
+                Position::new(0, 2)..Position::new(1, 2),
+                Position::new(1, 5)..Position::new(1, 5),
+                Position::new(3, 0)..Position::new(3, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn format_times_in_utc() {
+        let time = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1709249400);
+        assert_eq!(format_time(time), "2024-02-29 23:30:00");
+    }
+}
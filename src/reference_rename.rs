@@ -0,0 +1,76 @@
+//! Updating the references to a path after it is moved in the file explorer.
+//!
+//! The editor only notifies the LSP servers with `workspace/didRenameFiles`, which does not
+//! update the references, so they are searched in the whole project instead,
+//! and previewed as a quickfix list where the unwanted updates can be excluded before they are
+//! applied (see `Dispatch::ReplaceQuickfixListItems`).
+
+use std::path::Path;
+
+use itertools::Itertools;
+
+/// Returns the reference to the old path and its replacement, which are the trailing components
+/// of the paths (without the extension) that differ, joined by `separator`.
+///
+/// For example, moving `src/foo/bar.rs` to `src/baz/bar.rs` with the separator `::` of Rust
+/// replaces `foo::bar` with `baz::bar`, while renaming `src/foo.py` to `src/bar.py` replaces `foo`
+/// with `bar`.
+///
+/// Returns `None` if only the extension is changed.
+pub(crate) fn renamed_reference(
+    from: &Path,
+    to: &Path,
+    separator: &str,
+) -> Option<(String, String)> {
+    let components = |path: &Path| {
+        path.parent()
+            .into_iter()
+            .flat_map(|parent| parent.components())
+            .map(|component| component.as_os_str().to_string_lossy().to_string())
+            .chain(
+                path.file_stem()
+                    .map(|stem| stem.to_string_lossy().to_string()),
+            )
+            .collect_vec()
+    };
+    let from = components(from);
+    let to = components(to);
+    let common = from
+        .iter()
+        .zip(&to)
+        .take_while(|(from, to)| from == to)
+        .count();
+    if common == from.len() {
+        return None;
+    }
+    Some((from[common..].join(separator), to[common..].join(separator)))
+}
+
+#[cfg(test)]
+mod test_reference_rename {
+    use super::*;
+
+    #[test]
+    fn differing_trailing_components() {
+        let reference = |from: &str, to: &str, separator| {
+            renamed_reference(Path::new(from), Path::new(to), separator)
+        };
+        assert_eq!(
+            reference("/p/src/foo/bar.rs", "/p/src/baz/bar.rs", "::"),
+            Some(("foo::bar".to_string(), "baz::bar".to_string()))
+        );
+        assert_eq!(
+            reference("/p/src/foo.py", "/p/src/bar.py", "."),
+            Some(("foo".to_string(), "bar".to_string()))
+        );
+        assert_eq!(
+            reference("/p/src/foo.ts", "/p/lib/foo.ts", "/"),
+            Some(("src/foo".to_string(), "lib/foo".to_string()))
+        );
+        assert_eq!(
+            reference("/p/src/foo.rs", "/p/src/sub/foo.rs", "::"),
+            Some(("foo".to_string(), "sub::foo".to_string()))
+        );
+        assert_eq!(reference("/p/foo.js", "/p/foo.ts", "/"), None);
+    }
+}
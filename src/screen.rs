@@ -1,12 +1,28 @@
+use std::collections::HashSet;
+
 use itertools::Itertools;
 
 use crate::{
     app::Dimension,
     grid::{Grid, PositionedCell},
+    position::Position,
     rectangle::{Border, Rectangle},
     style::Style,
 };
 
+/// The screen is rendered progressively when more than this percentage of its cells are changed,
+/// for example after a resize or a theme change, so that the cursor region is shown sooner
+/// when the terminal is slow (e.g. over SSH).
+const PROGRESSIVE_RENDERING_PERCENTAGE: usize = 50;
+
+/// The first frame of a progressive rendering only contains the rows of the focused window
+/// that are at most this far from the cursor.
+const CURSOR_REGION_RADIUS: usize = 5;
+
+/// The number of rows rendered in each of the following frames, starting from the rows
+/// that are the closest to the cursor.
+const ROWS_PER_FRAME: usize = 10;
+
 #[derive(Default, Clone)]
 pub(crate) struct Screen {
     windows: Vec<Window>,
@@ -14,6 +30,10 @@ pub(crate) struct Screen {
     cursor: Option<crate::components::component::Cursor>,
     memoized_positioned_cells: Option<Vec<PositionedCell>>,
     border_style: Style,
+    /// The positions whose cells are left for the following frames of a progressive rendering
+    unrendered_positions: HashSet<Position>,
+    /// Rendered over the windows and the borders, for example the hover popup
+    floating_window: Option<Window>,
 }
 
 impl Screen {
@@ -29,6 +49,8 @@ impl Screen {
             cursor,
             memoized_positioned_cells: None,
             border_style,
+            unrendered_positions: HashSet::new(),
+            floating_window: None,
         }
     }
     /// This takes a `&mut self` instead of a `&self` because memoization.
@@ -38,6 +60,10 @@ impl Screen {
         if let Some(positioned_cells) = self.memoized_positioned_cells.clone() {
             positioned_cells
         } else {
+            let floating_rectangle = self
+                .floating_window
+                .as_ref()
+                .map(|window| window.rectangle.clone());
             self.memoized_positioned_cells = Some(
                 self.windows
                     .iter()
@@ -47,6 +73,16 @@ impl Screen {
                             .iter()
                             .flat_map(|border| border.to_positioned_cells(self.border_style)),
                     )
+                    .filter(|cell| {
+                        floating_rectangle
+                            .as_ref()
+                            .map_or(true, |rectangle| !rectangle.contains(&cell.position))
+                    })
+                    .chain(
+                        self.floating_window
+                            .iter()
+                            .flat_map(Window::to_positioned_cells),
+                    )
                     // Cells should be sorted reversed by column, so that multi-width character
                     // will not be overridden by blank character in terminal rendering
                     .sorted_by_key(|cell| (cell.position.line, -(cell.position.column as isize)))
@@ -69,10 +105,60 @@ impl Screen {
         let old: indexmap::IndexSet<PositionedCell> =
             old_screen.get_positioned_cells().into_iter().collect();
         new.into_iter()
-            .filter(|cell| !old.contains(cell))
+            .filter(|cell| {
+                !old.contains(cell) || old_screen.unrendered_positions.contains(&cell.position)
+            })
             .collect_vec()
     }
 
+    /// Returns the `cells` to be rendered in this frame, and leaves the rest for the next frames.
+    ///
+    /// `is_continuation` is true if the previous frame left some cells unrendered.
+    pub(crate) fn take_frame(
+        &mut self,
+        cells: Vec<PositionedCell>,
+        is_continuation: bool,
+    ) -> Vec<PositionedCell> {
+        let dimension = self.dimension();
+        let area = dimension.width as usize * dimension.height as usize;
+        if !is_continuation && cells.len() * 100 <= area * PROGRESSIVE_RENDERING_PERCENTAGE {
+            return cells;
+        }
+        let cursor = self
+            .cursor
+            .as_ref()
+            .map(|cursor| *cursor.position())
+            .unwrap_or_default();
+        let (now, later): (Vec<_>, Vec<_>) = if is_continuation {
+            let rows: HashSet<_> = cells
+                .iter()
+                .map(|cell| cell.position.line)
+                .unique()
+                .sorted_by_key(|line| line.abs_diff(cursor.line))
+                .take(ROWS_PER_FRAME)
+                .collect();
+            cells
+                .into_iter()
+                .partition(|cell| rows.contains(&cell.position.line))
+        } else {
+            let focused_window = self
+                .windows
+                .iter()
+                .find(|window| window.rectangle.contains(&cursor));
+            cells.into_iter().partition(|cell| {
+                cell.position.line.abs_diff(cursor.line) <= CURSOR_REGION_RADIUS
+                    && focused_window
+                        .map_or(true, |window| window.rectangle.contains(&cell.position))
+            })
+        };
+        self.unrendered_positions = later.into_iter().map(|cell| cell.position).collect();
+        now
+    }
+
+    pub(crate) fn is_fully_rendered(&self) -> bool {
+        self.unrendered_positions.is_empty()
+    }
+
     #[cfg(test)]
     pub(crate) fn stringify(&mut self) -> String {
         self.get_positioned_cells()
@@ -120,6 +206,13 @@ impl Screen {
         self.windows.push(window);
         self
     }
+
+    pub(crate) fn set_floating_window(self, floating_window: Option<Window>) -> Screen {
+        Screen {
+            floating_window,
+            ..self
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -147,10 +240,14 @@ impl Window {
 
 #[cfg(test)]
 mod test_screen {
+    use itertools::Itertools;
+
     use crate::{
         app::Dimension,
+        components::component::{Cursor, SetCursorStyle},
         grid::{Cell, Grid, PositionedCell},
         position::Position,
+        rectangle::Rectangle,
         screen::{Screen, Window},
     };
 
@@ -203,4 +300,89 @@ mod test_screen {
         .to_vec();
         assert_eq!(actual, expected);
     }
+
+    #[test]
+    fn progressive_rendering_starts_from_the_cursor() {
+        let dimension = Dimension {
+            height: 30,
+            width: 2,
+        };
+        let rectangle = Rectangle {
+            origin: Position::new(0, 0),
+            width: dimension.width,
+            height: dimension.height,
+        };
+        let mut screen = Screen::new(
+            [Window::new(Grid::new(dimension), rectangle)].to_vec(),
+            Vec::new(),
+            Some(Cursor::new(
+                Position::new(20, 0),
+                SetCursorStyle::BlinkingBlock,
+            )),
+            Default::default(),
+        );
+        fn lines(cells: Vec<PositionedCell>) -> Vec<usize> {
+            cells
+                .into_iter()
+                .map(|cell| cell.position.line)
+                .unique()
+                .sorted()
+                .collect_vec()
+        }
+        let cells = screen.get_positioned_cells();
+        assert_eq!(
+            lines(screen.take_frame(cells, false)),
+            (15..=25).collect_vec()
+        );
+        assert!(!screen.is_fully_rendered());
+
+        // The following frames render the rows that are the closest to the cursor first
+        let mut next = screen.clone();
+        let cells = next.diff(&mut screen);
+        assert_eq!(
+            lines(next.take_frame(cells, true)),
+            (9..=14).chain(26..=29).collect_vec()
+        );
+        let mut last = next.clone();
+        let cells = last.diff(&mut next);
+        assert_eq!(lines(last.take_frame(cells, true)), (0..=8).collect_vec());
+        assert!(last.is_fully_rendered());
+    }
+
+    #[test]
+    fn floating_window_hides_the_cells_under_it() {
+        let dimension = Dimension {
+            height: 2,
+            width: 4,
+        };
+        let mut screen = Screen::new(
+            [Window::new(
+                Grid::from_text(dimension, "abcd\nefgh"),
+                Rectangle {
+                    origin: Position::new(0, 0),
+                    width: dimension.width,
+                    height: dimension.height,
+                },
+            )]
+            .to_vec(),
+            Vec::new(),
+            None,
+            Default::default(),
+        )
+        .set_floating_window(Some(Window::new(
+            Grid::from_text(
+                Dimension {
+                    height: 1,
+                    width: 2,
+                },
+                "XY",
+            ),
+            Rectangle {
+                origin: Position::new(1, 1),
+                width: 2,
+                height: 1,
+            },
+        )));
+        assert_eq!(screen.stringify(), "abcd\neXYh");
+    }
 }
@@ -0,0 +1,141 @@
+//! Announces state changes (e.g. mode switches and the line under the cursor) as concise text,
+//! because screen readers cannot make sense of a full-screen terminal UI.
+//!
+//! This is enabled by the `KI_SCREEN_READER` environment variable, which is either:
+//! - `osc`: each announcement is emitted as an OSC 9 notification,
+//!   which terminals forward to the desktop notification (and accessibility) services
+//! - the path of a file (e.g. a named pipe consumed by a screen reader),
+//!   where each announcement is appended as a line
+use std::io::Write;
+
+use itertools::Itertools;
+
+enum Output {
+    Osc,
+    File(std::fs::File),
+}
+
+/// The state that is announced whenever it changes.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub(crate) struct ScreenReaderState {
+    pub(crate) mode: String,
+    pub(crate) title: String,
+    /// 0-based
+    pub(crate) line: usize,
+    pub(crate) line_content: String,
+    pub(crate) diagnostic: Option<String>,
+}
+
+pub(crate) struct ScreenReader {
+    output: Output,
+    previous_state: ScreenReaderState,
+}
+
+impl ScreenReader {
+    pub(crate) fn from_env() -> anyhow::Result<Option<ScreenReader>> {
+        let Ok(destination) = std::env::var("KI_SCREEN_READER") else {
+            return Ok(None);
+        };
+        let output = match destination.as_str() {
+            "" => return Ok(None),
+            "osc" => Output::Osc,
+            path => Output::File(
+                std::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)?,
+            ),
+        };
+        Ok(Some(ScreenReader {
+            output,
+            previous_state: ScreenReaderState::default(),
+        }))
+    }
+
+    pub(crate) fn update(&mut self, state: ScreenReaderState) -> anyhow::Result<()> {
+        let announcements = announcements(&self.previous_state, &state);
+        self.previous_state = state;
+        for announcement in announcements {
+            match &mut self.output {
+                Output::Osc => {
+                    // The terminator of the sequence (BEL) must not appear in its content
+                    let announcement = announcement.replace(['\x07', '\x1b'], "");
+                    let mut stdout = std::io::stdout();
+                    stdout.write_all(format!("\x1b]9;{announcement}\x07").as_bytes())?;
+                    stdout.flush()?;
+                }
+                Output::File(file) => writeln!(file, "{announcement}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn announcements(previous: &ScreenReaderState, current: &ScreenReaderState) -> Vec<String> {
+    let moved = previous.title != current.title || previous.line != current.line;
+    [
+        (previous.title != current.title).then(|| current.title.clone()),
+        (previous.mode != current.mode).then(|| format!("{} mode", current.mode)),
+        moved.then(|| {
+            format!(
+                "Line {}: {}",
+                current.line + 1,
+                current.line_content.trim_end_matches(['\r', '\n'])
+            )
+        }),
+        (previous.diagnostic != current.diagnostic)
+            .then(|| current.diagnostic.clone())
+            .flatten(),
+    ]
+    .into_iter()
+    .flatten()
+    .collect_vec()
+}
+
+#[cfg(test)]
+mod test_screen_reader {
+    use super::{announcements, ScreenReaderState};
+
+    fn state() -> ScreenReaderState {
+        ScreenReaderState {
+            mode: "MOVE".to_string(),
+            title: "main.rs".to_string(),
+            line: 0,
+            line_content: "fn main() {\n".to_string(),
+            diagnostic: None,
+        }
+    }
+
+    #[test]
+    fn nothing_is_announced_when_unchanged() {
+        assert!(announcements(&state(), &state()).is_empty())
+    }
+
+    #[test]
+    fn announce_mode_line_and_diagnostic_changes() {
+        let current = ScreenReaderState {
+            mode: "INSERT".to_string(),
+            line: 1,
+            line_content: "    let x = 1\n".to_string(),
+            diagnostic: Some("Error: expected `;`".to_string()),
+            ..state()
+        };
+        assert_eq!(
+            announcements(&state(), &current),
+            [
+                "INSERT mode",
+                "Line 2:     let x = 1",
+                "Error: expected `;`"
+            ]
+        )
+    }
+
+    #[test]
+    fn editing_the_current_line_is_not_announced() {
+        let current = ScreenReaderState {
+            line_content: "fn main() {}\n".to_string(),
+            ..state()
+        };
+        assert!(announcements(&state(), &current).is_empty())
+    }
+}
@@ -13,7 +13,7 @@ use crate::{
     non_empty_extensions::{NonEmptyTryCollectOption, NonEmptyTryCollectResult},
     position::Position,
     quickfix_list::DiagnosticSeverityRange,
-    selection_mode::{self, ApplyMovementResult, SelectionModeParams},
+    selection_mode::{self, text_object::TextObjectKind, ApplyMovementResult, SelectionModeParams},
 };
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -282,6 +282,13 @@ impl SelectionSet {
         Self { mode, ..self }
     }
 
+    pub(crate) fn set_cursor_index(self, cursor_index: usize) -> SelectionSet {
+        Self {
+            cursor_index: cursor_index.min(self.selections.len() - 1),
+            ..self
+        }
+    }
+
     pub(crate) fn secondary_selections(&self) -> Vec<&Selection> {
         self.selections
             .iter()
@@ -401,6 +408,7 @@ pub(crate) enum SelectionMode {
     // Syntax-tree
     SyntaxNode,
     SyntaxNodeFine,
+    TextObject(TextObjectKind),
 
     // LSP
     Diagnostic(DiagnosticSeverityRange),
@@ -432,6 +440,7 @@ impl SelectionMode {
             SelectionMode::Custom => "CUSTOM".to_string(),
             SelectionMode::SyntaxNode => "SYNTAX NODE".to_string(),
             SelectionMode::SyntaxNodeFine => "FINE SYNTAX NODE".to_string(),
+            SelectionMode::TextObject(kind) => kind.display(),
             SelectionMode::Find { search } => {
                 format!("{} {:?}", search.mode.display(), search.search)
             }
@@ -485,6 +494,9 @@ impl SelectionMode {
             },
             SelectionMode::SyntaxNode => Box::new(selection_mode::SyntaxNode { coarse: true }),
             SelectionMode::SyntaxNodeFine => Box::new(selection_mode::SyntaxNode { coarse: false }),
+            SelectionMode::TextObject(kind) => {
+                Box::new(selection_mode::TextObject::new(buffer, kind)?)
+            }
             SelectionMode::Diagnostic(severity) => {
                 Box::new(selection_mode::Diagnostic::new(*severity, params))
             }
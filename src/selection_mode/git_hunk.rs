@@ -1,4 +1,7 @@
-use crate::{buffer::Buffer, git::GitOperation};
+use crate::{
+    buffer::Buffer,
+    git::{diff_provider::diff_provider, hunk::Hunk},
+};
 use itertools::Itertools;
 
 use super::{ByteRange, SelectionMode};
@@ -15,9 +18,15 @@ impl GitHunk {
         let Some(path) = buffer.path() else {
             return Ok(GitHunk { ranges: Vec::new() });
         };
-        // TODO: pass in current working directory
-        let binding = path.file_diff(diff_mode, &".".try_into()?)?;
-        let hunks = binding.hunks();
+        let Some(provider) = diff_provider(&path, diff_mode) else {
+            return Ok(GitHunk { ranges: Vec::new() });
+        };
+        // The buffer content is used instead of the file content,
+        // so that unsaved changes are included
+        let hunks = match provider.base_content(&path) {
+            Ok(base_content) => Hunk::get(&base_content, &buffer.content()),
+            Err(_) => [Hunk::one_insert("[This file is untracked or renamed]")].to_vec(),
+        };
         let ranges = hunks
             .iter()
             .filter_map(|hunk| {
@@ -6,6 +6,7 @@ pub(crate) mod git_hunk;
 pub(crate) mod mark;
 pub(crate) mod naming_convention_agnostic;
 pub(crate) mod syntax_token;
+pub(crate) mod text_object;
 
 pub(crate) mod top_node;
 
@@ -32,6 +33,7 @@ use position_pair::ParsedChar;
 use std::ops::Range;
 pub(crate) use syntax_node::SyntaxNode;
 pub(crate) use syntax_token::SyntaxToken;
+pub(crate) use text_object::TextObject;
 pub(crate) use token::Token;
 pub(crate) use top_node::TopNode;
 pub(crate) use word::Word;
@@ -0,0 +1,170 @@
+use itertools::Itertools;
+
+use crate::{buffer::Buffer, components::editor::SurroundKind};
+
+use super::{ByteRange, SelectionMode};
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub(crate) enum TextObjectKind {
+    Function(SurroundKind),
+    Class(SurroundKind),
+    Argument,
+    Comment,
+}
+
+impl TextObjectKind {
+    /// Refer https://github.com/nvim-treesitter/nvim-treesitter-textobjects#built-in-textobjects
+    fn capture_name(&self) -> &'static str {
+        match self {
+            TextObjectKind::Function(SurroundKind::Inside) => "function.inner",
+            TextObjectKind::Function(SurroundKind::Around) => "function.outer",
+            TextObjectKind::Class(SurroundKind::Inside) => "class.inner",
+            TextObjectKind::Class(SurroundKind::Around) => "class.outer",
+            TextObjectKind::Argument => "parameter.inner",
+            TextObjectKind::Comment => "comment.outer",
+        }
+    }
+
+    pub(crate) fn display(&self) -> String {
+        match self {
+            TextObjectKind::Function(kind) => format!("FUNCTION ({})", kind).to_uppercase(),
+            TextObjectKind::Class(kind) => format!("CLASS ({})", kind).to_uppercase(),
+            TextObjectKind::Argument => "ARGUMENT".to_string(),
+            TextObjectKind::Comment => "COMMENT".to_string(),
+        }
+    }
+}
+
+pub(crate) struct TextObject {
+    ranges: Vec<ByteRange>,
+}
+
+impl TextObject {
+    pub(crate) fn new(buffer: &Buffer, kind: &TextObjectKind) -> anyhow::Result<Self> {
+        let tree = buffer.tree().ok_or(anyhow::anyhow!(
+            "TextObject::new: cannot find Treesitter language"
+        ))?;
+        let Some(query) = buffer
+            .language()
+            .and_then(|language| language.textobjects_query())
+        else {
+            return Err(anyhow::anyhow!(
+                "No text object queries are defined for the language of this file."
+            ));
+        };
+        let query = tree_sitter::Query::new(&tree.language(), query)?;
+        let Some(capture_index) = query.capture_index_for_name(kind.capture_name()) else {
+            return Ok(Self { ranges: Vec::new() });
+        };
+        let content = buffer.rope().to_string();
+        let inside = matches!(
+            kind,
+            TextObjectKind::Function(SurroundKind::Inside)
+                | TextObjectKind::Class(SurroundKind::Inside)
+        );
+        let ranges = tree_sitter::QueryCursor::new()
+            .matches(&query, tree.root_node(), content.as_bytes())
+            .flat_map(|query_match| {
+                query_match
+                    .captures
+                    .iter()
+                    .filter(|capture| capture.index == capture_index)
+                    .map(|capture| capture.node)
+                    .collect_vec()
+            })
+            .filter_map(|node| {
+                if inside {
+                    inside_range(node, &content)
+                } else {
+                    Some(node.byte_range())
+                }
+            })
+            .sorted_by_key(|range| (range.start, range.end))
+            .dedup()
+            .map(ByteRange::new)
+            .collect_vec();
+        Ok(Self { ranges })
+    }
+}
+
+/// Excludes the brackets of bodies such as `{ ... }`, and the whitespaces around the content.
+///
+/// Returns None if the body is empty.
+fn inside_range(node: tree_sitter::Node, content: &str) -> Option<std::ops::Range<usize>> {
+    let range = match (
+        node.child(0),
+        node.child(node.child_count().saturating_sub(1)),
+    ) {
+        (Some(first), Some(last))
+            if node.child_count() >= 2
+                && ["{", "(", "["].contains(&first.kind())
+                && ["}", ")", "]"].contains(&last.kind()) =>
+        {
+            first.end_byte()..last.start_byte()
+        }
+        _ => node.byte_range(),
+    };
+    let text = content.get(range.clone())?;
+    let start = range.start + (text.len() - text.trim_start().len());
+    let end = range.end - (text.len() - text.trim_end().len());
+    (start < end).then_some(start..end)
+}
+
+impl SelectionMode for TextObject {
+    fn iter<'a>(
+        &'a self,
+        _params: super::SelectionModeParams<'a>,
+    ) -> anyhow::Result<Box<dyn Iterator<Item = ByteRange> + 'a>> {
+        Ok(Box::new(self.ranges.clone().into_iter()))
+    }
+}
+
+#[cfg(test)]
+mod test_text_object {
+    use crate::{buffer::Buffer, components::editor::SurroundKind, selection::Selection};
+
+    use super::*;
+
+    fn rust_buffer(content: &str) -> Buffer {
+        let mut buffer = Buffer::new(Some(tree_sitter_rust::language()), content);
+        buffer
+            .set_language(shared::language::from_extension("rs").unwrap())
+            .unwrap();
+        buffer
+    }
+
+    #[test]
+    fn function_inside_and_around() {
+        let buffer = rust_buffer("fn f(a: A, b: B) {\n    a.g(b)\n}");
+        TextObject::new(&buffer, &TextObjectKind::Function(SurroundKind::Around))
+            .unwrap()
+            .assert_all_selections(
+                &buffer,
+                Selection::default(),
+                &[(0..31, "fn f(a: A, b: B) {\n    a.g(b)\n}")],
+            );
+        TextObject::new(&buffer, &TextObjectKind::Function(SurroundKind::Inside))
+            .unwrap()
+            .assert_all_selections(&buffer, Selection::default(), &[(23..29, "a.g(b)")]);
+    }
+
+    #[test]
+    fn argument_and_comment() {
+        let buffer = rust_buffer("/* f */ fn f(a: A, b: B) { g(a, b) }");
+        TextObject::new(&buffer, &TextObjectKind::Argument)
+            .unwrap()
+            .assert_all_selections(
+                &buffer,
+                Selection::default(),
+                &[
+                    (13..17, "a: A"),
+                    (19..23, "b: B"),
+                    (29..30, "a"),
+                    (32..33, "b"),
+                ],
+            );
+        TextObject::new(&buffer, &TextObjectKind::Comment)
+            .unwrap()
+            .assert_all_selections(&buffer, Selection::default(), &[(0..7, "/* f */")]);
+    }
+}
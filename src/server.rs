@@ -0,0 +1,317 @@
+//! The headless server mode (`ki serve`), where the editor runs without a terminal and is
+//! driven by the clients connected to a Unix domain socket, e.g. `ki attach` or a GUI.
+//! Every client drives the same editor, so they share the buffers and the layout.
+//!
+//! The messages are JSON-RPC 2.0, one JSON object per line. The requests of the clients are:
+//!
+//! | Method     | Params                          | Result                               |
+//! | ---------- | ------------------------------- | ------------------------------------ |
+//! | `input`    | `{ "keys": "space f" }`         | `null`, the keys are typed in order  |
+//! | `paste`    | `{ "text": "..." }`             | `null`                               |
+//! | `resize`   | `{ "columns": 80, "rows": 24 }` | `null`                               |
+//! | `openFile` | `{ "path": "src/main.rs" }`     | `null`                               |
+//! | `command`  | `{ "name": "write-all" }`       | `null`, see the command prompt       |
+//! | `buffers`  | none                            | The paths of the opened buffers      |
+//! | `content`  | none                            | The content of the focused component |
+//!
+//! Requests without an `id` are notifications, whose errors are shown in the editor instead.
+//!
+//! After each render, the server notifies every client with `output`, whose `data` is the
+//! base64 of the bytes that would be written to a terminal, so a client either writes it to its
+//! terminal as it is, or feeds it to a terminal emulator (e.g. the `vt100` crate).
+//! A client that just connected receives the whole screen with the next render.
+//!
+//! Each client is written to by its own thread, so that a client that stops reading (e.g. a
+//! suspended `ki attach`) cannot block the editor. Such a client is disconnected once
+//! `CLIENT_QUEUE_LEN` messages are waiting for it.
+
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader, Write},
+    net::Shutdown,
+    os::unix::{
+        fs::PermissionsExt,
+        net::{UnixListener, UnixStream},
+    },
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{Sender, SyncSender},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use anyhow::Context;
+use event::KeyEvent;
+
+use crate::app::{AppMessage, Dimension};
+
+/// The number of messages that can be waiting for a client before it is disconnected
+const CLIENT_QUEUE_LEN: usize = 64;
+
+/// A client whose socket cannot be written to for this long is disconnected
+const CLIENT_WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub(crate) fn default_socket_path() -> PathBuf {
+    grammar::cache_dir().join("server.sock")
+}
+
+#[derive(Debug)]
+pub(crate) enum RemoteRequest {
+    Input(Vec<KeyEvent>),
+    Paste(String),
+    Resize(Dimension),
+    OpenFile(PathBuf),
+    Command(String),
+    Buffers,
+    Content,
+}
+
+/// The connected clients, shared by the listener, the app and the remote frontend,
+/// where the messages of each client are queued for its writer thread
+#[derive(Clone, Default)]
+pub(crate) struct RemoteClients {
+    clients: Arc<Mutex<HashMap<usize, SyncSender<String>>>>,
+}
+
+impl RemoteClients {
+    /// The clients that cannot be written to, or that fall behind, are disconnected.
+    pub(crate) fn broadcast(&self, message: &serde_json::Value) {
+        let line = format!("{message}\n");
+        self.clients
+            .lock()
+            .unwrap()
+            .retain(|_, sender| sender.try_send(line.clone()).is_ok())
+    }
+
+    pub(crate) fn respond(
+        &self,
+        client_id: usize,
+        id: serde_json::Value,
+        result: Result<serde_json::Value, String>,
+    ) {
+        let message = match result {
+            Ok(result) => serde_json::json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(message) => serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": -32000, "message": message },
+            }),
+        };
+        let mut clients = self.clients.lock().unwrap();
+        if let Some(sender) = clients.get(&client_id) {
+            if sender.try_send(format!("{message}\n")).is_err() {
+                clients.remove(&client_id);
+            }
+        }
+    }
+
+    /// The socket is shut down once the client is removed or cannot be written to,
+    /// which also ends the thread that reads its requests.
+    fn add(&self, client_id: usize, mut stream: UnixStream) {
+        let (sender, receiver) = std::sync::mpsc::sync_channel::<String>(CLIENT_QUEUE_LEN);
+        std::thread::spawn(move || {
+            if stream.set_write_timeout(Some(CLIENT_WRITE_TIMEOUT)).is_ok() {
+                for line in receiver {
+                    if stream.write_all(line.as_bytes()).is_err() {
+                        break;
+                    }
+                }
+            }
+            let _ = stream.shutdown(Shutdown::Both);
+        });
+        self.clients.lock().unwrap().insert(client_id, sender);
+    }
+
+    fn remove(&self, client_id: usize) {
+        self.clients.lock().unwrap().remove(&client_id);
+    }
+}
+
+/// Accepts the clients on another thread, whose requests are sent as `AppMessage::RemoteRequest`.
+/// A stale socket file (e.g. left by a crashed server) is replaced.
+pub(crate) fn listen(
+    socket_path: &Path,
+    sender: Sender<AppMessage>,
+    clients: RemoteClients,
+) -> anyhow::Result<()> {
+    if UnixStream::connect(socket_path).is_ok() {
+        return Err(anyhow::anyhow!(
+            "Another server is already listening on {}",
+            socket_path.display()
+        ));
+    }
+    let _ = std::fs::remove_file(socket_path);
+    let listener = UnixListener::bind(socket_path)
+        .with_context(|| format!("Unable to listen on {}", socket_path.display()))?;
+    // Clients can run shell commands through the editor, so only the user may connect
+    std::fs::set_permissions(socket_path, std::fs::Permissions::from_mode(0o600))
+        .with_context(|| format!("Unable to restrict access to {}", socket_path.display()))?;
+    std::thread::spawn(move || {
+        for (client_id, stream) in listener.incoming().enumerate() {
+            let Ok(stream) = stream else {
+                continue;
+            };
+            let Ok(writer) = stream.try_clone() else {
+                continue;
+            };
+            clients.add(client_id, writer);
+            if sender.send(AppMessage::RemoteClientConnected).is_err() {
+                break;
+            }
+            let sender = sender.clone();
+            let clients = clients.clone();
+            std::thread::spawn(move || {
+                for line in BufReader::new(stream).lines().map_while(Result::ok) {
+                    let message = match parse_request(&line) {
+                        Ok((id, request)) => AppMessage::RemoteRequest {
+                            client_id,
+                            id,
+                            request,
+                        },
+                        Err(error) => {
+                            clients.respond(
+                                client_id,
+                                serde_json::Value::Null,
+                                Err(error.to_string()),
+                            );
+                            continue;
+                        }
+                    };
+                    if sender.send(message).is_err() {
+                        break;
+                    }
+                }
+                clients.remove(client_id)
+            });
+        }
+    });
+    Ok(())
+}
+
+/// Returns the `id` of the request (`None` for notifications) together with the request.
+fn parse_request(line: &str) -> anyhow::Result<(Option<serde_json::Value>, RemoteRequest)> {
+    let message: serde_json::Value = serde_json::from_str(line)?;
+    let method = message
+        .get("method")
+        .and_then(|method| method.as_str())
+        .ok_or_else(|| anyhow::anyhow!("The request has no method"))?;
+    let params = message.get("params").unwrap_or(&serde_json::Value::Null);
+    let string = |name: &str| {
+        params
+            .get(name)
+            .and_then(|value| value.as_str())
+            .map(|value| value.to_string())
+            .ok_or_else(|| anyhow::anyhow!("`{method}` expects the string param `{name}`"))
+    };
+    let number = |name: &str| {
+        params
+            .get(name)
+            .and_then(|value| value.as_u64())
+            .and_then(|value| u16::try_from(value).ok())
+            .ok_or_else(|| anyhow::anyhow!("`{method}` expects the number param `{name}`"))
+    };
+    let request = match method {
+        "input" => RemoteRequest::Input(
+            event::parse_key_events(&string("keys")?)
+                .map_err(|error| anyhow::anyhow!("Invalid keys: {error}"))?,
+        ),
+        "paste" => RemoteRequest::Paste(string("text")?),
+        "resize" => RemoteRequest::Resize(Dimension {
+            width: number("columns")?,
+            height: number("rows")?,
+        }),
+        "openFile" => RemoteRequest::OpenFile(string("path")?.into()),
+        "command" => RemoteRequest::Command(string("name")?),
+        "buffers" => RemoteRequest::Buffers,
+        "content" => RemoteRequest::Content,
+        _ => return Err(anyhow::anyhow!("Unknown method `{method}`")),
+    };
+    Ok((message.get("id").cloned(), request))
+}
+
+#[cfg(test)]
+mod test_server {
+    use super::*;
+
+    #[test]
+    fn parse_requests() -> anyhow::Result<()> {
+        let (id, request) = parse_request(
+            r#"{"jsonrpc":"2.0","id":1,"method":"input","params":{"keys":"x esc"}}"#,
+        )?;
+        assert_eq!(id, Some(serde_json::json!(1)));
+        assert!(matches!(
+            request,
+            RemoteRequest::Input(keys) if keys == event::parse_key_events("x esc").unwrap()
+        ));
+        let (id, request) = parse_request(
+            r#"{"jsonrpc":"2.0","method":"resize","params":{"columns":100,"rows":30}}"#,
+        )?;
+        assert_eq!(id, None);
+        assert!(matches!(
+            request,
+            RemoteRequest::Resize(Dimension {
+                width: 100,
+                height: 30
+            })
+        ));
+        let error = parse_request(r#"{"jsonrpc":"2.0","id":2,"method":"paste"}"#).unwrap_err();
+        assert_eq!(error.to_string(), "`paste` expects the string param `text`");
+        assert!(parse_request(r#"{"jsonrpc":"2.0","method":"fly"}"#).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn respond_to_the_requesting_client_only() -> anyhow::Result<()> {
+        let clients = RemoteClients::default();
+        let (first, first_server) = UnixStream::pair()?;
+        let (second, second_server) = UnixStream::pair()?;
+        clients.add(0, first_server);
+        clients.add(1, second_server);
+        clients.respond(1, serde_json::json!(7), Ok(serde_json::json!(["a.rs"])));
+        clients.broadcast(&serde_json::json!({ "jsonrpc": "2.0", "method": "output" }));
+        drop(clients);
+        let messages = |stream: UnixStream| {
+            BufReader::new(stream)
+                .lines()
+                .map_while(Result::ok)
+                .map(|line| serde_json::from_str::<serde_json::Value>(&line).unwrap())
+                .collect::<Vec<_>>()
+        };
+        let output = serde_json::json!({ "jsonrpc": "2.0", "method": "output" });
+        assert_eq!(messages(first), [output.clone()]);
+        assert_eq!(
+            messages(second),
+            [
+                serde_json::json!({ "jsonrpc": "2.0", "id": 7, "result": ["a.rs"] }),
+                output
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn clients_that_stop_reading_are_disconnected() -> anyhow::Result<()> {
+        let clients = RemoteClients::default();
+        // The client is kept connected, but never reads
+        let (_client, server) = UnixStream::pair()?;
+        clients.add(0, server);
+        let output = serde_json::json!({ "data": "x".repeat(1 << 18) });
+        for _ in 0..CLIENT_QUEUE_LEN + 2 {
+            clients.broadcast(&output);
+        }
+        assert!(clients.clients.lock().unwrap().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn only_the_user_can_connect() -> anyhow::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let socket_path = temp_dir.path().join("server.sock");
+        let (sender, _receiver) = std::sync::mpsc::channel();
+        listen(&socket_path, sender, RemoteClients::default())?;
+        let mode = std::fs::metadata(&socket_path)?.permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        Ok(())
+    }
+}
@@ -0,0 +1,109 @@
+//! The session of a working directory is saved when the editor is exited, and restored when
+//! the editor is launched in the same directory without a file to open.
+//!
+//! A session consists of the opened files with their cursor and scroll positions, the file that
+//! is shown in the main panel, and the search history. The windows themselves are not saved,
+//! because their rectangles are derived from the terminal dimension and the opened components.
+//!
+//! Encrypted files are never saved, because restoring them would prompt for their passphrases.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct Session {
+    /// In the order that they are opened
+    pub(crate) files: Vec<SessionFile>,
+    /// The file that is shown in the main panel
+    pub(crate) current_file: Option<PathBuf>,
+    /// The oldest search is the first
+    pub(crate) search_history: Vec<String>,
+    /// The oldest search is the first
+    pub(crate) global_search_history: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct SessionFile {
+    pub(crate) path: PathBuf,
+    /// The 0-based line of the primary cursor
+    pub(crate) line: usize,
+    /// The 0-based column of the primary cursor
+    pub(crate) column: usize,
+    pub(crate) scroll_offset: u16,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct SessionStore {
+    directory: PathBuf,
+}
+
+impl SessionStore {
+    pub(crate) fn directory() -> PathBuf {
+        grammar::cache_dir().join("sessions")
+    }
+
+    pub(crate) fn new(directory: PathBuf) -> SessionStore {
+        SessionStore { directory }
+    }
+
+    /// The session is named after the working directory, where the path separators are
+    /// replaced with `%`, for example `%home%user%project.json`.
+    fn file_path(&self, working_directory: &Path) -> PathBuf {
+        let name = working_directory
+            .display()
+            .to_string()
+            .replace(['/', '\\', ':'], "%");
+        self.directory.join(format!("{name}.json"))
+    }
+
+    pub(crate) fn save(&self, working_directory: &Path, session: &Session) -> anyhow::Result<()> {
+        std::fs::create_dir_all(&self.directory)?;
+        std::fs::write(
+            self.file_path(working_directory),
+            serde_json::to_string(session)?,
+        )?;
+        Ok(())
+    }
+
+    /// Returns `None` if no session is saved for the working directory.
+    pub(crate) fn load(&self, working_directory: &Path) -> anyhow::Result<Option<Session>> {
+        let file_path = self.file_path(working_directory);
+        if !file_path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::from_str(&std::fs::read_to_string(
+            file_path,
+        )?)?))
+    }
+}
+
+#[cfg(test)]
+mod test_session {
+    use super::*;
+
+    #[test]
+    fn sessions_are_saved_per_working_directory() -> anyhow::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let store = SessionStore::new(temp_dir.path().join("sessions"));
+        let project = Path::new("/home/me/project");
+        let session = Session {
+            files: [SessionFile {
+                path: project.join("main.rs"),
+                line: 3,
+                column: 4,
+                scroll_offset: 1,
+            }]
+            .to_vec(),
+            current_file: Some(project.join("main.rs")),
+            search_history: ["foo".to_string()].to_vec(),
+            global_search_history: Vec::new(),
+        };
+
+        assert_eq!(store.load(project)?, None);
+        store.save(project, &session)?;
+        assert_eq!(store.load(project)?, Some(session));
+        assert_eq!(store.load(Path::new("/home/me/project-other"))?, None);
+        Ok(())
+    }
+}
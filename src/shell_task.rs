@@ -0,0 +1,100 @@
+//! Shell tasks are the commands of a project (e.g. `cargo check` or `npm test`), which are
+//! configured in `[tasks]` of the config, or entered by the `build` command:
+//!
+//! ```toml
+//! [tasks]
+//! check = "cargo check"
+//! test = "npm test"
+//! ```
+//!
+//! A task runs in the working directory, and its output (where stderr is merged into stdout)
+//! is streamed line by line into the task output pane. Once it exits, the errors and warnings
+//! in its output are listed as the quickfix list, see `compiler_output`.
+//!
+//! Only one task runs at a time, so running a task kills the one that is still running.
+
+use std::{
+    io::{BufRead, BufReader},
+    path::Path,
+    process::{Child, Stdio},
+    sync::mpsc::Sender,
+};
+
+use crate::app::AppMessage;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct ShellTask {
+    pub(crate) name: String,
+    pub(crate) command: String,
+}
+
+pub(crate) struct RunningShellTask {
+    /// Distinguishes the messages of this task from the ones of the tasks killed before it
+    id: usize,
+    task: ShellTask,
+    child: Child,
+    /// Kept for parsing the errors and warnings once the task exits
+    output: Vec<String>,
+}
+
+impl RunningShellTask {
+    /// Each line of the output is sent as `AppMessage::ShellTaskOutput`,
+    /// followed by `AppMessage::ShellTaskExited` once the output is closed.
+    pub(crate) fn spawn(
+        id: usize,
+        task: ShellTask,
+        working_directory: &Path,
+        sender: Sender<AppMessage>,
+    ) -> anyhow::Result<RunningShellTask> {
+        let mut child = std::process::Command::new("bash")
+            .args(["-c", &format!("exec 2>&1\n{}", task.command)])
+            .current_dir(working_directory)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| anyhow::anyhow!("Failed to open the stdout of `{}`", task.command))?;
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if sender
+                    .send(AppMessage::ShellTaskOutput { id, line })
+                    .is_err()
+                {
+                    return;
+                }
+            }
+            let _ = sender.send(AppMessage::ShellTaskExited { id });
+        });
+        Ok(RunningShellTask {
+            id,
+            task,
+            child,
+            output: Vec::new(),
+        })
+    }
+
+    pub(crate) fn id(&self) -> usize {
+        self.id
+    }
+
+    pub(crate) fn task(&self) -> &ShellTask {
+        &self.task
+    }
+
+    pub(crate) fn push_output(&mut self, line: String) {
+        self.output.push(line)
+    }
+
+    /// Returns the output and the exit code, which is `None` if the task is killed by a signal.
+    pub(crate) fn wait(mut self) -> anyhow::Result<(String, Option<i32>)> {
+        let status = self.child.wait()?;
+        Ok((self.output.join("\n"), status.code()))
+    }
+
+    pub(crate) fn kill(mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
@@ -227,6 +227,29 @@ pub(crate) fn soft_wrap(text: &str, width: usize) -> WrappedLines {
     result
 }
 
+/// Returns the char ranges of each display line of `line` (which should not contain any newline),
+/// when `line` is soft-wrapped by `width`.
+pub(crate) fn display_line_ranges(line: &str, width: usize) -> Vec<std::ops::Range<usize>> {
+    let ranges = soft_wrap(line, width)
+        .lines()
+        .first()
+        .map(|line| line.lines())
+        .unwrap_or_default()
+        .into_iter()
+        .scan(0, |start, display_line| {
+            let end = *start + display_line.chars().count();
+            let range = *start..end;
+            *start = end;
+            Some(range)
+        })
+        .collect_vec();
+    if ranges.is_empty() {
+        [0..0].to_vec()
+    } else {
+        ranges
+    }
+}
+
 /// Chop the given string into chunks by the given `max_width`
 /// The width of each chunk is paired with each chunk in the result vector.
 fn chop_str(s: &str, max_width: usize) -> Vec<(usize, String)> {
@@ -281,9 +304,19 @@ fn chop_str(s: &str, max_width: usize) -> Vec<(usize, String)> {
 mod test_soft_wrap {
     use crate::position::Position;
 
-    use super::{chop_str, soft_wrap};
+    use super::{chop_str, display_line_ranges, soft_wrap};
     use unicode_width::UnicodeWidthStr;
 
+    #[test]
+    fn test_display_line_ranges() {
+        assert_eq!(
+            display_line_ranges("hello world spam", 8),
+            [0..6, 6..12, 12..16]
+        );
+        assert_eq!(display_line_ranges("hello", 8), [0..5]);
+        assert_eq!(display_line_ranges("", 8), [0..0]);
+    }
+
     #[test]
     fn test_chop_str() {
         assert_eq!(chop_str("hello", 6), vec![(5, "hello".to_string())]);
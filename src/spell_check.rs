@@ -0,0 +1,288 @@
+//! Spell checking of the comments and strings of code, and of prose (e.g. markdown), where the
+//! words are checked against a word list (one word per line, such as `/usr/share/dict/words`)
+//! and the user dictionary, which the words added to the dictionary are appended to.
+//!
+//! Words that are likely identifiers or abbreviations (e.g. `camelCase`, `HTTP` or words with
+//! digits) are not checked.
+
+use std::{
+    collections::HashSet,
+    io::Write,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use itertools::Itertools;
+
+use crate::{buffer::Buffer, grid::StyleKey, hard_wrap::is_prose};
+
+pub(crate) const DEFAULT_DICTIONARY: &str = "/usr/share/dict/words";
+
+/// Words shorter than this are not checked
+const MIN_WORD_LENGTH: usize = 3;
+
+const MAX_SUGGESTIONS: usize = 10;
+
+/// Suggestions differ from the misspelled word by at most this number of edits
+const MAX_SUGGESTION_DISTANCE: usize = 2;
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct SpellChecker {
+    /// In lower case
+    words: HashSet<String>,
+    /// The file that the words added to the dictionary are appended to, which is `None` in tests
+    user_dictionary: Option<PathBuf>,
+}
+
+impl SpellChecker {
+    pub(crate) fn user_dictionary_path() -> PathBuf {
+        grammar::config_dir().join("dictionary.txt")
+    }
+
+    /// The user dictionary is created once a word is added to the dictionary.
+    pub(crate) fn load(dictionary: &Path, user_dictionary: PathBuf) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(dictionary).map_err(|error| {
+            anyhow::anyhow!(
+                "Unable to read the spell check dictionary {}: {error}",
+                dictionary.display()
+            )
+        })?;
+        let user_content = if user_dictionary.exists() {
+            std::fs::read_to_string(&user_dictionary)?
+        } else {
+            String::new()
+        };
+        Ok(Self {
+            words: content
+                .lines()
+                .chain(user_content.lines())
+                .map(|word| word.trim().to_lowercase())
+                .filter(|word| !word.is_empty())
+                .collect(),
+            user_dictionary: Some(user_dictionary),
+        })
+    }
+
+    #[cfg(test)]
+    pub(crate) fn new(words: &[&str]) -> Self {
+        Self {
+            words: words.iter().map(|word| word.to_lowercase()).collect(),
+            user_dictionary: None,
+        }
+    }
+
+    fn is_correct(&self, word: &str) -> bool {
+        let letters = word.trim_end_matches("'s");
+        let is_checked = letters.chars().count() >= MIN_WORD_LENGTH
+            && letters.chars().all(char::is_alphabetic)
+            // A capital letter after the first letter, for example `camelCase` or `HTTP`
+            && !letters.chars().skip(1).any(char::is_uppercase);
+        !is_checked
+            || self.words.contains(&word.to_lowercase())
+            || self.words.contains(&letters.to_lowercase())
+    }
+
+    /// Returns the byte ranges of the misspelled words of `text`.
+    pub(crate) fn misspellings<'a>(
+        &'a self,
+        text: &'a str,
+    ) -> impl Iterator<Item = Range<usize>> + 'a {
+        lazy_regex::regex!(r"[\p{Alphabetic}\d_]+(?:'[\p{Alphabetic}]+)*")
+            .find_iter(text)
+            .filter(|found| !self.is_correct(found.as_str()))
+            .map(|found| found.range())
+    }
+
+    /// Returns the byte ranges of the misspelled words within `byte_range` of the buffer,
+    /// where only the comments and strings are checked, unless the buffer is prose,
+    /// in which case everything except code is checked.
+    pub(crate) fn buffer_misspellings(
+        &self,
+        buffer: &Buffer,
+        byte_range: Range<usize>,
+    ) -> Vec<Range<usize>> {
+        let spans = buffer
+            .highlighted_spans()
+            .iter()
+            .filter(|span| {
+                span.byte_range.start < byte_range.end && byte_range.start < span.byte_range.end
+            })
+            .filter_map(|span| match &span.style_key {
+                StyleKey::Syntax(key) => Some((key.as_str(), span.byte_range.clone())),
+                _ => None,
+            });
+        let checked_ranges = if buffer.path().is_some_and(|path| is_prose(&path)) {
+            let code = spans
+                .filter(|(key, _)| {
+                    key.starts_with("markup.raw") || key.starts_with("markup.link.url")
+                })
+                .map(|(_, range)| range)
+                .sorted_by_key(|range| range.start)
+                .collect_vec();
+            let mut start = byte_range.start;
+            let mut ranges = Vec::new();
+            for range in code {
+                if range.start > start {
+                    ranges.push(start..range.start)
+                }
+                start = start.max(range.end)
+            }
+            ranges.push(start..byte_range.end);
+            ranges
+        } else {
+            spans
+                .filter(|(key, _)| key.starts_with("comment") || key.starts_with("string"))
+                .map(|(_, range)| range.start.max(byte_range.start)..range.end.min(byte_range.end))
+                .sorted_by_key(|range| range.start)
+                .coalesce(|a, b| {
+                    if b.start <= a.end {
+                        Ok(a.start..a.end.max(b.end))
+                    } else {
+                        Err((a, b))
+                    }
+                })
+                .collect_vec()
+        };
+        let rope = buffer.rope();
+        checked_ranges
+            .into_iter()
+            .filter(|range| range.start < range.end && range.end <= rope.len_bytes())
+            .flat_map(|range| {
+                let text = rope.byte_slice(range.clone()).to_string();
+                self.misspellings(&text)
+                    .map(|misspelling| {
+                        misspelling.start + range.start..misspelling.end + range.start
+                    })
+                    .collect_vec()
+            })
+            .collect()
+    }
+
+    /// The closest words first, whose first letter is capitalized like the `word`.
+    pub(crate) fn suggestions(&self, word: &str) -> Vec<String> {
+        let lower = word.to_lowercase();
+        let length = lower.chars().count();
+        let is_capitalized = word.chars().next().is_some_and(char::is_uppercase);
+        self.words
+            .iter()
+            .filter(|candidate| {
+                candidate.chars().count().abs_diff(length) <= MAX_SUGGESTION_DISTANCE
+            })
+            .filter_map(|candidate| {
+                let distance = edit_distance(&lower, candidate);
+                (distance <= MAX_SUGGESTION_DISTANCE).then_some((distance, candidate))
+            })
+            .sorted()
+            .take(MAX_SUGGESTIONS)
+            .map(|(_, candidate)| {
+                if is_capitalized {
+                    let mut chars = candidate.chars();
+                    chars
+                        .next()
+                        .map(|first| first.to_uppercase().chain(chars).collect())
+                        .unwrap_or_default()
+                } else {
+                    candidate.clone()
+                }
+            })
+            .collect()
+    }
+
+    pub(crate) fn add_word(&mut self, word: &str) -> anyhow::Result<()> {
+        self.words.insert(word.to_lowercase());
+        if let Some(path) = &self.user_dictionary {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            let mut file = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)?;
+            writeln!(file, "{word}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The optimal string alignment distance, where swapping two adjacent characters is one edit.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let (a, b) = (a.chars().collect_vec(), b.chars().collect_vec());
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i
+    }
+    for j in 0..=b.len() {
+        distances[0][j] = j
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1)
+                .min(distances[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1)
+            }
+            distances[i][j] = distance
+        }
+    }
+    distances[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod test_spell_check {
+    use crate::syntax_highlight::{HighlighedSpan, HighlighedSpans};
+
+    use super::*;
+
+    #[test]
+    fn identifiers_and_abbreviations_are_not_checked() {
+        let spell_checker = SpellChecker::new(&["hello", "world"]);
+        let text = "Hello wrold, HTTP camelCase x1y2 foo_bar ab world's wrold's";
+        let misspelled = spell_checker
+            .misspellings(text)
+            .map(|range| &text[range])
+            .collect_vec();
+        assert_eq!(misspelled, ["wrold", "wrold's"]);
+    }
+
+    #[test]
+    fn suggestions_are_sorted_by_distance() {
+        let spell_checker = SpellChecker::new(&["world", "would", "word", "sword", "hello"]);
+        assert_eq!(
+            spell_checker.suggestions("wrold"),
+            ["world", "word", "would"]
+        );
+        assert_eq!(spell_checker.suggestions("Wrold")[0], "World");
+        assert_eq!(edit_distance("wrold", "world"), 1);
+        assert_eq!(edit_distance("wrold", "sword"), 3);
+    }
+
+    #[test]
+    fn only_comments_and_strings_of_code_are_checked() {
+        let content = "// a wrold\nfn wrold() { \"wrold\" }";
+        let mut buffer = Buffer::new(Some(tree_sitter_rust::language()), content);
+        buffer.update_highlighted_spans(HighlighedSpans(
+            [
+                (0..10, "comment"),
+                (24..31, "string"),
+                (25..30, "string.special"),
+            ]
+            .into_iter()
+            .map(|(byte_range, key)| HighlighedSpan {
+                byte_range,
+                style_key: StyleKey::Syntax(key.to_string()),
+            })
+            .collect(),
+        ));
+        let spell_checker = SpellChecker::new(&[]);
+        let misspellings = spell_checker.buffer_misspellings(&buffer, 0..content.len());
+        assert_eq!(
+            misspellings
+                .into_iter()
+                .map(|range| (range.start, &content[range]))
+                .collect_vec(),
+            [(5, "wrold"), (25, "wrold")]
+        );
+    }
+}
@@ -0,0 +1,174 @@
+//! The status line is the bottom row of the screen, which consists of the configured segments.
+//!
+//! Each segment is styled by `StatusLineStyles` of the theme on top of the status line style.
+
+use crate::{
+    app::Dimension,
+    grid::{CellUpdate, Grid, LineUpdate, RenderContentLineNumber},
+    position::Position,
+    style::Style,
+    themes::{StatusLineStyles, Theme},
+};
+
+const SEPARATOR: &str = " │ ";
+
+/// The segments of the status line are rendered in the order of the config,
+/// and a segment is hidden by leaving it out of the config.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum StatusLineComponent {
+    CurrentWorkingDirectory,
+    GitBranch,
+    Mode,
+    /// The register of the macro being recorded, hidden if no macro is being recorded
+    MacroRecording,
+    SelectionMode,
+    LastDispatch,
+    /// Word count and reading time of the current file, only shown for prose
+    WordCount,
+    /// Number of diagnostics of each severity in the current file, hidden if there is none
+    DiagnosticCounts,
+    /// The path of the current file relative to the working directory
+    FilePath,
+    /// Shown if the current buffer has unsaved changes
+    Modified,
+    /// The 1-based line and column of the primary cursor
+    CursorPosition,
+    /// Hidden if there is only one selection
+    SelectionCount,
+    /// The LSP server of the current file, hidden if there is none
+    LspStatus,
+    /// The indentation of the current file, for example `Spaces: 4` or `Tabs`
+    Indentation,
+    /// The line ending that the current file is written with, for example `LF` or `CRLF`
+    LineEnding,
+    /// The encoding that the current file is decoded from and written with, for example `UTF-8`
+    Charset,
+    /// A spinner with the oldest running background task, hidden if no task is running
+    BackgroundTasks,
+}
+
+impl StatusLineComponent {
+    /// Parses a comma-separated list of segment names, for example `mode,word-count`.
+    pub(crate) fn parse_config(config: &str) -> anyhow::Result<Vec<StatusLineComponent>> {
+        config
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(|name| {
+                Ok(match name {
+                    "cwd" => StatusLineComponent::CurrentWorkingDirectory,
+                    "git-branch" => StatusLineComponent::GitBranch,
+                    "mode" => StatusLineComponent::Mode,
+                    "macro-recording" => StatusLineComponent::MacroRecording,
+                    "selection-mode" => StatusLineComponent::SelectionMode,
+                    "last-dispatch" => StatusLineComponent::LastDispatch,
+                    "word-count" => StatusLineComponent::WordCount,
+                    "diagnostic-counts" => StatusLineComponent::DiagnosticCounts,
+                    "file-path" => StatusLineComponent::FilePath,
+                    "modified" => StatusLineComponent::Modified,
+                    "cursor-position" => StatusLineComponent::CursorPosition,
+                    "selection-count" => StatusLineComponent::SelectionCount,
+                    "lsp-status" => StatusLineComponent::LspStatus,
+                    "indentation" => StatusLineComponent::Indentation,
+                    "line-ending" => StatusLineComponent::LineEnding,
+                    "charset" => StatusLineComponent::Charset,
+                    "background-tasks" => StatusLineComponent::BackgroundTasks,
+                    _ => return Err(anyhow::anyhow!("Unknown status line segment: {name:?}")),
+                })
+            })
+            .collect()
+    }
+
+    pub(crate) fn style(&self, styles: &StatusLineStyles) -> Style {
+        match self {
+            StatusLineComponent::GitBranch => styles.git_branch,
+            StatusLineComponent::Mode => styles.mode,
+            StatusLineComponent::MacroRecording => styles.macro_recording,
+            StatusLineComponent::DiagnosticCounts => styles.diagnostic_counts,
+            StatusLineComponent::FilePath => styles.file_path,
+            StatusLineComponent::Modified => styles.modified,
+            StatusLineComponent::CursorPosition => styles.cursor_position,
+            StatusLineComponent::SelectionCount => styles.selection_count,
+            StatusLineComponent::LspStatus => styles.lsp_status,
+            StatusLineComponent::CurrentWorkingDirectory
+            | StatusLineComponent::SelectionMode
+            | StatusLineComponent::LastDispatch
+            | StatusLineComponent::WordCount
+            | StatusLineComponent::Indentation
+            | StatusLineComponent::LineEnding
+            | StatusLineComponent::Charset
+            | StatusLineComponent::BackgroundTasks => Style::new(),
+        }
+    }
+}
+
+/// Renders the segments into a row of `width` cells, where each segment is a text and its style.
+pub(crate) fn render(
+    segments: &[(String, Style)],
+    width: u16,
+    style: Style,
+    theme: &Theme,
+) -> Grid {
+    let mut content = " ".to_string();
+    let mut cell_updates = Vec::new();
+    for (index, (text, segment_style)) in segments.iter().enumerate() {
+        if index > 0 {
+            content.push_str(SEPARATOR);
+        }
+        let start = content.chars().count();
+        content.push_str(text);
+        cell_updates.extend((start..content.chars().count()).map(|column| CellUpdate {
+            style: *segment_style,
+            ..CellUpdate::new(Position::new(0, column))
+        }));
+    }
+    Grid::new(Dimension { height: 1, width }).render_content(
+        &content,
+        RenderContentLineNumber::NoLineNumber,
+        cell_updates,
+        [LineUpdate {
+            line_index: 0,
+            style,
+        }]
+        .to_vec(),
+        theme,
+    )
+}
+
+#[cfg(test)]
+mod test_status_line {
+    use itertools::Itertools;
+
+    use super::*;
+
+    #[test]
+    fn segments_are_styled_on_top_of_the_status_line() {
+        let theme = Theme::default();
+        let red = crate::themes::Color::new(255, 0, 0);
+        let grid = render(
+            &[
+                ("NORMAL".to_string(), Style::new().bold()),
+                ("[+]".to_string(), Style::new().foreground_color(red)),
+            ],
+            20,
+            theme.ui.global_title,
+            &theme,
+        );
+        let row = &grid.rows[0];
+        assert_eq!(
+            row.iter().map(|cell| cell.symbol.as_str()).join(""),
+            " NORMAL │ [+]       "
+        );
+        assert!(row[1].is_bold);
+        assert!(!row[7].is_bold);
+        assert_eq!(row[10].foreground_color, red);
+        assert_eq!(
+            row[8].foreground_color,
+            theme.ui.global_title.foreground_color.unwrap()
+        );
+        assert_eq!(
+            row[10].background_color,
+            theme.ui.global_title.background_color.unwrap()
+        );
+    }
+}
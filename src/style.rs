@@ -9,6 +9,9 @@ pub(crate) struct Style {
     pub(crate) background_color: Option<Color>,
     pub(crate) line: Option<CellLine>,
     pub(crate) is_bold: bool,
+    /// Swaps the foreground and background colors,
+    /// which is still visible when colors are not rendered (e.g. when `NO_COLOR` is set)
+    pub(crate) is_reversed: bool,
 }
 
 pub const fn fg(color: Color) -> Style {
@@ -22,6 +25,7 @@ impl Style {
             background_color: None,
             line: None,
             is_bold: false,
+            is_reversed: false,
         }
     }
 
@@ -77,4 +81,11 @@ impl Style {
             ..self
         }
     }
+
+    pub(crate) const fn reversed(self) -> Style {
+        Style {
+            is_reversed: true,
+            ..self
+        }
+    }
 }
@@ -86,7 +86,36 @@ pub(crate) fn get_surrounding_indices(
     Some((open_index, close_index))
 }
 
+/// Return the kind, open index and close index of the innermost pair of any kind
+/// that surrounds the cursor.
+pub(crate) fn get_nearest_surrounding_indices(
+    content: &str,
+    cursor_char_index: CharIndex,
+    include_cursor_position: bool,
+) -> Option<(EnclosureKind, CharIndex, CharIndex)> {
+    EnclosureKind::all()
+        .into_iter()
+        .filter_map(|kind| {
+            let (open, close) =
+                get_surrounding_indices(content, kind, cursor_char_index, include_cursor_position)?;
+            Some((kind, open, close))
+        })
+        .max_by_key(|(_, open, close)| (*open, std::cmp::Reverse(*close)))
+}
+
 impl EnclosureKind {
+    pub(crate) const fn all() -> [EnclosureKind; 7] {
+        [
+            EnclosureKind::AngularBrackets,
+            EnclosureKind::Parentheses,
+            EnclosureKind::SquareBrackets,
+            EnclosureKind::CurlyBraces,
+            EnclosureKind::DoubleQuotes,
+            EnclosureKind::SingleQuotes,
+            EnclosureKind::Backticks,
+        ]
+    }
+
     pub(crate) const fn open_close_symbols(&self) -> (char, char) {
         match self {
             EnclosureKind::Parentheses => ('(', ')'),
@@ -165,4 +194,19 @@ mod test_surround {
     fn test_get_surrounding_indices_3() {
         run_test("'hello'", SingleQuotes, 2, Some((0, 6)));
     }
+
+    #[test]
+    fn test_get_nearest_surrounding_indices() {
+        let run_test = |content: &str, cursor_char_index: usize, expected: Option<(_, _, _)>| {
+            assert_eq!(
+                get_nearest_surrounding_indices(content, CharIndex(cursor_char_index), true),
+                expected.map(|(kind, open, close)| (kind, CharIndex(open), CharIndex(close)))
+            )
+        };
+        run_test("f([a], {b})", 3, Some((SquareBrackets, 2, 4)));
+        run_test("f([a], {b})", 5, Some((Parentheses, 1, 10)));
+        run_test("f([a], {b})", 8, Some((CurlyBraces, 7, 9)));
+        run_test("(\"hello\")", 3, Some((DoubleQuotes, 1, 7)));
+        run_test("hello", 1, None);
+    }
 }
@@ -91,6 +91,19 @@ impl Highlight for HighlightConfiguration {
 #[derive(Clone, Default, Debug)]
 pub(crate) struct HighlighedSpans(pub Vec<HighlighedSpan>);
 impl HighlighedSpans {
+    fn offset(self, byte_offset: usize) -> HighlighedSpans {
+        HighlighedSpans(
+            self.0
+                .into_iter()
+                .map(|span| HighlighedSpan {
+                    byte_range: span.byte_range.start + byte_offset
+                        ..span.byte_range.end + byte_offset,
+                    ..span
+                })
+                .collect(),
+        )
+    }
+
     pub(crate) fn apply_edit(self, edited_range: &Range<usize>, change: isize) -> HighlighedSpans {
         HighlighedSpans(
             self.0
@@ -105,6 +118,9 @@ pub(crate) struct SyntaxHighlightRequest {
     pub(crate) component_id: ComponentId,
     pub(crate) language: Language,
     pub(crate) source_code: String,
+    /// The byte offset of `source_code` in the buffer, which is non-zero if only
+    /// a part of the buffer is highlighted (e.g. the visible lines of a large file)
+    pub(crate) byte_offset: usize,
 }
 
 pub(crate) fn start_thread(callback: Sender<AppMessage>) -> Sender<SyntaxHighlightRequest> {
@@ -124,7 +140,7 @@ pub(crate) fn start_thread(callback: Sender<AppMessage>) -> Sender<SyntaxHighlig
                 Ok(highlighted_spans) => {
                     let _ = callback.send(AppMessage::SyntaxHighlightResponse {
                         component_id: request.component_id,
-                        highlighted_spans,
+                        highlighted_spans: highlighted_spans.offset(request.byte_offset),
                     });
                 }
                 Err(error) => {
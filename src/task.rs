@@ -0,0 +1,237 @@
+//! Long operations (e.g. shell commands, file IO and git) run as tasks on a pool of worker
+//! threads, so that they never block the render loop.
+//!
+//! A task produces an `AppMessage`, which is delivered to the main loop wrapped in
+//! `AppMessage::TaskFinished`, and is dropped instead if the task is cancelled in the meantime.
+//! A task is cancelled when the component that it is started from is closed, so for example
+//! the result of a picker is not applied after the picker is gone.
+//!
+//! The running tasks are shown as a spinner in the status line.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc::Sender,
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use indexmap::IndexMap;
+
+use crate::{app::AppMessage, components::component::ComponentId};
+
+const WORKER_COUNT: usize = 4;
+
+pub(crate) const SPINNER_INTERVAL: Duration = Duration::from_millis(100);
+
+const SPINNER_FRAMES: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct TaskId(usize);
+
+/// What a task belongs to, which determines when it is cancelled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum TaskOrigin {
+    /// Never cancelled automatically
+    App,
+    /// Cancelled once the component is closed
+    Component(ComponentId),
+}
+
+type Job = Box<dyn FnOnce() -> AppMessage + Send>;
+
+struct RunningTask {
+    description: String,
+    origin: TaskOrigin,
+    /// Set when the task is cancelled, so that a job that has not been started is skipped
+    cancelled: Arc<AtomicBool>,
+    started_at: Instant,
+}
+
+pub(crate) struct Tasks {
+    job_sender: Sender<(TaskId, Arc<AtomicBool>, Job)>,
+    running: IndexMap<TaskId, RunningTask>,
+    /// Shared with the spinner thread, which only ticks while tasks are running
+    running_count: Arc<AtomicUsize>,
+    next_id: usize,
+}
+
+impl Tasks {
+    /// The worker threads stop once `sender` is disconnected.
+    pub(crate) fn new(sender: Sender<AppMessage>) -> Tasks {
+        let (job_sender, job_receiver) =
+            std::sync::mpsc::channel::<(TaskId, Arc<AtomicBool>, Job)>();
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+        for _ in 0..WORKER_COUNT {
+            let job_receiver = job_receiver.clone();
+            let sender = sender.clone();
+            std::thread::spawn(move || loop {
+                let Ok((id, cancelled, job)) = job_receiver.lock().unwrap().recv() else {
+                    return;
+                };
+                if cancelled.load(Ordering::Relaxed) {
+                    continue;
+                }
+                let message = Box::new(job());
+                if sender
+                    .send(AppMessage::TaskFinished { id, message })
+                    .is_err()
+                {
+                    return;
+                }
+            });
+        }
+        Tasks {
+            job_sender,
+            running: IndexMap::new(),
+            running_count: Arc::new(AtomicUsize::new(0)),
+            next_id: 0,
+        }
+    }
+
+    /// Sends `AppMessage::TaskSpinnerTicked` periodically while tasks are running,
+    /// which is started when the app is run, so that tests are not interrupted by the ticks.
+    pub(crate) fn start_spinner(&self, sender: Sender<AppMessage>) {
+        let running_count = self.running_count.clone();
+        std::thread::spawn(move || loop {
+            std::thread::sleep(SPINNER_INTERVAL);
+            if running_count.load(Ordering::Relaxed) > 0
+                && sender.send(AppMessage::TaskSpinnerTicked).is_err()
+            {
+                return;
+            }
+        });
+    }
+
+    pub(crate) fn spawn(
+        &mut self,
+        description: String,
+        origin: TaskOrigin,
+        job: impl FnOnce() -> AppMessage + Send + 'static,
+    ) -> TaskId {
+        let id = TaskId(self.next_id);
+        self.next_id += 1;
+        let cancelled = Arc::new(AtomicBool::new(false));
+        self.running.insert(
+            id,
+            RunningTask {
+                description,
+                origin,
+                cancelled: cancelled.clone(),
+                started_at: Instant::now(),
+            },
+        );
+        self.update_running_count();
+        if let Err(error) = self.job_sender.send((id, cancelled, Box::new(job))) {
+            log::error!("Tasks::spawn: failed to send job: {error:?}");
+        }
+        id
+    }
+
+    /// Returns false if the task is cancelled, in which case its message should be dropped.
+    pub(crate) fn finish(&mut self, id: TaskId) -> bool {
+        let finished = self.running.shift_remove(&id).is_some();
+        self.update_running_count();
+        finished
+    }
+
+    pub(crate) fn cancel(&mut self, id: TaskId) {
+        if let Some(task) = self.running.shift_remove(&id) {
+            task.cancelled.store(true, Ordering::Relaxed);
+            log::info!("Tasks::cancel: {}", task.description);
+        }
+        self.update_running_count();
+    }
+
+    /// Cancels the tasks whose components are no longer opened.
+    pub(crate) fn cancel_orphans(&mut self, opened_components: &[ComponentId]) {
+        let orphans = self
+            .running
+            .iter()
+            .filter(|(_, task)| match task.origin {
+                TaskOrigin::App => false,
+                TaskOrigin::Component(id) => !opened_components.contains(&id),
+            })
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>();
+        for id in orphans {
+            self.cancel(id)
+        }
+    }
+
+    fn update_running_count(&self) {
+        self.running_count
+            .store(self.running.len(), Ordering::Relaxed)
+    }
+
+    /// For example `⠙ Blame main.rs (+1)`, where the description is of the oldest task,
+    /// or `None` if no task is running.
+    pub(crate) fn status(&self) -> Option<String> {
+        let (_, oldest) = self.running.first()?;
+        let frame = (oldest.started_at.elapsed().as_millis() / SPINNER_INTERVAL.as_millis())
+            as usize
+            % SPINNER_FRAMES.len();
+        let others = self.running.len() - 1;
+        Some(format!(
+            "{} {}{}",
+            SPINNER_FRAMES[frame],
+            oldest.description,
+            if others > 0 {
+                format!(" (+{others})")
+            } else {
+                String::new()
+            }
+        ))
+    }
+
+    #[cfg(test)]
+    pub(crate) fn running_descriptions(&self) -> Vec<String> {
+        self.running
+            .values()
+            .map(|task| task.description.clone())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test_task {
+    use super::*;
+
+    #[test]
+    fn cancelled_tasks_are_not_finished() -> anyhow::Result<()> {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let mut tasks = Tasks::new(sender);
+        let picker = ComponentId::new();
+        let (unblock, blocked) = std::sync::mpsc::channel::<()>();
+        let app_task = tasks.spawn("Blame".to_string(), TaskOrigin::App, move || {
+            let _ = blocked.recv();
+            AppMessage::BellFlashFinished
+        });
+        let picker_task = tasks.spawn("Search".to_string(), TaskOrigin::Component(picker), || {
+            AppMessage::BellFlashFinished
+        });
+        assert!(tasks.status().unwrap().ends_with(" Blame (+1)"));
+
+        // The picker is closed
+        tasks.cancel_orphans(&[]);
+        assert_eq!(tasks.running_descriptions(), ["Blame"]);
+        unblock.send(())?;
+
+        // The picker job is either skipped, or already started by another worker,
+        // in which case its message is dropped
+        loop {
+            match receiver.recv_timeout(Duration::from_secs(10))? {
+                AppMessage::TaskFinished { id, .. } if id == app_task => {
+                    assert!(tasks.finish(id));
+                    break;
+                }
+                AppMessage::TaskFinished { id, .. } => assert!(!tasks.finish(id)),
+                _ => unreachable!(),
+            }
+        }
+        assert!(!tasks.finish(picker_task));
+        assert!(tasks.status().is_none());
+        Ok(())
+    }
+}
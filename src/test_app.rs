@@ -20,32 +20,44 @@ use shared::canonicalized_path::CanonicalizedPath;
 
 use crate::{
     app::{
-        App, Dimension, Dispatch, LocalSearchConfigUpdate, RequestParams, Scope,
-        StatusLineComponent,
+        App, Bell, Dimension, Dispatch, FilePickerKind, LocalSearchConfigUpdate, RequestParams,
+        Scope, LARGE_PASTE_THRESHOLD,
     },
     char_index_range::CharIndexRange,
     clipboard::CopiedTexts,
     components::{
         component::Component,
+        diff_view::DiffBase,
         editor::{Direction, DispatchEditor, IfCurrentNotFound, Mode, Movement, ViewAlignment},
+        prompt::PromptHistoryKey,
         suggestive_editor::{DispatchSuggestiveEditor, Info, SuggestiveEditorFilter},
     },
     context::{GlobalMode, LocalSearchConfigMode},
+    editorconfig::EndOfLine,
     frontend::{mock::MockFrontend, MyWriter, NullWriter, StringWriter},
     grid::StyleKey,
     integration_test::TestRunner,
+    keymap_config::KeymapConfig,
     list::grep::RegexConfig,
     lsp::{
         code_action::CodeAction,
         completion::{Completion, CompletionItem, CompletionItemEdit, PositionalEdit},
+        diagnostic::Severity,
+        document_highlight::DocumentHighlight,
         documentation::Documentation,
+        hierarchy::{HierarchyContext, HierarchyItem, HierarchyKind},
+        hover::Hover,
         process::FromEditor,
         signature_help::SignatureInformation,
         workspace_edit::{TextDocumentEdit, WorkspaceEdit},
     },
+    macros::MacroName,
     position::Position,
     quickfix_list::{DiagnosticSeverityRange, Location, QuickfixListItem},
+    recovery::Recovery,
     selection::SelectionMode,
+    session::{Session, SessionStore},
+    status_line::StatusLineComponent,
     style::Style,
     themes::Theme,
     ui_tree::ComponentKind,
@@ -61,6 +73,8 @@ pub(crate) enum Step {
     SuggestiveEditor(DispatchSuggestiveEditor),
     ExpectLater(Box<dyn Fn() -> ExpectKind>),
     ExpectCustom(Box<dyn Fn()>),
+    /// Wait until a background thread (e.g. a shell command) sends its result to the app
+    WaitForAppMessage,
 }
 
 #[derive(Debug, Clone)]
@@ -68,6 +82,7 @@ pub(crate) enum ExpectKind {
     FileExplorerContent(String),
     EditorInfoContent(&'static str),
     EditorInfoOpen(bool),
+    HoverPopupContent(Option<&'static str>),
     QuickfixListCurrentLine(&'static str),
     DropdownInfosCount(usize),
     QuickfixListContent(String),
@@ -87,6 +102,7 @@ pub(crate) enum ExpectKind {
     CurrentPrimarySelection(&'static str),
     CurrentCursorDirection(Direction),
     CurrentViewAlignment(Option<ViewAlignment>),
+    CurrentScrollOffset(u16),
     ComponentsLength(usize),
     Quickfixes(Box<[QuickfixListItem]>),
     AppGrid(String),
@@ -113,6 +129,7 @@ pub(crate) enum ExpectKind {
     CurrentGlobalMode(Option<GlobalMode>),
     LspRequestSent(FromEditor),
     CurrentCopiedTextHistoryOffset(isize),
+    BellFlashing(bool),
 }
 fn log<T: std::fmt::Debug>(s: T) {
     println!("===========\n{s:?}",);
@@ -213,6 +230,9 @@ impl ExpectKind {
                 component.borrow().editor().current_view_alignment(),
                 *view_alignment,
             ),
+            CurrentScrollOffset(scroll_offset) => {
+                contextualize(component.borrow().editor().scroll_offset(), *scroll_offset)
+            }
             GridCellBackground(row_index, column_index, background_color) => contextualize(
                 component
                     .borrow()
@@ -286,6 +306,10 @@ impl ExpectKind {
             EditorInfoContent(expected) => {
                 contextualize(app.editor_info_content(), Some(expected.to_string()))
             }
+            HoverPopupContent(expected) => contextualize(
+                app.hover_popup_content(),
+                expected.map(|content| content.to_string()),
+            ),
             AppGridContains(substring) => {
                 let content = app.get_screen().unwrap().stringify();
                 contextualize(content.contains(substring), true)
@@ -302,7 +326,7 @@ impl ExpectKind {
                     .editor()
                     .buffer()
                     .highlighted_spans()
-                    .into_iter()
+                    .iter()
                     .find(|span| &span.byte_range == expected_range)
                     .unwrap()
                     .style_key,
@@ -361,6 +385,7 @@ impl ExpectKind {
                     .primary_selection()?,
             ),
             CurrentGlobalMode(expected) => contextualize(expected, &app.context().mode()),
+            BellFlashing(expected) => contextualize(*expected, app.is_bell_flashing()),
         })
     }
 }
@@ -386,6 +411,13 @@ impl State {
         self.temp_dir.to_path_buf().join(path)
     }
 
+    /// Writes `content` into a new file at `path` of the temp dir.
+    pub(crate) fn new_file(&self, path: &str, content: impl AsRef<[u8]>) -> CanonicalizedPath {
+        let path = self.new_path(path);
+        std::fs::write(&path, content).unwrap();
+        path.try_into().unwrap()
+    }
+
     pub(crate) fn gitignore(&self) -> CanonicalizedPath {
         self.git_ignore.clone()
     }
@@ -393,6 +425,15 @@ impl State {
     pub(crate) fn temp_dir(&self) -> CanonicalizedPath {
         self.temp_dir.clone()
     }
+
+    /// `content` is the TOML of the config, see `Config::load`.
+    pub(crate) fn set_config(&self, content: &str) -> Step {
+        let path = self.new_path("config.toml");
+        std::fs::write(&path, content).unwrap();
+        Step::App(SetConfig(
+            crate::config::Config::load([path].to_vec()).unwrap(),
+        ))
+    }
 }
 
 pub(crate) fn execute_test(callback: impl Fn(State) -> Box<[Step]>) -> anyhow::Result<()> {
@@ -469,6 +510,7 @@ fn execute_test_helper(
                     log(dispatch);
                     app.handle_dispatch_suggestive_editor(dispatch.to_owned())?
                 }
+                WaitForAppMessage => app.wait_for_app_message()?,
             };
         }
 
@@ -830,6 +872,35 @@ fn signature_help() -> anyhow::Result<()> {
     })
 }
 
+#[test]
+fn hover_popup() -> anyhow::Result<()> {
+    execute_test(|s| {
+        let hover = || {
+            HandleLspNotification(LspNotification::Hover(Hover {
+                contents: ["## Hover heading\n- `hover` item".to_string()].to_vec(),
+            }))
+        };
+        Box::new([
+            App(OpenFile(s.main_rs())),
+            App(hover()),
+            Expect(HoverPopupContent(Some("Hover heading\n• hover item"))),
+            Expect(AppGridContains("• hover item")),
+            //
+            // Pressing esc should dismiss the popup
+            App(HandleKeyEvent(key!("esc"))),
+            Expect(HoverPopupContent(None)),
+            Expect(Not(Box::new(AppGridContains("• hover item")))),
+            //
+            // Moving the cursor should dismiss the popup
+            App(hover()),
+            Expect(AppGridContains("• hover item")),
+            Editor(MoveSelection(Right)),
+            Expect(Not(Box::new(AppGridContains("• hover item")))),
+            Expect(HoverPopupContent(None)),
+        ])
+    })
+}
+
 #[test]
 pub(crate) fn repo_git_hunks() -> Result<(), anyhow::Error> {
     execute_test(|s| {
@@ -889,6 +960,228 @@ pub(crate) fn repo_git_hunks() -> Result<(), anyhow::Error> {
     })
 }
 
+#[test]
+fn git_gutter_move_to_hunk_and_revert_hunk() -> anyhow::Result<()> {
+    execute_test(|s| {
+        let original_content = s.main_rs().read().unwrap();
+        Box::new([
+            App(OpenFile(s.main_rs())),
+            Editor(MatchLiteral("mod foo;".to_string())),
+            Editor(EnterInsertMode(Direction::End)),
+            Editor(Insert(" // changed".to_string())),
+            Editor(EnterNormalMode),
+            Editor(SelectLineAt(3)),
+            Editor(MoveToGitGutterHunk(Direction::Start)),
+            Expect(CurrentSelectedTexts(&["mod foo; // changed\n"])),
+            Editor(RevertGitGutterHunk),
+            App(SaveAll),
+            Expect(FileContent(s.main_rs(), original_content.clone())),
+        ])
+    })
+}
+
+#[test]
+fn git_blame_virtual_text_and_panel() -> anyhow::Result<()> {
+    execute_test(|s| {
+        Box::new([
+            App(TerminalDimensionChanged(Dimension {
+                width: 200,
+                height: 10,
+            })),
+            App(OpenFile(s.main_rs())),
+            App(SetShowGitBlame(true)),
+            WaitForAppMessage,
+            Expect(AppGridContains("1│mod foo;    ")),
+            Expect(AppGridContains(" • init")),
+            Editor(EnterInsertMode(Direction::Start)),
+            Editor(Insert("// ".to_string())),
+            // The blame is outdated once the content is modified
+            Expect(Not(Box::new(AppGridContains(" • init")))),
+            App(SetShowGitBlame(true)),
+            WaitForAppMessage,
+            Expect(AppGridContains("// mod foo;    Not committed yet")),
+            App(OpenGitBlamePanel),
+            Expect(CurrentComponentTitle("Git blame: src/main.rs")),
+            Expect(AppGridContains(" │ // mod foo;")),
+        ])
+    })
+}
+
+#[test]
+fn diff_selection_against_clipboard() -> anyhow::Result<()> {
+    execute_test(|s| {
+        Box::new([
+            App(OpenFile(s.main_rs())),
+            Editor(SetContent("fn a() { x }\nfn b() { y }".to_string())),
+            Editor(SetSelectionMode(IfCurrentNotFound::LookForward, Line)),
+            Editor(Copy {
+                use_system_clipboard: false,
+            }),
+            Editor(MoveSelection(Next)),
+            App(OpenClipboardDiffView {
+                use_system_clipboard: false,
+            }),
+            Expect(CurrentComponentTitle("Clipboard ↔ Selection")),
+            Expect(CurrentComponentContent("fn b() { y }")),
+            App(HandleKeyEvent(key!("tab"))),
+            Expect(CurrentComponentContent("fn a() { x }")),
+        ])
+    })
+}
+
+#[test]
+fn diff_view_against_file_on_disk() -> anyhow::Result<()> {
+    execute_test(|s| {
+        Box::new([
+            App(TerminalDimensionChanged(Dimension {
+                width: 80,
+                height: 6,
+            })),
+            App(OpenFile(s.main_rs())),
+            Editor(EnterInsertMode(Direction::Start)),
+            Editor(Insert("// ".to_string())),
+            Editor(EnterNormalMode),
+            App(OpenDiffView(DiffBase::FileOnDisk)),
+            Expect(CurrentComponentTitle(
+                "src/main.rs (on disk) ↔ src/main.rs (unsaved)",
+            )),
+            Expect(AppGridContains("1│mod foo;")),
+            Expect(AppGridContains("1│// mod foo;")),
+            Expect(CurrentComponentContent(
+                "// mod foo;\n\nfn main() {\n    foo::foo();\n    println!(\"Hello, world!\");\n}",
+            )),
+            // Tab moves the cursor to the other side
+            App(HandleKeyEvent(key!("tab"))),
+            Expect(CurrentComponentContent(
+                "mod foo;\n\nfn main() {\n    foo::foo();\n    println!(\"Hello, world!\");\n}",
+            )),
+        ])
+    })
+}
+
+#[test]
+fn reload_files_changed_on_disk() -> anyhow::Result<()> {
+    execute_test(|s| {
+        let notes = s.new_file("notes.txt", "foo\n");
+        // The modification time is set explicitly, because the test might be faster than
+        // the resolution of the modification time
+        let change_on_disk = |content: &'static str, modified_seconds: u64| {
+            let notes = notes.clone();
+            AppLater(Box::new(move || {
+                std::fs::write(&notes, content).unwrap();
+                std::fs::File::options()
+                    .write(true)
+                    .open(&notes)
+                    .unwrap()
+                    .set_modified(
+                        std::time::SystemTime::UNIX_EPOCH
+                            + std::time::Duration::from_secs(modified_seconds),
+                    )
+                    .unwrap();
+                HandleFileChangedOnDisk(notes.clone())
+            }))
+        };
+        Box::new([
+            App(OpenFile(notes.clone())),
+            change_on_disk("bar\n", 1),
+            Expect(CurrentComponentContent("bar\n")),
+            // The buffer is not reloaded when it is saved by itself
+            Editor(EnterInsertMode(Direction::Start)),
+            Editor(Insert("// ".to_string())),
+            Editor(Save),
+            App(HandleFileChangedOnDisk(notes.clone())),
+            Expect(CurrentComponentContent("// bar\n")),
+            // A conflict prompt is shown if there are unsaved changes
+            Editor(Insert("x".to_string())),
+            Editor(EnterNormalMode),
+            change_on_disk("baz\n", 2),
+            Expect(CurrentComponentTitle("notes.txt is changed on disk")),
+            App(HandleKeyEvents(keys!("d i f f enter").to_vec())),
+            Expect(CurrentComponentTitle(
+                "notes.txt (on disk) ↔ notes.txt (unsaved)",
+            )),
+            App(ReloadFile(notes.clone())),
+            App(OpenFile(notes.clone())),
+            Expect(CurrentComponentContent("baz\n")),
+        ])
+    })
+}
+
+#[test]
+fn restore_unsaved_changes_of_previous_session() -> anyhow::Result<()> {
+    execute_test(|s| {
+        let directory = s.new_path("recovery");
+        let notes = s.new_file("notes.txt", "saved\n");
+        Recovery::new(directory.clone())
+            .persist(&notes, "unsaved\n")
+            .unwrap();
+        let leftovers = {
+            let directory = directory.clone();
+            move || Recovery::new(directory.clone()).leftovers().unwrap()
+        };
+        Box::new([
+            App(SetRecovery(Recovery::new(directory.clone()))),
+            App(OpenRecoveryPrompt),
+            App(HandleKeyEvents(keys!("n o t e s enter").to_vec())),
+            Expect(CurrentComponentPath(Some(notes.clone()))),
+            Expect(CurrentComponentContent("unsaved\n")),
+            Expect(FileContent(notes.clone(), "saved\n".to_string())),
+            // The restored changes are persisted again until they are saved
+            App(PersistUnsavedChanges),
+            ExpectCustom(Box::new({
+                let leftovers = leftovers.clone();
+                move || assert_eq!(leftovers()[0].content, "unsaved\n")
+            })),
+            Editor(Save),
+            ExpectCustom(Box::new(move || assert!(leftovers().is_empty()))),
+        ])
+    })
+}
+
+#[test]
+fn restore_session_of_working_directory() -> anyhow::Result<()> {
+    execute_test(|s| {
+        let directory = s.new_path("sessions");
+        let saved_session = move || {
+            let entry = std::fs::read_dir(&directory)
+                .unwrap()
+                .next()
+                .unwrap()
+                .unwrap();
+            let content = std::fs::read_to_string(entry.path()).unwrap();
+            serde_json::from_str::<Session>(&content).unwrap()
+        };
+        Box::new([
+            App(SetSessionStore(SessionStore::new(s.new_path("sessions")))),
+            App(OpenFile(s.main_rs())),
+            App(OpenFile(s.foo_rs())),
+            Editor(MatchLiteral("b: ()".to_string())),
+            Expect(EditorCursorPosition(Position::new(2, 4))),
+            App(PushPromptHistory {
+                key: PromptHistoryKey::Search(Scope::Local),
+                line: "foo".to_string(),
+            }),
+            App(SaveSession),
+            ExpectCustom(Box::new({
+                let foo_rs = s.foo_rs();
+                move || {
+                    let session = saved_session();
+                    assert_eq!(session.files.len(), 2);
+                    assert_eq!(session.current_file.as_ref(), Some(foo_rs.to_path_buf()));
+                    assert_eq!(session.search_history, ["foo"]);
+                }
+            })),
+            App(CloseCurrentWindow),
+            App(CloseCurrentWindow),
+            Expect(OpenedFilesCount(0)),
+            App(RestoreSession),
+            Expect(OpenedFilesCount(2)),
+            Expect(CurrentComponentPath(Some(s.foo_rs()))),
+            Expect(EditorCursorPosition(Position::new(2, 4))),
+        ])
+    })
+}
+
 #[test]
 pub(crate) fn non_git_ignored_files() -> Result<(), anyhow::Error> {
     execute_test(|s| {
@@ -1060,6 +1353,7 @@ fn esc_global_quickfix_mode() -> Result<(), anyhow::Error> {
                 show_config_after_enter: false,
                 if_current_not_found: IfCurrentNotFound::LookForward,
             }),
+            WaitForAppMessage,
             Expect(CurrentGlobalMode(Some(GlobalMode::QuickfixListItem))),
             Expect(Quickfixes(Box::new([
                 QuickfixListItem::new(
@@ -1112,6 +1406,7 @@ fn local_lsp_references() -> anyhow::Result<()> {
                 crate::lsp::process::ResponseContext {
                     scope: Some(Scope::Local),
                     description: None,
+                    hierarchy: None,
                 },
                 [
                     Location {
@@ -1135,25 +1430,28 @@ fn local_lsp_references() -> anyhow::Result<()> {
 fn global_diagnostics() -> Result<(), anyhow::Error> {
     execute_test(|s| {
         let publish_diagnostics = |path: CanonicalizedPath| {
-            LspNotification::PublishDiagnostics(lsp_types::PublishDiagnosticsParams {
-                uri: path.to_url().unwrap(),
-                diagnostics: [lsp_types::Diagnostic {
-                    range: lsp_types::Range::new(
-                        lsp_types::Position {
-                            line: 0,
-                            character: 0,
-                        },
-                        lsp_types::Position {
-                            line: 0,
-                            character: 3,
-                        },
-                    ),
-                    message: "To err is normal, but to err again is not.".to_string(),
-                    ..Default::default()
-                }]
-                .to_vec(),
-                version: None,
-            })
+            LspNotification::PublishDiagnostics(
+                "rust-analyzer".to_string(),
+                lsp_types::PublishDiagnosticsParams {
+                    uri: path.to_url().unwrap(),
+                    diagnostics: [lsp_types::Diagnostic {
+                        range: lsp_types::Range::new(
+                            lsp_types::Position {
+                                line: 0,
+                                character: 0,
+                            },
+                            lsp_types::Position {
+                                line: 0,
+                                character: 3,
+                            },
+                        ),
+                        message: "To err is normal, but to err again is not.".to_string(),
+                        ..Default::default()
+                    }]
+                    .to_vec(),
+                    version: None,
+                },
+            )
         };
         Box::new([
             App(OpenFile(s.main_rs())),
@@ -1294,6 +1592,127 @@ fn global_search_replace_naming_convention_agnostic() -> Result<(), anyhow::Erro
     })
 }
 
+#[test]
+fn global_replace_preview_exclude_and_apply() -> Result<(), anyhow::Error> {
+    execute_test(|s| {
+        let new_dispatch = |update: LocalSearchConfigUpdate| -> Dispatch {
+            UpdateLocalSearchConfig {
+                update,
+                scope: Scope::Global,
+                show_config_after_enter: false,
+                if_current_not_found: IfCurrentNotFound::LookForward,
+            }
+        };
+        Box::new([
+            App(OpenFile(s.foo_rs())),
+            Editor(SetContent("hello sun".to_string())),
+            App(OpenFile(s.main_rs())),
+            Editor(SetContent("hello world\nhello moon".to_string())),
+            App(SaveAll),
+            App(new_dispatch(LocalSearchConfigUpdate::Replacement(
+                "bye".to_string(),
+            ))),
+            App(new_dispatch(LocalSearchConfigUpdate::Search(
+                "hello".to_string(),
+            ))),
+            WaitForAppMessage,
+            App(ShowGlobalReplacePreview),
+            WaitForAppMessage,
+            // Expect the info shows the line before and after the replacement
+            Expect(QuickfixListInfo("hello sun\nbye sun")),
+            // Exclude the change in foo.rs
+            App(ExcludeCurrentQuickfixListItem),
+            // Expect the excluded item is still listed and focused
+            Expect(QuickfixListContent(
+                format!(
+                    "
+■┬ {}
+ └─ 1:1  hello sun
+
+■┬ {}
+ ├─ 1:1  hello world
+ └─ 2:1  hello moon",
+                    s.foo_rs().display_absolute(),
+                    s.main_rs().display_absolute()
+                )
+                .trim()
+                .to_string(),
+            )),
+            Expect(QuickfixListInfo("hello sun\nbye sun")),
+            App(ReplaceQuickfixListItems),
+            Expect(FileContent(s.main_rs(), "bye world\nbye moon".to_string())),
+            // Expect the excluded change is not applied
+            Expect(FileContent(s.foo_rs(), "hello sun".to_string())),
+        ])
+    })
+}
+
+#[test]
+fn global_replace_preview_exclude_again_to_include() -> Result<(), anyhow::Error> {
+    execute_test(|s| {
+        let new_dispatch = |update: LocalSearchConfigUpdate| -> Dispatch {
+            UpdateLocalSearchConfig {
+                update,
+                scope: Scope::Global,
+                show_config_after_enter: false,
+                if_current_not_found: IfCurrentNotFound::LookForward,
+            }
+        };
+        Box::new([
+            App(OpenFile(s.foo_rs())),
+            Editor(SetContent("hello sun".to_string())),
+            App(OpenFile(s.main_rs())),
+            Editor(SetContent("hello world".to_string())),
+            App(SaveAll),
+            App(new_dispatch(LocalSearchConfigUpdate::Replacement(
+                "bye".to_string(),
+            ))),
+            App(new_dispatch(LocalSearchConfigUpdate::Search(
+                "hello".to_string(),
+            ))),
+            WaitForAppMessage,
+            App(ShowGlobalReplacePreview),
+            WaitForAppMessage,
+            // The same key toggles the exclusion of the change in foo.rs
+            App(ExcludeCurrentQuickfixListItem),
+            App(ExcludeCurrentQuickfixListItem),
+            App(ReplaceQuickfixListItems),
+            Expect(FileContent(s.main_rs(), "bye world".to_string())),
+            Expect(FileContent(s.foo_rs(), "bye sun".to_string())),
+        ])
+    })
+}
+
+#[test]
+fn update_references_after_moving_file() -> Result<(), anyhow::Error> {
+    execute_test(|s| {
+        let bar_rs = s.main_rs().to_path_buf().with_file_name("bar.rs");
+        Box::new([
+            App(OpenFile(s.main_rs())),
+            Editor(SetContent(
+                "mod foo;\nuse foo::Foo;\nfn food() {}\n".to_string(),
+            )),
+            App(SaveAll),
+            App(MoveFile {
+                from: s.foo_rs(),
+                to: bar_rs,
+            }),
+            Expect(AppGridContains(r#""foo" with "bar""#)),
+            App(PreviewReferenceUpdate {
+                reference: "foo".to_string(),
+                replacement: "bar".to_string(),
+            }),
+            WaitForAppMessage,
+            App(ReplaceQuickfixListItems),
+            // Only the whole words that match case-sensitively are replaced
+            Expect(FileContent(
+                s.main_rs(),
+                "mod bar;\nuse bar::Foo;\nfn food() {}\n".to_string(),
+            )),
+        ])
+    })
+}
+
 #[test]
 fn quickfix_list() -> Result<(), anyhow::Error> {
     execute_test(|s| {
@@ -1329,6 +1748,7 @@ foo a // Line 10
             App(new_dispatch(LocalSearchConfigUpdate::Search(
                 "foo".to_string(),
             ))),
+            WaitForAppMessage,
             Expect(QuickfixListContent(
                 // Line 10 should be placed below Line 2 (sorted numerically, not lexicograhically)
                 format!(
@@ -1422,18 +1842,21 @@ fn diagnostic_info() -> Result<(), anyhow::Error> {
         Box::new([
             App(OpenFile(s.foo_rs())),
             App(Dispatch::HandleLspNotification(
-                LspNotification::PublishDiagnostics(lsp_types::PublishDiagnosticsParams {
-                    uri: Url::from_file_path(s.foo_rs()).unwrap(),
-                    diagnostics: [lsp_types::Diagnostic::new_simple(
-                        lsp_types::Range::new(
-                            lsp_types::Position::new(0, 1),
-                            lsp_types::Position::new(0, 2),
-                        ),
-                        "Hello world".to_string(),
-                    )]
-                    .to_vec(),
-                    version: None,
-                }),
+                LspNotification::PublishDiagnostics(
+                    "rust-analyzer".to_string(),
+                    lsp_types::PublishDiagnosticsParams {
+                        uri: Url::from_file_path(s.foo_rs()).unwrap(),
+                        diagnostics: [lsp_types::Diagnostic::new_simple(
+                            lsp_types::Range::new(
+                                lsp_types::Position::new(0, 1),
+                                lsp_types::Position::new(0, 2),
+                            ),
+                            "Hello world".to_string(),
+                        )]
+                        .to_vec(),
+                        version: None,
+                    },
+                ),
             )),
             Editor(SetSelectionMode(
                 IfCurrentNotFound::LookForward,
@@ -1444,11 +1867,14 @@ fn diagnostic_info() -> Result<(), anyhow::Error> {
             App(HandleKeyEvent(key!("esc"))),
             Expect(EditorInfoOpen(false)),
             App(Dispatch::HandleLspNotification(
-                LspNotification::PublishDiagnostics(lsp_types::PublishDiagnosticsParams {
-                    uri: Url::from_file_path(s.foo_rs()).unwrap(),
-                    diagnostics: Default::default(),
-                    version: None,
-                }),
+                LspNotification::PublishDiagnostics(
+                    "rust-analyzer".to_string(),
+                    lsp_types::PublishDiagnosticsParams {
+                        uri: Url::from_file_path(s.foo_rs()).unwrap(),
+                        diagnostics: Default::default(),
+                        version: None,
+                    },
+                ),
             )),
             Editor(MoveSelection(Right)),
             Expect(EditorInfoOpen(false)),
@@ -1501,14 +1927,17 @@ fn diagnostic_severity_decoration_precedence() -> Result<(), anyhow::Error> {
                 width: 80,
             })),
             App(Dispatch::HandleLspNotification(
-                LspNotification::PublishDiagnostics(lsp_types::PublishDiagnosticsParams {
-                    uri: Url::from_file_path(s.foo_rs()).unwrap(),
-                    diagnostics: diagnostics
-                        .into_iter()
-                        .map(|(start, end, severity)| diagnostic(start, end, severity))
-                        .collect_vec(),
-                    version: None,
-                }),
+                LspNotification::PublishDiagnostics(
+                    "rust-analyzer".to_string(),
+                    lsp_types::PublishDiagnosticsParams {
+                        uri: Url::from_file_path(s.foo_rs()).unwrap(),
+                        diagnostics: diagnostics
+                            .into_iter()
+                            .map(|(start, end, severity)| diagnostic(start, end, severity))
+                            .collect_vec(),
+                        version: None,
+                    },
+                ),
             )),
             ExpectMulti(
                 (0..1)
@@ -1529,6 +1958,55 @@ fn diagnostic_severity_decoration_precedence() -> Result<(), anyhow::Error> {
     })
 }
 
+#[test]
+fn selection_background_and_diagnostic_underline_are_composited() -> Result<(), anyhow::Error> {
+    execute_test(|s| {
+        let error_color = hex!("#fedbac");
+        let selection_background = hex!("#badbad");
+        let theme = {
+            let mut theme = Theme::default();
+            // The background of the diagnostic is left out, because diagnostics only set the
+            // underline and bold
+            theme.diagnostic.error = Style::default()
+                .undercurl(error_color)
+                .background_color(hex!("#010101"));
+            theme.ui.primary_selection_background = selection_background;
+            theme.ui.primary_selection_anchor_background = selection_background;
+            theme
+        };
+        Box::new([
+            App(OpenFile(s.foo_rs())),
+            App(SetTheme(theme.clone())),
+            Editor(SetContent("who lives".to_string())),
+            App(TerminalDimensionChanged(Dimension {
+                height: 3,
+                width: 80,
+            })),
+            App(Dispatch::HandleLspNotification(
+                LspNotification::PublishDiagnostics(
+                    "rust-analyzer".to_string(),
+                    lsp_types::PublishDiagnosticsParams {
+                        uri: Url::from_file_path(s.foo_rs()).unwrap(),
+                        diagnostics: [lsp_types::Diagnostic {
+                            range: lsp_types::Range::new(
+                                lsp_types::Position::new(0, 0),
+                                lsp_types::Position::new(0, 3),
+                            ),
+                            severity: Some(lsp_types::DiagnosticSeverity::ERROR),
+                            ..Default::default()
+                        }]
+                        .to_vec(),
+                        version: None,
+                    },
+                ),
+            )),
+            Editor(MatchLiteral("who".to_string())),
+            Expect(GridCellLine(1, 3, error_color)),
+            Expect(GridCellBackground(1, 3, selection_background)),
+        ])
+    })
+}
+
 #[test]
 fn same_range_diagnostics_should_be_merged() -> Result<(), anyhow::Error> {
     execute_test(|s| {
@@ -1545,12 +2023,15 @@ fn same_range_diagnostics_should_be_merged() -> Result<(), anyhow::Error> {
         Box::new([
             App(OpenFile(s.foo_rs())),
             App(Dispatch::HandleLspNotification(
-                LspNotification::PublishDiagnostics(lsp_types::PublishDiagnosticsParams {
-                    uri: Url::from_file_path(s.foo_rs()).unwrap(),
-                    diagnostics: [diagnostic("foo"), diagnostic("bar"), diagnostic("spam")]
-                        .to_vec(),
-                    version: None,
-                }),
+                LspNotification::PublishDiagnostics(
+                    "rust-analyzer".to_string(),
+                    lsp_types::PublishDiagnosticsParams {
+                        uri: Url::from_file_path(s.foo_rs()).unwrap(),
+                        diagnostics: [diagnostic("foo"), diagnostic("bar"), diagnostic("spam")]
+                            .to_vec(),
+                        version: None,
+                    },
+                ),
             )),
             Editor(SetSelectionMode(
                 IfCurrentNotFound::LookForward,
@@ -1586,6 +2067,7 @@ fn code_action() -> anyhow::Result<()> {
                 resource_operations: Vec::new(),
             }),
             command: None,
+            server: None,
         };
         Box::new([
             App(OpenFile(s.main_rs())),
@@ -1637,6 +2119,7 @@ fn cycle_window() -> anyhow::Result<()> {
             detail: None,
             insert_text: None,
             completion_item: Default::default(),
+            server: None,
         };
 
         execute_test(|s| {
@@ -1694,6 +2177,7 @@ fn esc_in_normal_mode_in_suggestive_editor_should_close_all_other_windows() -> a
             detail: None,
             insert_text: None,
             completion_item: Default::default(),
+            server: None,
         };
         execute_test(|s| {
             Box::new([
@@ -1739,6 +2223,7 @@ fn saving_in_insert_mode_in_suggestive_editor_should_close_all_other_windows() -
             detail: None,
             insert_text: None,
             completion_item: Default::default(),
+            server: None,
         };
         execute_test(|s| {
             Box::new([
@@ -1943,13 +2428,270 @@ fn open_search_prompt_in_file_explorer() -> anyhow::Result<()> {
 }
 
 #[test]
-fn global_search_should_not_using_empty_pattern() -> anyhow::Result<()> {
-    execute_test(|_| {
+fn incremental_local_search_with_smart_case() -> anyhow::Result<()> {
+    execute_test(|s| {
         Box::new([
-            App(UpdateLocalSearchConfig {
-                update: LocalSearchConfigUpdate::Search("".to_string()),
-                scope: Scope::Global,
-                show_config_after_enter: true,
+            App(OpenFile(s.main_rs())),
+            Editor(SetContent("foo bar Bar".to_string())),
+            App(OpenSearchPrompt {
+                scope: Scope::Local,
+                if_current_not_found: IfCurrentNotFound::LookForward,
+            }),
+            App(HandleKeyEvents(keys!("B a r").to_vec())),
+            // Cancelling the prompt should keep the matches found while typing
+            App(HandleKeyEvents(keys!("esc esc").to_vec())),
+            Expect(CurrentPath(s.main_rs())),
+            Expect(CurrentSelectedTexts(&["Bar"])),
+        ])
+    })
+}
+
+#[test]
+fn go_to_line_prompt_previews_the_line() -> anyhow::Result<()> {
+    execute_test(|s| {
+        Box::new([
+            App(TerminalDimensionChanged(Dimension {
+                width: 50,
+                height: 10,
+            })),
+            App(OpenFile(s.main_rs())),
+            Editor(SetContent((1..=20).map(|n| n.to_string()).join("\n"))),
+            App(OpenGoToLinePrompt),
+            App(HandleKeyEvents(keys!("1 8").to_vec())),
+            Expect(AppGridContains("18│18")),
+            // Cancelling the prompt restores the original view
+            App(HandleKeyEvents(keys!("esc esc").to_vec())),
+            Expect(CurrentPath(s.main_rs())),
+            Expect(Not(Box::new(AppGridContains("18│18")))),
+            App(OpenGoToLinePrompt),
+            App(HandleKeyEvents(keys!("1 8 enter").to_vec())),
+            Expect(CurrentSelectedTexts(&["18"])),
+            App(OpenGoToLinePrompt),
+            App(HandleKeyEvents(keys!("- 3 enter").to_vec())),
+            Expect(CurrentSelectedTexts(&["15"])),
+            App(OpenGoToLinePrompt),
+            Editor(Insert("+2".to_string())),
+            App(HandleKeyEvent(key!("enter"))),
+            Expect(CurrentSelectedTexts(&["17"])),
+        ])
+    })
+}
+
+#[test]
+fn ime_preedit_and_commit() -> anyhow::Result<()> {
+    execute_test(|s| {
+        Box::new([
+            App(OpenFile(s.main_rs())),
+            Editor(SetContent("ab".to_string())),
+            Editor(MoveToLineEnd),
+            // The preedit is rendered at the cursor without modifying the buffer
+            App(HandleImePreeditEvent("ni".to_string())),
+            Expect(AppGridContains("1│abni")),
+            Expect(CurrentComponentContent("ab")),
+            App(HandleImeCommitEvent("你好".to_string())),
+            Expect(Not(Box::new(AppGridContains("ni")))),
+            Expect(CurrentComponentContent("ab你好")),
+            App(HandleImeCommitEvent("世界".to_string())),
+            Expect(CurrentComponentContent("ab你好世界")),
+            // Each committed text is undone at once
+            Editor(EnterNormalMode),
+            Editor(Undo),
+            Expect(CurrentComponentContent("ab你好")),
+        ])
+    })
+}
+
+#[test]
+fn right_to_left_text_is_rendered_in_visual_order() -> anyhow::Result<()> {
+    execute_test(|s| {
+        Box::new([
+            App(OpenFile(s.main_rs())),
+            Editor(SetContent("a שלום b".to_string())),
+            Expect(AppGridContains("1│a םולש b")),
+            // Selections still operate on the logical order
+            Editor(MatchLiteral("של".to_string())),
+            Expect(CurrentSelectedTexts(&["של"])),
+            App(SetLogicalOrderRendering(true)),
+            Expect(AppGridContains("1│a שלום b")),
+        ])
+    })
+}
+
+#[test]
+fn file_picker_ranks_frequently_opened_files_first() -> anyhow::Result<()> {
+    execute_test(|s| {
+        Box::new([
+            App(OpenFile(s.main_rs())),
+            App(OpenFile(s.foo_rs())),
+            App(OpenFile(s.main_rs())),
+            App(OpenFile(s.gitignore())),
+            App(OpenFilePicker(FilePickerKind::NonGitIgnored)),
+            WaitForAppMessage,
+            // The highlighted file is previewed
+            Expect(DropdownInfosCount(1)),
+            App(HandleKeyEvent(key!("enter"))),
+            Expect(CurrentPath(s.main_rs())),
+        ])
+    })
+}
+
+#[test]
+fn file_picker_keeps_the_filter_typed_before_the_files_are_found() -> anyhow::Result<()> {
+    execute_test(|s| {
+        Box::new([
+            App(OpenFile(s.main_rs())),
+            App(OpenFilePicker(FilePickerKind::NonGitIgnored)),
+            App(HandleKeyEvents(keys!("f o o").to_vec())),
+            WaitForAppMessage,
+            App(HandleKeyEvent(key!("enter"))),
+            Expect(CurrentPath(s.foo_rs())),
+        ])
+    })
+}
+
+#[test]
+fn buffer_list_close_and_save() -> anyhow::Result<()> {
+    execute_test(|s| {
+        Box::new([
+            App(OpenFile(s.main_rs())),
+            App(OpenFile(s.foo_rs())),
+            Editor(SetContent("// hello\n".to_string())),
+            App(OpenFilePicker(FilePickerKind::Opened)),
+            App(HandleKeyEvents(keys!("f o o alt+s").to_vec())),
+            Expect(FileContent(s.foo_rs(), "// hello\n".to_string())),
+            // The buffer list is reopened after each action
+            App(HandleKeyEvents(keys!("m a i n alt+x").to_vec())),
+            Expect(OpenedFilesCount(1)),
+            // Recently closed files are listed as well
+            App(HandleKeyEvents(keys!("m a i n enter").to_vec())),
+            Expect(CurrentPath(s.main_rs())),
+            Expect(OpenedFilesCount(2)),
+        ])
+    })
+}
+
+#[test]
+fn buffer_list_does_not_close_buffer_with_unsaved_changes() -> anyhow::Result<()> {
+    execute_test(|s| {
+        Box::new([
+            App(OpenFile(s.main_rs())),
+            App(OpenFile(s.foo_rs())),
+            Editor(SetContent("// unsaved\n".to_string())),
+            App(OpenFilePicker(FilePickerKind::Opened)),
+            App(HandleKeyEvents(keys!("f o o alt+x").to_vec())),
+            Expect(AppGridContains("unsaved changes")),
+            Expect(OpenedFilesCount(2)),
+            Expect(FileContent(s.foo_rs(), "// unsaved\n".to_string())),
+        ])
+    })
+}
+
+#[test]
+fn write_selections_to_file_and_insert_file_below_cursor() -> anyhow::Result<()> {
+    execute_test(|s| {
+        let path = s.new_path("selections.txt");
+        Box::new([
+            App(OpenFile(s.main_rs())),
+            Editor(SetContent("foo bar\nspam".to_string())),
+            Editor(MatchLiteral("foo".to_string())),
+            App(WriteSelections {
+                path: path.clone(),
+                append: false,
+            }),
+            WaitForAppMessage,
+            Editor(MatchLiteral("bar".to_string())),
+            App(WriteSelections {
+                path: path.clone(),
+                append: true,
+            }),
+            WaitForAppMessage,
+            ExpectCustom(Box::new({
+                let path = path.clone();
+                move || assert_eq!(std::fs::read_to_string(&path).unwrap(), "foo\nbar\n")
+            })),
+            Editor(MatchLiteral("spam".to_string())),
+            App(InsertBelowCursor(path.display().to_string())),
+            WaitForAppMessage,
+            Expect(CurrentComponentContent("foo bar\nspam\nfoo\nbar")),
+            Expect(CurrentSelectedTexts(&["foo\nbar"])),
+            Editor(MatchLiteral("spam".to_string())),
+            App(InsertBelowCursor("!echo hello".to_string())),
+            WaitForAppMessage,
+            Expect(CurrentComponentContent("foo bar\nspam\nhello\nfoo\nbar")),
+        ])
+    })
+}
+
+#[test]
+fn build_errors_are_listed_in_the_quickfix_list() -> anyhow::Result<()> {
+    execute_test(|s| {
+        Box::new([
+            App(OpenFile(s.foo_rs())),
+            // The location of a file that does not exist is skipped
+            App(Build(
+                "echo 'src/main.rs:4:5: error: oops
+missing.rs:1:1: warning: gone' >&2; exit 1"
+                    .to_string(),
+            )),
+            // Each line of the output, and then the exit
+            WaitForAppMessage,
+            WaitForAppMessage,
+            WaitForAppMessage,
+            Expect(Quickfixes(Box::new([QuickfixListItem::new(
+                Location {
+                    path: s.main_rs(),
+                    range: Position::new(3, 4)..Position::new(3, 4),
+                },
+                Some(Info::new(
+                    "Task build".to_string(),
+                    "error: oops".to_string(),
+                )),
+            )]))),
+            Expect(CurrentPath(s.main_rs())),
+            Expect(EditorCursorPosition(Position::new(3, 4))),
+        ])
+    })
+}
+
+#[test]
+fn shell_tasks_stream_their_output_beside_and_can_be_rerun() -> anyhow::Result<()> {
+    execute_test(|s| {
+        Box::new([
+            s.set_config("[tasks]\ngreet = \"echo hello; echo world\""),
+            App(OpenFile(s.main_rs())),
+            App(OpenShellTaskPrompt),
+            App(HandleKeyEvent(key!("enter"))),
+            WaitForAppMessage,
+            Expect(AppGridContains("1│hello")),
+            Expect(Not(Box::new(AppGridContains("world")))),
+            WaitForAppMessage,
+            WaitForAppMessage,
+            Expect(ComponentsOrder(vec![
+                ComponentKind::SuggestiveEditor,
+                ComponentKind::TaskOutput,
+            ])),
+            Expect(AppGridContains("[Task greet] exited with 0")),
+            Expect(AppGridContains("2│world")),
+            // The file stays focused
+            Expect(CurrentComponentPath(Some(s.main_rs()))),
+            App(RerunShellTask),
+            Expect(Not(Box::new(AppGridContains("exited")))),
+            WaitForAppMessage,
+            WaitForAppMessage,
+            WaitForAppMessage,
+            Expect(AppGridContains("[Task greet] exited with 0")),
+        ])
+    })
+}
+
+#[test]
+fn global_search_should_not_using_empty_pattern() -> anyhow::Result<()> {
+    execute_test(|_| {
+        Box::new([
+            App(UpdateLocalSearchConfig {
+                update: LocalSearchConfigUpdate::Search("".to_string()),
+                scope: Scope::Global,
+                show_config_after_enter: true,
                 if_current_not_found: IfCurrentNotFound::LookForward,
             }),
             Expect(ExpectKind::Quickfixes(Box::new([]))),
@@ -1957,6 +2699,88 @@ fn global_search_should_not_using_empty_pattern() -> anyhow::Result<()> {
     })
 }
 
+#[test]
+fn dropping_files_onto_terminal_opens_them() -> anyhow::Result<()> {
+    execute_test(|s| {
+        Box::new([
+            App(OpenFile(s.main_rs())),
+            App(HandlePasteEvent(format!(
+                "'{}'",
+                s.foo_rs().display_absolute()
+            ))),
+            App(HandleKeyEvent(key!("y"))),
+            Expect(CurrentPath(s.foo_rs())),
+            // Answering no pastes the path as usual
+            Editor(SetContent("".to_string())),
+            App(HandlePasteEvent(s.main_rs().display_absolute())),
+            App(HandleKeyEvent(key!("n"))),
+            Expect(CurrentPath(s.foo_rs())),
+            Expect(FileContent(s.foo_rs(), s.main_rs().display_absolute())),
+            // Paths are inserted as usual in insert mode
+            Editor(SetContent("".to_string())),
+            Editor(EnterInsertMode(Direction::Start)),
+            App(HandlePasteEvent(s.main_rs().display_absolute())),
+            Expect(CurrentPath(s.foo_rs())),
+            Expect(FileContent(s.foo_rs(), s.main_rs().display_absolute())),
+        ])
+    })
+}
+
+#[test]
+fn huge_paste_in_prompt_offers_scratch_buffer() -> anyhow::Result<()> {
+    execute_test(|s| {
+        let content: &'static str = "hello\n"
+            .repeat(crate::file_drop::HUGE_PASTE_THRESHOLD)
+            .leak();
+        Box::new([
+            App(OpenFile(s.main_rs())),
+            App(OpenSearchPrompt {
+                scope: Scope::Local,
+                if_current_not_found: IfCurrentNotFound::LookForward,
+            }),
+            App(HandlePasteEvent(content.to_string())),
+            App(HandleKeyEvent(key!("y"))),
+            Expect(CurrentComponentTitle("[Scratch]")),
+            Expect(CurrentComponentContent(content)),
+        ])
+    })
+}
+
+#[test]
+fn bracketed_paste_is_a_single_edit() -> anyhow::Result<()> {
+    execute_test(|s| {
+        Box::new([
+            App(OpenFile(s.main_rs())),
+            Editor(SetContent("fn main() {\n}".to_string())),
+            Editor(MatchLiteral("{".to_string())),
+            Editor(EnterInsertMode(Direction::End)),
+            // Terminals send the line breaks of a paste as carriage returns
+            App(HandlePasteEvent("\r    (x\r    {".to_string())),
+            // Neither the auto pairs nor the auto indentation apply
+            Expect(CurrentComponentContent("fn main() {\n    (x\n    {\n}")),
+            Editor(EnterNormalMode),
+            Editor(Undo),
+            Expect(CurrentComponentContent("fn main() {\n}")),
+        ])
+    })
+}
+
+#[test]
+fn large_paste_is_applied_after_a_frame() -> anyhow::Result<()> {
+    execute_test(|s| {
+        let notes = s.new_file("notes.txt", "");
+        let content = "x".repeat(LARGE_PASTE_THRESHOLD + 1);
+        Box::new([
+            App(OpenFile(notes.clone())),
+            App(HandlePasteEvent(content.clone())),
+            Expect(CurrentComponentContent("")),
+            WaitForAppMessage,
+            Editor(Save),
+            Expect(FileContent(notes, content)),
+        ])
+    })
+}
+
 #[test]
 fn workspace_edit() -> anyhow::Result<()> {
     execute_test(|s| {
@@ -1965,21 +2789,93 @@ fn workspace_edit() -> anyhow::Result<()> {
             Editor(SetContent("who lives in a pineapple".to_string())),
             Editor(MatchLiteral("pineapple".to_string())),
             Expect(CurrentSelectedTexts(&["pineapple"])),
-            App(Dispatch::ApplyWorkspaceEdit(WorkspaceEdit {
-                edits: [TextDocumentEdit {
-                    path: s.main_rs(),
-                    edits: [PositionalEdit {
-                        range: Position::new(0, 0)..Position::new(0, 0),
-                        new_text: "hello ".to_string(),
+            App(Dispatch::ApplyWorkspaceEdit {
+                workspace_edit: WorkspaceEdit {
+                    edits: [TextDocumentEdit {
+                        path: s.main_rs(),
+                        edits: [PositionalEdit {
+                            range: Position::new(0, 0)..Position::new(0, 0),
+                            new_text: "hello ".to_string(),
+                        }]
+                        .to_vec(),
                     }]
                     .to_vec(),
-                }]
-                .to_vec(),
-                resource_operations: Vec::new(),
-            })),
+                    resource_operations: Vec::new(),
+                },
+                description: "Code action \"Greet\"".to_string(),
+            }),
             Expect(CurrentComponentContent("hello who lives in a pineapple")),
             // Expect the selection is still "pineapple"
             Expect(CurrentSelectedTexts(&["pineapple"])),
+            // The refactor log jumps to the inserted text
+            App(ReviewRefactorLog),
+            Expect(CurrentSelectedTexts(&["hello "])),
+        ])
+    })
+}
+
+#[test]
+fn review_large_workspace_edit() -> anyhow::Result<()> {
+    execute_test(|s| {
+        let [a, b] = ["a.txt", "b.txt"].map(|name| s.new_file(name, "old\n"));
+        let edit = |path: &CanonicalizedPath| TextDocumentEdit {
+            path: path.clone(),
+            edits: [PositionalEdit {
+                range: Position::new(0, 0)..Position::new(0, 3),
+                new_text: "new".to_string(),
+            }]
+            .to_vec(),
+        };
+        Box::new([
+            s.set_config("workspace-edit-review-threshold = 1"),
+            App(ApplyWorkspaceEdit {
+                workspace_edit: WorkspaceEdit {
+                    edits: [edit(&a), edit(&b)].to_vec(),
+                    resource_operations: Vec::new(),
+                },
+                description: "Rename".to_string(),
+            }),
+            Expect(CurrentComponentTitle("Rename (1/2): a.txt")),
+            Expect(AppGridContains("a.txt (current) ↔ a.txt (edited)")),
+            Expect(FileContent(a.clone(), "old\n".to_string())),
+            App(HandleKeyEvents(keys!("r e j e c t enter").to_vec())),
+            Expect(CurrentComponentTitle("Rename (2/2): b.txt")),
+            App(HandleKeyEvents(keys!("a c c e p t enter").to_vec())),
+            Expect(Not(Box::new(AppGridContains("(edited)")))),
+            Expect(FileContent(a, "old\n".to_string())),
+            Expect(FileContent(b, "new\n".to_string())),
+        ])
+    })
+}
+
+#[test]
+fn closing_the_workspace_edit_review_prompt_cancels_the_review() -> anyhow::Result<()> {
+    execute_test(|s| {
+        let [a, b] = ["a.txt", "b.txt"].map(|name| s.new_file(name, "old\n"));
+        let edit = |path: &CanonicalizedPath| TextDocumentEdit {
+            path: path.clone(),
+            edits: [PositionalEdit {
+                range: Position::new(0, 0)..Position::new(0, 3),
+                new_text: "new".to_string(),
+            }]
+            .to_vec(),
+        };
+        Box::new([
+            s.set_config("workspace-edit-review-threshold = 1"),
+            App(ApplyWorkspaceEdit {
+                workspace_edit: WorkspaceEdit {
+                    edits: [edit(&a), edit(&b)].to_vec(),
+                    resource_operations: Vec::new(),
+                },
+                description: "Rename".to_string(),
+            }),
+            App(HandleKeyEvents(keys!("a c c e p t enter").to_vec())),
+            Expect(CurrentComponentTitle("Rename (2/2): b.txt")),
+            App(HandleKeyEvents(keys!("esc esc").to_vec())),
+            Expect(Not(Box::new(AppGridContains("(edited)")))),
+            // None of the files are edited, including the accepted one
+            Expect(FileContent(a, "old\n".to_string())),
+            Expect(FileContent(b, "old\n".to_string())),
         ])
     })
 }
@@ -2011,6 +2907,59 @@ fn request_signature_help() -> anyhow::Result<()> {
     })
 }
 
+#[test]
+fn browse_call_hierarchy() -> anyhow::Result<()> {
+    execute_test(|s| {
+        let item = |name, path: CanonicalizedPath, line, column| {
+            HierarchyItem::new(
+                name,
+                Location {
+                    path,
+                    range: Position::new(line, column)..Position::new(line, column + name.len()),
+                },
+            )
+        };
+        let foo = item("foo", s.foo_rs(), 5, 14);
+        let main = item("main", s.main_rs(), 2, 3);
+        let hierarchy = |parent: Option<&HierarchyItem>, items: &[&HierarchyItem]| {
+            HandleLspNotification(LspNotification::Hierarchy(
+                HierarchyContext {
+                    kind: HierarchyKind::IncomingCalls,
+                    parent: parent.cloned(),
+                },
+                items.iter().map(|item| (*item).clone()).collect(),
+            ))
+        };
+        let children_requested = |item: &HierarchyItem| {
+            LspRequestSent(FromEditor::HierarchyChildren {
+                kind: HierarchyKind::IncomingCalls,
+                item: item.clone(),
+            })
+        };
+        Box::new([
+            App(OpenFile(s.foo_rs())),
+            App(hierarchy(None, &[&foo])),
+            Expect(CurrentComponentTitle("Incoming calls of foo")),
+            // The children of the roots are requested right away
+            Expect(children_requested(&foo)),
+            App(hierarchy(Some(&foo), &[&main])),
+            Expect(CurrentComponentContent(
+                "▾ foo  src/foo.rs:6\n  ▸ main  src/main.rs:3",
+            )),
+            Editor(MatchLiteral("main".to_string())),
+            App(HandleKeyEvent(key!("tab"))),
+            Expect(children_requested(&main)),
+            App(hierarchy(Some(&main), &[])),
+            Expect(CurrentComponentContent(
+                "▾ foo  src/foo.rs:6\n    main  src/main.rs:3",
+            )),
+            App(HandleKeyEvent(key!("enter"))),
+            Expect(CurrentPath(s.main_rs())),
+            Expect(CurrentSelectedTexts(&["main"])),
+        ])
+    })
+}
+
 #[serial]
 #[test]
 fn copy_paste_using_system_clipboard() -> Result<(), anyhow::Error> {
@@ -2104,3 +3053,781 @@ c1 c2 a1\nb1\nc1
         }
     })
 }
+
+#[test]
+fn status_line_word_count_and_diagnostic_counts() -> anyhow::Result<()> {
+    assert_eq!(
+        StatusLineComponent::parse_config("diagnostic-counts, word-count")?,
+        [
+            StatusLineComponent::DiagnosticCounts,
+            StatusLineComponent::WordCount
+        ]
+    );
+    assert!(StatusLineComponent::parse_config("mode,unknown").is_err());
+    execute_test_helper(
+        || Box::new(NullWriter),
+        false,
+        [
+            StatusLineComponent::DiagnosticCounts,
+            StatusLineComponent::WordCount,
+        ]
+        .to_vec(),
+        |s| {
+            let readme = s.new_file("README.md", "Hello world,\nthis is prose.\n");
+            let diagnostic = |severity| lsp_types::Diagnostic {
+                severity: Some(severity),
+                ..lsp_types::Diagnostic::new_simple(
+                    lsp_types::Range::new(
+                        lsp_types::Position::new(0, 0),
+                        lsp_types::Position::new(0, 3),
+                    ),
+                    "Hello world".to_string(),
+                )
+            };
+            Box::new([
+                App(OpenFile(s.main_rs())),
+                App(HandleLspNotification(LspNotification::PublishDiagnostics(
+                    "rust-analyzer".to_string(),
+                    lsp_types::PublishDiagnosticsParams {
+                        uri: s.main_rs().to_url().unwrap(),
+                        diagnostics: [
+                            diagnostic(lsp_types::DiagnosticSeverity::ERROR),
+                            diagnostic(lsp_types::DiagnosticSeverity::ERROR),
+                            diagnostic(lsp_types::DiagnosticSeverity::WARNING),
+                        ]
+                        .to_vec(),
+                        version: None,
+                    },
+                ))),
+                Expect(AppGridContains(" ✘ 2 ▲ 1")),
+                Expect(Not(Box::new(AppGridContains("words")))),
+                App(OpenFile(readme)),
+                Expect(AppGridContains(" 5 words, 1 min read")),
+                // The words are counted again once the content is changed
+                Editor(SetContent("One two three\n".to_string())),
+                Expect(AppGridContains(" 3 words, 1 min read")),
+            ])
+        },
+    )?;
+    Ok(())
+}
+
+#[test]
+fn merge_diagnostics_of_every_lsp_server() -> anyhow::Result<()> {
+    execute_test_helper(
+        || Box::new(NullWriter),
+        false,
+        [StatusLineComponent::DiagnosticCounts].to_vec(),
+        |s| {
+            let publish_diagnostics = |server: &str, severities: &[_]| {
+                LspNotification::PublishDiagnostics(
+                    server.to_string(),
+                    lsp_types::PublishDiagnosticsParams {
+                        uri: s.main_rs().to_url().unwrap(),
+                        diagnostics: severities
+                            .iter()
+                            .map(|severity| lsp_types::Diagnostic {
+                                severity: Some(*severity),
+                                ..lsp_types::Diagnostic::new_simple(
+                                    lsp_types::Range::new(
+                                        lsp_types::Position::new(0, 0),
+                                        lsp_types::Position::new(0, 3),
+                                    ),
+                                    "Hello world".to_string(),
+                                )
+                            })
+                            .collect(),
+                        version: None,
+                    },
+                )
+            };
+            Box::new([
+                App(OpenFile(s.main_rs())),
+                App(HandleLspNotification(publish_diagnostics(
+                    "rust-analyzer",
+                    &[
+                        lsp_types::DiagnosticSeverity::ERROR,
+                        lsp_types::DiagnosticSeverity::ERROR,
+                    ],
+                ))),
+                App(HandleLspNotification(publish_diagnostics(
+                    "typos-lsp",
+                    &[lsp_types::DiagnosticSeverity::WARNING],
+                ))),
+                Expect(AppGridContains(" ✘ 2 ▲ 1")),
+                // Only the diagnostics of the server that published are replaced
+                App(HandleLspNotification(publish_diagnostics(
+                    "rust-analyzer",
+                    &[lsp_types::DiagnosticSeverity::ERROR],
+                ))),
+                Expect(AppGridContains(" ✘ 1 ▲ 1")),
+                App(HandleLspNotification(publish_diagnostics("typos-lsp", &[]))),
+                Expect(AppGridContains(" ✘ 1")),
+                Expect(Not(Box::new(AppGridContains("▲")))),
+            ])
+        },
+    )?;
+    Ok(())
+}
+
+#[test]
+fn filter_and_mute_diagnostics() -> anyhow::Result<()> {
+    execute_test_helper(
+        || Box::new(NullWriter),
+        false,
+        [StatusLineComponent::DiagnosticCounts].to_vec(),
+        |s| {
+            let diagnostic = |severity, source: &str, code: Option<&str>| lsp_types::Diagnostic {
+                severity: Some(severity),
+                source: Some(source.to_string()),
+                code: code.map(|code| lsp_types::NumberOrString::String(code.to_string())),
+                ..lsp_types::Diagnostic::new_simple(
+                    lsp_types::Range::new(
+                        lsp_types::Position::new(0, 0),
+                        lsp_types::Position::new(0, 3),
+                    ),
+                    "Hello world".to_string(),
+                )
+            };
+            Box::new([
+                App(OpenFile(s.main_rs())),
+                App(HandleLspNotification(LspNotification::PublishDiagnostics(
+                    "rust-analyzer".to_string(),
+                    lsp_types::PublishDiagnosticsParams {
+                        uri: s.main_rs().to_url().unwrap(),
+                        diagnostics: [
+                            diagnostic(
+                                lsp_types::DiagnosticSeverity::WARNING,
+                                "clippy",
+                                Some("needless_return"),
+                            ),
+                            diagnostic(lsp_types::DiagnosticSeverity::ERROR, "rustc", None),
+                            diagnostic(lsp_types::DiagnosticSeverity::HINT, "cspell", None),
+                        ]
+                        .to_vec(),
+                        version: None,
+                    },
+                ))),
+                Expect(AppGridContains(" ✘ 1 ▲ 1 ✎ 1")),
+                App(SetMinimumDiagnosticSeverity(Severity::Warning)),
+                Expect(AppGridContains(" ✘ 1 ▲ 1")),
+                Expect(Not(Box::new(AppGridContains("✎")))),
+                App(MuteDiagnosticAtCursor),
+                Expect(Not(Box::new(AppGridContains("▲")))),
+                App(SetQuickfixList(
+                    crate::quickfix_list::QuickfixListType::Diagnostic(
+                        DiagnosticSeverityRange::All,
+                    ),
+                )),
+                Expect(AppGridContains("(2 hidden by the diagnostic filter)")),
+                App(UnmuteDiagnostics("needless_return".to_string())),
+                Expect(AppGridContains(" ✘ 1 ▲ 1")),
+            ])
+        },
+    )?;
+    Ok(())
+}
+
+#[test]
+fn status_line_file_segments() -> anyhow::Result<()> {
+    execute_test_helper(
+        || Box::new(NullWriter),
+        false,
+        [
+            StatusLineComponent::Modified,
+            StatusLineComponent::CursorPosition,
+            StatusLineComponent::SelectionCount,
+        ]
+        .to_vec(),
+        |s| {
+            Box::new([
+                App(OpenFile(s.main_rs())),
+                Expect(AppGridContains(" 1:1")),
+                Editor(MatchLiteral("println".to_string())),
+                Expect(AppGridContains(" 5:5")),
+                Expect(Not(Box::new(AppGridContains("[+]")))),
+                Expect(Not(Box::new(AppGridContains("selections")))),
+                Editor(Insert("x".to_string())),
+                Expect(AppGridContains(" [+] │ ")),
+            ])
+        },
+    )?;
+    Ok(())
+}
+
+#[test]
+fn reload_config() -> anyhow::Result<()> {
+    execute_test(|s| {
+        let path = s.new_path("config.toml");
+        // The file is not indented, so that its indentation is not detected
+        let notes = s.new_file("notes.txt", "foo\nbar\n");
+        Box::new([
+            s.set_config("tab-width = 2"),
+            App(OpenFile(notes)),
+            Editor(MatchLiteral("foo".to_string())),
+            Editor(Indent),
+            Expect(CurrentLine("  foo")),
+            Editor(Dedent),
+            AppLater(Box::new(move || {
+                std::fs::write(&path, "tab-width = 3").unwrap();
+                ReloadConfig
+            })),
+            Editor(Indent),
+            Expect(CurrentLine("   foo")),
+        ])
+    })
+}
+
+#[test]
+fn markdown_preview() -> anyhow::Result<()> {
+    execute_test(|s| {
+        let readme = s.new_file("README.md", "# Title\n\n| a | b |\n| - | - |\n| 1 | 2 |\n");
+        Box::new([
+            App(OpenFile(readme.clone())),
+            App(OpenMarkdownPreview),
+            Expect(ComponentsOrder(vec![
+                ComponentKind::SuggestiveEditor,
+                ComponentKind::MarkdownPreview,
+            ])),
+            // The markdown file stays focused
+            Expect(CurrentComponentPath(Some(readme.clone()))),
+            Expect(AppGridContains("1│Title")),
+            Expect(AppGridContains("3│a │ b")),
+            Expect(AppGridContains("4│──┼──")),
+            Editor(MatchLiteral("Title".to_string())),
+            Editor(EnterInsertMode(Direction::End)),
+            Editor(Insert("d".to_string())),
+            // The preview is refreshed once the file is not edited for a while
+            Expect(Not(Box::new(AppGridContains("1│Titled")))),
+            WaitForAppMessage,
+            Expect(AppGridContains("1│Titled")),
+        ])
+    })
+}
+
+#[test]
+fn spelling_suggestions() -> anyhow::Result<()> {
+    execute_test(|s| {
+        let dictionary = s.new_file("words", "hello\nhelp\nthe\nworld\n");
+        let notes = s.new_file("notes.txt", "The helo world\n");
+        Box::new([
+            s.set_config(&format!(
+                "spell-check = true\nspell-check-dictionary = {:?}",
+                dictionary.display_absolute()
+            )),
+            App(OpenFile(notes)),
+            Expect(GridCellStyleKey(
+                Position::new(1, 7),
+                Some(StyleKey::UiMisspelling),
+            )),
+            App(OpenSpellingSuggestionsPrompt),
+            Expect(CurrentComponentTitle("Spelling suggestions for \"helo\"")),
+            App(HandleKeyEvents(keys!("enter").to_vec())),
+            Expect(CurrentComponentContent("The hello world\n")),
+            Expect(CurrentSelectedTexts(&["hello"])),
+            App(OpenSpellingSuggestionsPrompt),
+            Expect(AppGridContains("No misspelled word")),
+        ])
+    })
+}
+
+#[test]
+fn whitespace_rendering_and_trimming() -> anyhow::Result<()> {
+    execute_test(|s| {
+        let notes = s.new_file("notes.txt", "a\tb c  \n");
+        Box::new([
+            s.set_config("whitespace = true\ntrim-trailing-whitespace = true"),
+            App(OpenFile(notes.clone())),
+            Expect(AppGridContains("a→")),
+            // The space between words is not marked
+            Expect(AppGridContains("b c··¬")),
+            Editor(Save),
+            Expect(FileContent(notes, "a\tb c\n".to_string())),
+            Editor(SetContent("x \ny\t\n".to_string())),
+            Editor(TrimTrailingWhitespace),
+            Expect(CurrentComponentContent("x\ny\n")),
+        ])
+    })
+}
+
+#[test]
+fn indent_guides() -> anyhow::Result<()> {
+    execute_test(|s| {
+        let notes = s.new_file("notes.txt", "a {\n    b\n\n    c\n}\n");
+        Box::new([
+            s.set_config("indent-guides = true"),
+            App(OpenFile(notes)),
+            Expect(AppGridContains("││   b")),
+            // The cursor is on the line that opens the block, so the guide of the block is active
+            Expect(GridCellStyleKey(
+                Position::new(2, 2),
+                Some(StyleKey::UiIndentGuideActive),
+            )),
+            // The blank line is padded up to its guide
+            Expect(GridCellStyleKey(
+                Position::new(3, 2),
+                Some(StyleKey::UiIndentGuideActive),
+            )),
+        ])
+    })
+}
+
+#[test]
+fn document_highlights() -> anyhow::Result<()> {
+    execute_test(|s| {
+        let notes = s.new_file("notes.txt", "let x = 1;\nx += x;\n");
+        let highlight = |line, column, is_write| DocumentHighlight {
+            range: Position::new(line, column)..Position::new(line, column + 1),
+            is_write,
+        };
+        Box::new([
+            s.set_config("document-highlight-delay = 1"),
+            App(OpenFile(notes.clone())),
+            App(Render),
+            WaitForAppMessage,
+            Expect(LspRequestSent(FromEditor::TextDocumentDocumentHighlight(
+                RequestParams {
+                    path: notes,
+                    position: Position::new(0, 0),
+                    context: Default::default(),
+                },
+            ))),
+            App(HandleLspNotification(LspNotification::DocumentHighlight(
+                [
+                    highlight(0, 4, true),
+                    highlight(1, 0, true),
+                    highlight(1, 5, false),
+                ]
+                .to_vec(),
+            ))),
+            Expect(GridCellStyleKey(
+                Position::new(1, 6),
+                Some(StyleKey::UiDocumentHighlightWrite),
+            )),
+            Expect(GridCellStyleKey(
+                Position::new(2, 2),
+                Some(StyleKey::UiDocumentHighlightWrite),
+            )),
+            Expect(GridCellStyleKey(
+                Position::new(2, 7),
+                Some(StyleKey::UiDocumentHighlightRead),
+            )),
+            // The highlights are cleared once the cursor moves
+            Editor(MatchLiteral("1".to_string())),
+            Expect(GridCellStyleKey(Position::new(2, 7), None)),
+        ])
+    })
+}
+
+#[test]
+fn cursor_line_and_column() -> anyhow::Result<()> {
+    execute_test(|s| {
+        let notes = s.new_file("notes.txt", "ab\ncd\n");
+        let cursor_line_background = hex!("#badbad");
+        let theme = {
+            let mut theme = Theme::default();
+            theme.ui.cursor_line_background = cursor_line_background;
+            theme
+        };
+        let background = theme.ui.background_color;
+        Box::new([
+            s.set_config("cursor-line = true\ncursor-column = true"),
+            App(OpenFile(notes)),
+            App(SetTheme(theme)),
+            Editor(MatchLiteral("a".to_string())),
+            Expect(GridCellBackground(1, 0, cursor_line_background)),
+            Expect(GridCellBackground(1, 10, cursor_line_background)),
+            Expect(GridCellBackground(2, 2, cursor_line_background)),
+            Expect(GridCellBackground(2, 3, background)),
+            // The selection is not overridden
+            Expect(Not(Box::new(GridCellBackground(
+                1,
+                2,
+                cursor_line_background,
+            )))),
+        ])
+    })
+}
+
+#[test]
+fn editorconfig_overrides_config() -> anyhow::Result<()> {
+    execute_test(|s| {
+        s.new_file(
+            ".editorconfig",
+            "[*.txt]\nindent_style = tab\ntrim_trailing_whitespace = true",
+        );
+        let notes = s.new_file("notes.txt", "foo\nbar\n");
+        Box::new([
+            App(OpenFile(notes.clone())),
+            Editor(MatchLiteral("foo".to_string())),
+            Editor(Indent),
+            Expect(CurrentLine("\tfoo")),
+            Editor(EnterInsertMode(Direction::End)),
+            Editor(Insert("  ".to_string())),
+            Editor(EnterNormalMode),
+            Editor(Save),
+            Expect(FileContent(notes, "\tfoo\nbar\n".to_string())),
+        ])
+    })
+}
+
+#[test]
+fn scroll_bound_windows_are_scrolled_together() -> anyhow::Result<()> {
+    execute_test(|s| {
+        let info = (1..=20).map(|line| format!("line {line}")).join("\n");
+        Box::new([
+            App(OpenFile(s.main_rs())),
+            App(ToggleScrollBind),
+            App(ShowEditorInfo(Info::new("Title".to_string(), info))),
+            App(OtherWindow),
+            Editor(SetScrollOffset(10)),
+            App(ToggleScrollBind),
+            // The offset between the windows at the time they are bound is kept
+            Editor(SetScrollOffset(12)),
+            App(OtherWindow),
+            Expect(CurrentScrollOffset(2)),
+            App(OtherWindow),
+            Editor(SetScrollOffset(30)),
+            App(OtherWindow),
+            // The scroll offset is clamped to the last line of main.rs
+            Expect(CurrentScrollOffset(6)),
+            // Unbound windows are scrolled independently
+            App(ToggleScrollBind),
+            Editor(SetScrollOffset(0)),
+            App(OtherWindow),
+            Expect(CurrentScrollOffset(30)),
+        ])
+    })
+}
+
+#[test]
+fn detect_and_override_indentation() -> anyhow::Result<()> {
+    execute_test_helper(
+        || Box::new(NullWriter),
+        false,
+        [StatusLineComponent::Indentation].to_vec(),
+        |s| {
+            let path = s.new_file("notes.txt", "foo\n  bar\n    baz\n");
+            Box::new([
+                App(OpenFile(path)),
+                Expect(AppGridContains(" Spaces: 2")),
+                Editor(MatchLiteral("foo".to_string())),
+                Editor(Indent),
+                Expect(CurrentLine("  foo")),
+                App(SetIndentation(crate::config::Indentation {
+                    width: 4,
+                    expand_tab: false,
+                })),
+                Expect(AppGridContains(" Tabs")),
+                Editor(Indent),
+                Expect(CurrentLine("\t  foo")),
+            ])
+        },
+    )?;
+    Ok(())
+}
+
+#[test]
+fn preserve_and_convert_line_endings() -> anyhow::Result<()> {
+    execute_test_helper(
+        || Box::new(NullWriter),
+        false,
+        [StatusLineComponent::LineEnding].to_vec(),
+        |s| {
+            let path = s.new_file("notes.txt", "foo\r\nbar");
+            Box::new([
+                s.set_config("insert-final-newline = true"),
+                App(OpenFile(path.clone())),
+                Expect(AppGridContains(" CRLF")),
+                Editor(MatchLiteral("bar".to_string())),
+                Editor(EnterInsertMode(Direction::End)),
+                Editor(Insert("\nspam".to_string())),
+                Editor(EnterNormalMode),
+                Editor(Save),
+                // The inserted line ending and the final newline are written as CRLF
+                Expect(FileContent(
+                    path.clone(),
+                    "foo\r\nbar\r\nspam\r\n".to_string(),
+                )),
+                Editor(SetLineEnding(EndOfLine::Lf)),
+                Expect(AppGridContains(" LF")),
+                Expect(CurrentComponentContent("foo\nbar\nspam\n")),
+                Editor(Save),
+                Expect(FileContent(path, "foo\nbar\nspam\n".to_string())),
+            ])
+        },
+    )?;
+    Ok(())
+}
+
+#[test]
+fn reencode_files_on_save() -> anyhow::Result<()> {
+    execute_test_helper(
+        || Box::new(NullWriter),
+        false,
+        [StatusLineComponent::Charset].to_vec(),
+        |s| {
+            // "日本" in Shift-JIS
+            let path = s.new_file("notes.txt", b"\x93\xfa\x96\x7b");
+            Box::new([
+                App(OpenFile(path.clone())),
+                Expect(AppGridContains(" Shift-JIS")),
+                Expect(CurrentComponentContent("日本")),
+                Editor(MatchLiteral("本".to_string())),
+                Editor(EnterInsertMode(Direction::End)),
+                Editor(Insert("語".to_string())),
+                Editor(EnterNormalMode),
+                Editor(Save),
+                ExpectCustom(Box::new(move || {
+                    assert_eq!(std::fs::read(&path).unwrap(), b"\x93\xfa\x96\x7b\x8c\xea")
+                })),
+            ])
+        },
+    )?;
+    Ok(())
+}
+
+#[test]
+fn visual_bell_flashes_on_rejected_actions() -> anyhow::Result<()> {
+    execute_test(|s| {
+        Box::new([
+            App(SetBell(Bell::Visual)),
+            App(OpenFile(s.main_rs())),
+            Editor(SetSelectionMode(IfCurrentNotFound::LookForward, Line)),
+            Expect(BellFlashing(false)),
+            // There is no line before the first line
+            Editor(MoveSelection(Previous)),
+            Expect(BellFlashing(true)),
+            WaitForAppMessage,
+            Expect(BellFlashing(false)),
+            Editor(MoveSelection(Next)),
+            Expect(BellFlashing(false)),
+            // Unbound key
+            App(HandleKeyEvent(key!("ctrl+j"))),
+            Expect(BellFlashing(true)),
+        ])
+    })
+}
+
+#[test]
+fn user_defined_key_sequences() -> anyhow::Result<()> {
+    let keymap_config =
+        KeymapConfig::parse(r#"{ insert: { "j k": "esc" }, normal: { "ctrl+j": null } }"#)?;
+    execute_test(|s| {
+        Box::new([
+            App(SetBell(Bell::Visual)),
+            App(SetKeymapConfig(keymap_config.clone())),
+            App(OpenFile(s.main_rs())),
+            Editor(SetContent("".to_string())),
+            Editor(EnterInsertMode(Direction::Start)),
+            // The pending key is typed as it is once the key sequence times out
+            App(HandleKeyEvent(key!("j"))),
+            Expect(CurrentComponentContent("")),
+            Expect(EditorInfoContent("k → esc (Enter normal mode)")),
+            WaitForAppMessage,
+            Expect(CurrentComponentContent("j")),
+            Expect(EditorInfoOpen(false)),
+            // The pending key is typed as it is if the next key does not complete the sequence
+            App(HandleKeyEvents(keys!("x j y j k").to_vec())),
+            Expect(CurrentComponentContent("jxjy")),
+            Expect(CurrentMode(Mode::Normal)),
+            Expect(BellFlashing(false)),
+            App(HandleKeyEvent(key!("ctrl+j"))),
+            Expect(BellFlashing(true)),
+        ])
+    })
+}
+
+#[test]
+fn plugins_add_commands_and_key_sequences() -> anyhow::Result<()> {
+    execute_test(|s| {
+        let plugins = s.new_path("plugins");
+        std::fs::create_dir_all(&plugins).unwrap();
+        std::fs::write(
+            plugins.join("upcase.lua"),
+            r#"
+            ki.command("upcase", "Uppercase the buffer", function()
+              ki.buffer.set_text(ki.buffer.text():upper())
+            end)
+            ki.keymap("normal", "space u", "upcase")
+            ki.command("greet", "Greet someone", function()
+              ki.pick("Greet", { "world" }, function(name)
+                ki.buffer.set_text("hello " .. name)
+              end)
+            end)
+            "#,
+        )
+        .unwrap();
+        Box::new([
+            App(LoadPlugins(plugins)),
+            App(OpenFile(s.main_rs())),
+            Editor(SetContent("hello".to_string())),
+            App(HandleKeyEvents(keys!("space u").to_vec())),
+            Expect(CurrentComponentContent("HELLO")),
+            App(RunCommand("greet".to_string())),
+            App(HandleKeyEvent(key!("enter"))),
+            Expect(CurrentComponentContent("hello world")),
+        ])
+    })
+}
+
+#[test]
+fn edit_save_and_run_macros() -> anyhow::Result<()> {
+    execute_test(|s| {
+        Box::new([
+            App(EditMacro(MacroName::Register('q'))),
+            Expect(CurrentComponentContent("")),
+            Editor(SetContent("a\n  space x\n  shift+Y esc".to_string())),
+            App(WriteMacro),
+            App(EditMacro(MacroName::Register('q'))),
+            Expect(CurrentComponentContent("a space x shift+Y esc")),
+            App(SaveMacro(MacroName::Named("append".to_string()))),
+            App(OpenFile(s.main_rs())),
+            Editor(SetContent("w".to_string())),
+            App(RunMacro {
+                name: MacroName::Named("append".to_string()),
+                repeat: 1,
+                each_selection: false,
+            }),
+            Expect(CurrentComponentContent("w xY")),
+            Expect(CurrentMode(Mode::Normal)),
+        ])
+    })
+}
+
+#[test]
+fn record_and_run_macros() -> anyhow::Result<()> {
+    execute_test_helper(
+        || Box::new(NullWriter),
+        false,
+        [StatusLineComponent::MacroRecording].to_vec(),
+        |s| {
+            Box::new([
+                App(OpenFile(s.main_rs())),
+                Editor(SetContent("a\nb".to_string())),
+                Editor(SetSelectionMode(IfCurrentNotFound::LookForward, Line)),
+                App(StartMacroRecording('q')),
+                Expect(AppGridContains("Recording @q")),
+                // The keys that stop the recording are not recorded
+                App(HandleKeyEvents(keys!("i - esc space m r").to_vec())),
+                Expect(Not(Box::new(AppGridContains("Recording")))),
+                Expect(CurrentComponentContent("-a\nb")),
+                App(RunMacro {
+                    name: MacroName::Register('q'),
+                    repeat: 2,
+                    each_selection: false,
+                }),
+                Expect(CurrentComponentContent("---a\nb")),
+                Editor(SetContent("a\nb".to_string())),
+                Editor(SetSelectionMode(IfCurrentNotFound::LookForward, Line)),
+                Editor(CursorAddToAllSelections),
+                App(RunMacro {
+                    name: MacroName::Register('q'),
+                    repeat: 1,
+                    each_selection: true,
+                }),
+                Expect(CurrentComponentContent("-a\n-b")),
+                App(EditMacro(MacroName::Register('q'))),
+                Expect(CurrentComponentContent("i - esc")),
+            ])
+        },
+    )?;
+    Ok(())
+}
+
+#[test]
+fn passphrases_are_not_recorded_into_macros() -> anyhow::Result<()> {
+    execute_test(|s| {
+        let secret = s.new_file("secret.txt.gpg", "");
+        Box::new([
+            App(OpenFile(s.main_rs())),
+            App(StartMacroRecording('q')),
+            App(OpenFile(secret)),
+            App(HandleKeyEvents(keys!("h u n t e r esc space m r").to_vec())),
+            App(EditMacro(MacroName::Register('q'))),
+            Expect(CurrentComponentContent("")),
+        ])
+    })
+}
+
+#[test]
+fn named_marks_follow_edits_and_global_marks_are_jumped_to() -> anyhow::Result<()> {
+    execute_test(|s| {
+        Box::new([
+            App(OpenFile(s.main_rs())),
+            Editor(SetContent("foo bar".to_string())),
+            Editor(MatchLiteral("bar".to_string())),
+            App(SetNamedMark('a')),
+            App(SetNamedMark('A')),
+            Editor(MatchLiteral("foo".to_string())),
+            Editor(EnterInsertMode(Direction::Start)),
+            Editor(Insert("xx ".to_string())),
+            Editor(EnterNormalMode),
+            App(GoToNamedMark('a')),
+            Expect(EditorCursorPosition(Position::new(0, 7))),
+            App(OpenFile(s.foo_rs())),
+            App(GoToNamedMark('A')),
+            Expect(CurrentPath(s.main_rs())),
+            Expect(EditorCursorPosition(Position::new(0, 7))),
+            // The local marks of other buffers are not listed
+            App(OpenFile(s.foo_rs())),
+            App(OpenNamedMarkPicker),
+            Expect(AppGridContains("A  xx foo bar")),
+            Expect(Not(Box::new(AppGridContains("a  xx foo bar")))),
+        ])
+    })
+}
+
+#[test]
+fn breakpoints_are_marked_in_the_gutter_and_follow_edits() -> anyhow::Result<()> {
+    execute_test(|s| {
+        Box::new([
+            App(OpenFile(s.main_rs())),
+            Editor(SetContent("a\nb\nc".to_string())),
+            Editor(MatchLiteral("b".to_string())),
+            App(ToggleBreakpoint),
+            Expect(AppGridContains("2●b")),
+            Editor(EnterInsertMode(Direction::Start)),
+            Editor(Insert("x\n".to_string())),
+            Editor(EnterNormalMode),
+            Expect(AppGridContains("3●b")),
+            Expect(Not(Box::new(AppGridContains("2●")))),
+            Editor(MatchLiteral("b".to_string())),
+            App(ToggleBreakpoint),
+            Expect(Not(Box::new(AppGridContains("●")))),
+        ])
+    })
+}
+
+#[test]
+fn command_palette_executes_nested_keybindings() -> anyhow::Result<()> {
+    execute_test(|s| {
+        Box::new([
+            App(OpenFile(s.main_rs())),
+            Editor(SetContent("a = 1".to_string())),
+            App(OpenCommandPalette),
+            Editor(Insert("toggle comment".to_string())),
+            Expect(CompletionDropdownSelectedItem("Toggle comment (space /)")),
+            App(HandleKeyEvent(key!("enter"))),
+            Expect(CurrentComponentContent("// a = 1")),
+        ])
+    })
+}
+
+#[test]
+fn surplus_of_queued_movement_keys_is_discarded() -> anyhow::Result<()> {
+    execute_test(|s| {
+        Box::new([
+            App(OpenFile(s.main_rs())),
+            Editor(SetContent((1..=20).map(|n| n.to_string()).join("\n"))),
+            Editor(SetSelectionMode(IfCurrentNotFound::LookForward, Line)),
+            App(HandleQueuedKeyEvents(
+                keys!("n n n n n n n n n n n n").to_vec(),
+            )),
+            Expect(CurrentSelectedTexts(&["9"])),
+            // Keys that are not repeated are never discarded
+            App(HandleQueuedKeyEvents(keys!("n b n b n n").to_vec())),
+            Expect(CurrentSelectedTexts(&["11"])),
+        ])
+    })
+}
@@ -0,0 +1,235 @@
+//! Scripts that drive the editor by keys, as it is used, and describe the screen that results,
+//! as the plain text and the styles that a terminal would show.
+//!
+//! Unlike `test_app`, which dispatches to the app directly and inspects its state, every step
+//! is rendered to `MockFrontend`, so that the expectations are checked against what is drawn:
+//!
+//! ```ignore
+//! Script::new()?
+//!     .write("notes.rs", "")?
+//!     .open("notes.rs")?
+//!     .resize(20, 4)?
+//!     .keys("i h e l l o")?
+//!     .expect_text(" 🦀  notes.rs\n1│hello█\n\n INSERT")?;
+//! ```
+//!
+//! Each script runs in a copy of the mock repo, see `TestRunner`.
+
+use std::{rc::Rc, sync::Mutex};
+
+use itertools::Itertools;
+
+use crate::{
+    app::{App, Dimension, Dispatch},
+    config::Config,
+    frontend::{mock::MockFrontend, NullWriter},
+    grid::{Cell, StyleKey},
+    integration_test::TestRunner,
+    position::Position,
+    status_line::StatusLineComponent,
+};
+
+pub(crate) struct Script {
+    // The app is dropped before the runner, which removes the files that the app opened
+    app: App<MockFrontend>,
+    frontend: Rc<Mutex<MockFrontend>>,
+    runner: TestRunner,
+}
+
+impl Script {
+    /// Only the mode is shown in the status line, so that the last row is predictable.
+    pub(crate) fn new() -> anyhow::Result<Self> {
+        let runner = TestRunner::new()?;
+        let frontend = Rc::new(Mutex::new(MockFrontend::new(Box::new(NullWriter))));
+        let mut app = App::new(
+            frontend.clone(),
+            runner.temp_dir().clone(),
+            [StatusLineComponent::Mode].to_vec(),
+        )?;
+        app.disable_lsp();
+        app.render()?;
+        Ok(Self {
+            app,
+            frontend,
+            runner,
+        })
+    }
+
+    fn dispatch(mut self, dispatch: Dispatch) -> anyhow::Result<Self> {
+        self.app.handle_dispatch(dispatch)?;
+        self.app.render()?;
+        Ok(self)
+    }
+
+    /// `path` is relative to the mock repo.
+    pub(crate) fn write(self, path: &str, content: &str) -> anyhow::Result<Self> {
+        std::fs::write(self.runner.temp_dir().to_path_buf().join(path), content)?;
+        Ok(self)
+    }
+
+    pub(crate) fn open(self, path: &str) -> anyhow::Result<Self> {
+        let path = self.runner.temp_dir().join(path)?;
+        self.dispatch(Dispatch::OpenFile(path))
+    }
+
+    /// `content` is the TOML of the config, see `Config::load`.
+    pub(crate) fn config(self, content: &str) -> anyhow::Result<Self> {
+        let path = self.runner.temp_dir().to_path_buf().join("config.toml");
+        std::fs::write(&path, content)?;
+        let config = Config::load([path].to_vec())?;
+        self.dispatch(Dispatch::SetConfig(config))
+    }
+
+    pub(crate) fn resize(self, width: u16, height: u16) -> anyhow::Result<Self> {
+        self.dispatch(Dispatch::TerminalDimensionChanged(Dimension {
+            width,
+            height,
+        }))
+    }
+
+    /// `keys` are separated by spaces, for example `"i h i esc ctrl+s"`,
+    /// and the screen is rendered after each of them.
+    pub(crate) fn keys(self, keys: &str) -> anyhow::Result<Self> {
+        event::parse_key_events(keys)?
+            .into_iter()
+            .try_fold(self, |script, key| {
+                script.dispatch(Dispatch::HandleKeyEvent(key))
+            })
+    }
+
+    /// Pasted as the terminal does with bracketed paste.
+    pub(crate) fn paste(self, text: &str) -> anyhow::Result<Self> {
+        self.dispatch(Dispatch::HandlePasteEvent(text.to_string()))
+    }
+
+    /// The rows of the screen, where the cursor cell is shown as `█`,
+    /// and the trailing spaces and the trailing blank rows are trimmed.
+    fn text(&self) -> String {
+        let frontend = self.frontend.lock().unwrap();
+        frontend
+            .drawn()
+            .rows
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|cell| {
+                        if cell.is_cursor {
+                            "█"
+                        } else {
+                            cell.symbol.as_str()
+                        }
+                    })
+                    .collect::<String>()
+                    .trim_end()
+                    .to_string()
+            })
+            .join("\n")
+            .trim_end()
+            .to_string()
+    }
+
+    /// The leading and trailing newlines of `expected` are ignored,
+    /// so that it can be written as a raw string of several lines.
+    pub(crate) fn expect_text(self, expected: &str) -> anyhow::Result<Self> {
+        let actual = self.text();
+        let expected = expected.trim_matches('\n');
+        anyhow::ensure!(
+            actual == expected,
+            "Expected the screen to be\n{expected}\n\nbut it is\n{actual}"
+        );
+        Ok(self)
+    }
+
+    pub(crate) fn expect_text_contains(self, expected: &str) -> anyhow::Result<Self> {
+        let actual = self.text();
+        anyhow::ensure!(
+            actual.contains(expected),
+            "Expected the screen to contain {expected:?}, but it is\n{actual}"
+        );
+        Ok(self)
+    }
+
+    /// The cells of the first occurrence of `text`, which is searched row by row.
+    fn find(&self, text: &str) -> Option<Vec<Cell>> {
+        let symbols = text.chars().map(|c| c.to_string()).collect_vec();
+        let frontend = self.frontend.lock().unwrap();
+        frontend.drawn().rows.iter().find_map(|row| {
+            row.windows(symbols.len())
+                .find(|cells| {
+                    cells
+                        .iter()
+                        .zip(&symbols)
+                        .all(|(cell, symbol)| &cell.symbol == symbol)
+                })
+                .map(|cells| cells.to_vec())
+        })
+    }
+
+    /// Every cell of the first occurrence of `text` is drawn with `style_key`,
+    /// where `None` means that it is not styled by any key.
+    pub(crate) fn expect_style(
+        self,
+        text: &str,
+        style_key: Option<StyleKey>,
+    ) -> anyhow::Result<Self> {
+        anyhow::ensure!(!text.is_empty(), "The text to look for is empty");
+        let cells = self.find(text).ok_or_else(|| {
+            anyhow::anyhow!("{text:?} is not found on the screen\n{}", self.text())
+        })?;
+        let sources = cells.into_iter().map(|cell| cell.source).collect_vec();
+        anyhow::ensure!(
+            sources.iter().all(|source| source == &style_key),
+            "Expected {text:?} to be styled by {style_key:?}, but it is styled by {sources:?}"
+        );
+        Ok(self)
+    }
+
+    /// `position` is where the terminal cursor is, or `None` if it is hidden.
+    pub(crate) fn expect_cursor(self, position: Option<Position>) -> anyhow::Result<Self> {
+        let actual = self.frontend.lock().unwrap().cursor();
+        anyhow::ensure!(
+            actual == position,
+            "Expected the cursor to be at {position:?}, but it is at {actual:?}"
+        );
+        Ok(self)
+    }
+}
+
+#[test]
+fn typing_and_pasting_in_insert_mode() -> anyhow::Result<()> {
+    Script::new()?
+        .write("notes.rs", "")?
+        .open("notes.rs")?
+        .resize(20, 4)?
+        .keys("i h e")?
+        .paste("llo")?
+        .expect_text(
+            "
+ 🦀  notes.rs
+1│hello█
+
+ INSERT
+",
+        )?
+        .expect_cursor(Some(Position::new(1, 7)))?
+        .keys("esc")?
+        .expect_text_contains(" MOVE")?;
+    Ok(())
+}
+
+#[test]
+fn misspelled_words_are_underlined() -> anyhow::Result<()> {
+    let script = Script::new()?;
+    let dictionary = script.runner.temp_dir().to_path_buf().join("words");
+    std::fs::write(&dictionary, "hello\nthe\nworld\n")?;
+    script
+        .config(&format!(
+            "spell-check = true\nspell-check-dictionary = {:?}",
+            dictionary.display().to_string()
+        ))?
+        .write("notes.txt", "The helo world\n")?
+        .open("notes.txt")?
+        .expect_text_contains("helo world")?
+        .expect_style("helo", Some(StyleKey::UiMisspelling))?;
+    Ok(())
+}
@@ -0,0 +1,162 @@
+//! Themes based on the Okabe-Ito palette, which remains distinguishable
+//! with deuteranopia or protanopia (red-green color blindness).
+//!
+//! Red and green are never used to tell things apart,
+//! for example additions are blue and deletions are orange.
+
+use super::{DiagnosticStyles, HighlightName, SelectionAttributes, Theme, UiStyles};
+use crate::{
+    style::{fg, Style},
+    themes::SyntaxStyles,
+};
+use my_proc_macros::hex;
+
+pub fn colorblind_safe_dark() -> Theme {
+    Theme {
+        name: "Colorblind Safe (Dark)".to_string(),
+        syntax: SyntaxStyles::new({
+            use HighlightName::*;
+            &[
+                (Variable, fg(hex!("#e0e0e0"))),
+                (Keyword, fg(hex!("#56b4e9"))),
+                (KeywordModifier, fg(hex!("#56b4e9"))),
+                (Function, fg(hex!("#f0e442"))),
+                (Type, fg(hex!("#cc79a7"))),
+                (TypeBuiltin, fg(hex!("#cc79a7"))),
+                (String, fg(hex!("#e69f00"))),
+                (Comment, fg(hex!("#8c8c8c"))),
+                (Tag, fg(hex!("#56b4e9"))),
+                (TagAttribute, fg(hex!("#cc79a7"))),
+            ]
+        }),
+        ui: UiStyles {
+            global_title: Style::new()
+                .foreground_color(hex!("#ffffff"))
+                .background_color(hex!("#0072b2")),
+            window_title_focused: Style::new()
+                .foreground_color(hex!("#1e1e1e"))
+                .background_color(hex!("#ffffff")),
+            window_title_unfocused: Style::new()
+                .foreground_color(hex!("#969696"))
+                .background_color(hex!("#444444")),
+            parent_lines_background: hex!("#2e3440"),
+            cursor_line_background: hex!("#262a30"),
+            jump_mark_odd: Style::new()
+                .background_color(hex!("#0072b2"))
+                .foreground_color(hex!("#ffffff")),
+            jump_mark_even: Style::new()
+                .background_color(hex!("#e69f00"))
+                .foreground_color(hex!("#000000")),
+            background_color: hex!("#1e1e1e"),
+            text_foreground: hex!("#ffffff"),
+            primary_selection_background: hex!("#1f4e79"),
+            primary_selection_anchor_background: hex!("#2b619a"),
+            primary_selection_secondary_cursor: Style::new()
+                .background_color(hex!("#808080"))
+                .foreground_color(hex!("#ffffff")),
+            secondary_selection_background: hex!("#4d4d4d"),
+            secondary_selection_anchor_background: hex!("#5e5e5e"),
+            secondary_selection_primary_cursor: Style::new()
+                .background_color(hex!("#000000"))
+                .foreground_color(hex!("#ffffff")),
+            secondary_selection_secondary_cursor: Style::new()
+                .background_color(hex!("#808080"))
+                .foreground_color(hex!("#ffffff")),
+            line_number: Style::new().foreground_color(hex!("#858585")),
+            border: Style::new()
+                .background_color(hex!("#1e1e1e"))
+                .foreground_color(hex!("#858585")),
+            mark: Style::new()
+                .background_color(hex!("#f0e442"))
+                .foreground_color(hex!("#000000")),
+            possible_selection_background: hex!("#5c4400"),
+            keymap_hint: Style::new().underline(hex!("#e69f00")),
+            keymap_key: Style::new().bold().foreground_color(hex!("#e69f00")),
+            keymap_arrow: Style::new().foreground_color(hex!("#808080")),
+            virtual_text: Style::new().foreground_color(hex!("#808080")),
+            fuzzy_matched_char: Style::new().foreground_color(hex!("#56b4e9")),
+            selection_attributes: SelectionAttributes {
+                secondary: Style::new().underline(hex!("#ffffff")),
+                ..Default::default()
+            },
+        },
+        diagnostic: DiagnosticStyles::colorblind_safe(),
+        hunk: super::HunkStyles::colorblind_safe_dark(),
+        status_line: super::StatusLineStyles::colorblind_safe(),
+    }
+}
+
+pub fn colorblind_safe_light() -> Theme {
+    Theme {
+        name: "Colorblind Safe (Light)".to_string(),
+        syntax: SyntaxStyles::new({
+            use HighlightName::*;
+            &[
+                (Variable, fg(hex!("#1a1a1a"))),
+                (Keyword, fg(hex!("#0072b2"))),
+                (KeywordModifier, fg(hex!("#0072b2"))),
+                (Function, fg(hex!("#8a5a00"))),
+                (Type, fg(hex!("#a3487d"))),
+                (TypeBuiltin, fg(hex!("#a3487d"))),
+                (String, fg(hex!("#b35000"))),
+                (Comment, fg(hex!("#6e6e6e"))),
+                (Tag, fg(hex!("#0072b2"))),
+                (TagAttribute, fg(hex!("#a3487d"))),
+            ]
+        }),
+        ui: UiStyles {
+            global_title: Style::new()
+                .foreground_color(hex!("#ffffff"))
+                .background_color(hex!("#0072b2")),
+            window_title_focused: Style::new()
+                .foreground_color(hex!("#ffffff"))
+                .background_color(hex!("#2c2c2c")),
+            window_title_unfocused: Style::new()
+                .foreground_color(hex!("#ffffff"))
+                .background_color(hex!("#aaaaaa")),
+            parent_lines_background: hex!("#e6ebf0"),
+            cursor_line_background: hex!("#f3f5f8"),
+            jump_mark_odd: Style::new()
+                .background_color(hex!("#0072b2"))
+                .foreground_color(hex!("#ffffff")),
+            jump_mark_even: Style::new()
+                .background_color(hex!("#e69f00"))
+                .foreground_color(hex!("#000000")),
+            background_color: hex!("#ffffff"),
+            text_foreground: hex!("#1a1a1a"),
+            primary_selection_background: hex!("#c7e6ff"),
+            primary_selection_anchor_background: hex!("#add6ff"),
+            primary_selection_secondary_cursor: Style::new()
+                .background_color(hex!("#808080"))
+                .foreground_color(hex!("#ffffff")),
+            secondary_selection_background: hex!("#e0e0e0"),
+            secondary_selection_anchor_background: hex!("#cccccc"),
+            secondary_selection_primary_cursor: Style::new()
+                .background_color(hex!("#000000"))
+                .foreground_color(hex!("#ffffff")),
+            secondary_selection_secondary_cursor: Style::new()
+                .background_color(hex!("#808080"))
+                .foreground_color(hex!("#ffffff")),
+            line_number: Style::new().foreground_color(hex!("#6e6e6e")),
+            border: Style::new()
+                .foreground_color(hex!("#aaaaaa"))
+                .background_color(hex!("#ffffff")),
+            mark: Style::new()
+                .background_color(hex!("#f0e442"))
+                .foreground_color(hex!("#000000")),
+            possible_selection_background: hex!("#fbe7b5"),
+            keymap_hint: Style::new().underline(hex!("#0072b2")),
+            keymap_key: Style::new().bold().foreground_color(hex!("#0072b2")),
+            keymap_arrow: Style::new().foreground_color(hex!("#808080")),
+            virtual_text: Style::new().foreground_color(hex!("#808080")),
+            fuzzy_matched_char: Style::new().foreground_color(hex!("#d55e00")),
+            selection_attributes: SelectionAttributes {
+                secondary: Style::new().underline(hex!("#000000")),
+                ..Default::default()
+            },
+        },
+        diagnostic: DiagnosticStyles::colorblind_safe(),
+        hunk: super::HunkStyles::colorblind_safe_light(),
+        status_line: super::StatusLineStyles::colorblind_safe(),
+    }
+}
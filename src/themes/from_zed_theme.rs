@@ -1,4 +1,6 @@
-use super::{Color, DiagnosticStyles, HighlightName, Theme, UiStyles};
+use super::{
+    Color, DiagnosticStyles, HighlightName, SelectionAttributes, StatusLineStyles, Theme, UiStyles,
+};
 use crate::{style::Style, themes::SyntaxStyles};
 use itertools::Itertools;
 use my_proc_macros::hex;
@@ -78,6 +80,7 @@ pub fn from_zed_theme(url: &str) -> anyhow::Result<Vec<Theme>> {
                 .text_accent
                 .and_then(|hex| from_hex(&hex).ok())
                 .unwrap_or(text_color);
+            let text_muted = theme.style.text_muted.and_then(|hex| from_hex(&hex).ok());
             Ok(Theme {
                 name: theme.name,
                 syntax: SyntaxStyles::new(&{
@@ -127,6 +130,10 @@ pub fn from_zed_theme(url: &str) -> anyhow::Result<Vec<Theme>> {
                             theme.style.tab_inactive_background,
                         )),
                     parent_lines_background,
+                    cursor_line_background: from_some_hex(
+                        theme.style.editor_active_line_background,
+                    )
+                    .unwrap_or(parent_lines_background),
                     jump_mark_odd: Style::new()
                         .background_color(hex!("#b5485d"))
                         .foreground_color(hex!("#ffffff")),
@@ -155,12 +162,28 @@ pub fn from_zed_theme(url: &str) -> anyhow::Result<Vec<Theme>> {
                     .unwrap_or_default(),
                     keymap_hint: Style::new().underline(text_accent),
                     keymap_key: Style::new().bold().foreground_color(text_accent),
-                    keymap_arrow: Style::new().set_some_foreground_color(
-                        theme.style.text_muted.and_then(|hex| from_hex(&hex).ok()),
-                    ),
+                    keymap_arrow: Style::new().set_some_foreground_color(text_muted),
+                    virtual_text: Style::new().set_some_foreground_color(text_muted),
                     fuzzy_matched_char: Style::new()
                         .foreground_color(text_accent)
                         .underline(text_accent),
+                    selection_attributes: SelectionAttributes::default(),
+                },
+                status_line: {
+                    let foreground = |hex: &Option<String>| {
+                        Style::new().set_some_foreground_color(from_some_hex(hex.clone()))
+                    };
+                    StatusLineStyles {
+                        mode: Style::new().bold(),
+                        file_path: Style::new(),
+                        modified: foreground(&theme.style.modified).bold(),
+                        cursor_position: Style::new(),
+                        selection_count: Style::new().foreground_color(text_accent),
+                        lsp_status: Style::new().set_some_foreground_color(text_muted),
+                        git_branch: Style::new().foreground_color(text_accent),
+                        diagnostic_counts: foreground(&theme.style.error),
+                        macro_recording: foreground(&theme.style.error).bold(),
+                    }
                 },
                 diagnostic: {
                     let default = DiagnosticStyles::default();
@@ -0,0 +1,168 @@
+use super::{DiagnosticStyles, HighlightName, SelectionAttributes, Theme, UiStyles};
+use crate::{
+    style::{fg, Style},
+    themes::SyntaxStyles,
+};
+use my_proc_macros::hex;
+
+pub fn high_contrast_dark() -> Theme {
+    Theme {
+        name: "High Contrast (Dark)".to_string(),
+        syntax: SyntaxStyles::new({
+            use HighlightName::*;
+            &[
+                (Variable, fg(hex!("#9cdcfe"))),
+                (Keyword, fg(hex!("#569cd6")).bold()),
+                (KeywordModifier, fg(hex!("#569cd6")).bold()),
+                (Function, fg(hex!("#dcdcaa"))),
+                (Type, fg(hex!("#4ec9b0"))),
+                (TypeBuiltin, fg(hex!("#4ec9b0"))),
+                (String, fg(hex!("#ce9178"))),
+                (Comment, fg(hex!("#7ca668"))),
+                (Tag, fg(hex!("#569cd6"))),
+                (TagAttribute, fg(hex!("#9cdcfe"))),
+            ]
+        }),
+        ui: UiStyles {
+            global_title: Style::new()
+                .foreground_color(hex!("#000000"))
+                .background_color(hex!("#ffffff")),
+            window_title_focused: Style::new()
+                .foreground_color(hex!("#000000"))
+                .background_color(hex!("#f38518"))
+                .bold(),
+            window_title_unfocused: Style::new()
+                .foreground_color(hex!("#ffffff"))
+                .background_color(hex!("#000000")),
+            parent_lines_background: hex!("#1a1a1a"),
+            cursor_line_background: hex!("#1a1a1a"),
+            jump_mark_odd: Style::new()
+                .background_color(hex!("#ffff00"))
+                .foreground_color(hex!("#000000"))
+                .bold(),
+            jump_mark_even: Style::new()
+                .background_color(hex!("#00ffff"))
+                .foreground_color(hex!("#000000"))
+                .bold(),
+            background_color: hex!("#000000"),
+            text_foreground: hex!("#ffffff"),
+            primary_selection_background: hex!("#0f4a85"),
+            primary_selection_anchor_background: hex!("#1a66b3"),
+            primary_selection_secondary_cursor: Style::new()
+                .background_color(hex!("#ffffff"))
+                .foreground_color(hex!("#000000")),
+            secondary_selection_background: hex!("#3a3a3a"),
+            secondary_selection_anchor_background: hex!("#4d4d4d"),
+            secondary_selection_primary_cursor: Style::new()
+                .background_color(hex!("#f38518"))
+                .foreground_color(hex!("#000000")),
+            secondary_selection_secondary_cursor: Style::new()
+                .background_color(hex!("#ffffff"))
+                .foreground_color(hex!("#000000")),
+            line_number: Style::new().foreground_color(hex!("#ffffff")),
+            border: Style::new()
+                .background_color(hex!("#000000"))
+                .foreground_color(hex!("#6fc3df")),
+            mark: Style::new()
+                .background_color(hex!("#ffff00"))
+                .foreground_color(hex!("#000000")),
+            possible_selection_background: hex!("#5c3a00"),
+            keymap_hint: Style::new().underline(hex!("#f38518")),
+            keymap_key: Style::new().bold().foreground_color(hex!("#f38518")),
+            keymap_arrow: Style::new().foreground_color(hex!("#ffffff")),
+            virtual_text: Style::new().foreground_color(hex!("#c0c0c0")),
+            fuzzy_matched_char: Style::new()
+                .foreground_color(hex!("#f38518"))
+                .underline(hex!("#f38518")),
+            selection_attributes: SelectionAttributes {
+                primary: Style::new(),
+                secondary: Style::new().underline(hex!("#ffffff")),
+                possible: Style::new(),
+            },
+        },
+        diagnostic: DiagnosticStyles::default(),
+        hunk: super::HunkStyles::dark(),
+        status_line: super::StatusLineStyles::monochrome(),
+    }
+}
+
+pub fn high_contrast_light() -> Theme {
+    Theme {
+        name: "High Contrast (Light)".to_string(),
+        syntax: SyntaxStyles::new({
+            use HighlightName::*;
+            &[
+                (Variable, fg(hex!("#001080"))),
+                (Keyword, fg(hex!("#0f4a85")).bold()),
+                (KeywordModifier, fg(hex!("#0f4a85")).bold()),
+                (Function, fg(hex!("#5e2cbc"))),
+                (Type, fg(hex!("#185e73"))),
+                (TypeBuiltin, fg(hex!("#185e73"))),
+                (String, fg(hex!("#a31515"))),
+                (Comment, fg(hex!("#515151"))),
+                (Tag, fg(hex!("#0f4a85"))),
+                (TagAttribute, fg(hex!("#264f78"))),
+            ]
+        }),
+        ui: UiStyles {
+            global_title: Style::new()
+                .foreground_color(hex!("#ffffff"))
+                .background_color(hex!("#000000")),
+            window_title_focused: Style::new()
+                .foreground_color(hex!("#ffffff"))
+                .background_color(hex!("#0f4a85"))
+                .bold(),
+            window_title_unfocused: Style::new()
+                .foreground_color(hex!("#000000"))
+                .background_color(hex!("#ffffff")),
+            parent_lines_background: hex!("#e8e8e8"),
+            cursor_line_background: hex!("#e8e8e8"),
+            jump_mark_odd: Style::new()
+                .background_color(hex!("#000000"))
+                .foreground_color(hex!("#ffffff"))
+                .bold(),
+            jump_mark_even: Style::new()
+                .background_color(hex!("#b5200d"))
+                .foreground_color(hex!("#ffffff"))
+                .bold(),
+            background_color: hex!("#ffffff"),
+            text_foreground: hex!("#000000"),
+            primary_selection_background: hex!("#a6d2ff"),
+            primary_selection_anchor_background: hex!("#7fbfff"),
+            primary_selection_secondary_cursor: Style::new()
+                .background_color(hex!("#000000"))
+                .foreground_color(hex!("#ffffff")),
+            secondary_selection_background: hex!("#d9d9d9"),
+            secondary_selection_anchor_background: hex!("#c2c2c2"),
+            secondary_selection_primary_cursor: Style::new()
+                .background_color(hex!("#0f4a85"))
+                .foreground_color(hex!("#ffffff")),
+            secondary_selection_secondary_cursor: Style::new()
+                .background_color(hex!("#000000"))
+                .foreground_color(hex!("#ffffff")),
+            line_number: Style::new().foreground_color(hex!("#000000")),
+            border: Style::new()
+                .foreground_color(hex!("#0f4a85"))
+                .background_color(hex!("#ffffff")),
+            mark: Style::new()
+                .background_color(hex!("#ffd700"))
+                .foreground_color(hex!("#000000")),
+            possible_selection_background: hex!("#ffe8a3"),
+            keymap_hint: Style::new().underline(hex!("#0f4a85")),
+            keymap_key: Style::new().bold().foreground_color(hex!("#0f4a85")),
+            keymap_arrow: Style::new().foreground_color(hex!("#000000")),
+            virtual_text: Style::new().foreground_color(hex!("#515151")),
+            fuzzy_matched_char: Style::new()
+                .foreground_color(hex!("#b5200d"))
+                .underline(hex!("#b5200d")),
+            selection_attributes: SelectionAttributes {
+                primary: Style::new(),
+                secondary: Style::new().underline(hex!("#000000")),
+                possible: Style::new(),
+            },
+        },
+        diagnostic: DiagnosticStyles::default(),
+        hunk: super::HunkStyles::light(),
+        status_line: super::StatusLineStyles::monochrome(),
+    }
+}
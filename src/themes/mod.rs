@@ -1,9 +1,15 @@
+pub(crate) mod colorblind_safe;
 pub mod from_zed_theme;
+pub(crate) mod high_contrast;
+pub(crate) mod monochrome;
 pub mod vscode_dark;
 pub(crate) mod vscode_light;
 use std::collections::HashMap;
 
+pub(crate) use colorblind_safe::{colorblind_safe_dark, colorblind_safe_light};
+pub(crate) use high_contrast::{high_contrast_dark, high_contrast_light};
 use itertools::Itertools;
+pub(crate) use monochrome::monochrome;
 use my_proc_macros::hex;
 use once_cell::sync::OnceCell;
 use strum::IntoEnumIterator as _;
@@ -19,6 +25,7 @@ pub(crate) struct Theme {
     pub(crate) ui: UiStyles,
     pub(crate) diagnostic: DiagnosticStyles,
     pub(crate) hunk: HunkStyles,
+    pub(crate) status_line: StatusLineStyles,
 }
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub(crate) struct HunkStyles {
@@ -26,6 +33,9 @@ pub(crate) struct HunkStyles {
     pub(crate) new_background: Color,
     pub(crate) old_emphasized_background: Color,
     pub(crate) new_emphasized_background: Color,
+    pub(crate) added_gutter: Color,
+    pub(crate) modified_gutter: Color,
+    pub(crate) deleted_gutter: Color,
 }
 impl HunkStyles {
     fn dark() -> Self {
@@ -34,6 +44,9 @@ impl HunkStyles {
             old_background: hex!("#47221F"),
             old_emphasized_background: hex!("#682520"),
             new_emphasized_background: hex!("#4E5A32"),
+            added_gutter: hex!("#81B88B"),
+            modified_gutter: hex!("#1B81A8"),
+            deleted_gutter: hex!("#C74E39"),
         }
     }
     fn light() -> Self {
@@ -42,6 +55,44 @@ impl HunkStyles {
             old_background: hex!("#FCECEA"),
             old_emphasized_background: hex!("#F9D8D6"),
             new_emphasized_background: hex!("#BAF0C0"),
+            added_gutter: hex!("#48985D"),
+            modified_gutter: hex!("#2090D3"),
+            deleted_gutter: hex!("#E51400"),
+        }
+    }
+    /// Uses blue for additions and orange for deletions instead of green and red,
+    /// which are hard to tell apart with deuteranopia or protanopia
+    fn colorblind_safe_dark() -> Self {
+        Self {
+            new_background: hex!("#0D2F4A"),
+            old_background: hex!("#4A2F0D"),
+            old_emphasized_background: hex!("#6B4210"),
+            new_emphasized_background: hex!("#124569"),
+            added_gutter: hex!("#56B4E9"),
+            modified_gutter: hex!("#F0E442"),
+            deleted_gutter: hex!("#E69F00"),
+        }
+    }
+    fn colorblind_safe_light() -> Self {
+        Self {
+            new_background: hex!("#E3F1FB"),
+            old_background: hex!("#FDF0DC"),
+            old_emphasized_background: hex!("#F9DDB0"),
+            new_emphasized_background: hex!("#BFDFF5"),
+            added_gutter: hex!("#0072B2"),
+            modified_gutter: hex!("#CC79A7"),
+            deleted_gutter: hex!("#D55E00"),
+        }
+    }
+    fn monochrome() -> Self {
+        Self {
+            new_background: hex!("#303030"),
+            old_background: hex!("#303030"),
+            old_emphasized_background: hex!("#505050"),
+            new_emphasized_background: hex!("#505050"),
+            added_gutter: hex!("#ffffff"),
+            modified_gutter: hex!("#ffffff"),
+            deleted_gutter: hex!("#ffffff"),
         }
     }
 }
@@ -49,20 +100,51 @@ impl Theme {
     pub(crate) fn get_style(&self, source: &StyleKey) -> Style {
         match source {
             StyleKey::UiMark => self.ui.mark,
-            StyleKey::UiPrimarySelection => {
-                Style::new().background_color(self.ui.primary_selection_background)
-            }
-            StyleKey::UiPrimarySelectionAnchors => {
-                Style::new().background_color(self.ui.primary_selection_anchor_background)
-            }
-            StyleKey::UiSecondarySelection => {
-                Style::new().background_color(self.ui.secondary_selection_background)
+            StyleKey::UiPrimarySelection => self
+                .ui
+                .selection_attributes
+                .primary
+                .background_color(self.ui.primary_selection_background),
+            StyleKey::UiPrimarySelectionAnchors => self
+                .ui
+                .selection_attributes
+                .primary
+                .background_color(self.ui.primary_selection_anchor_background),
+            StyleKey::UiSecondarySelection => self
+                .ui
+                .selection_attributes
+                .secondary
+                .background_color(self.ui.secondary_selection_background),
+            StyleKey::UiSecondarySelectionAnchors => self
+                .ui
+                .selection_attributes
+                .secondary
+                .background_color(self.ui.secondary_selection_anchor_background),
+            StyleKey::UiPossibleSelection => self
+                .ui
+                .selection_attributes
+                .possible
+                .background_color(self.ui.possible_selection_background),
+            // Bold and underlined, so that it is visible without colors
+            StyleKey::UiMatchingBracket => Style::new()
+                .bold()
+                .underline(self.ui.text_foreground)
+                .background_color(self.ui.possible_selection_background),
+            StyleKey::UiMisspelling => self.diagnostic.info,
+            // Dimmed like the line numbers, so that it does not stand out from the content
+            StyleKey::UiWhitespace | StyleKey::UiDimmed => self.ui.line_number,
+            StyleKey::UiIndentGuide => {
+                Style::new().set_some_foreground_color(self.ui.border.foreground_color)
             }
-            StyleKey::UiSecondarySelectionAnchors => {
-                Style::new().background_color(self.ui.secondary_selection_anchor_background)
+            StyleKey::UiIndentGuideActive => Style::new().foreground_color(self.ui.text_foreground),
+            // The colors of the hunks are borrowed, so that themes need no colors of their own
+            StyleKey::UiBreakpoint => Style::new().foreground_color(self.hunk.deleted_gutter),
+            StyleKey::UiExecutionLine => Style::new().background_color(self.hunk.new_background),
+            StyleKey::UiDocumentHighlightRead => {
+                Style::new().background_color(self.hunk.new_background)
             }
-            StyleKey::UiPossibleSelection => {
-                Style::new().background_color(self.ui.possible_selection_background)
+            StyleKey::UiDocumentHighlightWrite => {
+                Style::new().background_color(self.hunk.old_background)
             }
             StyleKey::DiagnosticsHint => self.diagnostic.hint,
             StyleKey::DiagnosticsError => self.diagnostic.error,
@@ -116,6 +198,98 @@ impl DiagnosticStyles {
             default: Style::new(),
         }
     }
+
+    /// Based on the Okabe-Ito palette, which is distinguishable with deuteranopia or protanopia.
+    ///
+    /// Errors and warnings are undercurled while the others are underlined,
+    /// so that the severity can be told apart even without the colors.
+    const fn colorblind_safe() -> Self {
+        Self {
+            error: Style::new().undercurl(hex!("#D55E00")),
+            warning: Style::new().undercurl(hex!("#E69F00")),
+            info: Style::new().underline(hex!("#56B4E9")),
+            hint: Style::new().underline(hex!("#009E73")),
+            default: Style::new(),
+        }
+    }
+
+    /// Errors are also bold, because the underline color is not rendered without colors.
+    const fn monochrome() -> Self {
+        Self {
+            error: Style::new().undercurl(hex!("#ffffff")).bold(),
+            warning: Style::new().undercurl(hex!("#ffffff")),
+            info: Style::new().underline(hex!("#ffffff")),
+            hint: Style::new().underline(hex!("#ffffff")),
+            default: Style::new(),
+        }
+    }
+}
+
+/// The styles of the status line segments, which are applied on top of `UiStyles::global_title`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub(crate) struct StatusLineStyles {
+    pub(crate) mode: Style,
+    pub(crate) file_path: Style,
+    pub(crate) modified: Style,
+    pub(crate) cursor_position: Style,
+    pub(crate) selection_count: Style,
+    pub(crate) lsp_status: Style,
+    pub(crate) git_branch: Style,
+    pub(crate) diagnostic_counts: Style,
+    pub(crate) macro_recording: Style,
+}
+
+impl StatusLineStyles {
+    /// For status lines with a saturated background and a white foreground.
+    const fn default() -> Self {
+        Self {
+            mode: Style::new().bold(),
+            file_path: Style::new(),
+            modified: Style::new().foreground_color(hex!("#ffd700")).bold(),
+            cursor_position: Style::new(),
+            selection_count: Style::new().foreground_color(hex!("#ffd700")),
+            lsp_status: Style::new().foreground_color(hex!("#c8f7c5")),
+            git_branch: Style::new().foreground_color(hex!("#ffe4b5")),
+            diagnostic_counts: Style::new().foreground_color(hex!("#ffc0cb")),
+            macro_recording: Style::new()
+                .foreground_color(hex!("#ffffff"))
+                .background_color(hex!("#c62828"))
+                .bold(),
+        }
+    }
+
+    /// Based on the Okabe-Ito palette, which is distinguishable with deuteranopia or protanopia.
+    const fn colorblind_safe() -> Self {
+        Self {
+            mode: Style::new().bold(),
+            file_path: Style::new(),
+            modified: Style::new().foreground_color(hex!("#F0E442")).bold(),
+            cursor_position: Style::new(),
+            selection_count: Style::new().foreground_color(hex!("#F0E442")),
+            lsp_status: Style::new(),
+            git_branch: Style::new(),
+            diagnostic_counts: Style::new().foreground_color(hex!("#F0E442")),
+            macro_recording: Style::new()
+                .foreground_color(hex!("#000000"))
+                .background_color(hex!("#E69F00"))
+                .bold(),
+        }
+    }
+
+    /// The segments that need attention are emphasized without colors.
+    const fn monochrome() -> Self {
+        Self {
+            mode: Style::new().bold(),
+            file_path: Style::new(),
+            modified: Style::new().bold(),
+            cursor_position: Style::new(),
+            selection_count: Style::new(),
+            lsp_status: Style::new(),
+            git_branch: Style::new(),
+            diagnostic_counts: Style::new().bold(),
+            macro_recording: Style::new().reversed(),
+        }
+    }
 }
 
 #[derive(Default, Clone, PartialEq, Eq, Debug)]
@@ -125,6 +299,9 @@ pub(crate) struct UiStyles {
     pub(crate) window_title_focused: Style,
     pub(crate) window_title_unfocused: Style,
     pub(crate) parent_lines_background: Color,
+    /// The background of the cursor line and column, which is only applied to the cells
+    /// that are not highlighted otherwise
+    pub(crate) cursor_line_background: Color,
     pub(crate) jump_mark_odd: Style,
     pub(crate) jump_mark_even: Style,
     pub(crate) text_foreground: Color,
@@ -143,6 +320,18 @@ pub(crate) struct UiStyles {
     pub(crate) keymap_key: Style,
     pub(crate) keymap_arrow: Style,
     pub(crate) keymap_hint: Style,
+    /// Text that is rendered but not part of the buffer, such as the git blame of the current line
+    pub(crate) virtual_text: Style,
+    pub(crate) selection_attributes: SelectionAttributes,
+}
+
+/// Attributes that are applied on top of the selection backgrounds,
+/// so that selections remain distinguishable without colors.
+#[derive(Default, Clone, PartialEq, Eq, Debug)]
+pub(crate) struct SelectionAttributes {
+    pub(crate) primary: Style,
+    pub(crate) secondary: Style,
+    pub(crate) possible: Style,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Default)]
@@ -556,6 +745,18 @@ const ZED_THEME_LINKS: &[&str] = &[
     "https://raw.githubusercontent.com/catppuccin/zed/main/themes/catppuccin-mauve.json",
 ];
 
+fn builtin_themes() -> Vec<Theme> {
+    vec![
+        vscode_dark(),
+        vscode_light(),
+        high_contrast_dark(),
+        high_contrast_light(),
+        colorblind_safe_dark(),
+        colorblind_safe_light(),
+        monochrome(),
+    ]
+}
+
 pub(crate) fn themes() -> anyhow::Result<Vec<Theme>> {
     use rayon::prelude::*;
 
@@ -563,12 +764,31 @@ pub(crate) fn themes() -> anyhow::Result<Vec<Theme>> {
         .par_iter()
         .map(|link| from_zed_theme::from_zed_theme(link))
         .collect::<Result<Vec<_>, _>>()?;
-    Ok(vec![vscode_dark().clone(), vscode_light().clone()]
+    Ok(builtin_themes()
         .into_iter()
         .chain(zed_themes.into_iter().flatten())
         .collect_vec())
 }
 
+/// The builtin themes are looked up first, so that the Zed themes are only loaded if necessary.
+pub(crate) fn from_name(name: &str) -> anyhow::Result<Theme> {
+    if let Some(theme) = builtin_themes()
+        .into_iter()
+        .find(|theme| theme.name == name)
+    {
+        return Ok(theme);
+    }
+    themes()?
+        .into_iter()
+        .find(|theme| theme.name == name)
+        .ok_or_else(|| anyhow::anyhow!("Unknown theme: {name:?}"))
+}
+
+/// Refer https://no-color.org
+pub(crate) fn no_color() -> bool {
+    std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty())
+}
+
 #[cfg(test)]
 mod test_theme {
     #[test]
@@ -577,4 +797,29 @@ mod test_theme {
         super::themes()?;
         Ok(())
     }
+
+    #[test]
+    fn monochrome_theme_distinguishes_selections_and_diagnostics_without_color() {
+        use crate::grid::StyleKey::*;
+        use itertools::Itertools;
+        let theme = super::monochrome();
+        let attributes = [
+            UiPrimarySelection,
+            UiSecondarySelection,
+            UiPossibleSelection,
+            DiagnosticsError,
+            DiagnosticsWarning,
+            DiagnosticsInformation,
+        ]
+        .map(|key| {
+            let style = theme.get_style(&key);
+            (
+                style.line.map(|line| line.style),
+                style.is_bold,
+                style.is_reversed,
+            )
+        });
+        assert!(attributes.iter().all_unique(), "{attributes:?}");
+        assert!(!attributes.contains(&(None, false, false)));
+    }
 }
@@ -0,0 +1,60 @@
+//! A theme that only uses attributes (bold, underline and reverse) to tell things apart,
+//! which is used when the `NO_COLOR` environment variable is set.
+//!
+//! The colors are only black and white, and they are not rendered when `NO_COLOR` is set,
+//! so that the terminal's own foreground and background are used.
+
+use super::{DiagnosticStyles, HighlightName, SelectionAttributes, Theme, UiStyles};
+use crate::{style::Style, themes::SyntaxStyles};
+use my_proc_macros::hex;
+
+pub fn monochrome() -> Theme {
+    let white = hex!("#ffffff");
+    let black = hex!("#000000");
+    Theme {
+        name: "Monochrome".to_string(),
+        syntax: SyntaxStyles::new({
+            use HighlightName::*;
+            &[
+                (Keyword, Style::new().bold()),
+                (KeywordModifier, Style::new().bold()),
+                (Tag, Style::new().bold()),
+            ]
+        }),
+        ui: UiStyles {
+            global_title: Style::new().reversed(),
+            window_title_focused: Style::new().reversed().bold(),
+            window_title_unfocused: Style::new().reversed(),
+            parent_lines_background: black,
+            cursor_line_background: black,
+            jump_mark_odd: Style::new().reversed().bold(),
+            jump_mark_even: Style::new().bold().underline(white),
+            background_color: black,
+            text_foreground: white,
+            primary_selection_background: black,
+            primary_selection_anchor_background: black,
+            primary_selection_secondary_cursor: Style::new().bold().underline(white),
+            secondary_selection_background: black,
+            secondary_selection_anchor_background: black,
+            secondary_selection_primary_cursor: Style::new().reversed().bold(),
+            secondary_selection_secondary_cursor: Style::new().bold().underline(white),
+            line_number: Style::new(),
+            border: Style::new(),
+            mark: Style::new().underline(white),
+            possible_selection_background: black,
+            keymap_hint: Style::new().underline(white),
+            keymap_key: Style::new().bold(),
+            keymap_arrow: Style::new(),
+            virtual_text: Style::new(),
+            fuzzy_matched_char: Style::new().bold(),
+            selection_attributes: SelectionAttributes {
+                primary: Style::new().reversed(),
+                secondary: Style::new().reversed().underline(white),
+                possible: Style::new().bold(),
+            },
+        },
+        diagnostic: DiagnosticStyles::monochrome(),
+        hunk: super::HunkStyles::monochrome(),
+        status_line: super::StatusLineStyles::monochrome(),
+    }
+}
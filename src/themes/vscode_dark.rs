@@ -1,4 +1,4 @@
-use super::{DiagnosticStyles, HighlightName, Theme, UiStyles};
+use super::{DiagnosticStyles, HighlightName, SelectionAttributes, Theme, UiStyles};
 use crate::{
     style::{fg, Style},
     themes::SyntaxStyles,
@@ -34,6 +34,7 @@ pub fn vscode_dark() -> Theme {
                 .foreground_color(hex!("#969696"))
                 .background_color(hex!("#444444")),
             parent_lines_background: hex!("#3B3D41"),
+            cursor_line_background: hex!("#282828"),
             jump_mark_odd: Style::new()
                 .background_color(hex!("#b5485d"))
                 .foreground_color(hex!("#ffffff")),
@@ -64,9 +65,12 @@ pub fn vscode_dark() -> Theme {
             keymap_hint: Style::new().underline(hex!("#af00db")),
             keymap_key: Style::new().bold().foreground_color(hex!("#af00db")),
             keymap_arrow: Style::new().foreground_color(hex!("#808080")),
+            virtual_text: Style::new().foreground_color(hex!("#808080")),
+            selection_attributes: SelectionAttributes::default(),
             fuzzy_matched_char: Style::new().foreground_color(hex!("#55A8F8")),
         },
         diagnostic: DiagnosticStyles::default(),
         hunk: super::HunkStyles::dark(),
+        status_line: super::StatusLineStyles::default(),
     }
 }
@@ -0,0 +1,116 @@
+//! Delayed messages (e.g. debounced refreshes and animation frames) are sent by one timer
+//! thread that keeps the pending deadlines, instead of a thread that sleeps per event.
+//!
+//! Each deadline belongs to a `TimerKey`, and scheduling the same key again replaces its
+//! pending deadline, so that a burst of events results in one message.
+
+use std::{
+    sync::mpsc::{channel, RecvTimeoutError, Sender},
+    time::{Duration, Instant},
+};
+
+use crate::app::AppMessage;
+
+/// What a deadline is for, of which at most one deadline is pending at a time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum TimerKey {
+    DocumentHighlight,
+    MarkdownPreviewRefresh,
+    BellFlash,
+    ScrollAnimation,
+}
+
+struct Deadline {
+    key: TimerKey,
+    due: Instant,
+    message: AppMessage,
+}
+
+pub(crate) struct Timer {
+    sender: Sender<Deadline>,
+}
+
+impl Timer {
+    /// The timer thread stops once the `Timer` or the receiver of `app_sender` is dropped.
+    pub(crate) fn new(app_sender: Sender<AppMessage>) -> Timer {
+        let (sender, receiver) = channel::<Deadline>();
+        std::thread::spawn(move || {
+            let mut pending: Vec<Deadline> = Vec::new();
+            loop {
+                let received = match pending.iter().map(|deadline| deadline.due).min() {
+                    Some(due) => {
+                        receiver.recv_timeout(due.saturating_duration_since(Instant::now()))
+                    }
+                    None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+                };
+                match received {
+                    Ok(deadline) => {
+                        pending.retain(|pending| pending.key != deadline.key);
+                        pending.push(deadline);
+                    }
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Err(RecvTimeoutError::Disconnected) => return,
+                }
+                let now = Instant::now();
+                let (due, not_due): (Vec<_>, Vec<_>) = std::mem::take(&mut pending)
+                    .into_iter()
+                    .partition(|deadline| deadline.due <= now);
+                pending = not_due;
+                for deadline in due {
+                    if app_sender.send(deadline.message).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+        Timer { sender }
+    }
+
+    /// Sends `message` to the app once `delay` has passed,
+    /// unless `key` is scheduled again in the meantime.
+    pub(crate) fn schedule(&self, key: TimerKey, delay: Duration, message: AppMessage) {
+        let _ = self.sender.send(Deadline {
+            key,
+            due: Instant::now() + delay,
+            message,
+        });
+    }
+}
+
+#[cfg(test)]
+mod test_timer {
+    use std::{
+        sync::mpsc::channel,
+        time::{Duration, Instant},
+    };
+
+    use crate::app::AppMessage;
+
+    use super::{Timer, TimerKey};
+
+    #[test]
+    fn scheduling_a_key_again_replaces_its_deadline() -> anyhow::Result<()> {
+        let (sender, receiver) = channel();
+        let timer = Timer::new(sender);
+        let delay = Duration::from_millis(100);
+        let started = Instant::now();
+        timer.schedule(
+            TimerKey::DocumentHighlight,
+            delay,
+            AppMessage::DocumentHighlightDue { id: 1 },
+        );
+        timer.schedule(
+            TimerKey::DocumentHighlight,
+            delay,
+            AppMessage::DocumentHighlightDue { id: 2 },
+        );
+        assert!(matches!(
+            receiver.recv_timeout(Duration::from_secs(5))?,
+            AppMessage::DocumentHighlightDue { id: 2 }
+        ));
+        assert!(started.elapsed() >= delay);
+        // The replaced deadline is never sent
+        assert!(receiver.recv_timeout(delay * 2).is_err());
+        Ok(())
+    }
+}
@@ -1,5 +1,6 @@
+use std::collections::HashSet;
+
 use convert_case::Casing;
-use shared::process_command::ProcessCommand;
 
 use crate::{
     clipboard::CopiedTexts, selection_mode::NamingConventionAgnostic, soft_wrap::soft_wrap,
@@ -10,7 +11,10 @@ pub(crate) enum Transformation {
     Case(convert_case::Case),
     Join,
     Wrap,
-    PipeToShell { command: String },
+    Rot13,
+    SortLines { numeric: bool, case_sensitive: bool },
+    UniqueLines,
+    ReverseLines,
     ReplaceWithCopiedText { copied_texts: CopiedTexts },
     RegexReplace { regex: MyRegex, replacement: String },
     NamingConventionAgnosticReplace { search: String, replacement: String },
@@ -26,7 +30,22 @@ impl std::fmt::Display for Transformation {
             ),
             Transformation::Join => write!(f, "Join",),
             Transformation::Wrap => write!(f, "Wrap",),
-            Transformation::PipeToShell { command } => write!(f, "Pipe To Shell `{command}`",),
+            Transformation::Rot13 => write!(f, "ROT13",),
+            Transformation::SortLines {
+                numeric,
+                case_sensitive,
+            } => write!(
+                f,
+                "Sort Lines{}{}",
+                if *numeric { " Numerically" } else { "" },
+                if *case_sensitive {
+                    ""
+                } else {
+                    " (Case-insensitive)"
+                }
+            ),
+            Transformation::UniqueLines => write!(f, "Unique Lines",),
+            Transformation::ReverseLines => write!(f, "Reverse Lines",),
             Transformation::ReplaceWithCopiedText { .. } => {
                 write!(f, "Replace With Copied Text",)
             }
@@ -68,9 +87,48 @@ impl Transformation {
                 .replace_all(&string, " ")
                 .to_string()),
             Transformation::Wrap => Ok(soft_wrap(&string, 80).to_string()),
-            Transformation::PipeToShell { command } => {
-                ProcessCommand::new("bash", &["-c", command]).run_with_input(&string)
-            }
+            // Each ASCII letter is rotated by 13 places, so applying it twice is a no-op
+            Transformation::Rot13 => Ok(string
+                .chars()
+                .map(|c| match c {
+                    'a'..='z' => ((c as u8 - b'a' + 13) % 26 + b'a') as char,
+                    'A'..='Z' => ((c as u8 - b'A' + 13) % 26 + b'A') as char,
+                    _ => c,
+                })
+                .collect()),
+            Transformation::SortLines {
+                numeric,
+                case_sensitive,
+            } => Ok(map_lines(&string, |mut lines| {
+                let key = |line: &str| {
+                    let line = if *case_sensitive {
+                        line.to_string()
+                    } else {
+                        line.to_lowercase()
+                    };
+                    // Lines that do not start with a number are sorted after those that do
+                    let number = numeric.then(|| leading_number(&line)).flatten();
+                    (number.is_none(), number.unwrap_or_default(), line)
+                };
+                lines.sort_by(|a, b| {
+                    let (a, b) = (key(a), key(b));
+                    a.0.cmp(&b.0)
+                        .then(a.1.total_cmp(&b.1))
+                        .then_with(|| a.2.cmp(&b.2))
+                });
+                lines
+            })),
+            Transformation::UniqueLines => Ok(map_lines(&string, |lines| {
+                let mut seen = HashSet::new();
+                lines
+                    .into_iter()
+                    .filter(|line| seen.insert(*line))
+                    .collect()
+            })),
+            Transformation::ReverseLines => Ok(map_lines(&string, |mut lines| {
+                lines.reverse();
+                lines
+            })),
             Transformation::ReplaceWithCopiedText { copied_texts } => {
                 Ok(copied_texts.get(selection_index))
             }
@@ -85,6 +143,26 @@ impl Transformation {
     }
 }
 
+/// The trailing line break of `string` stays at the end, so that the last line
+/// does not lose its line break when it is moved.
+fn map_lines(string: &str, f: impl FnOnce(Vec<&str>) -> Vec<&str>) -> String {
+    let (content, trailing) = match string.strip_suffix('\n') {
+        Some(content) => (content, "\n"),
+        None => (string, ""),
+    };
+    format!("{}{trailing}", f(content.split('\n').collect()).join("\n"))
+}
+
+/// For example, `-1.5` for `  -1.5 apples`.
+fn leading_number(line: &str) -> Option<f64> {
+    let line = line.trim_start();
+    let length = line
+        .char_indices()
+        .take_while(|(index, c)| c.is_ascii_digit() || *c == '.' || (*index == 0 && *c == '-'))
+        .count();
+    line[..length].parse().ok()
+}
+
 #[cfg(test)]
 mod test_transformation {
     use super::Transformation;
@@ -118,4 +196,42 @@ who lives in a pineapple under the sea? Spongebob Squarepants! absorbent and yel
             .trim().to_string()).unwrap();
         assert_eq!(result, "who lives in a pineapple under the sea? Spongebob Squarepants! absorbent and \nyellow and porous is he? Spongebob Squarepants")
     }
+
+    #[test]
+    fn sort_lines() {
+        let apply = |numeric: bool, case_sensitive: bool, string: &str| {
+            Transformation::SortLines {
+                numeric,
+                case_sensitive,
+            }
+            .apply(0, string.to_string())
+            .unwrap()
+        };
+        assert_eq!(apply(false, true, "b\nC\na\n"), "C\na\nb\n");
+        assert_eq!(apply(false, false, "b\nC\na"), "a\nb\nC");
+        assert_eq!(
+            apply(true, true, "10 x\nfoo\n9 y\n-1.5\n"),
+            "-1.5\n9 y\n10 x\nfoo\n"
+        );
+    }
+
+    #[test]
+    fn unique_and_reverse_lines() {
+        let result = Transformation::UniqueLines
+            .apply(0, "a\nb\na\nc\nb\n".to_string())
+            .unwrap();
+        assert_eq!(result, "a\nb\nc\n");
+        let result = Transformation::ReverseLines.apply(0, result).unwrap();
+        assert_eq!(result, "c\nb\na\n")
+    }
+
+    #[test]
+    fn rot13() {
+        let result = Transformation::Rot13
+            .apply(0, "Hello, World! 123 ü".to_string())
+            .unwrap();
+        assert_eq!(result, "Uryyb, Jbeyq! 123 ü");
+        let result = Transformation::Rot13.apply(0, result).unwrap();
+        assert_eq!(result, "Hello, World! 123 ü")
+    }
 }
@@ -240,6 +240,12 @@ impl UiTree {
         self.get_current_node().data().component()
     }
 
+    pub(crate) fn current_component_parent(&self) -> Option<Rc<RefCell<dyn Component>>> {
+        self.get_current_node()
+            .parent()
+            .map(|parent| parent.data().component())
+    }
+
     pub(crate) fn replace_root_node_child(
         &mut self,
         kind: ComponentKind,
@@ -274,7 +280,7 @@ impl UiTree {
         )
     }
 
-    fn get_node_child_id(&self, node_id: NodeId, kind: ComponentKind) -> Option<NodeId> {
+    pub(crate) fn get_node_child_id(&self, node_id: NodeId, kind: ComponentKind) -> Option<NodeId> {
         Some(
             self.get(node_id)?
                 .traverse_pre_order()
@@ -334,6 +340,12 @@ pub(crate) enum ComponentKind {
     DropdownInfo,
     EditorInfo,
     KeymapLegend,
+    Terminal,
+    DiffView,
+    MarkdownPreview,
+    TaskOutput,
+    DebugPanel,
+    HierarchyView,
     /// The root should not be rendered
     Root,
 }
@@ -50,6 +50,14 @@ impl<T: Applicable> UndoTree<T> {
         }
     }
 
+    /// The edits that lead to the current state with their index in the history,
+    /// where the most recent edit comes first.
+    pub(crate) fn applied_edits(&self) -> impl Iterator<Item = (usize, &OldNew<T>)> + '_ {
+        (0..self.history.head().index)
+            .rev()
+            .filter_map(move |index| Some((index, self.history.get_entry(index)?.get())))
+    }
+
     pub(crate) fn display(&self) -> String {
         self.history.display().detailed(false).to_string()
     }
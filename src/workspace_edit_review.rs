@@ -0,0 +1,191 @@
+//! Large workspace edits (e.g. renaming a widely used symbol) are reviewed file by file
+//! before they are applied, where each file is shown as a diff that is either accepted
+//! or rejected, and only the accepted files are edited once every file is decided.
+
+use itertools::Itertools;
+
+use shared::canonicalized_path::CanonicalizedPath;
+
+use crate::lsp::{completion::PositionalEdit, workspace_edit::WorkspaceEdit};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ReviewDecision {
+    Accept,
+    Reject,
+    /// Accepts the current file and the files after it
+    AcceptRemaining,
+    /// Rejects the current file and the files after it
+    RejectRemaining,
+}
+
+impl ReviewDecision {
+    pub(crate) const ALL: [ReviewDecision; 4] = [
+        ReviewDecision::Accept,
+        ReviewDecision::Reject,
+        ReviewDecision::AcceptRemaining,
+        ReviewDecision::RejectRemaining,
+    ];
+
+    pub(crate) fn display(&self) -> &'static str {
+        match self {
+            ReviewDecision::Accept => "Accept",
+            ReviewDecision::Reject => "Reject",
+            ReviewDecision::AcceptRemaining => "Accept this and the remaining files",
+            ReviewDecision::RejectRemaining => "Reject this and the remaining files",
+        }
+    }
+}
+
+pub(crate) struct WorkspaceEditReview {
+    pub(crate) description: String,
+    workspace_edit: WorkspaceEdit,
+    /// The edited files in the order of their first edit
+    paths: Vec<CanonicalizedPath>,
+    /// Whether the first files are accepted, in the order of `paths`
+    decisions: Vec<bool>,
+}
+
+impl WorkspaceEditReview {
+    pub(crate) fn new(workspace_edit: WorkspaceEdit, description: String) -> WorkspaceEditReview {
+        WorkspaceEditReview {
+            description,
+            paths: workspace_edit
+                .edits
+                .iter()
+                .map(|edit| edit.path.clone())
+                .unique()
+                .collect_vec(),
+            workspace_edit,
+            decisions: Vec::new(),
+        }
+    }
+
+    pub(crate) fn into_parts(self) -> (WorkspaceEdit, String) {
+        (self.workspace_edit, self.description)
+    }
+
+    /// The file that is being reviewed, and its 1-based index among the files.
+    pub(crate) fn current(&self) -> Option<(usize, &CanonicalizedPath)> {
+        let index = self.decisions.len();
+        Some((index + 1, self.paths.get(index)?))
+    }
+
+    /// The edits of the file, where each batch is applied after the previous one.
+    pub(crate) fn edits_of(&self, path: &CanonicalizedPath) -> Vec<Vec<PositionalEdit>> {
+        self.workspace_edit
+            .edits
+            .iter()
+            .filter(|edit| &edit.path == path)
+            .map(|edit| edit.edits.clone())
+            .collect_vec()
+    }
+
+    pub(crate) fn file_count(&self) -> usize {
+        self.paths.len()
+    }
+
+    pub(crate) fn decide(&mut self, decision: ReviewDecision) {
+        let remaining = self.paths.len() - self.decisions.len();
+        let (accept, count) = match decision {
+            ReviewDecision::Accept => (true, 1),
+            ReviewDecision::Reject => (false, 1),
+            ReviewDecision::AcceptRemaining => (true, remaining),
+            ReviewDecision::RejectRemaining => (false, remaining),
+        };
+        self.decisions
+            .extend(std::iter::repeat(accept).take(count.min(remaining)))
+    }
+
+    /// The workspace edit of the accepted files.
+    ///
+    /// The resource operations (e.g. renaming a file) are kept unless every file is rejected,
+    /// because they cannot be shown as diffs.
+    pub(crate) fn accepted(&self) -> WorkspaceEdit {
+        let accepted_paths = self
+            .paths
+            .iter()
+            .zip(&self.decisions)
+            .filter(|(_, accepted)| **accepted)
+            .map(|(path, _)| path)
+            .collect_vec();
+        let edits = self
+            .workspace_edit
+            .edits
+            .iter()
+            .filter(|edit| accepted_paths.contains(&&edit.path))
+            .cloned()
+            .collect_vec();
+        WorkspaceEdit {
+            resource_operations: if accepted_paths.is_empty() && !self.paths.is_empty() {
+                Vec::new()
+            } else {
+                self.workspace_edit.resource_operations.clone()
+            },
+            edits,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_workspace_edit_review {
+    use super::*;
+    use crate::{lsp::workspace_edit::TextDocumentEdit, position::Position};
+
+    #[test]
+    fn accept_and_reject_files() -> anyhow::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let paths = ["a", "b", "c"]
+            .into_iter()
+            .map(|name| {
+                let path = temp_dir.path().join(name);
+                std::fs::write(&path, "")?;
+                CanonicalizedPath::try_from(path)
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let edit = |path: &CanonicalizedPath, new_text: &str| TextDocumentEdit {
+            path: path.clone(),
+            edits: [PositionalEdit {
+                range: Position::new(0, 0)..Position::new(0, 0),
+                new_text: new_text.to_string(),
+            }]
+            .to_vec(),
+        };
+        let workspace_edit = WorkspaceEdit {
+            edits: [
+                edit(&paths[0], "x"),
+                edit(&paths[1], "y"),
+                edit(&paths[0], "z"),
+                edit(&paths[2], "w"),
+            ]
+            .to_vec(),
+            resource_operations: Vec::new(),
+        };
+        let mut review = WorkspaceEditReview::new(workspace_edit, "Rename".to_string());
+        assert_eq!(review.file_count(), 3);
+        assert_eq!(review.current(), Some((1, &paths[0])));
+        assert_eq!(
+            review
+                .edits_of(&paths[0])
+                .into_iter()
+                .flatten()
+                .map(|edit| edit.new_text)
+                .collect_vec(),
+            ["x", "z"]
+        );
+
+        review.decide(ReviewDecision::Reject);
+        assert_eq!(review.current(), Some((2, &paths[1])));
+        review.decide(ReviewDecision::AcceptRemaining);
+        assert!(review.current().is_none());
+        assert_eq!(
+            review
+                .accepted()
+                .edits
+                .into_iter()
+                .map(|edit| edit.path)
+                .collect_vec(),
+            [paths[1].clone(), paths[2].clone()]
+        );
+        Ok(())
+    }
+}